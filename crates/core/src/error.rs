@@ -16,9 +16,15 @@ pub enum ApiError {
     #[error("not found: {0}")]
     NotFound(String),
 
+    #[error("method not allowed: {0}")]
+    MethodNotAllowed(String),
+
     #[error("conflict: {0}")]
     Conflict(String),
 
+    #[error("payload too large: {0}")]
+    PayloadTooLarge(String),
+
     #[error("validation failed")]
     UnprocessableEntity {
         message: String,
@@ -28,8 +34,14 @@ pub enum ApiError {
     #[error("too many requests")]
     TooManyRequests { retry_after_seconds: u64 },
 
+    #[error("request timeout: {0}")]
+    RequestTimeout(String),
+
     #[error("internal error: {0}")]
     Internal(String),
+
+    #[error("service unavailable: {0}")]
+    ServiceUnavailable(String),
 }
 
 impl ApiError {
@@ -39,10 +51,14 @@ impl ApiError {
             Self::Unauthorized(_) => "unauthorized",
             Self::Forbidden(_) => "forbidden",
             Self::NotFound(_) => "not_found",
+            Self::MethodNotAllowed(_) => "method_not_allowed",
             Self::Conflict(_) => "conflict",
+            Self::PayloadTooLarge(_) => "payload_too_large",
             Self::UnprocessableEntity { .. } => "validation_failed",
             Self::TooManyRequests { .. } => "too_many_requests",
+            Self::RequestTimeout(_) => "request_timeout",
             Self::Internal(_) => "internal_error",
+            Self::ServiceUnavailable(_) => "service_unavailable",
         }
     }
 
@@ -62,10 +78,14 @@ impl ApiError {
             Self::Unauthorized(_) => 401,
             Self::Forbidden(_) => 403,
             Self::NotFound(_) => 404,
+            Self::MethodNotAllowed(_) => 405,
             Self::Conflict(_) => 409,
+            Self::PayloadTooLarge(_) => 413,
             Self::UnprocessableEntity { .. } => 422,
             Self::TooManyRequests { .. } => 429,
+            Self::RequestTimeout(_) => 408,
             Self::Internal(_) => 500,
+            Self::ServiceUnavailable(_) => 503,
         }
     }
 
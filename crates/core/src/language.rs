@@ -0,0 +1,309 @@
+//! Language codes.
+//!
+//! Sidecar subtitles carry two- or three-letter codes from their filenames while
+//! ffprobe reports the three-letter tags stored in the container. Everything goes
+//! through [`normalize`] so `en`, `eng` and `EN` compare equal.
+
+/// ISO 639-1 two-letter language codes (common subset for validation).
+const LANG_CODES: &[&str] = &[
+    "aa", "ab", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bh", "bi",
+    "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de",
+    "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy",
+    "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia",
+    "id", "ie", "ig", "ii", "ik", "in", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj",
+    "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln",
+    "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb",
+    "nd", "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi",
+    "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk",
+    "sl", "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti",
+    "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo",
+    "wa", "wo", "xh", "yi", "yo", "za", "zh", "zu",
+];
+
+/// ISO 639-2 codes that have an ISO 639-1 equivalent, bibliographic and terminology
+/// forms alike (`fre` and `fra` both map to `fr`).
+const ISO_639_2_TO_1: &[(&str, &str)] = &[
+    ("aar", "aa"),
+    ("abk", "ab"),
+    ("afr", "af"),
+    ("aka", "ak"),
+    ("alb", "sq"),
+    ("amh", "am"),
+    ("ara", "ar"),
+    ("arg", "an"),
+    ("arm", "hy"),
+    ("asm", "as"),
+    ("ava", "av"),
+    ("aym", "ay"),
+    ("aze", "az"),
+    ("bak", "ba"),
+    ("bam", "bm"),
+    ("baq", "eu"),
+    ("bel", "be"),
+    ("ben", "bn"),
+    ("bih", "bh"),
+    ("bis", "bi"),
+    ("bod", "bo"),
+    ("bos", "bs"),
+    ("bre", "br"),
+    ("bul", "bg"),
+    ("bur", "my"),
+    ("cat", "ca"),
+    ("ces", "cs"),
+    ("cha", "ch"),
+    ("che", "ce"),
+    ("chi", "zh"),
+    ("chu", "cu"),
+    ("chv", "cv"),
+    ("cor", "kw"),
+    ("cos", "co"),
+    ("cre", "cr"),
+    ("cym", "cy"),
+    ("cze", "cs"),
+    ("dan", "da"),
+    ("deu", "de"),
+    ("div", "dv"),
+    ("dut", "nl"),
+    ("dzo", "dz"),
+    ("ell", "el"),
+    ("eng", "en"),
+    ("epo", "eo"),
+    ("est", "et"),
+    ("eus", "eu"),
+    ("ewe", "ee"),
+    ("fao", "fo"),
+    ("fas", "fa"),
+    ("fij", "fj"),
+    ("fin", "fi"),
+    ("fra", "fr"),
+    ("fre", "fr"),
+    ("fry", "fy"),
+    ("ful", "ff"),
+    ("geo", "ka"),
+    ("ger", "de"),
+    ("gla", "gd"),
+    ("gle", "ga"),
+    ("glg", "gl"),
+    ("glv", "gv"),
+    ("gre", "el"),
+    ("grn", "gn"),
+    ("guj", "gu"),
+    ("hat", "ht"),
+    ("hau", "ha"),
+    ("heb", "he"),
+    ("her", "hz"),
+    ("hin", "hi"),
+    ("hmo", "ho"),
+    ("hrv", "hr"),
+    ("hun", "hu"),
+    ("hye", "hy"),
+    ("ibo", "ig"),
+    ("ice", "is"),
+    ("ido", "io"),
+    ("iii", "ii"),
+    ("iku", "iu"),
+    ("ile", "ie"),
+    ("ina", "ia"),
+    ("ind", "id"),
+    ("ipk", "ik"),
+    ("isl", "is"),
+    ("ita", "it"),
+    ("jav", "jv"),
+    ("jpn", "ja"),
+    ("kal", "kl"),
+    ("kan", "kn"),
+    ("kas", "ks"),
+    ("kat", "ka"),
+    ("kau", "kr"),
+    ("kaz", "kk"),
+    ("khm", "km"),
+    ("kik", "ki"),
+    ("kin", "rw"),
+    ("kir", "ky"),
+    ("kom", "kv"),
+    ("kon", "kg"),
+    ("kor", "ko"),
+    ("kua", "kj"),
+    ("kur", "ku"),
+    ("lao", "lo"),
+    ("lat", "la"),
+    ("lav", "lv"),
+    ("lim", "li"),
+    ("lin", "ln"),
+    ("lit", "lt"),
+    ("ltz", "lb"),
+    ("lub", "lu"),
+    ("lug", "lg"),
+    ("mac", "mk"),
+    ("mah", "mh"),
+    ("mal", "ml"),
+    ("mao", "mi"),
+    ("mar", "mr"),
+    ("may", "ms"),
+    ("mkd", "mk"),
+    ("mlg", "mg"),
+    ("mlt", "mt"),
+    ("mon", "mn"),
+    ("mri", "mi"),
+    ("msa", "ms"),
+    ("mya", "my"),
+    ("nau", "na"),
+    ("nav", "nv"),
+    ("nbl", "nr"),
+    ("nde", "nd"),
+    ("ndo", "ng"),
+    ("nep", "ne"),
+    ("nld", "nl"),
+    ("nno", "nn"),
+    ("nob", "nb"),
+    ("nor", "no"),
+    ("nya", "ny"),
+    ("oci", "oc"),
+    ("oji", "oj"),
+    ("ori", "or"),
+    ("orm", "om"),
+    ("oss", "os"),
+    ("pan", "pa"),
+    ("per", "fa"),
+    ("pli", "pi"),
+    ("pol", "pl"),
+    ("por", "pt"),
+    ("pus", "ps"),
+    ("que", "qu"),
+    ("roh", "rm"),
+    ("ron", "ro"),
+    ("rum", "ro"),
+    ("run", "rn"),
+    ("rus", "ru"),
+    ("sag", "sg"),
+    ("san", "sa"),
+    ("sin", "si"),
+    ("slk", "sk"),
+    ("slo", "sk"),
+    ("slv", "sl"),
+    ("sme", "se"),
+    ("smo", "sm"),
+    ("sna", "sn"),
+    ("snd", "sd"),
+    ("som", "so"),
+    ("sot", "st"),
+    ("spa", "es"),
+    ("sqi", "sq"),
+    ("srd", "sc"),
+    ("srp", "sr"),
+    ("ssw", "ss"),
+    ("sun", "su"),
+    ("swa", "sw"),
+    ("swe", "sv"),
+    ("tah", "ty"),
+    ("tam", "ta"),
+    ("tat", "tt"),
+    ("tel", "te"),
+    ("tgk", "tg"),
+    ("tgl", "tl"),
+    ("tha", "th"),
+    ("tib", "bo"),
+    ("tir", "ti"),
+    ("ton", "to"),
+    ("tsn", "tn"),
+    ("tso", "ts"),
+    ("tuk", "tk"),
+    ("tur", "tr"),
+    ("twi", "tw"),
+    ("uig", "ug"),
+    ("ukr", "uk"),
+    ("urd", "ur"),
+    ("uzb", "uz"),
+    ("ven", "ve"),
+    ("vie", "vi"),
+    ("vol", "vo"),
+    ("wel", "cy"),
+    ("wln", "wa"),
+    ("wol", "wo"),
+    ("xho", "xh"),
+    ("yid", "yi"),
+    ("yor", "yo"),
+    ("zha", "za"),
+    ("zho", "zh"),
+    ("zul", "zu"),
+];
+
+/// ISO 639-2 codes with no two-letter form. `und`, `mul`, `mis` and `zxx` are left
+/// out on purpose: they say nothing about the language of the track.
+const ISO_639_2_ONLY: &[&str] = &[
+    "ace", "ach", "ada", "ady", "afh", "ain", "akk", "ale", "alt", "ang", "anp", "arc", "arn",
+    "arp", "arw", "ast", "awa", "bal", "ban", "bas", "bej", "bem", "bho", "bik", "bin", "bla",
+    "bra", "bua", "bug", "byn", "cad", "car", "ceb", "chb", "chg", "chk", "chm", "chn", "cho",
+    "chp", "chr", "chy", "cop", "crh", "csb", "dak", "dar", "del", "den", "dgr", "din", "doi",
+    "dsb", "dua", "dum", "dyu", "efi", "egy", "eka", "elx", "enm", "ewo", "fan", "fat", "fil",
+    "fon", "frm", "fro", "frr", "frs", "fur", "gaa", "gay", "gba", "gez", "gil", "gmh", "goh",
+    "gon", "gor", "got", "grb", "grc", "gsw", "gwi", "hai", "haw", "hil", "hit", "hmn", "hsb",
+    "hup", "iba", "ilo", "inh", "jbo", "jpr", "jrb", "kaa", "kab", "kac", "kam", "kaw", "kbd",
+    "kha", "kho", "kmb", "kok", "kos", "kpe", "krc", "krl", "kru", "kum", "kut", "lad", "lah",
+    "lam", "lez", "lol", "loz", "lua", "lui", "lun", "luo", "lus", "mad", "mag", "mai", "mak",
+    "man", "mas", "mdf", "mdr", "men", "mga", "mic", "min", "mnc", "mni", "moh", "mos", "mus",
+    "mwl", "mwr", "myv", "nap", "nds", "new", "nia", "niu", "nog", "non", "nqo", "nso", "nwc",
+    "nym", "nyn", "nyo", "nzi", "osa", "ota", "pag", "pal", "pam", "pap", "pau", "peo", "phn",
+    "pon", "pro", "raj", "rap", "rar", "rom", "rup", "sad", "sah", "sam", "sas", "sat", "scn",
+    "sco", "sel", "sga", "shn", "sid", "sma", "smj", "smn", "sms", "snk", "sog", "sos", "srn",
+    "srr", "suk", "sus", "sux", "syc", "syr", "tem", "ter", "tet", "tig", "tiv", "tkl", "tlh",
+    "tli", "tmh", "tog", "tpi", "tsi", "tum", "tvl", "tyv", "udm", "uga", "umb", "vai", "vot",
+    "wal", "war", "was", "xal", "yao", "yap", "zap", "zbl", "zen", "zgh", "zun", "zza",
+];
+/// Normalize a language code, or `None` if it is not one.
+///
+/// Three-letter codes collapse to their two-letter form where one exists (`eng` →
+/// `en`), so either spelling names the same language.
+pub fn normalize(code: &str) -> Option<String> {
+    let lower = code.to_ascii_lowercase();
+    match lower.len() {
+        2 if LANG_CODES.contains(&lower.as_str()) => Some(lower),
+        3 => ISO_639_2_TO_1
+            .iter()
+            .find(|(three, _)| *three == lower)
+            .map(|(_, two)| two.to_string())
+            .or_else(|| ISO_639_2_ONLY.contains(&lower.as_str()).then_some(lower)),
+        _ => None,
+    }
+}
+
+/// Whether two language tags name the same language, e.g. `en` and `eng`.
+///
+/// Region subtags (`en-US`, `pt_BR`) do not change the language. Codes that are
+/// not in the tables are compared as they are.
+pub fn same_language(a: &str, b: &str) -> bool {
+    let (a, b) = (primary(a), primary(b));
+    !a.is_empty() && a == b
+}
+
+fn primary(tag: &str) -> String {
+    let tag = tag.trim().to_ascii_lowercase();
+    let base = tag.split(['-', '_']).next().unwrap_or_default();
+    normalize(base).unwrap_or_else(|| base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn three_letter_codes_collapse_to_two() {
+        assert_eq!(normalize("eng").as_deref(), Some("en"));
+        assert_eq!(normalize("fra").as_deref(), Some("fr"));
+        assert_eq!(normalize("FRE").as_deref(), Some("fr"));
+        assert_eq!(normalize("de").as_deref(), Some("de"));
+        assert_eq!(normalize("haw").as_deref(), Some("haw"));
+        assert_eq!(normalize("xyz"), None);
+        assert_eq!(normalize("und"), None);
+    }
+
+    #[test]
+    fn language_codes_match_across_iso_forms() {
+        assert!(same_language("en", "eng"));
+        assert!(same_language("FRE", "fra"));
+        assert!(same_language("pt-BR", "por"));
+        assert!(same_language("sq", "alb"));
+        assert!(!same_language("en", "fre"));
+        assert!(!same_language("", ""));
+    }
+}
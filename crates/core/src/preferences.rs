@@ -0,0 +1,211 @@
+//! Typed per-user preferences.
+//!
+//! Stored as a JSON blob in `user_pref.json`. Reads are lenient (unknown keys and
+//! values of the wrong type fall back to defaults) so older blobs keep working;
+//! PATCHes are strict and merged into the current value.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// When subtitles are turned on automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleMode {
+    /// Only when the audio language is not a preferred language.
+    #[default]
+    Default,
+    Always,
+    OnlyForced,
+    None,
+}
+
+/// Which copy wins when a subtitle is both embedded and a sidecar file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleSource {
+    /// The first in listing order (sidecars come first).
+    #[default]
+    Any,
+    Embedded,
+    Sidecar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SubtitleAppearance {
+    pub size: SubtitleSize,
+    /// `#RRGGBB`
+    pub text_color: String,
+    /// Background box opacity in `0.0..=1.0`.
+    pub background_opacity: f32,
+}
+
+impl Default for SubtitleAppearance {
+    fn default() -> Self {
+        Self {
+            size: SubtitleSize::Medium,
+            text_color: "#FFFFFF".to_string(),
+            background_opacity: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UserPreferences {
+    pub theme: Theme,
+    /// ISO 639 codes, most preferred first.
+    pub preferred_audio_languages: Vec<String>,
+    pub preferred_subtitle_languages: Vec<String>,
+    pub subtitle_mode: SubtitleMode,
+    pub subtitle_source: SubtitleSource,
+    pub subtitle_appearance: SubtitleAppearance,
+    pub autoplay_next_episode: bool,
+    pub show_missing_episodes: bool,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            theme: Theme::System,
+            preferred_audio_languages: Vec::new(),
+            preferred_subtitle_languages: Vec::new(),
+            subtitle_mode: SubtitleMode::Default,
+            subtitle_source: SubtitleSource::Any,
+            subtitle_appearance: SubtitleAppearance::default(),
+            autoplay_next_episode: true,
+            show_missing_episodes: false,
+        }
+    }
+}
+
+/// Recursively merge `patch` into `base`; nested objects merge, everything else replaces.
+fn merge_json(base: &mut Value, patch: &Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                match base.get_mut(key) {
+                    Some(existing) if existing.is_object() && value.is_object() => {
+                        merge_json(existing, value)
+                    }
+                    _ => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+fn is_language_code(code: &str) -> bool {
+    (2..=3).contains(&code.len()) && code.chars().all(|c| c.is_ascii_lowercase())
+}
+
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl UserPreferences {
+    /// Parse a stored blob, keeping every field that is still valid.
+    pub fn from_stored(stored: &Value) -> Self {
+        let mut prefs = Value::Object(Map::new());
+        if let Value::Object(fields) = stored {
+            for (key, value) in fields {
+                let mut candidate = prefs.clone();
+                merge_json(&mut candidate, &json!({ key: value }));
+                if serde_json::from_value::<Self>(candidate.clone()).is_ok() {
+                    prefs = candidate;
+                }
+            }
+        }
+        serde_json::from_value(prefs).unwrap_or_default()
+    }
+
+    /// Apply a partial update. Returns field-level errors (same shape as
+    /// `ApiError::validation`) if any key is unknown or has an invalid value.
+    pub fn apply_patch(&self, patch: &Value) -> Result<Self, Value> {
+        let Value::Object(fields) = patch else {
+            return Err(json!({ "preferences": ["must be a JSON object"] }));
+        };
+
+        let mut merged = serde_json::to_value(self).unwrap_or_else(|_| json!({}));
+        let mut errors = Map::new();
+        for (key, value) in fields {
+            let mut candidate = merged.clone();
+            merge_json(&mut candidate, &json!({ key: value }));
+            match serde_json::from_value::<Self>(candidate.clone()) {
+                Ok(_) => merged = candidate,
+                Err(e) => {
+                    errors.insert(key.clone(), json!([e.to_string()]));
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Value::Object(errors));
+        }
+
+        let prefs: Self = serde_json::from_value(merged)
+            .map_err(|e| json!({ "preferences": [e.to_string()] }))?;
+        prefs.validate().map(|_| prefs)
+    }
+
+    fn validate(&self) -> Result<(), Value> {
+        let mut errors = Map::new();
+
+        for (field, codes) in [
+            ("preferred_audio_languages", &self.preferred_audio_languages),
+            (
+                "preferred_subtitle_languages",
+                &self.preferred_subtitle_languages,
+            ),
+        ] {
+            if codes.len() > 16 {
+                errors.insert(field.to_string(), json!(["must have at most 16 entries"]));
+            } else if !codes.iter().all(|c| is_language_code(c)) {
+                errors.insert(
+                    field.to_string(),
+                    json!(["entries must be lowercase ISO 639 codes"]),
+                );
+            }
+        }
+
+        let appearance = &self.subtitle_appearance;
+        if !is_hex_color(&appearance.text_color) {
+            errors.insert(
+                "subtitle_appearance.text_color".to_string(),
+                json!(["must be a #RRGGBB color"]),
+            );
+        }
+        if !(0.0..=1.0).contains(&appearance.background_opacity) {
+            errors.insert(
+                "subtitle_appearance.background_opacity".to_string(),
+                json!(["must be between 0 and 1"]),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Value::Object(errors))
+        }
+    }
+}
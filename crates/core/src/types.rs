@@ -33,15 +33,26 @@ impl std::fmt::Display for ItemKind {
 pub enum LibraryKind {
     Movies,
     TvShows,
+    Music,
+    /// Movies and episodes side by side, told apart by each file's path.
+    Mixed,
 }
 
 impl LibraryKind {
+    pub const ALL: [Self; 4] = [Self::Movies, Self::TvShows, Self::Music, Self::Mixed];
+
     pub fn as_str(self) -> &'static str {
         match self {
             Self::Movies => "movies",
             Self::TvShows => "tv_shows",
+            Self::Music => "music",
+            Self::Mixed => "mixed",
         }
     }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == value)
+    }
 }
 
 impl std::fmt::Display for LibraryKind {
@@ -101,3 +112,69 @@ impl std::fmt::Display for JobStatus {
         f.write_str(self.as_str())
     }
 }
+
+/// Field a library item listing can be sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemSortBy {
+    #[default]
+    Title,
+    DateAdded,
+    Year,
+    Rating,
+}
+
+impl ItemSortBy {
+    pub const ALL: [Self; 4] = [Self::Title, Self::DateAdded, Self::Year, Self::Rating];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::DateAdded => "date_added",
+            Self::Year => "year",
+            Self::Rating => "rating",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.as_str() == value)
+    }
+}
+
+impl std::fmt::Display for ItemSortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Sort direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "asc" => Some(Self::Asc),
+            "desc" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
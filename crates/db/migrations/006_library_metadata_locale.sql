@@ -0,0 +1,3 @@
+-- Per-library metadata language/region overrides. NULL means use the server defaults.
+ALTER TABLE library_settings ADD COLUMN metadata_language TEXT;
+ALTER TABLE library_settings ADD COLUMN metadata_region TEXT;
@@ -0,0 +1,14 @@
+-- Delta sync support: per-row change timestamps and item deletion tombstones.
+ALTER TABLE user_item_state ADD COLUMN updated_ts INTEGER NOT NULL DEFAULT 0;
+
+UPDATE user_item_state SET updated_ts = COALESCE(last_played_ts, 0);
+
+CREATE TABLE IF NOT EXISTS item_tombstone (
+    item_id TEXT PRIMARY KEY,
+    library_id TEXT NOT NULL,
+    deleted_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_updated ON item(updated_ts, id);
+CREATE INDEX IF NOT EXISTS idx_user_item_state_updated ON user_item_state(user_id, updated_ts);
+CREATE INDEX IF NOT EXISTS idx_item_tombstone_deleted ON item_tombstone(deleted_ts, item_id);
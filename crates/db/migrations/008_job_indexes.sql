@@ -0,0 +1,4 @@
+-- The jobs listing orders by creation time. Purging finished jobs filters by status
+-- and last update.
+CREATE INDEX IF NOT EXISTS idx_job_created ON job(created_ts);
+CREATE INDEX IF NOT EXISTS idx_job_status_updated ON job(status, updated_ts);
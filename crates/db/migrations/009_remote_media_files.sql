@@ -0,0 +1,2 @@
+-- Remote media (.strm files): `path` holds the http(s) URL instead of a local path.
+ALTER TABLE media_file ADD COLUMN is_remote INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Automatic rescan interval per library in seconds. NULL means scans only run on demand.
+ALTER TABLE library_settings ADD COLUMN scan_interval_secs INTEGER;
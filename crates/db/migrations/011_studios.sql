@@ -0,0 +1,15 @@
+-- Normalized studios so items can be browsed by studio. name_key is the
+-- lowercased name, so differently cased spellings share one row.
+CREATE TABLE IF NOT EXISTS studio (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    name_key TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS item_studio (
+    item_id TEXT NOT NULL REFERENCES item(id) ON DELETE CASCADE,
+    studio_id TEXT NOT NULL REFERENCES studio(id) ON DELETE CASCADE,
+    PRIMARY KEY(item_id, studio_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_studio_studio ON item_studio(studio_id);
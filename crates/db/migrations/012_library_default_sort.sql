@@ -0,0 +1,3 @@
+-- Per-library default item ordering. NULL means title ascending.
+ALTER TABLE library_settings ADD COLUMN default_sort TEXT;
+ALTER TABLE library_settings ADD COLUMN default_order TEXT;
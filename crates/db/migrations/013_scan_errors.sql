@@ -0,0 +1,10 @@
+-- Files a library scan could not process. A retry scan re-reads only these paths
+-- instead of walking the whole library again.
+CREATE TABLE IF NOT EXISTS scan_error (
+    library_id TEXT NOT NULL REFERENCES library(id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    last_attempt_ts INTEGER NOT NULL,
+    PRIMARY KEY(library_id, path)
+);
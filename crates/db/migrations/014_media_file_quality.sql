@@ -0,0 +1,3 @@
+-- Resolution and source tags from the filename, e.g. 2160p and BluRay. NULL when absent.
+ALTER TABLE media_file ADD COLUMN resolution TEXT;
+ALTER TABLE media_file ADD COLUMN source TEXT;
@@ -0,0 +1,11 @@
+-- Administrative actions (user, library and server config changes). The actor is not
+-- a foreign key so entries outlive the accounts that made them.
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    actor_user_id TEXT,
+    action TEXT NOT NULL,
+    target TEXT,
+    summary TEXT NOT NULL,
+    ts INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_audit_log_ts ON audit_log(ts DESC, id DESC)
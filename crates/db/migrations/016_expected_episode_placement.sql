@@ -0,0 +1,6 @@
+-- Where a special (season 0) airs relative to the regular episodes, when the provider
+-- says so. airs_before_* places it ahead of that episode (or season), airs_after_season
+-- after that season's finale.
+ALTER TABLE episode_expected ADD COLUMN airs_after_season INTEGER;
+ALTER TABLE episode_expected ADD COLUMN airs_before_season INTEGER;
+ALTER TABLE episode_expected ADD COLUMN airs_before_episode INTEGER
@@ -0,0 +1,10 @@
+-- Theme songs, theme videos and extra backdrops found in an item's folder.
+-- kind is theme_audio, theme_video or backdrop. sort_index keeps backdrop order.
+CREATE TABLE IF NOT EXISTS item_extra (
+    item_id TEXT NOT NULL REFERENCES item(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    path TEXT NOT NULL,
+    sort_index INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY(item_id, path)
+);
+CREATE INDEX IF NOT EXISTS idx_item_extra_kind ON item_extra(item_id, kind, sort_index);
@@ -0,0 +1,2 @@
+-- How a TV library treats season 0 files: 'season', 'skip' or 'separate'. NULL means 'season'.
+ALTER TABLE library_settings ADD COLUMN specials_policy TEXT;
@@ -0,0 +1,3 @@
+-- Season number of season items and episode number of episode items, set by the scanner.
+-- NULL for other kinds and for items scanned before this column existed.
+ALTER TABLE item ADD COLUMN index_number INTEGER;
@@ -0,0 +1,2 @@
+-- Download artwork into the image cache after each scan. Off unless the library opts in.
+ALTER TABLE library_settings ADD COLUMN prefetch_images INTEGER NOT NULL DEFAULT 0;
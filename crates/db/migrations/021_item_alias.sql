@@ -0,0 +1,8 @@
+-- Other titles an item is known by, matched by search: the original-language title
+-- and the provider's alternative titles. Rebuilt whenever provider metadata is merged.
+CREATE TABLE IF NOT EXISTS item_alias (
+    item_id TEXT NOT NULL REFERENCES item(id) ON DELETE CASCADE,
+    alias TEXT NOT NULL,
+    PRIMARY KEY(item_id, alias)
+);
+CREATE INDEX IF NOT EXISTS idx_item_alias_alias ON item_alias(alias);
@@ -0,0 +1,2 @@
+-- Scan this library when the server boots if the server's scan_on_startup setting is 'flagged'.
+ALTER TABLE library_settings ADD COLUMN scan_on_startup INTEGER NOT NULL DEFAULT 0;
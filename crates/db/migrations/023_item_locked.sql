@@ -0,0 +1,3 @@
+-- Whole-item lock: scans, metadata merges and artwork refreshes leave a locked item alone.
+-- Distinct from the per-field locks in item_field_lock.
+ALTER TABLE item ADD COLUMN locked INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Derive ids of newly scanned items from the library and what the scanner matches on,
+-- so rebuilding the database from the same tree yields the same ids.
+ALTER TABLE library_settings ADD COLUMN deterministic_ids INTEGER NOT NULL DEFAULT 0;
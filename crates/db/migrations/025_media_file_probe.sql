@@ -0,0 +1,16 @@
+-- Last ffprobe result of each media file, as MediaInfo JSON.
+CREATE TABLE IF NOT EXISTS media_file_probe (
+    file_id TEXT PRIMARY KEY REFERENCES media_file(id) ON DELETE CASCADE,
+    info_json TEXT NOT NULL,
+    probed_ts INTEGER NOT NULL
+);
+
+-- Audio and subtitle languages found by the probe, for language listings and filters.
+CREATE TABLE IF NOT EXISTS media_file_language (
+    file_id TEXT NOT NULL REFERENCES media_file(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    language TEXT NOT NULL,
+    PRIMARY KEY (file_id, kind, language)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_file_language_kind ON media_file_language(kind, language);
@@ -0,0 +1,14 @@
+-- Cast and crew details fetched from a metadata provider, cached by the
+-- provider's person id. Filled in when a person page is first requested.
+CREATE TABLE IF NOT EXISTS person (
+    provider TEXT NOT NULL,
+    provider_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    biography TEXT,
+    birthday TEXT,
+    deathday TEXT,
+    place_of_birth TEXT,
+    image_url TEXT,
+    fetched_ts INTEGER NOT NULL,
+    PRIMARY KEY (provider, provider_id)
+);
@@ -0,0 +1,14 @@
+-- Ordered lists of items a user asked to play in a row, e.g. "play all" on a
+-- season, so the next item can be looked up after a client reloads.
+CREATE TABLE IF NOT EXISTS play_queue (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+    created_ts INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS play_queue_item (
+    queue_id TEXT NOT NULL REFERENCES play_queue(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    item_id TEXT NOT NULL,
+    PRIMARY KEY (queue_id, position)
+);
@@ -0,0 +1,21 @@
+-- Saved playlists. Imported ones mirror an .m3u or .m3u8 file found in a library
+-- folder and are re-read when the file's mtime changes.
+CREATE TABLE IF NOT EXISTS playlist (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    library_id TEXT REFERENCES library(id) ON DELETE CASCADE,
+    source_path TEXT UNIQUE,
+    source_mtime_ts INTEGER,
+    created_ts INTEGER NOT NULL,
+    updated_ts INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_playlist_user ON playlist(user_id);
+
+CREATE TABLE IF NOT EXISTS playlist_item (
+    playlist_id TEXT NOT NULL REFERENCES playlist(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    item_id TEXT NOT NULL REFERENCES item(id) ON DELETE CASCADE,
+    PRIMARY KEY (playlist_id, position)
+);
@@ -0,0 +1,3 @@
+-- In movie libraries, skip new files shorter than this many seconds as samples or
+-- extras. 0 turns the check off.
+ALTER TABLE library_settings ADD COLUMN sample_max_duration_secs INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- Position of a library in listings, set by an admin. Libraries without one sort
+-- after those that have it, by name.
+ALTER TABLE library ADD COLUMN display_order INTEGER;
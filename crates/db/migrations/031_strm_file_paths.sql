@@ -0,0 +1,5 @@
+-- `.strm` media files are keyed by the path of the `.strm` file itself, and the
+-- http(s) URL it points at is kept in `remote_url`. Older rows used the URL as
+-- their path, and the next scan moves them to the `.strm` path.
+ALTER TABLE media_file ADD COLUMN remote_url TEXT;
+UPDATE media_file SET remote_url = path WHERE is_remote = 1;
@@ -0,0 +1,8 @@
+-- When a scan of the library last started, kept here so schedules survive the
+-- job history being purged. Filled from the jobs still on record.
+ALTER TABLE library_settings ADD COLUMN last_scan_ts INTEGER;
+UPDATE library_settings SET last_scan_ts = (
+  SELECT MAX(j.created_ts) FROM job j
+  WHERE j.kind = 'library_scan'
+    AND json_extract(j.payload_json, '$.library_id') = library_settings.library_id
+);
@@ -0,0 +1,24 @@
+-- Probe results keyed by path, size and mtime, so a scan can record the probe of a
+-- file before (or instead of) adding it, and a changed file is probed again.
+-- file_id is set once the file's track languages have been recorded.
+ALTER TABLE media_file_probe RENAME TO media_file_probe_old;
+
+CREATE TABLE IF NOT EXISTS media_file_probe (
+    path TEXT PRIMARY KEY,
+    size_bytes INTEGER NOT NULL,
+    mtime_ts INTEGER NOT NULL,
+    duration_secs REAL,
+    info_json TEXT NOT NULL,
+    file_id TEXT REFERENCES media_file(id) ON DELETE CASCADE,
+    probed_ts INTEGER NOT NULL
+);
+
+INSERT INTO media_file_probe (path, size_bytes, mtime_ts, duration_secs, info_json, file_id, probed_ts)
+SELECT f.path, f.size_bytes, f.mtime_ts, json_extract(o.info_json, '$.duration_secs'),
+       o.info_json, o.file_id, o.probed_ts
+FROM media_file_probe_old o
+JOIN media_file f ON f.id = o.file_id;
+
+DROP TABLE media_file_probe_old;
+
+CREATE INDEX IF NOT EXISTS idx_media_file_probe_file ON media_file_probe(file_id);
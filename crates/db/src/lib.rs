@@ -2,10 +2,12 @@
 pub mod migrate;
 pub mod repo;
 
+use sqlx::ConnectOptions;
 use sqlx::SqlitePool;
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
 use std::path::Path;
 use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, thiserror::Error)]
 pub enum DbError {
@@ -15,22 +17,37 @@ pub enum DbError {
     Hash(String),
 }
 
+/// Statements slower than this are logged at `warn` by default.
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+
 /// Create a SQLite connection pool with WAL mode enabled.
 pub async fn connect(db_path: &str) -> Result<SqlitePool, sqlx::Error> {
+    connect_with(connect_options(db_path, DEFAULT_SLOW_QUERY_THRESHOLD)?).await
+}
+
+/// Connection options for `db_path`: WAL mode, foreign keys, and a `warn` event
+/// (target `sqlx::query`) for any statement slower than `slow_query_threshold`.
+/// The event carries the SQL text and timings; bound parameters are never logged.
+pub fn connect_options(
+    db_path: &str,
+    slow_query_threshold: Duration,
+) -> Result<SqliteConnectOptions, sqlx::Error> {
     // Ensure parent directory exists
     if let Some(parent) = Path::new(db_path).parent() {
         std::fs::create_dir_all(parent).ok();
     }
 
-    let opts = SqliteConnectOptions::from_str(db_path)?
+    Ok(SqliteConnectOptions::from_str(db_path)?
         .create_if_missing(true)
         .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-        .foreign_keys(true);
+        .foreign_keys(true)
+        .log_slow_statements(log::LevelFilter::Warn, slow_query_threshold))
+}
 
-    let pool = SqlitePoolOptions::new()
+/// Create a connection pool from [`connect_options`].
+pub async fn connect_with(opts: SqliteConnectOptions) -> Result<SqlitePool, sqlx::Error> {
+    SqlitePoolOptions::new()
         .max_connections(5)
         .connect_with(opts)
-        .await?;
-
-    Ok(pool)
+        .await
 }
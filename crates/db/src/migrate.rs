@@ -22,6 +22,109 @@ const MIGRATIONS: &[(&str, &str)] = &[
         "005_library_settings",
         include_str!("../migrations/005_library_settings.sql"),
     ),
+    (
+        "006_library_metadata_locale",
+        include_str!("../migrations/006_library_metadata_locale.sql"),
+    ),
+    ("007_sync", include_str!("../migrations/007_sync.sql")),
+    (
+        "008_job_indexes",
+        include_str!("../migrations/008_job_indexes.sql"),
+    ),
+    (
+        "009_remote_media_files",
+        include_str!("../migrations/009_remote_media_files.sql"),
+    ),
+    (
+        "010_library_scan_schedule",
+        include_str!("../migrations/010_library_scan_schedule.sql"),
+    ),
+    ("011_studios", include_str!("../migrations/011_studios.sql")),
+    (
+        "012_library_default_sort",
+        include_str!("../migrations/012_library_default_sort.sql"),
+    ),
+    (
+        "013_scan_errors",
+        include_str!("../migrations/013_scan_errors.sql"),
+    ),
+    (
+        "014_media_file_quality",
+        include_str!("../migrations/014_media_file_quality.sql"),
+    ),
+    (
+        "015_audit_log",
+        include_str!("../migrations/015_audit_log.sql"),
+    ),
+    (
+        "016_expected_episode_placement",
+        include_str!("../migrations/016_expected_episode_placement.sql"),
+    ),
+    (
+        "017_item_extras",
+        include_str!("../migrations/017_item_extras.sql"),
+    ),
+    (
+        "018_library_specials_policy",
+        include_str!("../migrations/018_library_specials_policy.sql"),
+    ),
+    (
+        "019_item_index_number",
+        include_str!("../migrations/019_item_index_number.sql"),
+    ),
+    (
+        "020_library_image_prefetch",
+        include_str!("../migrations/020_library_image_prefetch.sql"),
+    ),
+    (
+        "021_item_alias",
+        include_str!("../migrations/021_item_alias.sql"),
+    ),
+    (
+        "022_library_scan_on_startup",
+        include_str!("../migrations/022_library_scan_on_startup.sql"),
+    ),
+    (
+        "023_item_locked",
+        include_str!("../migrations/023_item_locked.sql"),
+    ),
+    (
+        "024_library_deterministic_ids",
+        include_str!("../migrations/024_library_deterministic_ids.sql"),
+    ),
+    (
+        "025_media_file_probe",
+        include_str!("../migrations/025_media_file_probe.sql"),
+    ),
+    ("026_person", include_str!("../migrations/026_person.sql")),
+    (
+        "027_play_queue",
+        include_str!("../migrations/027_play_queue.sql"),
+    ),
+    (
+        "028_playlist",
+        include_str!("../migrations/028_playlist.sql"),
+    ),
+    (
+        "029_library_sample_detection",
+        include_str!("../migrations/029_library_sample_detection.sql"),
+    ),
+    (
+        "030_library_display_order",
+        include_str!("../migrations/030_library_display_order.sql"),
+    ),
+    (
+        "031_strm_file_paths",
+        include_str!("../migrations/031_strm_file_paths.sql"),
+    ),
+    (
+        "032_library_last_scan",
+        include_str!("../migrations/032_library_last_scan.sql"),
+    ),
+    (
+        "033_media_file_probe_by_path",
+        include_str!("../migrations/033_media_file_probe_by_path.sql"),
+    ),
 ];
 
 /// Run forward-only migrations. Tracks applied migrations in a `_migrations` table.
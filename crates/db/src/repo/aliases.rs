@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use sqlx::SqlitePool;
+
+/// Replace an item's search aliases with `aliases`.
+///
+/// Blank entries are dropped and the rest deduped case-insensitively, keeping the
+/// first spelling seen.
+pub async fn set_item_aliases(
+    pool: &SqlitePool,
+    item_id: &str,
+    aliases: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM item_alias WHERE item_id = ?")
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut seen = HashSet::new();
+    for alias in aliases {
+        let alias = alias.trim();
+        if alias.is_empty() || !seen.insert(alias.to_lowercase()) {
+            continue;
+        }
+        sqlx::query("INSERT OR IGNORE INTO item_alias (item_id, alias) VALUES (?, ?)")
+            .bind(item_id)
+            .bind(alias)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// An item's aliases, in alphabetical order.
+pub async fn list_item_aliases(
+    pool: &SqlitePool,
+    item_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> =
+        sqlx::query_as("SELECT alias FROM item_alias WHERE item_id = ? ORDER BY alias")
+            .bind(item_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().map(|(alias,)| alias).collect())
+}
@@ -0,0 +1,97 @@
+use sqlx::SqlitePool;
+
+/// One administrative action recorded in the audit log.
+#[derive(Debug, Clone)]
+pub struct AuditEntryRow {
+    pub id: String,
+    /// The admin who performed the action; `None` for actions without a session.
+    pub actor_user_id: Option<String>,
+    /// Dotted action name, e.g. `user.create`.
+    pub action: String,
+    /// Id of the user, library or setting the action applied to.
+    pub target: Option<String>,
+    /// Human-readable description. Never contains passwords or API keys.
+    pub summary: String,
+    pub ts: i64,
+}
+
+/// Append an entry to the audit log.
+pub async fn record_audit(
+    pool: &SqlitePool,
+    actor_user_id: Option<&str>,
+    action: &str,
+    target: Option<&str>,
+    summary: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO audit_log (id, actor_user_id, action, target, summary, ts) \
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(actor_user_id)
+    .bind(action)
+    .bind(target)
+    .bind(summary)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Optional filters for [`list_audit_page`].
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter<'a> {
+    pub actor_user_id: Option<&'a str>,
+    pub action: Option<&'a str>,
+    pub target: Option<&'a str>,
+}
+
+type AuditTuple = (String, Option<String>, String, Option<String>, String, i64);
+
+/// One page of audit entries (newest first) plus the total number matching the filter.
+pub async fn list_audit_page(
+    pool: &SqlitePool,
+    filter: &AuditFilter<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<AuditEntryRow>, i64), sqlx::Error> {
+    let (total,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM audit_log \
+         WHERE (?1 IS NULL OR actor_user_id = ?1) AND (?2 IS NULL OR action = ?2) \
+         AND (?3 IS NULL OR target = ?3)",
+    )
+    .bind(filter.actor_user_id)
+    .bind(filter.action)
+    .bind(filter.target)
+    .fetch_one(pool)
+    .await?;
+
+    let rows: Vec<AuditTuple> = sqlx::query_as(
+        "SELECT id, actor_user_id, action, target, summary, ts FROM audit_log \
+         WHERE (?1 IS NULL OR actor_user_id = ?1) AND (?2 IS NULL OR action = ?2) \
+         AND (?3 IS NULL OR target = ?3) \
+         ORDER BY ts DESC, rowid DESC LIMIT ?4 OFFSET ?5",
+    )
+    .bind(filter.actor_user_id)
+    .bind(filter.action)
+    .bind(filter.target)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let entries = rows
+        .into_iter()
+        .map(
+            |(id, actor_user_id, action, target, summary, ts)| AuditEntryRow {
+                id,
+                actor_user_id,
+                action,
+                target,
+                summary,
+                ts,
+            },
+        )
+        .collect();
+    Ok((entries, total))
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct DuplicateMember {
+    pub item_id: String,
+    pub library_id: String,
+    pub title: String,
+    pub year: Option<i64>,
+    pub tmdb_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup {
+    pub kind: String,
+    /// Why the members were grouped: `provider:<id>` or `title:<normalized title>|<year>`.
+    pub key: String,
+    pub items: Vec<DuplicateMember>,
+}
+
+fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Group top-level movies/series that look like the same title across the whole server.
+///
+/// Items match when they share a TMDB id, or when their normalized title and year are equal.
+pub async fn find_duplicate_groups(pool: &SqlitePool) -> Result<Vec<DuplicateGroup>, sqlx::Error> {
+    let rows: Vec<(String, String, String, String, Option<i64>, Option<String>)> = sqlx::query_as(
+        "SELECT i.id, i.library_id, i.kind, i.title, i.year, p.value \
+         FROM item i \
+         LEFT JOIN item_provider_id p ON p.item_id = i.id AND p.provider = 'tmdb' \
+         WHERE i.parent_id IS NULL AND i.kind IN ('movie', 'series') \
+         ORDER BY i.kind, i.title, i.id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let title_keys: Vec<String> = rows
+        .iter()
+        .map(|(_, _, kind, title, year, _)| {
+            format!(
+                "{kind}|title:{}|{}",
+                normalize_title(title),
+                year.map(|y| y.to_string()).unwrap_or_default()
+            )
+        })
+        .collect();
+
+    let mut parent: Vec<usize> = (0..rows.len()).collect();
+    let mut first_by_key: HashMap<String, usize> = HashMap::new();
+    for (idx, (_, _, kind, _, _, tmdb_id)) in rows.iter().enumerate() {
+        let mut keys = vec![title_keys[idx].clone()];
+        if let Some(tmdb_id) = tmdb_id {
+            keys.push(format!("{kind}|provider:tmdb:{tmdb_id}"));
+        }
+        for key in keys {
+            if let Some(&other) = first_by_key.get(&key) {
+                let a = find_root(&mut parent, idx);
+                let b = find_root(&mut parent, other);
+                parent[a] = b;
+            } else {
+                first_by_key.insert(key, idx);
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in 0..rows.len() {
+        let root = find_root(&mut parent, idx);
+        groups.entry(root).or_default().push(idx);
+    }
+
+    let mut result: Vec<DuplicateGroup> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(_, members)| {
+            let first = &rows[members[0]];
+            let shared_tmdb = first.5.as_ref().filter(|id| {
+                members
+                    .iter()
+                    .all(|&m| rows[m].5.as_deref() == Some(id.as_str()))
+            });
+            let key = match shared_tmdb {
+                Some(id) => format!("provider:tmdb:{id}"),
+                None => title_keys[members[0]]
+                    .split_once('|')
+                    .map(|(_, k)| k.to_string())
+                    .unwrap_or_default(),
+            };
+            DuplicateGroup {
+                kind: first.2.clone(),
+                key,
+                items: members
+                    .into_iter()
+                    .map(|m| {
+                        let (id, library_id, _, title, year, tmdb_id) = rows[m].clone();
+                        DuplicateMember {
+                            item_id: id,
+                            library_id,
+                            title,
+                            year,
+                            tmdb_id,
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    result.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(result)
+}
+
+/// Items sharing one provider id within a library.
+#[derive(Debug, Clone)]
+pub struct ProviderIdConflict {
+    pub library_id: String,
+    pub kind: String,
+    pub provider: String,
+    pub provider_id: String,
+    pub items: Vec<DuplicateMember>,
+}
+
+/// Other items of the same kind in `item_id`'s library that already hold
+/// `provider_id` for `provider`.
+pub async fn provider_id_owners(
+    pool: &SqlitePool,
+    item_id: &str,
+    provider: &str,
+    provider_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT o.id FROM item_provider_id p \
+         JOIN item o ON o.id = p.item_id \
+         JOIN item i ON i.id = ? \
+         WHERE p.provider = ? AND p.value = ? \
+           AND o.id != i.id AND o.library_id = i.library_id AND o.kind = i.kind \
+         ORDER BY o.id",
+    )
+    .bind(item_id)
+    .bind(provider)
+    .bind(provider_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Every provider id held by more than one item of the same kind in a library.
+pub async fn find_provider_id_conflicts(
+    pool: &SqlitePool,
+) -> Result<Vec<ProviderIdConflict>, sqlx::Error> {
+    let rows: Vec<(String, String, String, String, String, String, Option<i64>)> = sqlx::query_as(
+        "SELECT i.library_id, i.kind, p.provider, p.value, i.id, i.title, i.year \
+         FROM item_provider_id p \
+         JOIN item i ON i.id = p.item_id \
+         WHERE (i.library_id, i.kind, p.provider, p.value) IN ( \
+           SELECT i2.library_id, i2.kind, p2.provider, p2.value \
+           FROM item_provider_id p2 JOIN item i2 ON i2.id = p2.item_id \
+           GROUP BY i2.library_id, i2.kind, p2.provider, p2.value \
+           HAVING COUNT(*) > 1) \
+         ORDER BY i.library_id, i.kind, p.provider, p.value, i.title, i.id",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut result: Vec<ProviderIdConflict> = Vec::new();
+    for (library_id, kind, provider, provider_id, item_id, title, year) in rows {
+        let member = DuplicateMember {
+            item_id,
+            library_id: library_id.clone(),
+            title,
+            year,
+            tmdb_id: (provider == "tmdb").then(|| provider_id.clone()),
+        };
+        match result.last_mut() {
+            Some(group)
+                if group.library_id == library_id
+                    && group.kind == kind
+                    && group.provider == provider
+                    && group.provider_id == provider_id =>
+            {
+                group.items.push(member);
+            }
+            _ => result.push(ProviderIdConflict {
+                library_id,
+                kind,
+                provider,
+                provider_id,
+                items: vec![member],
+            }),
+        }
+    }
+    Ok(result)
+}
+
+/// Merge `source_id` into `target_id`: the source's files, provider ids, missing
+/// metadata and per-user state move to the target, then the source item is deleted.
+///
+/// Seasons and episodes of a series are merged the same way into the target's
+/// child with the same number (or, without one, the same title); the rest are
+/// moved under the target, into its library.
+///
+/// Returns `false` if either item does not exist.
+pub async fn merge_items(
+    pool: &SqlitePool,
+    source_id: &str,
+    target_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM item WHERE id IN (?, ?)")
+        .bind(source_id)
+        .bind(target_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if count != 2 {
+        return Ok(false);
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut pending = vec![(source_id.to_string(), target_id.to_string())];
+    let mut merged = Vec::new();
+    while let Some((source_id, target_id)) = pending.pop() {
+        merge_item_state(&mut tx, &source_id, &target_id, now).await?;
+
+        let children: Vec<(String, String, Option<i64>, String)> = sqlx::query_as(
+            "SELECT id, kind, index_number, title FROM item WHERE parent_id = ? ORDER BY id",
+        )
+        .bind(&source_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        for (child_id, kind, index_number, title) in children {
+            let twin: Option<(String,)> = sqlx::query_as(
+                "SELECT id FROM item WHERE parent_id = ? AND kind = ? \
+                   AND (index_number = ? OR (? IS NULL AND index_number IS NULL AND title = ?)) \
+                 ORDER BY id LIMIT 1",
+            )
+            .bind(&target_id)
+            .bind(&kind)
+            .bind(index_number)
+            .bind(index_number)
+            .bind(&title)
+            .fetch_optional(&mut *tx)
+            .await?;
+            match twin {
+                Some((twin_id,)) => pending.push((child_id, twin_id)),
+                None => {
+                    sqlx::query(
+                        "UPDATE item SET parent_id = CASE WHEN id = ?3 THEN ?1 ELSE parent_id END, \
+                           library_id = (SELECT library_id FROM item WHERE id = ?1), \
+                           updated_ts = ?2 \
+                         WHERE id = ?3 OR parent_id = ?3",
+                    )
+                    .bind(&target_id)
+                    .bind(now)
+                    .bind(&child_id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+        merged.push(source_id);
+    }
+
+    // Children go first so a parent's cascade never reaches an unmerged item.
+    for source_id in merged.iter().rev() {
+        sqlx::query(
+            "INSERT OR REPLACE INTO item_tombstone (item_id, library_id, deleted_ts) \
+             SELECT id, library_id, ? FROM item WHERE id = ?",
+        )
+        .bind(now)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM item WHERE id = ?")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Move one item's files, provider ids, missing metadata and per-user state to
+/// another.
+async fn merge_item_state(
+    conn: &mut sqlx::SqliteConnection,
+    source_id: &str,
+    target_id: &str,
+    now: i64,
+) -> Result<(), sqlx::Error> {
+    // Files become additional versions of the target.
+    sqlx::query("UPDATE episode_file_map SET episode_item_id = ? WHERE episode_item_id = ?")
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *conn)
+        .await?;
+
+    // Fill metadata blanks on the target from the source.
+    sqlx::query(
+        "UPDATE item SET \
+           sort_title = COALESCE(item.sort_title, s.sort_title), \
+           year = COALESCE(item.year, s.year), \
+           overview = COALESCE(item.overview, s.overview), \
+           original_title = COALESCE(item.original_title, s.original_title), \
+           tagline = COALESCE(item.tagline, s.tagline), \
+           premiere_date = COALESCE(item.premiere_date, s.premiere_date), \
+           end_date = COALESCE(item.end_date, s.end_date), \
+           runtime_minutes = COALESCE(item.runtime_minutes, s.runtime_minutes), \
+           community_rating = COALESCE(item.community_rating, s.community_rating), \
+           official_rating = COALESCE(item.official_rating, s.official_rating), \
+           genres_json = COALESCE(item.genres_json, s.genres_json), \
+           studios_json = COALESCE(item.studios_json, s.studios_json), \
+           poster_url = COALESCE(item.poster_url, s.poster_url), \
+           backdrop_url = COALESCE(item.backdrop_url, s.backdrop_url), \
+           logo_url = COALESCE(item.logo_url, s.logo_url), \
+           thumb_url = COALESCE(item.thumb_url, s.thumb_url), \
+           updated_ts = ? \
+         FROM (SELECT * FROM item WHERE id = ?) AS s \
+         WHERE item.id = ?",
+    )
+    .bind(now)
+    .bind(source_id)
+    .bind(target_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO item_provider_id (item_id, provider, value, locked) \
+         SELECT ?, provider, value, locked FROM item_provider_id WHERE item_id = ?",
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await?;
+
+    // Studio links follow `studios_json`, which only moves when the target has none.
+    sqlx::query(
+        "INSERT OR IGNORE INTO item_studio (item_id, studio_id) \
+         SELECT ?1, studio_id FROM item_studio WHERE item_id = ?2 \
+           AND NOT EXISTS (SELECT 1 FROM item_studio WHERE item_id = ?1)",
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT OR IGNORE INTO user_item_state \
+         (user_id, item_id, played, progress_ms, last_played_ts, favorite) \
+         SELECT user_id, ?, played, progress_ms, last_played_ts, favorite \
+         FROM user_item_state WHERE item_id = ?",
+    )
+    .bind(target_id)
+    .bind(source_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
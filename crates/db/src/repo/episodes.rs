@@ -8,9 +8,47 @@ pub struct ExpectedEpisodeRow {
     pub title: Option<String>,
     pub overview: Option<String>,
     pub air_date: Option<String>,
+    pub airs_after_season: Option<i32>,
+    pub airs_before_season: Option<i32>,
+    pub airs_before_episode: Option<i32>,
+}
+
+/// Where a special airs among the regular episodes, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpecialPlacement {
+    pub airs_after_season: Option<i32>,
+    pub airs_before_season: Option<i32>,
+    pub airs_before_episode: Option<i32>,
+}
+
+type ExpectedEpisodeTuple = (
+    String,
+    i32,
+    i32,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<i32>,
+    Option<i32>,
+    Option<i32>,
+);
+
+fn row_to_expected(r: ExpectedEpisodeTuple) -> ExpectedEpisodeRow {
+    ExpectedEpisodeRow {
+        series_id: r.0,
+        season_number: r.1,
+        episode_number: r.2,
+        title: r.3,
+        overview: r.4,
+        air_date: r.5,
+        airs_after_season: r.6,
+        airs_before_season: r.7,
+        airs_before_episode: r.8,
+    }
 }
 
 /// Insert or update an expected episode.
+#[allow(clippy::too_many_arguments)]
 pub async fn upsert_expected_episode(
     pool: &SqlitePool,
     series_id: &str,
@@ -19,14 +57,19 @@ pub async fn upsert_expected_episode(
     title: Option<&str>,
     overview: Option<&str>,
     air_date: Option<&str>,
+    placement: SpecialPlacement,
 ) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO episode_expected (series_id, season_number, episode_number, title, overview, air_date) \
-         VALUES (?, ?, ?, ?, ?, ?) \
+        "INSERT INTO episode_expected (series_id, season_number, episode_number, title, overview, air_date, \
+         airs_after_season, airs_before_season, airs_before_episode) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) \
          ON CONFLICT(series_id, season_number, episode_number) DO UPDATE SET \
          title = COALESCE(excluded.title, title), \
          overview = COALESCE(excluded.overview, overview), \
-         air_date = COALESCE(excluded.air_date, air_date)",
+         air_date = COALESCE(excluded.air_date, air_date), \
+         airs_after_season = excluded.airs_after_season, \
+         airs_before_season = excluded.airs_before_season, \
+         airs_before_episode = excluded.airs_before_episode",
     )
     .bind(series_id)
     .bind(season_number)
@@ -34,6 +77,9 @@ pub async fn upsert_expected_episode(
     .bind(title)
     .bind(overview)
     .bind(air_date)
+    .bind(placement.airs_after_season)
+    .bind(placement.airs_before_season)
+    .bind(placement.airs_before_episode)
     .execute(pool)
     .await?;
     Ok(())
@@ -44,15 +90,9 @@ pub async fn get_expected_episodes(
     pool: &SqlitePool,
     series_id: &str,
 ) -> Result<Vec<ExpectedEpisodeRow>, sqlx::Error> {
-    let rows: Vec<(
-        String,
-        i32,
-        i32,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-    )> = sqlx::query_as(
-        "SELECT series_id, season_number, episode_number, title, overview, air_date \
+    let rows: Vec<ExpectedEpisodeTuple> = sqlx::query_as(
+        "SELECT series_id, season_number, episode_number, title, overview, air_date, \
+             airs_after_season, airs_before_season, airs_before_episode \
              FROM episode_expected WHERE series_id = ? \
              ORDER BY season_number, episode_number",
     )
@@ -60,17 +100,31 @@ pub async fn get_expected_episodes(
     .fetch_all(pool)
     .await?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| ExpectedEpisodeRow {
-            series_id: r.0,
-            season_number: r.1,
-            episode_number: r.2,
-            title: r.3,
-            overview: r.4,
-            air_date: r.5,
-        })
-        .collect())
+    Ok(rows.into_iter().map(row_to_expected).collect())
+}
+
+/// Reorder episodes into broadcast order: specials with placement data move next to
+/// the regular episodes they aired among, and specials without it go to the end.
+pub fn sort_in_aired_order(episodes: &mut [ExpectedEpisodeRow]) {
+    // (unplaced, season, slot, episode): regular episode E sits in slot 2E, a special
+    // airing before it in slot 2E-1, and one airing after the finale in the last slot.
+    episodes.sort_by_key(|ep| {
+        if ep.season_number > 0 {
+            return (false, ep.season_number, 2 * i64::from(ep.episode_number), 0);
+        }
+        match (
+            ep.airs_before_season,
+            ep.airs_before_episode,
+            ep.airs_after_season,
+        ) {
+            (Some(season), Some(before), _) => {
+                (false, season, 2 * i64::from(before) - 1, ep.episode_number)
+            }
+            (Some(season), None, _) => (false, season, i64::MIN, ep.episode_number),
+            (None, _, Some(season)) => (false, season, i64::MAX, ep.episode_number),
+            _ => (true, 0, 0, ep.episode_number),
+        }
+    });
 }
 
 /// Get expected episodes for a specific season.
@@ -79,15 +133,9 @@ pub async fn get_season_expected(
     series_id: &str,
     season_number: i32,
 ) -> Result<Vec<ExpectedEpisodeRow>, sqlx::Error> {
-    let rows: Vec<(
-        String,
-        i32,
-        i32,
-        Option<String>,
-        Option<String>,
-        Option<String>,
-    )> = sqlx::query_as(
-        "SELECT series_id, season_number, episode_number, title, overview, air_date \
+    let rows: Vec<ExpectedEpisodeTuple> = sqlx::query_as(
+        "SELECT series_id, season_number, episode_number, title, overview, air_date, \
+             airs_after_season, airs_before_season, airs_before_episode \
              FROM episode_expected WHERE series_id = ? AND season_number = ? \
              ORDER BY episode_number",
     )
@@ -96,17 +144,7 @@ pub async fn get_season_expected(
     .fetch_all(pool)
     .await?;
 
-    Ok(rows
-        .into_iter()
-        .map(|r| ExpectedEpisodeRow {
-            series_id: r.0,
-            season_number: r.1,
-            episode_number: r.2,
-            title: r.3,
-            overview: r.4,
-            air_date: r.5,
-        })
-        .collect())
+    Ok(rows.into_iter().map(row_to_expected).collect())
 }
 
 /// Get present episode numbers for a series (from actual items).
@@ -0,0 +1,66 @@
+use sqlx::SqlitePool;
+
+/// A theme song, theme video or extra backdrop linked to an item.
+#[derive(Debug, Clone)]
+pub struct ItemExtraRow {
+    pub item_id: String,
+    /// `theme_audio`, `theme_video` or `backdrop`.
+    pub kind: String,
+    pub path: String,
+    pub sort_index: i64,
+}
+
+/// Replace the extras linked to `item_id` with `extras` (`(kind, path)` pairs),
+/// numbering each kind in the order given.
+pub async fn replace_item_extras(
+    pool: &SqlitePool,
+    item_id: &str,
+    extras: &[(&str, &str)],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM item_extra WHERE item_id = ?")
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?;
+    let mut counts = std::collections::HashMap::new();
+    for (kind, path) in extras {
+        let index = counts.entry(*kind).or_insert(0i64);
+        sqlx::query(
+            "INSERT OR IGNORE INTO item_extra (item_id, kind, path, sort_index) VALUES (?, ?, ?, ?)",
+        )
+        .bind(item_id)
+        .bind(kind)
+        .bind(path)
+        .bind(*index)
+        .execute(&mut *tx)
+        .await?;
+        *index += 1;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Extras of one kind for an item, in their stored order.
+pub async fn list_item_extras(
+    pool: &SqlitePool,
+    item_id: &str,
+    kind: &str,
+) -> Result<Vec<ItemExtraRow>, sqlx::Error> {
+    let rows: Vec<(String, String, String, i64)> = sqlx::query_as(
+        "SELECT item_id, kind, path, sort_index FROM item_extra \
+         WHERE item_id = ? AND kind = ? ORDER BY sort_index",
+    )
+    .bind(item_id)
+    .bind(kind)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(item_id, kind, path, sort_index)| ItemExtraRow {
+            item_id,
+            kind,
+            path,
+            sort_index,
+        })
+        .collect())
+}
@@ -1,3 +1,4 @@
+use rustfin_core::types::{ItemSortBy, SortOrder};
 use sqlx::SqlitePool;
 
 #[derive(Debug, Clone)]
@@ -107,6 +108,82 @@ pub async fn get_library_items(
     Ok(rows.into_iter().map(row_to_item).collect())
 }
 
+/// Filters of [`list_library_items`]; `None` fields don't filter.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ItemFilter<'a> {
+    /// Linked to this studio (case-insensitive name).
+    pub studio: Option<&'a str>,
+    /// A file of the item (or of one of its episodes) has an audio track in this
+    /// language, per `media_file_language`.
+    pub audio_language: Option<&'a str>,
+    /// Likewise for subtitle tracks.
+    pub subtitle_language: Option<&'a str>,
+}
+
+/// Top-level items in a library that pass `filter`. Items missing the sort value
+/// always come last.
+pub async fn list_library_items(
+    pool: &SqlitePool,
+    library_id: &str,
+    filter: ItemFilter<'_>,
+    sort_by: ItemSortBy,
+    order: SortOrder,
+) -> Result<Vec<ItemRow>, sqlx::Error> {
+    let sort_column = match sort_by {
+        ItemSortBy::Title => "i.title COLLATE NOCASE",
+        ItemSortBy::DateAdded => "i.created_ts",
+        ItemSortBy::Year => "i.year",
+        ItemSortBy::Rating => "i.community_rating",
+    };
+    let direction = match order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    // Files of the item itself, its seasons' episodes, or episodes directly below it.
+    let language_match = "SELECT 1 FROM media_file_language l \
+         JOIN episode_file_map m ON m.file_id = l.file_id \
+         JOIN item e ON e.id = m.episode_item_id \
+         LEFT JOIN item s ON s.id = e.parent_id \
+         WHERE (e.id = i.id OR e.parent_id = i.id OR s.parent_id = i.id)";
+    let sql = format!(
+        "SELECT i.id, i.library_id, i.kind, i.parent_id, i.title, i.sort_title, i.year, \
+         i.overview, i.poster_url, i.backdrop_url, i.logo_url, i.thumb_url, \
+         i.created_ts, i.updated_ts FROM item i \
+         WHERE i.library_id = ?1 AND i.parent_id IS NULL \
+           AND (?2 IS NULL OR EXISTS (SELECT 1 FROM item_studio isl \
+                JOIN studio s ON s.id = isl.studio_id \
+                WHERE isl.item_id = i.id AND s.name_key = ?2)) \
+           AND (?3 IS NULL OR EXISTS ({language_match} AND l.kind = 'audio' AND l.language = ?3)) \
+           AND (?4 IS NULL OR EXISTS ({language_match} AND l.kind = 'subtitle' AND l.language = ?4)) \
+         ORDER BY {sort_column} IS NULL, {sort_column} {direction}, i.title, i.id"
+    );
+
+    let rows: Vec<(
+        String,
+        String,
+        String,
+        Option<String>,
+        String,
+        Option<String>,
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        i64,
+        i64,
+    )> = sqlx::query_as(&sql)
+        .bind(library_id)
+        .bind(filter.studio.map(super::studios::studio_key))
+        .bind(filter.audio_language.map(|l| l.trim().to_lowercase()))
+        .bind(filter.subtitle_language.map(|l| l.trim().to_lowercase()))
+        .fetch_all(pool)
+        .await?;
+
+    Ok(rows.into_iter().map(row_to_item).collect())
+}
+
 /// Get the media file ID associated with an item (via episode_file_map).
 pub async fn get_item_file_id(
     pool: &SqlitePool,
@@ -150,6 +227,28 @@ pub async fn get_item_media_path(
     Ok(row.map(|(path,)| path))
 }
 
+/// Item runtime in milliseconds: the metadata runtime, else the primary file's
+/// probed duration. `None` when neither is known.
+pub async fn get_item_runtime_ms(
+    pool: &SqlitePool,
+    item_id: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let row: Option<(Option<i64>,)> = sqlx::query_as(
+        "SELECT COALESCE(NULLIF(i.runtime_minutes, 0) * 60000, \
+            (SELECT mf.duration_ms \
+             FROM episode_file_map ef \
+             JOIN media_file mf ON mf.id = ef.file_id \
+             WHERE ef.episode_item_id = i.id AND mf.duration_ms > 0 \
+             ORDER BY ef.created_ts, ef.id \
+             LIMIT 1)) \
+         FROM item i WHERE i.id = ?",
+    )
+    .bind(item_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(|(ms,)| ms))
+}
+
 pub async fn get_first_descendant_media_path(
     pool: &SqlitePool,
     item_id: &str,
@@ -175,6 +274,252 @@ pub async fn get_first_descendant_media_path(
     Ok(row.map(|(path,)| path))
 }
 
+/// An episode beneath a series, with its numbering, file and one user's play state.
+#[derive(Debug, Clone)]
+pub struct SeriesEpisodeRow {
+    pub item: ItemRow,
+    pub season_number: Option<i64>,
+    pub episode_number: Option<i64>,
+    /// First mapped media file.
+    pub file_id: Option<String>,
+    pub played: bool,
+    pub progress_ms: i64,
+    pub last_played_ts: Option<i64>,
+    pub favorite: bool,
+}
+
+/// Episodes anywhere beneath `series_id` (all `kind = 'episode'` descendants).
+/// Season number of season `s`: the scanned number, else parsed from its title.
+const SEASON_NUMBER_SQL: &str = "COALESCE(s.index_number,
+            CASE WHEN s.title = 'Specials' THEN 0
+                 WHEN LOWER(s.title) LIKE 'season %' THEN CAST(SUBSTR(s.title, 8) AS INTEGER)
+            END)";
+
+/// Episode number of episode `e`: the scanned number, else parsed from its title.
+const EPISODE_NUMBER_SQL: &str = "COALESCE(e.index_number,
+            CASE WHEN LOWER(e.title) LIKE 'episode %' THEN CAST(SUBSTR(e.title, 9) AS INTEGER)
+            END)";
+
+fn series_episodes_cte() -> String {
+    format!(
+        "WITH RECURSIVE descendants(id) AS (
+        SELECT id FROM item WHERE id = ?
+        UNION ALL
+        SELECT i.id FROM item i JOIN descendants d ON i.parent_id = d.id
+     ),
+     episodes AS (
+        SELECT e.*,
+          {SEASON_NUMBER_SQL} AS season_number,
+          {EPISODE_NUMBER_SQL} AS episode_number
+        FROM descendants d
+        JOIN item e ON e.id = d.id AND e.kind = 'episode'
+        LEFT JOIN item s ON s.id = e.parent_id AND s.kind = 'season'
+     )"
+    )
+}
+
+/// All episodes of a series across its seasons, ordered by season then episode
+/// number. Items scanned before numbers were recorded fall back to the numbers in
+/// their `Season N` / `Episode N` titles; unnumbered episodes sort last by title.
+pub async fn list_series_episodes(
+    pool: &SqlitePool,
+    series_id: &str,
+    user_id: &str,
+) -> Result<Vec<SeriesEpisodeRow>, sqlx::Error> {
+    // The episodes and their per-user state are read in two queries (one row would
+    // not fit in a tuple); one transaction keeps them reading the same snapshot.
+    let mut tx = pool.begin().await?;
+    let rows: Vec<(
+        String,
+        String,
+        String,
+        Option<String>,
+        String,
+        Option<String>,
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        i64,
+        i64,
+        Option<i64>,
+        Option<i64>,
+    )> = sqlx::query_as(&format!(
+        "{}
+         SELECT id, library_id, kind, parent_id, title, sort_title, year, overview,
+           poster_url, backdrop_url, logo_url, thumb_url, created_ts, updated_ts,
+           season_number, episode_number
+         FROM episodes
+         ORDER BY season_number IS NULL, season_number,
+           episode_number IS NULL, episode_number, COALESCE(sort_title, title), id",
+        series_episodes_cte()
+    ))
+    .bind(series_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let extras: Vec<(String, Option<String>, bool, i64, Option<i64>, bool)> =
+        sqlx::query_as(&format!(
+            "{}
+             SELECT e.id,
+               (SELECT ef.file_id FROM episode_file_map ef WHERE ef.episode_item_id = e.id
+                ORDER BY ef.created_ts, ef.id LIMIT 1),
+               COALESCE(s.played, 0), COALESCE(s.progress_ms, 0), s.last_played_ts,
+               COALESCE(s.favorite, 0)
+             FROM episodes e
+             LEFT JOIN user_item_state s ON s.item_id = e.id AND s.user_id = ?",
+            series_episodes_cte()
+        ))
+        .bind(series_id)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    let mut extras: std::collections::HashMap<_, _> = extras
+        .into_iter()
+        .map(
+            |(id, file_id, played, progress_ms, last_played_ts, favorite)| {
+                (id, (file_id, played, progress_ms, last_played_ts, favorite))
+            },
+        )
+        .collect();
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let (season_number, episode_number) = (r.14, r.15);
+            let item = row_to_item((
+                r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9, r.10, r.11, r.12, r.13,
+            ));
+            let (file_id, played, progress_ms, last_played_ts, favorite) =
+                extras.remove(&item.id).unwrap_or_default();
+            SeriesEpisodeRow {
+                item,
+                season_number,
+                episode_number,
+                file_id,
+                played,
+                progress_ms,
+                last_played_ts,
+                favorite,
+            }
+        })
+        .collect())
+}
+
+/// Where an episode sits in its show.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EpisodeContextRow {
+    pub series_id: Option<String>,
+    pub series_title: Option<String>,
+    pub season_number: Option<i64>,
+    pub episode_number: Option<i64>,
+}
+
+/// Series and numbering for each episode in `item_ids`, keyed by episode id.
+/// Episodes sit either in a season folder or directly under the series; ids that
+/// are not episodes are left out.
+pub async fn get_episode_contexts(
+    pool: &SqlitePool,
+    item_ids: &[String],
+) -> Result<std::collections::HashMap<String, EpisodeContextRow>, sqlx::Error> {
+    if item_ids.is_empty() {
+        return Ok(std::collections::HashMap::new());
+    }
+    let sql = format!(
+        "SELECT e.id, COALESCE(g.id, p.id), COALESCE(g.title, p.title),
+           {SEASON_NUMBER_SQL}, {EPISODE_NUMBER_SQL}
+         FROM item e
+         LEFT JOIN item s ON s.id = e.parent_id AND s.kind = 'season'
+         LEFT JOIN item g ON g.id = s.parent_id AND g.kind = 'series'
+         LEFT JOIN item p ON p.id = e.parent_id AND p.kind = 'series'
+         WHERE e.kind = 'episode' AND e.id IN ({})",
+        vec!["?"; item_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<
+        _,
+        (
+            String,
+            Option<String>,
+            Option<String>,
+            Option<i64>,
+            Option<i64>,
+        ),
+    >(&sql);
+    for id in item_ids {
+        query = query.bind(id);
+    }
+    Ok(query
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(
+            |(id, series_id, series_title, season_number, episode_number)| {
+                (
+                    id,
+                    EpisodeContextRow {
+                        series_id,
+                        series_title,
+                        season_number,
+                        episode_number,
+                    },
+                )
+            },
+        )
+        .collect())
+}
+
+/// `(child_count, recursive_item_count)` for each item in `item_ids` that has
+/// children: its direct children, and its playable descendants (movies and
+/// episodes) at any depth. A series counts its seasons and all their episodes.
+pub async fn get_child_counts(
+    pool: &SqlitePool,
+    item_ids: &[String],
+) -> Result<std::collections::HashMap<String, (i64, i64)>, sqlx::Error> {
+    if item_ids.is_empty() {
+        return Ok(std::collections::HashMap::new());
+    }
+    let sql = format!(
+        "WITH RECURSIVE tree(root_id, id, kind, depth) AS (
+           SELECT parent_id, id, kind, 1 FROM item WHERE parent_id IN ({})
+           UNION ALL
+           SELECT t.root_id, i.id, i.kind, t.depth + 1
+           FROM item i JOIN tree t ON i.parent_id = t.id
+         )
+         SELECT root_id, SUM(depth = 1), SUM(kind IN ('movie', 'episode'))
+         FROM tree GROUP BY root_id",
+        vec!["?"; item_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<_, (String, i64, i64)>(&sql);
+    for id in item_ids {
+        query = query.bind(id);
+    }
+    Ok(query
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|(id, children, recursive)| (id, (children, recursive)))
+        .collect())
+}
+
+/// Poster and backdrop URLs of every item in a library that has either, newest
+/// items first.
+pub async fn list_library_artwork(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<(String, Option<String>, Option<String>)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, poster_url, backdrop_url FROM item
+         WHERE library_id = ? AND (poster_url IS NOT NULL OR backdrop_url IS NOT NULL)
+         ORDER BY created_ts DESC, id",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_item_artwork(
     pool: &SqlitePool,
     item_id: &str,
@@ -273,3 +618,269 @@ pub async fn get_item_image_url(
         .await?;
     Ok(row.and_then(|(url,)| url))
 }
+
+/// Unlocked movies and episodes of a library that have no poster, each with the id
+/// and path of a local media file.
+pub async fn list_posterless_media_items(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<(String, String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT i.id, MIN(mf.id), mf.path
+         FROM item i
+         JOIN episode_file_map ef ON ef.episode_item_id = i.id
+         JOIN media_file mf ON mf.id = ef.file_id
+         WHERE i.library_id = ? AND i.kind IN ('movie', 'episode') AND i.poster_url IS NULL
+           AND i.locked = 0 AND mf.is_remote = 0
+         GROUP BY i.id
+         ORDER BY i.id",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether the item is locked against scans and metadata refreshes.
+pub async fn is_item_locked(pool: &SqlitePool, item_id: &str) -> Result<bool, sqlx::Error> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT locked FROM item WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some_and(|(locked,)| locked != 0))
+}
+
+/// Lock or unlock an item as a whole. Returns `false` if there is no such item.
+pub async fn set_item_locked(
+    pool: &SqlitePool,
+    item_id: &str,
+    locked: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE item SET locked = ?, updated_ts = ? WHERE id = ?")
+        .bind(locked as i64)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(item_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Point one of an item's images (poster, backdrop, logo, thumb) at `url`.
+pub async fn set_item_image_url(
+    pool: &SqlitePool,
+    item_id: &str,
+    image_type: &str,
+    url: &str,
+) -> Result<(), sqlx::Error> {
+    let col = match image_type {
+        "poster" => "poster_url",
+        "backdrop" => "backdrop_url",
+        "logo" => "logo_url",
+        "thumb" => "thumb_url",
+        _ => return Ok(()),
+    };
+    let query = format!("UPDATE item SET {col} = ?, updated_ts = ? WHERE id = ?");
+    sqlx::query(&query)
+        .bind(url)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(item_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// All media file IDs mapped to an item (more than one for multi-version items).
+pub async fn get_item_file_ids(
+    pool: &SqlitePool,
+    item_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows: Vec<(String,)> = sqlx::query_as(
+        "SELECT file_id FROM episode_file_map WHERE episode_item_id = ? ORDER BY created_ts, id",
+    )
+    .bind(item_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Items whose title, original title or an alias contains `query`
+/// (case-insensitively for ASCII), seasons excluded. Exact title matches come
+/// first, then by sort title. `library_ids` restricts the libraries searched;
+/// `None` means all libraries.
+pub async fn search_items(
+    pool: &SqlitePool,
+    query: &str,
+    library_ids: Option<&[String]>,
+    limit: i64,
+) -> Result<Vec<ItemRow>, sqlx::Error> {
+    let library_filter = match library_ids {
+        Some([]) => return Ok(Vec::new()),
+        // Numbered after the fixed parameters: a bare `?` here would reuse `?2`.
+        Some(ids) => format!(
+            " AND i.library_id IN ({})",
+            (4..4 + ids.len())
+                .map(|n| format!("?{n}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        None => String::new(),
+    };
+    let sql = format!(
+        "SELECT i.id, i.library_id, i.kind, i.parent_id, i.title, i.sort_title, i.year, \
+         i.overview, i.poster_url, i.backdrop_url, i.logo_url, i.thumb_url, \
+         i.created_ts, i.updated_ts FROM item i \
+         WHERE i.kind != 'season' \
+           AND (i.title LIKE ?1 ESCAPE '\\' OR i.original_title LIKE ?1 ESCAPE '\\' \
+             OR EXISTS (SELECT 1 FROM item_alias a \
+                        WHERE a.item_id = i.id AND a.alias LIKE ?1 ESCAPE '\\')){library_filter} \
+         ORDER BY LOWER(i.title) = LOWER(?2) DESC, COALESCE(i.sort_title, i.title), i.id \
+         LIMIT ?3"
+    );
+    let pattern = format!(
+        "%{}%",
+        query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+
+    let mut q = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<i64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            i64,
+            i64,
+        ),
+    >(&sql)
+    .bind(pattern)
+    .bind(query)
+    .bind(limit);
+    for id in library_ids.unwrap_or_default() {
+        q = q.bind(id);
+    }
+    let rows = q.fetch_all(pool).await?;
+
+    Ok(rows.into_iter().map(row_to_item).collect())
+}
+
+/// Items other than seasons whose `premiere_date` sorts at or after `since`, with
+/// that date as stored. The comparison is on the text, so callers pass a prefix
+/// such as a year and parse the dates themselves.
+pub async fn list_items_premiered_since(
+    pool: &SqlitePool,
+    since: &str,
+    library_ids: Option<&[String]>,
+) -> Result<Vec<(ItemRow, String)>, sqlx::Error> {
+    let library_filter = match library_ids {
+        Some([]) => return Ok(Vec::new()),
+        Some(ids) => format!(" AND library_id IN ({})", vec!["?"; ids.len()].join(", ")),
+        None => String::new(),
+    };
+    let sql = format!(
+        "SELECT id, library_id, kind, parent_id, title, sort_title, year, overview, \
+         poster_url, backdrop_url, logo_url, thumb_url, created_ts, updated_ts, premiere_date \
+         FROM item \
+         WHERE kind != 'season' AND premiere_date >= ?{library_filter}"
+    );
+
+    let mut q = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<i64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            i64,
+            i64,
+            String,
+        ),
+    >(&sql)
+    .bind(since);
+    for id in library_ids.unwrap_or_default() {
+        q = q.bind(id);
+    }
+    let rows = q.fetch_all(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| {
+            let item = row_to_item((
+                r.0, r.1, r.2, r.3, r.4, r.5, r.6, r.7, r.8, r.9, r.10, r.11, r.12, r.13,
+            ));
+            (item, r.14)
+        })
+        .collect())
+}
+
+/// Items changed after the keyset position `(after_ts, after_id)` and no later than
+/// `until_ts`, ordered by `(updated_ts, id)`. `library_ids` restricts the libraries
+/// searched; `None` means all libraries.
+pub async fn get_items_changed_since(
+    pool: &SqlitePool,
+    after_ts: i64,
+    after_id: &str,
+    until_ts: i64,
+    library_ids: Option<&[String]>,
+    limit: i64,
+) -> Result<Vec<ItemRow>, sqlx::Error> {
+    let library_filter = match library_ids {
+        Some([]) => return Ok(Vec::new()),
+        Some(ids) => format!(" AND library_id IN ({})", vec!["?"; ids.len()].join(", ")),
+        None => String::new(),
+    };
+    let sql = format!(
+        "SELECT id, library_id, kind, parent_id, title, sort_title, year, overview, \
+         poster_url, backdrop_url, logo_url, thumb_url, \
+         created_ts, updated_ts FROM item \
+         WHERE (updated_ts > ? OR (updated_ts = ? AND id > ?)) AND updated_ts <= ?{library_filter} \
+         ORDER BY updated_ts, id LIMIT ?"
+    );
+
+    let mut query = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            String,
+            Option<String>,
+            String,
+            Option<String>,
+            Option<i64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            i64,
+            i64,
+        ),
+    >(&sql)
+    .bind(after_ts)
+    .bind(after_ts)
+    .bind(after_id)
+    .bind(until_ts);
+    for id in library_ids.unwrap_or_default() {
+        query = query.bind(id);
+    }
+    let rows = query.bind(limit).fetch_all(pool).await?;
+
+    Ok(rows.into_iter().map(row_to_item).collect())
+}
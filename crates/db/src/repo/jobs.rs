@@ -44,7 +44,29 @@ pub async fn create_job(
     })
 }
 
-pub async fn list_jobs(pool: &SqlitePool) -> Result<Vec<JobRow>, sqlx::Error> {
+/// Optional filters for [`list_jobs_page`].
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter<'a> {
+    pub status: Option<&'a str>,
+    pub kind: Option<&'a str>,
+}
+
+/// One page of jobs (newest first) plus the total number matching the filter.
+pub async fn list_jobs_page(
+    pool: &SqlitePool,
+    filter: &JobFilter<'_>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<JobRow>, i64), sqlx::Error> {
+    let (total,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM job \
+         WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR kind = ?2)",
+    )
+    .bind(filter.status)
+    .bind(filter.kind)
+    .fetch_one(pool)
+    .await?;
+
     let rows: Vec<(
         String,
         String,
@@ -56,12 +78,54 @@ pub async fn list_jobs(pool: &SqlitePool) -> Result<Vec<JobRow>, sqlx::Error> {
         i64,
     )> = sqlx::query_as(
         "SELECT id, kind, status, progress, payload_json, error, created_ts, updated_ts \
-             FROM job ORDER BY created_ts DESC",
+             FROM job \
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR kind = ?2) \
+             ORDER BY created_ts DESC, id DESC LIMIT ?3 OFFSET ?4",
     )
+    .bind(filter.status)
+    .bind(filter.kind)
+    .bind(limit)
+    .bind(offset)
     .fetch_all(pool)
     .await?;
 
-    Ok(rows.into_iter().map(row_to_job).collect())
+    Ok((rows.into_iter().map(row_to_job).collect(), total))
+}
+
+/// Jobs that are queued or running.
+pub async fn count_active_jobs(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let (count,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM job WHERE status IN ('queued', 'running')")
+            .fetch_one(pool)
+            .await?;
+    Ok(count)
+}
+
+/// Fail every queued or running job. Jobs only run inside the server process, so
+/// at startup any such job was cut short by the previous shutdown. Returns how
+/// many were failed.
+pub async fn fail_interrupted_jobs(pool: &SqlitePool) -> Result<u64, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE job SET status = 'failed', error = 'interrupted by a server restart', \
+         updated_ts = ? WHERE status IN ('queued', 'running')",
+    )
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Delete finished (completed, failed or cancelled) jobs last updated before `before_ts`.
+pub async fn purge_finished_jobs(pool: &SqlitePool, before_ts: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "DELETE FROM job \
+         WHERE status IN ('completed', 'failed', 'cancelled') AND updated_ts < ?",
+    )
+    .bind(before_ts)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
 }
 
 pub async fn get_job(pool: &SqlitePool, job_id: &str) -> Result<Option<JobRow>, sqlx::Error> {
@@ -106,6 +170,21 @@ pub async fn update_job_status(
     Ok(result.rows_affected() > 0)
 }
 
+/// Move a job from `queued` to `running`. `false` when it is no longer queued,
+/// e.g. because it was cancelled while waiting for a worker.
+pub async fn start_job(pool: &SqlitePool, job_id: &str) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE job SET status = 'running', progress = 0, updated_ts = ? \
+         WHERE id = ? AND status = 'queued'",
+    )
+    .bind(now)
+    .bind(job_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
 /// Cancel a job (only if queued or running).
 pub async fn cancel_job(pool: &SqlitePool, job_id: &str) -> Result<bool, sqlx::Error> {
     let now = chrono::Utc::now().timestamp();
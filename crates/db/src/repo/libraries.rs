@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use sqlx::SqlitePool;
 
 #[derive(Debug, Clone)]
@@ -5,6 +7,8 @@ pub struct LibraryRow {
     pub id: String,
     pub name: String,
     pub kind: String,
+    /// Position set with [`set_library_display_order`]; `None` sorts last.
+    pub display_order: Option<i64>,
     pub created_ts: i64,
     pub updated_ts: i64,
 }
@@ -18,6 +22,14 @@ pub struct LibraryPathRow {
     pub created_ts: i64,
 }
 
+/// A library path together with the library that owns it.
+#[derive(Debug, Clone)]
+pub struct LibraryPathOwnerRow {
+    pub library_id: String,
+    pub library_name: String,
+    pub path: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct LibrarySettingsRow {
     pub library_id: String,
@@ -27,6 +39,23 @@ pub struct LibrarySettingsRow {
     pub updated_ts: i64,
 }
 
+/// Every per-library setting in one row; see the individual getters for what
+/// `None` means.
+#[derive(Debug, Clone)]
+pub struct LibraryOptionsRow {
+    pub settings: LibrarySettingsRow,
+    pub metadata_language: Option<String>,
+    pub metadata_region: Option<String>,
+    pub scan_interval_secs: Option<i64>,
+    pub default_sort: Option<String>,
+    pub default_order: Option<String>,
+    pub specials_policy: Option<String>,
+    pub prefetch_images: bool,
+    pub scan_on_startup: bool,
+    pub deterministic_ids: bool,
+    pub sample_max_duration_secs: i64,
+}
+
 pub async fn create_library(
     pool: &SqlitePool,
     name: &str,
@@ -78,26 +107,34 @@ pub async fn create_library(
         id,
         name: name.to_string(),
         kind: kind.to_string(),
+        display_order: None,
         created_ts: now,
         updated_ts: now,
     })
 }
 
+/// All libraries in display order: those with a position first, then the rest,
+/// each by name.
 pub async fn list_libraries(pool: &SqlitePool) -> Result<Vec<LibraryRow>, sqlx::Error> {
-    let rows: Vec<(String, String, String, i64, i64)> =
-        sqlx::query_as("SELECT id, name, kind, created_ts, updated_ts FROM library ORDER BY name")
-            .fetch_all(pool)
-            .await?;
+    let rows: Vec<(String, String, String, Option<i64>, i64, i64)> = sqlx::query_as(
+        "SELECT id, name, kind, display_order, created_ts, updated_ts FROM library \
+         ORDER BY display_order IS NULL, display_order, name",
+    )
+    .fetch_all(pool)
+    .await?;
 
     Ok(rows
         .into_iter()
-        .map(|(id, name, kind, created_ts, updated_ts)| LibraryRow {
-            id,
-            name,
-            kind,
-            created_ts,
-            updated_ts,
-        })
+        .map(
+            |(id, name, kind, display_order, created_ts, updated_ts)| LibraryRow {
+                id,
+                name,
+                kind,
+                display_order,
+                created_ts,
+                updated_ts,
+            },
+        )
         .collect())
 }
 
@@ -105,21 +142,43 @@ pub async fn get_library(
     pool: &SqlitePool,
     library_id: &str,
 ) -> Result<Option<LibraryRow>, sqlx::Error> {
-    let row: Option<(String, String, String, i64, i64)> =
-        sqlx::query_as("SELECT id, name, kind, created_ts, updated_ts FROM library WHERE id = ?")
-            .bind(library_id)
-            .fetch_optional(pool)
-            .await?;
+    let row: Option<(String, String, String, Option<i64>, i64, i64)> = sqlx::query_as(
+        "SELECT id, name, kind, display_order, created_ts, updated_ts FROM library WHERE id = ?",
+    )
+    .bind(library_id)
+    .fetch_optional(pool)
+    .await?;
 
-    Ok(
-        row.map(|(id, name, kind, created_ts, updated_ts)| LibraryRow {
+    Ok(row.map(
+        |(id, name, kind, display_order, created_ts, updated_ts)| LibraryRow {
             id,
             name,
             kind,
+            display_order,
             created_ts,
             updated_ts,
-        }),
-    )
+        },
+    ))
+}
+
+/// Give `library_ids` the positions 0, 1, 2, ... in that order. Libraries not
+/// listed lose any position they had and sort after the listed ones.
+pub async fn set_library_display_order(
+    pool: &SqlitePool,
+    library_ids: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("UPDATE library SET display_order = NULL")
+        .execute(&mut *tx)
+        .await?;
+    for (position, id) in library_ids.iter().enumerate() {
+        sqlx::query("UPDATE library SET display_order = ? WHERE id = ?")
+            .bind(position as i64)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await
 }
 
 pub async fn update_library(
@@ -186,10 +245,24 @@ pub async fn replace_library_paths(
 }
 
 pub async fn delete_library(pool: &SqlitePool, library_id: &str) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Leave tombstones so delta-sync clients can prune the cascaded items.
+    sqlx::query(
+        "INSERT OR REPLACE INTO item_tombstone (item_id, library_id, deleted_ts) \
+         SELECT id, library_id, ? FROM item WHERE library_id = ?",
+    )
+    .bind(chrono::Utc::now().timestamp())
+    .bind(library_id)
+    .execute(&mut *tx)
+    .await?;
+
     let result = sqlx::query("DELETE FROM library WHERE id = ?")
         .bind(library_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await?;
+
+    tx.commit().await?;
     Ok(result.rows_affected() > 0)
 }
 
@@ -229,6 +302,61 @@ pub async fn count_library_items(pool: &SqlitePool, library_id: &str) -> Result<
     Ok(count)
 }
 
+/// Paths of each library in `library_ids`, keyed by library id. Libraries
+/// without paths are left out.
+pub async fn get_library_paths_for(
+    pool: &SqlitePool,
+    library_ids: &[String],
+) -> Result<HashMap<String, Vec<LibraryPathRow>>, sqlx::Error> {
+    if library_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sql = format!(
+        "SELECT id, library_id, path, is_read_only, created_ts FROM library_path \
+         WHERE library_id IN ({})",
+        vec!["?"; library_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<_, (String, String, String, bool, i64)>(&sql);
+    for id in library_ids {
+        query = query.bind(id);
+    }
+    let mut paths: HashMap<String, Vec<LibraryPathRow>> = HashMap::new();
+    for (id, library_id, path, is_read_only, created_ts) in query.fetch_all(pool).await? {
+        paths
+            .entry(library_id.clone())
+            .or_default()
+            .push(LibraryPathRow {
+                id,
+                library_id,
+                path,
+                is_read_only,
+                created_ts,
+            });
+    }
+    Ok(paths)
+}
+
+/// [`count_library_items`] for each library in `library_ids`. Empty libraries
+/// are left out.
+pub async fn count_library_items_for(
+    pool: &SqlitePool,
+    library_ids: &[String],
+) -> Result<HashMap<String, i64>, sqlx::Error> {
+    if library_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sql = format!(
+        "SELECT library_id, COUNT(*) FROM item \
+         WHERE parent_id IS NULL AND library_id IN ({}) GROUP BY library_id",
+        vec!["?"; library_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<_, (String, i64)>(&sql);
+    for id in library_ids {
+        query = query.bind(id);
+    }
+    Ok(query.fetch_all(pool).await?.into_iter().collect())
+}
+
 /// Get all library paths across all libraries.
 pub async fn get_all_library_paths(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
     let rows: Vec<(String,)> = sqlx::query_as("SELECT path FROM library_path")
@@ -237,6 +365,28 @@ pub async fn get_all_library_paths(pool: &SqlitePool) -> Result<Vec<String>, sql
     Ok(rows.into_iter().map(|r| r.0).collect())
 }
 
+/// Get every library path with its owning library, ordered by library name.
+pub async fn list_library_path_owners(
+    pool: &SqlitePool,
+) -> Result<Vec<LibraryPathOwnerRow>, sqlx::Error> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT l.id, l.name, lp.path FROM library_path lp \
+         JOIN library l ON l.id = lp.library_id \
+         ORDER BY l.name COLLATE NOCASE, lp.path",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(library_id, library_name, path)| LibraryPathOwnerRow {
+            library_id,
+            library_name,
+            path,
+        })
+        .collect())
+}
+
 pub async fn get_library_settings(
     pool: &SqlitePool,
     library_id: &str,
@@ -297,3 +447,380 @@ pub async fn upsert_library_settings(
         updated_ts: now,
     })
 }
+
+/// All settings of each library in `library_ids`, keyed by library id. Libraries
+/// without a settings row are left out.
+pub async fn get_library_options_for(
+    pool: &SqlitePool,
+    library_ids: &[String],
+) -> Result<HashMap<String, LibraryOptionsRow>, sqlx::Error> {
+    if library_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sql = format!(
+        "SELECT library_id, show_images, prefer_local_artwork, fetch_online_artwork, updated_ts, \
+           metadata_language, metadata_region, scan_interval_secs, default_sort, default_order, \
+           specials_policy, prefetch_images, scan_on_startup, deterministic_ids, \
+           sample_max_duration_secs \
+         FROM library_settings WHERE library_id IN ({})",
+        vec!["?"; library_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<
+        _,
+        (
+            String,
+            bool,
+            bool,
+            bool,
+            i64,
+            Option<String>,
+            Option<String>,
+            Option<i64>,
+            Option<String>,
+            Option<String>,
+            Option<String>,
+            bool,
+            bool,
+            bool,
+            i64,
+        ),
+    >(&sql);
+    for id in library_ids {
+        query = query.bind(id);
+    }
+    Ok(query
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                row.0.clone(),
+                LibraryOptionsRow {
+                    settings: LibrarySettingsRow {
+                        library_id: row.0,
+                        show_images: row.1,
+                        prefer_local_artwork: row.2,
+                        fetch_online_artwork: row.3,
+                        updated_ts: row.4,
+                    },
+                    metadata_language: row.5,
+                    metadata_region: row.6,
+                    scan_interval_secs: row.7,
+                    default_sort: row.8,
+                    default_order: row.9,
+                    specials_policy: row.10,
+                    prefetch_images: row.11,
+                    scan_on_startup: row.12,
+                    deterministic_ids: row.13,
+                    sample_max_duration_secs: row.14,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Per-library metadata locale override as `(language, region)`; `None` means inherit.
+pub async fn get_library_metadata_locale(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<(Option<String>, Option<String>), sqlx::Error> {
+    let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT metadata_language, metadata_region FROM library_settings WHERE library_id = ?",
+    )
+    .bind(library_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.unwrap_or((None, None)))
+}
+
+pub async fn set_library_metadata_locale(
+    pool: &SqlitePool,
+    library_id: &str,
+    language: Option<&str>,
+    region: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET metadata_language = ?, metadata_region = ?, updated_ts = ? \
+         WHERE library_id = ?",
+    )
+    .bind(language)
+    .bind(region)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Default item ordering as `(sort, order)`; `None` means title ascending.
+pub async fn get_library_default_sort(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<(Option<String>, Option<String>), sqlx::Error> {
+    let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT default_sort, default_order FROM library_settings WHERE library_id = ?",
+    )
+    .bind(library_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.unwrap_or((None, None)))
+}
+
+pub async fn set_library_default_sort(
+    pool: &SqlitePool,
+    library_id: &str,
+    sort: Option<&str>,
+    order: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET default_sort = ?, default_order = ?, updated_ts = ? \
+         WHERE library_id = ?",
+    )
+    .bind(sort)
+    .bind(order)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Automatic rescan interval in seconds; `None` means scans only run on demand.
+pub async fn get_library_scan_interval(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Option<i64>, sqlx::Error> {
+    let row: Option<(Option<i64>,)> =
+        sqlx::query_as("SELECT scan_interval_secs FROM library_settings WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|r| r.0))
+}
+
+pub async fn set_library_scan_interval(
+    pool: &SqlitePool,
+    library_id: &str,
+    interval_secs: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET scan_interval_secs = ?, updated_ts = ? WHERE library_id = ?",
+    )
+    .bind(interval_secs)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// How season 0 files are imported; `None` means as a "Specials" season.
+pub async fn get_library_specials_policy(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT specials_policy FROM library_settings WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.and_then(|r| r.0))
+}
+
+pub async fn set_library_specials_policy(
+    pool: &SqlitePool,
+    library_id: &str,
+    policy: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET specials_policy = ?, updated_ts = ? WHERE library_id = ?",
+    )
+    .bind(policy)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Whether artwork is downloaded into the image cache after each scan.
+pub async fn get_library_prefetch_images(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> =
+        sqlx::query_as("SELECT prefetch_images FROM library_settings WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.is_some_and(|r| r.0))
+}
+
+pub async fn set_library_prefetch_images(
+    pool: &SqlitePool,
+    library_id: &str,
+    enabled: bool,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET prefetch_images = ?, updated_ts = ? WHERE library_id = ?",
+    )
+    .bind(enabled)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Whether the library is scanned at boot when the server scans flagged libraries.
+pub async fn get_library_scan_on_startup(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> =
+        sqlx::query_as("SELECT scan_on_startup FROM library_settings WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.is_some_and(|r| r.0))
+}
+
+pub async fn set_library_scan_on_startup(
+    pool: &SqlitePool,
+    library_id: &str,
+    enabled: bool,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET scan_on_startup = ?, updated_ts = ? WHERE library_id = ?",
+    )
+    .bind(enabled)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Whether newly scanned items get ids derived from what they are rather than random ones.
+pub async fn get_library_deterministic_ids(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> =
+        sqlx::query_as("SELECT deterministic_ids FROM library_settings WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.is_some_and(|r| r.0))
+}
+
+pub async fn set_library_deterministic_ids(
+    pool: &SqlitePool,
+    library_id: &str,
+    enabled: bool,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET deterministic_ids = ?, updated_ts = ? WHERE library_id = ?",
+    )
+    .bind(enabled)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// In a movie library, new files shorter than this many seconds are skipped as
+/// samples; `0` (the default) imports them.
+pub async fn get_library_sample_max_duration(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<i64, sqlx::Error> {
+    let row: Option<(i64,)> = sqlx::query_as(
+        "SELECT sample_max_duration_secs FROM library_settings WHERE library_id = ?",
+    )
+    .bind(library_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map_or(0, |r| r.0))
+}
+
+pub async fn set_library_sample_max_duration(
+    pool: &SqlitePool,
+    library_id: &str,
+    secs: i64,
+) -> Result<bool, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let result = sqlx::query(
+        "UPDATE library_settings SET sample_max_duration_secs = ?, updated_ts = ? \
+         WHERE library_id = ?",
+    )
+    .bind(secs)
+    .bind(now)
+    .bind(library_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanScheduleRow {
+    pub library_id: String,
+    pub library_kind: String,
+    pub interval_secs: i64,
+    /// When the library's last scan started, or the library was created if never.
+    pub last_scan_ts: i64,
+    /// A `library_scan` job for this library is still queued or running.
+    pub scan_active: bool,
+}
+
+/// Note that a scan of `library_id` started at `ts` (unix seconds).
+pub async fn record_library_scan(
+    pool: &SqlitePool,
+    library_id: &str,
+    ts: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE library_settings SET last_scan_ts = ? WHERE library_id = ?")
+        .bind(ts)
+        .bind(library_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Libraries with an automatic rescan interval, with their latest scan state.
+pub async fn list_scan_schedules(pool: &SqlitePool) -> Result<Vec<ScanScheduleRow>, sqlx::Error> {
+    let rows: Vec<(String, String, i64, i64, bool)> = sqlx::query_as(
+        "SELECT l.id, l.kind, s.scan_interval_secs, COALESCE(s.last_scan_ts, l.created_ts), \
+           EXISTS(SELECT 1 FROM job j \
+                  WHERE j.kind = 'library_scan' AND j.status IN ('queued', 'running') \
+                    AND json_extract(j.payload_json, '$.library_id') = l.id) \
+         FROM library l JOIN library_settings s ON s.library_id = l.id \
+         WHERE s.scan_interval_secs > 0 \
+         ORDER BY l.name",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(library_id, library_kind, interval_secs, last_scan_ts, scan_active)| {
+                ScanScheduleRow {
+                    library_id,
+                    library_kind,
+                    interval_secs,
+                    last_scan_ts,
+                    scan_active,
+                }
+            },
+        )
+        .collect())
+}
@@ -0,0 +1,36 @@
+use sqlx::SqlitePool;
+
+/// Bytes the database takes up: the main file plus its write-ahead log, or its
+/// pages for an in-memory database.
+pub async fn database_size(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
+    let files: Vec<(i64, String, String)> = sqlx::query_as("PRAGMA database_list")
+        .fetch_all(pool)
+        .await?;
+    if let Some((_, _, file)) = files.into_iter().find(|(_, name, _)| name == "main")
+        && !file.is_empty()
+    {
+        let mut size = 0;
+        for path in [file.clone(), format!("{file}-wal")] {
+            size += tokio::fs::metadata(path)
+                .await
+                .map_or(0, |m| m.len() as i64);
+        }
+        return Ok(size);
+    }
+    let (pages,): (i64,) = sqlx::query_as("PRAGMA page_count").fetch_one(pool).await?;
+    let (page_size,): (i64,) = sqlx::query_as("PRAGMA page_size").fetch_one(pool).await?;
+    Ok(pages * page_size)
+}
+
+/// Refresh query planner statistics, rebuild the file without its free pages and
+/// fold the write-ahead log back into it. Runs on one connection, so `VACUUM`
+/// waits for other writers through the busy timeout instead of interleaving.
+pub async fn optimize(pool: &SqlitePool) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("PRAGMA optimize").execute(&mut *conn).await?;
+    sqlx::query("VACUUM").execute(&mut *conn).await?;
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
@@ -9,6 +9,14 @@ pub struct MediaFileRow {
     pub container: Option<String>,
     pub duration_ms: Option<i64>,
     pub stream_info_json: Option<String>,
+    /// `path` is a `.strm` file pointing at `remote_url` rather than a media file.
+    pub is_remote: bool,
+    /// For `.strm` files, the http(s) URL the file points at.
+    pub remote_url: Option<String>,
+    /// Resolution tag from the filename, e.g. `1080p`.
+    pub resolution: Option<String>,
+    /// Source tag from the filename, e.g. `BluRay`.
+    pub source: Option<String>,
     pub created_ts: i64,
     pub updated_ts: i64,
 }
@@ -25,11 +33,16 @@ pub async fn get_media_file(
         Option<String>,
         Option<i64>,
         Option<String>,
+        bool,
+        Option<String>,
+        Option<String>,
+        Option<String>,
         i64,
         i64,
     )> = sqlx::query_as(
         "SELECT id, path, size_bytes, mtime_ts, container, duration_ms, stream_info_json, \
-         created_ts, updated_ts FROM media_file WHERE id = ?",
+         is_remote, remote_url, resolution, source, created_ts, updated_ts \
+         FROM media_file WHERE id = ?",
     )
     .bind(file_id)
     .fetch_optional(pool)
@@ -43,7 +56,132 @@ pub async fn get_media_file(
         container: r.4,
         duration_ms: r.5,
         stream_info_json: r.6,
-        created_ts: r.7,
-        updated_ts: r.8,
+        is_remote: r.7,
+        remote_url: r.8,
+        resolution: r.9,
+        source: r.10,
+        created_ts: r.11,
+        updated_ts: r.12,
     }))
 }
+
+/// Record the size and mtime a local file has on disk now, if they changed since
+/// the last scan, so its stored probe no longer matches it.
+pub async fn update_file_stat(
+    pool: &SqlitePool,
+    file_id: &str,
+    size_bytes: i64,
+    mtime_ts: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE media_file SET size_bytes = ?, mtime_ts = ?, updated_ts = ? \
+         WHERE id = ? AND is_remote = 0 AND (size_bytes != ? OR mtime_ts != ?)",
+    )
+    .bind(size_bytes)
+    .bind(mtime_ts)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(file_id)
+    .bind(size_bytes)
+    .bind(mtime_ts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Files linked to a library's items, as `(file_id, path)`. `.strm` files are
+/// listed by their own path.
+pub async fn list_library_files(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<(String, String)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT DISTINCT f.id, f.path FROM media_file f \
+         JOIN episode_file_map m ON m.file_id = f.id \
+         JOIN item i ON i.id = m.episode_item_id \
+         WHERE i.library_id = ? ORDER BY f.path",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// The movie or episode a media file at `path` belongs to, if one is known.
+pub async fn item_id_for_path(
+    pool: &SqlitePool,
+    path: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT m.episode_item_id FROM media_file f \
+         JOIN episode_file_map m ON m.file_id = f.id WHERE f.path = ? \
+         ORDER BY m.episode_item_id LIMIT 1",
+    )
+    .bind(path)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(id,)| id))
+}
+
+/// Delete media files that are gone from disk, then the movies and episodes left
+/// without a file and the seasons and series left without children. Removed items
+/// get tombstones for delta sync. Returns how many items were removed.
+///
+/// Locked items are never removed, and files mapped to one are kept.
+pub async fn remove_missing_files(
+    pool: &SqlitePool,
+    file_ids: &[String],
+) -> Result<usize, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+
+    let mut candidates = Vec::new();
+    for file_id in file_ids {
+        let items: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT m.episode_item_id, i.locked FROM episode_file_map m \
+             JOIN item i ON i.id = m.episode_item_id WHERE m.file_id = ?",
+        )
+        .bind(file_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if items.iter().any(|(_, locked)| *locked != 0) {
+            continue;
+        }
+        candidates.extend(items.into_iter().map(|(id, _)| id));
+        sqlx::query("DELETE FROM media_file WHERE id = ?")
+            .bind(file_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    // Walk up from the affected items, removing each one nothing hangs off any more.
+    let mut removed = 0;
+    while let Some(item_id) = candidates.pop() {
+        let orphan: Option<(Option<String>,)> = sqlx::query_as(
+            "SELECT parent_id FROM item i WHERE id = ? AND locked = 0 \
+               AND NOT EXISTS (SELECT 1 FROM episode_file_map m WHERE m.episode_item_id = i.id) \
+               AND NOT EXISTS (SELECT 1 FROM item c WHERE c.parent_id = i.id)",
+        )
+        .bind(&item_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((parent_id,)) = orphan else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT OR REPLACE INTO item_tombstone (item_id, library_id, deleted_ts) \
+             SELECT id, library_id, ? FROM item WHERE id = ?",
+        )
+        .bind(now)
+        .bind(&item_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM item WHERE id = ?")
+            .bind(&item_id)
+            .execute(&mut *tx)
+            .await?;
+        removed += 1;
+        candidates.extend(parent_id);
+    }
+
+    tx.commit().await?;
+    Ok(removed)
+}
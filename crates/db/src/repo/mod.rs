@@ -1,10 +1,22 @@
+pub mod aliases;
+pub mod audit;
+pub mod duplicates;
 pub mod episodes;
+pub mod extras;
 pub mod idempotency;
 pub mod items;
 pub mod jobs;
 pub mod libraries;
+pub mod maintenance;
 pub mod media_files;
+pub mod people;
+pub mod play_queues;
+pub mod playlists;
 pub mod playstate;
+pub mod probes;
+pub mod scan_errors;
 pub mod settings;
 pub mod setup_session;
+pub mod studios;
+pub mod sync;
 pub mod users;
@@ -0,0 +1,91 @@
+use sqlx::SqlitePool;
+
+/// A cast or crew member's details as fetched from a metadata provider.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PersonRow {
+    /// Provider name, e.g. `tmdb`.
+    pub provider: String,
+    pub provider_id: String,
+    pub name: String,
+    pub biography: Option<String>,
+    pub birthday: Option<String>,
+    pub deathday: Option<String>,
+    pub place_of_birth: Option<String>,
+    pub image_url: Option<String>,
+    /// When the details were fetched, in unix seconds.
+    pub fetched_ts: i64,
+}
+
+/// The cached details for a provider's person id, if they were fetched before.
+pub async fn get_person(
+    pool: &SqlitePool,
+    provider: &str,
+    provider_id: &str,
+) -> Result<Option<PersonRow>, sqlx::Error> {
+    let row: Option<(
+        String,
+        String,
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        i64,
+    )> = sqlx::query_as(
+        "SELECT provider, provider_id, name, biography, birthday, deathday, place_of_birth, \
+         image_url, fetched_ts FROM person WHERE provider = ? AND provider_id = ?",
+    )
+    .bind(provider)
+    .bind(provider_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(
+        |(
+            provider,
+            provider_id,
+            name,
+            biography,
+            birthday,
+            deathday,
+            place_of_birth,
+            image_url,
+            fetched_ts,
+        )| PersonRow {
+            provider,
+            provider_id,
+            name,
+            biography,
+            birthday,
+            deathday,
+            place_of_birth,
+            image_url,
+            fetched_ts,
+        },
+    ))
+}
+
+/// Insert or replace the cached details for `person.provider`/`person.provider_id`.
+pub async fn upsert_person(pool: &SqlitePool, person: &PersonRow) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO person (provider, provider_id, name, biography, birthday, deathday, \
+         place_of_birth, image_url, fetched_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) \
+         ON CONFLICT(provider, provider_id) DO UPDATE SET name = excluded.name, \
+         biography = excluded.biography, birthday = excluded.birthday, \
+         deathday = excluded.deathday, place_of_birth = excluded.place_of_birth, \
+         image_url = excluded.image_url, fetched_ts = excluded.fetched_ts",
+    )
+    .bind(&person.provider)
+    .bind(&person.provider_id)
+    .bind(&person.name)
+    .bind(&person.biography)
+    .bind(&person.birthday)
+    .bind(&person.deathday)
+    .bind(&person.place_of_birth)
+    .bind(&person.image_url)
+    .bind(person.fetched_ts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use sqlx::SqlitePool;
+
+/// Store `item_ids`, in order, as a new queue owned by `user_id`. Returns its id.
+///
+/// Queues created more than `max_age_secs` ago are dropped, and so are the user's
+/// oldest queues beyond the newest `keep`, this one included.
+pub async fn create_queue(
+    pool: &SqlitePool,
+    user_id: &str,
+    item_ids: &[String],
+    keep: i64,
+    max_age_secs: i64,
+) -> Result<String, sqlx::Error> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    sqlx::query("INSERT INTO play_queue (id, user_id, created_ts) VALUES (?, ?, ?)")
+        .bind(&id)
+        .bind(user_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    for (position, item_id) in item_ids.iter().enumerate() {
+        sqlx::query("INSERT INTO play_queue_item (queue_id, position, item_id) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(position as i64)
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(
+        "DELETE FROM play_queue WHERE created_ts < ? \
+            OR id IN (SELECT id FROM play_queue WHERE user_id = ? \
+                      ORDER BY created_ts DESC, rowid DESC LIMIT -1 OFFSET ?)",
+    )
+    .bind(now - max_age_secs)
+    .bind(user_id)
+    .bind(keep)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Delete `user_id`'s queue `queue_id`. Returns whether there was one.
+pub async fn delete_queue(
+    pool: &SqlitePool,
+    queue_id: &str,
+    user_id: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM play_queue WHERE id = ? AND user_id = ?")
+        .bind(queue_id)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// The item ids of `user_id`'s queue `queue_id` in order, or `None` if the user
+/// has no such queue.
+pub async fn get_queue_items(
+    pool: &SqlitePool,
+    queue_id: &str,
+    user_id: &str,
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    let owned: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM play_queue WHERE id = ? AND user_id = ?")
+            .bind(queue_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    if owned.is_none() {
+        return Ok(None);
+    }
+    let items: Vec<(String,)> =
+        sqlx::query_as("SELECT item_id FROM play_queue_item WHERE queue_id = ? ORDER BY position")
+            .bind(queue_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(Some(items.into_iter().map(|(id,)| id).collect()))
+}
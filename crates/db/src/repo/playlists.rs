@@ -0,0 +1,169 @@
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct PlaylistRow {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    /// Library whose folder held the imported playlist file.
+    pub library_id: Option<String>,
+    /// The `.m3u` file an imported playlist mirrors.
+    pub source_path: Option<String>,
+    pub created_ts: i64,
+    pub updated_ts: i64,
+}
+
+const PLAYLIST_COLUMNS: &str = "id, user_id, name, library_id, source_path, created_ts, updated_ts";
+
+type PlaylistTuple = (
+    String,
+    String,
+    String,
+    Option<String>,
+    Option<String>,
+    i64,
+    i64,
+);
+
+fn playlist_row(r: PlaylistTuple) -> PlaylistRow {
+    PlaylistRow {
+        id: r.0,
+        user_id: r.1,
+        name: r.2,
+        library_id: r.3,
+        source_path: r.4,
+        created_ts: r.5,
+        updated_ts: r.6,
+    }
+}
+
+pub async fn get_playlist(
+    pool: &SqlitePool,
+    playlist_id: &str,
+) -> Result<Option<PlaylistRow>, sqlx::Error> {
+    let row: Option<PlaylistTuple> = sqlx::query_as(&format!(
+        "SELECT {PLAYLIST_COLUMNS} FROM playlist WHERE id = ?"
+    ))
+    .bind(playlist_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(playlist_row))
+}
+
+/// Playlists owned by `user_id`, by name.
+pub async fn list_user_playlists(
+    pool: &SqlitePool,
+    user_id: &str,
+) -> Result<Vec<PlaylistRow>, sqlx::Error> {
+    let rows: Vec<PlaylistTuple> = sqlx::query_as(&format!(
+        "SELECT {PLAYLIST_COLUMNS} FROM playlist WHERE user_id = ? ORDER BY name COLLATE NOCASE, id"
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(playlist_row).collect())
+}
+
+/// Item ids of a playlist in order.
+pub async fn get_playlist_items(
+    pool: &SqlitePool,
+    playlist_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    let items: Vec<(String,)> =
+        sqlx::query_as("SELECT item_id FROM playlist_item WHERE playlist_id = ? ORDER BY position")
+            .bind(playlist_id)
+            .fetch_all(pool)
+            .await?;
+    Ok(items.into_iter().map(|(id,)| id).collect())
+}
+
+/// Imported playlists of a library, as `(playlist_id, source_path, source_mtime_ts)`.
+pub async fn list_imported_playlists(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<(String, String, i64)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT id, source_path, source_mtime_ts FROM playlist \
+         WHERE library_id = ? AND source_path IS NOT NULL ORDER BY source_path",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Create or replace the playlist imported from `source_path`, owned by `user_id`,
+/// with `item_ids` in order. Returns its id, which stays stable across re-imports.
+pub async fn upsert_imported_playlist(
+    pool: &SqlitePool,
+    library_id: &str,
+    user_id: &str,
+    source_path: &str,
+    source_mtime_ts: i64,
+    name: &str,
+    item_ids: &[String],
+) -> Result<String, sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    let existing: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM playlist WHERE source_path = ?")
+            .bind(source_path)
+            .fetch_optional(&mut *tx)
+            .await?;
+    let id = match existing {
+        Some((id,)) => {
+            sqlx::query(
+                "UPDATE playlist SET user_id = ?, name = ?, library_id = ?, \
+                 source_mtime_ts = ?, updated_ts = ? WHERE id = ?",
+            )
+            .bind(user_id)
+            .bind(name)
+            .bind(library_id)
+            .bind(source_mtime_ts)
+            .bind(now)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM playlist_item WHERE playlist_id = ?")
+                .bind(&id)
+                .execute(&mut *tx)
+                .await?;
+            id
+        }
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            sqlx::query(
+                "INSERT INTO playlist (id, user_id, name, library_id, source_path, \
+                 source_mtime_ts, created_ts, updated_ts) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(user_id)
+            .bind(name)
+            .bind(library_id)
+            .bind(source_path)
+            .bind(source_mtime_ts)
+            .bind(now)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            id
+        }
+    };
+    for (position, item_id) in item_ids.iter().enumerate() {
+        sqlx::query("INSERT INTO playlist_item (playlist_id, position, item_id) VALUES (?, ?, ?)")
+            .bind(&id)
+            .bind(position as i64)
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn delete_playlist(pool: &SqlitePool, playlist_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM playlist WHERE id = ?")
+        .bind(playlist_id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
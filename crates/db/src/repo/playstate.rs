@@ -3,31 +3,91 @@ use sqlx::SqlitePool;
 /// We store playback sessions in memory for now (they're ephemeral).
 /// Progress is persisted via user_item_state.
 
+/// Record a progress report observed at `played_at` (unix seconds) in one atomic
+/// upsert. Reports older than the stored `last_played_ts` are ignored, so when two
+/// devices race the newest report wins and `last_played_ts` never moves backward.
+/// Returns `false` when the report was stale and nothing changed.
 pub async fn update_progress(
     pool: &SqlitePool,
     user_id: &str,
     item_id: &str,
     progress_ms: i64,
     played: bool,
-) -> Result<(), sqlx::Error> {
+    played_at: i64,
+) -> Result<bool, sqlx::Error> {
     let now = chrono::Utc::now().timestamp();
-    sqlx::query(
-        "INSERT INTO user_item_state (user_id, item_id, played, progress_ms, last_played_ts) \
-         VALUES (?, ?, ?, ?, ?) \
+    let result = sqlx::query(
+        "INSERT INTO user_item_state (user_id, item_id, played, progress_ms, last_played_ts, updated_ts) \
+         VALUES (?, ?, ?, ?, ?, ?) \
          ON CONFLICT(user_id, item_id) DO UPDATE SET \
          played = excluded.played, progress_ms = excluded.progress_ms, \
-         last_played_ts = excluded.last_played_ts",
+         last_played_ts = excluded.last_played_ts, updated_ts = excluded.updated_ts \
+         WHERE user_item_state.last_played_ts IS NULL \
+         OR excluded.last_played_ts >= user_item_state.last_played_ts",
     )
     .bind(user_id)
     .bind(item_id)
     .bind(played as i32)
     .bind(progress_ms)
+    .bind(played_at)
     .bind(now)
     .execute(pool)
     .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Reset an item to unplayed with no resume point. The row is zeroed rather than
+/// deleted so delta sync reports the change; `favorite` is kept unless
+/// `clear_favorite` is set.
+pub async fn reset_progress(
+    pool: &SqlitePool,
+    user_id: &str,
+    item_id: &str,
+    clear_favorite: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE user_item_state SET played = 0, progress_ms = 0, last_played_ts = NULL, \
+         favorite = CASE WHEN ? THEN 0 ELSE favorite END, updated_ts = ? \
+         WHERE user_id = ? AND item_id = ?",
+    )
+    .bind(clear_favorite)
+    .bind(chrono::Utc::now().timestamp())
+    .bind(user_id)
+    .bind(item_id)
+    .execute(pool)
+    .await?;
     Ok(())
 }
 
+/// Mark every item in `item_ids` played at `played_at`, clearing resume points.
+/// Unlike [`update_progress`] this overrides newer reports, since the user asked
+/// for it explicitly. Favorites are kept.
+pub async fn mark_played(
+    pool: &SqlitePool,
+    user_id: &str,
+    item_ids: &[String],
+    played_at: i64,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    for item_id in item_ids {
+        sqlx::query(
+            "INSERT INTO user_item_state (user_id, item_id, played, progress_ms, last_played_ts, updated_ts) \
+             VALUES (?, ?, 1, 0, ?, ?) \
+             ON CONFLICT(user_id, item_id) DO UPDATE SET \
+             played = 1, progress_ms = 0, last_played_ts = excluded.last_played_ts, \
+             updated_ts = excluded.updated_ts",
+        )
+        .bind(user_id)
+        .bind(item_id)
+        .bind(played_at)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
 #[derive(Debug, Clone)]
 pub struct PlayStateRow {
     pub user_id: String,
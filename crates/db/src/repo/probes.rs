@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use sqlx::SqlitePool;
+
+/// Audio tracks, in [`LanguageRow::kind`].
+pub const AUDIO: &str = "audio";
+/// Subtitle tracks, in [`LanguageRow::kind`].
+pub const SUBTITLE: &str = "subtitle";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageRow {
+    /// [`AUDIO`] or [`SUBTITLE`].
+    pub kind: String,
+    pub language: String,
+    /// Top-level items in the library with a file carrying this language.
+    pub item_count: i64,
+}
+
+/// Lowercased language tag, or `None` for blank and undetermined (`und`) tags.
+pub fn language_key(language: &str) -> Option<String> {
+    let key = language.trim().to_lowercase();
+    (!key.is_empty() && key != "und").then_some(key)
+}
+
+/// Store a file's probe result and replace its recorded track languages.
+///
+/// The probe is keyed by the file's path, size and mtime as the library last saw it.
+pub async fn record_probe(
+    pool: &SqlitePool,
+    file_id: &str,
+    duration_secs: Option<f64>,
+    info_json: &str,
+    audio_languages: &[String],
+    subtitle_languages: &[String],
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "INSERT INTO media_file_probe \
+           (path, size_bytes, mtime_ts, duration_secs, info_json, file_id, probed_ts) \
+         SELECT path, size_bytes, mtime_ts, ?, ?, id, ? FROM media_file WHERE id = ? \
+         ON CONFLICT(path) DO UPDATE SET size_bytes = excluded.size_bytes, \
+           mtime_ts = excluded.mtime_ts, duration_secs = excluded.duration_secs, \
+           info_json = excluded.info_json, file_id = excluded.file_id, \
+           probed_ts = excluded.probed_ts",
+    )
+    .bind(duration_secs)
+    .bind(info_json)
+    .bind(now)
+    .bind(file_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query("DELETE FROM media_file_language WHERE file_id = ?")
+        .bind(file_id)
+        .execute(&mut *tx)
+        .await?;
+    for (kind, languages) in [(AUDIO, audio_languages), (SUBTITLE, subtitle_languages)] {
+        for language in languages.iter().filter_map(|l| language_key(l)) {
+            sqlx::query(
+                "INSERT OR IGNORE INTO media_file_language (file_id, kind, language) \
+                 VALUES (?, ?, ?)",
+            )
+            .bind(file_id)
+            .bind(kind)
+            .bind(language)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+    tx.commit().await
+}
+
+/// Store the probe a scan took of a file it has not added (yet). The file's
+/// languages are recorded from it once the file is added.
+pub async fn record_path_probe(
+    pool: &SqlitePool,
+    path: &str,
+    size_bytes: i64,
+    mtime_ts: i64,
+    duration_secs: Option<f64>,
+    info_json: &str,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query(
+        "INSERT INTO media_file_probe \
+           (path, size_bytes, mtime_ts, duration_secs, info_json, file_id, probed_ts) \
+         VALUES (?, ?, ?, ?, ?, NULL, ?) \
+         ON CONFLICT(path) DO UPDATE SET size_bytes = excluded.size_bytes, \
+           mtime_ts = excluded.mtime_ts, duration_secs = excluded.duration_secs, \
+           info_json = excluded.info_json, file_id = NULL, probed_ts = excluded.probed_ts",
+    )
+    .bind(path)
+    .bind(size_bytes)
+    .bind(mtime_ts)
+    .bind(duration_secs)
+    .bind(info_json)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Probed durations of the given paths, for those whose probe was taken at this
+/// size and mtime; `None` durations could not be read. Keyed by path.
+pub async fn get_path_durations(
+    pool: &SqlitePool,
+    paths: &[(String, i64, i64)],
+) -> Result<HashMap<String, Option<f64>>, sqlx::Error> {
+    let mut durations = HashMap::new();
+    for (path, size_bytes, mtime_ts) in paths {
+        let row: Option<(Option<f64>,)> = sqlx::query_as(
+            "SELECT duration_secs FROM media_file_probe \
+             WHERE path = ? AND size_bytes = ? AND mtime_ts = ?",
+        )
+        .bind(path)
+        .bind(size_bytes)
+        .bind(mtime_ts)
+        .fetch_optional(pool)
+        .await?;
+        if let Some((duration,)) = row {
+            durations.insert(path.clone(), duration);
+        }
+    }
+    Ok(durations)
+}
+
+/// Forget scan-time probes of files under `root` that were never added and are no
+/// longer there (a deleted sample), keeping those of `present` paths.
+pub async fn prune_path_probes(
+    pool: &SqlitePool,
+    root: &str,
+    present: &HashSet<String>,
+) -> Result<u64, sqlx::Error> {
+    let unfiled: Vec<(String,)> =
+        sqlx::query_as("SELECT path FROM media_file_probe WHERE file_id IS NULL")
+            .fetch_all(pool)
+            .await?;
+    let mut removed = 0;
+    for (path,) in unfiled {
+        if Path::new(&path).starts_with(root) && !present.contains(&path) {
+            removed +=
+                sqlx::query("DELETE FROM media_file_probe WHERE path = ? AND file_id IS NULL")
+                    .bind(&path)
+                    .execute(pool)
+                    .await?
+                    .rows_affected();
+        }
+    }
+    Ok(removed)
+}
+
+/// The stored probe of a file, if it was taken at the size and mtime the library
+/// has for it.
+pub async fn get_file_probe(
+    pool: &SqlitePool,
+    file_id: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT p.info_json FROM media_file f \
+         JOIN media_file_probe p ON p.path = f.path \
+           AND p.size_bytes = f.size_bytes AND p.mtime_ts = f.mtime_ts \
+         WHERE f.id = ?",
+    )
+    .bind(file_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|(json,)| json))
+}
+
+/// A local file of a library whose languages have not been recorded for its
+/// current size and mtime.
+#[derive(Debug, Clone)]
+pub struct UnprobedFile {
+    pub file_id: String,
+    pub path: String,
+    /// A probe a scan took of the file before adding it, still matching it.
+    pub info_json: Option<String>,
+}
+
+/// Local files of a library's items without a recorded probe at their current
+/// size and mtime.
+pub async fn list_unprobed_files(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<UnprobedFile>, sqlx::Error> {
+    let rows: Vec<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT DISTINCT f.id, f.path, p.info_json FROM media_file f \
+         JOIN episode_file_map m ON m.file_id = f.id \
+         JOIN item i ON i.id = m.episode_item_id \
+         LEFT JOIN media_file_probe p ON p.path = f.path \
+           AND p.size_bytes = f.size_bytes AND p.mtime_ts = f.mtime_ts \
+         WHERE i.library_id = ? AND f.is_remote = 0 \
+           AND (p.path IS NULL OR p.file_id IS NOT f.id) \
+         ORDER BY f.path",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(file_id, path, info_json)| UnprobedFile {
+            file_id,
+            path,
+            info_json,
+        })
+        .collect())
+}
+
+/// Audio and subtitle languages of a library's probed files, with how many
+/// top-level items (movies, series) carry each. Ordered by kind, then language.
+pub async fn list_library_languages(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<LanguageRow>, sqlx::Error> {
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(
+        "SELECT l.kind, l.language, \
+                COUNT(DISTINCT COALESCE(s.parent_id, e.parent_id, e.id)) \
+         FROM media_file_language l \
+         JOIN episode_file_map m ON m.file_id = l.file_id \
+         JOIN item e ON e.id = m.episode_item_id \
+         LEFT JOIN item s ON s.id = e.parent_id \
+         WHERE e.library_id = ? \
+         GROUP BY l.kind, l.language \
+         ORDER BY l.kind, l.language",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(kind, language, item_count)| LanguageRow {
+            kind,
+            language,
+            item_count,
+        })
+        .collect())
+}
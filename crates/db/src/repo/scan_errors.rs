@@ -0,0 +1,102 @@
+use sqlx::SqlitePool;
+
+/// A file the last scan of a library failed to process.
+#[derive(Debug, Clone)]
+pub struct ScanErrorRow {
+    pub library_id: String,
+    pub path: String,
+    pub error: String,
+    /// Consecutive scans that failed on this path.
+    pub attempts: i64,
+    pub last_attempt_ts: i64,
+}
+
+/// Record a failure for `path`, bumping the attempt count if it failed before.
+pub async fn record_scan_error(
+    pool: &SqlitePool,
+    library_id: &str,
+    path: &str,
+    error: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO scan_error (library_id, path, error, attempts, last_attempt_ts) \
+         VALUES (?, ?, ?, 1, ?) \
+         ON CONFLICT(library_id, path) DO UPDATE SET \
+         error = excluded.error, attempts = attempts + 1, last_attempt_ts = excluded.last_attempt_ts",
+    )
+    .bind(library_id)
+    .bind(path)
+    .bind(error)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Forget a failure once the path scans cleanly (or is gone from the library).
+pub async fn clear_scan_error(
+    pool: &SqlitePool,
+    library_id: &str,
+    path: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM scan_error WHERE library_id = ? AND path = ?")
+        .bind(library_id)
+        .bind(path)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Drop every failure for the library except those in `failed_paths`; called after a
+/// full scan so paths that now scan cleanly, or no longer exist, are forgotten.
+pub async fn retain_scan_errors(
+    pool: &SqlitePool,
+    library_id: &str,
+    failed_paths: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let recorded: Vec<(String,)> =
+        sqlx::query_as("SELECT path FROM scan_error WHERE library_id = ?")
+            .bind(library_id)
+            .fetch_all(&mut *tx)
+            .await?;
+    for (path,) in recorded {
+        if failed_paths.contains(&path) {
+            continue;
+        }
+        sqlx::query("DELETE FROM scan_error WHERE library_id = ? AND path = ?")
+            .bind(library_id)
+            .bind(&path)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Failures recorded for a library, ordered by path.
+pub async fn list_scan_errors(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<ScanErrorRow>, sqlx::Error> {
+    let rows: Vec<(String, String, String, i64, i64)> = sqlx::query_as(
+        "SELECT library_id, path, error, attempts, last_attempt_ts FROM scan_error \
+         WHERE library_id = ? ORDER BY path",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(library_id, path, error, attempts, last_attempt_ts)| ScanErrorRow {
+                library_id,
+                path,
+                error,
+                attempts,
+                last_attempt_ts,
+            },
+        )
+        .collect())
+}
@@ -64,6 +64,8 @@ pub async fn insert_defaults(pool: &SqlitePool) -> Result<(), sqlx::Error> {
         ("allow_remote_access", "false"),
         ("enable_automatic_port_mapping", "false"),
         ("trusted_proxies", "[]"),
+        ("image_cache_max_age_secs", "86400"),
+        ("media_cacheable", "false"),
     ];
     for (key, value) in defaults {
         sqlx::query("INSERT OR IGNORE INTO settings (key, value) VALUES (?, ?)")
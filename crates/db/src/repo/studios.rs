@@ -0,0 +1,86 @@
+use std::collections::HashSet;
+
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct StudioRow {
+    pub id: String,
+    pub name: String,
+    /// Top-level items in the library linked to this studio.
+    pub item_count: i64,
+}
+
+/// Case-insensitive key used to dedupe studio names.
+pub fn studio_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Replace an item's studio links with `names`, creating studios as needed.
+///
+/// Names are deduped case-insensitively; the first spelling seen for a studio is kept.
+pub async fn set_item_studios(
+    pool: &SqlitePool,
+    item_id: &str,
+    names: &[String],
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM item_studio WHERE item_id = ?")
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let mut seen = HashSet::new();
+    for name in names {
+        let key = studio_key(name);
+        if key.is_empty() || !seen.insert(key.clone()) {
+            continue;
+        }
+
+        sqlx::query("INSERT OR IGNORE INTO studio (id, name, name_key) VALUES (?, ?, ?)")
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(name.trim())
+            .bind(&key)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO item_studio (item_id, studio_id) \
+             SELECT ?, id FROM studio WHERE name_key = ?",
+        )
+        .bind(item_id)
+        .bind(&key)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Studios used by top-level items in a library, with item counts, ordered by name.
+pub async fn list_studios(
+    pool: &SqlitePool,
+    library_id: &str,
+) -> Result<Vec<StudioRow>, sqlx::Error> {
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(
+        "SELECT s.id, s.name, COUNT(*) \
+         FROM studio s \
+         JOIN item_studio isl ON isl.studio_id = s.id \
+         JOIN item i ON i.id = isl.item_id \
+         WHERE i.library_id = ? AND i.parent_id IS NULL \
+         GROUP BY s.id \
+         ORDER BY s.name COLLATE NOCASE",
+    )
+    .bind(library_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, item_count)| StudioRow {
+            id,
+            name,
+            item_count,
+        })
+        .collect())
+}
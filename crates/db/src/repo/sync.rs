@@ -0,0 +1,128 @@
+//! Delta-sync queries: play-state changes and item deletion tombstones.
+//!
+//! All queries use keyset pagination on `(timestamp, id)` and an inclusive upper
+//! bound so a page never contains rows written after the sync snapshot.
+
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone)]
+pub struct PlayStateChangeRow {
+    pub item_id: String,
+    pub played: bool,
+    pub progress_ms: i64,
+    pub last_played_ts: Option<i64>,
+    pub favorite: bool,
+    pub updated_ts: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TombstoneRow {
+    pub item_id: String,
+    pub library_id: String,
+    pub deleted_ts: i64,
+}
+
+fn library_filter(column: &str, library_ids: Option<&[String]>) -> String {
+    match library_ids {
+        Some(ids) if !ids.is_empty() => {
+            format!(" AND {column} IN ({})", vec!["?"; ids.len()].join(", "))
+        }
+        Some(_) => " AND 0".to_string(),
+        None => String::new(),
+    }
+}
+
+/// A user's play-state rows changed after `(after_ts, after_item_id)`.
+pub async fn get_play_states_changed_since(
+    pool: &SqlitePool,
+    user_id: &str,
+    after_ts: i64,
+    after_item_id: &str,
+    until_ts: i64,
+    library_ids: Option<&[String]>,
+    limit: i64,
+) -> Result<Vec<PlayStateChangeRow>, sqlx::Error> {
+    let sql = format!(
+        "SELECT s.item_id, s.played, s.progress_ms, s.last_played_ts, s.favorite, s.updated_ts \
+         FROM user_item_state s JOIN item i ON i.id = s.item_id \
+         WHERE s.user_id = ? \
+           AND (s.updated_ts > ? OR (s.updated_ts = ? AND s.item_id > ?)) \
+           AND s.updated_ts <= ?{} \
+         ORDER BY s.updated_ts, s.item_id LIMIT ?",
+        library_filter("i.library_id", library_ids)
+    );
+
+    let mut query = sqlx::query_as::<_, (String, bool, i64, Option<i64>, bool, i64)>(&sql)
+        .bind(user_id)
+        .bind(after_ts)
+        .bind(after_ts)
+        .bind(after_item_id)
+        .bind(until_ts);
+    for id in library_ids.unwrap_or_default() {
+        query = query.bind(id);
+    }
+    let rows = query.bind(limit).fetch_all(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(item_id, played, progress_ms, last_played_ts, favorite, updated_ts)| {
+                PlayStateChangeRow {
+                    item_id,
+                    played,
+                    progress_ms,
+                    last_played_ts,
+                    favorite,
+                    updated_ts,
+                }
+            },
+        )
+        .collect())
+}
+
+/// Item tombstones recorded after `(after_ts, after_item_id)`.
+///
+/// Tombstones whose library no longer exists are always visible, since per-user
+/// access rows are removed together with the library.
+pub async fn get_tombstones_since(
+    pool: &SqlitePool,
+    after_ts: i64,
+    after_item_id: &str,
+    until_ts: i64,
+    library_ids: Option<&[String]>,
+    limit: i64,
+) -> Result<Vec<TombstoneRow>, sqlx::Error> {
+    let access_filter = match library_ids {
+        Some(ids) if !ids.is_empty() => format!(
+            " AND (library_id IN ({}) OR library_id NOT IN (SELECT id FROM library))",
+            vec!["?"; ids.len()].join(", ")
+        ),
+        Some(_) => " AND library_id NOT IN (SELECT id FROM library)".to_string(),
+        None => String::new(),
+    };
+    let sql = format!(
+        "SELECT item_id, library_id, deleted_ts FROM item_tombstone \
+         WHERE (deleted_ts > ? OR (deleted_ts = ? AND item_id > ?)) \
+           AND deleted_ts <= ?{access_filter} \
+         ORDER BY deleted_ts, item_id LIMIT ?"
+    );
+
+    let mut query = sqlx::query_as::<_, (String, String, i64)>(&sql)
+        .bind(after_ts)
+        .bind(after_ts)
+        .bind(after_item_id)
+        .bind(until_ts);
+    for id in library_ids.unwrap_or_default() {
+        query = query.bind(id);
+    }
+    let rows = query.bind(limit).fetch_all(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(item_id, library_id, deleted_ts)| TombstoneRow {
+            item_id,
+            library_id,
+            deleted_ts,
+        })
+        .collect())
+}
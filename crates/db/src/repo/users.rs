@@ -1,9 +1,10 @@
 use argon2::{
-    Argon2,
+    Algorithm, Argon2, Params, Version,
     password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
 };
 use password_hash::rand_core::OsRng;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
 
 /// User row from the database.
 #[derive(Debug, Clone)]
@@ -15,15 +16,107 @@ pub struct UserRow {
     pub created_ts: i64,
 }
 
+/// Argon2id cost parameters used for new password hashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHashParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashParams {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl PasswordHashParams {
+    /// Defaults, overridden by `RUSTFIN_ARGON2_MEMORY_KIB`, `RUSTFIN_ARGON2_ITERATIONS`
+    /// and `RUSTFIN_ARGON2_PARALLELISM`. Combinations Argon2 rejects fall back to the
+    /// defaults.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: u32| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        let params = Self {
+            memory_kib: read("RUSTFIN_ARGON2_MEMORY_KIB", defaults.memory_kib),
+            iterations: read("RUSTFIN_ARGON2_ITERATIONS", defaults.iterations),
+            parallelism: read("RUSTFIN_ARGON2_PARALLELISM", defaults.parallelism),
+        };
+        match params.argon2() {
+            Ok(_) => params,
+            Err(e) => {
+                tracing::warn!(error = %e, "invalid Argon2 parameters, using defaults");
+                defaults
+            }
+        }
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>, crate::DbError> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| crate::DbError::Hash(e.to_string()))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Whether `hash` should be replaced: it is not Argon2id v19, or any of its costs
+    /// is below these parameters.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return true;
+        };
+        if parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+        match Params::try_from(&parsed) {
+            Ok(current) => {
+                current.m_cost() < self.memory_kib
+                    || current.t_cost() < self.iterations
+                    || current.p_cost() < self.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
 /// Create a new user. Returns the user ID.
+///
+/// The password is hashed with [`PasswordHashParams::from_env`].
 pub async fn create_user(
     pool: &SqlitePool,
     username: &str,
     password: &str,
     role: &str,
+) -> Result<String, crate::DbError> {
+    create_user_with_params(
+        pool,
+        username,
+        password,
+        role,
+        &PasswordHashParams::from_env(),
+    )
+    .await
+}
+
+/// Create a new user, hashing the password with `params`. Returns the user ID.
+pub async fn create_user_with_params(
+    pool: &SqlitePool,
+    username: &str,
+    password: &str,
+    role: &str,
+    params: &PasswordHashParams,
 ) -> Result<String, crate::DbError> {
     let id = uuid::Uuid::new_v4().to_string();
-    let hash = hash_password(password)?;
+    let hash = hash_password(password, params)?;
     let now = chrono::Utc::now().timestamp();
 
     sqlx::query(
@@ -177,6 +270,31 @@ pub async fn get_library_access(
     Ok(rows.into_iter().map(|(id,)| id).collect())
 }
 
+/// [`get_library_access`] for each user in `user_ids`, keyed by user id. Users
+/// without any library are left out.
+pub async fn get_library_access_map(
+    pool: &SqlitePool,
+    user_ids: &[String],
+) -> Result<HashMap<String, Vec<String>>, sqlx::Error> {
+    if user_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let sql = format!(
+        "SELECT user_id, library_id FROM user_library_access \
+         WHERE user_id IN ({}) ORDER BY user_id, library_id",
+        vec!["?"; user_ids.len()].join(", ")
+    );
+    let mut query = sqlx::query_as::<_, (String, String)>(&sql);
+    for id in user_ids {
+        query = query.bind(id);
+    }
+    let mut access: HashMap<String, Vec<String>> = HashMap::new();
+    for (user_id, library_id) in query.fetch_all(pool).await? {
+        access.entry(user_id).or_default().push(library_id);
+    }
+    Ok(access)
+}
+
 /// Check whether a user can access a specific library.
 pub async fn is_library_allowed(
     pool: &SqlitePool,
@@ -193,6 +311,16 @@ pub async fn is_library_allowed(
     Ok(row.is_some())
 }
 
+/// The longest-standing admin, which owns server-created content such as
+/// imported playlists.
+pub async fn oldest_admin_id(pool: &SqlitePool) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM user WHERE role = 'admin' ORDER BY created_ts, id LIMIT 1")
+            .fetch_optional(pool)
+            .await?;
+    Ok(row.map(|(id,)| id))
+}
+
 /// Check if any users exist (for admin bootstrap).
 pub async fn count_users(pool: &SqlitePool) -> Result<i64, sqlx::Error> {
     let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM user")
@@ -237,9 +365,27 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool, crate::DbErro
         .is_ok())
 }
 
-fn hash_password(password: &str) -> Result<String, crate::DbError> {
+/// Re-hash a user's password with `params` and store it. Called after a successful
+/// login when [`PasswordHashParams::needs_rehash`] flags the stored hash.
+pub async fn rehash_password(
+    pool: &SqlitePool,
+    user_id: &str,
+    password: &str,
+    params: &PasswordHashParams,
+) -> Result<(), crate::DbError> {
+    let hash = hash_password(password, params)?;
+    sqlx::query("UPDATE user SET password_hash = ? WHERE id = ?")
+        .bind(&hash)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+fn hash_password(password: &str, params: &PasswordHashParams) -> Result<String, crate::DbError> {
     let salt = SaltString::generate(&mut OsRng);
-    let hash = Argon2::default()
+    let hash = params
+        .argon2()?
         .hash_password(password.as_bytes(), &salt)
         .map_err(|e| crate::DbError::Hash(e.to_string()))?;
     Ok(hash.to_string())
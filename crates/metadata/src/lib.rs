@@ -1,5 +1,6 @@
 #![allow(clippy::type_complexity)]
 pub mod merge;
+pub mod opensubtitles;
 pub mod provider;
 pub mod tmdb;
 
@@ -22,6 +23,8 @@ pub enum MetadataError {
 pub struct ItemMetadata {
     pub title: Option<String>,
     pub original_title: Option<String>,
+    /// Other titles the provider knows the item by (regional releases, translations).
+    pub alternative_titles: Option<Vec<String>>,
     pub sort_title: Option<String>,
     pub overview: Option<String>,
     pub tagline: Option<String>,
@@ -46,6 +49,21 @@ pub struct PersonInfo {
     pub role: String, // "Actor", "Director", etc.
     pub character: Option<String>,
     pub thumb_url: Option<String>,
+    /// The provider's id for the person, for [`provider::MetadataProvider::get_person`].
+    #[serde(default)]
+    pub provider_id: Option<String>,
+}
+
+/// Biography and photo of a cast or crew member.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PersonDetails {
+    pub name: String,
+    pub biography: Option<String>,
+    /// `YYYY-MM-DD`.
+    pub birthday: Option<String>,
+    pub deathday: Option<String>,
+    pub place_of_birth: Option<String>,
+    pub image_url: Option<String>,
 }
 
 /// Episode metadata from a provider (for expected episodes).
@@ -57,4 +75,13 @@ pub struct EpisodeInfo {
     pub overview: Option<String>,
     pub air_date: Option<String>,
     pub still_url: Option<String>,
+    /// Specials only: the special airs after this season's finale.
+    #[serde(default)]
+    pub airs_after_season: Option<i32>,
+    /// Specials only: the special airs before this season (or, with
+    /// `airs_before_episode`, before that episode of it).
+    #[serde(default)]
+    pub airs_before_season: Option<i32>,
+    #[serde(default)]
+    pub airs_before_episode: Option<i32>,
 }
@@ -1,7 +1,7 @@
 //! Metadata merge engine.
 //!
 //! Merge rules:
-//! 1. User edits (locked fields) always win.
+//! 1. User edits (locked fields) always win; a locked item is not touched at all.
 //! 2. Provider metadata fills in blanks.
 //! 3. Multiple providers: first non-null wins (priority order).
 
@@ -18,6 +18,25 @@ pub async fn merge_metadata(
     item_id: &str,
     provider_meta: &ItemMetadata,
 ) -> Result<MergeResult, sqlx::Error> {
+    merge_metadata_with(pool, item_id, provider_meta, true).await
+}
+
+/// Like [`merge_metadata`], but with `replace = false` provider values only fill
+/// fields the item does not have yet.
+pub async fn merge_metadata_with(
+    pool: &SqlitePool,
+    item_id: &str,
+    provider_meta: &ItemMetadata,
+    replace: bool,
+) -> Result<MergeResult, sqlx::Error> {
+    if rustfin_db::repo::items::is_item_locked(pool, item_id).await? {
+        debug!(item_id, "item is locked; skipping metadata merge");
+        return Ok(MergeResult {
+            metadata: get_current_metadata(pool, item_id).await?,
+            updated_fields: Vec::new(),
+        });
+    }
+
     // Get locked fields for this item
     let locked = get_locked_fields(pool, item_id).await?;
 
@@ -31,7 +50,7 @@ pub async fn merge_metadata(
     macro_rules! merge_field {
         ($field:ident) => {
             if provider_meta.$field.is_some() && !locked.contains(&stringify!($field).to_string()) {
-                if current.$field.is_none() || current.$field != provider_meta.$field {
+                if current.$field.is_none() || (replace && current.$field != provider_meta.$field) {
                     merged.$field = provider_meta.$field.clone();
                     updated_fields.push(stringify!($field).to_string());
                 }
@@ -60,15 +79,41 @@ pub async fn merge_metadata(
 
     if !updated_fields.is_empty() {
         save_metadata(pool, item_id, &merged).await?;
+        if updated_fields.iter().any(|f| f == "studios") {
+            rustfin_db::repo::studios::set_item_studios(
+                pool,
+                item_id,
+                merged.studios.as_deref().unwrap_or_default(),
+            )
+            .await?;
+        }
         debug!(item_id, ?updated_fields, "merged metadata");
     }
 
+    if provider_meta.original_title.is_some() || provider_meta.alternative_titles.is_some() {
+        let aliases = search_aliases(&merged, provider_meta);
+        rustfin_db::repo::aliases::set_item_aliases(pool, item_id, &aliases).await?;
+    }
+
     Ok(MergeResult {
         metadata: merged,
         updated_fields,
     })
 }
 
+/// Titles other than the item's own that search should match: its original-language
+/// title and the provider's alternative titles.
+fn search_aliases(merged: &ItemMetadata, provider_meta: &ItemMetadata) -> Vec<String> {
+    let title = merged.title.as_deref().unwrap_or_default().trim();
+    merged
+        .original_title
+        .iter()
+        .chain(provider_meta.alternative_titles.iter().flatten())
+        .filter(|alias| !alias.trim().eq_ignore_ascii_case(title))
+        .cloned()
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct MergeResult {
     pub metadata: ItemMetadata,
@@ -107,7 +152,11 @@ pub async fn unlock_field(
     Ok(())
 }
 
-async fn get_locked_fields(pool: &SqlitePool, item_id: &str) -> Result<Vec<String>, sqlx::Error> {
+/// Fields locked against provider updates on an item.
+pub async fn get_locked_fields(
+    pool: &SqlitePool,
+    item_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
     let rows: Vec<(String,)> =
         sqlx::query_as("SELECT field FROM item_field_lock WHERE item_id = ?")
             .bind(item_id)
@@ -130,9 +179,11 @@ async fn get_current_metadata(
         Option<f64>,
         Option<String>,
         Option<String>,
+        Option<String>,
+        Option<String>,
     )> = sqlx::query_as(
         "SELECT title, sort_title, overview, tagline, year, premiere_date, \
-         community_rating, poster_url, backdrop_url \
+         community_rating, poster_url, backdrop_url, studios_json, original_title \
          FROM item WHERE id = ?",
     )
     .bind(item_id)
@@ -150,6 +201,8 @@ async fn get_current_metadata(
             community_rating: r.6,
             poster_url: r.7,
             backdrop_url: r.8,
+            studios: r.9.and_then(|json| serde_json::from_str(&json).ok()),
+            original_title: r.10,
             ..Default::default()
         }),
         None => Ok(ItemMetadata::default()),
@@ -172,6 +225,8 @@ async fn save_metadata(
          community_rating = ?, \
          poster_url = ?, \
          backdrop_url = ?, \
+         studios_json = ?, \
+         original_title = COALESCE(?, original_title), \
          updated_ts = ? \
          WHERE id = ?",
     )
@@ -184,6 +239,12 @@ async fn save_metadata(
     .bind(meta.community_rating)
     .bind(&meta.poster_url)
     .bind(&meta.backdrop_url)
+    .bind(
+        meta.studios
+            .as_ref()
+            .and_then(|studios| serde_json::to_string(studios).ok()),
+    )
+    .bind(&meta.original_title)
     .bind(chrono::Utc::now().timestamp())
     .bind(item_id)
     .execute(pool)
@@ -277,6 +338,215 @@ mod tests {
         assert!(result.updated_fields.contains(&"overview".to_string()));
     }
 
+    #[tokio::test]
+    async fn locked_item_is_not_merged() {
+        let pool = rustfin_db::connect(":memory:").await.unwrap();
+        rustfin_db::migrate::run(&pool).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO library (id, name, kind, created_ts, updated_ts) \
+             VALUES ('lib1', 'Test', 'movies', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO item (id, library_id, kind, title, created_ts, updated_ts) \
+             VALUES ('curated', 'lib1', 'movie', 'My Cut', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        rustfin_db::repo::items::set_item_locked(&pool, "curated", true)
+            .await
+            .unwrap();
+
+        let provider_meta = ItemMetadata {
+            title: Some("Theatrical Cut".into()),
+            overview: Some("From the provider".into()),
+            original_title: Some("Originaltitel".into()),
+            ..Default::default()
+        };
+        let result = merge_metadata(&pool, "curated", &provider_meta)
+            .await
+            .unwrap();
+        assert!(result.updated_fields.is_empty());
+        assert_eq!(result.metadata.title.as_deref(), Some("My Cut"));
+        assert_eq!(result.metadata.overview, None);
+        assert!(
+            rustfin_db::repo::aliases::list_item_aliases(&pool, "curated")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        rustfin_db::repo::items::set_item_locked(&pool, "curated", false)
+            .await
+            .unwrap();
+        let result = merge_metadata(&pool, "curated", &provider_meta)
+            .await
+            .unwrap();
+        assert_eq!(result.metadata.title.as_deref(), Some("Theatrical Cut"));
+    }
+
+    #[tokio::test]
+    async fn merge_without_replace_only_fills_blanks() {
+        let pool = rustfin_db::connect(":memory:").await.unwrap();
+        rustfin_db::migrate::run(&pool).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO library (id, name, kind, created_ts, updated_ts) \
+             VALUES ('lib1', 'Test', 'movies', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let item_id = "test-item-3";
+        sqlx::query(
+            "INSERT INTO item (id, library_id, kind, title, sort_title, overview, created_ts, updated_ts) \
+             VALUES (?, 'lib1', 'movie', 'Test', 'test', 'Hand-written overview', 0, 0)",
+        )
+        .bind(item_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let provider_meta = ItemMetadata {
+            overview: Some("Provider overview".into()),
+            tagline: Some("Provider tagline".into()),
+            ..Default::default()
+        };
+
+        let result = merge_metadata_with(&pool, item_id, &provider_meta, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.metadata.overview.as_deref(),
+            Some("Hand-written overview")
+        );
+        assert_eq!(result.metadata.tagline.as_deref(), Some("Provider tagline"));
+        assert_eq!(result.updated_fields, vec!["tagline".to_string()]);
+
+        let result = merge_metadata_with(&pool, item_id, &provider_meta, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.metadata.overview.as_deref(),
+            Some("Provider overview")
+        );
+    }
+
+    #[tokio::test]
+    async fn merged_studios_are_normalized_and_relinked() {
+        let pool = rustfin_db::connect(":memory:").await.unwrap();
+        rustfin_db::migrate::run(&pool).await.unwrap();
+
+        sqlx::query(
+            "INSERT INTO library (id, name, kind, created_ts, updated_ts) \
+             VALUES ('lib1', 'Test', 'movies', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        for id in ["movie-a", "movie-b"] {
+            sqlx::query(
+                "INSERT INTO item (id, library_id, kind, title, sort_title, created_ts, updated_ts) \
+                 VALUES (?, 'lib1', 'movie', ?, ?, 0, 0)",
+            )
+            .bind(id)
+            .bind(id)
+            .bind(id)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let studios = |names: &[&str]| ItemMetadata {
+            studios: Some(names.iter().map(|n| n.to_string()).collect()),
+            ..Default::default()
+        };
+        merge_metadata(
+            &pool,
+            "movie-a",
+            &studios(&["Pixar", "Walt Disney Pictures"]),
+        )
+        .await
+        .unwrap();
+        let result = merge_metadata(&pool, "movie-b", &studios(&["pixar ", "PIXAR"]))
+            .await
+            .unwrap();
+        assert!(result.updated_fields.contains(&"studios".to_string()));
+
+        let rows = rustfin_db::repo::studios::list_studios(&pool, "lib1")
+            .await
+            .unwrap();
+        let counts: Vec<(&str, i64)> = rows
+            .iter()
+            .map(|s| (s.name.as_str(), s.item_count))
+            .collect();
+        assert_eq!(counts, vec![("Pixar", 2), ("Walt Disney Pictures", 1)]);
+
+        // Re-merging the same studios is a no-op now that they are stored.
+        let result = merge_metadata(&pool, "movie-b", &studios(&["pixar ", "PIXAR"]))
+            .await
+            .unwrap();
+        assert!(result.updated_fields.is_empty());
+
+        // Changed metadata drops the stale links.
+        merge_metadata(&pool, "movie-a", &studios(&["A24"]))
+            .await
+            .unwrap();
+        let rows = rustfin_db::repo::studios::list_studios(&pool, "lib1")
+            .await
+            .unwrap();
+        let counts: Vec<(&str, i64)> = rows
+            .iter()
+            .map(|s| (s.name.as_str(), s.item_count))
+            .collect();
+        assert_eq!(counts, vec![("A24", 1), ("Pixar", 1)]);
+    }
+
+    #[tokio::test]
+    async fn original_and_alternative_titles_become_aliases() {
+        let pool = rustfin_db::connect(":memory:").await.unwrap();
+        rustfin_db::migrate::run(&pool).await.unwrap();
+        sqlx::query(
+            "INSERT INTO library (id, name, kind, created_ts, updated_ts) \
+             VALUES ('lib1', 'Test', 'movies', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO item (id, library_id, kind, title, sort_title, created_ts, updated_ts) \
+             VALUES ('parasite', 'lib1', 'movie', 'Parasite', 'parasite', 0, 0)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let provider_meta = ItemMetadata {
+            title: Some("Parasite".into()),
+            original_title: Some("기생충".into()),
+            alternative_titles: Some(vec!["Gisaengchung".into(), "parasite".into()]),
+            ..Default::default()
+        };
+        let result = merge_metadata(&pool, "parasite", &provider_meta)
+            .await
+            .unwrap();
+        assert_eq!(result.metadata.original_title.as_deref(), Some("기생충"));
+
+        let aliases = rustfin_db::repo::aliases::list_item_aliases(&pool, "parasite")
+            .await
+            .unwrap();
+        assert_eq!(aliases.len(), 2);
+        assert!(aliases.iter().any(|a| a == "기생충"));
+        assert!(aliases.iter().any(|a| a == "Gisaengchung"));
+
+        let current = get_current_metadata(&pool, "parasite").await.unwrap();
+        assert_eq!(current.original_title.as_deref(), Some("기생충"));
+    }
+
     #[tokio::test]
     async fn provider_ids_crud() {
         let pool = rustfin_db::connect(":memory:").await.unwrap();
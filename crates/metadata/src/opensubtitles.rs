@@ -0,0 +1,272 @@
+//! OpenSubtitles subtitle search and download client.
+//!
+//! Uses the OpenSubtitles REST API v1: https://opensubtitles.stoplight.io/docs/opensubtitles-api
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::MetadataError;
+
+const BASE_URL: &str = "https://api.opensubtitles.com/api/v1";
+/// OpenSubtitles rejects requests without an application user agent.
+const USER_AGENT: &str = concat!("Rustyfin v", env!("CARGO_PKG_VERSION"));
+/// Largest subtitle file accepted from a download link.
+pub const MAX_SUBTITLE_BYTES: usize = 5 * 1024 * 1024;
+
+pub struct OpenSubtitlesClient {
+    api_key: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+/// What to search for. Provider ids are preferred over the title when known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubtitleQuery {
+    pub title: String,
+    pub year: Option<i32>,
+    /// TMDB id of the movie, or of the series for an episode.
+    pub tmdb_id: Option<String>,
+    pub season_number: Option<i64>,
+    pub episode_number: Option<i64>,
+    /// Language codes such as `en` or `pt-BR`.
+    pub languages: Vec<String>,
+}
+
+/// One subtitle file offered by OpenSubtitles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtitleCandidate {
+    /// Pass to [`OpenSubtitlesClient::download`].
+    pub file_id: i64,
+    pub language: String,
+    /// Release name the subtitle was timed against.
+    pub release: Option<String>,
+    pub file_name: Option<String>,
+    pub download_count: i64,
+    pub hearing_impaired: bool,
+    /// Only covers foreign-language parts.
+    pub forced: bool,
+    /// Machine or AI translated rather than made by a person.
+    pub machine_translated: bool,
+}
+
+/// A downloaded subtitle file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadedSubtitle {
+    pub file_name: Option<String>,
+    pub bytes: Vec<u8>,
+}
+
+impl OpenSubtitlesClient {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            base_url: BASE_URL.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Point the client at a different API root (proxies, tests).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}{path}", self.base_url);
+        debug!(url = %url, "OpenSubtitles request");
+        self.client
+            .request(method, url)
+            .header("Api-Key", &self.api_key)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .header(reqwest::header::ACCEPT, "application/json")
+    }
+
+    async fn json(resp: reqwest::Response) -> Result<serde_json::Value, MetadataError> {
+        match resp.status() {
+            status if status.is_success() => resp
+                .json()
+                .await
+                .map_err(|e| MetadataError::Provider(format!("parse JSON: {e}"))),
+            reqwest::StatusCode::NOT_FOUND => Err(MetadataError::NotFound),
+            status => Err(MetadataError::Provider(format!(
+                "OpenSubtitles returned {status}"
+            ))),
+        }
+    }
+
+    /// Subtitles matching `query`, most downloaded first within each language.
+    pub async fn search(
+        &self,
+        query: &SubtitleQuery,
+    ) -> Result<Vec<SubtitleCandidate>, MetadataError> {
+        // OpenSubtitles wants lowercase, sorted, comma-separated languages.
+        let mut languages: Vec<String> = query
+            .languages
+            .iter()
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect();
+        languages.sort();
+        languages.dedup();
+
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if !languages.is_empty() {
+            params.push(("languages", languages.join(",")));
+        }
+        let episode = query.season_number.is_some() || query.episode_number.is_some();
+        match &query.tmdb_id {
+            Some(id) if episode => params.push(("parent_tmdb_id", id.clone())),
+            Some(id) => params.push(("tmdb_id", id.clone())),
+            None => {
+                params.push(("query", query.title.clone()));
+                if let Some(year) = query.year {
+                    params.push(("year", year.to_string()));
+                }
+            }
+        }
+        if let Some(season) = query.season_number {
+            params.push(("season_number", season.to_string()));
+        }
+        if let Some(episode) = query.episode_number {
+            params.push(("episode_number", episode.to_string()));
+        }
+
+        let resp = self
+            .request(reqwest::Method::GET, "/subtitles")
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| MetadataError::Network(e.to_string()))?;
+        let data = Self::json(resp).await?;
+
+        let mut candidates = parse_search_results(&data);
+        candidates.sort_by(|a, b| {
+            a.language
+                .cmp(&b.language)
+                .then(b.download_count.cmp(&a.download_count))
+        });
+        Ok(candidates)
+    }
+
+    /// Fetch the subtitle file with this `file_id`.
+    pub async fn download(&self, file_id: i64) -> Result<DownloadedSubtitle, MetadataError> {
+        let resp = self
+            .request(reqwest::Method::POST, "/download")
+            .json(&serde_json::json!({ "file_id": file_id }))
+            .send()
+            .await
+            .map_err(|e| MetadataError::Network(e.to_string()))?;
+        let data = Self::json(resp).await?;
+        let link = data["link"]
+            .as_str()
+            .ok_or_else(|| MetadataError::Provider("download response has no link".into()))?;
+
+        let resp = self
+            .client
+            .get(link)
+            .header(reqwest::header::USER_AGENT, USER_AGENT)
+            .send()
+            .await
+            .map_err(|e| MetadataError::Network(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(MetadataError::Provider(format!(
+                "subtitle download returned {}",
+                resp.status()
+            )));
+        }
+        if resp
+            .content_length()
+            .is_some_and(|len| len > MAX_SUBTITLE_BYTES as u64)
+        {
+            return Err(MetadataError::Provider("subtitle file is too large".into()));
+        }
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| MetadataError::Network(e.to_string()))?;
+        if bytes.len() > MAX_SUBTITLE_BYTES {
+            return Err(MetadataError::Provider("subtitle file is too large".into()));
+        }
+
+        Ok(DownloadedSubtitle {
+            file_name: data["file_name"].as_str().map(|s| s.to_string()),
+            bytes: bytes.to_vec(),
+        })
+    }
+}
+
+/// One candidate per file in a `/subtitles` response.
+fn parse_search_results(data: &serde_json::Value) -> Vec<SubtitleCandidate> {
+    let mut candidates = Vec::new();
+    for result in data["data"].as_array().into_iter().flatten() {
+        let attrs = &result["attributes"];
+        let Some(language) = attrs["language"].as_str() else {
+            continue;
+        };
+        for file in attrs["files"].as_array().into_iter().flatten() {
+            let Some(file_id) = file["file_id"].as_i64() else {
+                continue;
+            };
+            candidates.push(SubtitleCandidate {
+                file_id,
+                language: language.to_string(),
+                release: attrs["release"].as_str().map(|s| s.to_string()),
+                file_name: file["file_name"].as_str().map(|s| s.to_string()),
+                download_count: attrs["download_count"].as_i64().unwrap_or(0),
+                hearing_impaired: attrs["hearing_impaired"].as_bool().unwrap_or(false),
+                forced: attrs["foreign_parts_only"].as_bool().unwrap_or(false),
+                machine_translated: attrs["machine_translated"].as_bool().unwrap_or(false)
+                    || attrs["ai_translated"].as_bool().unwrap_or(false),
+            });
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_results_flatten_files_and_flags() {
+        let data = serde_json::json!({
+            "data": [
+                {
+                    "attributes": {
+                        "language": "en",
+                        "release": "Arrival.2016.1080p.BluRay",
+                        "download_count": 900,
+                        "hearing_impaired": true,
+                        "foreign_parts_only": false,
+                        "ai_translated": false,
+                        "machine_translated": false,
+                        "files": [{ "file_id": 11, "file_name": "Arrival.2016.en.srt" }]
+                    }
+                },
+                {
+                    "attributes": {
+                        "language": "fr",
+                        "download_count": 12,
+                        "foreign_parts_only": true,
+                        "ai_translated": true,
+                        "files": [{ "file_id": 12 }, { "file_name": "no id" }]
+                    }
+                },
+                { "attributes": { "files": [{ "file_id": 13 }] } }
+            ]
+        });
+        let candidates = parse_search_results(&data);
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].file_id, 11);
+        assert_eq!(candidates[0].language, "en");
+        assert!(candidates[0].hearing_impaired);
+        assert_eq!(
+            candidates[0].file_name.as_deref(),
+            Some("Arrival.2016.en.srt")
+        );
+        assert_eq!(candidates[1].file_id, 12);
+        assert!(candidates[1].forced);
+        assert!(candidates[1].machine_translated);
+        assert_eq!(candidates[1].release, None);
+    }
+}
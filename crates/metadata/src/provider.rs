@@ -1,4 +1,4 @@
-use crate::{EpisodeInfo, ItemMetadata, MetadataError};
+use crate::{EpisodeInfo, ItemMetadata, MetadataError, PersonDetails};
 
 /// A metadata provider that can search and fetch metadata.
 #[async_trait::async_trait]
@@ -31,6 +31,9 @@ pub trait MetadataProvider: Send + Sync {
         series_provider_id: &str,
         season_number: i32,
     ) -> Result<Vec<EpisodeInfo>, MetadataError>;
+
+    /// Get a cast or crew member's biography and photo by provider ID.
+    async fn get_person(&self, provider_id: &str) -> Result<PersonDetails, MetadataError>;
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -5,13 +5,18 @@
 use tracing::debug;
 
 use crate::provider::{MetadataProvider, SearchResult};
-use crate::{EpisodeInfo, ItemMetadata, MetadataError, PersonInfo};
+use crate::{EpisodeInfo, ItemMetadata, MetadataError, PersonDetails, PersonInfo};
 
 const BASE_URL: &str = "https://api.themoviedb.org/3";
 const IMAGE_BASE: &str = "https://image.tmdb.org/t/p";
+/// Language used when a localized response leaves text fields empty.
+const FALLBACK_LANGUAGE: &str = "en-US";
 
 pub struct TmdbClient {
     api_key: String,
+    base_url: String,
+    language: Option<String>,
+    region: Option<String>,
     client: reqwest::Client,
 }
 
@@ -19,19 +24,89 @@ impl TmdbClient {
     pub fn new(api_key: String) -> Self {
         Self {
             api_key,
+            base_url: BASE_URL.to_string(),
+            language: None,
+            region: None,
             client: reqwest::Client::new(),
         }
     }
 
+    /// Request localized metadata. `language` is an ISO 639-1 code (or a full
+    /// `xx-YY` tag) and `region` an ISO 3166-1 country code.
+    pub fn with_locale(mut self, language: Option<String>, region: Option<String>) -> Self {
+        self.language = language
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty());
+        self.region = region
+            .map(|r| r.trim().to_uppercase())
+            .filter(|r| !r.is_empty());
+        self
+    }
+
+    /// Point the client at a different API root (mirrors, proxies, tests).
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Check the API key against TMDB's `/configuration` endpoint: `Ok(false)` when
+    /// TMDB rejects it, an error when TMDB could not be asked.
+    pub async fn validate_key(&self) -> Result<bool, MetadataError> {
+        let resp = self
+            .client
+            .get(format!("{}/configuration", self.base_url))
+            .query(&[("api_key", self.api_key.as_str())])
+            .send()
+            .await
+            .map_err(|e| MetadataError::Network(e.to_string()))?;
+        match resp.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Ok(false),
+            status => Err(MetadataError::Provider(format!("TMDB returned {status}"))),
+        }
+    }
+
+    /// TMDB `language` parameter for the configured locale, e.g. `fr-FR`.
+    pub fn language_tag(&self) -> Option<String> {
+        let language = self.language.as_deref()?;
+        if language.contains('-') {
+            return Some(language.to_string());
+        }
+        Some(match self.region.as_deref() {
+            Some(region) => format!("{}-{region}", language.to_lowercase()),
+            None => language.to_lowercase(),
+        })
+    }
+
+    /// Whether empty localized text should be backfilled from English.
+    fn wants_english_fallback(&self) -> bool {
+        self.language_tag()
+            .is_some_and(|tag| !tag.eq_ignore_ascii_case("en") && !tag.starts_with("en-"))
+    }
+
     async fn get_json(
         &self,
         path: &str,
         params: &[(&str, &str)],
+    ) -> Result<serde_json::Value, MetadataError> {
+        let language = self.language_tag();
+        self.get_json_with_language(path, params, language.as_deref())
+            .await
+    }
+
+    async fn get_json_with_language(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+        language: Option<&str>,
     ) -> Result<serde_json::Value, MetadataError> {
         let mut all_params = vec![("api_key", self.api_key.as_str())];
+        if let Some(language) = language {
+            all_params.push(("language", language));
+        }
         all_params.extend_from_slice(params);
 
-        let url = format!("{BASE_URL}{path}");
+        let url = format!("{}{path}", self.base_url);
         debug!(url = %url, "TMDB request");
 
         let resp = self
@@ -75,6 +150,9 @@ impl MetadataProvider for TmdbClient {
         if let Some(ref y) = year_str {
             params.push(("year", y));
         }
+        if let Some(ref region) = self.region {
+            params.push(("region", region));
+        }
 
         let data = self.get_json("/search/movie", &params).await?;
         let results = data["results"].as_array().cloned().unwrap_or_default();
@@ -130,25 +208,43 @@ impl MetadataProvider for TmdbClient {
     }
 
     async fn get_movie(&self, provider_id: &str) -> Result<ItemMetadata, MetadataError> {
+        let path = format!("/movie/{provider_id}");
         let data = self
             .get_json(
-                &format!("/movie/{provider_id}"),
-                &[("append_to_response", "credits")],
+                &path,
+                &[("append_to_response", "credits,alternative_titles")],
             )
             .await?;
+        let mut meta = parse_movie_metadata(&data);
 
-        Ok(parse_movie_metadata(&data))
+        if self.wants_english_fallback() && has_missing_text(&meta) {
+            let english = self
+                .get_json_with_language(&path, &[], Some(FALLBACK_LANGUAGE))
+                .await?;
+            fill_missing_text(&mut meta, &parse_movie_metadata(&english));
+        }
+
+        Ok(meta)
     }
 
     async fn get_series(&self, provider_id: &str) -> Result<ItemMetadata, MetadataError> {
+        let path = format!("/tv/{provider_id}");
         let data = self
             .get_json(
-                &format!("/tv/{provider_id}"),
-                &[("append_to_response", "credits")],
+                &path,
+                &[("append_to_response", "credits,alternative_titles")],
             )
             .await?;
+        let mut meta = parse_series_metadata(&data);
+
+        if self.wants_english_fallback() && has_missing_text(&meta) {
+            let english = self
+                .get_json_with_language(&path, &[], Some(FALLBACK_LANGUAGE))
+                .await?;
+            fill_missing_text(&mut meta, &parse_series_metadata(&english));
+        }
 
-        Ok(parse_series_metadata(&data))
+        Ok(meta)
     }
 
     async fn get_season_episodes(
@@ -156,29 +252,136 @@ impl MetadataProvider for TmdbClient {
         series_provider_id: &str,
         season_number: i32,
     ) -> Result<Vec<EpisodeInfo>, MetadataError> {
-        let data = self
-            .get_json(
-                &format!("/tv/{series_provider_id}/season/{season_number}"),
-                &[],
-            )
-            .await?;
+        let path = format!("/tv/{series_provider_id}/season/{season_number}");
+        let data = self.get_json(&path, &[]).await?;
+        let mut episodes = parse_season_episodes(&data);
+
+        if self.wants_english_fallback()
+            && episodes.iter().any(|ep| is_blank(ep.overview.as_deref()))
+        {
+            let english = self
+                .get_json_with_language(&path, &[], Some(FALLBACK_LANGUAGE))
+                .await?;
+            let english = parse_season_episodes(&english);
+            for ep in &mut episodes {
+                let Some(en) = english
+                    .iter()
+                    .find(|en| en.episode_number == ep.episode_number)
+                else {
+                    continue;
+                };
+                if is_blank(ep.overview.as_deref()) {
+                    ep.overview = en.overview.clone();
+                }
+                if is_blank(ep.title.as_deref()) {
+                    ep.title = en.title.clone();
+                }
+            }
+        }
+
+        Ok(episodes)
+    }
 
-        let episodes = data["episodes"].as_array().cloned().unwrap_or_default();
+    async fn get_person(&self, provider_id: &str) -> Result<PersonDetails, MetadataError> {
+        let path = format!("/person/{provider_id}");
+        let data = self.get_json(&path, &[]).await?;
+        let mut person = parse_person_details(&data);
 
-        Ok(episodes
+        if self.wants_english_fallback() && is_blank(person.biography.as_deref()) {
+            let english = self
+                .get_json_with_language(&path, &[], Some(FALLBACK_LANGUAGE))
+                .await?;
+            person.biography = parse_person_details(&english).biography;
+        }
+
+        Ok(person)
+    }
+}
+
+/// Smallest TMDB image size covering `width` x `height` for an artwork `kind`
+/// (`poster`, `backdrop`, `logo` or `thumb`), e.g. `w185`. `None` when neither is given.
+pub fn image_size_for(kind: &str, width: Option<u32>, height: Option<u32>) -> Option<String> {
+    if width.is_none() && height.is_none() {
+        return None;
+    }
+    // Widths TMDB serves for each kind, and the kind's usual aspect ratio.
+    let (widths, (aspect_w, aspect_h)): (&[u32], _) = match kind {
+        "backdrop" => (&[300, 780, 1280], (16, 9)),
+        "logo" => (&[45, 92, 154, 185, 300, 500], (4, 1)),
+        "thumb" => (&[92, 185, 300], (16, 9)),
+        _ => (&[92, 154, 185, 342, 500, 780], (2, 3)),
+    };
+    let needed = width.unwrap_or(0).max(
+        height
+            .map(|h| h.saturating_mul(aspect_w).div_ceil(aspect_h))
+            .unwrap_or(0),
+    );
+    Some(
+        widths
             .iter()
-            .map(|ep| EpisodeInfo {
-                season_number: ep["season_number"].as_i64().unwrap_or(0) as i32,
-                episode_number: ep["episode_number"].as_i64().unwrap_or(0) as i32,
-                title: ep["name"].as_str().map(|s| s.to_string()),
-                overview: ep["overview"].as_str().map(|s| s.to_string()),
-                air_date: ep["air_date"].as_str().map(|s| s.to_string()),
-                still_url: ep["still_path"]
-                    .as_str()
-                    .map(|p| format!("{IMAGE_BASE}/w300{p}")),
-            })
-            .collect())
+            .find(|&&w| w >= needed)
+            .map(|w| format!("w{w}"))
+            .unwrap_or_else(|| "original".to_string()),
+    )
+}
+
+/// Point a TMDB image URL (`.../t/p/{size}/{file}`) at another size. `None` for
+/// other URLs. Any host with TMDB's path layout counts, so image mirrors work too.
+pub fn with_image_size(url: &str, size: &str) -> Option<String> {
+    let (prefix, rest) = url.split_once("/t/p/")?;
+    let (current, file) = rest.split_once('/')?;
+    let is_size = current == "original"
+        || current
+            .strip_prefix(['w', 'h'])
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    if !is_size || file.is_empty() {
+        return None;
     }
+    Some(format!("{prefix}/t/p/{size}/{file}"))
+}
+
+fn is_blank(value: Option<&str>) -> bool {
+    value.is_none_or(|s| s.trim().is_empty())
+}
+
+/// TMDB returns empty strings for fields that have no translation yet.
+fn has_missing_text(meta: &ItemMetadata) -> bool {
+    is_blank(meta.overview.as_deref()) || is_blank(meta.title.as_deref())
+}
+
+/// Backfill empty localized text fields from an English response.
+fn fill_missing_text(meta: &mut ItemMetadata, english: &ItemMetadata) {
+    if is_blank(meta.title.as_deref()) {
+        meta.title = english.title.clone();
+        meta.sort_title = english.sort_title.clone();
+    }
+    if is_blank(meta.overview.as_deref()) {
+        meta.overview = english.overview.clone();
+    }
+    if is_blank(meta.tagline.as_deref()) {
+        meta.tagline = english.tagline.clone();
+    }
+}
+
+fn parse_season_episodes(data: &serde_json::Value) -> Vec<EpisodeInfo> {
+    let episodes = data["episodes"].as_array().cloned().unwrap_or_default();
+
+    episodes
+        .iter()
+        .map(|ep| EpisodeInfo {
+            season_number: ep["season_number"].as_i64().unwrap_or(0) as i32,
+            episode_number: ep["episode_number"].as_i64().unwrap_or(0) as i32,
+            title: ep["name"].as_str().map(|s| s.to_string()),
+            overview: ep["overview"].as_str().map(|s| s.to_string()),
+            air_date: ep["air_date"].as_str().map(|s| s.to_string()),
+            still_url: ep["still_path"]
+                .as_str()
+                .map(|p| format!("{IMAGE_BASE}/w300{p}")),
+            airs_after_season: ep["airs_after_season"].as_i64().map(|n| n as i32),
+            airs_before_season: ep["airs_before_season"].as_i64().map(|n| n as i32),
+            airs_before_episode: ep["airs_before_episode"].as_i64().map(|n| n as i32),
+        })
+        .collect()
 }
 
 fn parse_movie_metadata(data: &serde_json::Value) -> ItemMetadata {
@@ -187,6 +390,7 @@ fn parse_movie_metadata(data: &serde_json::Value) -> ItemMetadata {
     ItemMetadata {
         title: data["title"].as_str().map(|s| s.to_string()),
         original_title: data["original_title"].as_str().map(|s| s.to_string()),
+        alternative_titles: extract_alternative_titles(&data["alternative_titles"]["titles"]),
         sort_title: data["title"].as_str().map(|s| s.to_string()),
         overview: data["overview"].as_str().map(|s| s.to_string()),
         tagline: data["tagline"].as_str().map(|s| s.to_string()),
@@ -231,6 +435,7 @@ fn parse_series_metadata(data: &serde_json::Value) -> ItemMetadata {
     ItemMetadata {
         title: data["name"].as_str().map(|s| s.to_string()),
         original_title: data["original_name"].as_str().map(|s| s.to_string()),
+        alternative_titles: extract_alternative_titles(&data["alternative_titles"]["results"]),
         sort_title: data["name"].as_str().map(|s| s.to_string()),
         overview: data["overview"].as_str().map(|s| s.to_string()),
         tagline: data["tagline"].as_str().map(|s| s.to_string()),
@@ -273,6 +478,18 @@ fn parse_series_metadata(data: &serde_json::Value) -> ItemMetadata {
     }
 }
 
+/// `alternative_titles` lists titles under `titles` for movies and `results` for TV.
+fn extract_alternative_titles(titles: &serde_json::Value) -> Option<Vec<String>> {
+    let titles: Vec<String> = titles
+        .as_array()?
+        .iter()
+        .filter_map(|t| t["title"].as_str())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    (!titles.is_empty()).then_some(titles)
+}
+
 fn extract_credits(credits: Option<&serde_json::Value>) -> Vec<PersonInfo> {
     let mut people = Vec::new();
 
@@ -287,6 +504,7 @@ fn extract_credits(credits: Option<&serde_json::Value>) -> Vec<PersonInfo> {
                     thumb_url: person["profile_path"]
                         .as_str()
                         .map(|p| format!("{IMAGE_BASE}/w185{p}")),
+                    provider_id: person_id(person),
                 });
             }
         }
@@ -302,6 +520,7 @@ fn extract_credits(credits: Option<&serde_json::Value>) -> Vec<PersonInfo> {
                         thumb_url: person["profile_path"]
                             .as_str()
                             .map(|p| format!("{IMAGE_BASE}/w185{p}")),
+                        provider_id: person_id(person),
                     });
                 }
             }
@@ -311,6 +530,30 @@ fn extract_credits(credits: Option<&serde_json::Value>) -> Vec<PersonInfo> {
     people
 }
 
+fn person_id(person: &serde_json::Value) -> Option<String> {
+    person["id"].as_i64().map(|id| id.to_string())
+}
+
+fn parse_person_details(data: &serde_json::Value) -> PersonDetails {
+    let text = |key: &str| {
+        data[key]
+            .as_str()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+    };
+    PersonDetails {
+        name: text("name").unwrap_or_default(),
+        biography: text("biography"),
+        birthday: text("birthday"),
+        deathday: text("deathday"),
+        place_of_birth: text("place_of_birth"),
+        image_url: data["profile_path"]
+            .as_str()
+            .map(|p| format!("{IMAGE_BASE}/original{p}")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -336,7 +579,7 @@ mod tests {
             ],
             "credits": {
                 "cast": [
-                    { "name": "Leonardo DiCaprio", "character": "Cobb", "profile_path": "/leo.jpg" }
+                    { "id": 6193, "name": "Leonardo DiCaprio", "character": "Cobb", "profile_path": "/leo.jpg" }
                 ],
                 "crew": [
                     { "name": "Christopher Nolan", "job": "Director", "profile_path": "/nolan.jpg" }
@@ -356,6 +599,7 @@ mod tests {
         assert_eq!(people.len(), 2);
         assert_eq!(people[0].name, "Leonardo DiCaprio");
         assert_eq!(people[0].role, "Actor");
+        assert_eq!(people[0].provider_id.as_deref(), Some("6193"));
         assert_eq!(people[1].name, "Christopher Nolan");
         assert_eq!(people[1].role, "Director");
     }
@@ -380,4 +624,198 @@ mod tests {
         assert_eq!(meta.year, Some(2008));
         assert_eq!(meta.end_date.as_deref(), Some("2013-09-29"));
     }
+
+    #[test]
+    fn parse_season_episodes_keeps_special_placement() {
+        let json = serde_json::json!({
+            "episodes": [
+                { "season_number": 0, "episode_number": 5, "name": "Mid-season Special",
+                  "airs_before_season": 2, "airs_before_episode": 8 },
+                { "season_number": 0, "episode_number": 6, "name": "Behind the Scenes" }
+            ]
+        });
+
+        let episodes = parse_season_episodes(&json);
+        assert_eq!(episodes[0].airs_before_season, Some(2));
+        assert_eq!(episodes[0].airs_before_episode, Some(8));
+        assert_eq!(episodes[0].airs_after_season, None);
+        assert_eq!(episodes[1].airs_before_season, None);
+    }
+
+    /// Minimal HTTP stub: records each request target and answers with the
+    /// JSON returned by `respond`.
+    async fn spawn_stub(
+        respond: fn(&str) -> serde_json::Value,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen_task = seen.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut sock, _)) = listener.accept().await else {
+                    break;
+                };
+                let mut buf = vec![0u8; 8192];
+                let n = sock.read(&mut buf).await.unwrap_or(0);
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let target = req
+                    .lines()
+                    .next()
+                    .and_then(|l| l.split_whitespace().nth(1))
+                    .unwrap_or("")
+                    .to_string();
+                seen_task.lock().unwrap().push(target.clone());
+
+                let body = respond(&target).to_string();
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            }
+        });
+
+        (format!("http://{addr}"), seen)
+    }
+
+    #[tokio::test]
+    async fn person_details_fall_back_to_english_biography() {
+        let (base, seen) = spawn_stub(|target| {
+            if target.contains("language=en-US") {
+                serde_json::json!({ "name": "Jane Doe", "biography": "An actor." })
+            } else {
+                serde_json::json!({
+                    "id": 42,
+                    "name": "Jane Doe",
+                    "biography": "",
+                    "birthday": "1970-01-02",
+                    "deathday": null,
+                    "place_of_birth": "Lyon, France",
+                    "profile_path": "/jane.jpg"
+                })
+            }
+        })
+        .await;
+        let client = TmdbClient::new("k".into())
+            .with_base_url(base)
+            .with_locale(Some("fr".into()), None);
+
+        let person = client.get_person("42").await.unwrap();
+        assert_eq!(person.name, "Jane Doe");
+        assert_eq!(person.biography.as_deref(), Some("An actor."));
+        assert_eq!(person.birthday.as_deref(), Some("1970-01-02"));
+        assert_eq!(person.deathday, None);
+        assert_eq!(person.place_of_birth.as_deref(), Some("Lyon, France"));
+        assert_eq!(
+            person.image_url.as_deref(),
+            Some("https://image.tmdb.org/t/p/original/jane.jpg")
+        );
+        let seen = seen.lock().unwrap();
+        assert!(seen.iter().all(|t| t.starts_with("/person/42?")));
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn language_tag_combines_language_and_region() {
+        let client = TmdbClient::new("k".into()).with_locale(Some("fr".into()), Some("fr".into()));
+        assert_eq!(client.language_tag().as_deref(), Some("fr-FR"));
+
+        let client = TmdbClient::new("k".into()).with_locale(Some("pt-BR".into()), None);
+        assert_eq!(client.language_tag().as_deref(), Some("pt-BR"));
+
+        let client = TmdbClient::new("k".into()).with_locale(Some("  ".into()), Some("US".into()));
+        assert_eq!(client.language_tag(), None);
+    }
+
+    #[tokio::test]
+    async fn requests_include_configured_language() {
+        let (base, seen) =
+            spawn_stub(|_| serde_json::json!({ "title": "Le Film", "overview": "Un film." })).await;
+        let client = TmdbClient::new("k".into())
+            .with_base_url(base)
+            .with_locale(Some("fr".into()), Some("FR".into()));
+
+        let meta = client.get_movie("42").await.unwrap();
+        assert_eq!(meta.overview.as_deref(), Some("Un film."));
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert!(seen[0].starts_with("/movie/42?"));
+        assert!(seen[0].contains("language=fr-FR"), "{}", seen[0]);
+    }
+
+    #[test]
+    fn image_size_covers_the_requested_box() {
+        assert_eq!(image_size_for("poster", None, None), None);
+        assert_eq!(
+            image_size_for("poster", Some(150), None).as_deref(),
+            Some("w154")
+        );
+        assert_eq!(
+            image_size_for("poster", Some(185), None).as_deref(),
+            Some("w185")
+        );
+        // 300px tall posters are 200px wide.
+        assert_eq!(
+            image_size_for("poster", None, Some(300)).as_deref(),
+            Some("w342")
+        );
+        assert_eq!(
+            image_size_for("backdrop", Some(1920), None).as_deref(),
+            Some("original")
+        );
+        assert_eq!(
+            image_size_for("thumb", Some(120), Some(90)).as_deref(),
+            Some("w185")
+        );
+    }
+
+    #[test]
+    fn with_image_size_rewrites_only_tmdb_urls() {
+        assert_eq!(
+            with_image_size("https://image.tmdb.org/t/p/original/abc.jpg", "w185").as_deref(),
+            Some("https://image.tmdb.org/t/p/w185/abc.jpg")
+        );
+        assert_eq!(
+            with_image_size("http://127.0.0.1:9/t/p/w500/abc.jpg", "w92").as_deref(),
+            Some("http://127.0.0.1:9/t/p/w92/abc.jpg")
+        );
+        assert_eq!(
+            with_image_size("https://example.com/poster.jpg", "w92"),
+            None
+        );
+        assert_eq!(
+            with_image_size("https://example.com/t/p/big/a.jpg", "w92"),
+            None
+        );
+        assert_eq!(with_image_size("/media/poster.jpg", "w92"), None);
+    }
+
+    #[tokio::test]
+    async fn empty_localized_overview_falls_back_to_english() {
+        let (base, seen) = spawn_stub(|target| {
+            if target.contains("language=en-US") {
+                serde_json::json!({ "name": "Dark", "overview": "A family saga." })
+            } else {
+                serde_json::json!({ "name": "Dark", "overview": "" })
+            }
+        })
+        .await;
+        let client = TmdbClient::new("k".into())
+            .with_base_url(base)
+            .with_locale(Some("de".into()), Some("DE".into()));
+
+        let meta = client.get_series("70523").await.unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Dark"));
+        assert_eq!(meta.overview.as_deref(), Some("A family saga."));
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert!(seen[0].contains("language=de-DE"));
+        assert!(seen[1].contains("language=en-US"));
+    }
 }
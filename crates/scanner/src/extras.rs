@@ -0,0 +1,170 @@
+//! Theme media and extra artwork kept in an item's folder.
+//!
+//! Naming conventions:
+//! - `theme.mp3` (any audio extension) → theme song
+//! - `theme.mkv` (any video extension) → theme video
+//! - `backdrops/*.jpg`                  → extra backdrops, in filename order
+//!
+//! These files are linked to the item and never become library items themselves.
+
+use std::path::{Path, PathBuf};
+
+use crate::parser;
+
+static AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "m4a", "aac", "ogg", "oga", "opus", "wav"];
+static IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Folder (inside the item folder) holding extra backdrop images.
+const BACKDROPS_DIR: &str = "backdrops";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtraKind {
+    ThemeAudio,
+    ThemeVideo,
+    Backdrop,
+}
+
+impl ExtraKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ThemeAudio => "theme_audio",
+            Self::ThemeVideo => "theme_video",
+            Self::Backdrop => "backdrop",
+        }
+    }
+}
+
+/// A theme or backdrop file found beside an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalExtra {
+    pub kind: ExtraKind,
+    pub path: PathBuf,
+}
+
+fn extension(filename: &str) -> Option<String> {
+    filename
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+}
+
+fn theme_kind(filename: &str) -> Option<ExtraKind> {
+    let (stem, _) = filename.rsplit_once('.')?;
+    if !stem.eq_ignore_ascii_case("theme") {
+        return None;
+    }
+    if parser::is_video_file(filename) {
+        Some(ExtraKind::ThemeVideo)
+    } else if AUDIO_EXTENSIONS.contains(&extension(filename)?.as_str()) {
+        Some(ExtraKind::ThemeAudio)
+    } else {
+        None
+    }
+}
+
+/// Whether a file is a theme song or video rather than a title of its own.
+pub fn is_theme_file(filename: &str) -> bool {
+    theme_kind(filename).is_some()
+}
+
+/// Whether a directory holds extras for its parent instead of library media.
+pub fn is_extras_dir(dirname: &str) -> bool {
+    dirname.eq_ignore_ascii_case(BACKDROPS_DIR)
+}
+
+/// Theme files directly in `dir` and images in its `backdrops/` folder.
+///
+/// Theme audio comes before theme video; backdrops are sorted by filename.
+pub fn discover_extras(dir: &Path) -> Vec<LocalExtra> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut themes = Vec::new();
+    let mut backdrop_dir = None;
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if path.is_dir() {
+            if is_extras_dir(&name) {
+                backdrop_dir = Some(path);
+            }
+        } else if let Some(kind) = theme_kind(&name) {
+            themes.push(LocalExtra { kind, path });
+        }
+    }
+    themes.sort_by(|a, b| {
+        (a.kind != ExtraKind::ThemeAudio, &a.path).cmp(&(b.kind != ExtraKind::ThemeAudio, &b.path))
+    });
+
+    let mut backdrops: Vec<PathBuf> = backdrop_dir
+        .and_then(|dir| std::fs::read_dir(dir).ok())
+        .into_iter()
+        .flat_map(|rd| rd.flatten().map(|e| e.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .filter(|n| !n.starts_with('.'))
+                    .and_then(|n| extension(&n))
+                    .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()))
+        })
+        .collect();
+    backdrops.sort();
+
+    themes
+        .into_iter()
+        .chain(backdrops.into_iter().map(|path| LocalExtra {
+            kind: ExtraKind::Backdrop,
+            path,
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_files_are_recognised_by_name() {
+        assert!(is_theme_file("theme.mp3"));
+        assert!(is_theme_file("Theme.MKV"));
+        assert!(!is_theme_file("theme.jpg"));
+        assert!(!is_theme_file("Anime Theme.mkv"));
+        assert!(!is_theme_file("theme"));
+        assert!(is_extras_dir("Backdrops"));
+        assert!(!is_extras_dir("Season 01"));
+    }
+
+    #[test]
+    fn discovers_themes_and_sorted_backdrops() {
+        let dir = std::env::temp_dir().join(format!("rf_extras_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("backdrops")).unwrap();
+        std::fs::write(dir.join("theme.mkv"), b"v").unwrap();
+        std::fs::write(dir.join("theme.mp3"), b"a").unwrap();
+        std::fs::write(dir.join("S01E01.mkv"), b"e").unwrap();
+        std::fs::write(dir.join("backdrops").join("b.jpg"), b"i").unwrap();
+        std::fs::write(dir.join("backdrops").join("a.png"), b"i").unwrap();
+        std::fs::write(dir.join("backdrops").join("notes.txt"), b"t").unwrap();
+
+        let kinds: Vec<_> = discover_extras(&dir)
+            .into_iter()
+            .map(|e| {
+                (
+                    e.kind,
+                    e.path.file_name().unwrap().to_string_lossy().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (ExtraKind::ThemeAudio, "theme.mp3".to_string()),
+                (ExtraKind::ThemeVideo, "theme.mkv".to_string()),
+                (ExtraKind::Backdrop, "a.png".to_string()),
+                (ExtraKind::Backdrop, "b.jpg".to_string()),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
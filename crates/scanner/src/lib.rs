@@ -4,6 +4,7 @@
     clippy::manual_range_contains,
     clippy::collapsible_str_replace
 )]
+pub mod extras;
 pub mod parser;
 pub mod scan;
 pub mod subtitles;
@@ -12,11 +12,31 @@ pub struct MovieInfo {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct EpisodeInfo {
     pub series_title: String,
+    /// First-aired year from a `Title (2015)` or `Title (2015-2021)` series name.
+    pub series_year: Option<u16>,
     pub season: u32,
     pub episode: u32,
     pub episode_title: Option<String>,
 }
 
+/// A series name split into title and the years it ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesName {
+    pub title: String,
+    pub year: Option<u16>,
+    /// Last year of a finished run; `None` for a single year or an open range.
+    pub end_year: Option<u16>,
+}
+
+/// Resolution and source detected from release tags in a filename.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QualityInfo {
+    /// e.g. `2160p`, `1080p`.
+    pub resolution: Option<String>,
+    /// e.g. `BluRay`, `WEB-DL`, `HDTV`.
+    pub source: Option<String>,
+}
+
 /// Result of parsing a media filename.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ParsedMedia {
@@ -59,12 +79,37 @@ static RE_XEP: LazyLock<Regex> =
 static RE_SEASON_EPISODE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?i)Season\s+(\d+)\s+Episode\s+(\d+)").unwrap());
 
+// Season folder names, built-in plus `RUSTFIN_SEASON_FOLDER_WORDS`
+static SEASON_FOLDERS: LazyLock<SeasonFolderNames> = LazyLock::new(SeasonFolderNames::from_env);
+
+// Episode number at the start of a filename: "05", "05 - Title", "E05.Title", "Episode 5"
+static RE_LEADING_EPISODE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:(?:Episode|Ep|E)[\s._-]*)?(\d{1,3})(?:[\s._-]+(.*))?$").unwrap()
+});
+
 // Movie: "Title (Year)" or "Title.Year"
 static RE_MOVIE_YEAR_PAREN: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^(.+?)\s*\((\d{4})\)").unwrap());
 
-static RE_MOVIE_YEAR_DOT: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"^(.+?)[\.\s](\d{4})(?:[\.\s]|$)").unwrap());
+// Series: "Title (2015)", "Title (2015-2021)", or "Title (2015-)" while still running.
+static RE_SERIES_YEARS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(.+?)\s*\((\d{4})(?:\s*[-\u{2013}]\s*(\d{4})?)?\)\s*$").unwrap()
+});
+
+// A bare 4-digit number after a separator; what follows is checked separately so
+// consecutive candidates ("2049.2017") don't swallow each other's separator.
+static RE_MOVIE_YEAR_DOT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\.\s](\d{4})").unwrap());
+
+// Release tags: resolution, source, codec and HDR markers. "web" and "dv" are left
+// out because they also turn up in titles ("Charlotte's Web").
+static RE_QUALITY_TOKEN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(?:\d{3,4}[pi]|4k|uhd|blu-?ray|bdrip|brrip|bdremux|remux|web-?dl|webrip|hdtv|dvdrip|hdrip|x26[45]|h26[45]|hevc|avc|xvid|av1|hdr|hdr10\+?|dovi|10bit)$",
+    )
+    .unwrap()
+});
+
+static RE_RESOLUTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\d{3,4}[pi]$").unwrap());
 
 // Provider ID in folder name: [tmdb=12345], [tvdb=67890], [imdb=tt123]
 static RE_PROVIDER_ID: LazyLock<Regex> =
@@ -87,6 +132,61 @@ pub fn is_video_file(filename: &str) -> bool {
     }
 }
 
+/// Check if a file is a `.strm` pointer to remote media.
+pub fn is_strm_file(filename: &str) -> bool {
+    filename
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("strm"))
+}
+
+/// Read the URL out of a `.strm` file: the first non-empty, non-comment line.
+///
+/// Only `http://` and `https://` URLs are accepted.
+pub fn parse_strm_url(contents: &str) -> Option<String> {
+    let line = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))?;
+    let lower = line.to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("http://")
+        .or_else(|| lower.strip_prefix("https://"))?;
+    if rest.is_empty() || line.chars().any(char::is_whitespace) {
+        return None;
+    }
+    Some(line.to_string())
+}
+
+/// Check if a file is an `.m3u` / `.m3u8` playlist.
+pub fn is_playlist_file(filename: &str) -> bool {
+    filename
+        .rsplit_once('.')
+        .is_some_and(|(_, ext)| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+}
+
+/// The entries of an `.m3u` playlist, in order: every non-empty, non-comment line.
+///
+/// Returns `None` for HLS playlists (any `#EXT-X-` tag), which are stream manifests
+/// such as transcode output rather than lists of library files.
+pub fn parse_m3u_entries(contents: &str) -> Option<Vec<String>> {
+    let lines: Vec<&str> = contents
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .map(str::trim)
+        .collect();
+    if lines.iter().any(|l| l.starts_with("#EXT-X-")) {
+        return None;
+    }
+    Some(
+        lines
+            .into_iter()
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
 /// Extract provider IDs from a folder/file name like `[tmdb=12345]`.
 pub fn extract_provider_ids(name: &str) -> Vec<(String, String)> {
     RE_PROVIDER_ID
@@ -100,6 +200,84 @@ fn clean_title(raw: &str) -> String {
     raw.replace('.', " ").replace('_', " ").trim().to_string()
 }
 
+/// Cut a cleaned title at its first release tag: "Movie 2160p HDR" → "Movie".
+/// Empty when the title starts with one.
+fn strip_quality_tokens(title: &str) -> String {
+    let words: Vec<&str> = title.split_whitespace().collect();
+    match words
+        .iter()
+        .position(|w| RE_QUALITY_TOKEN.is_match(w.trim_matches(['[', ']', '(', ')'])))
+    {
+        Some(end) => words[..end].join(" "),
+        None => title.to_string(),
+    }
+}
+
+/// [`clean_title`] without trailing release tags. A title made only of tags is kept as is.
+fn clean_release_title(raw: &str) -> String {
+    let title = clean_title(raw);
+    let stripped = strip_quality_tokens(&title);
+    if stripped.is_empty() { title } else { stripped }
+}
+
+/// Episode title text after the episode marker, or `None` if only release tags remain.
+fn episode_title(raw: &str) -> Option<String> {
+    Some(strip_quality_tokens(&clean_title(raw))).filter(|t| !t.is_empty())
+}
+
+/// Split a series folder name (or the part of a filename before the episode
+/// marker) into title and years: `The Expanse (2015-2021)` → `The Expanse`, 2015
+/// to 2021. Names without a trailing year or year range come back unchanged.
+pub fn parse_series_name(name: &str) -> SeriesName {
+    let name = name.trim();
+    let plausible = |y: &str| y.parse::<u16>().ok().filter(|y| (1900..=2100).contains(y));
+    let parsed = RE_SERIES_YEARS.captures(name).and_then(|caps| {
+        let year = plausible(&caps[2])?;
+        let end_year = match caps.get(3) {
+            Some(end) => Some(plausible(end.as_str()).filter(|end| *end >= year)?),
+            None => None,
+        };
+        Some(SeriesName {
+            title: caps[1].trim().to_string(),
+            year: Some(year),
+            end_year,
+        })
+    });
+    parsed.unwrap_or_else(|| SeriesName {
+        title: name.to_string(),
+        year: None,
+        end_year: None,
+    })
+}
+
+/// Resolution and source tags in a filename, like `2160p` and `BluRay`.
+pub fn parse_quality(filename: &str) -> QualityInfo {
+    let mut quality = QualityInfo::default();
+    for token in filename.split(['.', ' ', '_', '[', ']', '(', ')']) {
+        let token = token.to_ascii_lowercase();
+        if quality.resolution.is_none() {
+            quality.resolution = match token.as_str() {
+                "4k" | "uhd" => Some("2160p".to_string()),
+                t if RE_RESOLUTION.is_match(t) => Some(t.to_string()),
+                _ => None,
+            };
+        }
+        if quality.source.is_none() {
+            quality.source = match token.as_str() {
+                "bluray" | "blu-ray" | "bdrip" | "brrip" | "bdremux" => Some("BluRay"),
+                "web-dl" | "webdl" => Some("WEB-DL"),
+                "webrip" => Some("WEBRip"),
+                "hdtv" => Some("HDTV"),
+                "dvdrip" => Some("DVD"),
+                "hdrip" => Some("HDRip"),
+                _ => None,
+            }
+            .map(String::from);
+        }
+    }
+    quality
+}
+
 /// Parse a video filename into movie or episode info.
 pub fn parse_filename(filename: &str) -> ParsedMedia {
     let stem = filename
@@ -129,7 +307,7 @@ pub fn parse_filename(filename: &str) -> ParsedMedia {
 
     // Fallback: treat as movie with just a title
     ParsedMedia::Movie(MovieInfo {
-        title: clean_title(stem),
+        title: clean_release_title(stem),
         year: None,
     })
 }
@@ -140,17 +318,16 @@ fn try_parse_episode(stem: &str) -> Option<EpisodeInfo> {
         let season: u32 = caps[1].parse().ok()?;
         let episode: u32 = caps[2].parse().ok()?;
         let match_start = caps.get(0)?.start();
-        let series_raw = &stem[..match_start];
-        let series_title = clean_title(series_raw);
+        let series = parse_series_name(&clean_title(&stem[..match_start]));
         let after = &stem[caps.get(0)?.end()..];
         let episode_title = if after.len() > 1 {
-            let t = clean_title(after.trim_start_matches(['-', '.', ' ', '_']));
-            if t.is_empty() { None } else { Some(t) }
+            episode_title(after.trim_start_matches(['-', '.', ' ', '_']))
         } else {
             None
         };
         return Some(EpisodeInfo {
-            series_title,
+            series_title: series.title,
+            series_year: series.year,
             season,
             episode,
             episode_title,
@@ -162,10 +339,10 @@ fn try_parse_episode(stem: &str) -> Option<EpisodeInfo> {
         let season: u32 = caps[1].parse().ok()?;
         let episode: u32 = caps[2].parse().ok()?;
         let match_start = caps.get(0)?.start();
-        let series_raw = &stem[..match_start];
-        let series_title = clean_title(series_raw);
+        let series = parse_series_name(&clean_title(&stem[..match_start]));
         return Some(EpisodeInfo {
-            series_title,
+            series_title: series.title,
+            series_year: series.year,
             season,
             episode,
             episode_title: None,
@@ -177,10 +354,10 @@ fn try_parse_episode(stem: &str) -> Option<EpisodeInfo> {
         let season: u32 = caps[1].parse().ok()?;
         let episode: u32 = caps[2].parse().ok()?;
         let match_start = caps.get(0)?.start();
-        let series_raw = &stem[..match_start];
-        let series_title = clean_title(series_raw);
+        let series = parse_series_name(&clean_title(&stem[..match_start]));
         return Some(EpisodeInfo {
-            series_title,
+            series_title: series.title,
+            series_year: series.year,
             season,
             episode,
             episode_title: None,
@@ -190,10 +367,101 @@ fn try_parse_episode(stem: &str) -> Option<EpisodeInfo> {
     None
 }
 
+/// Season number of a season folder (`Season 02`, `Staffel 2`, `S2`, `Specials` → 0),
+/// using the built-in words plus any from `RUSTFIN_SEASON_FOLDER_WORDS`.
+pub fn parse_season_folder(name: &str) -> Option<u32> {
+    SEASON_FOLDERS.parse(name)
+}
+
+/// Words that name a season folder when followed by its number, e.g. `Saison 3`.
+/// `S` + number (`S1`) and `Specials` are always recognised.
+#[derive(Debug, Clone)]
+pub struct SeasonFolderNames {
+    words: Vec<String>,
+    pattern: Regex,
+}
+
+impl SeasonFolderNames {
+    /// English, UK `Series`, and the common European translations.
+    pub const BUILT_IN: &[&str] = &[
+        "Season",
+        "Series",
+        "Staffel",
+        "Saison",
+        "Temporada",
+        "Stagione",
+        "Seizoen",
+        "Säsong",
+        "Sæson",
+        "Sesong",
+        "Sezon",
+        "Kausi",
+    ];
+
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut words: Vec<String> = words
+            .into_iter()
+            .map(Into::into)
+            .map(|w| w.trim().to_string())
+            .filter(|w| !w.is_empty())
+            .collect();
+        let mut seen = std::collections::HashSet::new();
+        words.retain(|w| seen.insert(w.to_lowercase()));
+        let alternatives: Vec<String> = words.iter().map(|w| regex::escape(w)).collect();
+        let pattern = Regex::new(&format!(
+            r"(?i)^(?:(?:{})[\s._-]*|S)(\d{{1,3}})$",
+            alternatives.join("|")
+        ))
+        .expect("escaped season words form a valid pattern");
+        Self { words, pattern }
+    }
+
+    /// The built-in words plus comma-separated extras from `RUSTFIN_SEASON_FOLDER_WORDS`.
+    pub fn from_env() -> Self {
+        let extra = std::env::var("RUSTFIN_SEASON_FOLDER_WORDS").unwrap_or_default();
+        Self::default().with_words(extra.split(',').map(str::to_string))
+    }
+
+    /// These names plus `words`.
+    pub fn with_words<I: IntoIterator<Item = String>>(self, words: I) -> Self {
+        Self::new(self.words.into_iter().chain(words))
+    }
+
+    pub fn parse(&self, name: &str) -> Option<u32> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("specials") {
+            return Some(0);
+        }
+        self.pattern.captures(name)?[1].parse().ok()
+    }
+}
+
+impl Default for SeasonFolderNames {
+    fn default() -> Self {
+        Self::new(Self::BUILT_IN.iter().copied())
+    }
+}
+
+/// Episode number and optional title from a filename that starts with an episode
+/// number, like `05.mkv`, `05 - Pilot.mkv` or `E05.mkv`. Used for episodes that only
+/// get their season from the folder they sit in.
+pub fn parse_leading_episode(filename: &str) -> Option<(u32, Option<String>)> {
+    let stem = filename.rsplit(['/', '\\']).next().unwrap_or(filename);
+    let stem = stem.rfind('.').map_or(stem, |pos| &stem[..pos]);
+    let caps = RE_LEADING_EPISODE.captures(stem)?;
+    let episode = caps[1].parse().ok()?;
+    let title = caps.get(2).and_then(|m| episode_title(m.as_str()));
+    Some((episode, title))
+}
+
 fn try_parse_movie(stem: &str) -> Option<MovieInfo> {
     // "Title (2024)"
     if let Some(caps) = RE_MOVIE_YEAR_PAREN.captures(stem) {
-        let title = clean_title(&caps[1]);
+        let title = clean_release_title(&caps[1]);
         let year: u16 = caps[2].parse().ok()?;
         return Some(MovieInfo {
             title,
@@ -201,19 +469,29 @@ fn try_parse_movie(stem: &str) -> Option<MovieInfo> {
         });
     }
 
-    // "Title.2024.etc"
-    if let Some(caps) = RE_MOVIE_YEAR_DOT.captures(stem) {
-        let title = clean_title(&caps[1]);
-        let year: u16 = caps[2].parse().ok()?;
-        if year >= 1900 && year <= 2100 {
-            return Some(MovieInfo {
-                title,
-                year: Some(year),
-            });
-        }
+    // "Title.2024.etc": the last plausible year wins, so a number that belongs to
+    // the title ("Blade.Runner.2049.2017") stays in it.
+    let (start, year) = RE_MOVIE_YEAR_DOT
+        .captures_iter(stem)
+        .filter_map(|caps| {
+            let start = caps.get(0)?.start();
+            let m = caps.get(1)?;
+            let ends_token = stem[m.end()..]
+                .chars()
+                .next()
+                .is_none_or(|c| c == '.' || c.is_whitespace());
+            let year: u16 = m.as_str().parse().ok()?;
+            (ends_token && (1900..=2100).contains(&year)).then_some((start, year))
+        })
+        .last()?;
+    let title = clean_release_title(&stem[..start]);
+    if title.is_empty() {
+        return None;
     }
-
-    None
+    Some(MovieInfo {
+        title,
+        year: Some(year),
+    })
 }
 
 // ─── Tests ───────────────────────────────────────────────────────────────────
@@ -222,6 +500,37 @@ fn try_parse_movie(stem: &str) -> Option<MovieInfo> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn strm_url_parsing() {
+        assert!(is_strm_file("Movie (2020).STRM"));
+        assert!(!is_strm_file("Movie (2020).mkv"));
+        assert_eq!(
+            parse_strm_url("\u{feff}# comment\n\n  https://cdn.example.com/a.mp4  \n"),
+            Some("https://cdn.example.com/a.mp4".into())
+        );
+        assert_eq!(parse_strm_url("file:///etc/passwd"), None);
+        assert_eq!(parse_strm_url("http://"), None);
+        assert_eq!(parse_strm_url("http://host/a b.mp4"), None);
+        assert_eq!(parse_strm_url(""), None);
+    }
+
+    #[test]
+    fn m3u_entries_skip_comments_and_refuse_hls() {
+        assert!(is_playlist_file("Favourites.M3U"));
+        assert!(is_playlist_file("mix.m3u8"));
+        assert!(!is_playlist_file("Movie (2020).mkv"));
+        assert_eq!(
+            parse_m3u_entries(
+                "\u{feff}#EXTM3U\n#EXTINF:5400,Heat\nMovies/Heat (1995).mkv\n\n  /srv/b.mkv  \n"
+            ),
+            Some(vec!["Movies/Heat (1995).mkv".into(), "/srv/b.mkv".into()])
+        );
+        assert_eq!(
+            parse_m3u_entries("#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\nseg_00000.ts\n"),
+            None
+        );
+    }
+
     #[test]
     fn parse_sxxexx() {
         let r = parse_filename("Breaking.Bad.S02E05.Episode.Title.mkv");
@@ -229,6 +538,7 @@ mod tests {
             r,
             ParsedMedia::Episode(EpisodeInfo {
                 series_title: "Breaking Bad".into(),
+                series_year: None,
                 season: 2,
                 episode: 5,
                 episode_title: Some("Episode Title".into()),
@@ -243,6 +553,7 @@ mod tests {
             r,
             ParsedMedia::Episode(EpisodeInfo {
                 series_title: "the office".into(),
+                series_year: None,
                 season: 1,
                 episode: 1,
                 episode_title: Some("pilot".into()),
@@ -257,6 +568,7 @@ mod tests {
             r,
             ParsedMedia::Episode(EpisodeInfo {
                 series_title: "Seinfeld".into(),
+                series_year: None,
                 season: 3,
                 episode: 12,
                 episode_title: None,
@@ -271,6 +583,7 @@ mod tests {
             r,
             ParsedMedia::Episode(EpisodeInfo {
                 series_title: "Friends".into(),
+                series_year: None,
                 season: 2,
                 episode: 14,
                 episode_title: None,
@@ -302,6 +615,107 @@ mod tests {
         );
     }
 
+    #[test]
+    fn numbers_in_the_title_are_not_the_year() {
+        for (filename, title, year) in [
+            ("Blade Runner 2049 (2017).mkv", "Blade Runner 2049", 2017),
+            ("2012 (2009).mkv", "2012", 2009),
+            ("1917 (2019).mkv", "1917", 2019),
+            (
+                "Blade.Runner.2049.2017.1080p.BluRay.mkv",
+                "Blade Runner 2049",
+                2017,
+            ),
+            ("2012.2009.720p.mkv", "2012", 2009),
+            ("Blade.Runner.2049.(2017).mkv", "Blade Runner 2049", 2017),
+        ] {
+            assert_eq!(
+                parse_filename(filename),
+                ParsedMedia::Movie(MovieInfo {
+                    title: title.into(),
+                    year: Some(year),
+                }),
+                "{filename}"
+            );
+        }
+    }
+
+    #[test]
+    fn title_that_is_only_a_number_has_no_year() {
+        assert_eq!(
+            parse_filename("1917.mkv"),
+            ParsedMedia::Movie(MovieInfo {
+                title: "1917".into(),
+                year: None,
+            })
+        );
+    }
+
+    #[test]
+    fn quality_tags_stay_out_of_titles() {
+        for (filename, title, year) in [
+            (
+                "Movie (2020) 2160p HDR BluRay x265.mkv",
+                "Movie",
+                Some(2020),
+            ),
+            ("Movie.2160p.HDR.BluRay.x265.mkv", "Movie", None),
+            ("Some.Movie.1080p.WEB-DL.H264.mkv", "Some Movie", None),
+            ("Some Movie [720p] [HDTV].mkv", "Some Movie", None),
+            ("Charlotte's Web (1973).mkv", "Charlotte's Web", Some(1973)),
+            ("1080p.mkv", "1080p", None),
+        ] {
+            assert_eq!(
+                parse_filename(filename),
+                ParsedMedia::Movie(MovieInfo {
+                    title: title.into(),
+                    year,
+                }),
+                "{filename}"
+            );
+        }
+
+        let ParsedMedia::Episode(ep) = parse_filename("Show.S01E02.Pilot.1080p.WEB-DL.x264.mkv")
+        else {
+            panic!("expected an episode");
+        };
+        assert_eq!(ep.episode_title.as_deref(), Some("Pilot"));
+        let ParsedMedia::Episode(ep) = parse_filename("Show.S01E03.2160p.WEBRip.mkv") else {
+            panic!("expected an episode");
+        };
+        assert_eq!(ep.episode_title, None);
+        assert_eq!(
+            parse_leading_episode("04 - Finale 720p.mkv"),
+            Some((4, Some("Finale".into())))
+        );
+    }
+
+    #[test]
+    fn quality_is_detected_from_release_tags() {
+        assert_eq!(
+            parse_quality("Movie (2020) 2160p HDR BluRay x265.mkv"),
+            QualityInfo {
+                resolution: Some("2160p".into()),
+                source: Some("BluRay".into()),
+            }
+        );
+        assert_eq!(
+            parse_quality("Show.S01E02.720p.WEB-DL.mkv"),
+            QualityInfo {
+                resolution: Some("720p".into()),
+                source: Some("WEB-DL".into()),
+            }
+        );
+        assert_eq!(
+            parse_quality("Movie.4K.HDR.mkv").resolution.as_deref(),
+            Some("2160p")
+        );
+        assert_eq!(
+            parse_quality("The Matrix (1999).mkv"),
+            QualityInfo::default()
+        );
+    }
+
     #[test]
     fn parse_movie_no_year() {
         let r = parse_filename("Some Random Movie.mp4");
@@ -357,10 +771,112 @@ mod tests {
             r,
             ParsedMedia::Episode(EpisodeInfo {
                 series_title: "Show Name".into(),
+                series_year: None,
                 season: 0,
                 episode: 1,
                 episode_title: Some("Special".into()),
             })
         );
     }
+
+    #[test]
+    fn series_names_with_year_ranges() {
+        let name = |title: &str, year, end_year| SeriesName {
+            title: title.into(),
+            year,
+            end_year,
+        };
+        assert_eq!(
+            parse_series_name("The Expanse (2015-2021)"),
+            name("The Expanse", Some(2015), Some(2021))
+        );
+        assert_eq!(
+            parse_series_name("Friends (1994)"),
+            name("Friends", Some(1994), None)
+        );
+        assert_eq!(
+            parse_series_name("One Piece (1999\u{2013})"),
+            name("One Piece", Some(1999), None)
+        );
+        assert_eq!(
+            parse_series_name("Show Name"),
+            name("Show Name", None, None)
+        );
+        // A range that ends before it starts is not a year range.
+        assert_eq!(
+            parse_series_name("Odd (2021-2015)"),
+            name("Odd (2021-2015)", None, None)
+        );
+    }
+
+    #[test]
+    fn episode_filenames_carry_the_series_years() {
+        let r = parse_filename("The.Expanse.(2015-2021).S01E01.Dulcinea.mkv");
+        assert_eq!(
+            r,
+            ParsedMedia::Episode(EpisodeInfo {
+                series_title: "The Expanse".into(),
+                series_year: Some(2015),
+                season: 1,
+                episode: 1,
+                episode_title: Some("Dulcinea".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn movie_year_is_not_read_as_a_range() {
+        assert_eq!(
+            parse_filename("Friends (1994).mkv"),
+            ParsedMedia::Movie(MovieInfo {
+                title: "Friends".into(),
+                year: Some(1994),
+            })
+        );
+        assert!(matches!(
+            parse_filename("Anthology (2015-2021).mkv"),
+            ParsedMedia::Movie(MovieInfo { year: None, .. })
+        ));
+    }
+
+    #[test]
+    fn season_folder_names() {
+        assert_eq!(parse_season_folder("Season 02"), Some(2));
+        assert_eq!(parse_season_folder("season.3"), Some(3));
+        assert_eq!(parse_season_folder("S04"), Some(4));
+        assert_eq!(parse_season_folder("Specials"), Some(0));
+        assert_eq!(parse_season_folder("Extras"), None);
+        assert_eq!(parse_season_folder("Season Two"), None);
+        assert_eq!(parse_season_folder("Staffel 02"), Some(2));
+        assert_eq!(parse_season_folder("Saison 3"), Some(3));
+        assert_eq!(parse_season_folder("S1"), Some(1));
+        assert_eq!(parse_season_folder("Series 1"), Some(1));
+        assert_eq!(parse_season_folder("säsong 4"), Some(4));
+        assert_eq!(parse_season_folder("Staffelei 2"), None);
+    }
+
+    #[test]
+    fn season_folder_words_are_extensible() {
+        let names = SeasonFolderNames::default().with_words(["Sezóna".to_string()]);
+        assert_eq!(names.parse("Sezóna 5"), Some(5));
+        assert_eq!(names.parse("Season 6"), Some(6));
+        assert_eq!(SeasonFolderNames::default().parse("Sezóna 5"), None);
+    }
+
+    #[test]
+    fn leading_episode_numbers() {
+        assert_eq!(parse_leading_episode("05.mkv"), Some((5, None)));
+        assert_eq!(
+            parse_leading_episode("12 - The Finale.mkv"),
+            Some((12, Some("The Finale".into())))
+        );
+        assert_eq!(parse_leading_episode("Pilot.mkv"), None);
+        assert_eq!(parse_leading_episode("2049.mkv"), None);
+        assert_eq!(parse_leading_episode("E07.mkv"), Some((7, None)));
+        assert_eq!(
+            parse_leading_episode("Episode 3 - Ghosts.mkv"),
+            Some((3, Some("Ghosts".into())))
+        );
+        assert_eq!(parse_leading_episode("Epilogue.mkv"), None);
+    }
 }
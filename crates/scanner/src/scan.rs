@@ -1,84 +1,865 @@
-use sqlx::SqlitePool;
-use std::path::Path;
-use tracing::{info, warn};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
 
 use crate::parser::{self, ParsedMedia};
-use crate::walk;
+use crate::{extras, walk};
 
 /// Run a full scan for a library, creating/updating items and media files.
+///
+/// Files that fail (unreadable, vanished mid-scan, a failed insert) are logged,
+/// counted in [`ScanResult::errors`] and recorded so [`retry_failed_files`] can
+/// revisit just them.
+///
+/// Files the library knows under a root that the walk no longer finds are removed,
+/// along with items left without files. A root that cannot be read (an unmounted
+/// disk) is unavailable rather than empty: it is skipped with a warning and its
+/// items are kept. The scan fails only when every root is unavailable.
 pub async fn run_library_scan(
     pool: &SqlitePool,
     library_id: &str,
     library_kind: &str,
 ) -> Result<ScanResult, ScanError> {
-    let paths = rustfin_db::repo::libraries::get_library_paths(pool, library_id)
+    run_library_scan_with(pool, library_id, library_kind, &ScanOptions::from_env()).await
+}
+
+/// [`run_library_scan`] with explicit parallelism and batching.
+///
+/// Up to `options.parallelism` library paths are walked at once; their files are
+/// still added path by path, in the library's path order, so items shared between
+/// paths (a series split across disks) are created once.
+///
+/// With `options.duration_probe` set, a movie library's new files shorter than its
+/// `sample_max_duration_secs` are counted as skipped instead of imported.
+pub async fn run_library_scan_with(
+    pool: &SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    options: &ScanOptions,
+) -> Result<ScanResult, ScanError> {
+    let all_paths = rustfin_db::repo::libraries::get_library_paths(pool, library_id)
         .await
         .map_err(ScanError::Db)?;
 
+    let (mut paths, mut unavailable) = (Vec::new(), Vec::new());
+    for lib_path in all_paths {
+        match std::fs::read_dir(&lib_path.path) {
+            Ok(_) => paths.push(lib_path),
+            Err(e) => unavailable.push((lib_path.path, e)),
+        }
+    }
+    if paths.is_empty()
+        && let Some((path, source)) = unavailable.pop()
+    {
+        return Err(ScanError::RootUnavailable { path, source });
+    }
+    for (path, e) in &unavailable {
+        warn!(
+            library_id = library_id,
+            path = %path,
+            error = %e,
+            "library path is unavailable; skipping it and keeping its items"
+        );
+    }
+
+    let policy = ItemPolicy::for_library(pool, library_id).await?;
+    let samples = SampleFilter::for_library(pool, library_id, library_kind, options).await?;
     let mut result = ScanResult::default();
+    let mut failed_paths = Vec::new();
+    let limits = walk::WalkLimits::from_env();
 
-    for lib_path in &paths {
-        let root = Path::new(&lib_path.path);
-        if !root.exists() {
-            warn!(path = %lib_path.path, "library path does not exist, skipping");
-            continue;
-        }
+    let mut playlists = PlaylistScan::default();
 
-        let entries = walk::walk_media_dir(root);
+    let permits = Arc::new(tokio::sync::Semaphore::new(options.parallelism.max(1)));
+    let walks: Vec<_> = paths
+        .iter()
+        .map(|lib_path| {
+            let root = PathBuf::from(&lib_path.path);
+            let permits = permits.clone();
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                tokio::task::spawn_blocking(move || {
+                    walk::walk_media_dir_with_failures(&root, &limits)
+                })
+                .await
+            })
+        })
+        .collect();
+
+    for (lib_path, walk) in paths.iter().zip(walks) {
+        let root = Path::new(&lib_path.path);
+        let walked = walk
+            .await
+            .and_then(|joined| joined)
+            .map_err(|e| ScanError::Io(std::io::Error::other(e)))??;
         info!(
             library_id = library_id,
             path = %lib_path.path,
-            files_found = entries.len(),
+            files_found = walked.entries.len(),
             "scan found video files"
         );
 
-        for entry in &entries {
-            let path_str = entry.path.to_string_lossy().to_string();
+        for failure in &walked.failures {
+            let path = failure.path.to_string_lossy().to_string();
+            record_failure(pool, library_id, &path, &failure.error, &mut result).await;
+            failed_paths.push(path);
+        }
+
+        let entries = match &samples {
+            Some(filter) => Cow::Owned(
+                filter
+                    .keep(library_id, root, &walked.entries, &mut result)
+                    .await,
+            ),
+            None => Cow::Borrowed(walked.entries.as_slice()),
+        };
+        for batch in entries.chunks(options.batch_size.clamp(1, ScanOptions::MAX_BATCH_SIZE)) {
+            if batch.len() > 1 {
+                match add_batch(pool, library_id, library_kind, policy, root, batch).await {
+                    Ok((added, skipped)) => {
+                        result.added += added;
+                        result.skipped += skipped;
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "batched insert failed; adding files one at a time");
+                    }
+                }
+            }
+            for entry in batch {
+                if let Err(e) = scan_entry(
+                    pool,
+                    library_id,
+                    library_kind,
+                    policy,
+                    root,
+                    entry,
+                    &mut result,
+                )
+                .await
+                {
+                    let path = entry.path.to_string_lossy().to_string();
+                    record_failure(pool, library_id, &path, &e.to_string(), &mut result).await;
+                    failed_paths.push(path);
+                }
+            }
+        }
+
+        if matches!(library_kind, "tv_shows" | "mixed") {
+            link_series_extras(pool, library_id, root, &walked.entries).await;
+        }
+
+        result.removed += remove_missing(pool, library_id, root, &walked).await;
+        playlists.add_root(root, walked);
+    }
+
+    // After every root, so entries can point at files under any of them.
+    import_playlists(pool, library_id, &playlists).await;
+
+    // Failures recorded under unavailable roots can't have been retried; keep them.
+    if !unavailable.is_empty() {
+        match rustfin_db::repo::scan_errors::list_scan_errors(pool, library_id).await {
+            Ok(recorded) => failed_paths.extend(recorded.into_iter().map(|e| e.path).filter(|p| {
+                unavailable
+                    .iter()
+                    .any(|(root, _)| Path::new(p).starts_with(root))
+            })),
+            Err(e) => {
+                warn!(library_id = library_id, error = %e, "failed to read recorded scan errors")
+            }
+        }
+    }
+
+    if let Err(e) =
+        rustfin_db::repo::scan_errors::retain_scan_errors(pool, library_id, &failed_paths).await
+    {
+        warn!(library_id = library_id, error = %e, "failed to prune recorded scan errors");
+    }
+
+    Ok(result)
+}
+
+/// Skips new files in a movie library that are too short to be the movie itself,
+/// from the library's `sample_max_duration_secs`.
+///
+/// Probes are recorded in `media_file_probe` by path, size and mtime, so a skipped
+/// sample is not probed again until it changes, and the server reuses the probes
+/// of the files that are added.
+struct SampleFilter {
+    pool: SqlitePool,
+    probe: DurationProbe,
+    parallelism: usize,
+    max_secs: f64,
+    /// Files the library already has; only files new to it are probed.
+    known: HashSet<String>,
+}
+
+impl SampleFilter {
+    /// `None` unless this is a movie library with the check on and a probe to run it.
+    async fn for_library(
+        pool: &SqlitePool,
+        library_id: &str,
+        library_kind: &str,
+        options: &ScanOptions,
+    ) -> Result<Option<Self>, ScanError> {
+        let Some(probe) = options.duration_probe.clone() else {
+            return Ok(None);
+        };
+        if library_kind != "movies" {
+            return Ok(None);
+        }
+        let max_secs =
+            rustfin_db::repo::libraries::get_library_sample_max_duration(pool, library_id)
+                .await
+                .map_err(ScanError::Db)?;
+        if max_secs <= 0 {
+            return Ok(None);
+        }
+        let known = rustfin_db::repo::media_files::list_library_files(pool, library_id)
+            .await
+            .map_err(ScanError::Db)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        Ok(Some(Self {
+            pool: pool.clone(),
+            probe,
+            parallelism: options.parallelism.max(1),
+            max_secs: max_secs as f64,
+            known,
+        }))
+    }
+
+    /// The entries under `root` to import. A file whose duration can't be read is
+    /// imported.
+    async fn keep(
+        &self,
+        library_id: &str,
+        root: &Path,
+        entries: &[walk::MediaEntry],
+        result: &mut ScanResult,
+    ) -> Vec<walk::MediaEntry> {
+        let new: Vec<(String, i64, i64)> = entries
+            .iter()
+            .filter(|e| e.remote_url.is_none())
+            .map(|e| {
+                (
+                    e.path.to_string_lossy().to_string(),
+                    e.size_bytes as i64,
+                    e.mtime_ts,
+                )
+            })
+            .filter(|(path, _, _)| !self.known.contains(path))
+            .collect();
+        let mut durations = rustfin_db::repo::probes::get_path_durations(&self.pool, &new)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(library_id = library_id, error = %e, "failed to read recorded probes");
+                Default::default()
+            });
+
+        let permits = Arc::new(tokio::sync::Semaphore::new(self.parallelism));
+        let mut probes = tokio::task::JoinSet::new();
+        for (path, size_bytes, mtime_ts) in new {
+            if durations.contains_key(&path) {
+                continue;
+            }
+            let (permits, probe) = (permits.clone(), self.probe.clone());
+            probes.spawn(async move {
+                let _permit = permits.acquire_owned().await;
+                let probed = (probe.0)(PathBuf::from(&path)).await;
+                (path, size_bytes, mtime_ts, probed)
+            });
+        }
+        while let Some(joined) = probes.join_next().await {
+            let Ok((path, size_bytes, mtime_ts, probed)) = joined else {
+                continue;
+            };
+            let Some(probed) = probed else {
+                continue;
+            };
+            if let Err(e) = rustfin_db::repo::probes::record_path_probe(
+                &self.pool,
+                &path,
+                size_bytes,
+                mtime_ts,
+                probed.duration_secs,
+                &probed.info_json,
+            )
+            .await
+            {
+                warn!(path = %path, error = %e, "failed to record probe");
+            }
+            durations.insert(path, probed.duration_secs);
+        }
+
+        let present = entries
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect();
+        if let Err(e) = rustfin_db::repo::probes::prune_path_probes(
+            &self.pool,
+            &root.to_string_lossy(),
+            &present,
+        )
+        .await
+        {
+            warn!(library_id = library_id, error = %e, "failed to prune recorded probes");
+        }
 
-            // Check if media_file already exists for this path
-            let existing = file_exists(pool, &path_str).await.map_err(ScanError::Db)?;
-            if existing {
+        let mut kept = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if let Some(Some(secs)) = durations.get(entry.path.to_string_lossy().as_ref())
+                && *secs > 0.0
+                && *secs < self.max_secs
+            {
+                debug!(
+                    library_id = library_id,
+                    path = %entry.path.display(),
+                    duration_secs = secs,
+                    "skipping short file as a sample"
+                );
                 result.skipped += 1;
                 continue;
             }
+            kept.push(entry.clone());
+        }
+        kept
+    }
+}
 
-            // Determine relative path for parsing
-            let rel = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+/// Playlist files found by a scan, and where it could look for them.
+#[derive(Default)]
+struct PlaylistScan {
+    files: Vec<walk::PlaylistFile>,
+    /// Roots that were walked; imports under other roots are left alone.
+    roots: Vec<PathBuf>,
+    /// Paths the walk could not read, which may still hold playlist files.
+    unreadable: Vec<PathBuf>,
+}
 
-            // Parse based on library kind
-            let parsed = match library_kind {
-                "movies" => parse_movie_entry(rel),
-                "tv_shows" => parse_tv_entry(rel),
-                _ => {
-                    warn!(kind = library_kind, "unknown library kind");
-                    continue;
-                }
-            };
+impl PlaylistScan {
+    fn add_root(&mut self, root: &Path, walked: walk::WalkOutput) {
+        self.roots.push(root.to_path_buf());
+        self.unreadable
+            .extend(walked.failures.into_iter().map(|f| f.path));
+        self.files.extend(walked.playlists);
+    }
+}
 
-            match parsed {
-                ParsedMedia::Movie(info) => {
-                    create_movie_item(pool, library_id, &info, &path_str, entry)
-                        .await
-                        .map_err(ScanError::Db)?;
-                    result.added += 1;
-                }
-                ParsedMedia::Episode(info) => {
-                    create_episode_item(pool, library_id, &info, &path_str, entry)
-                        .await
-                        .map_err(ScanError::Db)?;
-                    result.added += 1;
-                }
-                ParsedMedia::Unknown(name) => {
-                    warn!(file = %name, "could not parse media filename");
-                    result.skipped += 1;
-                }
+/// `.m3u` files bigger than this are not read.
+const MAX_PLAYLIST_BYTES: u64 = 1024 * 1024;
+
+/// Import `.m3u` / `.m3u8` files as playlists owned by the oldest admin account.
+///
+/// A file is re-read only when its mtime differs from the last import. Entries are
+/// resolved to items by the path of a scanned media file (relative entries against
+/// the playlist's folder); entries that match no file are skipped. Imports whose
+/// file is gone from a walked root are deleted.
+async fn import_playlists(pool: &SqlitePool, library_id: &str, scan: &PlaylistScan) {
+    let known = match rustfin_db::repo::playlists::list_imported_playlists(pool, library_id).await {
+        Ok(known) => known,
+        Err(e) => {
+            warn!(library_id = library_id, error = %e, "failed to list imported playlists");
+            return;
+        }
+    };
+
+    for (playlist_id, source_path, _) in &known {
+        let path = Path::new(source_path);
+        if scan.roots.iter().any(|root| path.starts_with(root))
+            && !scan.unreadable.iter().any(|p| path.starts_with(p))
+            && !scan.files.iter().any(|f| f.path == path)
+        {
+            match rustfin_db::repo::playlists::delete_playlist(pool, playlist_id).await {
+                Ok(_) => info!(path = %source_path, "removed playlist whose file is gone"),
+                Err(e) => warn!(path = %source_path, error = %e, "failed to remove playlist"),
             }
         }
     }
 
+    let changed: Vec<&walk::PlaylistFile> = scan
+        .files
+        .iter()
+        .filter(|file| {
+            !known
+                .iter()
+                .any(|(_, path, mtime)| Path::new(path) == file.path && *mtime == file.mtime_ts)
+        })
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+    let owner = match rustfin_db::repo::users::oldest_admin_id(pool).await {
+        Ok(Some(owner)) => owner,
+        Ok(None) => {
+            warn!(
+                library_id = library_id,
+                "no admin account to own imported playlists"
+            );
+            return;
+        }
+        Err(e) => {
+            warn!(library_id = library_id, error = %e, "failed to look up playlist owner");
+            return;
+        }
+    };
+
+    for file in changed {
+        if let Err(e) = import_playlist(pool, library_id, &owner, file).await {
+            warn!(path = %file.path.display(), error = %e, "failed to import playlist");
+        }
+    }
+}
+
+async fn import_playlist(
+    pool: &SqlitePool,
+    library_id: &str,
+    owner: &str,
+    file: &walk::PlaylistFile,
+) -> Result<(), String> {
+    let size = std::fs::metadata(&file.path)
+        .map_err(|e| e.to_string())?
+        .len();
+    if size > MAX_PLAYLIST_BYTES {
+        return Err(format!("larger than {MAX_PLAYLIST_BYTES} bytes"));
+    }
+    let contents = std::fs::read(&file.path).map_err(|e| e.to_string())?;
+    let Some(entries) = parser::parse_m3u_entries(&String::from_utf8_lossy(&contents)) else {
+        tracing::debug!(path = %file.path.display(), "skipping HLS playlist");
+        return Ok(());
+    };
+
+    let mut item_ids = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        let path = resolve_playlist_entry(&file.path, entry);
+        match rustfin_db::repo::media_files::item_id_for_path(pool, &path).await {
+            Ok(Some(item_id)) => item_ids.push(item_id),
+            Ok(None) => {
+                tracing::debug!(playlist = %file.path.display(), entry = %entry, "skipping unresolved playlist entry");
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+
+    let source_path = file.path.to_string_lossy();
+    let name = file
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| source_path.to_string());
+    rustfin_db::repo::playlists::upsert_imported_playlist(
+        pool,
+        library_id,
+        owner,
+        &source_path,
+        file.mtime_ts,
+        &name,
+        &item_ids,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    info!(
+        path = %source_path,
+        items = item_ids.len(),
+        skipped = entries.len() - item_ids.len(),
+        "imported playlist"
+    );
+    Ok(())
+}
+
+/// The media file path an `.m3u` entry refers to: URLs as they are, other entries
+/// as paths relative to the playlist's folder unless absolute, with `\` read as a
+/// separator and `.` / `..` resolved.
+fn resolve_playlist_entry(playlist: &Path, entry: &str) -> String {
+    if entry.starts_with("http://") || entry.starts_with("https://") {
+        return entry.to_string();
+    }
+    let entry = PathBuf::from(entry.replace('\\', "/"));
+    let joined = match playlist.parent() {
+        Some(dir) if entry.is_relative() => dir.join(entry),
+        _ => entry,
+    };
+    let mut resolved = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved.to_string_lossy().to_string()
+}
+
+/// Re-scan only the files recorded as failed by earlier scans of the library.
+///
+/// Paths that now scan cleanly, or are no longer inside the library, are forgotten;
+/// paths that fail again stay recorded with their attempt count bumped.
+pub async fn retry_failed_files(
+    pool: &SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+) -> Result<ScanResult, ScanError> {
+    let paths = rustfin_db::repo::libraries::get_library_paths(pool, library_id)
+        .await
+        .map_err(ScanError::Db)?;
+    let failed = rustfin_db::repo::scan_errors::list_scan_errors(pool, library_id)
+        .await
+        .map_err(ScanError::Db)?;
+    let policy = ItemPolicy::for_library(pool, library_id).await?;
+
+    let mut result = ScanResult::default();
+    for row in &failed {
+        let path = Path::new(&row.path);
+        let Some(root) = paths
+            .iter()
+            .map(|p| Path::new(&p.path))
+            .find(|root| path.starts_with(root))
+        else {
+            clear_failure(pool, library_id, &row.path).await;
+            continue;
+        };
+
+        let outcome = match walk::media_entry(path) {
+            Ok(Some(entry)) => scan_entry(
+                pool,
+                library_id,
+                library_kind,
+                policy,
+                root,
+                &entry,
+                &mut result,
+            )
+            .await
+            .map_err(|e| e.to_string()),
+            // A directory that could not be listed before: walk just that subtree.
+            Err(_) | Ok(None) if path.is_dir() => {
+                rescan_subtree(
+                    pool,
+                    library_id,
+                    library_kind,
+                    policy,
+                    root,
+                    path,
+                    &mut result,
+                )
+                .await
+            }
+            Ok(None) => {
+                result.skipped += 1;
+                Ok(())
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        match outcome {
+            Ok(()) => clear_failure(pool, library_id, &row.path).await,
+            Err(e) => record_failure(pool, library_id, &row.path, &e, &mut result).await,
+        }
+    }
+
+    info!(
+        library_id = library_id,
+        retried = failed.len(),
+        added = result.added,
+        errors = result.errors,
+        "retried failed scan paths"
+    );
     Ok(result)
 }
 
+/// Walk a directory that previously could not be read. Files inside it that fail
+/// are recorded individually; the directory itself succeeds once it can be listed.
+async fn rescan_subtree(
+    pool: &SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    policy: ItemPolicy,
+    root: &Path,
+    dir: &Path,
+    result: &mut ScanResult,
+) -> Result<(), String> {
+    std::fs::read_dir(dir).map_err(|e| e.to_string())?;
+    let walked = walk::walk_media_dir_with_failures(dir, &walk::WalkLimits::from_env())
+        .map_err(|e| e.to_string())?;
+    for failure in &walked.failures {
+        let path = failure.path.to_string_lossy();
+        record_failure(pool, library_id, &path, &failure.error, result).await;
+    }
+    for entry in &walked.entries {
+        if let Err(e) =
+            scan_entry(pool, library_id, library_kind, policy, root, entry, result).await
+        {
+            let path = entry.path.to_string_lossy();
+            record_failure(pool, library_id, &path, &e.to_string(), result).await;
+        }
+    }
+    Ok(())
+}
+
+/// Add one walked file to the library, counting it as added or skipped.
+async fn scan_entry(
+    pool: &SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    policy: ItemPolicy,
+    root: &Path,
+    entry: &walk::MediaEntry,
+    result: &mut ScanResult,
+) -> Result<(), sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    match resolve_entry(&mut conn, library_id, library_kind, policy, root, entry).await? {
+        Resolved::Add(file) => {
+            insert_media_files(&mut conn, std::slice::from_ref(&file)).await?;
+            result.added += 1;
+        }
+        Resolved::Skipped => result.skipped += 1,
+        Resolved::Ignored => {}
+    }
+    Ok(())
+}
+
+/// Add several walked files in one transaction, with one multi-row insert for their
+/// media files and mappings. Returns how many were added and skipped; on error
+/// nothing is written and the caller falls back to [`scan_entry`].
+async fn add_batch(
+    pool: &SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    policy: ItemPolicy,
+    root: &Path,
+    entries: &[walk::MediaEntry],
+) -> Result<(usize, usize), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let mut pending = Vec::with_capacity(entries.len());
+    let mut skipped = 0;
+    for entry in entries {
+        match resolve_entry(&mut tx, library_id, library_kind, policy, root, entry).await? {
+            Resolved::Add(file) => pending.push(file),
+            Resolved::Skipped => skipped += 1,
+            Resolved::Ignored => {}
+        }
+    }
+    insert_media_files(&mut tx, &pending).await?;
+    tx.commit().await?;
+    Ok((pending.len(), skipped))
+}
+
+/// A walked file whose item exists and whose media file still has to be inserted.
+struct PendingFile<'a> {
+    path: String,
+    entry: &'a walk::MediaEntry,
+    item_id: String,
+}
+
+enum Resolved<'a> {
+    Add(PendingFile<'a>),
+    /// Already in the library, not a recognisable movie or episode, or a special
+    /// in a library that skips them.
+    Skipped,
+    /// The library kind is not scannable.
+    Ignored,
+}
+
+/// Parse a walked file and find or create the item it belongs to.
+async fn resolve_entry<'a>(
+    conn: &mut SqliteConnection,
+    library_id: &str,
+    library_kind: &str,
+    policy: ItemPolicy,
+    root: &Path,
+    entry: &'a walk::MediaEntry,
+) -> Result<Resolved<'a>, sqlx::Error> {
+    let path_str = entry.path.to_string_lossy().to_string();
+    if let Some(url) = &entry.remote_url {
+        update_remote_file(conn, &path_str, url, entry.mtime_ts).await?;
+    } else {
+        update_local_file(conn, &path_str, entry.size_bytes as i64, entry.mtime_ts).await?;
+    }
+
+    // Check if media_file already exists for this path
+    if file_exists(conn, &path_str).await? {
+        return Ok(Resolved::Skipped);
+    }
+
+    // Determine relative path for parsing
+    let rel = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+
+    let Some(parsed) = parse_library_path(library_kind, rel) else {
+        warn!(kind = library_kind, "unknown library kind");
+        return Ok(Resolved::Ignored);
+    };
+
+    let item_id = match parsed {
+        ParsedMedia::Movie(info) => movie_item(conn, library_id, &info, policy.ids).await?,
+        ParsedMedia::Episode(info)
+            if info.season == 0 && policy.specials == SpecialsPolicy::Skip =>
+        {
+            return Ok(Resolved::Skipped);
+        }
+        ParsedMedia::Episode(info) => episode_item(conn, library_id, &info, policy).await?,
+        ParsedMedia::Unknown(name) => {
+            warn!(file = %name, "could not parse media filename");
+            return Ok(Resolved::Skipped);
+        }
+    };
+    Ok(Resolved::Add(PendingFile {
+        path: path_str,
+        entry,
+        item_id,
+    }))
+}
+
+/// Remove the library's files under `root` that the walk did not find, and the
+/// items left without files. Files below a directory the walk could not read are
+/// kept. Returns how many items were removed.
+async fn remove_missing(
+    pool: &SqlitePool,
+    library_id: &str,
+    root: &Path,
+    walked: &walk::WalkOutput,
+) -> usize {
+    let known = match rustfin_db::repo::media_files::list_library_files(pool, library_id).await {
+        Ok(known) => known,
+        Err(e) => {
+            warn!(library_id = library_id, error = %e, "failed to list library files");
+            return 0;
+        }
+    };
+    let found: HashSet<&Path> = walked.entries.iter().map(|e| e.path.as_path()).collect();
+    let gone: Vec<String> = known
+        .into_iter()
+        .filter(|(_, path)| {
+            let path = Path::new(path);
+            path.starts_with(root)
+                && !found.contains(path)
+                && !walked.failures.iter().any(|f| path.starts_with(&f.path))
+        })
+        .map(|(id, _)| id)
+        .collect();
+    if gone.is_empty() {
+        return 0;
+    }
+
+    match rustfin_db::repo::media_files::remove_missing_files(pool, &gone).await {
+        Ok(removed) => {
+            info!(
+                library_id = library_id,
+                path = %root.display(),
+                files = gone.len(),
+                items = removed,
+                "removed files no longer on disk"
+            );
+            removed
+        }
+        Err(e) => {
+            warn!(library_id = library_id, error = %e, "failed to remove missing files");
+            0
+        }
+    }
+}
+
+async fn record_failure(
+    pool: &SqlitePool,
+    library_id: &str,
+    path: &str,
+    error: &str,
+    result: &mut ScanResult,
+) {
+    warn!(
+        library_id = library_id,
+        path = path,
+        error = error,
+        "failed to scan file"
+    );
+    result.errors += 1;
+    if let Err(e) =
+        rustfin_db::repo::scan_errors::record_scan_error(pool, library_id, path, error).await
+    {
+        warn!(path = path, error = %e, "failed to record scan error");
+    }
+}
+
+async fn clear_failure(pool: &SqlitePool, library_id: &str, path: &str) {
+    if let Err(e) = rustfin_db::repo::scan_errors::clear_scan_error(pool, library_id, path).await {
+        warn!(path = path, error = %e, "failed to clear recorded scan error");
+    }
+}
+
+/// Link theme files and `backdrops/` images in each series folder to its series
+/// item, replacing whatever an earlier scan linked. Locked series are left alone.
+async fn link_series_extras(
+    pool: &SqlitePool,
+    library_id: &str,
+    root: &Path,
+    entries: &[walk::MediaEntry],
+) {
+    let mut linked = std::collections::HashSet::new();
+    for entry in entries {
+        let rel = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+        // Only a series kept in a folder of its own has somewhere to put extras.
+        let Some(series_dir) = find_series_dir(rel).filter(|_| rel.components().count() > 1) else {
+            continue;
+        };
+        if linked.contains(&series_dir) {
+            continue;
+        }
+        let ParsedMedia::Episode(info) = parse_tv_entry(rel) else {
+            continue;
+        };
+        let series: Result<Option<(String,)>, _> = sqlx::query_as(
+            "SELECT id FROM item WHERE library_id = ? AND kind = 'series' AND parent_id IS NULL \
+             AND title = ? AND locked = 0",
+        )
+        .bind(library_id)
+        .bind(&info.series_title)
+        .fetch_optional(pool)
+        .await;
+        let series_id = match series {
+            Ok(Some((id,))) => id,
+            Ok(None) => continue,
+            Err(e) => {
+                warn!(series = %info.series_title, error = %e, "failed to look up series for extras");
+                continue;
+            }
+        };
+
+        let found = extras::discover_extras(&root.join(&series_dir));
+        let paths: Vec<String> = found
+            .iter()
+            .map(|e| e.path.to_string_lossy().to_string())
+            .collect();
+        let pairs: Vec<(&str, &str)> = found
+            .iter()
+            .zip(&paths)
+            .map(|(e, path)| (e.kind.as_str(), path.as_str()))
+            .collect();
+        if let Err(e) =
+            rustfin_db::repo::extras::replace_item_extras(pool, &series_id, &pairs).await
+        {
+            warn!(series = %info.series_title, error = %e, "failed to link series extras");
+        }
+        linked.insert(series_dir);
+    }
+}
+
+/// Parse a file path, relative to its library root, the way a scan of a library
+/// of `library_kind` does. `None` for kinds that are not scanned.
+pub fn parse_library_path(library_kind: &str, rel: &Path) -> Option<ParsedMedia> {
+    match library_kind {
+        "movies" => Some(parse_movie_entry(rel)),
+        "tv_shows" => Some(parse_tv_entry(rel)),
+        "mixed" => Some(parse_mixed_entry(rel)),
+        _ => None,
+    }
+}
+
 /// Parse a relative path for a movie entry.
 /// Supports: `Movie (Year)/Movie (Year).mkv` or just `Movie.Year.mkv`
 fn parse_movie_entry(rel: &Path) -> ParsedMedia {
@@ -98,7 +879,9 @@ fn parse_movie_entry(rel: &Path) -> ParsedMedia {
 }
 
 /// Parse a relative path for a TV entry.
-/// Supports: `Show Name/Season 01/S01E02.mkv` or `Show Name/S01E02.mkv`
+/// Supports: `Show Name/Season 01/S01E02.mkv` or `Show Name/S01E02.mkv`.
+/// Files without an episode pattern inside a season folder
+/// (`Show Name/Season 01/02.mkv`) take their season from the folder.
 fn parse_tv_entry(rel: &Path) -> ParsedMedia {
     let filename = rel.file_name().unwrap_or_default().to_string_lossy();
 
@@ -106,29 +889,74 @@ fn parse_tv_entry(rel: &Path) -> ParsedMedia {
 
     match parsed {
         ParsedMedia::Episode(mut ep) => {
-            // If series_title is empty, try parent directory
-            if ep.series_title.is_empty() {
-                if let Some(series_dir) = find_series_dir(rel) {
-                    ep.series_title = parser::extract_provider_ids(&series_dir)
-                        .first()
-                        .map(|_| {
-                            // Strip provider IDs from folder name
-                            let cleaned = regex::Regex::new(r"\s*\[.*?\]\s*")
-                                .unwrap()
-                                .replace_all(&series_dir, "")
-                                .trim()
-                                .to_string();
-                            cleaned
-                        })
-                        .unwrap_or_else(|| series_dir.clone());
-                    if ep.series_title.is_empty() {
-                        ep.series_title = series_dir;
-                    }
+            // Fill in what the filename lacks from the series folder
+            if let Some(series) = find_series_dir(rel).map(series_from_dir) {
+                if ep.series_title.is_empty() {
+                    ep.series_title = series.title;
+                    ep.series_year = series.year;
+                } else if ep.series_year.is_none()
+                    && ep.series_title.eq_ignore_ascii_case(&series.title)
+                {
+                    // `The Expanse (2015-2021)/The.Expanse.S01E01.mkv`
+                    ep.series_year = series.year;
                 }
             }
             ParsedMedia::Episode(ep)
         }
-        other => other,
+        other => parse_season_folder_episode(rel).unwrap_or(other),
+    }
+}
+
+/// Parse a relative path in a mixed library: an episode when the path carries a
+/// season/episode marker (`S01E02`, `1x02`, a season folder), otherwise a movie.
+fn parse_mixed_entry(rel: &Path) -> ParsedMedia {
+    match parse_tv_entry(rel) {
+        episode @ ParsedMedia::Episode(_) => episode,
+        _ => parse_movie_entry(rel),
+    }
+}
+
+/// `Show Name/Season 02/05.mkv` → S02E05 of `Show Name`.
+fn parse_season_folder_episode(rel: &Path) -> Option<ParsedMedia> {
+    let season_dir = rel.parent()?.file_name()?.to_string_lossy();
+    let season = parser::parse_season_folder(&season_dir)?;
+    let (episode, episode_title) =
+        parser::parse_leading_episode(&rel.file_name()?.to_string_lossy())?;
+    // The season folder must sit inside a series folder.
+    if rel.components().count() < 3 {
+        return None;
+    }
+    let series = series_from_dir(find_series_dir(rel)?);
+    Some(ParsedMedia::Episode(parser::EpisodeInfo {
+        series_title: series.title,
+        series_year: series.year,
+        season,
+        episode,
+        episode_title,
+    }))
+}
+
+/// Series title and years from its folder name, with `[provider=id]` tags stripped.
+fn series_from_dir(series_dir: String) -> parser::SeriesName {
+    parser::parse_series_name(&series_title_from_dir(series_dir))
+}
+
+fn series_title_from_dir(series_dir: String) -> String {
+    let cleaned = parser::extract_provider_ids(&series_dir)
+        .first()
+        .map(|_| {
+            // Strip provider IDs from folder name
+            regex::Regex::new(r"\s*\[.*?\]\s*")
+                .unwrap()
+                .replace_all(&series_dir, "")
+                .trim()
+                .to_string()
+        })
+        .unwrap_or_else(|| series_dir.clone());
+    if cleaned.is_empty() {
+        series_dir
+    } else {
+        cleaned
     }
 }
 
@@ -145,48 +973,209 @@ fn find_series_dir(rel: &Path) -> Option<String> {
 
 // ─── DB helpers ──────────────────────────────────────────────────────────────
 
-async fn file_exists(pool: &SqlitePool, path: &str) -> Result<bool, sqlx::Error> {
+async fn file_exists(conn: &mut SqliteConnection, path: &str) -> Result<bool, sqlx::Error> {
     let row: Option<(String,)> = sqlx::query_as("SELECT id FROM media_file WHERE path = ?")
         .bind(path)
-        .fetch_optional(pool)
+        .fetch_optional(conn)
         .await?;
     Ok(row.is_some())
 }
 
-async fn create_media_file(
-    pool: &SqlitePool,
+/// Record the size and mtime a local file at `path` has now. A file replaced in
+/// place keeps its row, and the new values leave its stored probe behind so the
+/// server probes it again.
+async fn update_local_file(
+    conn: &mut SqliteConnection,
     path: &str,
-    entry: &walk::MediaEntry,
-) -> Result<String, sqlx::Error> {
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-
+    size_bytes: i64,
+    mtime_ts: i64,
+) -> Result<(), sqlx::Error> {
     sqlx::query(
-        "INSERT INTO media_file (id, path, size_bytes, mtime_ts, created_ts, updated_ts) \
-         VALUES (?, ?, ?, ?, ?, ?)",
+        "UPDATE media_file SET size_bytes = ?, mtime_ts = ?, updated_ts = ? \
+         WHERE path = ? AND is_remote = 0 AND (size_bytes != ? OR mtime_ts != ?)",
     )
-    .bind(&id)
+    .bind(size_bytes)
+    .bind(mtime_ts)
+    .bind(chrono::Utc::now().timestamp())
     .bind(path)
-    .bind(entry.size_bytes as i64)
-    .bind(entry.mtime_ts)
+    .bind(size_bytes)
+    .bind(mtime_ts)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Point the `.strm` file at `path` to `url`, which may have changed since it was
+/// last scanned. A row from before `.strm` files were keyed by their own path has
+/// the URL as its path and is moved to `path`.
+async fn update_remote_file(
+    conn: &mut SqliteConnection,
+    path: &str,
+    url: &str,
+    mtime_ts: i64,
+) -> Result<(), sqlx::Error> {
+    let now = chrono::Utc::now().timestamp();
+    sqlx::query(
+        "UPDATE media_file SET remote_url = ?, mtime_ts = ?, updated_ts = ? \
+         WHERE path = ? AND is_remote = 1 AND remote_url IS NOT ?",
+    )
+    .bind(url)
+    .bind(mtime_ts)
     .bind(now)
+    .bind(path)
+    .bind(url)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "UPDATE media_file SET path = ?, mtime_ts = ?, updated_ts = ? \
+         WHERE path = ? AND is_remote = 1 AND remote_url = path \
+           AND NOT EXISTS (SELECT 1 FROM media_file WHERE path = ?)",
+    )
+    .bind(path)
+    .bind(mtime_ts)
     .bind(now)
-    .execute(pool)
+    .bind(url)
+    .bind(path)
+    .execute(&mut *conn)
     .await?;
+    Ok(())
+}
 
-    Ok(id)
+/// Insert media files and link each to its item (`episode_file_map` is reused for
+/// movie→file too), one multi-row statement per table.
+async fn insert_media_files(
+    conn: &mut SqliteConnection,
+    files: &[PendingFile<'_>],
+) -> Result<(), sqlx::Error> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let now = chrono::Utc::now().timestamp();
+    let file_ids: Vec<String> = files
+        .iter()
+        .map(|_| uuid::Uuid::new_v4().to_string())
+        .collect();
+
+    let mut insert = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO media_file (id, path, size_bytes, mtime_ts, is_remote, remote_url, \
+         resolution, source, created_ts, updated_ts) ",
+    );
+    insert.push_values(files.iter().zip(&file_ids), |mut row, (file, id)| {
+        let file_name = file
+            .entry
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let quality = parser::parse_quality(&file_name);
+        row.push_bind(id)
+            .push_bind(&file.path)
+            .push_bind(file.entry.size_bytes as i64)
+            .push_bind(file.entry.mtime_ts)
+            .push_bind(file.entry.remote_url.is_some())
+            .push_bind(&file.entry.remote_url)
+            .push_bind(quality.resolution)
+            .push_bind(quality.source)
+            .push_bind(now)
+            .push_bind(now);
+    });
+    insert.build().execute(&mut *conn).await?;
+
+    let mut link = QueryBuilder::<Sqlite>::new(
+        "INSERT INTO episode_file_map (id, episode_item_id, file_id, map_kind, created_ts) ",
+    );
+    link.push_values(files.iter().zip(&file_ids), |mut row, (file, file_id)| {
+        row.push_bind(uuid::Uuid::new_v4().to_string())
+            .push_bind(&file.item_id)
+            .push_bind(file_id)
+            .push_bind("primary")
+            .push_bind(now);
+    });
+    link.build().execute(&mut *conn).await?;
+    Ok(())
 }
 
+/// `index_number` is the season or episode number; it is also filled in on a
+/// matching item scanned before numbers were recorded.
+///
+/// The insert only happens when no matching item exists at that moment, in the
+/// same statement, so two scans of one library racing on a new item agree on
+/// a single row.
+#[allow(clippy::too_many_arguments)]
 async fn find_or_create_item(
-    pool: &SqlitePool,
+    conn: &mut SqliteConnection,
+    ids: ItemIds,
     library_id: &str,
     kind: &str,
     parent_id: Option<&str>,
     title: &str,
     year: Option<u16>,
+    index_number: Option<u32>,
 ) -> Result<String, sqlx::Error> {
-    // Try to find existing item with same title, kind, and parent
-    let existing: Option<(String,)> = if let Some(pid) = parent_id {
+    if let Some(id) = existing_item_id(conn, library_id, kind, parent_id, title).await? {
+        if let Some(index) = index_number {
+            sqlx::query(
+                "UPDATE item SET index_number = ? WHERE id = ? AND index_number IS NULL \
+                 AND locked = 0",
+            )
+            .bind(i64::from(index))
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+        }
+        return Ok(id);
+    }
+
+    let id = match ids {
+        ItemIds::Random => uuid::Uuid::new_v4().to_string(),
+        ItemIds::Deterministic => deterministic_item_id(library_id, kind, parent_id, title),
+    };
+    let now = chrono::Utc::now().timestamp();
+
+    // A derived id can already exist when the item's title was edited since; the
+    // file then joins that item.
+    let inserted = sqlx::query(
+        "INSERT INTO item (id, library_id, kind, parent_id, title, year, index_number, \
+         created_ts, updated_ts) \
+         SELECT ?, ?, ?, ?, ?, ?, ?, ?, ? \
+         WHERE NOT EXISTS (SELECT 1 FROM item \
+           WHERE library_id = ? AND kind = ? AND parent_id IS ? AND title = ?) \
+         ON CONFLICT(id) DO NOTHING",
+    )
+    .bind(&id)
+    .bind(library_id)
+    .bind(kind)
+    .bind(parent_id)
+    .bind(title)
+    .bind(year.map(|y| y as i64))
+    .bind(index_number.map(i64::from))
+    .bind(now)
+    .bind(now)
+    .bind(library_id)
+    .bind(kind)
+    .bind(parent_id)
+    .bind(title)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if inserted == 0
+        && let Some(existing) = existing_item_id(conn, library_id, kind, parent_id, title).await?
+    {
+        // Another scan created it between the lookup and the insert.
+        return Ok(existing);
+    }
+    Ok(id)
+}
+
+/// The item [`find_or_create_item`] matches: same library, kind, parent and title.
+async fn existing_item_id(
+    conn: &mut SqliteConnection,
+    library_id: &str,
+    kind: &str,
+    parent_id: Option<&str>,
+    title: &str,
+) -> Result<Option<String>, sqlx::Error> {
+    let row: Option<(String,)> = if let Some(pid) = parent_id {
         sqlx::query_as(
             "SELECT id FROM item WHERE library_id = ? AND kind = ? AND parent_id = ? AND title = ?",
         )
@@ -194,7 +1183,7 @@ async fn find_or_create_item(
         .bind(kind)
         .bind(pid)
         .bind(title)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?
     } else {
         sqlx::query_as(
@@ -203,73 +1192,84 @@ async fn find_or_create_item(
         .bind(library_id)
         .bind(kind)
         .bind(title)
-        .fetch_optional(pool)
+        .fetch_optional(&mut *conn)
         .await?
     };
+    Ok(row.map(|(id,)| id))
+}
 
-    if let Some((id,)) = existing {
-        return Ok(id);
-    }
-
-    let id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-
-    sqlx::query(
-        "INSERT INTO item (id, library_id, kind, parent_id, title, year, created_ts, updated_ts) \
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(&id)
-    .bind(library_id)
-    .bind(kind)
-    .bind(parent_id)
-    .bind(title)
-    .bind(year.map(|y| y as i64))
-    .bind(now)
-    .bind(now)
-    .execute(pool)
-    .await?;
+/// The id an item gets under [`ItemIds::Deterministic`]: a name-based (version 5
+/// style, SHA-1) UUID of the library and the fields the scanner matches items on.
+/// Parents are themselves derived, so an episode's id follows from its series,
+/// season and title, and the same tree always produces the same ids.
+pub fn deterministic_item_id(
+    library_id: &str,
+    kind: &str,
+    parent_id: Option<&str>,
+    title: &str,
+) -> String {
+    use sha1::{Digest, Sha1};
 
-    Ok(id)
+    let mut hasher = Sha1::new();
+    hasher.update(ITEM_ID_NAMESPACE.as_bytes());
+    for part in [library_id, kind, parent_id.unwrap_or(""), title] {
+        hasher.update((part.len() as u64).to_be_bytes());
+        hasher.update(part.as_bytes());
+    }
+    let hash = hasher.finalize();
+    let bytes: [u8; 16] = hash[..16].try_into().expect("SHA-1 digests are 20 bytes");
+    uuid::Builder::from_sha1_bytes(bytes)
+        .into_uuid()
+        .to_string()
 }
 
-async fn create_movie_item(
-    pool: &SqlitePool,
+/// Namespace of [`deterministic_item_id`], so its ids never match other v5 UUIDs.
+const ITEM_ID_NAMESPACE: uuid::Uuid = uuid::uuid!("5f0e6b2c-3d5a-4c1e-9a57-6e2b1d0c8f43");
+
+async fn movie_item(
+    conn: &mut SqliteConnection,
     library_id: &str,
     info: &parser::MovieInfo,
-    file_path: &str,
-    entry: &walk::MediaEntry,
-) -> Result<(), sqlx::Error> {
-    let item_id =
-        find_or_create_item(pool, library_id, "movie", None, &info.title, info.year).await?;
-    let file_id = create_media_file(pool, file_path, entry).await?;
-
-    // Link file to item via episode_file_map (reused for movie→file too)
-    let map_id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-    sqlx::query(
-        "INSERT INTO episode_file_map (id, episode_item_id, file_id, map_kind, created_ts) \
-         VALUES (?, ?, ?, 'primary', ?)",
+    ids: ItemIds,
+) -> Result<String, sqlx::Error> {
+    find_or_create_item(
+        conn,
+        ids,
+        library_id,
+        "movie",
+        None,
+        &info.title,
+        info.year,
+        None,
     )
-    .bind(&map_id)
-    .bind(&item_id)
-    .bind(&file_id)
-    .bind(now)
-    .execute(pool)
-    .await?;
-
-    Ok(())
+    .await
 }
 
-async fn create_episode_item(
-    pool: &SqlitePool,
+/// Find or create the series, season and episode items for an episode file.
+async fn episode_item(
+    conn: &mut SqliteConnection,
     library_id: &str,
     info: &parser::EpisodeInfo,
-    file_path: &str,
-    entry: &walk::MediaEntry,
-) -> Result<(), sqlx::Error> {
+    policy: ItemPolicy,
+) -> Result<String, sqlx::Error> {
+    let ids = policy.ids;
     // Create or find series
-    let series_id =
-        find_or_create_item(pool, library_id, "series", None, &info.series_title, None).await?;
+    let series_title = if info.season == 0 && policy.specials == SpecialsPolicy::Separate {
+        format!("{} Specials", info.series_title)
+    } else {
+        info.series_title.clone()
+    };
+    let series_id = find_or_create_item(
+        conn,
+        ids,
+        library_id,
+        "series",
+        None,
+        &series_title,
+        info.series_year,
+        None,
+    )
+    .await?;
 
     // Create or find season
     let season_title = if info.season == 0 {
@@ -278,56 +1278,200 @@ async fn create_episode_item(
         format!("Season {}", info.season)
     };
     let season_id = find_or_create_item(
-        pool,
+        conn,
+        ids,
         library_id,
         "season",
         Some(&series_id),
         &season_title,
         None,
+        Some(info.season),
     )
     .await?;
 
-    // Create episode
+    // Create or find episode
     let ep_title = info
         .episode_title
         .clone()
         .unwrap_or_else(|| format!("Episode {}", info.episode));
-    let episode_id = find_or_create_item(
-        pool,
+    find_or_create_item(
+        conn,
+        ids,
         library_id,
         "episode",
         Some(&season_id),
         &ep_title,
         None,
+        Some(info.episode),
     )
-    .await?;
+    .await
+}
 
-    // Create media file
-    let file_id = create_media_file(pool, file_path, entry).await?;
+// ─── Types ───────────────────────────────────────────────────────────────────
 
-    // Link file to episode
-    let map_id = uuid::Uuid::new_v4().to_string();
-    let now = chrono::Utc::now().timestamp();
-    sqlx::query(
-        "INSERT INTO episode_file_map (id, episode_item_id, file_id, map_kind, created_ts) \
-         VALUES (?, ?, ?, 'primary', ?)",
-    )
-    .bind(&map_id)
-    .bind(&episode_id)
-    .bind(&file_id)
-    .bind(now)
-    .execute(pool)
-    .await?;
+/// Parallelism, batching and probing for a full library scan.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Library paths walked at the same time.
+    pub parallelism: usize,
+    /// Files added per database transaction; `1` adds each file on its own.
+    pub batch_size: usize,
+    /// Probes new files' durations for sample detection; without one, the
+    /// library's `sample_max_duration_secs` has no effect.
+    pub duration_probe: Option<DurationProbe>,
+}
 
-    Ok(())
+/// What a [`DurationProbe`] read from a media file.
+#[derive(Debug, Clone)]
+pub struct ProbedFile {
+    /// Seconds; `None` when the file reports no duration.
+    pub duration_secs: Option<f64>,
+    /// The whole probe result, recorded in `media_file_probe` for later passes.
+    pub info_json: String,
 }
 
-// ─── Types ───────────────────────────────────────────────────────────────────
+type DurationFuture = Pin<Box<dyn Future<Output = Option<ProbedFile>> + Send>>;
+
+/// Probes a media file for its duration; `None` when it can't be probed.
+#[derive(Clone)]
+pub struct DurationProbe(Arc<dyn Fn(PathBuf) -> DurationFuture + Send + Sync>);
+
+impl DurationProbe {
+    pub fn new<F, Fut>(probe: F) -> Self
+    where
+        F: Fn(PathBuf) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Option<ProbedFile>> + Send + 'static,
+    {
+        Self(Arc::new(move |path| Box::pin(probe(path))))
+    }
+}
+
+impl std::fmt::Debug for DurationProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DurationProbe")
+    }
+}
+
+impl ScanOptions {
+    /// Largest batch: keeps a multi-row insert well under SQLite's bound-parameter limit.
+    pub const MAX_BATCH_SIZE: usize = 1_000;
+
+    /// Defaults, overridden by `RUSTFIN_SCAN_PARALLELISM` / `RUSTFIN_SCAN_BATCH_SIZE`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            parallelism: read("RUSTFIN_SCAN_PARALLELISM", defaults.parallelism),
+            batch_size: read("RUSTFIN_SCAN_BATCH_SIZE", defaults.batch_size)
+                .min(Self::MAX_BATCH_SIZE),
+            duration_probe: None,
+        }
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            parallelism: 4,
+            batch_size: 200,
+            duration_probe: None,
+        }
+    }
+}
+
+/// The library settings that decide which items a file becomes part of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ItemPolicy {
+    specials: SpecialsPolicy,
+    ids: ItemIds,
+}
+
+impl ItemPolicy {
+    async fn for_library(pool: &SqlitePool, library_id: &str) -> Result<Self, ScanError> {
+        Ok(Self {
+            specials: SpecialsPolicy::for_library(pool, library_id).await?,
+            ids: ItemIds::for_library(pool, library_id).await?,
+        })
+    }
+}
+
+/// How new items get their ids, from the library's `deterministic_ids`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemIds {
+    /// A random (v4) UUID.
+    #[default]
+    Random,
+    /// [`deterministic_item_id`], so rebuilding the database from the same tree
+    /// keeps the ids clients have saved.
+    Deterministic,
+}
+
+impl ItemIds {
+    pub async fn for_library(pool: &SqlitePool, library_id: &str) -> Result<Self, ScanError> {
+        let enabled = rustfin_db::repo::libraries::get_library_deterministic_ids(pool, library_id)
+            .await
+            .map_err(ScanError::Db)?;
+        Ok(if enabled {
+            Self::Deterministic
+        } else {
+            Self::Random
+        })
+    }
+}
+
+/// How a TV library imports season 0 files, from the library's `specials_policy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpecialsPolicy {
+    /// A "Specials" season inside the series.
+    #[default]
+    Season,
+    /// Not imported; counted as skipped.
+    Skip,
+    /// A "Specials" season of a series of their own, `<Series> Specials`, so the
+    /// main series only lists regular seasons.
+    Separate,
+}
+
+impl SpecialsPolicy {
+    pub const ALL: [Self; 3] = [Self::Season, Self::Skip, Self::Separate];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Season => "season",
+            Self::Skip => "skip",
+            Self::Separate => "separate",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(value.trim()))
+    }
+
+    /// The library's stored policy; unset or unrecognised values mean [`Self::Season`].
+    pub async fn for_library(pool: &SqlitePool, library_id: &str) -> Result<Self, ScanError> {
+        let stored = rustfin_db::repo::libraries::get_library_specials_policy(pool, library_id)
+            .await
+            .map_err(ScanError::Db)?;
+        Ok(stored.as_deref().and_then(Self::parse).unwrap_or_default())
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct ScanResult {
     pub added: usize,
     pub skipped: usize,
+    /// Files or directories that could not be scanned; see `scan_error`.
+    pub errors: usize,
+    /// Items removed because their files are gone.
+    pub removed: usize,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -336,4 +1480,120 @@ pub enum ScanError {
     Db(sqlx::Error),
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Walk(#[from] walk::WalkError),
+    #[error("library path {path} is not accessible: {source}")]
+    RootUnavailable {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode(rel: &str) -> (String, u32, u32) {
+        match parse_tv_entry(Path::new(rel)) {
+            ParsedMedia::Episode(ep) => (ep.series_title, ep.season, ep.episode),
+            other => panic!("expected an episode for {rel}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn deterministic_ids_depend_on_every_matched_field() {
+        let id = deterministic_item_id("lib", "episode", Some("season"), "Pilot");
+        assert_eq!(
+            id,
+            deterministic_item_id("lib", "episode", Some("season"), "Pilot")
+        );
+        assert_eq!(uuid::Uuid::parse_str(&id).unwrap().get_version_num(), 5);
+        for other in [
+            deterministic_item_id("other", "episode", Some("season"), "Pilot"),
+            deterministic_item_id("lib", "movie", Some("season"), "Pilot"),
+            deterministic_item_id("lib", "episode", None, "Pilot"),
+            deterministic_item_id("lib", "episode", Some("season"), "Pilot 2"),
+            deterministic_item_id("lib", "episode", Some("seasonPilot"), ""),
+        ] {
+            assert_ne!(id, other);
+        }
+    }
+
+    #[test]
+    fn playlist_entries_resolve_against_the_playlist_folder() {
+        let playlist = Path::new("/srv/media/Lists/mix.m3u");
+        assert_eq!(
+            resolve_playlist_entry(playlist, "../Movies/Heat (1995).mkv"),
+            "/srv/media/Movies/Heat (1995).mkv"
+        );
+        assert_eq!(
+            resolve_playlist_entry(playlist, "./Extras\\clip.mkv"),
+            "/srv/media/Lists/Extras/clip.mkv"
+        );
+        assert_eq!(resolve_playlist_entry(playlist, "/mnt/b.mkv"), "/mnt/b.mkv");
+        assert_eq!(
+            resolve_playlist_entry(playlist, "https://cdn.example.com/a.mp4"),
+            "https://cdn.example.com/a.mp4"
+        );
+    }
+
+    #[test]
+    fn season_folder_supplies_missing_episode_pattern() {
+        assert_eq!(episode("Show/Season 02/05.mkv"), ("Show".into(), 2, 5));
+        assert_eq!(episode("Show/Specials/03.mkv"), ("Show".into(), 0, 3));
+        assert_eq!(
+            episode("Show [tvdb=123]/Season 1/01 - Pilot.mkv"),
+            ("Show".into(), 1, 1)
+        );
+    }
+
+    #[test]
+    fn localized_season_folders_supply_the_season() {
+        assert_eq!(episode("Dark/Staffel 02/E05.mkv"), ("Dark".into(), 2, 5));
+        assert_eq!(
+            episode("Lupin/Saison 3/Episode 4 - Le Pardon.mkv"),
+            ("Lupin".into(), 3, 4)
+        );
+        assert_eq!(
+            episode("Taskmaster/S1/E02.mkv"),
+            ("Taskmaster".into(), 1, 2)
+        );
+        assert_eq!(
+            episode("Sherlock/Series 1/03.mkv"),
+            ("Sherlock".into(), 1, 3)
+        );
+    }
+
+    #[test]
+    fn series_folder_years_are_split_from_the_title() {
+        let parsed = |rel: &str| match parse_tv_entry(Path::new(rel)) {
+            ParsedMedia::Episode(ep) => (ep.series_title, ep.series_year),
+            other => panic!("expected an episode for {rel}, got {other:?}"),
+        };
+        assert_eq!(
+            parsed("The Expanse (2015-2021)/Season 01/01 - Dulcinea.mkv"),
+            ("The Expanse".into(), Some(2015))
+        );
+        assert_eq!(
+            parsed("The Expanse (2015-2021)/Season 01/The.Expanse.S01E02.mkv"),
+            ("The Expanse".into(), Some(2015))
+        );
+        assert_eq!(
+            parsed("Friends (1994)/S01E01.mkv"),
+            ("Friends".into(), Some(1994))
+        );
+    }
+
+    #[test]
+    fn explicit_episode_pattern_wins_over_season_folder() {
+        assert_eq!(episode("Show/Season 02/S03E07.mkv"), ("Show".into(), 3, 7));
+    }
+
+    #[test]
+    fn bare_number_outside_season_folder_is_not_an_episode() {
+        assert!(matches!(
+            parse_tv_entry(Path::new("Show/Extras/05.mkv")),
+            ParsedMedia::Movie(_)
+        ));
+    }
 }
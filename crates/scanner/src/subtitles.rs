@@ -3,6 +3,7 @@
 //! Naming conventions:
 //! - `Movie.en.srt`          → language "en"
 //! - `Movie.en.forced.srt`   → language "en", forced
+//! - `Movie.eng.srt`         → language "en" (ISO 639-2 codes are normalized)
 //! - `Movie.srt`             → unknown language
 //! - `Movie.en.hi.srt`       → language "en", hearing impaired
 //!
@@ -10,6 +11,7 @@
 
 use std::path::{Path, PathBuf};
 
+use rustfin_core::language;
 use serde::{Deserialize, Serialize};
 
 /// A discovered sidecar subtitle file.
@@ -58,27 +60,39 @@ impl SubtitleFormat {
             Self::Idx => "text/plain",
         }
     }
+
+    /// Whether the format is plain text that can be re-encoded for serving.
+    /// `.sup` and `.idx` belong to bitmap subtitle tracks and are left alone.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            Self::Srt | Self::Sub | Self::Ass | Self::Ssa | Self::Vtt
+        )
+    }
 }
 
-/// ISO 639-1 two-letter language codes (common subset for validation).
-const LANG_CODES: &[&str] = &[
-    "aa", "ab", "af", "ak", "am", "an", "ar", "as", "av", "ay", "az", "ba", "be", "bg", "bh", "bi",
-    "bm", "bn", "bo", "br", "bs", "ca", "ce", "ch", "co", "cr", "cs", "cu", "cv", "cy", "da", "de",
-    "dv", "dz", "ee", "el", "en", "eo", "es", "et", "eu", "fa", "ff", "fi", "fj", "fo", "fr", "fy",
-    "ga", "gd", "gl", "gn", "gu", "gv", "ha", "he", "hi", "ho", "hr", "ht", "hu", "hy", "hz", "ia",
-    "id", "ie", "ig", "ii", "ik", "in", "io", "is", "it", "iu", "ja", "jv", "ka", "kg", "ki", "kj",
-    "kk", "kl", "km", "kn", "ko", "kr", "ks", "ku", "kv", "kw", "ky", "la", "lb", "lg", "li", "ln",
-    "lo", "lt", "lu", "lv", "mg", "mh", "mi", "mk", "ml", "mn", "mr", "ms", "mt", "my", "na", "nb",
-    "nd", "ne", "ng", "nl", "nn", "no", "nr", "nv", "ny", "oc", "oj", "om", "or", "os", "pa", "pi",
-    "pl", "ps", "pt", "qu", "rm", "rn", "ro", "ru", "rw", "sa", "sc", "sd", "se", "sg", "si", "sk",
-    "sl", "sm", "sn", "so", "sq", "sr", "ss", "st", "su", "sv", "sw", "ta", "te", "tg", "th", "ti",
-    "tk", "tl", "tn", "to", "tr", "ts", "tt", "tw", "ty", "ug", "uk", "ur", "uz", "ve", "vi", "vo",
-    "wa", "wo", "xh", "yi", "yo", "za", "zh", "zu",
-];
-
-fn is_lang_code(s: &str) -> bool {
-    let lower = s.to_ascii_lowercase();
-    LANG_CODES.contains(&lower.as_str()) || lower.len() == 3 // also accept ISO 639-2 (3-letter)
+/// Decode a text subtitle file to UTF-8 with `\n` line endings.
+///
+/// A UTF-8 BOM is stripped and UTF-16 BOMs are honoured; bytes that are not valid
+/// UTF-8 are treated as Windows-1252, the usual encoding of legacy SRT files.
+/// Returns `None` if the data looks binary (e.g. a VobSub `.sub`).
+pub fn decode_text_subtitle(bytes: &[u8]) -> Option<String> {
+    let text = if let Some((encoding, bom_len)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        text.into_owned()
+    } else if bytes.contains(&0) {
+        return None;
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => {
+                let (text, _) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(bytes);
+                text.into_owned()
+            }
+        }
+    };
+
+    Some(text.replace("\r\n", "\n").replace('\r', "\n"))
 }
 
 /// Parse subtitle metadata from filename parts.
@@ -107,8 +121,8 @@ fn parse_sub_markers(media_stem: &str, sub_stem: &str) -> (Option<String>, bool,
             forced = true;
         } else if lower == "sdh" || lower == "hi" || lower == "cc" {
             sdh = true;
-        } else if is_lang_code(part) && language.is_none() {
-            language = Some(lower);
+        } else if language.is_none() {
+            language = language::normalize(&lower);
         }
     }
 
@@ -178,6 +192,39 @@ pub fn discover_sidecars(media_path: &Path) -> Vec<SidecarSubtitle> {
     results
 }
 
+/// Where a sidecar for `media_path` goes so [`discover_sidecars`] reads back the
+/// same markers: `Movie.en.srt`, `Movie.en.forced.srt`, `Movie.en.sdh.srt`.
+///
+/// Only the primary subtag of `language` is used (`pt-BR` becomes `pt`, `eng`
+/// becomes `en`); returns `None` if that is not a language code.
+pub fn sidecar_path(
+    media_path: &Path,
+    language: &str,
+    forced: bool,
+    sdh: bool,
+    format: SubtitleFormat,
+) -> Option<PathBuf> {
+    let stem = media_path.file_stem()?.to_str()?;
+    let language = language::normalize(language.split(['-', '_']).next().unwrap_or_default())?;
+    let mut name = format!("{stem}.{language}");
+    if forced {
+        name.push_str(".forced");
+    }
+    if sdh {
+        name.push_str(".sdh");
+    }
+    let ext = match format {
+        SubtitleFormat::Srt => "srt",
+        SubtitleFormat::Sub => "sub",
+        SubtitleFormat::Ass => "ass",
+        SubtitleFormat::Ssa => "ssa",
+        SubtitleFormat::Vtt => "vtt",
+        SubtitleFormat::Sup => "sup",
+        SubtitleFormat::Idx => "idx",
+    };
+    Some(media_path.with_file_name(format!("{name}.{ext}")))
+}
+
 fn build_title(language: &Option<String>, forced: bool, sdh: bool) -> String {
     let mut parts = Vec::new();
     if let Some(lang) = language {
@@ -199,6 +246,39 @@ mod tests {
     use super::*;
     use std::fs;
 
+    #[test]
+    fn decode_strips_utf8_bom_and_normalizes_newlines() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(b"1\r\n00:00:01,000 --> 00:00:02,000\r\nLine one\r\nLine two\r\n");
+        assert_eq!(
+            decode_text_subtitle(&bytes).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nLine one\nLine two\n"
+        );
+    }
+
+    #[test]
+    fn decode_converts_windows_1252() {
+        // "Café – déjà vu" in Windows-1252 (0x96 is an en dash).
+        let bytes = b"1\n00:00:01,000 --> 00:00:02,000\nCaf\xE9 \x96 d\xE9j\xE0 vu\n";
+        assert_eq!(
+            decode_text_subtitle(bytes).unwrap(),
+            "1\n00:00:01,000 --> 00:00:02,000\nCafé – déjà vu\n"
+        );
+    }
+
+    #[test]
+    fn decode_handles_utf16_and_rejects_binary() {
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in "Hallo Welt".encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        assert_eq!(decode_text_subtitle(&utf16).unwrap(), "Hallo Welt");
+        assert_eq!(decode_text_subtitle(&[0x00, 0x00, 0x01, 0xBA, 0x44]), None);
+        assert!(SubtitleFormat::Srt.is_text());
+        assert!(!SubtitleFormat::Sup.is_text());
+        assert!(!SubtitleFormat::Idx.is_text());
+    }
+
     #[test]
     fn subtitle_format_detection() {
         assert_eq!(
@@ -256,6 +336,44 @@ mod tests {
         assert!(!sdh);
     }
 
+    #[test]
+    fn parse_markers_normalizes_iso_639_2() {
+        let (lang, forced, _) = parse_sub_markers("Movie.2020", "Movie.2020.eng.forced");
+        assert_eq!(lang.as_deref(), Some("en"));
+        assert!(forced);
+        let (lang, _, _) = parse_sub_markers("Movie.2020", "Movie.2020.xyz");
+        assert!(lang.is_none());
+        let (lang, _, _) = parse_sub_markers("Movie.2020", "Movie.2020.xyz.es");
+        assert_eq!(lang.as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn sidecar_path_round_trips_through_discovery() {
+        let media = Path::new("/media/Arrival (2016)/Arrival (2016).mkv");
+        let path = sidecar_path(media, "en", false, false, SubtitleFormat::Srt).unwrap();
+        assert_eq!(
+            path,
+            Path::new("/media/Arrival (2016)/Arrival (2016).en.srt")
+        );
+        let path = sidecar_path(media, "pt-BR", true, true, SubtitleFormat::Ass).unwrap();
+        assert_eq!(
+            path,
+            Path::new("/media/Arrival (2016)/Arrival (2016).pt.forced.sdh.ass")
+        );
+        assert_eq!(
+            parse_sub_markers("Arrival (2016)", "Arrival (2016).pt.forced.sdh"),
+            (Some("pt".into()), true, true)
+        );
+        let path = sidecar_path(media, "eng", false, false, SubtitleFormat::Srt).unwrap();
+        assert_eq!(
+            path,
+            Path::new("/media/Arrival (2016)/Arrival (2016).en.srt")
+        );
+        assert!(sidecar_path(media, "xyz", false, false, SubtitleFormat::Srt).is_none());
+        assert!(sidecar_path(media, "../x", false, false, SubtitleFormat::Srt).is_none());
+        assert!(sidecar_path(media, "", false, false, SubtitleFormat::Srt).is_none());
+    }
+
     #[test]
     fn discover_sidecars_finds_subtitles() {
         let tmp = std::env::temp_dir().join(format!("rf_sub_test_{}", std::process::id()));
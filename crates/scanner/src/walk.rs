@@ -1,7 +1,8 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::parser;
+use crate::{extras, parser};
 
 static SKIP_DIR_NAMES: &[&str] = &[
     ".git",
@@ -21,25 +22,196 @@ pub struct MediaEntry {
     pub path: PathBuf,
     pub size_bytes: u64,
     pub mtime_ts: i64,
+    /// For `.strm` files, the remote URL the file points at.
+    pub remote_url: Option<String>,
+}
+
+/// A file or directory below the root that could not be read.
+#[derive(Debug, Clone)]
+pub struct WalkFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// An `.m3u` / `.m3u8` file discovered during a walk.
+#[derive(Debug, Clone)]
+pub struct PlaylistFile {
+    pub path: PathBuf,
+    pub mtime_ts: i64,
+}
+
+/// Media found by a walk, plus the paths it had to skip because of I/O errors.
+#[derive(Debug, Default)]
+pub struct WalkOutput {
+    pub entries: Vec<MediaEntry>,
+    pub failures: Vec<WalkFailure>,
+    pub playlists: Vec<PlaylistFile>,
+}
+
+/// `.strm` files are a single URL; anything bigger is not one.
+const MAX_STRM_BYTES: u64 = 8 * 1024;
+
+/// Safety limits for a single walk, so a library pointed at `/` or a home
+/// directory cannot hang the scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalkLimits {
+    /// Directories nested deeper than this below the root are not descended into.
+    pub max_depth: usize,
+    /// The walk aborts once this many filesystem entries have been visited.
+    pub max_files: usize,
+}
+
+impl Default for WalkLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 32,
+            max_files: 500_000,
+        }
+    }
+}
+
+impl WalkLimits {
+    /// Defaults, overridden by `RUSTFIN_SCAN_MAX_DEPTH` / `RUSTFIN_SCAN_MAX_FILES`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let read = |name: &str, default: usize| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            max_depth: read("RUSTFIN_SCAN_MAX_DEPTH", defaults.max_depth),
+            max_files: read("RUSTFIN_SCAN_MAX_FILES", defaults.max_files),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WalkError {
+    #[error("walk of {root} aborted after visiting more than {limit} entries")]
+    TooManyFiles { root: PathBuf, limit: usize },
+}
+
+struct WalkState<'a> {
+    /// The directory the walk started at, reported when it is aborted.
+    root: &'a Path,
+    limits: &'a WalkLimits,
+    visited_entries: usize,
+    /// Canonical paths of directories already walked, to break symlink loops.
+    visited_dirs: HashSet<PathBuf>,
+    output: WalkOutput,
 }
 
 /// Walk a directory recursively and collect video files, skipping ignored patterns.
-pub fn walk_media_dir(root: &Path) -> Vec<MediaEntry> {
-    let mut entries = Vec::new();
-    walk_recursive(root, &mut entries);
-    entries
+///
+/// Directories deeper than `limits.max_depth` are skipped with a warning; exceeding
+/// `limits.max_files` aborts the walk with an error.
+pub fn walk_media_dir(root: &Path, limits: &WalkLimits) -> Result<Vec<MediaEntry>, WalkError> {
+    walk_media_dir_with_failures(root, limits).map(|output| output.entries)
+}
+
+/// Like [`walk_media_dir`], but also reports files and directories that could not
+/// be read instead of only logging them.
+pub fn walk_media_dir_with_failures(
+    root: &Path,
+    limits: &WalkLimits,
+) -> Result<WalkOutput, WalkError> {
+    let mut state = WalkState {
+        root,
+        limits,
+        visited_entries: 0,
+        visited_dirs: HashSet::new(),
+        output: WalkOutput::default(),
+    };
+    if let Err(e) = walk_recursive(root, 0, &mut state) {
+        warn!(path = %root.display(), error = %e, "aborting directory walk");
+        return Err(e);
+    }
+    Ok(state.output)
+}
+
+/// Build the entry for a single video or `.strm` file.
+///
+/// Returns `Ok(None)` for files that are not media, or `.strm` files without a
+/// usable URL.
+pub fn media_entry(path: &Path) -> std::io::Result<Option<MediaEntry>> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let is_strm = parser::is_strm_file(&name);
+    if (!is_strm && !parser::is_video_file(&name)) || extras::is_theme_file(&name) {
+        return Ok(None);
+    }
+
+    let metadata = std::fs::metadata(path)?;
+    let mtime = mtime_ts(&metadata);
+
+    if is_strm {
+        let url = (metadata.len() <= MAX_STRM_BYTES)
+            .then(|| std::fs::read_to_string(path).ok())
+            .flatten()
+            .and_then(|contents| parser::parse_strm_url(&contents));
+        let Some(url) = url else {
+            warn!(path = %path.display(), "skipping .strm file without a valid http(s) URL");
+            return Ok(None);
+        };
+        return Ok(Some(MediaEntry {
+            path: path.to_path_buf(),
+            size_bytes: 0,
+            mtime_ts: mtime,
+            remote_url: Some(url),
+        }));
+    }
+
+    Ok(Some(MediaEntry {
+        path: path.to_path_buf(),
+        size_bytes: metadata.len(),
+        mtime_ts: mtime,
+        remote_url: None,
+    }))
 }
 
-fn walk_recursive(dir: &Path, entries: &mut Vec<MediaEntry>) {
+/// A file's mtime in whole unix seconds, as stored in `media_file.mtime_ts`.
+pub fn mtime_ts(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn walk_recursive(dir: &Path, depth: usize, state: &mut WalkState<'_>) -> Result<(), WalkError> {
+    let canonical = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    if !state.visited_dirs.insert(canonical) {
+        debug!(path = %dir.display(), "skipping already visited directory (symlink loop?)");
+        return Ok(());
+    }
+
     let read_dir = match std::fs::read_dir(dir) {
         Ok(rd) => rd,
         Err(e) => {
             tracing::warn!(path = %dir.display(), error = %e, "cannot read directory");
-            return;
+            state.output.failures.push(WalkFailure {
+                path: dir.to_path_buf(),
+                error: e.to_string(),
+            });
+            return Ok(());
         }
     };
 
     for entry in read_dir.flatten() {
+        state.visited_entries += 1;
+        if state.visited_entries > state.limits.max_files {
+            return Err(WalkError::TooManyFiles {
+                root: state.root.to_path_buf(),
+                limit: state.limits.max_files,
+            });
+        }
+
         let path = entry.path();
         let file_name = entry.file_name();
         let name = file_name.to_string_lossy();
@@ -60,24 +232,129 @@ fn walk_recursive(dir: &Path, entries: &mut Vec<MediaEntry>) {
             if name == "@eaDir" || name == "#recycle" || name == ".Trash" {
                 continue;
             }
-            walk_recursive(&path, entries);
-        } else if parser::is_video_file(&name) {
-            let metadata = match std::fs::metadata(&path) {
-                Ok(m) => m,
-                Err(_) => continue,
-            };
-            let mtime = metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs() as i64)
-                .unwrap_or(0);
-
-            entries.push(MediaEntry {
-                path,
-                size_bytes: metadata.len(),
-                mtime_ts: mtime,
-            });
+            // Backdrop folders belong to their parent item; see `extras`.
+            if extras::is_extras_dir(&name) {
+                continue;
+            }
+            if depth >= state.limits.max_depth {
+                warn!(
+                    path = %path.display(),
+                    max_depth = state.limits.max_depth,
+                    "directory exceeds maximum scan depth, skipping"
+                );
+                continue;
+            }
+            walk_recursive(&path, depth + 1, state)?;
+        } else if extras::is_theme_file(&name) {
+            debug!(path = %path.display(), "skipping theme file");
+        } else if parser::is_video_file(&name) || parser::is_strm_file(&name) {
+            match media_entry(&path) {
+                Ok(Some(entry)) => state.output.entries.push(entry),
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "cannot read media file");
+                    state.output.failures.push(WalkFailure {
+                        path,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        } else if parser::is_playlist_file(&name) {
+            match std::fs::metadata(&path) {
+                Ok(metadata) => state.output.playlists.push(PlaylistFile {
+                    mtime_ts: mtime_ts(&metadata),
+                    path,
+                }),
+                Err(e) => warn!(path = %path.display(), error = %e, "cannot read playlist file"),
+            }
         }
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rf_walk_{name}_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn walk_truncates_beyond_max_depth() {
+        let root = temp_root("depth");
+        let mut dir = root.clone();
+        for level in 0..6 {
+            std::fs::write(dir.join(format!("Movie {level} (2000).mkv")), b"x").unwrap();
+            dir = dir.join(format!("level{level}"));
+            std::fs::create_dir_all(&dir).unwrap();
+        }
+
+        let limits = WalkLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+        let mut names: Vec<String> = walk_media_dir(&root, &limits)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "Movie 0 (2000).mkv",
+                "Movie 1 (2000).mkv",
+                "Movie 2 (2000).mkv"
+            ]
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn walk_aborts_when_file_limit_exceeded() {
+        let root = temp_root("count");
+        std::fs::create_dir_all(root.join("Movies")).unwrap();
+        for i in 0..5 {
+            std::fs::write(root.join(format!("Movies/Movie {i} (2000).mkv")), b"x").unwrap();
+        }
+
+        let limits = WalkLimits {
+            max_files: 3,
+            ..Default::default()
+        };
+        // The library root is reported, not the subdirectory the limit was hit in.
+        assert!(matches!(
+            walk_media_dir(&root, &limits),
+            Err(WalkError::TooManyFiles { root: ref r, limit: 3 }) if *r == root
+        ));
+        assert_eq!(
+            walk_media_dir(&root, &WalkLimits::default()).unwrap().len(),
+            5
+        );
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_survives_symlink_cycle() {
+        let root = temp_root("cycle");
+        let sub = root.join("Films");
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(sub.join("Heat (1995).mkv"), b"x").unwrap();
+        std::os::unix::fs::symlink(&root, sub.join("loop")).unwrap();
+
+        let limits = WalkLimits {
+            max_depth: 1000,
+            ..Default::default()
+        };
+        let entries = walk_media_dir(&root, &limits).unwrap();
+        assert_eq!(entries.len(), 1);
+
+        std::fs::remove_dir_all(&root).ok();
+    }
 }
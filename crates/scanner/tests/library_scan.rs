@@ -0,0 +1,615 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use rustfin_scanner::scan::{DurationProbe, ProbedFile, ScanOptions, run_library_scan_with};
+
+fn touch(path: PathBuf) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, b"fake").unwrap();
+}
+
+/// `(kind, parent title, title)` for every item, plus the number of media files.
+async fn library_contents(pool: &sqlx::SqlitePool) -> (Vec<(String, String, String)>, i64) {
+    let mut items: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT i.kind, COALESCE(p.title, ''), i.title FROM item i \
+         LEFT JOIN item p ON p.id = i.parent_id",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    items.sort();
+    let (files,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM media_file f JOIN episode_file_map m ON m.file_id = f.id",
+    )
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    (items, files)
+}
+
+async fn scan(paths: &[&Path], options: ScanOptions) -> (Vec<(String, String, String)>, i64) {
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|p| p.to_string_lossy().to_string())
+        .collect();
+    let lib = rustfin_db::repo::libraries::create_library(&pool, "TV", "tv_shows", &paths)
+        .await
+        .unwrap();
+    let result = run_library_scan_with(&pool, &lib.id, "tv_shows", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.errors, 0);
+    library_contents(&pool).await
+}
+
+#[tokio::test]
+async fn batched_parallel_scan_matches_one_by_one_scan() {
+    let tmp = std::env::temp_dir().join(format!("rf_multi_path_{}", uuid::Uuid::new_v4()));
+    let (disk_a, disk_b) = (tmp.join("a"), tmp.join("b"));
+    for episode in 1..=5 {
+        touch(disk_a.join(format!("Lost/Season 01/Lost.S01E0{episode}.mkv")));
+    }
+    touch(disk_a.join("Fringe/Season 01/Fringe.S01E01.mkv"));
+    // The same series continues on the second disk.
+    touch(disk_b.join("Lost/Season 02/Lost.S02E01.mkv"));
+    touch(disk_b.join("Lost/Season 02/Lost.S02E02.mkv"));
+    touch(disk_b.join("Dark/Season 01/Dark.S01E01.mkv"));
+
+    let one_by_one = scan(
+        &[&disk_a, &disk_b],
+        ScanOptions {
+            parallelism: 1,
+            batch_size: 1,
+            ..ScanOptions::default()
+        },
+    )
+    .await;
+    let batched = scan(
+        &[&disk_a, &disk_b],
+        ScanOptions {
+            parallelism: 2,
+            batch_size: 3,
+            ..ScanOptions::default()
+        },
+    )
+    .await;
+
+    // Both paths were scanned and "Lost" was created once.
+    assert_eq!(one_by_one.1, 9);
+    let series: Vec<_> = one_by_one
+        .0
+        .iter()
+        .filter(|(kind, _, _)| kind == "series")
+        .map(|(_, _, title)| title.as_str())
+        .collect();
+    assert_eq!(series, ["Dark", "Fringe", "Lost"]);
+    assert_eq!(batched, one_by_one);
+
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+/// Scan a series with one regular episode and one special under `policy`.
+async fn scan_with_specials(
+    policy: Option<&str>,
+) -> (
+    Vec<(String, String, String)>,
+    rustfin_scanner::scan::ScanResult,
+) {
+    let tmp = std::env::temp_dir().join(format!("rf_specials_{}", uuid::Uuid::new_v4()));
+    touch(tmp.join("Doctor Who/Season 01/Doctor.Who.S01E01.mkv"));
+    touch(tmp.join("Doctor Who/Specials/Doctor.Who.S00E01.mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_db::repo::libraries::set_library_specials_policy(&pool, &lib.id, policy)
+        .await
+        .unwrap();
+    let result = run_library_scan_with(&pool, &lib.id, "tv_shows", &ScanOptions::default())
+        .await
+        .unwrap();
+    let (items, _) = library_contents(&pool).await;
+    std::fs::remove_dir_all(&tmp).ok();
+    (items, result)
+}
+
+#[tokio::test]
+async fn specials_are_skipped_when_disabled() {
+    let (items, result) = scan_with_specials(Some("skip")).await;
+    assert_eq!((result.added, result.skipped), (1, 1));
+    assert!(
+        items.iter().all(|(_, _, title)| title != "Specials"),
+        "no specials season: {items:?}"
+    );
+}
+
+#[tokio::test]
+async fn specials_are_imported_as_season_zero_by_default() {
+    for policy in [None, Some("season")] {
+        let (items, result) = scan_with_specials(policy).await;
+        assert_eq!((result.added, result.skipped), (2, 0));
+        assert!(items.contains(&(
+            "season".to_string(),
+            "Doctor Who".to_string(),
+            "Specials".to_string()
+        )));
+    }
+
+    let (items, result) = scan_with_specials(Some("separate")).await;
+    assert_eq!(result.added, 2);
+    assert!(items.contains(&(
+        "season".to_string(),
+        "Doctor Who Specials".to_string(),
+        "Specials".to_string()
+    )));
+    assert!(!items.contains(&(
+        "season".to_string(),
+        "Doctor Who".to_string(),
+        "Specials".to_string()
+    )));
+}
+
+#[tokio::test]
+async fn mixed_library_sorts_movies_from_episodes_by_path() {
+    let tmp = std::env::temp_dir().join(format!("rf_mixed_{}", uuid::Uuid::new_v4()));
+    touch(tmp.join("Arrival (2016)/Arrival (2016).mkv"));
+    touch(tmp.join("Severance/Season 01/Severance.S01E02.mkv"));
+    touch(tmp.join("Dark/Staffel 02/05.mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Everything",
+        "mixed",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let result = run_library_scan_with(&pool, &lib.id, "mixed", &ScanOptions::default())
+        .await
+        .unwrap();
+    assert_eq!((result.added, result.errors), (3, 0));
+
+    let (items, files) = library_contents(&pool).await;
+    let kinds = |kind: &str| -> Vec<(String, String)> {
+        items
+            .iter()
+            .filter(|(k, _, _)| k == kind)
+            .map(|(_, parent, title)| (parent.clone(), title.clone()))
+            .collect()
+    };
+    assert_eq!(kinds("movie"), vec![(String::new(), "Arrival".to_string())]);
+    assert_eq!(
+        kinds("series"),
+        vec![
+            (String::new(), "Dark".to_string()),
+            (String::new(), "Severance".to_string())
+        ]
+    );
+    assert_eq!(
+        kinds("season"),
+        vec![
+            ("Dark".to_string(), "Season 2".to_string()),
+            ("Severance".to_string(), "Season 1".to_string())
+        ]
+    );
+    assert_eq!(kinds("episode").len(), 2);
+    assert_eq!(files, 3);
+
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+/// Item ids of a TV library scanned, emptied of items and scanned again, as
+/// `(kind, title, id)` for each scan.
+async fn ids_across_rebuild(deterministic: bool) -> [Vec<(String, String, String)>; 2] {
+    let tmp = std::env::temp_dir().join(format!("rf_item_ids_{}", uuid::Uuid::new_v4()));
+    touch(tmp.join("Lost/Season 01/Lost.S01E01.mkv"));
+    touch(tmp.join("Lost/Season 01/Lost.S01E02.mkv"));
+    touch(tmp.join("Lost/Season 02/Lost.S02E01.mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_db::repo::libraries::set_library_deterministic_ids(&pool, &lib.id, deterministic)
+        .await
+        .unwrap();
+
+    let mut scans = Vec::new();
+    for batch_size in [1, 50] {
+        for table in ["episode_file_map", "media_file", "item"] {
+            sqlx::query(&format!("DELETE FROM {table}"))
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let options = ScanOptions {
+            parallelism: 1,
+            batch_size,
+            ..ScanOptions::default()
+        };
+        let result = run_library_scan_with(&pool, &lib.id, "tv_shows", &options)
+            .await
+            .unwrap();
+        assert_eq!(result.added, 3);
+        let mut ids: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT kind, title, id FROM item")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        ids.sort();
+        scans.push(ids);
+    }
+    std::fs::remove_dir_all(&tmp).ok();
+    scans.try_into().unwrap()
+}
+
+#[tokio::test]
+async fn deterministic_ids_survive_a_rebuild() {
+    let [first, second] = ids_across_rebuild(true).await;
+    // Series, two seasons and three episodes.
+    assert_eq!(first.len(), 6);
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn random_ids_are_the_default() {
+    let [first, second] = ids_across_rebuild(false).await;
+    assert_eq!(first.len(), second.len());
+    for (before, after) in first.iter().zip(&second) {
+        assert_eq!((&before.0, &before.1), (&after.0, &after.1));
+        assert_ne!(before.2, after.2, "{} kept its id", before.1);
+    }
+}
+
+#[tokio::test]
+async fn unavailable_path_keeps_its_items_while_present_path_is_reconciled() {
+    let tmp = std::env::temp_dir().join(format!("rf_unmounted_{}", uuid::Uuid::new_v4()));
+    let (disk_a, disk_b) = (tmp.join("a"), tmp.join("b"));
+    touch(disk_a.join("Lost/Season 01/Lost.S01E01.mkv"));
+    touch(disk_a.join("Lost/Season 01/Lost.S01E02.mkv"));
+    touch(disk_a.join("Fringe/Season 01/Fringe.S01E01.mkv"));
+    touch(disk_b.join("Dark/Season 01/Dark.S01E01.mkv"));
+    touch(disk_b.join("Lost/Season 02/Lost.S02E01.mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let paths = [&disk_a, &disk_b].map(|p| p.to_string_lossy().to_string());
+    let lib = rustfin_db::repo::libraries::create_library(&pool, "TV", "tv_shows", &paths)
+        .await
+        .unwrap();
+    let options = ScanOptions {
+        parallelism: 1,
+        batch_size: 1,
+        ..ScanOptions::default()
+    };
+    let result = run_library_scan_with(&pool, &lib.id, "tv_shows", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.added, 5);
+    let (_, files) = library_contents(&pool).await;
+    assert_eq!(files, 5);
+
+    // Disk B is unmounted, and Fringe plus one Lost episode were deleted from disk A.
+    std::fs::rename(&disk_b, tmp.join("b-unmounted")).unwrap();
+    std::fs::remove_dir_all(disk_a.join("Fringe")).unwrap();
+    std::fs::remove_file(disk_a.join("Lost/Season 01/Lost.S01E02.mkv")).unwrap();
+
+    let result = run_library_scan_with(&pool, &lib.id, "tv_shows", &options)
+        .await
+        .unwrap();
+    // Fringe's episode, season and series, and Lost S01E02.
+    assert_eq!(result.removed, 4);
+    let (items, files) = library_contents(&pool).await;
+    assert_eq!(files, 3);
+    let titles: Vec<&str> = items
+        .iter()
+        .filter(|(kind, _, _)| kind == "series")
+        .map(|(_, _, title)| title.as_str())
+        .collect();
+    assert_eq!(titles, ["Dark", "Lost"]);
+    assert!(
+        items
+            .iter()
+            .any(|(kind, _, title)| kind == "season" && title == "Season 2")
+    );
+
+    // With every path gone the scan fails instead of emptying the library.
+    std::fs::rename(&disk_a, tmp.join("a-unmounted")).unwrap();
+    assert!(matches!(
+        run_library_scan_with(&pool, &lib.id, "tv_shows", &options).await,
+        Err(rustfin_scanner::scan::ScanError::RootUnavailable { .. })
+    ));
+    assert_eq!(library_contents(&pool).await.1, 3);
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+#[tokio::test]
+async fn locked_items_survive_their_file_disappearing() {
+    let tmp = std::env::temp_dir().join(format!("rf_locked_{}", uuid::Uuid::new_v4()));
+    touch(tmp.join("Arrival (2016)/Arrival (2016).mkv"));
+    touch(tmp.join("Sicario (2015)/Sicario (2015).mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let options = ScanOptions::default();
+    run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let arrival = items.iter().find(|i| i.title == "Arrival").unwrap();
+    rustfin_db::repo::items::set_item_locked(&pool, &arrival.id, true)
+        .await
+        .unwrap();
+
+    std::fs::remove_dir_all(tmp.join("Arrival (2016)")).unwrap();
+    std::fs::remove_dir_all(tmp.join("Sicario (2015)")).unwrap();
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.removed, 1);
+    let (items, files) = library_contents(&pool).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].2, "Arrival");
+    assert_eq!(files, 1, "the locked item keeps its media file row");
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+/// A probe that reads every file as two hours long, except those named `*sample*`.
+fn sample_probe(probed: Arc<Mutex<Vec<PathBuf>>>) -> DurationProbe {
+    DurationProbe::new(move |path: PathBuf| {
+        probed.lock().unwrap().push(path.clone());
+        let name = path.file_name().unwrap().to_string_lossy().to_lowercase();
+        async move {
+            let secs = if name.contains("sample") {
+                45.0
+            } else {
+                7200.0
+            };
+            Some(ProbedFile {
+                duration_secs: Some(secs),
+                info_json: format!("{{\"duration_secs\":{secs}}}"),
+            })
+        }
+    })
+}
+
+#[tokio::test]
+async fn short_new_files_are_skipped_as_samples_when_enabled() {
+    let tmp = std::env::temp_dir().join(format!("rf_samples_{}", uuid::Uuid::new_v4()));
+    touch(tmp.join("Heat (1995)/Heat (1995).mkv"));
+    touch(tmp.join("Alien (1979)/Alien (1979).mkv"));
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let paths = [tmp.to_string_lossy().to_string()];
+    let lib = rustfin_db::repo::libraries::create_library(&pool, "Movies", "movies", &paths)
+        .await
+        .unwrap();
+    let probed = Arc::new(Mutex::new(Vec::new()));
+    let options = ScanOptions {
+        duration_probe: Some(sample_probe(probed.clone())),
+        ..ScanOptions::default()
+    };
+
+    // Off by default: nothing is probed.
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.added, 2);
+    assert!(probed.lock().unwrap().is_empty());
+
+    rustfin_db::repo::libraries::set_library_sample_max_duration(&pool, &lib.id, 300)
+        .await
+        .unwrap();
+    touch(tmp.join("Heat (1995)/heat-sample.mkv"));
+    touch(tmp.join("Ronin (1998)/Ronin (1998).mkv"));
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    // The sample is skipped along with the two unchanged movies.
+    assert_eq!((result.added, result.skipped, result.errors), (1, 3, 0));
+    let (items, files) = library_contents(&pool).await;
+    assert_eq!(files, 3);
+    let titles: Vec<&str> = items.iter().map(|(_, _, title)| title.as_str()).collect();
+    assert_eq!(titles, ["Alien", "Heat", "Ronin"]);
+    // Only the files new to the library were probed.
+    let mut names: Vec<String> = probed
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Ronin (1998).mkv", "heat-sample.mkv"]);
+
+    // The recorded probes are reused: the sample is skipped again without a probe.
+    let recorded: Vec<(String,)> =
+        sqlx::query_as("SELECT path FROM media_file_probe ORDER BY path")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(recorded.len(), 2);
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    assert_eq!((result.added, result.skipped), (0, 4));
+    assert_eq!(probed.lock().unwrap().len(), 2);
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+#[tokio::test]
+async fn concurrent_scans_of_one_library_create_each_item_once() {
+    let tmp = std::env::temp_dir().join(format!("rf_concurrent_{}", uuid::Uuid::new_v4()));
+    let media = tmp.join("media");
+    for season in 1..=3 {
+        for episode in 1..=8 {
+            touch(media.join(format!(
+                "Lost/Season 0{season}/Lost.S0{season}E0{episode}.mkv"
+            )));
+        }
+    }
+    for year in 2000..2010 {
+        touch(media.join(format!("Movie {year} ({year})/Movie {year} ({year}).mkv")));
+    }
+
+    let db_path = tmp.join("rustfin.db");
+    let pool = rustfin_db::connect(db_path.to_str().unwrap())
+        .await
+        .unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let paths = [media.to_string_lossy().to_string()];
+    let lib = rustfin_db::repo::libraries::create_library(&pool, "Mixed", "mixed", &paths)
+        .await
+        .unwrap();
+    let options = ScanOptions {
+        batch_size: 1,
+        ..ScanOptions::default()
+    };
+    let (first, second) = tokio::join!(
+        run_library_scan_with(&pool, &lib.id, "mixed", &options),
+        run_library_scan_with(&pool, &lib.id, "mixed", &options),
+    );
+    first.unwrap();
+    second.unwrap();
+    // A file whose insert lost the race is picked up by the next scan.
+    run_library_scan_with(&pool, &lib.id, "mixed", &options)
+        .await
+        .unwrap();
+
+    let (items, files) = library_contents(&pool).await;
+    assert_eq!(files, 34);
+    let mut unique = items.clone();
+    unique.dedup();
+    assert_eq!(items, unique, "an item was created twice");
+    // One series, three seasons, 24 episodes and ten movies.
+    assert_eq!(items.len(), 38);
+    pool.close().await;
+    std::fs::remove_dir_all(&tmp).ok();
+}
+
+#[tokio::test]
+async fn strm_files_keyed_by_url_are_moved_to_their_own_path() {
+    let tmp = std::env::temp_dir().join(format!("rf_strm_rekey_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&tmp).unwrap();
+    let strm = tmp.join("Remote Movie (2020).strm");
+    std::fs::write(&strm, "https://cdn.example.com/a.mp4\n").unwrap();
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let options = ScanOptions::default();
+    run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    // How rows looked before `.strm` files were keyed by their own path.
+    sqlx::query("UPDATE media_file SET path = remote_url WHERE is_remote = 1")
+        .execute(&pool)
+        .await
+        .unwrap();
+    let (file_id,): (String,) = sqlx::query_as("SELECT id FROM media_file")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    assert_eq!(result.added, 0);
+    let rows: Vec<(String, String)> = sqlx::query_as("SELECT id, path FROM media_file")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![(file_id, strm.to_string_lossy().to_string())]);
+    let _ = std::fs::remove_dir_all(&tmp);
+}
+
+#[tokio::test]
+async fn files_replaced_in_place_lose_their_probe() {
+    let tmp = std::env::temp_dir().join(format!("rf_replaced_{}", uuid::Uuid::new_v4()));
+    let movie = tmp.join("Heat (1995)/Heat (1995).mkv");
+    touch(movie.clone());
+
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    let paths = [tmp.to_string_lossy().to_string()];
+    let lib = rustfin_db::repo::libraries::create_library(&pool, "Movies", "movies", &paths)
+        .await
+        .unwrap();
+    let options = ScanOptions::default();
+    run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    let unprobed = rustfin_db::repo::probes::list_unprobed_files(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(unprobed.len(), 1);
+    let file_id = unprobed[0].file_id.clone();
+    rustfin_db::repo::probes::record_probe(&pool, &file_id, Some(6000.0), "{}", &[], &[])
+        .await
+        .unwrap();
+
+    // A rescan of the unchanged file keeps its probe.
+    let result = run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    assert_eq!((result.added, result.skipped), (0, 1));
+    assert!(
+        rustfin_db::repo::probes::list_unprobed_files(&pool, &lib.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // A re-encode of a different size leaves it behind.
+    std::fs::write(&movie, b"re-encoded").unwrap();
+    run_library_scan_with(&pool, &lib.id, "movies", &options)
+        .await
+        .unwrap();
+    let unprobed = rustfin_db::repo::probes::list_unprobed_files(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(unprobed.len(), 1);
+    assert_eq!(unprobed[0].file_id, file_id);
+    assert!(unprobed[0].info_json.is_none());
+    assert!(
+        rustfin_db::repo::probes::get_file_probe(&pool, &file_id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    std::fs::remove_dir_all(&tmp).ok();
+}
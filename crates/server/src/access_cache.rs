@@ -0,0 +1,82 @@
+//! Library access and path lookups memoized for the length of one request.
+//!
+//! Listings check many users or libraries at once. [`AccessCache`] loads everything
+//! a listing asks for up front, with one batched query per kind, and then answers
+//! from memory. A cache is built inside a handler and dropped with it, so grants
+//! and paths changed by other requests are never stale.
+
+use std::collections::HashMap;
+
+use rustfin_db::repo::libraries::LibraryPathRow;
+use sqlx::SqlitePool;
+
+pub struct AccessCache<'a> {
+    pool: &'a SqlitePool,
+    /// Library ids each loaded user may access; empty if none.
+    access: HashMap<String, Vec<String>>,
+    /// Paths of each loaded library; empty if none.
+    paths: HashMap<String, Vec<LibraryPathRow>>,
+}
+
+impl<'a> AccessCache<'a> {
+    pub fn new(pool: &'a SqlitePool) -> Self {
+        Self {
+            pool,
+            access: HashMap::new(),
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Load the library grants of every user in `user_ids` not loaded yet.
+    pub async fn load_access(&mut self, user_ids: &[String]) -> Result<(), sqlx::Error> {
+        let missing: Vec<String> = user_ids
+            .iter()
+            .filter(|id| !self.access.contains_key(*id))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let mut loaded =
+            rustfin_db::repo::users::get_library_access_map(self.pool, &missing).await?;
+        for id in missing {
+            let libraries = loaded.remove(&id).unwrap_or_default();
+            self.access.insert(id, libraries);
+        }
+        Ok(())
+    }
+
+    /// Library ids `user_id` may access, ordered by id.
+    pub async fn library_access(&mut self, user_id: &str) -> Result<&[String], sqlx::Error> {
+        self.load_access(&[user_id.to_string()]).await?;
+        Ok(&self.access[user_id])
+    }
+
+    /// Load the paths of every library in `library_ids` not loaded yet.
+    pub async fn load_paths(&mut self, library_ids: &[String]) -> Result<(), sqlx::Error> {
+        let missing: Vec<String> = library_ids
+            .iter()
+            .filter(|id| !self.paths.contains_key(*id))
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let mut loaded =
+            rustfin_db::repo::libraries::get_library_paths_for(self.pool, &missing).await?;
+        for id in missing {
+            let paths = loaded.remove(&id).unwrap_or_default();
+            self.paths.insert(id, paths);
+        }
+        Ok(())
+    }
+
+    /// Paths of `library_id`.
+    pub async fn library_paths(
+        &mut self,
+        library_id: &str,
+    ) -> Result<&[LibraryPathRow], sqlx::Error> {
+        self.load_paths(&[library_id.to_string()]).await?;
+        Ok(&self.paths[library_id])
+    }
+}
@@ -0,0 +1,76 @@
+//! Overrides for the API root of an outside metadata provider.
+//!
+//! `tmdb_base_url` and `opensubtitles_base_url` exist so tests and local mirrors can
+//! stand in for the real services. They are only set in the database or the
+//! environment, never through the API. API keys are sent to whatever root is
+//! configured, so an override has to be `https`, or plain `http` to a loopback host.
+//! Anything else is ignored with a warning and the real service is used.
+
+/// The trimmed override if it is allowed, else `None`.
+pub fn allowed(setting: &str, value: Option<String>) -> Option<String> {
+    let value = value?.trim().to_string();
+    if value.is_empty() {
+        return None;
+    }
+    if is_allowed(&value) {
+        Some(value)
+    } else {
+        tracing::warn!(
+            setting,
+            url = %value,
+            "ignoring API root override: it must be https or http to a loopback host"
+        );
+        None
+    }
+}
+
+fn is_allowed(value: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(value) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    match url.scheme() {
+        "https" => true,
+        "http" => {
+            host.eq_ignore_ascii_case("localhost")
+                || host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<std::net::IpAddr>()
+                    .is_ok_and(|ip| ip.is_loopback())
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn https_and_loopback_http_are_allowed() {
+        assert!(is_allowed("https://api.example.com/3"));
+        assert!(is_allowed("http://127.0.0.1:8080"));
+        assert!(is_allowed("http://[::1]:8080/api"));
+        assert!(is_allowed("http://localhost:9000"));
+    }
+
+    #[test]
+    fn other_roots_are_refused() {
+        assert!(!is_allowed("http://api.example.com"));
+        assert!(!is_allowed("http://10.0.0.5"));
+        assert!(!is_allowed("ftp://127.0.0.1"));
+        assert!(!is_allowed("not a url"));
+        assert_eq!(
+            allowed("tmdb_base_url", Some("http://evil.test".into())),
+            None
+        );
+        assert_eq!(allowed("tmdb_base_url", Some("  ".into())), None);
+        assert_eq!(
+            allowed("tmdb_base_url", Some(" https://mirror.test ".into())),
+            Some("https://mirror.test".into())
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::Context;
+use futures::StreamExt;
 use rustfin_metadata::ItemMetadata;
 use rustfin_metadata::provider::{MetadataProvider, SearchResult};
 use tracing::{debug, warn};
@@ -45,30 +46,174 @@ async fn resolve_tmdb_api_key(pool: &sqlx::SqlitePool) -> anyhow::Result<Option<
     }))
 }
 
+/// Metadata language/region for a library: its own override, else the server settings.
+async fn resolve_metadata_locale(
+    pool: &sqlx::SqlitePool,
+    library_id: &str,
+) -> anyhow::Result<(Option<String>, Option<String>)> {
+    let (library_language, library_region) =
+        rustfin_db::repo::libraries::get_library_metadata_locale(pool, library_id)
+            .await
+            .context("failed to read library metadata locale")?;
+
+    let language = match library_language {
+        Some(language) => Some(language),
+        None => rustfin_db::repo::settings::get(pool, "metadata_language")
+            .await
+            .context("failed to read metadata_language from settings")?,
+    };
+    let region = match library_region {
+        Some(region) => Some(region),
+        None => rustfin_db::repo::settings::get(pool, "metadata_region")
+            .await
+            .context("failed to read metadata_region from settings")?,
+    };
+
+    Ok((language, region))
+}
+
+/// TMDB API root: the `tmdb_base_url` setting, else `RUSTFIN_TMDB_BASE_URL`, else TMDB
+/// itself. The override is for tests and local mirrors and must pass
+/// [`crate::api_root::allowed`].
+async fn resolve_tmdb_base_url(pool: &sqlx::SqlitePool) -> anyhow::Result<Option<String>> {
+    let base_url = rustfin_db::repo::settings::get(pool, "tmdb_base_url")
+        .await
+        .context("failed to read tmdb_base_url from settings")?
+        .or_else(|| std::env::var("RUSTFIN_TMDB_BASE_URL").ok());
+    Ok(crate::api_root::allowed("tmdb_base_url", base_url))
+}
+
+/// TMDB client using the configured key, API root and the library's metadata locale.
+/// `None` when no API key is configured.
+pub async fn tmdb_client_for_library(
+    pool: &sqlx::SqlitePool,
+    library_id: &str,
+) -> anyhow::Result<Option<rustfin_metadata::tmdb::TmdbClient>> {
+    let Some(key) = resolve_tmdb_api_key(pool).await? else {
+        return Ok(None);
+    };
+    let (language, region) = resolve_metadata_locale(pool, library_id).await?;
+    let client = rustfin_metadata::tmdb::TmdbClient::new(key).with_locale(language, region);
+    Ok(Some(match resolve_tmdb_base_url(pool).await? {
+        Some(base_url) => client.with_base_url(base_url),
+        None => client,
+    }))
+}
+
+/// TMDB client for lookups not tied to a library, using the configured key, API root
+/// and server-wide metadata locale. `None` when no API key is configured.
+pub async fn tmdb_client(
+    pool: &sqlx::SqlitePool,
+) -> anyhow::Result<Option<rustfin_metadata::tmdb::TmdbClient>> {
+    let Some(key) = resolve_tmdb_api_key(pool).await? else {
+        return Ok(None);
+    };
+    let language = rustfin_db::repo::settings::get(pool, "metadata_language")
+        .await
+        .context("failed to read metadata_language from settings")?;
+    let region = rustfin_db::repo::settings::get(pool, "metadata_region")
+        .await
+        .context("failed to read metadata_region from settings")?;
+    let client = rustfin_metadata::tmdb::TmdbClient::new(key).with_locale(language, region);
+    Ok(Some(match resolve_tmdb_base_url(pool).await? {
+        Some(base_url) => client.with_base_url(base_url),
+        None => client,
+    }))
+}
+
+/// TMDB client for `api_key` at the configured API root, e.g. to check a key before
+/// saving it.
+pub async fn tmdb_client_with_key(
+    pool: &sqlx::SqlitePool,
+    api_key: String,
+) -> anyhow::Result<rustfin_metadata::tmdb::TmdbClient> {
+    let client = rustfin_metadata::tmdb::TmdbClient::new(api_key);
+    Ok(match resolve_tmdb_base_url(pool).await? {
+        Some(base_url) => client.with_base_url(base_url),
+        None => client,
+    })
+}
+
+/// Items refreshed at once when `RUSTFIN_TMDB_CONCURRENCY` is unset.
+pub const DEFAULT_TMDB_CONCURRENCY: usize = 4;
+
+/// Concurrency limit for provider lookups: `RUSTFIN_TMDB_CONCURRENCY`, else the default.
+fn tmdb_concurrency() -> usize {
+    std::env::var("RUSTFIN_TMDB_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|&v| v > 0)
+        .unwrap_or(DEFAULT_TMDB_CONCURRENCY)
+}
+
 pub async fn enrich_library_artwork(
     pool: &sqlx::SqlitePool,
     library_id: &str,
     library_kind: &str,
 ) -> anyhow::Result<()> {
-    let settings = rustfin_db::repo::libraries::get_library_settings(pool, library_id)
-        .await
-        .context("failed to read library settings")?
-        .unwrap_or_else(|| rustfin_db::repo::libraries::LibrarySettingsRow {
-            library_id: library_id.to_string(),
-            show_images: true,
-            prefer_local_artwork: true,
-            fetch_online_artwork: true,
-            updated_ts: chrono::Utc::now().timestamp(),
-        });
+    refresh_library_metadata(pool, library_id, library_kind, true, |_, _| async {}).await
+}
+
+/// Fetch provider metadata and artwork for every top-level item in a library.
+///
+/// Locked items and locked fields are never touched. With `replace = false` only missing values are
+/// filled in; with `replace = true` provider values overwrite existing ones.
+/// `on_progress(done, total)` is awaited once up front and after each top-level item.
+pub async fn refresh_library_metadata<F, Fut>(
+    pool: &sqlx::SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    replace: bool,
+    on_progress: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(usize, usize) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    refresh_library_metadata_with_concurrency(
+        pool,
+        library_id,
+        library_kind,
+        replace,
+        tmdb_concurrency(),
+        on_progress,
+    )
+    .await
+}
+
+/// Settings shared by every item of one [`refresh_library_metadata`] run.
+struct RefreshContext {
+    settings: rustfin_db::repo::libraries::LibrarySettingsRow,
+    tmdb_client: Option<rustfin_metadata::tmdb::TmdbClient>,
+    library_kind: String,
+    replace: bool,
+    /// Provider lookups run concurrently; database writes take this lock one item at a time.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+/// [`refresh_library_metadata`] with up to `concurrency` items in flight at once.
+///
+/// A failure on one item is logged and the run carries on with the rest.
+pub async fn refresh_library_metadata_with_concurrency<F, Fut>(
+    pool: &sqlx::SqlitePool,
+    library_id: &str,
+    library_kind: &str,
+    replace: bool,
+    concurrency: usize,
+    mut on_progress: F,
+) -> anyhow::Result<()>
+where
+    F: FnMut(usize, usize) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let settings = library_settings(pool, library_id).await?;
 
     if !settings.show_images {
         return Ok(());
     }
 
     let tmdb_client = if settings.fetch_online_artwork {
-        resolve_tmdb_api_key(pool)
-            .await?
-            .map(rustfin_metadata::tmdb::TmdbClient::new)
+        tmdb_client_for_library(pool, library_id).await?
     } else {
         None
     };
@@ -83,90 +228,278 @@ pub async fn enrich_library_artwork(
         .await
         .context("failed to list library items")?;
 
-    for item in top_level_items {
-        if item.kind != "movie" && item.kind != "series" {
-            continue;
+    let ctx = RefreshContext {
+        settings,
+        tmdb_client,
+        library_kind: library_kind.to_string(),
+        replace,
+        write_lock: tokio::sync::Mutex::new(()),
+    };
+
+    let total = top_level_items.len();
+    on_progress(0, total).await;
+
+    let mut results = futures::stream::iter(top_level_items)
+        .map(|item| {
+            let ctx = &ctx;
+            async move {
+                let result = refresh_item(pool, ctx, &item).await;
+                (item.id, result)
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut done = 0;
+    let mut failed = 0;
+    while let Some((item_id, result)) = results.next().await {
+        if let Err(err) = result {
+            failed += 1;
+            warn!(item_id = %item_id, error = %format!("{err:#}"), "metadata refresh failed for item");
         }
+        done += 1;
+        on_progress(done, total).await;
+    }
+    if failed > 0 {
+        warn!(library_id = %library_id, failed, total, "metadata refresh skipped failed items");
+    }
 
-        let local = find_local_item_artwork(pool, &item.id, &item.kind)
-            .await
-            .unwrap_or_default();
-        let existing_tmdb_id = rustfin_metadata::merge::get_provider_ids(pool, &item.id)
+    Ok(())
+}
+
+/// The library's artwork settings, or the defaults when it has none stored.
+async fn library_settings(
+    pool: &sqlx::SqlitePool,
+    library_id: &str,
+) -> anyhow::Result<rustfin_db::repo::libraries::LibrarySettingsRow> {
+    Ok(
+        rustfin_db::repo::libraries::get_library_settings(pool, library_id)
             .await
-            .context("failed to fetch provider IDs")?
-            .into_iter()
-            .find_map(|(provider, value)| {
-                if provider.eq_ignore_ascii_case("tmdb") {
-                    Some(value)
-                } else {
-                    None
-                }
-            });
-
-        let fetched = match (&tmdb_client, library_kind, item.kind.as_str()) {
-            (Some(client), "movies", "movie") => {
-                fetch_tmdb_movie_metadata(client, &item, existing_tmdb_id.as_deref()).await
-            }
-            (Some(client), "tv_shows", "series") => {
-                fetch_tmdb_series_metadata(client, &item, existing_tmdb_id.as_deref()).await
-            }
-            _ => FetchedProviderMetadata::default(),
-        };
+            .context("failed to read library settings")?
+            .unwrap_or_else(|| rustfin_db::repo::libraries::LibrarySettingsRow {
+                library_id: library_id.to_string(),
+                show_images: true,
+                prefer_local_artwork: true,
+                fetch_online_artwork: true,
+                updated_ts: chrono::Utc::now().timestamp(),
+            }),
+    )
+}
 
-        if let Some(provider_id) = fetched.provider_id.as_deref() {
-            rustfin_metadata::merge::set_provider_id(pool, &item.id, "tmdb", provider_id)
-                .await
-                .context("failed to store TMDB provider id")?;
+/// Apply metadata fetched for a movie or series the user identified by provider
+/// id, replacing what the item had. Locked fields are left alone, as in a refresh.
+pub async fn apply_identified_metadata(
+    pool: &sqlx::SqlitePool,
+    item: &rustfin_db::repo::items::ItemRow,
+    metadata: &ItemMetadata,
+) -> anyhow::Result<()> {
+    let settings = library_settings(pool, &item.library_id).await?;
+    let local = find_local_item_artwork(pool, &item.id, &item.kind)
+        .await
+        .unwrap_or_default();
+    apply_provider_metadata(pool, &settings, item, &local, Some(metadata), true).await
+}
+
+/// Refresh one top-level item (and, for series, its seasons).
+async fn refresh_item(
+    pool: &sqlx::SqlitePool,
+    ctx: &RefreshContext,
+    item: &rustfin_db::repo::items::ItemRow,
+) -> anyhow::Result<()> {
+    if item.kind != "movie" && item.kind != "series" {
+        return Ok(());
+    }
+    if rustfin_db::repo::items::is_item_locked(pool, &item.id)
+        .await
+        .context("failed to read item lock")?
+    {
+        debug!(item_id = %item.id, "item is locked; skipping refresh");
+        return Ok(());
+    }
+    let local = find_local_item_artwork(pool, &item.id, &item.kind)
+        .await
+        .unwrap_or_default();
+    let existing_tmdb_id = stored_tmdb_id(pool, &item.id).await?;
+
+    let fetched = match (
+        &ctx.tmdb_client,
+        ctx.library_kind.as_str(),
+        item.kind.as_str(),
+    ) {
+        (Some(client), "movies" | "mixed", "movie") => {
+            fetch_tmdb_movie_metadata(client, item, existing_tmdb_id.as_deref()).await
         }
-        if let Some(provider_meta) = fetched.metadata.as_ref() {
-            rustfin_metadata::merge::merge_metadata(pool, &item.id, provider_meta)
+        (Some(client), "tv_shows" | "mixed", "series") => {
+            fetch_tmdb_series_metadata(client, item, existing_tmdb_id.as_deref()).await
+        }
+        _ => FetchedProviderMetadata::default(),
+    };
+
+    let _write_guard = ctx.write_lock.lock().await;
+
+    let mut fetched = fetched;
+    if let Some(provider_id) = fetched.provider_id.as_deref() {
+        let assignment =
+            crate::provider_policy::assign_provider_id(pool, &item.id, "tmdb", provider_id)
                 .await
-                .context("failed to merge TMDB metadata")?;
+                .context("failed to store TMDB provider id")?;
+        // A rejected match is treated as no match: local artwork still applies.
+        if !assignment.stored {
+            fetched = FetchedProviderMetadata::default();
         }
+    }
+    apply_provider_metadata(
+        pool,
+        &ctx.settings,
+        item,
+        &local,
+        fetched.metadata.as_ref(),
+        ctx.replace,
+    )
+    .await
+}
 
-        let online = artwork_from_metadata(fetched.metadata.as_ref());
+/// Merge provider metadata into a top-level item, then pick its artwork (and, for
+/// series, its seasons' artwork) from local files, the provider and what it has.
+async fn apply_provider_metadata(
+    pool: &sqlx::SqlitePool,
+    settings: &rustfin_db::repo::libraries::LibrarySettingsRow,
+    item: &rustfin_db::repo::items::ItemRow,
+    local: &Artwork,
+    metadata: Option<&ItemMetadata>,
+    replace: bool,
+) -> anyhow::Result<()> {
+    if let Some(provider_meta) = metadata {
+        // Without replace, artwork is left to merge_and_apply_artwork so that
+        // "keep what the item already has" is judged before anything is written.
+        let provider_meta = if replace {
+            provider_meta.clone()
+        } else {
+            ItemMetadata {
+                poster_url: None,
+                backdrop_url: None,
+                logo_url: None,
+                thumb_url: None,
+                ..provider_meta.clone()
+            }
+        };
+        rustfin_metadata::merge::merge_metadata_with(pool, &item.id, &provider_meta, replace)
+            .await
+            .context("failed to merge TMDB metadata")?;
+    }
 
-        merge_and_apply_artwork(
-            pool,
-            &item.id,
-            &local,
-            &online,
-            settings.prefer_local_artwork,
-            settings.fetch_online_artwork,
-        )
-        .await?;
+    apply_artwork(
+        pool,
+        settings,
+        item,
+        local,
+        &artwork_from_metadata(metadata),
+        replace,
+    )
+    .await
+}
 
-        if item.kind == "series" {
-            let children = rustfin_db::repo::items::get_children(pool, &item.id)
+/// Pick an item's artwork (and, for series, its seasons' artwork) from local files,
+/// the provider's images and what it already has. Locked fields keep their value.
+async fn apply_artwork(
+    pool: &sqlx::SqlitePool,
+    settings: &rustfin_db::repo::libraries::LibrarySettingsRow,
+    item: &rustfin_db::repo::items::ItemRow,
+    local: &Artwork,
+    online: &Artwork,
+    replace: bool,
+) -> anyhow::Result<()> {
+    merge_and_apply_artwork(
+        pool,
+        &item.id,
+        local,
+        online,
+        settings.prefer_local_artwork,
+        settings.fetch_online_artwork,
+        replace,
+    )
+    .await?;
+
+    if item.kind == "series" {
+        let children = rustfin_db::repo::items::get_children(pool, &item.id)
+            .await
+            .context("failed to fetch season children")?;
+        for season in children.into_iter().filter(|c| c.kind == "season") {
+            if rustfin_db::repo::items::is_item_locked(pool, &season.id)
                 .await
-                .context("failed to fetch season children")?;
-            for season in children.into_iter().filter(|c| c.kind == "season") {
-                let season_local = find_local_item_artwork(pool, &season.id, "season")
-                    .await
-                    .unwrap_or_default();
-                let fallback_from_series = Artwork {
-                    poster: online.poster.clone().or(local.poster.clone()),
-                    backdrop: online.backdrop.clone().or(local.backdrop.clone()),
-                    logo: online.logo.clone().or(local.logo.clone()),
-                    thumb: online.thumb.clone().or(local.thumb.clone()),
-                };
-
-                merge_and_apply_artwork(
-                    pool,
-                    &season.id,
-                    &season_local,
-                    &fallback_from_series,
-                    settings.prefer_local_artwork,
-                    settings.fetch_online_artwork,
-                )
-                .await?;
+                .context("failed to read item lock")?
+            {
+                continue;
             }
+            let season_local = find_local_item_artwork(pool, &season.id, "season")
+                .await
+                .unwrap_or_default();
+            let fallback_from_series = Artwork {
+                poster: online.poster.clone().or(local.poster.clone()),
+                backdrop: online.backdrop.clone().or(local.backdrop.clone()),
+                logo: online.logo.clone().or(local.logo.clone()),
+                thumb: online.thumb.clone().or(local.thumb.clone()),
+            };
+
+            merge_and_apply_artwork(
+                pool,
+                &season.id,
+                &season_local,
+                &fallback_from_series,
+                settings.prefer_local_artwork,
+                settings.fetch_online_artwork,
+                replace,
+            )
+            .await?;
         }
     }
 
     Ok(())
 }
 
+/// Re-pick an item's artwork from its local files and, for a movie or series already
+/// matched on TMDB, the provider's images. Text metadata is not touched and no new
+/// match is searched for. Locked fields and the library's artwork settings apply.
+pub async fn refresh_item_images(
+    pool: &sqlx::SqlitePool,
+    item: &rustfin_db::repo::items::ItemRow,
+) -> anyhow::Result<()> {
+    let settings = library_settings(pool, &item.library_id).await?;
+    let local = find_local_item_artwork(pool, &item.id, &item.kind)
+        .await
+        .unwrap_or_default();
+
+    let mut online = Artwork::default();
+    if settings.fetch_online_artwork
+        && (item.kind == "movie" || item.kind == "series")
+        && let Some(tmdb_id) = stored_tmdb_id(pool, &item.id).await?
+        && let Some(client) = tmdb_client_for_library(pool, &item.library_id).await?
+    {
+        let fetched = if item.kind == "movie" {
+            fetch_tmdb_movie_metadata(&client, item, Some(&tmdb_id)).await
+        } else {
+            fetch_tmdb_series_metadata(&client, item, Some(&tmdb_id)).await
+        };
+        online = artwork_from_metadata(fetched.metadata.as_ref());
+    }
+
+    apply_artwork(pool, &settings, item, &local, &online, true).await
+}
+
+/// The TMDB id stored for an item, if it has been matched.
+async fn stored_tmdb_id(pool: &sqlx::SqlitePool, item_id: &str) -> anyhow::Result<Option<String>> {
+    Ok(rustfin_metadata::merge::get_provider_ids(pool, item_id)
+        .await
+        .context("failed to fetch provider IDs")?
+        .into_iter()
+        .find_map(|(provider, value)| {
+            if provider.eq_ignore_ascii_case("tmdb") {
+                Some(value)
+            } else {
+                None
+            }
+        }))
+}
+
 fn artwork_from_metadata(metadata: Option<&ItemMetadata>) -> Artwork {
     match metadata {
         Some(meta) => Artwork {
@@ -307,6 +640,7 @@ async fn merge_and_apply_artwork(
     online: &Artwork,
     prefer_local_artwork: bool,
     fetch_online_artwork: bool,
+    replace: bool,
 ) -> anyhow::Result<()> {
     let existing = rustfin_db::repo::items::get_item_artwork(pool, item_id)
         .await
@@ -318,9 +652,17 @@ async fn merge_and_apply_artwork(
             thumb,
         })
         .unwrap_or_default();
-
-    let choose = |current: &Option<String>, local_v: &Option<String>, online_v: &Option<String>| {
-        if prefer_local_artwork {
+    let locked = rustfin_metadata::merge::get_locked_fields(pool, item_id)
+        .await
+        .context("failed to read field locks")?;
+
+    let choose = |field: &str,
+                  current: &Option<String>,
+                  local_v: &Option<String>,
+                  online_v: &Option<String>| {
+        if locked.iter().any(|f| f == field) || (!replace && current.is_some()) {
+            current.clone()
+        } else if prefer_local_artwork {
             local_v
                 .clone()
                 .or_else(|| {
@@ -342,10 +684,20 @@ async fn merge_and_apply_artwork(
     };
 
     let merged = Artwork {
-        poster: choose(&existing.poster, &local.poster, &online.poster),
-        backdrop: choose(&existing.backdrop, &local.backdrop, &online.backdrop),
-        logo: choose(&existing.logo, &local.logo, &online.logo),
-        thumb: choose(&existing.thumb, &local.thumb, &online.thumb),
+        poster: choose(
+            "poster_url",
+            &existing.poster,
+            &local.poster,
+            &online.poster,
+        ),
+        backdrop: choose(
+            "backdrop_url",
+            &existing.backdrop,
+            &local.backdrop,
+            &online.backdrop,
+        ),
+        logo: choose("logo_url", &existing.logo, &local.logo, &online.logo),
+        thumb: choose("thumb_url", &existing.thumb, &local.thumb, &online.thumb),
     };
 
     if merged.poster != existing.poster
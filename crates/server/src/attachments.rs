@@ -0,0 +1,136 @@
+//! Attachments carried inside media files: fonts for client-side ASS rendering
+//! and embedded cover art as a poster fallback.
+//!
+//! A file's attachments are extracted together to `cache_dir/attachments/{file_id}/`,
+//! with the list of them, stamped with the source's mtime and extracted again once
+//! that changes. Cover art goes to `cache_dir/covers/{file_id}.{ext}` and becomes
+//! the item's poster.
+
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use rustfin_transcoder::TranscodeError;
+use rustfin_transcoder::ffprobe::AttachmentStream;
+
+use crate::keyed_lock::KeyedLocks;
+use crate::state::AppState;
+
+/// Records which version of the source the extracted attachments came from.
+const STAMP_FILE: &str = ".source-mtime";
+
+/// The extracted attachments, as JSON, so serving one doesn't need a probe.
+const LIST_FILE: &str = ".attachments.json";
+
+/// One extraction per file at a time: each uses its own `.partial` directory.
+static EXTRACTING: LazyLock<KeyedLocks<String>> = LazyLock::new(KeyedLocks::default);
+
+pub fn attachments_dir(cache_dir: &Path, file_id: &str) -> PathBuf {
+    cache_dir.join("attachments").join(file_id)
+}
+
+fn stamp(mtime: SystemTime) -> String {
+    mtime
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// The directory holding every attachment of `source` and the list of them,
+/// probing the file and extracting them first if they are missing or came from an
+/// older version of the file.
+pub async fn extracted_attachments(
+    state: &AppState,
+    file_id: &str,
+    source: &Path,
+) -> Result<(PathBuf, Vec<AttachmentStream>), TranscodeError> {
+    let dir = attachments_dir(&state.cache_dir, file_id);
+    let source_stamp = stamp(std::fs::metadata(source)?.modified()?);
+    let share = EXTRACTING.share(&file_id.to_string());
+    let _guard = share.lock.lock().await;
+    let current = std::fs::read_to_string(dir.join(STAMP_FILE)).ok();
+    if current.as_deref() == Some(source_stamp.as_str())
+        && let Some(list) = std::fs::read(dir.join(LIST_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+    {
+        return Ok((dir, list));
+    }
+
+    let info = rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), source).await?;
+    if let Some(parent) = dir.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    rustfin_transcoder::attachments::extract_attachments(
+        state.transcoder.ffmpeg_path(),
+        source,
+        &info.attachments,
+        &dir,
+    )
+    .await?;
+    let list: Vec<AttachmentStream> = info
+        .attachments
+        .into_iter()
+        .filter(|a| !a.attached_pic)
+        .collect();
+    let json = serde_json::to_vec(&list).map_err(std::io::Error::other)?;
+    tokio::fs::write(dir.join(LIST_FILE), json).await?;
+    tokio::fs::write(dir.join(STAMP_FILE), source_stamp).await?;
+    Ok((dir, list))
+}
+
+/// Use embedded cover art as the poster of every movie and episode in the library
+/// that has no artwork at all, reading each file's stored probe where it has one.
+/// Returns how many items got one.
+pub async fn apply_embedded_cover_art(state: &AppState, library_id: &str) -> anyhow::Result<usize> {
+    if state.media_tools.ffmpeg.is_missing() || state.media_tools.ffprobe.is_missing() {
+        return Ok(0);
+    }
+    let items = rustfin_db::repo::items::list_posterless_media_items(&state.db, library_id)
+        .await
+        .context("failed to list items without a poster")?;
+    let covers_dir = state.cache_dir.join("covers");
+    let mut applied = 0;
+    for (item_id, file_id, path) in items {
+        let source = Path::new(&path);
+        if !source.is_file() {
+            continue;
+        }
+        let Some(info) = crate::probe_cache::cached_or_probe(state, &file_id, source).await else {
+            continue;
+        };
+        let Some(cover) = rustfin_transcoder::attachments::cover_art(&info) else {
+            continue;
+        };
+        tokio::fs::create_dir_all(&covers_dir)
+            .await
+            .context("failed to create cover art dir")?;
+        let output = covers_dir.join(format!(
+            "{file_id}.{}",
+            rustfin_transcoder::attachments::cover_art_extension(cover)
+        ));
+        if let Err(e) = rustfin_transcoder::attachments::extract_cover_art(
+            state.transcoder.ffmpeg_path(),
+            source,
+            cover,
+            &output,
+        )
+        .await
+        {
+            tracing::warn!(item_id = %item_id, error = %e, "failed to extract embedded cover art");
+            continue;
+        }
+        rustfin_db::repo::items::set_item_image_url(
+            &state.db,
+            &item_id,
+            "poster",
+            &output.to_string_lossy(),
+        )
+        .await
+        .context("failed to store cover art poster")?;
+        applied += 1;
+    }
+    Ok(applied)
+}
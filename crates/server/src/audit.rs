@@ -0,0 +1,22 @@
+//! Audit trail for administrative actions, queried at `/api/v1/system/audit`.
+//!
+//! Summaries describe what changed (names, roles, which settings) and must never
+//! include passwords, tokens or API keys.
+
+use sqlx::SqlitePool;
+
+/// Record an admin action. Failures are logged rather than failing the action itself.
+pub async fn record(
+    pool: &SqlitePool,
+    actor_user_id: &str,
+    action: &str,
+    target: Option<&str>,
+    summary: &str,
+) {
+    if let Err(e) =
+        rustfin_db::repo::audit::record_audit(pool, Some(actor_user_id), action, target, summary)
+            .await
+    {
+        tracing::warn!(action, error = %e, "failed to write audit log entry");
+    }
+}
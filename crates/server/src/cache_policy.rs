@@ -0,0 +1,48 @@
+//! `Cache-Control` values for images and media, tunable from the server settings.
+//!
+//! `image_cache_max_age_secs` sets how long clients and CDNs may keep artwork;
+//! `media_cacheable` lets direct-play responses be cached instead of `no-store`,
+//! by the client only: media needs a login, so shared caches must not keep it.
+
+use sqlx::SqlitePool;
+
+pub const IMAGE_CACHE_MAX_AGE_KEY: &str = "image_cache_max_age_secs";
+pub const MEDIA_CACHEABLE_KEY: &str = "media_cacheable";
+
+pub const DEFAULT_IMAGE_CACHE_MAX_AGE_SECS: u32 = 86_400;
+/// One year, the longest max-age caches are expected to honour.
+pub const MAX_IMAGE_CACHE_MAX_AGE_SECS: u32 = 31_536_000;
+/// Max-age used for media when `media_cacheable` is on.
+const MEDIA_CACHE_MAX_AGE_SECS: u32 = 86_400;
+
+/// Stored image max-age, falling back to the default if unset or unparsable.
+pub async fn image_cache_max_age(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+    Ok(
+        rustfin_db::repo::settings::get(pool, IMAGE_CACHE_MAX_AGE_KEY)
+            .await?
+            .and_then(|v| v.trim().parse().ok())
+            .filter(|v| *v <= MAX_IMAGE_CACHE_MAX_AGE_SECS)
+            .unwrap_or(DEFAULT_IMAGE_CACHE_MAX_AGE_SECS),
+    )
+}
+
+pub async fn media_cacheable(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(rustfin_db::repo::settings::get(pool, MEDIA_CACHEABLE_KEY)
+        .await?
+        .is_some_and(|v| v.trim() == "true"))
+}
+
+pub async fn image_cache_control(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+    Ok(match image_cache_max_age(pool).await? {
+        0 => "no-cache".to_string(),
+        max_age => format!("public, max-age={max_age}"),
+    })
+}
+
+pub async fn media_cache_control(pool: &SqlitePool) -> Result<String, sqlx::Error> {
+    Ok(if media_cacheable(pool).await? {
+        format!("private, max-age={MEDIA_CACHE_MAX_AGE_SECS}")
+    } else {
+        "no-store".to_string()
+    })
+}
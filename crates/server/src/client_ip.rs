@@ -0,0 +1,173 @@
+//! The address a request came from, for policies that depend on the client's network.
+//!
+//! That is the connection's peer, unless the peer is one of the `trusted_proxies`
+//! (addresses or CIDR ranges, e.g. `10.0.0.0/8`): then it is the right-most
+//! `X-Forwarded-For` address that is not itself a trusted proxy.
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::HeaderMap;
+use axum::http::request::Parts;
+use sqlx::SqlitePool;
+
+use crate::error::AppError;
+use crate::state::AppState;
+use rustfin_core::error::ApiError;
+
+pub const TRUSTED_PROXIES_KEY: &str = "trusted_proxies";
+
+/// The resolved client address; `None` when the request did not come over a
+/// socket (in-process callers), which policies treat as local.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+impl FromRequestParts<AppState> for ClientIp {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        let trusted = match peer {
+            Some(_) => trusted_proxies(&state.db)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+            None => Vec::new(),
+        };
+        Ok(ClientIp(resolve(peer, &parts.headers, &trusted)))
+    }
+}
+
+impl ClientIp {
+    /// Whether the client is on this machine or the local network.
+    pub fn is_local(&self) -> bool {
+        self.0.is_none_or(is_local)
+    }
+}
+
+/// The stored `trusted_proxies` list; an unset or unparsable value trusts no one.
+pub async fn trusted_proxies(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    Ok(rustfin_db::repo::settings::get(pool, TRUSTED_PROXIES_KEY)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default())
+}
+
+/// The client address for a request from `peer` carrying `headers`.
+pub fn resolve(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[String]) -> Option<IpAddr> {
+    let peer = peer?.to_canonical();
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|entry| in_range(entry, ip));
+    if !is_trusted(peer) {
+        return Some(peer);
+    }
+    let mut client = peer;
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map_while(|hop| hop.trim().parse::<IpAddr>().ok())
+        .collect();
+    for hop in forwarded.into_iter().rev() {
+        client = hop.to_canonical();
+        if !is_trusted(client) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+/// Loopback, private (RFC 1918, `fc00::/7`) and link-local addresses.
+pub fn is_local(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => v4.is_loopback() || v4.is_private() || v4.is_link_local(),
+        IpAddr::V6(v6) => v6.is_loopback() || v6.is_unique_local() || v6.is_unicast_link_local(),
+    }
+}
+
+/// Whether `ip` is the address `entry`, or inside it when `entry` is a CIDR range.
+fn in_range(entry: &str, ip: IpAddr) -> bool {
+    let (addr, prefix) = match entry.trim().split_once('/') {
+        Some((addr, prefix)) => (addr, prefix.parse::<u32>().ok()),
+        None => (entry.trim(), None),
+    };
+    let Ok(network) = addr.parse::<IpAddr>() else {
+        return false;
+    };
+    match (network.to_canonical(), ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            let prefix = prefix.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(network) & mask == u32::from(ip) & mask
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => {
+            let prefix = prefix.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(network) & mask == u128::from(ip) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn forwarded_for_is_only_honoured_from_trusted_proxies() {
+        let trusted = vec!["10.0.0.0/8".to_string(), "fd00::1".to_string()];
+        let headers = forwarded("203.0.113.9, 198.51.100.7, 10.1.2.3");
+        assert_eq!(
+            resolve(Some(ip("10.0.0.2")), &headers, &trusted),
+            Some(ip("198.51.100.7"))
+        );
+        assert_eq!(
+            resolve(Some(ip("fd00::1")), &forwarded("10.0.0.9"), &trusted),
+            Some(ip("10.0.0.9"))
+        );
+        assert_eq!(
+            resolve(Some(ip("192.168.1.20")), &headers, &trusted),
+            Some(ip("192.168.1.20"))
+        );
+        assert_eq!(
+            resolve(Some(ip("10.0.0.2")), &HeaderMap::new(), &trusted),
+            Some(ip("10.0.0.2"))
+        );
+        assert_eq!(resolve(None, &headers, &trusted), None);
+    }
+
+    #[test]
+    fn local_addresses_are_loopback_private_or_link_local() {
+        for local in [
+            "127.0.0.1",
+            "10.4.0.1",
+            "172.16.5.5",
+            "192.168.0.10",
+            "169.254.1.1",
+            "::1",
+            "fd12::5",
+            "fe80::1",
+            "::ffff:192.168.1.2",
+        ] {
+            assert!(is_local(ip(local)), "{local}");
+        }
+        for remote in ["203.0.113.5", "8.8.8.8", "2001:db8::1", "::ffff:8.8.8.8"] {
+            assert!(!is_local(ip(remote)), "{remote}");
+        }
+    }
+}
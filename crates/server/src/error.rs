@@ -4,6 +4,7 @@ use axum::response::{IntoResponse, Response};
 use rustfin_core::error::{ApiError, ApiErrorWithCode, ErrorEnvelope};
 
 /// Newtype wrapper so we can implement `IntoResponse` in this crate.
+#[derive(Debug)]
 pub struct AppError(pub ApiError);
 
 impl IntoResponse for AppError {
@@ -38,3 +39,25 @@ impl From<ApiErrorWithCode> for AppErrorWithCode {
         Self(e)
     }
 }
+
+/// Give the bare 413 and 408 responses from the body-limit and timeout layers the
+/// usual JSON error envelope. Handler responses already carry one.
+pub async fn envelope_layer_errors(response: Response) -> Response {
+    let is_json = response
+        .headers()
+        .get(axum::http::header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if is_json {
+        return response;
+    }
+    match response.status() {
+        StatusCode::PAYLOAD_TOO_LARGE => AppError(ApiError::PayloadTooLarge(
+            "request body is too large".into(),
+        ))
+        .into_response(),
+        StatusCode::REQUEST_TIMEOUT => {
+            AppError(ApiError::RequestTimeout("request took too long".into())).into_response()
+        }
+        _ => response,
+    }
+}
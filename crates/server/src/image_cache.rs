@@ -0,0 +1,279 @@
+//! Artwork cached on disk under `cache_dir/images`, and the opt-in prefetch that
+//! fills it after a scan so first page loads don't wait on each image.
+//!
+//! Files are keyed by item, image type and size: TMDB images by the TMDB size
+//! closest to the requested width/height, other sources by the exact request.
+
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use crate::image_proxy::{self, FetchError, MAX_IMAGE_BYTES};
+
+/// Sizes warmed for each item: the unsized request the web UI makes, and the
+/// widths native clients ask for in grids and detail views.
+pub const PREFETCH_SIZES: &[(&str, Option<u32>)] = &[
+    ("poster", None),
+    ("poster", Some(342)),
+    ("backdrop", None),
+    ("backdrop", Some(1280)),
+];
+
+/// Where one size of an item's image is cached and the URL to fill it from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub fetch_url: String,
+    pub path: PathBuf,
+}
+
+/// The cache entry for `image_url` requested at `width` x `height`. `extra_index`
+/// picks one of the extra backdrops; `format` overrides the file extension.
+#[allow(clippy::too_many_arguments)]
+pub fn cache_entry(
+    images_dir: &Path,
+    item_id: &str,
+    img_type: &str,
+    image_url: &str,
+    extra_index: Option<usize>,
+    width: Option<u32>,
+    height: Option<u32>,
+    format: Option<&str>,
+) -> CacheEntry {
+    // TMDB serves each image in several sizes; fetch the one closest to what was asked
+    // for and cache per size. Other sources are cached per requested size.
+    let tmdb_variant =
+        rustfin_metadata::tmdb::image_size_for(img_type, width, height).and_then(|size| {
+            rustfin_metadata::tmdb::with_image_size(image_url, &size).map(|url| (size, url))
+        });
+    let (fetch_url, size_key) = match tmdb_variant {
+        Some((size, url)) => (url, size),
+        None => (
+            image_url.to_string(),
+            format!("{}_{}", width.unwrap_or(0), height.unwrap_or(0)),
+        ),
+    };
+    let cache_key = match extra_index {
+        Some(index) => format!("{item_id}_{img_type}{index}_{size_key}"),
+        None => format!("{item_id}_{img_type}_{size_key}"),
+    };
+    let ext = match format {
+        Some(format) => format,
+        None if image_url.contains(".png") => "png",
+        None if image_url.contains(".webp") => "webp",
+        None => "jpg",
+    };
+    CacheEntry {
+        fetch_url,
+        path: images_dir.join(format!("{cache_key}.{ext}")),
+    }
+}
+
+/// Limits for [`prefetch_library_images`].
+#[derive(Clone, Copy, Debug)]
+pub struct PrefetchOptions {
+    /// Prefetching stops once the image cache holds this many bytes.
+    pub max_cache_bytes: u64,
+    /// Pause between remote downloads, to stay under TMDB's rate limit.
+    pub min_interval: Duration,
+}
+
+impl PrefetchOptions {
+    pub const DEFAULT_MAX_CACHE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+    pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+    /// `RUSTFIN_IMAGE_CACHE_MAX_MB` and `RUSTFIN_IMAGE_PREFETCH_INTERVAL_MS`, else the
+    /// defaults.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+        };
+        Self {
+            max_cache_bytes: var("RUSTFIN_IMAGE_CACHE_MAX_MB")
+                .filter(|&mb| mb > 0)
+                .map(|mb| mb * 1024 * 1024)
+                .unwrap_or(Self::DEFAULT_MAX_CACHE_BYTES),
+            min_interval: var("RUSTFIN_IMAGE_PREFETCH_INTERVAL_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(Self::DEFAULT_MIN_INTERVAL),
+        }
+    }
+}
+
+impl Default for PrefetchOptions {
+    fn default() -> Self {
+        Self {
+            max_cache_bytes: Self::DEFAULT_MAX_CACHE_BYTES,
+            min_interval: Self::DEFAULT_MIN_INTERVAL,
+        }
+    }
+}
+
+/// What one prefetch run did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrefetchSummary {
+    /// Images written to the cache.
+    pub cached: usize,
+    /// Images that were already cached.
+    pub skipped: usize,
+    /// Images that could not be fetched; they are left to on-demand loading.
+    pub failed: usize,
+    /// Whether the run stopped early because the cache reached its budget.
+    pub over_budget: bool,
+}
+
+/// Download an image over HTTP(S) with the proxy's client, so it times out and
+/// gives up on bodies over [`MAX_IMAGE_BYTES`].
+pub async fn download(url: String) -> anyhow::Result<Vec<u8>> {
+    let resp = image_proxy::client()
+        .context("failed to build HTTP client")?
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("failed to request {url}"))?
+        .error_for_status()
+        .with_context(|| format!("failed to download {url}"))?;
+    match image_proxy::read_limited(resp).await {
+        Ok(body) => Ok(body),
+        Err(FetchError::TooLarge) => {
+            anyhow::bail!("{url} is larger than {MAX_IMAGE_BYTES} bytes")
+        }
+        Err(FetchError::Http(e)) => Err(e).with_context(|| format!("failed to download {url}")),
+    }
+}
+
+/// Fill the image cache with [`PREFETCH_SIZES`] of every poster and backdrop in a
+/// library, newest items first. Images already cached are skipped, so after the
+/// first run only newly added items are fetched.
+///
+/// Remote images are fetched with `fetch`, waiting `min_interval` between
+/// downloads; local artwork is copied. Nothing is fetched for libraries with
+/// images turned off.
+pub async fn prefetch_library_images<F, Fut>(
+    pool: &sqlx::SqlitePool,
+    cache_dir: &Path,
+    library_id: &str,
+    options: PrefetchOptions,
+    mut fetch: F,
+) -> anyhow::Result<PrefetchSummary>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<Vec<u8>>>,
+{
+    let mut summary = PrefetchSummary::default();
+    let show_images = rustfin_db::repo::libraries::get_library_settings(pool, library_id)
+        .await
+        .context("failed to read library settings")?
+        .is_none_or(|s| s.show_images);
+    if !show_images {
+        return Ok(summary);
+    }
+
+    let images_dir = cache_dir.join("images");
+    tokio::fs::create_dir_all(&images_dir)
+        .await
+        .context("failed to create image cache dir")?;
+    let mut cache_bytes = cache_size(&images_dir).await;
+    let items = rustfin_db::repo::items::list_library_artwork(pool, library_id)
+        .await
+        .context("failed to list library artwork")?;
+
+    let mut fetched_any = false;
+    for (item_id, poster, backdrop) in items {
+        for &(img_type, width) in PREFETCH_SIZES {
+            let source = match img_type {
+                "poster" => poster.as_deref(),
+                _ => backdrop.as_deref(),
+            };
+            let Some(source) = source else {
+                continue;
+            };
+            let entry = cache_entry(
+                &images_dir,
+                &item_id,
+                img_type,
+                source,
+                None,
+                width,
+                None,
+                None,
+            );
+            if entry.path.exists() {
+                summary.skipped += 1;
+                continue;
+            }
+            if cache_bytes >= options.max_cache_bytes {
+                summary.over_budget = true;
+                return Ok(summary);
+            }
+
+            let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+                if fetched_any && !options.min_interval.is_zero() {
+                    tokio::time::sleep(options.min_interval).await;
+                }
+                fetched_any = true;
+                fetch(entry.fetch_url.clone()).await
+            } else {
+                tokio::fs::read(source)
+                    .await
+                    .with_context(|| format!("failed to read {source}"))
+            };
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    summary.failed += 1;
+                    tracing::debug!(item_id = %item_id, error = %format!("{err:#}"), "image prefetch failed");
+                    continue;
+                }
+            };
+            if cache_bytes + bytes.len() as u64 > options.max_cache_bytes {
+                summary.over_budget = true;
+                return Ok(summary);
+            }
+            write_cached(&entry.path, &bytes)
+                .await
+                .with_context(|| format!("failed to write {}", entry.path.display()))?;
+            cache_bytes += bytes.len() as u64;
+            summary.cached += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// Bytes held by the files directly in `dir`, counted off the async runtime.
+pub(crate) async fn cache_size(dir: &Path) -> u64 {
+    let dir = dir.to_path_buf();
+    tokio::task::spawn_blocking(move || dir_size(&dir))
+        .await
+        .unwrap_or(0)
+}
+
+/// Write `bytes` to `path` through a temporary file next to it, so readers find
+/// either no image or the whole one.
+pub(crate) async fn write_cached(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let partial = path.with_extension(format!("{}.partial", uuid::Uuid::new_v4()));
+    let written = match tokio::fs::write(&partial, bytes).await {
+        Ok(()) => tokio::fs::rename(&partial, path).await,
+        Err(e) => Err(e),
+    };
+    if written.is_err() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+    written
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .filter_map(|e| e.metadata().ok())
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+                .sum()
+        })
+        .unwrap_or(0)
+}
@@ -0,0 +1,198 @@
+//! Remote artwork served through `GET /api/v1/images/proxy?url=...`, so clients
+//! never fetch provider images directly and every fetch goes through the image cache.
+//!
+//! Only `http(s)` URLs on an allowlisted host are fetched: `image.tmdb.org`, plus any
+//! hosts listed (comma-separated) in the `image_proxy_hosts` setting. Redirects are
+//! not followed and no `Referer` is sent upstream, so the proxy cannot be pointed at
+//! internal services or leak which client asked for an image.
+//!
+//! Fragments are dropped and the host lowercased before fetching, so one image is
+//! cached once however its URL is written; the query is part of the key, since a
+//! custom host may pick the image by it. Fetched images count toward the image cache
+//! budget (see [`crate::image_cache::PrefetchOptions::max_cache_bytes`]); once it is
+//! spent they are served without being cached.
+
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+pub const IMAGE_PROXY_HOSTS_KEY: &str = "image_proxy_hosts";
+pub const DEFAULT_HOSTS: &[&str] = &["image.tmdb.org"];
+pub const PROXY_PATH: &str = "/api/v1/images/proxy";
+/// Larger upstream responses are refused rather than cached.
+pub const MAX_IMAGE_BYTES: usize = 20 * 1024 * 1024;
+
+/// File extensions cached images may have, with their content types.
+const IMAGE_TYPES: [(&str, &str); 4] = [
+    ("jpg", "image/jpeg"),
+    ("png", "image/png"),
+    ("webp", "image/webp"),
+    ("gif", "image/gif"),
+];
+
+/// The proxy URL clients should use for the remote image `url`. Local paths and
+/// other non-HTTP values are returned as `None`.
+pub fn proxy_url(url: &str) -> Option<String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return None;
+    }
+    let proxied =
+        reqwest::Url::parse_with_params(&format!("http://localhost{PROXY_PATH}"), [("url", url)])
+            .ok()?;
+    Some(format!("{PROXY_PATH}?{}", proxied.query()?))
+}
+
+/// Hosts the proxy may fetch from.
+pub async fn allowed_hosts(pool: &SqlitePool) -> Result<Vec<String>, sqlx::Error> {
+    let mut hosts: Vec<String> = DEFAULT_HOSTS.iter().map(|h| h.to_string()).collect();
+    if let Some(extra) = rustfin_db::repo::settings::get(pool, IMAGE_PROXY_HOSTS_KEY).await? {
+        hosts.extend(
+            extra
+                .split(',')
+                .map(|h| h.trim().to_ascii_lowercase())
+                .filter(|h| !h.is_empty()),
+        );
+    }
+    Ok(hosts)
+}
+
+/// `url` normalized, if it is an `http(s)` URL on one of `hosts`: the host is
+/// lowercased and any fragment dropped.
+pub fn allowed_url(url: &str, hosts: &[String]) -> Option<reqwest::Url> {
+    let mut parsed = reqwest::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") || !parsed.username().is_empty() {
+        return None;
+    }
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    if !hosts.contains(&host) {
+        return None;
+    }
+    parsed.set_host(Some(&host)).ok()?;
+    parsed.set_fragment(None);
+    Some(parsed)
+}
+
+/// Where the image for `url` is cached, with its content type, if it has been fetched.
+pub fn cached(images_dir: &Path, url: &reqwest::Url) -> Option<(PathBuf, &'static str)> {
+    let stem = cache_stem(url);
+    IMAGE_TYPES.iter().find_map(|(ext, content_type)| {
+        let path = images_dir.join(format!("{stem}.{ext}"));
+        path.exists().then_some((path, *content_type))
+    })
+}
+
+/// Cache extension and served content type for an upstream `Content-Type`, if it
+/// is an image type we serve.
+pub fn image_type_for(content_type: &str) -> Option<(&'static str, &'static str)> {
+    let essence = content_type.split(';').next()?.trim();
+    IMAGE_TYPES
+        .iter()
+        .find(|(_, ct)| ct.eq_ignore_ascii_case(essence))
+        .copied()
+}
+
+/// Cache path for `url` once fetched with extension `ext`.
+pub fn cache_path(images_dir: &Path, url: &reqwest::Url, ext: &str) -> PathBuf {
+    images_dir.join(format!("{}.{ext}", cache_stem(url)))
+}
+
+fn cache_stem(url: &reqwest::Url) -> String {
+    format!(
+        "proxy_{}",
+        hex::encode(Sha256::digest(url.as_str().as_bytes()))
+    )
+}
+
+/// The body of `resp`, refused once it passes [`MAX_IMAGE_BYTES`] without reading
+/// the rest.
+pub async fn read_limited(mut resp: reqwest::Response) -> Result<Vec<u8>, FetchError> {
+    if resp
+        .content_length()
+        .is_some_and(|len| len > MAX_IMAGE_BYTES as u64)
+    {
+        return Err(FetchError::TooLarge);
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(FetchError::Http)? {
+        if body.len() + chunk.len() > MAX_IMAGE_BYTES {
+            return Err(FetchError::TooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Why [`read_limited`] gave up.
+#[derive(Debug)]
+pub enum FetchError {
+    TooLarge,
+    Http(reqwest::Error),
+}
+
+/// HTTP client for upstream fetches: no redirects, no referer.
+pub fn client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .referer(false)
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_http_urls_on_allowlisted_hosts_are_fetched() {
+        let hosts: Vec<String> = DEFAULT_HOSTS.iter().map(|h| h.to_string()).collect();
+        assert!(allowed_url("https://image.tmdb.org/t/p/w342/a.jpg", &hosts).is_some());
+        assert!(allowed_url("https://IMAGE.tmdb.org/t/p/w342/a.jpg", &hosts).is_some());
+        for url in [
+            "https://example.com/a.jpg",
+            "https://image.tmdb.org.evil.test/a.jpg",
+            "http://169.254.169.254/latest/meta-data",
+            "https://user@image.tmdb.org/a.jpg",
+            "file:///etc/passwd",
+            "/srv/media/poster.jpg",
+        ] {
+            assert!(allowed_url(url, &hosts).is_none(), "{url}");
+        }
+    }
+
+    #[test]
+    fn decorated_urls_share_a_cache_entry() {
+        let hosts: Vec<String> = DEFAULT_HOSTS.iter().map(|h| h.to_string()).collect();
+        let plain = allowed_url("https://image.tmdb.org/t/p/w342/a.jpg", &hosts).unwrap();
+        for url in [
+            "https://IMAGE.TMDB.ORG/t/p/w342/a.jpg",
+            "https://image.tmdb.org/t/p/w342/a.jpg#top",
+        ] {
+            let normalized = allowed_url(url, &hosts).unwrap();
+            assert_eq!(normalized.as_str(), plain.as_str(), "{url}");
+            assert_eq!(cache_stem(&normalized), cache_stem(&plain));
+        }
+        let other = allowed_url("https://image.tmdb.org/t/p/w342/b.jpg", &hosts).unwrap();
+        assert_ne!(cache_stem(&other), cache_stem(&plain));
+    }
+
+    #[test]
+    fn urls_differing_in_their_query_are_cached_apart() {
+        let hosts = vec!["art.example.com".to_string()];
+        let first = allowed_url("https://art.example.com/img?id=1", &hosts).unwrap();
+        let second = allowed_url("https://art.example.com/img?id=2", &hosts).unwrap();
+        assert_eq!(first.as_str(), "https://art.example.com/img?id=1");
+        assert_ne!(cache_stem(&first), cache_stem(&second));
+    }
+
+    #[test]
+    fn proxy_urls_carry_the_encoded_source() {
+        assert_eq!(
+            proxy_url("https://image.tmdb.org/t/p/original/a b.jpg?x=1&y=2").as_deref(),
+            Some(
+                "/api/v1/images/proxy?url=https%3A%2F%2Fimage.tmdb.org%2Ft%2Fp%2Foriginal%2Fa+b.jpg%3Fx%3D1%26y%3D2"
+            )
+        );
+        assert_eq!(proxy_url("/srv/media/poster.jpg"), None);
+    }
+}
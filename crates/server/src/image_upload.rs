@@ -0,0 +1,743 @@
+//! Manual artwork uploads: a resumable, chunked transfer into `cache_dir/uploads`,
+//! then a check of the finished file before it replaces an item's image.
+//!
+//! Uploads are only accepted as JPEG, PNG or WebP, sniffed from the magic bytes
+//! rather than trusted from a name or content type. The dimensions in the header
+//! are checked before anything is decoded, so a small file claiming a huge canvas
+//! (a decompression bomb) is turned away. The file is then rewritten chunk by
+//! chunk with EXIF, XMP and text metadata dropped, and finally decoded and encoded
+//! afresh by ffmpeg, so only its pixels are stored and a file that does not decode
+//! is refused.
+//!
+//! Uploads nobody has written to for [`ABANDONED_AFTER`] are swept, and one user's
+//! uploads in progress may declare at most [`MAX_PENDING_BYTES_PER_USER`] between
+//! them.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use rustfin_transcoder::TranscodeError;
+use rustfin_transcoder::images::ImageCodec;
+use serde::{Deserialize, Serialize};
+
+/// Largest upload accepted, in bytes.
+pub const MAX_UPLOAD_BYTES: u64 = 32 * 1024 * 1024;
+/// Longest side accepted, in pixels.
+pub const MAX_DIMENSION: u32 = 16_384;
+/// Largest canvas accepted, in pixels (about an 8K backdrop with headroom).
+pub const MAX_PIXELS: u64 = 50_000_000;
+/// Uploads with no chunk for this long are deleted.
+pub const ABANDONED_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+/// Most bytes one user's uploads in progress may declare between them.
+pub const MAX_PENDING_BYTES_PER_USER: u64 = 4 * MAX_UPLOAD_BYTES;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+        }
+    }
+
+    fn codec(self) -> ImageCodec {
+        match self {
+            Self::Jpeg => ImageCodec::Jpeg,
+            Self::Png => ImageCodec::Png,
+            Self::Webp => ImageCodec::Webp,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Jpeg => "JPEG",
+            Self::Png => "PNG",
+            Self::Webp => "WebP",
+        }
+    }
+
+    /// The format named by the file's magic bytes, if it is one we accept.
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(PNG_SIGNATURE) {
+            Some(Self::Png)
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why an uploaded file was turned away.
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ImageRejection {
+    #[error("file is not a JPEG, PNG or WebP image")]
+    NotAnImage,
+    #[error("{0} image is malformed: {1}")]
+    Malformed(&'static str, &'static str),
+    #[error(
+        "image is {width}x{height}, larger than the {MAX_DIMENSION}px / {MAX_PIXELS} pixel limit"
+    )]
+    TooLarge { width: u32, height: u32 },
+    #[error("{0} image data could not be decoded")]
+    Undecodable(&'static str),
+}
+
+/// Why an upload could not be normalized.
+#[derive(Debug, thiserror::Error)]
+pub enum NormalizeError {
+    #[error(transparent)]
+    Rejected(#[from] ImageRejection),
+    #[error(transparent)]
+    Transcode(TranscodeError),
+}
+
+/// An accepted upload, ready to store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedImage {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// Check an uploaded file, then decode it and encode it again with ffmpeg. `work`
+/// names the scratch files, which are removed before returning.
+pub async fn normalize(
+    ffmpeg_path: &Path,
+    bytes: &[u8],
+    work: &Path,
+) -> Result<NormalizedImage, NormalizeError> {
+    let stripped = strip(bytes)?;
+    let extension = stripped.format.extension();
+    let input = work.with_extension(format!("in.{extension}"));
+    let output = work.with_extension(format!("out.{extension}"));
+    let reencoded = async {
+        tokio::fs::write(&input, &stripped.bytes).await?;
+        rustfin_transcoder::images::reencode_image(
+            ffmpeg_path,
+            &input,
+            stripped.format.codec(),
+            &output,
+        )
+        .await?;
+        Ok::<_, TranscodeError>(tokio::fs::read(&output).await?)
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&input).await;
+    let _ = tokio::fs::remove_file(&output).await;
+    let reencoded = reencoded.map_err(|e| match e {
+        TranscodeError::FfmpegFailed(_) => {
+            ImageRejection::Undecodable(stripped.format.name()).into()
+        }
+        e => NormalizeError::Transcode(e),
+    })?;
+
+    // What ffmpeg wrote has to be the same picture in the same format.
+    let image = strip(&reencoded)?;
+    if image.format != stripped.format
+        || (image.width, image.height) != (stripped.width, stripped.height)
+    {
+        return Err(ImageRejection::Undecodable(stripped.format.name()).into());
+    }
+    Ok(image)
+}
+
+/// Check an uploaded file's header and rewrite it without metadata, without
+/// decoding any pixels.
+pub fn strip(bytes: &[u8]) -> Result<NormalizedImage, ImageRejection> {
+    let format = ImageFormat::sniff(bytes).ok_or(ImageRejection::NotAnImage)?;
+    let (width, height, bytes) = match format {
+        ImageFormat::Png => normalize_png(bytes)?,
+        ImageFormat::Jpeg => normalize_jpeg(bytes)?,
+        ImageFormat::Webp => normalize_webp(bytes)?,
+    };
+    check_dimensions(format, width, height)?;
+    Ok(NormalizedImage {
+        format,
+        width,
+        height,
+        bytes,
+    })
+}
+
+fn check_dimensions(format: ImageFormat, width: u32, height: u32) -> Result<(), ImageRejection> {
+    if width == 0 || height == 0 {
+        return Err(ImageRejection::Malformed(
+            format.name(),
+            "zero width or height",
+        ));
+    }
+    if width > MAX_DIMENSION || height > MAX_DIMENSION || width as u64 * height as u64 > MAX_PIXELS
+    {
+        return Err(ImageRejection::TooLarge { width, height });
+    }
+    Ok(())
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Ancillary PNG chunks that affect how the image looks; every other ancillary
+/// chunk (text, EXIF, timestamps, animation) is dropped.
+const PNG_KEPT_ANCILLARY: &[&[u8; 4]] = &[
+    b"tRNS", b"gAMA", b"cHRM", b"sRGB", b"iCCP", b"sBIT", b"bKGD", b"pHYs",
+];
+
+fn normalize_png(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), ImageRejection> {
+    let malformed = |why| ImageRejection::Malformed("PNG", why);
+    let mut out = PNG_SIGNATURE.to_vec();
+    let mut pos = PNG_SIGNATURE.len();
+    let mut dims = None;
+    let mut seen_idat = false;
+    loop {
+        let header = bytes
+            .get(pos..pos + 8)
+            .ok_or_else(|| malformed("missing IEND chunk"))?;
+        let len = u32::from_be_bytes(header[..4].try_into().unwrap()) as usize;
+        let kind: &[u8; 4] = header[4..8].try_into().unwrap();
+        let end = pos
+            .checked_add(12 + len)
+            .filter(|&end| end <= bytes.len())
+            .ok_or_else(|| malformed("chunk runs past the end of the file"))?;
+        let data = &bytes[pos + 8..pos + 8 + len];
+        let crc = u32::from_be_bytes(bytes[end - 4..end].try_into().unwrap());
+        if crc32(&bytes[pos + 4..pos + 8 + len]) != crc {
+            return Err(malformed("chunk checksum mismatch"));
+        }
+
+        if dims.is_none() {
+            if kind != b"IHDR" || len != 13 {
+                return Err(malformed("first chunk is not IHDR"));
+            }
+            dims = Some((
+                u32::from_be_bytes(data[..4].try_into().unwrap()),
+                u32::from_be_bytes(data[4..8].try_into().unwrap()),
+            ));
+        }
+        seen_idat |= kind == b"IDAT";
+
+        // Bit 5 of the first type byte marks a chunk as ancillary.
+        let critical = kind[0] & 0x20 == 0;
+        if critical || PNG_KEPT_ANCILLARY.contains(&kind) {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+        if kind == b"IEND" {
+            break;
+        }
+    }
+    if !seen_idat {
+        return Err(malformed("no image data"));
+    }
+    let (width, height) = dims.unwrap();
+    Ok((width, height, out))
+}
+
+fn normalize_jpeg(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), ImageRejection> {
+    let malformed = |why| ImageRejection::Malformed("JPEG", why);
+    let mut out = vec![0xFF, 0xD8];
+    let mut pos = 2;
+    let mut dims = None;
+    loop {
+        // Markers may be preceded by any number of 0xFF fill bytes.
+        while bytes.get(pos) == Some(&0xFF) && bytes.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        let marker = match bytes.get(pos..pos + 2) {
+            Some([0xFF, marker]) => *marker,
+            _ => return Err(malformed("expected a segment marker")),
+        };
+        if marker == 0xD9 {
+            return Err(malformed("no image data"));
+        }
+        let len = bytes
+            .get(pos + 2..pos + 4)
+            .map(|l| u16::from_be_bytes([l[0], l[1]]) as usize)
+            .filter(|&len| len >= 2)
+            .ok_or_else(|| malformed("truncated segment"))?;
+        let end = pos + 2 + len;
+        let data = bytes
+            .get(pos + 4..end)
+            .ok_or_else(|| malformed("segment runs past the end of the file"))?;
+
+        match marker {
+            // Start of frame, except DHT (C4), JPG (C8) and DAC (CC).
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                if data.len() < 5 {
+                    return Err(malformed("truncated frame header"));
+                }
+                let height = u16::from_be_bytes([data[1], data[2]]) as u32;
+                let width = u16::from_be_bytes([data[3], data[4]]) as u32;
+                dims = Some((width, height));
+            }
+            // Start of scan: everything after it is entropy-coded data.
+            0xDA => {
+                let (width, height) = dims.ok_or_else(|| malformed("scan before frame header"))?;
+                out.extend_from_slice(&bytes[pos..]);
+                return Ok((width, height, out));
+            }
+            _ => {}
+        }
+
+        // APP0 (JFIF), APP2 (ICC profile) and APP14 (Adobe colour transform) change
+        // how the image decodes. Other APPn segments (EXIF, XMP) and comments go.
+        let dropped = matches!(marker, 0xE1 | 0xE3..=0xED | 0xEF | 0xFE);
+        if !dropped {
+            out.extend_from_slice(&bytes[pos..end]);
+        }
+        pos = end;
+    }
+}
+
+fn normalize_webp(bytes: &[u8]) -> Result<(u32, u32, Vec<u8>), ImageRejection> {
+    let malformed = |why| ImageRejection::Malformed("WebP", why);
+    let riff_len = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+    let body = bytes
+        .get(12..8 + riff_len)
+        .filter(|_| riff_len >= 4)
+        .ok_or_else(|| malformed("RIFF size runs past the end of the file"))?;
+
+    let mut chunks = Vec::new();
+    let mut dims = None;
+    let mut pos = 0;
+    while pos < body.len() {
+        let header = body
+            .get(pos..pos + 8)
+            .ok_or_else(|| malformed("truncated chunk header"))?;
+        let kind: [u8; 4] = header[..4].try_into().unwrap();
+        let len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let data = body
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| malformed("chunk runs past the end of the file"))?;
+        pos += 8 + len + (len & 1);
+
+        match &kind {
+            b"VP8X" => {
+                if data.len() < 10 {
+                    return Err(malformed("truncated VP8X chunk"));
+                }
+                let width = u32::from_le_bytes([data[4], data[5], data[6], 0]) + 1;
+                let height = u32::from_le_bytes([data[7], data[8], data[9], 0]) + 1;
+                dims = Some((width, height));
+            }
+            b"VP8 " => {
+                if data.len() < 10 || data[3..6] != [0x9D, 0x01, 0x2A] {
+                    return Err(malformed("bad VP8 frame header"));
+                }
+                let width = u16::from_le_bytes([data[6], data[7]]) as u32 & 0x3FFF;
+                let height = u16::from_le_bytes([data[8], data[9]]) as u32 & 0x3FFF;
+                check_dimensions(ImageFormat::Webp, width, height)?;
+                dims.get_or_insert((width, height));
+            }
+            b"VP8L" => {
+                if data.len() < 5 || data[0] != 0x2F {
+                    return Err(malformed("bad VP8L header"));
+                }
+                let bits = u32::from_le_bytes([data[1], data[2], data[3], data[4]]);
+                let (width, height) = ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1);
+                check_dimensions(ImageFormat::Webp, width, height)?;
+                dims.get_or_insert((width, height));
+            }
+            b"EXIF" | b"XMP " => continue,
+            _ => {}
+        }
+        chunks.push((kind, data));
+    }
+    let (width, height) = dims.ok_or_else(|| malformed("no image data"))?;
+
+    let mut out = Vec::with_capacity(bytes.len());
+    out.extend_from_slice(b"RIFF\0\0\0\0WEBP");
+    for (kind, data) in chunks {
+        out.extend_from_slice(&kind);
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        let start = out.len();
+        out.extend_from_slice(data);
+        if &kind == b"VP8X" {
+            // Clear the EXIF (0x08) and XMP (0x04) flags for the chunks just dropped.
+            out[start] &= !0x0C;
+        }
+        if data.len() & 1 == 1 {
+            out.push(0);
+        }
+    }
+    let riff_len = (out.len() - 8) as u32;
+    out[4..8].copy_from_slice(&riff_len.to_le_bytes());
+    Ok((width, height, out))
+}
+
+/// CRC-32 as used by PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// An upload in progress. The bytes received so far sit next to it in
+/// `<id>.part`; its length is the offset the next chunk must start at.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    pub user_id: String,
+    pub item_id: String,
+    pub img_type: String,
+    pub size: u64,
+}
+
+impl UploadSession {
+    pub fn dir(cache_dir: &Path) -> PathBuf {
+        cache_dir.join("uploads")
+    }
+
+    fn meta_path(cache_dir: &Path, id: &str) -> PathBuf {
+        Self::dir(cache_dir).join(format!("{id}.json"))
+    }
+
+    pub fn part_path(&self, cache_dir: &Path) -> PathBuf {
+        Self::dir(cache_dir).join(format!("{}.part", self.id))
+    }
+
+    /// Record a new session with an empty `.part` file.
+    pub async fn create(&self, cache_dir: &Path) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(Self::dir(cache_dir)).await?;
+        tokio::fs::write(self.part_path(cache_dir), b"").await?;
+        let json = serde_json::to_vec(self).map_err(std::io::Error::other)?;
+        tokio::fs::write(Self::meta_path(cache_dir, &self.id), json).await
+    }
+
+    /// The session with this id, if one is in progress. Ids are only ever
+    /// generated UUIDs, so anything else is treated as unknown.
+    pub async fn load(cache_dir: &Path, id: &str) -> std::io::Result<Option<Self>> {
+        if uuid::Uuid::parse_str(id).is_err() {
+            return Ok(None);
+        }
+        match tokio::fs::read(Self::meta_path(cache_dir, id)).await {
+            Ok(json) => Ok(serde_json::from_slice(&json).ok()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Bytes received so far.
+    pub async fn offset(&self, cache_dir: &Path) -> std::io::Result<u64> {
+        Ok(tokio::fs::metadata(self.part_path(cache_dir)).await?.len())
+    }
+
+    /// Append a chunk to the received bytes.
+    pub async fn append(&self, cache_dir: &Path, chunk: &[u8]) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+        let mut file = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(self.part_path(cache_dir))
+            .await?;
+        file.write_all(chunk).await?;
+        file.flush().await
+    }
+
+    /// Delete the session and anything received.
+    pub async fn remove(&self, cache_dir: &Path) {
+        let _ = tokio::fs::remove_file(self.part_path(cache_dir)).await;
+        let _ = tokio::fs::remove_file(Self::meta_path(cache_dir, &self.id)).await;
+    }
+
+    /// Delete uploads nothing has been written to for `max_age`, along with any
+    /// stray file that old, and return the sessions still in progress.
+    pub async fn sweep(cache_dir: &Path, max_age: Duration) -> std::io::Result<Vec<Self>> {
+        let mut entries = match tokio::fs::read_dir(Self::dir(cache_dir)).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let now = SystemTime::now();
+        let stale = |modified: std::io::Result<SystemTime>| {
+            modified
+                .ok()
+                .and_then(|m| now.duration_since(m).ok())
+                .is_some_and(|age| age > max_age)
+        };
+        let mut live = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            if path.extension().is_some_and(|e| e == "json") {
+                let session = tokio::fs::read(&path)
+                    .await
+                    .ok()
+                    .and_then(|json| serde_json::from_slice::<Self>(&json).ok());
+                let Some(session) = session else {
+                    if stale(metadata.modified()) {
+                        let _ = tokio::fs::remove_file(&path).await;
+                    }
+                    continue;
+                };
+                // Each chunk appended touches the `.part` file.
+                let last_write = match tokio::fs::metadata(session.part_path(cache_dir)).await {
+                    Ok(part) => part.modified(),
+                    Err(_) => metadata.modified(),
+                };
+                if stale(last_write) {
+                    tracing::info!(upload_id = %session.id, "removing abandoned image upload");
+                    session.remove(cache_dir).await;
+                } else {
+                    live.push(session);
+                }
+            } else if stale(metadata.modified())
+                && !tokio::fs::try_exists(path.with_extension("json"))
+                    .await
+                    .unwrap_or(true)
+            {
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+        Ok(live)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_chunk(kind: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        let crc = crc32(&chunk[4..]);
+        chunk.extend_from_slice(&crc.to_be_bytes());
+        chunk
+    }
+
+    fn png(width: u32, height: u32, extra: &[Vec<u8>]) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&height.to_be_bytes());
+        ihdr.extend_from_slice(&[8, 2, 0, 0, 0]);
+        let mut bytes = PNG_SIGNATURE.to_vec();
+        bytes.extend(png_chunk(b"IHDR", &ihdr));
+        for chunk in extra {
+            bytes.extend_from_slice(chunk);
+        }
+        // zlib stream of one filtered RGB pixel.
+        bytes.extend(png_chunk(
+            b"IDAT",
+            &[
+                0x78, 0x9C, 0x63, 0xF8, 0xCF, 0xC0, 0x00, 0x00, 0x03, 0x01, 0x01, 0x00,
+            ],
+        ));
+        bytes.extend(png_chunk(b"IEND", &[]));
+        bytes
+    }
+
+    #[test]
+    fn crc32_matches_png_iend() {
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn png_metadata_chunks_are_dropped() {
+        let exif = png_chunk(b"eXIf", b"MM\0*gps");
+        let text = png_chunk(b"tEXt", b"Comment\0hello");
+        let gamma = png_chunk(b"gAMA", &45455u32.to_be_bytes());
+        let image = strip(&png(1, 1, &[exif, text, gamma.clone()])).unwrap();
+        assert_eq!(image.format, ImageFormat::Png);
+        assert_eq!((image.width, image.height), (1, 1));
+        assert_eq!(image.bytes, png(1, 1, &[gamma]));
+    }
+
+    #[test]
+    fn png_with_bad_checksum_is_rejected() {
+        let mut bytes = png(1, 1, &[]);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xFF;
+        assert!(matches!(
+            strip(&bytes),
+            Err(ImageRejection::Malformed("PNG", _))
+        ));
+    }
+
+    #[test]
+    fn huge_dimensions_are_rejected_before_decoding() {
+        assert_eq!(
+            strip(&png(100_000, 100_000, &[])),
+            Err(ImageRejection::TooLarge {
+                width: 100_000,
+                height: 100_000
+            })
+        );
+        assert_eq!(
+            strip(&png(10_000, 10_000, &[])),
+            Err(ImageRejection::TooLarge {
+                width: 10_000,
+                height: 10_000
+            })
+        );
+    }
+
+    fn jpeg_segment(marker: u8, data: &[u8]) -> Vec<u8> {
+        let mut seg = vec![0xFF, marker];
+        seg.extend_from_slice(&((data.len() + 2) as u16).to_be_bytes());
+        seg.extend_from_slice(data);
+        seg
+    }
+
+    fn jpeg(width: u16, height: u16, app1: bool) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend(jpeg_segment(0xE0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0"));
+        if app1 {
+            bytes.extend(jpeg_segment(0xE1, b"Exif\0\0MM\0*"));
+            bytes.extend(jpeg_segment(0xFE, b"shot on a phone"));
+        }
+        let mut sof = vec![8];
+        sof.extend_from_slice(&height.to_be_bytes());
+        sof.extend_from_slice(&width.to_be_bytes());
+        sof.extend_from_slice(&[1, 1, 0x11, 0]);
+        bytes.extend(jpeg_segment(0xC0, &sof));
+        bytes.extend(jpeg_segment(0xDA, &[1, 1, 0, 0, 0x3F, 0]));
+        bytes.extend_from_slice(&[0x12, 0x34, 0xFF, 0x00, 0xFF, 0xD9]);
+        bytes
+    }
+
+    #[test]
+    fn jpeg_exif_and_comments_are_dropped() {
+        let image = strip(&jpeg(640, 480, true)).unwrap();
+        assert_eq!(image.format, ImageFormat::Jpeg);
+        assert_eq!((image.width, image.height), (640, 480));
+        assert_eq!(image.bytes, jpeg(640, 480, false));
+    }
+
+    #[test]
+    fn jpeg_claiming_a_huge_frame_is_rejected() {
+        assert_eq!(
+            strip(&jpeg(65_000, 65_000, false)),
+            Err(ImageRejection::TooLarge {
+                width: 65_000,
+                height: 65_000
+            })
+        );
+    }
+
+    fn webp(chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut body = b"WEBP".to_vec();
+        for (kind, data) in chunks {
+            body.extend_from_slice(*kind);
+            body.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            body.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        bytes.extend(body);
+        bytes
+    }
+
+    fn vp8x(flags: u8, width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![flags, 0, 0, 0];
+        data.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+        data.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+        data
+    }
+
+    fn vp8l(width: u32, height: u32) -> Vec<u8> {
+        let bits = (width - 1) | ((height - 1) << 14);
+        let mut data = vec![0x2F];
+        data.extend_from_slice(&bits.to_le_bytes());
+        data.push(0);
+        data
+    }
+
+    #[test]
+    fn webp_exif_is_dropped_and_flags_cleared() {
+        let bytes = webp(&[
+            (b"VP8X", vp8x(0x08, 300, 200)),
+            (b"VP8L", vp8l(300, 200)),
+            (b"EXIF", b"MM\0*".to_vec()),
+        ]);
+        let image = strip(&bytes).unwrap();
+        assert_eq!(image.format, ImageFormat::Webp);
+        assert_eq!((image.width, image.height), (300, 200));
+        assert_eq!(
+            image.bytes,
+            webp(&[(b"VP8X", vp8x(0, 300, 200)), (b"VP8L", vp8l(300, 200))])
+        );
+    }
+
+    #[test]
+    fn webp_canvas_over_the_limit_is_rejected() {
+        let bytes = webp(&[(b"VP8X", vp8x(0, 16_000, 16_000)), (b"VP8L", vp8l(1, 1))]);
+        assert!(matches!(
+            strip(&bytes),
+            Err(ImageRejection::TooLarge { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn abandoned_uploads_are_swept() {
+        let cache_dir = std::env::temp_dir().join(format!("rf_sweep_{}", uuid::Uuid::new_v4()));
+        let session = |user: &str| UploadSession {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user.into(),
+            item_id: "item".into(),
+            img_type: "poster".into(),
+            size: 10,
+        };
+        let (fresh, abandoned) = (session("a"), session("b"));
+        fresh.create(&cache_dir).await.unwrap();
+        abandoned.create(&cache_dir).await.unwrap();
+        let stray = UploadSession::dir(&cache_dir).join("leftover.in.png");
+        std::fs::write(&stray, b"x").unwrap();
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        for path in [abandoned.part_path(&cache_dir), stray.clone()] {
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(two_days_ago)
+                .unwrap();
+        }
+
+        let live = UploadSession::sweep(&cache_dir, ABANDONED_AFTER)
+            .await
+            .unwrap();
+        assert_eq!(live, vec![fresh.clone()]);
+        assert!(fresh.part_path(&cache_dir).exists());
+        assert!(!abandoned.part_path(&cache_dir).exists());
+        assert!(
+            UploadSession::load(&cache_dir, &abandoned.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(!stray.exists());
+
+        std::fs::remove_dir_all(&cache_dir).ok();
+    }
+
+    #[test]
+    fn text_is_not_an_image() {
+        assert_eq!(
+            strip(b"definitely a poster, trust me\n"),
+            Err(ImageRejection::NotAnImage)
+        );
+        assert_eq!(strip(b""), Err(ImageRejection::NotAnImage));
+    }
+}
@@ -0,0 +1,318 @@
+//! Bounded worker pool for background jobs (scans, metadata refreshes, image
+//! prefetches). A job stays `queued` until it holds one of the pool's slots.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore};
+
+/// Runs at most `max_concurrent` jobs at a time; the rest wait in FIFO order.
+#[derive(Clone, Debug)]
+pub struct JobQueue {
+    permits: Arc<Semaphore>,
+    /// Ids of jobs waiting for a slot, oldest first.
+    waiting: Arc<Mutex<VecDeque<String>>>,
+    /// Library id -> the claim on it: a generation, and the scan job once created.
+    scans: Arc<Mutex<LibraryScans>>,
+}
+
+#[derive(Debug, Default)]
+struct LibraryScans {
+    next_generation: u64,
+    claims: HashMap<String, (u64, Option<String>)>,
+    /// Library id -> held by the scan task writing to it; outlives the claims.
+    writers: HashMap<String, Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl LibraryScans {
+    fn claim(&mut self, library_id: &str) -> u64 {
+        self.next_generation += 1;
+        self.claims
+            .insert(library_id.to_string(), (self.next_generation, None));
+        self.next_generation
+    }
+
+    fn writer(&mut self, library_id: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.writers
+            .entry(library_id.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+impl JobQueue {
+    pub const DEFAULT_MAX_CONCURRENT: usize = 2;
+
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            waiting: Arc::default(),
+            scans: Arc::default(),
+        }
+    }
+
+    /// `RUSTFIN_MAX_CONCURRENT_JOBS`, else the default.
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("RUSTFIN_MAX_CONCURRENT_JOBS")
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(Self::DEFAULT_MAX_CONCURRENT),
+        )
+    }
+
+    /// Wait for a free slot for `job_id`. The job counts as waiting, and has a
+    /// [`position`](Self::position), until this returns or is dropped.
+    pub async fn acquire(&self, job_id: &str) -> JobSlot {
+        let _waiting = Waiting::register(&self.waiting, job_id);
+        // Tokio's semaphore is fair, so slots go out in the order jobs registered.
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("job semaphore is never closed");
+        JobSlot { _permit: permit }
+    }
+
+    /// 1-based place of `job_id` among the jobs waiting for a slot, or `None` when
+    /// it is not waiting.
+    pub fn position(&self, job_id: &str) -> Option<usize> {
+        let waiting = self.waiting.lock().unwrap_or_else(|e| e.into_inner());
+        waiting.iter().position(|id| id == job_id).map(|i| i + 1)
+    }
+}
+
+impl JobQueue {
+    /// Reserve `library_id` for one scan, held until the returned claim is dropped.
+    /// While another claim holds it this fails with that scan's job id, or `None`
+    /// when its job has not been created yet.
+    pub fn claim_library_scan(&self, library_id: &str) -> Result<LibraryScanClaim, Option<String>> {
+        let mut scans = self.scans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, job_id)) = scans.claims.get(library_id) {
+            return Err(job_id.clone());
+        }
+        Ok(self.library_scan_claim(&mut scans, library_id))
+    }
+
+    /// Take `library_id` over from the claim of `finished_job_id`, a job that ended
+    /// (or was cancelled) while its task still holds the claim. Fails like
+    /// [`claim_library_scan`](Self::claim_library_scan) when the claim has moved on.
+    ///
+    /// A cancelled scan keeps writing until it finishes, so the replacement must
+    /// [`wait_for_writer`](LibraryScanClaim::wait_for_writer) before it scans.
+    pub fn replace_library_scan(
+        &self,
+        library_id: &str,
+        finished_job_id: &str,
+    ) -> Result<LibraryScanClaim, Option<String>> {
+        let mut scans = self.scans.lock().unwrap_or_else(|e| e.into_inner());
+        match scans.claims.get(library_id) {
+            Some((_, Some(job_id))) if job_id == finished_job_id => {}
+            Some((_, job_id)) => return Err(job_id.clone()),
+            None => {}
+        }
+        Ok(self.library_scan_claim(&mut scans, library_id))
+    }
+
+    fn library_scan_claim(&self, scans: &mut LibraryScans, library_id: &str) -> LibraryScanClaim {
+        let generation = scans.claim(library_id);
+        let writer = scans.writer(library_id);
+        // Taken right away when free, so an earlier scan can never queue behind it.
+        let writing = writer.clone().try_lock_owned().ok();
+        LibraryScanClaim {
+            scans: self.scans.clone(),
+            library_id: library_id.to_string(),
+            generation,
+            writer,
+            writing,
+        }
+    }
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+/// A running job's slot, released when dropped.
+#[derive(Debug)]
+pub struct JobSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// A library reserved for one scan job, released when dropped.
+#[derive(Debug)]
+pub struct LibraryScanClaim {
+    scans: Arc<Mutex<LibraryScans>>,
+    library_id: String,
+    generation: u64,
+    writer: Arc<tokio::sync::Mutex<()>>,
+    writing: Option<OwnedMutexGuard<()>>,
+}
+
+impl LibraryScanClaim {
+    /// Wait until no earlier scan of the library is still running. From then on
+    /// the library is this claim's to write to until it is dropped.
+    pub async fn wait_for_writer(&mut self) {
+        if self.writing.is_none() {
+            self.writing = Some(self.writer.clone().lock_owned().await);
+        }
+    }
+
+    /// Record the job running the scan, so later requests are pointed at it.
+    pub fn set_job(&self, job_id: &str) {
+        let mut scans = self.scans.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((generation, job)) = scans.claims.get_mut(&self.library_id)
+            && *generation == self.generation
+        {
+            *job = Some(job_id.to_string());
+        }
+    }
+}
+
+impl Drop for LibraryScanClaim {
+    fn drop(&mut self) {
+        let mut scans = self.scans.lock().unwrap_or_else(|e| e.into_inner());
+        // A claim that was taken over belongs to its replacement now.
+        if scans
+            .claims
+            .get(&self.library_id)
+            .is_some_and(|(generation, _)| *generation == self.generation)
+        {
+            scans.claims.remove(&self.library_id);
+        }
+    }
+}
+
+/// Entry in the waiting list, removed when dropped.
+struct Waiting<'a> {
+    waiting: &'a Mutex<VecDeque<String>>,
+    job_id: String,
+}
+
+impl<'a> Waiting<'a> {
+    fn register(waiting: &'a Mutex<VecDeque<String>>, job_id: &str) -> Self {
+        waiting
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_back(job_id.to_string());
+        Self {
+            waiting,
+            job_id: job_id.to_string(),
+        }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.waiting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = waiting.iter().position(|id| *id == self.job_id) {
+            waiting.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waiting_jobs_have_positions_until_a_slot_frees() {
+        let queue = JobQueue::new(1);
+        let running = queue.acquire("a").await;
+        assert_eq!(queue.position("a"), None);
+
+        let (q1, q2) = (queue.clone(), queue.clone());
+        let b = tokio::spawn(async move { q1.acquire("b").await });
+        while queue.position("b").is_none() {
+            tokio::task::yield_now().await;
+        }
+        let c = tokio::spawn(async move { q2.acquire("c").await });
+        while queue.position("c").is_none() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(queue.position("b"), Some(1));
+        assert_eq!(queue.position("c"), Some(2));
+
+        drop(running);
+        let b_slot = b.await.unwrap();
+        assert_eq!(queue.position("b"), None);
+        assert_eq!(queue.position("c"), Some(1));
+
+        drop(b_slot);
+        c.await.unwrap();
+        assert_eq!(queue.position("c"), None);
+    }
+
+    #[test]
+    fn a_library_has_one_scan_claim_at_a_time() {
+        let queue = JobQueue::default();
+        let claim = queue.claim_library_scan("lib").unwrap();
+        assert_eq!(queue.claim_library_scan("lib").unwrap_err(), None);
+        claim.set_job("job-1");
+        assert_eq!(
+            queue.claim_library_scan("lib").unwrap_err(),
+            Some("job-1".to_string())
+        );
+        // Other libraries are unaffected.
+        let _other = queue.claim_library_scan("other").unwrap();
+
+        drop(claim);
+        assert!(queue.claim_library_scan("lib").is_ok());
+    }
+
+    #[test]
+    fn a_finished_scan_claim_can_be_taken_over() {
+        let queue = JobQueue::default();
+        let stale = queue.claim_library_scan("lib").unwrap();
+        stale.set_job("job-1");
+
+        assert_eq!(
+            queue.replace_library_scan("lib", "job-0").unwrap_err(),
+            Some("job-1".to_string())
+        );
+        let claim = queue.replace_library_scan("lib", "job-1").unwrap();
+        claim.set_job("job-2");
+        // The old claim neither overwrites nor releases its replacement.
+        stale.set_job("job-1");
+        drop(stale);
+        assert_eq!(
+            queue.claim_library_scan("lib").unwrap_err(),
+            Some("job-2".to_string())
+        );
+        drop(claim);
+        assert!(queue.claim_library_scan("lib").is_ok());
+    }
+
+    #[tokio::test]
+    async fn a_replacement_scan_waits_for_the_cancelled_one_to_finish() {
+        let queue = JobQueue::default();
+        let mut cancelled = queue.claim_library_scan("lib").unwrap();
+        cancelled.wait_for_writer().await;
+        cancelled.set_job("job-1");
+
+        let mut replacement = queue.replace_library_scan("lib", "job-1").unwrap();
+        replacement.set_job("job-2");
+        let waiting = tokio::spawn(async move {
+            replacement.wait_for_writer().await;
+            replacement
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished(), "both scans would write at once");
+
+        drop(cancelled);
+        let replacement = waiting.await.unwrap();
+        // The writer is held until the replacement is done, too.
+        let mut next = queue.replace_library_scan("lib", "job-2").unwrap();
+        assert!(
+            tokio::time::timeout(std::time::Duration::from_millis(50), next.wait_for_writer())
+                .await
+                .is_err()
+        );
+        drop(replacement);
+        next.wait_for_writer().await;
+    }
+}
@@ -0,0 +1,99 @@
+//! Async locks handed out per key (a cache path, an upload id) and forgotten once
+//! nobody holds or waits on them, so the map only ever holds keys in use.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+type Locks<K> = HashMap<K, Arc<tokio::sync::Mutex<()>>>;
+
+#[derive(Debug)]
+pub struct KeyedLocks<K> {
+    locks: Arc<Mutex<Locks<K>>>,
+}
+
+impl<K> Default for KeyedLocks<K> {
+    fn default() -> Self {
+        Self {
+            locks: Arc::default(),
+        }
+    }
+}
+
+impl<K> Clone for KeyedLocks<K> {
+    fn clone(&self) -> Self {
+        Self {
+            locks: self.locks.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> KeyedLocks<K> {
+    /// A share of the lock for `key`; lock it with `share.lock.lock().await`.
+    pub fn share(&self, key: &K) -> KeyLock<K> {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        KeyLock {
+            locks: self.locks.clone(),
+            key: key.clone(),
+            lock,
+        }
+    }
+
+    /// Whether anyone holds or waits on the lock for `key`.
+    pub fn is_shared(&self, key: &K) -> bool {
+        self.locks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(key)
+    }
+}
+
+/// A caller's share of one key's lock. The lock is forgotten when the last share
+/// is dropped, including one dropped while still waiting.
+pub struct KeyLock<K: Eq + Hash> {
+    locks: Arc<Mutex<Locks<K>>>,
+    key: K,
+    pub lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<K: Eq + Hash> Drop for KeyLock<K> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+        // The map's copy and this one.
+        if Arc::strong_count(&self.lock) == 2 {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn locks_are_forgotten_when_a_waiter_is_dropped() {
+        let locks = KeyedLocks::default();
+        let key = "f_2".to_string();
+        let first = locks.share(&key);
+        let held = first.lock.lock().await;
+
+        // A second caller gives up while waiting behind the first.
+        let waiting = async {
+            let share = locks.share(&key);
+            let _guard = share.lock.lock().await;
+        };
+        let timed_out = tokio::time::timeout(std::time::Duration::from_millis(20), waiting).await;
+        assert!(timed_out.is_err());
+        assert!(locks.is_shared(&key));
+
+        drop(held);
+        drop(first);
+        assert!(!locks.is_shared(&key));
+    }
+}
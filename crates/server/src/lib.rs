@@ -3,12 +3,37 @@
     clippy::ptr_arg,
     clippy::should_implement_trait
 )]
+pub mod access_cache;
+pub mod api_root;
 pub mod artwork;
+pub mod attachments;
+pub mod audit;
 pub mod auth;
+pub mod cache_policy;
+pub mod client_ip;
 pub mod error;
+pub mod image_cache;
+pub mod image_proxy;
+pub mod image_upload;
+pub mod job_queue;
+pub mod keyed_lock;
 pub mod library_scan;
+pub mod openapi;
+pub mod opensubtitles;
+pub mod password_policy;
+pub mod path_policy;
+pub mod playback_policy;
+pub mod probe_cache;
+pub mod provider_policy;
+pub mod release_dates;
+pub mod request_log;
 pub mod routes;
+pub mod serve;
 pub mod setup;
+pub mod startup_scan;
 pub mod state;
 pub mod streaming;
+pub mod subtitle_cache;
+pub mod subtitle_selection;
+pub mod time_zone;
 pub mod user_pipeline;
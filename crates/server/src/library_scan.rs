@@ -10,11 +10,49 @@ pub async fn enqueue_library_scan(
     library_id: &str,
     library_kind: &str,
 ) -> Result<rustfin_db::repo::jobs::JobRow, AppError> {
-    let payload = serde_json::json!({ "library_id": library_id });
+    enqueue_scan_job(state, library_id, library_kind, false).await
+}
+
+/// Enqueue a `library_scan` job that only revisits files earlier scans failed on
+/// (see [`rustfin_scanner::scan::retry_failed_files`]).
+pub async fn enqueue_failed_file_retry(
+    state: &AppState,
+    library_id: &str,
+    library_kind: &str,
+) -> Result<rustfin_db::repo::jobs::JobRow, AppError> {
+    enqueue_scan_job(state, library_id, library_kind, true).await
+}
+
+async fn enqueue_scan_job(
+    state: &AppState,
+    library_id: &str,
+    library_kind: &str,
+    retry_failed: bool,
+) -> Result<rustfin_db::repo::jobs::JobRow, AppError> {
+    // One scan per library at a time: a second request gets the job already
+    // queued or running, whichever kind of scan it is.
+    let mut claimed = state.jobs.claim_library_scan(library_id);
+    if let Err(Some(job_id)) = &claimed {
+        let job = rustfin_db::repo::jobs::get_job(&state.db, job_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        match job {
+            Some(job) if matches!(job.status.as_str(), "queued" | "running") => return Ok(job),
+            // Finished or cancelled, with its task still winding down.
+            _ => claimed = state.jobs.replace_library_scan(library_id, job_id),
+        }
+    }
+    let Ok(claim) = claimed else {
+        return Err(
+            ApiError::Conflict("a scan of this library is already being queued".into()).into(),
+        );
+    };
+    let payload = serde_json::json!({ "library_id": library_id, "retry_failed": retry_failed });
     let job =
         rustfin_db::repo::jobs::create_job(&state.db, "library_scan", Some(&payload.to_string()))
             .await
             .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    claim.set_job(&job.id);
 
     // Spawn scan in background.
     let job_id = job.id.clone();
@@ -22,17 +60,35 @@ pub async fn enqueue_library_scan(
     let lib_id = library_id.to_string();
     let lib_kind = library_kind.to_string();
     let events_tx = state.events.clone();
+    let jobs = state.jobs.clone();
+    let state = state.clone();
     tokio::spawn(async move {
-        if let Err(e) = update_job_status_with_retry(&pool, &job_id, "running", 0.0, None).await {
-            tracing::error!(job_id = %job_id, error = %e, "failed to set job status to running");
-        }
+        let mut claim = claim;
+        // A cancelled scan this one replaces may still be writing to the library.
+        claim.wait_for_writer().await;
+        let Some(_slot) = start_queued_job(&jobs, &pool, &job_id).await else {
+            return;
+        };
         let _ = events_tx.send(crate::state::ServerEvent::JobUpdate {
             job_id: job_id.clone(),
             status: "running".into(),
             progress: 0.0,
         });
+        // Scheduled scans count from here, whether or not this one succeeds.
+        let started = chrono::Utc::now().timestamp();
+        if let Err(e) =
+            rustfin_db::repo::libraries::record_library_scan(&pool, &lib_id, started).await
+        {
+            tracing::warn!(library_id = %lib_id, error = %e, "failed to record scan start");
+        }
 
-        match rustfin_scanner::scan::run_library_scan(&pool, &lib_id, &lib_kind).await {
+        let scan = if retry_failed {
+            rustfin_scanner::scan::retry_failed_files(&pool, &lib_id, &lib_kind).await
+        } else {
+            let options = scan_options(&state);
+            rustfin_scanner::scan::run_library_scan_with(&pool, &lib_id, &lib_kind, &options).await
+        };
+        match scan {
             Ok(result) => {
                 if let Err(err) =
                     crate::artwork::enrich_library_artwork(&pool, &lib_id, &lib_kind).await
@@ -43,14 +99,37 @@ pub async fn enqueue_library_scan(
                         "scan completed but artwork enrichment failed"
                     );
                 }
+                if let Err(err) = crate::probe_cache::probe_new_files(&state, &lib_id).await {
+                    tracing::warn!(
+                        library_id = %lib_id,
+                        error = %err,
+                        "scan completed but probing new files failed"
+                    );
+                }
+                // Items still without a poster fall back to their embedded cover art.
+                if let Err(err) =
+                    crate::attachments::apply_embedded_cover_art(&state, &lib_id).await
+                {
+                    tracing::warn!(
+                        library_id = %lib_id,
+                        error = %err,
+                        "scan completed but embedded cover art lookup failed"
+                    );
+                }
                 tracing::info!(
                     job_id = %job_id,
                     added = result.added,
                     skipped = result.skipped,
+                    errors = result.errors,
+                    removed = result.removed,
                     "scan completed"
                 );
+                // Per-file failures don't fail the job; the count is kept on it instead.
+                let error = (result.errors > 0)
+                    .then(|| format!("{} file(s) could not be scanned", result.errors));
                 if let Err(e) =
-                    update_job_status_with_retry(&pool, &job_id, "completed", 1.0, None).await
+                    update_job_status_with_retry(&pool, &job_id, "completed", 1.0, error.as_deref())
+                        .await
                 {
                     tracing::error!(
                         job_id = %job_id,
@@ -59,15 +138,33 @@ pub async fn enqueue_library_scan(
                     );
                 }
                 let _ = events_tx.send(crate::state::ServerEvent::ScanComplete {
-                    library_id: lib_id,
+                    library_id: lib_id.clone(),
                     job_id: job_id.clone(),
                     items_added: result.added as u64,
+                    files_failed: result.errors as u64,
                 });
                 let _ = events_tx.send(crate::state::ServerEvent::JobUpdate {
                     job_id,
                     status: "completed".into(),
                     progress: 1.0,
                 });
+
+                match rustfin_db::repo::libraries::get_library_prefetch_images(&pool, &lib_id).await
+                {
+                    Ok(true) => {
+                        if let Err(e) = enqueue_image_prefetch(&state, &lib_id).await {
+                            tracing::warn!(
+                                library_id = %lib_id,
+                                status = e.0.status_code(),
+                                "scan completed but image prefetch enqueue failed"
+                            );
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        tracing::warn!(library_id = %lib_id, error = %e, "failed to read image prefetch setting");
+                    }
+                }
             }
             Err(e) => {
                 tracing::error!(job_id = %job_id, error = %e, "scan failed");
@@ -98,6 +195,250 @@ pub async fn enqueue_library_scan(
     Ok(job)
 }
 
+/// Scan options from the environment, probing durations with ffprobe for sample
+/// detection when it is installed.
+fn scan_options(state: &AppState) -> rustfin_scanner::scan::ScanOptions {
+    let mut options = rustfin_scanner::scan::ScanOptions::from_env();
+    if !state.media_tools.ffprobe.is_missing() {
+        let ffprobe = state.transcoder.ffprobe_path().to_path_buf();
+        options.duration_probe = Some(rustfin_scanner::scan::DurationProbe::new(
+            move |path: std::path::PathBuf| {
+                let ffprobe = ffprobe.clone();
+                async move {
+                    let info = rustfin_transcoder::ffprobe::probe(&ffprobe, &path)
+                        .await
+                        .ok()?;
+                    Some(rustfin_scanner::scan::ProbedFile {
+                        duration_secs: Some(info.duration_secs).filter(|secs| *secs > 0.0),
+                        info_json: serde_json::to_string(&info).ok()?,
+                    })
+                }
+            },
+        ));
+    }
+    options
+}
+
+/// Enqueue a `metadata_refresh` job that re-fetches provider metadata and artwork
+/// for every item in the library (see [`crate::artwork::refresh_library_metadata`]).
+pub async fn enqueue_metadata_refresh(
+    state: &AppState,
+    library_id: &str,
+    library_kind: &str,
+    replace: bool,
+) -> Result<rustfin_db::repo::jobs::JobRow, AppError> {
+    let payload = serde_json::json!({ "library_id": library_id, "replace": replace });
+    let job = rustfin_db::repo::jobs::create_job(
+        &state.db,
+        "metadata_refresh",
+        Some(&payload.to_string()),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let job_id = job.id.clone();
+    let pool = state.db.clone();
+    let lib_id = library_id.to_string();
+    let lib_kind = library_kind.to_string();
+    let events_tx = state.events.clone();
+    let jobs = state.jobs.clone();
+    tokio::spawn(async move {
+        let Some(_slot) = start_queued_job(&jobs, &pool, &job_id).await else {
+            return;
+        };
+
+        let on_progress = |done: usize, total: usize| {
+            let pool = pool.clone();
+            let job_id = job_id.clone();
+            let events_tx = events_tx.clone();
+            async move {
+                let progress = if total == 0 {
+                    1.0
+                } else {
+                    done as f64 / total as f64
+                };
+                if let Err(e) = rustfin_db::repo::jobs::update_job_status(
+                    &pool, &job_id, "running", progress, None,
+                )
+                .await
+                {
+                    tracing::warn!(job_id = %job_id, error = %e, "failed to record job progress");
+                }
+                let _ = events_tx.send(crate::state::ServerEvent::JobUpdate {
+                    job_id,
+                    status: "running".into(),
+                    progress,
+                });
+            }
+        };
+
+        let (status, progress, error) = match crate::artwork::refresh_library_metadata(
+            &pool,
+            &lib_id,
+            &lib_kind,
+            replace,
+            on_progress,
+        )
+        .await
+        {
+            Ok(()) => {
+                tracing::info!(job_id = %job_id, library_id = %lib_id, "metadata refresh completed");
+                ("completed", 1.0, None)
+            }
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "metadata refresh failed");
+                ("failed", 0.0, Some(format!("{e:#}")))
+            }
+        };
+
+        if let Err(e) =
+            update_job_status_with_retry(&pool, &job_id, status, progress, error.as_deref()).await
+        {
+            tracing::error!(job_id = %job_id, error = %e, "failed to set final job status");
+        }
+        let _ = events_tx.send(crate::state::ServerEvent::JobUpdate {
+            job_id,
+            status: status.into(),
+            progress,
+        });
+    });
+
+    Ok(job)
+}
+
+/// Enqueue an `image_prefetch` job that downloads a library's artwork into the image
+/// cache (see [`crate::image_cache::prefetch_library_images`]).
+pub async fn enqueue_image_prefetch(
+    state: &AppState,
+    library_id: &str,
+) -> Result<rustfin_db::repo::jobs::JobRow, AppError> {
+    let payload = serde_json::json!({ "library_id": library_id });
+    let job =
+        rustfin_db::repo::jobs::create_job(&state.db, "image_prefetch", Some(&payload.to_string()))
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let job_id = job.id.clone();
+    let pool = state.db.clone();
+    let cache_dir = state.cache_dir.clone();
+    let lib_id = library_id.to_string();
+    let events_tx = state.events.clone();
+    let jobs = state.jobs.clone();
+    tokio::spawn(async move {
+        let Some(_slot) = start_queued_job(&jobs, &pool, &job_id).await else {
+            return;
+        };
+
+        let (status, progress, error) = match crate::image_cache::prefetch_library_images(
+            &pool,
+            &cache_dir,
+            &lib_id,
+            crate::image_cache::PrefetchOptions::from_env(),
+            crate::image_cache::download,
+        )
+        .await
+        {
+            Ok(summary) => {
+                tracing::info!(
+                    job_id = %job_id,
+                    library_id = %lib_id,
+                    cached = summary.cached,
+                    skipped = summary.skipped,
+                    failed = summary.failed,
+                    over_budget = summary.over_budget,
+                    "image prefetch completed"
+                );
+                ("completed", 1.0, None)
+            }
+            Err(e) => {
+                tracing::error!(job_id = %job_id, error = %e, "image prefetch failed");
+                ("failed", 0.0, Some(format!("{e:#}")))
+            }
+        };
+
+        if let Err(e) =
+            update_job_status_with_retry(&pool, &job_id, status, progress, error.as_deref()).await
+        {
+            tracing::error!(job_id = %job_id, error = %e, "failed to set final job status");
+        }
+        let _ = events_tx.send(crate::state::ServerEvent::JobUpdate {
+            job_id,
+            status: status.into(),
+            progress,
+        });
+    });
+
+    Ok(job)
+}
+
+/// How often [`spawn_scan_scheduler`] checks library scan intervals.
+pub const SCAN_SCHEDULE_TICK: Duration = Duration::from_secs(60);
+
+/// Enqueue a scan for every library whose `scan_interval_secs` has elapsed at `now`
+/// (unix seconds) since its last scan. Libraries with a scan still queued or running
+/// are skipped so a slow scan is never stacked.
+pub async fn enqueue_due_scans(
+    state: &AppState,
+    now: i64,
+) -> Result<Vec<rustfin_db::repo::jobs::JobRow>, AppError> {
+    let schedules = rustfin_db::repo::libraries::list_scan_schedules(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let mut jobs = Vec::new();
+    for schedule in schedules {
+        if schedule.scan_active || now - schedule.last_scan_ts < schedule.interval_secs {
+            continue;
+        }
+        tracing::info!(library_id = %schedule.library_id, "starting scheduled scan");
+        jobs.push(enqueue_library_scan(state, &schedule.library_id, &schedule.library_kind).await?);
+    }
+    Ok(jobs)
+}
+
+/// Run [`enqueue_due_scans`] every `tick` for the lifetime of the server.
+pub fn spawn_scan_scheduler(state: AppState, tick: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(tick).await;
+            if let Err(e) = enqueue_due_scans(&state, chrono::Utc::now().timestamp()).await {
+                tracing::warn!(
+                    status = e.0.status_code(),
+                    "failed to enqueue scheduled scans"
+                );
+            }
+        }
+    })
+}
+
+/// Wait for a worker slot, then move the job from `queued` to `running`. `None`
+/// when the job was cancelled while it waited, in which case it must not run.
+async fn start_queued_job(
+    jobs: &crate::job_queue::JobQueue,
+    pool: &sqlx::SqlitePool,
+    job_id: &str,
+) -> Option<crate::job_queue::JobSlot> {
+    let slot = jobs.acquire(job_id).await;
+    let mut last_err: Option<sqlx::Error> = None;
+    for _ in 0..5 {
+        match rustfin_db::repo::jobs::start_job(pool, job_id).await {
+            Ok(true) => return Some(slot),
+            Ok(false) => {
+                tracing::info!(job_id = %job_id, "job is no longer queued, not starting it");
+                return None;
+            }
+            Err(e) => {
+                last_err = Some(e);
+                tokio::time::sleep(Duration::from_millis(120)).await;
+            }
+        }
+    }
+    if let Some(e) = last_err {
+        tracing::error!(job_id = %job_id, error = %e, "failed to set job status to running");
+    }
+    Some(slot)
+}
+
 async fn update_job_status_with_retry(
     pool: &sqlx::SqlitePool,
     job_id: &str,
@@ -3,32 +3,36 @@ use std::path::Path;
 use tracing::info;
 use tracing_subscriber::EnvFilter;
 
-fn probe_binary(path: &Path, name: &str) {
-    match std::process::Command::new(path).arg("-version").output() {
-        Ok(out) if out.status.success() => {
-            tracing::info!(binary = %name, path = %path.display(), "binary available");
+fn log_tool_status(name: &str, status: &rustfin_transcoder::tools::ToolStatus) {
+    use rustfin_transcoder::tools::ToolStatus;
+    match status {
+        ToolStatus::Available { path, version } => {
+            tracing::info!(binary = %name, path = %path, version = ?version, "binary available");
         }
-        Ok(out) => {
-            let stderr = String::from_utf8_lossy(&out.stderr);
+        ToolStatus::Missing { path, error } => {
             tracing::warn!(
                 binary = %name,
-                path = %path.display(),
-                status = %out.status,
-                stderr = %stderr.trim(),
-                "binary check failed; playback/transcoding may fail"
-            );
-        }
-        Err(err) => {
-            tracing::warn!(
-                binary = %name,
-                path = %path.display(),
-                error = %err,
-                "binary is not executable or missing; playback/transcoding may fail"
+                path = %path,
+                error = %error,
+                "binary is not executable or missing; transcoding is unavailable"
             );
         }
+        ToolStatus::Unchecked => {}
     }
 }
 
+/// Read a boolean env var (`1`/`true`/`yes`/`on`, case-insensitive).
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+        .unwrap_or(false)
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Initialize tracing
@@ -40,7 +44,15 @@ async fn main() -> anyhow::Result<()> {
     let db_path = std::env::var("RUSTFIN_DB").unwrap_or_else(|_| "rustfin.db".to_string());
     info!(db_path = %db_path, "connecting to database");
 
-    let pool = rustfin_db::connect(&db_path)
+    // Statements slower than RUSTFIN_SLOW_QUERY_MS are logged at warn
+    let slow_query = std::env::var("RUSTFIN_SLOW_QUERY_MS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(rustfin_db::DEFAULT_SLOW_QUERY_THRESHOLD);
+    let db_opts =
+        rustfin_db::connect_options(&db_path, slow_query).context("invalid database path")?;
+    let pool = rustfin_db::connect_with(db_opts)
         .await
         .context("failed to connect to database")?;
 
@@ -50,6 +62,17 @@ async fn main() -> anyhow::Result<()> {
         .context("failed to run migrations")?;
     info!("migrations complete");
 
+    // Jobs run in-process, so any still queued or running died with the last run.
+    let interrupted = rustfin_db::repo::jobs::fail_interrupted_jobs(&pool)
+        .await
+        .context("failed to reset interrupted jobs")?;
+    if interrupted > 0 {
+        info!(
+            interrupted,
+            "marked jobs interrupted by the last shutdown as failed"
+        );
+    }
+
     // Ensure setup defaults exist (idempotent)
     rustfin_db::repo::settings::insert_defaults(&pool)
         .await
@@ -89,6 +112,14 @@ async fn main() -> anyhow::Result<()> {
         .ok()
         .and_then(|v| v.parse().ok())
         .unwrap_or(4);
+    let min_free_mb: u64 = std::env::var("RUSTFIN_TRANSCODE_MIN_FREE_MB")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1024);
+    let stale_dir_age_secs: u64 = std::env::var("RUSTFIN_TRANSCODE_STALE_DIR_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60 * 60);
     let ffmpeg_path = std::env::var("RUSTFIN_FFMPEG_PATH").unwrap_or_else(|_| "ffmpeg".to_string());
     let ffprobe_path =
         std::env::var("RUSTFIN_FFPROBE_PATH").unwrap_or_else(|_| "ffprobe".to_string());
@@ -98,14 +129,37 @@ async fn main() -> anyhow::Result<()> {
         ffprobe_path: ffprobe_path.clone().into(),
         transcode_dir: transcode_dir.into(),
         max_concurrent: max_transcodes,
+        seek_restart: env_flag("RUSTFIN_HLS_SEEK_RESTART"),
+        hls_single_file: env_flag("RUSTFIN_HLS_SINGLE_FILE"),
+        min_free_bytes: min_free_mb * 1024 * 1024,
+        stale_dir_age_secs,
         ..Default::default()
     };
 
-    probe_binary(Path::new(&ffmpeg_path), "ffmpeg");
-    probe_binary(Path::new(&ffprobe_path), "ffprobe");
+    let media_tools = rustfin_transcoder::tools::MediaTools::detect(
+        Path::new(&ffmpeg_path),
+        Path::new(&ffprobe_path),
+    )
+    .await;
+    log_tool_status("ffmpeg", &media_tools.ffmpeg);
+    log_tool_status("ffprobe", &media_tools.ffprobe);
 
     let session_mgr =
         std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
+    // Nothing is live yet, so session dirs left in the transcode dir are from a
+    // previous run that did not shut down cleanly.
+    session_mgr.remove_stale_session_dirs().await;
+
+    // Spawn stale session dir sweep task, for dirs whose teardown failed while running
+    {
+        let mgr = session_mgr.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(std::time::Duration::from_secs(10 * 60)).await;
+                mgr.remove_stale_session_dirs().await;
+            }
+        });
+    }
 
     // Spawn idle session cleanup task
     {
@@ -118,18 +172,56 @@ async fn main() -> anyhow::Result<()> {
         });
     }
 
+    // Spawn finished-job purge task (0 keeps jobs forever)
+    let job_retention_days: i64 = std::env::var("RUSTFIN_JOB_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    if job_retention_days > 0 {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            loop {
+                let cutoff = chrono::Utc::now().timestamp() - job_retention_days * 86_400;
+                match rustfin_db::repo::jobs::purge_finished_jobs(&pool, cutoff).await {
+                    Ok(0) => {}
+                    Ok(purged) => info!(purged, "purged old finished jobs"),
+                    Err(e) => tracing::warn!(error = %e, "failed to purge old jobs"),
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            }
+        });
+    }
+
     // Cache directory
     let cache_dir: std::path::PathBuf = std::env::var("RUSTFIN_CACHE_DIR")
         .unwrap_or_else(|_| "/tmp/rustfin_cache".to_string())
         .into();
     std::fs::create_dir_all(&cache_dir).context("failed to create cache dir")?;
 
-    // Event broadcast channel
-    let (events_tx, _) = tokio::sync::broadcast::channel::<rustfin_server::state::ServerEvent>(256);
+    // Spawn abandoned image upload sweep
+    {
+        let cache_dir = cache_dir.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = rustfin_server::image_upload::UploadSession::sweep(
+                    &cache_dir,
+                    rustfin_server::image_upload::ABANDONED_AFTER,
+                )
+                .await
+                {
+                    tracing::warn!(error = %e, "failed to sweep abandoned image uploads");
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(3600)).await;
+            }
+        });
+    }
+
+    // Event bus (broadcast plus replay buffer for reconnecting SSE clients)
+    let events = rustfin_server::state::EventBus::default();
 
     // Spawn heartbeat emitter
     {
-        let tx = events_tx.clone();
+        let tx = events.clone();
         tokio::spawn(async move {
             let mut seq = 0u64;
             loop {
@@ -145,17 +237,36 @@ async fn main() -> anyhow::Result<()> {
         jwt_secret,
         transcoder: session_mgr,
         cache_dir,
-        events: events_tx,
+        events,
+        ready: rustfin_server::state::Readiness::default(),
+        streams: rustfin_server::streaming::StreamLimiter::from_env(),
+        subtitles: rustfin_server::subtitle_cache::SubtitleCache::default(),
+        uploads: rustfin_server::keyed_lock::KeyedLocks::default(),
+        limits: rustfin_server::state::RequestLimits::from_env(),
+        media_tools,
+        jobs: rustfin_server::job_queue::JobQueue::from_env(),
     };
 
+    rustfin_server::library_scan::spawn_scan_scheduler(
+        app_state.clone(),
+        rustfin_server::library_scan::SCAN_SCHEDULE_TICK,
+    );
+    rustfin_server::startup_scan::spawn_startup_scans(app_state.clone());
+
+    let ready = app_state.ready.clone();
     let app = rustfin_server::routes::build_router(app_state);
 
+    let tls = rustfin_server::serve::tls_config_from_env()?;
+
     let bind_addr = std::env::var("RUSTFIN_BIND").unwrap_or_else(|_| "0.0.0.0:8096".to_string());
     let listener = tokio::net::TcpListener::bind(&bind_addr)
         .await
         .context("failed to bind")?;
-    info!(addr = %bind_addr, "server listening");
+    info!(addr = %bind_addr, tls = tls.is_some(), "server listening");
+
+    // Bootstrap is done; `/health/ready` can start reporting ready.
+    ready.mark_ready();
 
-    axum::serve(listener, app).await?;
+    rustfin_server::serve::serve(listener, app, tls).await?;
     Ok(())
 }
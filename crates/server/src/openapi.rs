@@ -0,0 +1,113 @@
+//! OpenAPI 3 description of the HTTP API, served at `/api/v1/openapi.json`.
+//!
+//! The document is hand-maintained in `openapi.json` next to this file. The tests
+//! below compare it with the routes registered in `routes.rs`, so adding or removing
+//! an endpoint without updating the document fails the build.
+
+use axum::http::header;
+use axum::response::IntoResponse;
+
+pub const OPENAPI_JSON: &str = include_str!("openapi.json");
+
+pub async fn openapi_document() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "application/json")], OPENAPI_JSON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Router-building functions in `routes.rs` and the prefix each is nested under.
+    const ROUTERS: &[(&str, &str)] = &[
+        ("fn build_router(", ""),
+        ("fn stream_router(", "/stream"),
+        ("fn api_router(", "/api/v1"),
+        ("fn maintenance_router(", "/api/v1"),
+        ("fn setup_router(", "/api/v1/setup"),
+    ];
+    const METHODS: &[&str] = &["get", "post", "put", "patch", "delete"];
+
+    /// `(METHOD, path)` for every `.route(...)` call in `routes.rs`.
+    fn registered_routes() -> BTreeSet<(String, String)> {
+        let source = include_str!("routes.rs");
+        let method_re = regex::Regex::new(r"\b(get|post|put|patch|delete)\(").unwrap();
+        let mut routes = BTreeSet::new();
+
+        for (marker, prefix) in ROUTERS {
+            let start = source.find(marker).expect("router function not found");
+            let body = &source[start..];
+            let body = &body[..body.find("\n}\n").expect("router function end")];
+
+            let mut rest = body;
+            while let Some(pos) = rest.find(".route(") {
+                let call = &rest[pos + ".route(".len()..];
+                let mut depth = 1;
+                let end = call
+                    .char_indices()
+                    .find(|&(_, c)| {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .map(|(i, _)| i)
+                    .expect("unbalanced .route(");
+                let call = &call[..end];
+                let path = call.split('"').nth(1).expect("route path literal");
+                for m in method_re.captures_iter(call) {
+                    routes.insert((m[1].to_uppercase(), format!("{prefix}{path}")));
+                }
+                rest = &rest[pos + ".route(".len() + end..];
+            }
+        }
+        routes
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let doc: serde_json::Value = serde_json::from_str(OPENAPI_JSON).unwrap();
+        let mut routes = BTreeSet::new();
+        for (path, item) in doc["paths"].as_object().unwrap() {
+            for method in METHODS {
+                if item.get(*method).is_some() {
+                    routes.insert((method.to_uppercase(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let registered = registered_routes();
+        let documented = documented_routes();
+        assert!(registered.contains(&("POST".into(), "/api/v1/auth/login".into())));
+
+        let missing: Vec<_> = registered.difference(&documented).collect();
+        assert!(
+            missing.is_empty(),
+            "routes missing from openapi.json: {missing:?}"
+        );
+        let stale: Vec<_> = documented.difference(&registered).collect();
+        assert!(
+            stale.is_empty(),
+            "openapi.json documents unknown routes: {stale:?}"
+        );
+    }
+
+    #[test]
+    fn schema_references_resolve() {
+        let doc: serde_json::Value = serde_json::from_str(OPENAPI_JSON).unwrap();
+        let refs = regex::Regex::new(r##""\$ref": "#/components/(\w+)/(\w+)""##).unwrap();
+        for m in refs.captures_iter(OPENAPI_JSON) {
+            assert!(
+                doc["components"][&m[1]].get(&m[2]).is_some(),
+                "dangling reference #/components/{}/{}",
+                &m[1],
+                &m[2]
+            );
+        }
+    }
+}
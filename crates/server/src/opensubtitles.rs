@@ -0,0 +1,50 @@
+//! OpenSubtitles access for downloading sidecar subtitles.
+//!
+//! The API key is the `opensubtitles_api_key` setting, else `RUSTFIN_OPENSUBTITLES_KEY`.
+//! The API root is the `opensubtitles_base_url` setting, else
+//! `RUSTFIN_OPENSUBTITLES_BASE_URL`, else OpenSubtitles itself. The override is for
+//! tests and local mirrors and must pass [`crate::api_root::allowed`].
+
+use anyhow::Context;
+use rustfin_metadata::opensubtitles::OpenSubtitlesClient;
+use sqlx::SqlitePool;
+
+pub const API_KEY_SETTING: &str = "opensubtitles_api_key";
+pub const BASE_URL_SETTING: &str = "opensubtitles_base_url";
+
+fn non_empty(value: String) -> Option<String> {
+    let trimmed = value.trim();
+    (!trimmed.is_empty()).then(|| trimmed.to_string())
+}
+
+/// The API key and where it came from (`database` or `environment`).
+pub async fn resolve_api_key(pool: &SqlitePool) -> anyhow::Result<Option<(String, &'static str)>> {
+    let db_key = rustfin_db::repo::settings::get(pool, API_KEY_SETTING)
+        .await
+        .context("failed to read opensubtitles_api_key from settings")?
+        .and_then(non_empty);
+    if let Some(key) = db_key {
+        return Ok(Some((key, "database")));
+    }
+    Ok(std::env::var("RUSTFIN_OPENSUBTITLES_KEY")
+        .ok()
+        .and_then(non_empty)
+        .map(|key| (key, "environment")))
+}
+
+/// Client using the configured key and API root. `None` when no API key is configured.
+pub async fn client(pool: &SqlitePool) -> anyhow::Result<Option<OpenSubtitlesClient>> {
+    let Some((key, _)) = resolve_api_key(pool).await? else {
+        return Ok(None);
+    };
+    let base_url = rustfin_db::repo::settings::get(pool, BASE_URL_SETTING)
+        .await
+        .context("failed to read opensubtitles_base_url from settings")?
+        .or_else(|| std::env::var("RUSTFIN_OPENSUBTITLES_BASE_URL").ok());
+    let base_url = crate::api_root::allowed(BASE_URL_SETTING, base_url);
+    let client = OpenSubtitlesClient::new(key);
+    Ok(Some(match base_url {
+        Some(base_url) => client.with_base_url(base_url),
+        None => client,
+    }))
+}
@@ -0,0 +1,206 @@
+//! Password rules shared by the setup wizard's admin account and user management.
+//!
+//! - `password_min_length`: at least this many characters (default 12, 8..=128).
+//! - `password_require_mixed`: at least three of lowercase, uppercase, digits and
+//!   symbols (default off).
+//! - `password_block_common`: refuse well-known passwords, ignoring case (default on).
+//!
+//! Passwords are never longer than [`MAX_PASSWORD_LEN`].
+
+use sqlx::SqlitePool;
+
+pub const PASSWORD_MIN_LENGTH_KEY: &str = "password_min_length";
+pub const PASSWORD_REQUIRE_MIXED_KEY: &str = "password_require_mixed";
+pub const PASSWORD_BLOCK_COMMON_KEY: &str = "password_block_common";
+
+pub const DEFAULT_MIN_LENGTH: u32 = 12;
+/// Bounds an admin may set `password_min_length` to.
+pub const MIN_LENGTH_RANGE: std::ops::RangeInclusive<u32> = 8..=128;
+pub const MAX_PASSWORD_LEN: usize = 1024;
+
+/// Well-known passwords of 8 or more characters, the shortest minimum length an
+/// admin can set.
+const COMMON_PASSWORDS: &[&str] = &[
+    "00000000",
+    "000000000000",
+    "11111111",
+    "111111111111",
+    "123123123",
+    "123123123123",
+    "12341234",
+    "12345678",
+    "123456789",
+    "1234567890",
+    "123456789012",
+    "1234567890123",
+    "12345678910",
+    "1q2w3e4r5t6y",
+    "1qaz2wsx",
+    "87654321",
+    "88888888",
+    "abc12345",
+    "abc123456789",
+    "abcd1234",
+    "admin123",
+    "administrator",
+    "baseball",
+    "changeme",
+    "changeme1234",
+    "football",
+    "iloveyou",
+    "iloveyou1234",
+    "letmein1",
+    "letmein12345",
+    "passw0rd",
+    "password",
+    "password1",
+    "password12",
+    "password1234",
+    "password12345",
+    "password123456",
+    "passwordpassword",
+    "princess",
+    "qwerty12",
+    "qwerty123",
+    "qwerty123456",
+    "qwertyuiop",
+    "qwertyuiop123",
+    "qwertyuiopasdf",
+    "rustyfin1234",
+    "starwars",
+    "sunshine",
+    "superman",
+    "trustno1",
+    "welcome1",
+    "welcome12345",
+    "whatever",
+    "zaq12wsx",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    pub min_length: u32,
+    pub require_mixed: bool,
+    pub block_common: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_MIN_LENGTH,
+            require_mixed: false,
+            block_common: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// The stored policy; unset or invalid values fall back to the defaults.
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        let default = Self::default();
+        let setting = |key| rustfin_db::repo::settings::get(pool, key);
+        Ok(Self {
+            min_length: setting(PASSWORD_MIN_LENGTH_KEY)
+                .await?
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|n| MIN_LENGTH_RANGE.contains(n))
+                .unwrap_or(default.min_length),
+            require_mixed: setting(PASSWORD_REQUIRE_MIXED_KEY)
+                .await?
+                .map_or(default.require_mixed, |v| v.trim() == "true"),
+            block_common: setting(PASSWORD_BLOCK_COMMON_KEY)
+                .await?
+                .map_or(default.block_common, |v| v.trim() != "false"),
+        })
+    }
+
+    /// Reasons `password` is refused, empty when it is acceptable.
+    pub fn check(&self, password: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let len = password.chars().count();
+        if len < self.min_length as usize || password.len() > MAX_PASSWORD_LEN {
+            problems.push(format!(
+                "must be between {} and {MAX_PASSWORD_LEN} characters",
+                self.min_length
+            ));
+        }
+        if self.require_mixed && character_classes(password) < 3 {
+            problems.push(
+                "must use at least three of: lowercase letters, uppercase letters, digits, symbols"
+                    .to_string(),
+            );
+        }
+        if self.block_common
+            && COMMON_PASSWORDS
+                .iter()
+                .any(|common| common.eq_ignore_ascii_case(password))
+        {
+            problems.push("is too common".to_string());
+        }
+        problems
+    }
+}
+
+fn character_classes(password: &str) -> usize {
+    [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_numeric()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .into_iter()
+    .filter(|&present| present)
+    .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STRICT: PasswordPolicy = PasswordPolicy {
+        min_length: 16,
+        require_mixed: true,
+        block_common: true,
+    };
+
+    #[test]
+    fn weak_passwords_pass_the_default_policy_but_not_a_strict_one() {
+        let weak = "lowercaseonly";
+        assert!(PasswordPolicy::default().check(weak).is_empty());
+        assert_eq!(STRICT.check(weak).len(), 2);
+        assert!(STRICT.check("Mixed-Case-And-42").is_empty());
+    }
+
+    #[test]
+    fn common_passwords_are_refused_ignoring_case_unless_allowed() {
+        let default = PasswordPolicy::default();
+        assert_eq!(default.check("Password1234"), vec!["is too common"]);
+        let allowed = PasswordPolicy {
+            block_common: false,
+            ..default
+        };
+        assert!(allowed.check("Password1234").is_empty());
+    }
+
+    #[test]
+    fn short_common_passwords_are_refused_at_the_lowest_minimum() {
+        let policy = PasswordPolicy {
+            min_length: *MIN_LENGTH_RANGE.start(),
+            ..PasswordPolicy::default()
+        };
+        for common in ["password", "12345678", "iloveyou", "Qwertyuiop"] {
+            assert_eq!(policy.check(common), vec!["is too common"], "{common}");
+        }
+        assert!(policy.check("kettle-drum").is_empty());
+    }
+
+    #[test]
+    fn length_counts_characters_not_bytes() {
+        let policy = PasswordPolicy {
+            min_length: 8,
+            ..PasswordPolicy::default()
+        };
+        assert!(!policy.check("ééééééé").is_empty());
+        assert!(policy.check("éééééééé").is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+//! How streaming decides that a media file lies inside a library.
+//!
+//! `library_symlinks` is `resolve` (the default) or `follow`:
+//!
+//! - `resolve`: both the file and the library roots are fully canonicalized. A
+//!   symlink inside a library that points elsewhere, common with NAS media trees,
+//!   is refused.
+//! - `follow`: the configured library path is the boundary. The file's stored path
+//!   only has to sit under it, wherever its symlinks lead.
+//!
+//! A path with a `..` component is refused in both modes.
+
+use std::path::{Component, Path, PathBuf};
+
+use sqlx::SqlitePool;
+
+pub const LIBRARY_SYMLINKS_KEY: &str = "library_symlinks";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LibrarySymlinks {
+    #[default]
+    Resolve,
+    Follow,
+}
+
+impl LibrarySymlinks {
+    pub const ALL: [Self; 2] = [Self::Resolve, Self::Follow];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Resolve => "resolve",
+            Self::Follow => "follow",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+
+    /// The stored policy, falling back to the default if unset or unknown.
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(rustfin_db::repo::settings::get(pool, LIBRARY_SYMLINKS_KEY)
+            .await?
+            .and_then(|v| Self::parse(v.trim()))
+            .unwrap_or_default())
+    }
+}
+
+/// `path` without `.` components, or `None` if it is relative or has a `..`.
+pub fn lexical_path(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => return None,
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
+
+/// Whether `file` lies under the library root `root` under `policy`.
+pub fn is_within_root(file: &Path, root: &Path, policy: LibrarySymlinks) -> bool {
+    let Some(file) = lexical_path(file) else {
+        return false;
+    };
+    if policy == LibrarySymlinks::Follow
+        && lexical_path(root).is_some_and(|root| file.starts_with(root))
+    {
+        return true;
+    }
+    match (file.canonicalize(), root.canonicalize()) {
+        (Ok(file), Ok(root)) => file.starts_with(root),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lexical_path_drops_dots_and_refuses_parents() {
+        assert_eq!(
+            lexical_path(Path::new("/media/./movies/a.mkv")),
+            Some(PathBuf::from("/media/movies/a.mkv"))
+        );
+        assert_eq!(lexical_path(Path::new("/media/movies/../etc/passwd")), None);
+        assert_eq!(lexical_path(Path::new("movies/a.mkv")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_trees_are_only_inside_the_root_when_followed() {
+        let tmp = std::env::temp_dir().join(format!("rf_path_policy_{}", uuid::Uuid::new_v4()));
+        let root = tmp.join("library");
+        let nas = tmp.join("nas");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::create_dir_all(&nas).unwrap();
+        std::fs::write(nas.join("a.mkv"), b"x").unwrap();
+        std::os::unix::fs::symlink(&nas, root.join("linked")).unwrap();
+        std::fs::write(root.join("b.mkv"), b"x").unwrap();
+
+        let linked = root.join("linked").join("a.mkv");
+        assert!(!is_within_root(&linked, &root, LibrarySymlinks::Resolve));
+        assert!(is_within_root(&linked, &root, LibrarySymlinks::Follow));
+        for policy in LibrarySymlinks::ALL {
+            assert!(is_within_root(&root.join("b.mkv"), &root, policy));
+            assert!(!is_within_root(&nas.join("a.mkv"), &root, policy));
+            let escape = root.join("..").join("nas").join("a.mkv");
+            assert!(!is_within_root(&escape, &root, policy));
+        }
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
+}
@@ -0,0 +1,116 @@
+//! Server-wide switches for how media may be delivered.
+//!
+//! `direct_play_enabled` turns off `/stream/file` for local media so every client
+//! goes through a transcoding session; `direct_play_admin_override` keeps it open
+//! for admins. Remote (`.strm`) items are unaffected since they cannot be transcoded.
+//!
+//! `allow_remote_access` off keeps direct streams and transcoding sessions to clients
+//! on the local network (see [`crate::client_ip`]); `remote_access_admin_override`
+//! lets admins stream from anywhere regardless.
+//!
+//! `max_streams_per_user` and `max_streams_per_admin` cap how many transcoding
+//! sessions and direct file reads one account may have open at once, so a shared
+//! login can't hold dozens of streams. `0` means no limit; admins have none unless
+//! one is set.
+
+use sqlx::SqlitePool;
+
+pub const DIRECT_PLAY_ENABLED_KEY: &str = "direct_play_enabled";
+pub const DIRECT_PLAY_ADMIN_OVERRIDE_KEY: &str = "direct_play_admin_override";
+pub const ALLOW_REMOTE_ACCESS_KEY: &str = "allow_remote_access";
+pub const REMOTE_ACCESS_ADMIN_OVERRIDE_KEY: &str = "remote_access_admin_override";
+
+pub const MAX_STREAMS_PER_USER_KEY: &str = "max_streams_per_user";
+pub const MAX_STREAMS_PER_ADMIN_KEY: &str = "max_streams_per_admin";
+pub const DEFAULT_MAX_STREAMS_PER_USER: u32 = 3;
+
+/// How long a client over its stream limit is told to wait before retrying.
+pub const STREAM_LIMIT_RETRY_AFTER_SECS: u64 = 10;
+
+/// Message returned when a client asks for a direct stream while it is disabled.
+pub const DIRECT_PLAY_DISABLED_MESSAGE: &str =
+    "direct play is disabled on this server; start a playback session at /api/v1/playback/sessions";
+
+/// Direct play stays on unless an admin explicitly stored `false`.
+pub async fn direct_play_enabled(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(
+        rustfin_db::repo::settings::get(pool, DIRECT_PLAY_ENABLED_KEY)
+            .await?
+            .is_none_or(|v| v.trim() != "false"),
+    )
+}
+
+pub async fn direct_play_admin_override(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(
+        rustfin_db::repo::settings::get(pool, DIRECT_PLAY_ADMIN_OVERRIDE_KEY)
+            .await?
+            .is_some_and(|v| v.trim() == "true"),
+    )
+}
+
+/// Whether an account with `role` may stream local files directly.
+pub async fn direct_play_allowed(pool: &SqlitePool, role: &str) -> Result<bool, sqlx::Error> {
+    if direct_play_enabled(pool).await? {
+        return Ok(true);
+    }
+    Ok(role == "admin" && direct_play_admin_override(pool).await?)
+}
+
+/// Message returned when a client outside the local network asks for a stream
+/// while remote access is off.
+pub const REMOTE_ACCESS_DISABLED_MESSAGE: &str =
+    "remote access is disabled on this server; streaming is only available on the local network";
+
+/// Remote access stays off unless it was explicitly turned on.
+pub async fn allow_remote_access(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(
+        rustfin_db::repo::settings::get(pool, ALLOW_REMOTE_ACCESS_KEY)
+            .await?
+            .is_some_and(|v| v.trim() == "true"),
+    )
+}
+
+pub async fn remote_access_admin_override(pool: &SqlitePool) -> Result<bool, sqlx::Error> {
+    Ok(
+        rustfin_db::repo::settings::get(pool, REMOTE_ACCESS_ADMIN_OVERRIDE_KEY)
+            .await?
+            .is_some_and(|v| v.trim() == "true"),
+    )
+}
+
+/// Whether an account with `role` may stream from outside the local network.
+pub async fn remote_streaming_allowed(pool: &SqlitePool, role: &str) -> Result<bool, sqlx::Error> {
+    if allow_remote_access(pool).await? {
+        return Ok(true);
+    }
+    Ok(role == "admin" && remote_access_admin_override(pool).await?)
+}
+
+async fn stream_limit_setting(
+    pool: &SqlitePool,
+    key: &str,
+    default: u32,
+) -> Result<u32, sqlx::Error> {
+    Ok(rustfin_db::repo::settings::get(pool, key)
+        .await?
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default))
+}
+
+pub async fn max_streams_per_user(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+    stream_limit_setting(pool, MAX_STREAMS_PER_USER_KEY, DEFAULT_MAX_STREAMS_PER_USER).await
+}
+
+pub async fn max_streams_per_admin(pool: &SqlitePool) -> Result<u32, sqlx::Error> {
+    stream_limit_setting(pool, MAX_STREAMS_PER_ADMIN_KEY, 0).await
+}
+
+/// Most streams an account with `role` may have open at once; `None` means no limit.
+pub async fn stream_limit(pool: &SqlitePool, role: &str) -> Result<Option<usize>, sqlx::Error> {
+    let limit = if role == "admin" {
+        max_streams_per_admin(pool).await?
+    } else {
+        max_streams_per_user(pool).await?
+    };
+    Ok(Some(limit as usize).filter(|&l| l > 0))
+}
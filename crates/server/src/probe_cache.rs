@@ -0,0 +1,124 @@
+//! Probe results kept in `media_file_probe`, with each file's track languages in
+//! `media_file_language` for language listings and filters.
+//!
+//! Probes are keyed by a file's path, size and mtime. A file is recorded whenever
+//! the server probes it for playback, and after each library scan for files
+//! without a probe at their current size and mtime. Scans pick up the new size
+//! and mtime of a file replaced in place, so it is probed again.
+//!
+//! A scan that checks durations for samples records its probes first, by path,
+//! and those are reused here rather than probing again.
+
+use std::path::Path;
+
+use anyhow::Context;
+use rustfin_transcoder::ffprobe::MediaInfo;
+use sqlx::SqlitePool;
+
+use crate::state::AppState;
+
+/// Store `info` as the latest probe of `file_id`. Failures are logged, not returned:
+/// the probe itself succeeded and the caller still has it.
+pub async fn record(pool: &SqlitePool, file_id: &str, info: &MediaInfo) {
+    let json = match serde_json::to_string(info) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::warn!(file_id = %file_id, error = %e, "failed to serialize probe result");
+            return;
+        }
+    };
+    let audio: Vec<String> = info
+        .audio
+        .iter()
+        .filter_map(|a| a.language.clone())
+        .collect();
+    let subtitles: Vec<String> = info
+        .subtitles
+        .iter()
+        .filter_map(|s| s.language.clone())
+        .collect();
+    let duration = Some(info.duration_secs).filter(|secs| *secs > 0.0);
+    if let Err(e) =
+        rustfin_db::repo::probes::record_probe(pool, file_id, duration, &json, &audio, &subtitles)
+            .await
+    {
+        tracing::warn!(file_id = %file_id, error = %e, "failed to store probe result");
+    }
+}
+
+/// The stored probe of `file_id` if it still matches the file on disk, else a
+/// fresh one, which is recorded. `None` when the file can't be probed.
+pub async fn cached_or_probe(state: &AppState, file_id: &str, path: &Path) -> Option<MediaInfo> {
+    // The file may have been replaced since the last scan.
+    if let Ok(metadata) = tokio::fs::metadata(path).await {
+        let mtime = rustfin_scanner::walk::mtime_ts(&metadata);
+        if let Err(e) = rustfin_db::repo::media_files::update_file_stat(
+            &state.db,
+            file_id,
+            metadata.len() as i64,
+            mtime,
+        )
+        .await
+        {
+            tracing::warn!(file_id = %file_id, error = %e, "failed to update file size and mtime");
+        }
+    }
+    match rustfin_db::repo::probes::get_file_probe(&state.db, file_id).await {
+        Ok(Some(json)) => {
+            if let Ok(info) = serde_json::from_str(&json) {
+                return Some(info);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!(file_id = %file_id, error = %e, "failed to read stored probe"),
+    }
+    match rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), path).await {
+        Ok(info) => {
+            record(&state.db, file_id, &info).await;
+            Some(info)
+        }
+        Err(e) => {
+            tracing::debug!(file_id = %file_id, error = %e, "probe failed");
+            None
+        }
+    }
+}
+
+/// Record every local file of the library without a probe at its current size
+/// and mtime, from the scan's own probe when it took one. Returns how many were
+/// recorded; files that fail to probe are skipped until the next scan.
+pub async fn probe_new_files(state: &AppState, library_id: &str) -> anyhow::Result<usize> {
+    if state.media_tools.ffprobe.is_missing() {
+        return Ok(0);
+    }
+    let files = rustfin_db::repo::probes::list_unprobed_files(&state.db, library_id)
+        .await
+        .context("failed to list unprobed files")?;
+    let mut recorded = 0;
+    for file in files {
+        let scanned = file
+            .info_json
+            .and_then(|json| serde_json::from_str::<MediaInfo>(&json).ok());
+        let info = match scanned {
+            Some(info) => info,
+            None => {
+                let path = Path::new(&file.path);
+                if !path.is_file() {
+                    continue;
+                }
+                match rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), path)
+                    .await
+                {
+                    Ok(info) => info,
+                    Err(e) => {
+                        tracing::debug!(file_id = %file.file_id, error = %e, "probe failed");
+                        continue;
+                    }
+                }
+            }
+        };
+        record(&state.db, &file.file_id, &info).await;
+        recorded += 1;
+    }
+    Ok(recorded)
+}
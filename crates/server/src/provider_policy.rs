@@ -0,0 +1,93 @@
+//! What happens when an item is given a provider id that another item of the same
+//! kind in its library already holds — usually a mis-scan or a wrong match.
+//!
+//! `provider_id_conflicts` is `warn` (store it and log the clash), `reject` (leave
+//! the item as it was) or `allow` (no check). Clashes already stored are listed by
+//! `GET /system/provider-id-conflicts`.
+
+use sqlx::SqlitePool;
+
+pub const PROVIDER_ID_CONFLICTS_KEY: &str = "provider_id_conflicts";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProviderIdConflictPolicy {
+    Allow,
+    #[default]
+    Warn,
+    Reject,
+}
+
+impl ProviderIdConflictPolicy {
+    pub const ALL: [Self; 3] = [Self::Allow, Self::Warn, Self::Reject];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Reject => "reject",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+
+    /// The stored policy, falling back to the default if unset or unknown.
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(
+            rustfin_db::repo::settings::get(pool, PROVIDER_ID_CONFLICTS_KEY)
+                .await?
+                .and_then(|v| Self::parse(v.trim()))
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Outcome of [`assign_provider_id`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderIdAssignment {
+    /// Whether the id was stored on the item.
+    pub stored: bool,
+    /// Other items in the library that already held the id.
+    pub conflicts: Vec<String>,
+}
+
+/// Store `provider_id` on an item after checking it against the other items of the
+/// same kind in its library, as the server's [`ProviderIdConflictPolicy`] says.
+pub async fn assign_provider_id(
+    pool: &SqlitePool,
+    item_id: &str,
+    provider: &str,
+    provider_id: &str,
+) -> Result<ProviderIdAssignment, sqlx::Error> {
+    let policy = ProviderIdConflictPolicy::load(pool).await?;
+    let conflicts = if policy == ProviderIdConflictPolicy::Allow {
+        Vec::new()
+    } else {
+        rustfin_db::repo::duplicates::provider_id_owners(pool, item_id, provider, provider_id)
+            .await?
+    };
+
+    if !conflicts.is_empty() {
+        tracing::warn!(
+            item_id = %item_id,
+            provider = %provider,
+            provider_id = %provider_id,
+            ?conflicts,
+            policy = policy.as_str(),
+            "provider id is already used by another item in the library"
+        );
+        if policy == ProviderIdConflictPolicy::Reject {
+            return Ok(ProviderIdAssignment {
+                stored: false,
+                conflicts,
+            });
+        }
+    }
+
+    rustfin_metadata::merge::set_provider_id(pool, item_id, provider, provider_id).await?;
+    Ok(ProviderIdAssignment {
+        stored: true,
+        conflicts,
+    })
+}
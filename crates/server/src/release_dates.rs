@@ -0,0 +1,67 @@
+//! Premiere dates as providers and NFO files store them.
+//!
+//! Most are `YYYY-MM-DD`, but some carry a time (`2024-03-01T00:00:00Z`) and some
+//! only a year or a month. A partial date stands for the first day it covers.
+
+use chrono::NaiveDate;
+
+/// The day `raw` names, or `None` when it is not a recognisable date.
+pub fn parse_premiere_date(raw: &str) -> Option<NaiveDate> {
+    let date = raw.trim().split(['T', ' ']).next()?;
+    let mut parts = date.split(['-', '/', '.']);
+    let year = parts.next().filter(|y| y.len() == 4)?.parse().ok()?;
+    let month = match parts.next() {
+        Some(month) => month.parse().ok()?,
+        None => 1,
+    };
+    let day = match parts.next() {
+        Some(day) => day.parse().ok()?,
+        None => 1,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(y, m, d)
+    }
+
+    #[test]
+    fn full_dates_and_timestamps_parse() {
+        assert_eq!(parse_premiere_date("2024-03-15"), ymd(2024, 3, 15));
+        assert_eq!(parse_premiere_date(" 2024-03-15 "), ymd(2024, 3, 15));
+        assert_eq!(
+            parse_premiere_date("2024-03-15T20:00:00Z"),
+            ymd(2024, 3, 15)
+        );
+        assert_eq!(parse_premiere_date("2024-03-15 20:00:00"), ymd(2024, 3, 15));
+        assert_eq!(parse_premiere_date("2024/03/15"), ymd(2024, 3, 15));
+    }
+
+    #[test]
+    fn partial_dates_stand_for_their_first_day() {
+        assert_eq!(parse_premiere_date("2024-03"), ymd(2024, 3, 1));
+        assert_eq!(parse_premiere_date("2024"), ymd(2024, 1, 1));
+    }
+
+    #[test]
+    fn unrecognisable_dates_are_rejected() {
+        for raw in [
+            "",
+            "soon",
+            "15-03-2024",
+            "24-03-15",
+            "2024-13-01",
+            "2024-02-30",
+            "2024-03-15-1",
+        ] {
+            assert_eq!(parse_premiere_date(raw), None, "{raw}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Warn about slow `/api/v1` requests so performance problems show up in the logs.
+//!
+//! Requests are identified by method and route template (`/api/v1/items/{id}`),
+//! never by the concrete path or query string, so item ids, media paths and
+//! tokens stay out of the log.
+
+use std::time::{Duration, Instant};
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Route logged for requests that matched no route.
+const UNMATCHED_ROUTE: &str = "<unmatched>";
+
+/// Log a `warn` event for any request whose response took longer than `threshold`.
+pub async fn log_slow_requests(
+    State(threshold): State<Duration>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let method = req.method().clone();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string());
+    let started = Instant::now();
+    let response = next.run(req).await;
+    let elapsed = started.elapsed();
+    if elapsed > threshold {
+        tracing::warn!(
+            method = %method,
+            route = route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+            status = response.status().as_u16(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            "slow request"
+        );
+    }
+    response
+}
@@ -1,15 +1,22 @@
 use axum::extract::{Path, Query, State};
-use axum::routing::{get, post};
+use axum::routing::{get, patch, post};
 use axum::{Extension, Json, Router};
 use rustfin_core::error::ApiError;
+use rustfin_core::preferences::UserPreferences;
+use rustfin_core::types::{ItemSortBy, LibraryKind, SortOrder};
+use rustfin_scanner::scan::SpecialsPolicy;
+use rustfin_transcoder::decision::{
+    Delivery, DeviceProfile, PlayMethod, StreamAction, TranscodeReason,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::auth::{
     AdminUser, AuthUser, issue_stream_token, issue_token, validate_stream_token, validate_token,
 };
 use crate::error::AppError;
+use crate::provider_policy::ProviderIdConflictPolicy;
 use crate::setup::rate_limit::RateLimiter;
 use crate::state::AppState;
 use crate::user_pipeline;
@@ -60,16 +67,62 @@ fn resolve_stream_request_identity(
 }
 
 pub fn build_router(state: AppState) -> Router {
+    let limits = state.limits;
     Router::new()
         .route("/health", get(health))
-        .nest("/api/v1", api_router())
+        .route("/health/live", get(health_live))
+        .route("/health/ready", get(health_ready))
+        .nest(
+            "/api/v1",
+            api_router()
+                .layer(tower_http::timeout::TimeoutLayer::with_status_code(
+                    axum::http::StatusCode::REQUEST_TIMEOUT,
+                    limits.timeout,
+                ))
+                .merge(maintenance_router().layer(
+                    tower_http::timeout::TimeoutLayer::with_status_code(
+                        axum::http::StatusCode::REQUEST_TIMEOUT,
+                        limits.maintenance_timeout,
+                    ),
+                ))
+                // The tower-http limit replaces axum's built-in 2 MiB extractor cap.
+                .layer(axum::extract::DefaultBodyLimit::disable())
+                .layer(tower_http::limit::RequestBodyLimitLayer::new(
+                    limits.max_body_bytes,
+                ))
+                .layer(axum::middleware::map_response(
+                    crate::error::envelope_layer_errors,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    limits.slow_request,
+                    crate::request_log::log_slow_requests,
+                )),
+        )
         .nest("/stream", stream_router())
+        .fallback(route_not_found)
+        .method_not_allowed_fallback(method_not_allowed)
         .with_state(state)
 }
 
+async fn route_not_found(uri: axum::http::Uri) -> AppError {
+    ApiError::NotFound(format!("no route for {}", uri.path())).into()
+}
+
+async fn method_not_allowed(method: axum::http::Method, uri: axum::http::Uri) -> AppError {
+    ApiError::MethodNotAllowed(format!("{method} is not supported for {}", uri.path())).into()
+}
+
 fn stream_router() -> Router<AppState> {
     Router::new()
         .route("/file/{file_id}", get(crate::streaming::stream_file_range))
+        .route(
+            "/file/{file_id}/subtitles/{index}",
+            get(crate::streaming::stream_embedded_subtitle),
+        )
+        .route(
+            "/file/{file_id}/attachments/{index}",
+            get(crate::streaming::stream_attachment),
+        )
         .route("/hls/{sid}/master.m3u8", get(hls_master))
         .route("/hls/{sid}/{filename}", get(hls_segment))
         .route("/subtitles/{sub_path}", get(serve_subtitle))
@@ -82,6 +135,7 @@ fn api_router() -> Router<AppState> {
             "/system/info/public",
             get(crate::setup::handlers::get_public_system_info),
         )
+        .route("/openapi.json", get(crate::openapi::openapi_document))
         // Setup routes
         .nest("/setup", setup_router())
         .route("/auth/login", post(auth_login))
@@ -94,6 +148,7 @@ fn api_router() -> Router<AppState> {
         .route("/users/me/preferences", get(get_prefs).patch(update_prefs))
         // Libraries
         .route("/libraries", post(create_library).get(list_libraries))
+        .route("/libraries/order", patch(set_library_order))
         .route(
             "/libraries/{id}",
             get(get_library)
@@ -101,31 +156,94 @@ fn api_router() -> Router<AppState> {
                 .delete(delete_library),
         )
         .route("/libraries/{id}/scan", post(scan_library))
+        .route(
+            "/libraries/{id}/refresh-metadata",
+            post(refresh_library_metadata),
+        )
         .route("/libraries/{id}/items", get(list_library_items))
+        .route("/libraries/{id}/studios", get(list_library_studios))
+        .route("/libraries/{id}/languages", get(list_library_languages))
         // Items
+        .route("/items/recent-releases", get(list_recent_releases))
         .route("/items/{id}", get(get_item))
         .route("/items/{id}/playback", get(get_item_playback))
+        .route("/items/{id}/playback-info", get(get_item_playback_info))
         .route("/items/{id}/children", get(get_item_children))
+        .route("/items/{id}/episodes", get(get_series_episodes))
         .route("/items/{id}/subtitles", get(get_item_subtitles))
+        .route("/items/{id}/attachments", get(get_item_attachments))
+        .route("/items/{id}/subtitles/search", post(search_item_subtitles))
+        .route(
+            "/items/{id}/subtitles/download",
+            post(download_item_subtitle),
+        )
         .route("/items/{id}/images/{img_type}", get(get_item_image))
+        .route("/images/proxy", get(get_proxied_image))
+        .route(
+            "/items/{id}/images/{img_type}/uploads",
+            post(create_image_upload),
+        )
+        .route(
+            "/uploads/images/{upload_id}",
+            get(get_image_upload)
+                .put(put_image_upload_chunk)
+                .delete(cancel_image_upload),
+        )
+        .route("/items/{id}/theme", get(get_item_theme))
         .route("/items/{id}/metadata/refresh", post(refresh_item_metadata))
+        .route("/items/{id}/refresh-images", post(refresh_item_images))
         .route("/items/{id}/providers", get(get_item_providers))
+        .route("/items/{id}/match-candidates", get(get_match_candidates))
+        .route("/items/{id}/identify", post(identify_item))
+        .route("/items/{id}/merge-into/{target_id}", post(merge_item_into))
+        .route(
+            "/items/{id}/progress",
+            axum::routing::delete(reset_item_progress),
+        )
+        .route("/items/{id}/watched-through", post(mark_watched_through))
         .route(
             "/items/{id}/field-locks",
             post(lock_item_field).delete(unlock_item_field),
         )
+        .route("/items/{id}/lock", post(lock_item))
+        .route("/items/{id}/unlock", post(unlock_item))
         // TV expected episodes
         .route("/items/{id}/expected-episodes", get(get_expected_episodes))
         .route("/items/{id}/missing-episodes", get(get_missing_episodes))
         // Playback
         .route("/playback/progress", post(update_progress))
+        .route("/playback/queue", post(create_play_queue))
+        .route(
+            "/playback/queue/{id}",
+            get(get_play_queue).delete(delete_play_queue),
+        )
+        .route("/playback/queue/{id}/next", get(get_play_queue_next))
+        .route("/playlists", get(list_playlists))
+        .route("/playlists/{id}", get(get_playlist))
         .route("/playback/state/{item_id}", get(get_play_state))
         .route("/playback/sessions", post(create_playback_session))
         .route("/playback/sessions/{sid}/stop", post(stop_playback_session))
+        .route("/playback/sessions/{sid}/seek", post(seek_playback_session))
         .route("/playback/info/{file_id}", get(get_media_info))
+        .route("/people/{provider}/{provider_id}", get(get_person))
+        .route("/search", get(search_items))
+        // Delta sync
+        .route("/sync", get(get_sync_delta))
         .route("/system/pick-directory", post(pick_directory))
+        .route("/system/info", get(get_system_info))
         .route("/system/gpu", get(get_gpu_caps))
         .route("/system/tmdb", get(get_tmdb_config).put(update_tmdb_config))
+        .route(
+            "/system/config",
+            get(get_system_config).patch(update_system_config),
+        )
+        .route("/system/duplicates", get(list_duplicates))
+        .route(
+            "/system/provider-id-conflicts",
+            get(list_provider_id_conflicts),
+        )
+        .route("/system/audit", get(list_audit_log))
+        .route("/system/parse-preview", post(preview_path_parse))
         .route("/events", get(sse_events))
         // Jobs
         .route("/jobs", get(list_jobs))
@@ -133,6 +251,17 @@ fn api_router() -> Router<AppState> {
         .route("/jobs/{id}/cancel", post(cancel_job))
 }
 
+/// Admin routes that may run well past the regular request timeout: a database
+/// `VACUUM`, or a provider key checked against a slow upstream.
+fn maintenance_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/system/metadata-config",
+            get(get_metadata_config).patch(update_metadata_config),
+        )
+        .route("/system/maintenance/optimize", post(optimize_database))
+}
+
 fn setup_router() -> Router<AppState> {
     let rate_limiter = RateLimiter::new(30, 60); // 30 requests per 60s window
     Router::new()
@@ -182,17 +311,80 @@ struct HealthResponse {
     status: String,
 }
 
+/// `ok`, or `degraded` when ffmpeg or ffprobe was not found at startup. Which
+/// one, and their versions, are on the admin-only `/system/info`.
 async fn health(State(state): State<AppState>) -> Result<Json<HealthResponse>, AppError> {
     sqlx::query("SELECT 1")
         .execute(&state.db)
         .await
         .map_err(|e| ApiError::Internal(format!("database check failed: {e}")))?;
 
+    let tools = &state.media_tools;
+    let status = if tools.ffmpeg.is_missing() || tools.ffprobe.is_missing() {
+        "degraded"
+    } else {
+        "ok"
+    };
     Ok(Json(HealthResponse {
-        status: "ok".to_string(),
+        status: status.to_string(),
     }))
 }
 
+/// Liveness: the process is up and serving requests. Checks nothing else.
+async fn health_live() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+    })
+}
+
+#[derive(Serialize)]
+struct ReadinessChecks {
+    startup: bool,
+    database: bool,
+    transcode_dir: bool,
+}
+
+#[derive(Serialize)]
+struct ReadinessResponse {
+    status: String,
+    checks: ReadinessChecks,
+}
+
+/// Readiness: startup has finished, the database answers and the transcode dir is
+/// writable. Returns 503 until all three hold.
+async fn health_ready(
+    State(state): State<AppState>,
+) -> (axum::http::StatusCode, Json<ReadinessResponse>) {
+    let checks = ReadinessChecks {
+        startup: state.ready.is_ready(),
+        database: sqlx::query("SELECT 1").execute(&state.db).await.is_ok(),
+        transcode_dir: transcode_dir_writable(state.transcoder.transcode_dir()).await,
+    };
+    let ready = checks.startup && checks.database && checks.transcode_dir;
+    let status = if ready {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(ReadinessResponse {
+            status: if ready { "ready" } else { "not_ready" }.to_string(),
+            checks,
+        }),
+    )
+}
+
+async fn transcode_dir_writable(dir: &std::path::Path) -> bool {
+    if tokio::fs::create_dir_all(dir).await.is_err() {
+        return false;
+    }
+    let probe = dir.join(format!(".ready-{}", uuid::Uuid::new_v4()));
+    let writable = tokio::fs::write(&probe, b"").await.is_ok();
+    let _ = tokio::fs::remove_file(&probe).await;
+    writable
+}
+
 // ---------------------------------------------------------------------------
 // Auth
 // ---------------------------------------------------------------------------
@@ -227,6 +419,21 @@ async fn auth_login(
         return Err(ApiError::Unauthorized("invalid credentials".into()).into());
     }
 
+    // Upgrade hashes made with older, weaker parameters while we have the password.
+    let hash_params = rustfin_db::repo::users::PasswordHashParams::from_env();
+    if hash_params.needs_rehash(&user.password_hash) {
+        if let Err(e) = rustfin_db::repo::users::rehash_password(
+            &state.db,
+            &user.id,
+            &body.password,
+            &hash_params,
+        )
+        .await
+        {
+            tracing::warn!(user_id = %user.id, error = %e, "failed to upgrade password hash");
+        }
+    }
+
     let token = issue_token(&user.id, &user.username, &user.role, &state.jwt_secret)?;
 
     Ok(Json(LoginResponse {
@@ -311,7 +518,7 @@ async fn ensure_library_access(
 }
 
 async fn create_user_route(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<AppState>,
     Json(body): Json<CreateUserRequest>,
 ) -> Result<Json<CreateUserResponse>, AppError> {
@@ -325,6 +532,14 @@ async fn create_user_route(
         &library_ids,
     )
     .await?;
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "user.create",
+        Some(&id),
+        &format!("created {role} account '{}'", body.username),
+    )
+    .await;
 
     Ok(Json(CreateUserResponse {
         id,
@@ -351,13 +566,26 @@ async fn list_users_route(
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
+    let mut cache = crate::access_cache::AccessCache::new(&state.db);
+    let user_ids: Vec<String> = users
+        .iter()
+        .filter(|u| u.role == "user")
+        .map(|u| u.id.clone())
+        .collect();
+    cache
+        .load_access(&user_ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
     Ok(Json({
         let mut out = Vec::with_capacity(users.len());
         for u in users {
             let library_ids = if u.role == "user" {
-                rustfin_db::repo::users::get_library_access(&state.db, &u.id)
+                cache
+                    .library_access(&u.id)
                     .await
                     .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+                    .to_vec()
             } else {
                 vec![]
             };
@@ -448,6 +676,19 @@ async fn update_user_route(
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("user not found".into()))?;
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "user.update",
+        Some(&user_id),
+        &format!(
+            "updated account '{}': role {}, {} libraries",
+            updated.username,
+            updated.role,
+            final_library_ids.len()
+        ),
+    )
+    .await;
 
     Ok(Json(UpdateUserResponse {
         id: updated.id,
@@ -465,6 +706,9 @@ async fn delete_user_route(
     if user_id == admin.user_id {
         return Err(ApiError::BadRequest("cannot delete yourself".into()).into());
     }
+    let existing = rustfin_db::repo::users::find_by_id(&state.db, &user_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
     let deleted = rustfin_db::repo::users::delete_user(&state.db, &user_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
@@ -472,6 +716,15 @@ async fn delete_user_route(
     if !deleted {
         return Err(ApiError::NotFound("user not found".into()).into());
     }
+    let username = existing.map(|u| u.username).unwrap_or_default();
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "user.delete",
+        Some(&user_id),
+        &format!("deleted account '{username}'"),
+    )
+    .await;
 
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
@@ -480,34 +733,40 @@ async fn delete_user_route(
 // Preferences
 // ---------------------------------------------------------------------------
 
-async fn get_prefs(
-    auth: AuthUser,
-    State(state): State<AppState>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    let json_str = rustfin_db::repo::users::get_preferences(&state.db, &auth.user_id)
+async fn load_prefs(state: &AppState, user_id: &str) -> Result<UserPreferences, AppError> {
+    let json_str = rustfin_db::repo::users::get_preferences(&state.db, user_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .unwrap_or_else(|| "{}".to_string());
 
-    let val: serde_json::Value = serde_json::from_str(&json_str)
-        .map_err(|e| ApiError::Internal(format!("invalid prefs JSON: {e}")))?;
+    // Older installs stored free-form JSON; keep whatever still fits the schema.
+    let stored: serde_json::Value = serde_json::from_str(&json_str).unwrap_or_default();
+    Ok(UserPreferences::from_stored(&stored))
+}
 
-    Ok(Json(val))
+async fn get_prefs(
+    auth: AuthUser,
+    State(state): State<AppState>,
+) -> Result<Json<UserPreferences>, AppError> {
+    Ok(Json(load_prefs(&state, &auth.user_id).await?))
 }
 
 async fn update_prefs(
     auth: AuthUser,
     State(state): State<AppState>,
     Json(body): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    let json_str = serde_json::to_string(&body)
+) -> Result<Json<UserPreferences>, AppError> {
+    let current = load_prefs(&state, &auth.user_id).await?;
+    let updated = current.apply_patch(&body).map_err(ApiError::validation)?;
+
+    let json_str = serde_json::to_string(&updated)
         .map_err(|e| ApiError::Internal(format!("json serialize error: {e}")))?;
 
     rustfin_db::repo::users::update_preferences(&state.db, &auth.user_id, &json_str)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    Ok(Json(body))
+    Ok(Json(updated))
 }
 
 // ---------------------------------------------------------------------------
@@ -520,6 +779,27 @@ struct LibrarySettingsPatchRequest {
     show_images: Option<bool>,
     prefer_local_artwork: Option<bool>,
     fetch_online_artwork: Option<bool>,
+    /// Overrides the server `metadata_language`; an empty string clears it.
+    metadata_language: Option<String>,
+    /// Overrides the server `metadata_region`; an empty string clears it.
+    metadata_region: Option<String>,
+    /// Automatic rescan interval in seconds; `0` turns scheduled scans off.
+    scan_interval_secs: Option<i64>,
+    /// Item ordering used when a listing omits `sort_by`; an empty string clears it.
+    default_sort: Option<String>,
+    /// `asc` or `desc`; an empty string clears it.
+    default_order: Option<String>,
+    /// TV libraries: `season`, `skip` or `separate` for season 0 files.
+    specials_policy: Option<String>,
+    /// Download artwork into the image cache after each scan.
+    prefetch_images: Option<bool>,
+    /// Scan at boot when the server's `scan_on_startup` is `flagged`.
+    scan_on_startup: Option<bool>,
+    /// Derive new items' ids from the library and what they are instead of at random.
+    deterministic_ids: Option<bool>,
+    /// Movie libraries: new files shorter than this many seconds are skipped as
+    /// samples; `0` turns the check off.
+    sample_max_duration_secs: Option<i64>,
 }
 
 #[derive(Deserialize)]
@@ -529,6 +809,9 @@ struct CreateLibraryRequest {
     paths: Vec<String>,
     #[serde(default)]
     settings: LibrarySettingsPatchRequest,
+    /// Allow paths that overlap another library's paths.
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Serialize)]
@@ -536,6 +819,16 @@ struct LibrarySettingsResponse {
     show_images: bool,
     prefer_local_artwork: bool,
     fetch_online_artwork: bool,
+    metadata_language: Option<String>,
+    metadata_region: Option<String>,
+    scan_interval_secs: Option<i64>,
+    default_sort: Option<String>,
+    default_order: Option<String>,
+    specials_policy: &'static str,
+    prefetch_images: bool,
+    scan_on_startup: bool,
+    deterministic_ids: bool,
+    sample_max_duration_secs: i64,
 }
 
 #[derive(Serialize)]
@@ -543,11 +836,71 @@ struct LibraryResponse {
     id: String,
     name: String,
     kind: String,
+    /// Position set with `PATCH /libraries/order`; libraries without one come last.
+    display_order: Option<i64>,
     paths: Vec<LibraryPathResponse>,
     settings: LibrarySettingsResponse,
     item_count: i64,
     created_ts: i64,
     updated_ts: i64,
+    /// `created_ts` in the server time zone; only with `formatted_dates=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    /// `updated_ts` in the server time zone; only with `formatted_dates=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+}
+
+/// `?formatted_dates=true` adds RFC 3339 `created_at`/`updated_at` fields in the
+/// server's `default_time_zone` next to the raw unix timestamps.
+#[derive(Debug, Default, Deserialize)]
+struct FormattedDatesQuery {
+    #[serde(default)]
+    formatted_dates: bool,
+}
+
+impl FormattedDatesQuery {
+    /// The server time zone when formatted dates were asked for.
+    async fn time_zone(
+        &self,
+        state: &AppState,
+    ) -> Result<Option<std::sync::Arc<crate::time_zone::ServerTimeZone>>, AppError> {
+        if !self.formatted_dates {
+            return Ok(None);
+        }
+        crate::time_zone::ServerTimeZone::load(&state.db)
+            .await
+            .map(Some)
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")).into())
+    }
+
+    async fn apply_to_libraries(
+        &self,
+        state: &AppState,
+        responses: &mut [LibraryResponse],
+    ) -> Result<(), AppError> {
+        if let Some(tz) = self.time_zone(state).await? {
+            for response in responses {
+                response.created_at = Some(tz.format(response.created_ts));
+                response.updated_at = Some(tz.format(response.updated_ts));
+            }
+        }
+        Ok(())
+    }
+
+    async fn apply_to_items(
+        &self,
+        state: &AppState,
+        responses: &mut [ItemResponse],
+    ) -> Result<(), AppError> {
+        if let Some(tz) = self.time_zone(state).await? {
+            for response in responses {
+                response.created_at = Some(tz.format(response.created_ts));
+                response.updated_at = Some(tz.format(response.updated_ts));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize)]
@@ -601,69 +954,438 @@ fn validate_and_normalize_paths(paths: &[String]) -> Result<Vec<String>, AppErro
     Ok(normalized_paths)
 }
 
-async fn load_library_settings_response(
+/// Canonical form used for overlap checks; falls back to the path as given when it
+/// cannot be resolved (e.g. a stored path that has since been unmounted).
+fn canonical_library_path(path: &str) -> std::path::PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| std::path::PathBuf::from(path))
+}
+
+/// Reject `paths` that contain, or are contained in, a path of another library.
+///
+/// `library_id` is the library being edited, whose own paths are ignored. With `force`
+/// overlaps are only logged.
+async fn ensure_paths_do_not_overlap(
+    state: &AppState,
+    library_id: Option<&str>,
+    paths: &[String],
+    force: bool,
+) -> Result<(), AppError> {
+    let owners = rustfin_db::repo::libraries::list_library_path_owners(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let mut errors = serde_json::Map::new();
+    for (i, raw) in paths.iter().enumerate() {
+        let candidate = canonical_library_path(raw);
+        let conflicts: Vec<String> = owners
+            .iter()
+            .filter(|o| Some(o.library_id.as_str()) != library_id)
+            .filter(|o| {
+                let existing = canonical_library_path(&o.path);
+                candidate.starts_with(&existing) || existing.starts_with(&candidate)
+            })
+            .map(|o| format!("overlaps '{}' in library '{}'", o.path, o.library_name))
+            .collect();
+        if conflicts.is_empty() {
+            continue;
+        }
+        if force {
+            tracing::warn!(path = %raw, ?conflicts, "library path overlaps another library");
+        } else {
+            errors.insert(format!("paths[{i}]"), json!(conflicts));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(serde_json::Value::Object(errors)).into())
+    }
+}
+
+fn library_settings_response(
+    options: Option<rustfin_db::repo::libraries::LibraryOptionsRow>,
+) -> LibrarySettingsResponse {
+    let Some(options) = options else {
+        return LibrarySettingsResponse {
+            show_images: true,
+            prefer_local_artwork: true,
+            fetch_online_artwork: true,
+            metadata_language: None,
+            metadata_region: None,
+            scan_interval_secs: None,
+            default_sort: None,
+            default_order: None,
+            specials_policy: SpecialsPolicy::default().as_str(),
+            prefetch_images: false,
+            scan_on_startup: false,
+            deterministic_ids: false,
+            sample_max_duration_secs: 0,
+        };
+    };
+    LibrarySettingsResponse {
+        show_images: options.settings.show_images,
+        prefer_local_artwork: options.settings.prefer_local_artwork,
+        fetch_online_artwork: options.settings.fetch_online_artwork,
+        metadata_language: options.metadata_language,
+        metadata_region: options.metadata_region,
+        scan_interval_secs: options.scan_interval_secs,
+        default_sort: options.default_sort,
+        default_order: options.default_order,
+        specials_policy: options
+            .specials_policy
+            .as_deref()
+            .and_then(SpecialsPolicy::parse)
+            .unwrap_or_default()
+            .as_str(),
+        prefetch_images: options.prefetch_images,
+        scan_on_startup: options.scan_on_startup,
+        deterministic_ids: options.deterministic_ids,
+        sample_max_duration_secs: options.sample_max_duration_secs,
+    }
+}
+
+/// Shortest accepted automatic rescan interval.
+const MIN_SCAN_INTERVAL_SECS: i64 = 300;
+
+/// Longest accepted sample cutoff; anything longer would start skipping short films.
+const MAX_SAMPLE_DURATION_SECS: i64 = 1_200;
+
+fn validate_scan_interval(patch: &LibrarySettingsPatchRequest) -> Result<(), AppError> {
+    match patch.scan_interval_secs {
+        Some(secs) if secs != 0 && secs < MIN_SCAN_INTERVAL_SECS => {
+            Err(ApiError::validation(json!({
+                "settings.scan_interval_secs": [
+                    format!("must be 0 (off) or at least {MIN_SCAN_INTERVAL_SECS} seconds")
+                ]
+            }))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_sample_max_duration(patch: &LibrarySettingsPatchRequest) -> Result<(), AppError> {
+    match patch.sample_max_duration_secs {
+        Some(secs) if !(0..=MAX_SAMPLE_DURATION_SECS).contains(&secs) => {
+            Err(ApiError::validation(json!({
+                "settings.sample_max_duration_secs": [
+                    format!("must be between 0 (off) and {MAX_SAMPLE_DURATION_SECS} seconds")
+                ]
+            }))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_default_sort(patch: &LibrarySettingsPatchRequest) -> Result<(), AppError> {
+    let mut errors = serde_json::Map::new();
+    if let Some(sort) = patch.default_sort.as_deref().map(str::trim) {
+        if !sort.is_empty() && ItemSortBy::parse(sort).is_none() {
+            let allowed: Vec<&str> = ItemSortBy::ALL.iter().map(|s| s.as_str()).collect();
+            errors.insert(
+                "settings.default_sort".into(),
+                json!([format!("must be one of: {}", allowed.join(", "))]),
+            );
+        }
+    }
+    if let Some(order) = patch.default_order.as_deref().map(str::trim) {
+        if !order.is_empty() && SortOrder::parse(order).is_none() {
+            errors.insert(
+                "settings.default_order".into(),
+                json!(["must be 'asc' or 'desc'"]),
+            );
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(serde_json::Value::Object(errors)).into())
+    }
+}
+
+fn validate_specials_policy(patch: &LibrarySettingsPatchRequest) -> Result<(), AppError> {
+    match patch.specials_policy.as_deref() {
+        Some(policy) if SpecialsPolicy::parse(policy).is_none() => {
+            let allowed: Vec<&str> = SpecialsPolicy::ALL.iter().map(|p| p.as_str()).collect();
+            Err(ApiError::validation(json!({
+                "settings.specials_policy": [format!("must be one of: {}", allowed.join(", "))]
+            }))
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Apply the specials part of a settings patch. Returns whether anything changed.
+async fn apply_library_specials_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(policy) = patch
+        .specials_policy
+        .as_deref()
+        .and_then(SpecialsPolicy::parse)
+    else {
+        return Ok(false);
+    };
+    rustfin_db::repo::libraries::set_library_specials_policy(
+        &state.db,
+        library_id,
+        Some(policy.as_str()),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the default sort part of a settings patch. Returns whether anything changed.
+async fn apply_library_sort_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    if patch.default_sort.is_none() && patch.default_order.is_none() {
+        return Ok(false);
+    }
+    validate_default_sort(patch)?;
+
+    let (current_sort, current_order) =
+        rustfin_db::repo::libraries::get_library_default_sort(&state.db, library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let merge = |patch: &Option<String>, current: Option<String>| match patch {
+        Some(value) if value.trim().is_empty() => None,
+        Some(value) => Some(value.trim().to_string()),
+        None => current,
+    };
+    rustfin_db::repo::libraries::set_library_default_sort(
+        &state.db,
+        library_id,
+        merge(&patch.default_sort, current_sort).as_deref(),
+        merge(&patch.default_order, current_order).as_deref(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the image prefetch part of a settings patch. Returns whether anything changed.
+async fn apply_library_prefetch_patch(
     state: &AppState,
     library_id: &str,
-) -> Result<LibrarySettingsResponse, AppError> {
-    let settings = rustfin_db::repo::libraries::get_library_settings(&state.db, library_id)
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(enabled) = patch.prefetch_images else {
+        return Ok(false);
+    };
+    rustfin_db::repo::libraries::set_library_prefetch_images(&state.db, library_id, enabled)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-    let settings = settings.unwrap_or(rustfin_db::repo::libraries::LibrarySettingsRow {
-        library_id: library_id.to_string(),
-        show_images: true,
-        prefer_local_artwork: true,
-        fetch_online_artwork: true,
-        updated_ts: chrono::Utc::now().timestamp(),
-    });
+    Ok(true)
+}
 
-    Ok(LibrarySettingsResponse {
-        show_images: settings.show_images,
-        prefer_local_artwork: settings.prefer_local_artwork,
-        fetch_online_artwork: settings.fetch_online_artwork,
-    })
+/// Apply the startup scan part of a settings patch. Returns whether anything changed.
+async fn apply_library_startup_scan_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(enabled) = patch.scan_on_startup else {
+        return Ok(false);
+    };
+    rustfin_db::repo::libraries::set_library_scan_on_startup(&state.db, library_id, enabled)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the item id part of a settings patch. Returns whether anything changed.
+/// Only items created by later scans are affected.
+async fn apply_library_id_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(enabled) = patch.deterministic_ids else {
+        return Ok(false);
+    };
+    rustfin_db::repo::libraries::set_library_deterministic_ids(&state.db, library_id, enabled)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the sample detection part of a settings patch. Returns whether anything
+/// changed. Only files found by later scans are affected.
+async fn apply_library_sample_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(secs) = patch.sample_max_duration_secs else {
+        return Ok(false);
+    };
+    validate_sample_max_duration(patch)?;
+    rustfin_db::repo::libraries::set_library_sample_max_duration(&state.db, library_id, secs)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the scan schedule part of a settings patch. Returns whether anything changed.
+async fn apply_library_schedule_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    let Some(secs) = patch.scan_interval_secs else {
+        return Ok(false);
+    };
+    validate_scan_interval(patch)?;
+    rustfin_db::repo::libraries::set_library_scan_interval(
+        &state.db,
+        library_id,
+        Some(secs).filter(|&s| s > 0),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
+}
+
+/// Apply the metadata locale part of a settings patch. Returns whether anything changed.
+async fn apply_library_locale_patch(
+    state: &AppState,
+    library_id: &str,
+    patch: &LibrarySettingsPatchRequest,
+) -> Result<bool, AppError> {
+    if patch.metadata_language.is_none() && patch.metadata_region.is_none() {
+        return Ok(false);
+    }
+
+    let (current_language, current_region) =
+        rustfin_db::repo::libraries::get_library_metadata_locale(&state.db, library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let merge = |patch: &Option<String>, current: Option<String>| match patch {
+        Some(value) if value.trim().is_empty() => None,
+        Some(value) => Some(value.trim().to_string()),
+        None => current,
+    };
+    let language = merge(&patch.metadata_language, current_language);
+    let region = merge(&patch.metadata_region, current_region);
+
+    if let Some(errors) = crate::setup::validation::validate_metadata(
+        language.as_deref().unwrap_or("en"),
+        region.as_deref().unwrap_or("US"),
+    ) {
+        return Err(ApiError::validation(errors).into());
+    }
+
+    rustfin_db::repo::libraries::set_library_metadata_locale(
+        &state.db,
+        library_id,
+        language.as_deref(),
+        region.as_deref(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(true)
 }
 
 async fn library_row_to_response(
     state: &AppState,
     lib: rustfin_db::repo::libraries::LibraryRow,
 ) -> Result<LibraryResponse, AppError> {
-    let paths = rustfin_db::repo::libraries::get_library_paths(&state.db, &lib.id)
+    let mut responses = library_rows_to_responses(state, vec![lib]).await?;
+    Ok(responses.remove(0))
+}
+
+/// Responses for `libs` in order, with a fixed number of queries however many
+/// libraries there are.
+async fn library_rows_to_responses(
+    state: &AppState,
+    libs: Vec<rustfin_db::repo::libraries::LibraryRow>,
+) -> Result<Vec<LibraryResponse>, AppError> {
+    let ids: Vec<String> = libs.iter().map(|lib| lib.id.clone()).collect();
+    let mut cache = crate::access_cache::AccessCache::new(&state.db);
+    cache
+        .load_paths(&ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let item_counts = rustfin_db::repo::libraries::count_library_items_for(&state.db, &ids)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-    let item_count = rustfin_db::repo::libraries::count_library_items(&state.db, &lib.id)
+    let mut options = rustfin_db::repo::libraries::get_library_options_for(&state.db, &ids)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-    let settings = load_library_settings_response(state, &lib.id).await?;
 
-    Ok(LibraryResponse {
-        id: lib.id,
-        name: lib.name,
-        kind: lib.kind,
-        paths: paths
-            .into_iter()
-            .map(|p| LibraryPathResponse {
-                id: p.id,
-                path: p.path,
-                is_read_only: p.is_read_only,
-            })
-            .collect(),
-        settings,
-        item_count,
-        created_ts: lib.created_ts,
-        updated_ts: lib.updated_ts,
-    })
+    let mut responses = Vec::with_capacity(libs.len());
+    for lib in libs {
+        let paths = cache
+            .library_paths(&lib.id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        responses.push(LibraryResponse {
+            paths: paths
+                .iter()
+                .map(|p| LibraryPathResponse {
+                    id: p.id.clone(),
+                    path: p.path.clone(),
+                    is_read_only: p.is_read_only,
+                })
+                .collect(),
+            settings: library_settings_response(options.remove(&lib.id)),
+            item_count: item_counts.get(&lib.id).copied().unwrap_or(0),
+            id: lib.id,
+            name: lib.name,
+            kind: lib.kind,
+            display_order: lib.display_order,
+            created_ts: lib.created_ts,
+            updated_ts: lib.updated_ts,
+            created_at: None,
+            updated_at: None,
+        });
+    }
+    Ok(responses)
 }
 
 async fn create_library(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<AppState>,
     Json(body): Json<CreateLibraryRequest>,
 ) -> Result<(axum::http::StatusCode, Json<LibraryResponse>), AppError> {
     // Validate kind
-    if body.kind != "movies" && body.kind != "tv_shows" {
-        return Err(ApiError::BadRequest("kind must be 'movies' or 'tv_shows'".into()).into());
+    if LibraryKind::parse(&body.kind).is_none() {
+        let allowed: Vec<&str> = LibraryKind::ALL.iter().map(|k| k.as_str()).collect();
+        return Err(
+            ApiError::BadRequest(format!("kind must be one of: {}", allowed.join(", "))).into(),
+        );
     }
     let normalized_paths = validate_and_normalize_paths(&body.paths)?;
+    ensure_paths_do_not_overlap(&state, None, &normalized_paths, body.force).await?;
+    if let Some(errors) = crate::setup::validation::validate_metadata(
+        body.settings
+            .metadata_language
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .unwrap_or("en"),
+        body.settings
+            .metadata_region
+            .as_deref()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .unwrap_or("US"),
+    ) {
+        return Err(ApiError::validation(errors).into());
+    }
+    validate_scan_interval(&body.settings)?;
+    validate_default_sort(&body.settings)?;
+    validate_sample_max_duration(&body.settings)?;
+    validate_specials_policy(&body.settings)?;
 
     let lib = rustfin_db::repo::libraries::create_library(
         &state.db,
@@ -683,8 +1405,24 @@ async fn create_library(
     )
     .await
     .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    apply_library_locale_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_schedule_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_sort_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_specials_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_prefetch_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_startup_scan_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_id_patch(&state, &lib.id, &body.settings).await?;
+    apply_library_sample_patch(&state, &lib.id, &body.settings).await?;
 
     let response = library_row_to_response(&state, lib).await?;
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "library.create",
+        Some(&response.id),
+        &format!("created {} library '{}'", response.kind, response.name),
+    )
+    .await;
 
     // Auto-scan newly created libraries so items populate without manual scan.
     if let Err(e) =
@@ -703,40 +1441,97 @@ async fn create_library(
 async fn list_libraries(
     auth: AuthUser,
     State(state): State<AppState>,
+    Query(dates): Query<FormattedDatesQuery>,
 ) -> Result<Json<Vec<LibraryResponse>>, AppError> {
+    let mut cache = crate::access_cache::AccessCache::new(&state.db);
     let allowed_library_ids = if auth.role == "admin" {
         None
     } else {
         Some(
-            rustfin_db::repo::users::get_library_access(&state.db, &auth.user_id)
+            cache
+                .library_access(&auth.user_id)
                 .await
                 .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-                .into_iter()
+                .iter()
+                .cloned()
                 .collect::<HashSet<_>>(),
         )
     };
 
-    let libs = rustfin_db::repo::libraries::list_libraries(&state.db)
+    let mut libs = rustfin_db::repo::libraries::list_libraries(&state.db)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if let Some(allowed) = &allowed_library_ids {
+        libs.retain(|lib| allowed.contains(&lib.id));
+    }
 
-    let mut result = Vec::with_capacity(libs.len());
-    for lib in libs {
-        if let Some(allowed) = &allowed_library_ids {
-            if !allowed.contains(&lib.id) {
-                continue;
-            }
+    let mut responses = library_rows_to_responses(&state, libs).await?;
+    dates.apply_to_libraries(&state, &mut responses).await?;
+    Ok(Json(responses))
+}
+
+#[derive(Deserialize)]
+struct LibraryOrderRequest {
+    /// Library ids in the order listings should show them.
+    library_ids: Vec<String>,
+}
+
+/// Set the order libraries are listed in. Libraries left out of the list sort
+/// after the listed ones, by name.
+async fn set_library_order(
+    admin: AdminUser,
+    State(state): State<AppState>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Json(body): Json<LibraryOrderRequest>,
+) -> Result<Json<Vec<LibraryResponse>>, AppError> {
+    let libs = rustfin_db::repo::libraries::list_libraries(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let known: HashSet<&str> = libs.iter().map(|lib| lib.id.as_str()).collect();
+    let mut seen = HashSet::new();
+    for id in &body.library_ids {
+        if !known.contains(id.as_str()) {
+            return Err(ApiError::validation(json!({
+                "library_ids": [format!("unknown library id '{id}'")]
+            }))
+            .into());
+        }
+        if !seen.insert(id.as_str()) {
+            return Err(ApiError::validation(json!({
+                "library_ids": [format!("library id '{id}' is listed more than once")]
+            }))
+            .into());
         }
-        result.push(library_row_to_response(&state, lib).await?);
     }
 
-    Ok(Json(result))
+    rustfin_db::repo::libraries::set_library_display_order(&state.db, &body.library_ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "library.reorder",
+        None,
+        &format!(
+            "set the display order of {} libraries",
+            body.library_ids.len()
+        ),
+    )
+    .await;
+
+    let libs = rustfin_db::repo::libraries::list_libraries(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut responses = library_rows_to_responses(&state, libs).await?;
+    dates.apply_to_libraries(&state, &mut responses).await?;
+    Ok(Json(responses))
 }
 
 async fn get_library(
     auth: AuthUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
 ) -> Result<Json<LibraryResponse>, AppError> {
     let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
         .await
@@ -744,7 +1539,11 @@ async fn get_library(
         .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
     ensure_library_access(&auth, &state, &lib.id).await?;
 
-    Ok(Json(library_row_to_response(&state, lib).await?))
+    let mut response = library_row_to_response(&state, lib).await?;
+    dates
+        .apply_to_libraries(&state, std::slice::from_mut(&mut response))
+        .await?;
+    Ok(Json(response))
 }
 
 #[derive(Default, Deserialize)]
@@ -753,10 +1552,12 @@ struct UpdateLibraryRequest {
     name: Option<String>,
     paths: Option<Vec<String>>,
     settings: LibrarySettingsPatchRequest,
+    /// Allow paths that overlap another library's paths.
+    force: bool,
 }
 
 async fn update_library(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(body): Json<UpdateLibraryRequest>,
@@ -765,6 +1566,10 @@ async fn update_library(
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
+    validate_scan_interval(&body.settings)?;
+    validate_default_sort(&body.settings)?;
+    validate_sample_max_duration(&body.settings)?;
+    validate_specials_policy(&body.settings)?;
 
     let mut did_update = false;
     let mut should_rescan = false;
@@ -779,6 +1584,7 @@ async fn update_library(
 
     if let Some(paths) = &body.paths {
         let normalized_paths = validate_and_normalize_paths(paths)?;
+        ensure_paths_do_not_overlap(&state, Some(&id), &normalized_paths, body.force).await?;
         let replaced =
             rustfin_db::repo::libraries::replace_library_paths(&state.db, &id, &normalized_paths)
                 .await
@@ -819,9 +1625,35 @@ async fn update_library(
         should_rescan = true;
     }
 
+    if apply_library_locale_patch(&state, &id, &body.settings).await? {
+        did_update = true;
+        should_rescan = true;
+    }
+
+    if apply_library_specials_patch(&state, &id, &body.settings).await? {
+        did_update = true;
+        should_rescan = true;
+    }
+
+    // Schedule, sort and prefetch changes do not need a rescan of their own.
+    did_update |= apply_library_schedule_patch(&state, &id, &body.settings).await?;
+    did_update |= apply_library_sort_patch(&state, &id, &body.settings).await?;
+    did_update |= apply_library_prefetch_patch(&state, &id, &body.settings).await?;
+    did_update |= apply_library_startup_scan_patch(&state, &id, &body.settings).await?;
+    did_update |= apply_library_id_patch(&state, &id, &body.settings).await?;
+    did_update |= apply_library_sample_patch(&state, &id, &body.settings).await?;
+
     if !did_update {
         return Err(ApiError::BadRequest("no update fields provided".into()).into());
     }
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "library.update",
+        Some(&id),
+        &format!("updated library '{}'", existing.name),
+    )
+    .await;
 
     if should_rescan {
         if let Err(e) =
@@ -839,24 +1671,44 @@ async fn update_library(
 }
 
 async fn delete_library(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<Json<serde_json::Value>, AppError> {
+    let existing = rustfin_db::repo::libraries::get_library(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
     let deleted = rustfin_db::repo::libraries::delete_library(&state.db, &id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
     if !deleted {
         return Err(ApiError::NotFound("library not found".into()).into());
     }
+    let name = existing.map(|lib| lib.name).unwrap_or_default();
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "library.delete",
+        Some(&id),
+        &format!("deleted library '{name}'"),
+    )
+    .await;
 
     Ok(Json(serde_json::json!({ "deleted": true })))
 }
 
+#[derive(Deserialize)]
+struct ScanLibraryQuery {
+    /// Only revisit files earlier scans failed on instead of walking everything.
+    #[serde(default)]
+    retry_failed: bool,
+}
+
 async fn scan_library(
     _admin: AdminUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<ScanLibraryQuery>,
 ) -> Result<(axum::http::StatusCode, Json<JobResponse>), AppError> {
     // Verify library exists
     let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
@@ -864,7 +1716,37 @@ async fn scan_library(
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
 
-    let job = crate::library_scan::enqueue_library_scan(&state, &lib.id, &lib.kind).await?;
+    let job = if query.retry_failed {
+        crate::library_scan::enqueue_failed_file_retry(&state, &lib.id, &lib.kind).await?
+    } else {
+        crate::library_scan::enqueue_library_scan(&state, &lib.id, &lib.kind).await?
+    };
+
+    Ok((axum::http::StatusCode::ACCEPTED, Json(job_to_response(job))))
+}
+
+#[derive(Deserialize, Default)]
+struct RefreshLibraryMetadataRequest {
+    /// Overwrite existing (unlocked) values instead of only filling blanks.
+    #[serde(default)]
+    replace: bool,
+}
+
+async fn refresh_library_metadata(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    body: Option<Json<RefreshLibraryMetadataRequest>>,
+) -> Result<(axum::http::StatusCode, Json<JobResponse>), AppError> {
+    let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
+    let Json(body) = body.unwrap_or_default();
+
+    let job =
+        crate::library_scan::enqueue_metadata_refresh(&state, &lib.id, &lib.kind, body.replace)
+            .await?;
 
     Ok((axum::http::StatusCode::ACCEPTED, Json(job_to_response(job))))
 }
@@ -883,6 +1765,9 @@ struct JobResponse {
     error: Option<String>,
     created_ts: i64,
     updated_ts: i64,
+    /// 1-based place among jobs waiting for a worker; only while `queued`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    queue_position: Option<usize>,
 }
 
 fn job_to_response(job: rustfin_db::repo::jobs::JobRow) -> JobResponse {
@@ -899,18 +1784,54 @@ fn job_to_response(job: rustfin_db::repo::jobs::JobRow) -> JobResponse {
         error: job.error,
         created_ts: job.created_ts,
         updated_ts: job.updated_ts,
+        queue_position: None,
     }
 }
 
+const JOBS_DEFAULT_LIMIT: i64 = 50;
+const JOBS_MAX_LIMIT: i64 = 200;
+
+#[derive(Deserialize)]
+struct JobsQuery {
+    status: Option<String>,
+    kind: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct JobsPageResponse {
+    jobs: Vec<JobResponse>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
+
 async fn list_jobs(
     _auth: AuthUser,
     State(state): State<AppState>,
-) -> Result<Json<Vec<JobResponse>>, AppError> {
-    let jobs = rustfin_db::repo::jobs::list_jobs(&state.db)
+    Query(query): Query<JobsQuery>,
+) -> Result<Json<JobsPageResponse>, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(JOBS_DEFAULT_LIMIT)
+        .clamp(1, JOBS_MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let filter = rustfin_db::repo::jobs::JobFilter {
+        status: query.status.as_deref().filter(|s| !s.is_empty()),
+        kind: query.kind.as_deref().filter(|s| !s.is_empty()),
+    };
+
+    let (jobs, total) = rustfin_db::repo::jobs::list_jobs_page(&state.db, &filter, limit, offset)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    Ok(Json(jobs.into_iter().map(job_to_response).collect()))
+    Ok(Json(JobsPageResponse {
+        jobs: jobs.into_iter().map(job_to_response).collect(),
+        total,
+        limit,
+        offset,
+    }))
 }
 
 async fn get_job(
@@ -923,7 +1844,15 @@ async fn get_job(
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("job not found".into()))?;
 
-    Ok(Json(job_to_response(job)))
+    let queue_position = if job.status == "queued" {
+        state.jobs.position(&job.id)
+    } else {
+        None
+    };
+    Ok(Json(JobResponse {
+        queue_position,
+        ..job_to_response(job)
+    }))
 }
 
 async fn cancel_job(
@@ -960,8 +1889,35 @@ struct ItemResponse {
     backdrop_url: Option<String>,
     logo_url: Option<String>,
     thumb_url: Option<String>,
+    /// Images from the item's `backdrops/` folder. Only filled in by `GET /items/{id}`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extra_backdrop_urls: Vec<String>,
+    /// Theme song (or theme video) stream. Only filled in by `GET /items/{id}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    theme_url: Option<String>,
+    /// Whether scans and metadata refreshes skip the item. Only filled in by `GET /items/{id}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    locked: Option<bool>,
+    /// Series, season and episode numbers; only set for episodes.
+    series_id: Option<String>,
+    series_title: Option<String>,
+    season_number: Option<i64>,
+    episode_number: Option<i64>,
+    /// Direct children: seasons of a series, episodes of a season. Only set for
+    /// series and seasons, with `include_counts=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    child_count: Option<i64>,
+    /// Movies and episodes at any depth below the item; set alongside `child_count`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recursive_item_count: Option<i64>,
     created_ts: i64,
     updated_ts: i64,
+    /// `created_ts` in the server time zone; only with `formatted_dates=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    /// `updated_ts` in the server time zone; only with `formatted_dates=true`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -981,6 +1937,25 @@ fn item_image_url(item_id: &str, img_type: &str, include_images: bool) -> Option
     }
 }
 
+/// Each library's `show_images` setting, read once per listing of items from
+/// several libraries.
+#[derive(Default)]
+struct ShowImages(HashMap<String, bool>);
+
+impl ShowImages {
+    async fn get(&mut self, state: &AppState, library_id: &str) -> Result<bool, AppError> {
+        if let Some(show) = self.0.get(library_id) {
+            return Ok(*show);
+        }
+        let show = rustfin_db::repo::libraries::get_library_settings(&state.db, library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .is_none_or(|s| s.show_images);
+        self.0.insert(library_id.to_string(), show);
+        Ok(show)
+    }
+}
+
 fn item_to_response(item: rustfin_db::repo::items::ItemRow, include_images: bool) -> ItemResponse {
     ItemResponse {
         id: item.id.clone(),
@@ -1011,15 +1986,136 @@ fn item_to_response(item: rustfin_db::repo::items::ItemRow, include_images: bool
         } else {
             None
         },
+        extra_backdrop_urls: Vec::new(),
+        theme_url: None,
+        locked: None,
+        series_id: None,
+        series_title: None,
+        season_number: None,
+        episode_number: None,
+        child_count: None,
+        recursive_item_count: None,
         created_ts: item.created_ts,
         updated_ts: item.updated_ts,
+        created_at: None,
+        updated_at: None,
+    }
+}
+
+/// Fill in the series and numbering of any episodes among `responses`.
+async fn add_episode_context(
+    state: &AppState,
+    responses: &mut [ItemResponse],
+) -> Result<(), AppError> {
+    let episode_ids: Vec<String> = responses
+        .iter()
+        .filter(|r| r.kind == "episode")
+        .map(|r| r.id.clone())
+        .collect();
+    let mut contexts = rustfin_db::repo::items::get_episode_contexts(&state.db, &episode_ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    for response in responses {
+        if let Some(context) = contexts.remove(&response.id) {
+            response.series_id = context.series_id;
+            response.series_title = context.series_title;
+            response.season_number = context.season_number;
+            response.episode_number = context.episode_number;
+        }
+    }
+    Ok(())
+}
+
+/// `?include_counts=true` adds `child_count`/`recursive_item_count` to series and
+/// seasons. Off by default, since it costs a query over every listed item's tree.
+#[derive(Debug, Default, Deserialize)]
+struct ItemCountsQuery {
+    #[serde(default)]
+    include_counts: bool,
+}
+
+impl ItemCountsQuery {
+    async fn apply_to_items(
+        &self,
+        state: &AppState,
+        responses: &mut [ItemResponse],
+    ) -> Result<(), AppError> {
+        if !self.include_counts {
+            return Ok(());
+        }
+        let is_container = |r: &ItemResponse| matches!(r.kind.as_str(), "series" | "season");
+        let ids: Vec<String> = responses
+            .iter()
+            .filter(|r| is_container(r))
+            .map(|r| r.id.clone())
+            .collect();
+        let counts = rustfin_db::repo::items::get_child_counts(&state.db, &ids)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        for response in responses.iter_mut().filter(|r| is_container(r)) {
+            let (children, recursive) = counts.get(&response.id).copied().unwrap_or_default();
+            response.child_count = Some(children);
+            response.recursive_item_count = Some(recursive);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct LibraryItemsQuery {
+    /// Only items linked to this studio (case-insensitive name).
+    studio: Option<String>,
+    /// Only items with an audio track in this language, e.g. `jpn`.
+    audio_lang: Option<String>,
+    /// Only items with a subtitle track in this language.
+    subtitle_lang: Option<String>,
+    /// Falls back to the library's `default_sort`, then `title`.
+    sort_by: Option<String>,
+    /// Falls back to the library's `default_order`, then `asc`.
+    sort_order: Option<String>,
+}
+
+/// Resolve the ordering for a listing. The library default only applies when
+/// `sort_by` is omitted; a stored value that is no longer valid is ignored.
+async fn resolve_library_sort(
+    state: &AppState,
+    library_id: &str,
+    query: &LibraryItemsQuery,
+) -> Result<(ItemSortBy, SortOrder), AppError> {
+    let order = match query.sort_order.as_deref() {
+        Some(value) => Some(
+            SortOrder::parse(value)
+                .ok_or_else(|| ApiError::BadRequest("sort_order must be 'asc' or 'desc'".into()))?,
+        ),
+        None => None,
+    };
+    if let Some(value) = query.sort_by.as_deref() {
+        let sort_by = ItemSortBy::parse(value)
+            .ok_or_else(|| ApiError::BadRequest(format!("unsupported sort_by: {value}")))?;
+        return Ok((sort_by, order.unwrap_or_default()));
     }
+
+    let (default_sort, default_order) =
+        rustfin_db::repo::libraries::get_library_default_sort(&state.db, library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let sort_by = default_sort
+        .as_deref()
+        .and_then(ItemSortBy::parse)
+        .unwrap_or_default();
+    let order = order
+        .or_else(|| default_order.as_deref().and_then(SortOrder::parse))
+        .unwrap_or_default();
+    Ok((sort_by, order))
 }
 
 async fn list_library_items(
     auth: AuthUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<LibraryItemsQuery>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Query(counts): Query<ItemCountsQuery>,
 ) -> Result<Json<Vec<ItemResponse>>, AppError> {
     let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
         .await
@@ -1027,7 +2123,16 @@ async fn list_library_items(
         .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
     ensure_library_access(&auth, &state, &lib.id).await?;
 
-    let items = rustfin_db::repo::items::get_library_items(&state.db, &id)
+    let (sort_by, order) = resolve_library_sort(&state, &id, &query).await?;
+    fn non_empty(value: &Option<String>) -> Option<&str> {
+        value.as_deref().filter(|s| !s.trim().is_empty())
+    }
+    let filter = rustfin_db::repo::items::ItemFilter {
+        studio: non_empty(&query.studio),
+        audio_language: non_empty(&query.audio_lang),
+        subtitle_language: non_empty(&query.subtitle_lang),
+    };
+    let items = rustfin_db::repo::items::list_library_items(&state.db, &id, filter, sort_by, order)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
     let show_images = rustfin_db::repo::libraries::get_library_settings(&state.db, &id)
@@ -1036,18 +2141,101 @@ async fn list_library_items(
         .map(|s| s.show_images)
         .unwrap_or(true);
 
+    let mut responses: Vec<ItemResponse> = items
+        .into_iter()
+        .map(|item| item_to_response(item, show_images))
+        .collect();
+    add_episode_context(&state, &mut responses).await?;
+    dates.apply_to_items(&state, &mut responses).await?;
+    counts.apply_to_items(&state, &mut responses).await?;
+    Ok(Json(responses))
+}
+
+#[derive(Serialize)]
+struct StudioResponse {
+    id: String,
+    name: String,
+    item_count: i64,
+}
+
+async fn list_library_studios(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<StudioResponse>>, AppError> {
+    let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
+    ensure_library_access(&auth, &state, &lib.id).await?;
+
+    let studios = rustfin_db::repo::studios::list_studios(&state.db, &lib.id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
     Ok(Json(
-        items
+        studios
             .into_iter()
-            .map(|item| item_to_response(item, show_images))
+            .map(|s| StudioResponse {
+                id: s.id,
+                name: s.name,
+                item_count: s.item_count,
+            })
             .collect(),
     ))
 }
 
+#[derive(Serialize)]
+struct LanguageCountResponse {
+    language: String,
+    item_count: i64,
+}
+
+#[derive(Serialize)]
+struct LibraryLanguagesResponse {
+    audio: Vec<LanguageCountResponse>,
+    subtitles: Vec<LanguageCountResponse>,
+}
+
+/// Track languages of the library's probed files, with how many items carry each.
+async fn list_library_languages(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<LibraryLanguagesResponse>, AppError> {
+    let lib = rustfin_db::repo::libraries::get_library(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("library not found".into()))?;
+    ensure_library_access(&auth, &state, &lib.id).await?;
+
+    let rows = rustfin_db::repo::probes::list_library_languages(&state.db, &lib.id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut response = LibraryLanguagesResponse {
+        audio: Vec::new(),
+        subtitles: Vec::new(),
+    };
+    for row in rows {
+        let list = if row.kind == rustfin_db::repo::probes::AUDIO {
+            &mut response.audio
+        } else {
+            &mut response.subtitles
+        };
+        list.push(LanguageCountResponse {
+            language: row.language,
+            item_count: row.item_count,
+        });
+    }
+    Ok(Json(response))
+}
+
 async fn get_item(
     auth: AuthUser,
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Query(counts): Query<ItemCountsQuery>,
 ) -> Result<Json<ItemResponse>, AppError> {
     let item = rustfin_db::repo::items::get_item(&state.db, &id)
         .await
@@ -1061,7 +2249,95 @@ async fn get_item(
             .map(|s| s.show_images)
             .unwrap_or(true);
 
-    Ok(Json(item_to_response(item, show_images)))
+    let backdrops = rustfin_db::repo::extras::list_item_extras(&state.db, &id, "backdrop")
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut themes = Vec::new();
+    for kind in ["theme_audio", "theme_video"] {
+        themes.extend(
+            rustfin_db::repo::extras::list_item_extras(&state.db, &id, kind)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        );
+    }
+
+    let mut response = item_to_response(item, show_images);
+    if show_images {
+        response.extra_backdrop_urls = (1..=backdrops.len())
+            .map(|index| format!("/api/v1/items/{id}/images/backdrop?index={index}"))
+            .collect();
+    }
+    response.theme_url = (!themes.is_empty()).then(|| format!("/api/v1/items/{id}/theme"));
+    response.locked = Some(
+        rustfin_db::repo::items::is_item_locked(&state.db, &id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+    );
+    add_episode_context(&state, std::slice::from_mut(&mut response)).await?;
+    dates
+        .apply_to_items(&state, std::slice::from_mut(&mut response))
+        .await?;
+    counts
+        .apply_to_items(&state, std::slice::from_mut(&mut response))
+        .await?;
+    Ok(Json(response))
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ThemeQuery {
+    /// `audio` or `video`; without it the theme song is preferred over the video.
+    kind: Option<String>,
+}
+
+/// Stream the theme song or theme video found in an item's folder.
+async fn get_item_theme(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ThemeQuery>,
+    headers: axum::http::HeaderMap,
+) -> Result<axum::response::Response, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+
+    let kinds: &[&str] = match query.kind.as_deref() {
+        None => &["theme_audio", "theme_video"],
+        Some("audio") => &["theme_audio"],
+        Some("video") => &["theme_video"],
+        Some(other) => {
+            return Err(ApiError::BadRequest(format!(
+                "invalid theme kind '{other}', must be audio or video"
+            ))
+            .into());
+        }
+    };
+    let mut theme = None;
+    for kind in kinds {
+        theme = rustfin_db::repo::extras::list_item_extras(&state.db, &id, kind)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .into_iter()
+            .next();
+        if theme.is_some() {
+            break;
+        }
+    }
+    let theme = theme.ok_or_else(|| ApiError::NotFound("no theme for item".into()))?;
+
+    let path = std::path::PathBuf::from(&theme.path);
+    let size = std::fs::metadata(&path)
+        .ok()
+        .filter(|m| m.is_file())
+        .ok_or_else(|| ApiError::NotFound("theme file not found on disk".into()))?
+        .len();
+    let cache_control = crate::cache_policy::media_cache_control(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    crate::streaming::serve_file(&state, &path, size, &headers, &cache_control, None).await
 }
 
 async fn get_item_playback(
@@ -1075,7 +2351,16 @@ async fn get_item_playback(
         .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
     ensure_library_access(&auth, &state, &item.library_id).await?;
 
-    let file_id = rustfin_db::repo::items::get_item_file_id(&state.db, &id)
+    Ok(Json(playback_descriptor(&auth, &state, id).await?))
+}
+
+/// Stream URLs for an item the caller is known to have access to.
+async fn playback_descriptor(
+    auth: &AuthUser,
+    state: &AppState,
+    item_id: String,
+) -> Result<PlaybackDescriptorResponse, AppError> {
+    let file_id = rustfin_db::repo::items::get_item_file_id(&state.db, &item_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| {
@@ -1091,856 +2376,3003 @@ async fn get_item_playback(
         &state.jwt_secret,
     )?;
 
-    Ok(Json(PlaybackDescriptorResponse {
-        item_id: id,
+    Ok(PlaybackDescriptorResponse {
+        item_id,
         file_id: file_id.clone(),
         direct_url: format!("/stream/file/{file_id}?st={token}"),
         hls_start_url: "/api/v1/playback/sessions".to_string(),
         media_info_url: format!("/api/v1/playback/info/{file_id}"),
-    }))
+    })
 }
 
-async fn get_item_children(
-    auth: AuthUser,
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<Vec<ItemResponse>>, AppError> {
-    let parent = rustfin_db::repo::items::get_item(&state.db, &id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
-    ensure_library_access(&auth, &state, &parent.library_id).await?;
+/// Longest queue a client may store.
+const PLAY_QUEUE_MAX_ITEMS: usize = 1000;
 
-    let children = rustfin_db::repo::items::get_children(&state.db, &id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-    let show_images =
-        rustfin_db::repo::libraries::get_library_settings(&state.db, &parent.library_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .map(|s| s.show_images)
-            .unwrap_or(true);
+/// Queues kept per user; creating another drops the oldest.
+const PLAY_QUEUES_PER_USER: i64 = 20;
 
-    Ok(Json(
-        children
-            .into_iter()
-            .map(|item| item_to_response(item, show_images))
-            .collect(),
-    ))
+/// Queues older than this are dropped when any queue is created.
+const PLAY_QUEUE_MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Deserialize)]
+struct CreatePlayQueueRequest {
+    item_ids: Vec<String>,
 }
 
-// ---------------------------------------------------------------------------
-// Playback progress
-// ---------------------------------------------------------------------------
+#[derive(Serialize)]
+struct PlayQueueResponse {
+    queue_id: String,
+    item_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct PlayQueueNextResponse {
+    queue_id: String,
+    /// 0-based place of `item` in the queue.
+    position: usize,
+    item: ItemResponse,
+    playback: PlaybackDescriptorResponse,
+}
 
 #[derive(Deserialize)]
-struct ProgressRequest {
-    item_id: String,
-    progress_ms: i64,
-    #[serde(default)]
-    played: bool,
+struct PlayQueueNextQuery {
+    /// The item just played; without it the queue starts from the top.
+    after: Option<String>,
 }
 
-async fn update_progress(
+/// Remember an ordered list of items to play in a row, such as a season, so
+/// `/next` can pick the following item after a client reloads. Each user keeps
+/// their newest [`PLAY_QUEUES_PER_USER`] queues for up to 30 days.
+async fn create_play_queue(
     auth: AuthUser,
     State(state): State<AppState>,
-    Json(body): Json<ProgressRequest>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    let item = rustfin_db::repo::items::get_item(&state.db, &body.item_id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
-    ensure_library_access(&auth, &state, &item.library_id).await?;
+    Json(body): Json<CreatePlayQueueRequest>,
+) -> Result<Json<PlayQueueResponse>, AppError> {
+    if body.item_ids.is_empty() || body.item_ids.len() > PLAY_QUEUE_MAX_ITEMS {
+        return Err(ApiError::validation(json!({
+            "item_ids": [format!("must hold between 1 and {PLAY_QUEUE_MAX_ITEMS} items")]
+        }))
+        .into());
+    }
+    let mut seen = std::collections::HashSet::new();
+    if let Some(repeated) = body.item_ids.iter().find(|id| !seen.insert(id.as_str())) {
+        return Err(ApiError::validation(json!({
+            "item_ids": [format!("item {repeated} is listed more than once")]
+        }))
+        .into());
+    }
+    for item_id in &body.item_ids {
+        let item = rustfin_db::repo::items::get_item(&state.db, item_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .ok_or_else(|| ApiError::NotFound(format!("item {item_id} not found")))?;
+        ensure_library_access(&auth, &state, &item.library_id).await?;
+    }
 
-    rustfin_db::repo::playstate::update_progress(
+    let queue_id = rustfin_db::repo::play_queues::create_queue(
         &state.db,
         &auth.user_id,
-        &body.item_id,
-        body.progress_ms,
-        body.played,
+        &body.item_ids,
+        PLAY_QUEUES_PER_USER,
+        PLAY_QUEUE_MAX_AGE_SECS,
     )
     .await
     .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-
-    Ok(Json(serde_json::json!({ "ok": true })))
+    Ok(Json(PlayQueueResponse {
+        queue_id,
+        item_ids: body.item_ids,
+    }))
 }
 
-#[derive(Serialize)]
-struct PlayStateResponse {
-    item_id: String,
-    played: bool,
-    progress_ms: i64,
-    last_played_ts: Option<i64>,
-    favorite: bool,
+async fn load_play_queue(
+    auth: &AuthUser,
+    state: &AppState,
+    queue_id: &str,
+) -> Result<Vec<String>, AppError> {
+    rustfin_db::repo::play_queues::get_queue_items(&state.db, queue_id, &auth.user_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("play queue not found".into()).into())
 }
 
-async fn get_play_state(
+async fn get_play_queue(
     auth: AuthUser,
     State(state): State<AppState>,
-    Path(item_id): Path<String>,
-) -> Result<Json<PlayStateResponse>, AppError> {
-    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
-        .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
-    ensure_library_access(&auth, &state, &item.library_id).await?;
+    Path(queue_id): Path<String>,
+) -> Result<Json<PlayQueueResponse>, AppError> {
+    let item_ids = load_play_queue(&auth, &state, &queue_id).await?;
+    Ok(Json(PlayQueueResponse { queue_id, item_ids }))
+}
 
-    let state_row = rustfin_db::repo::playstate::get_play_state(&state.db, &auth.user_id, &item_id)
+async fn delete_play_queue(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(queue_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let deleted = rustfin_db::repo::play_queues::delete_queue(&state.db, &queue_id, &auth.user_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-
-    match state_row {
-        Some(s) => Ok(Json(PlayStateResponse {
-            item_id: s.item_id,
-            played: s.played,
-            progress_ms: s.progress_ms,
-            last_played_ts: s.last_played_ts,
-            favorite: s.favorite,
-        })),
-        None => Ok(Json(PlayStateResponse {
-            item_id,
-            played: false,
-            progress_ms: 0,
-            last_played_ts: None,
-            favorite: false,
-        })),
+    if !deleted {
+        return Err(ApiError::NotFound("play queue not found".into()).into());
     }
+    Ok(Json(json!({ "ok": true })))
 }
 
-// ---------------------------------------------------------------------------
-// Playback sessions (HLS transcode)
-// ---------------------------------------------------------------------------
+/// The item after `after` in a queue, with its stream URLs. Items that were
+/// removed or are no longer accessible are skipped; past the end there is no
+/// content.
+async fn get_play_queue_next(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(queue_id): Path<String>,
+    Query(query): Query<PlayQueueNextQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::response::IntoResponse;
 
-fn map_transcode_session_error(err: rustfin_transcoder::TranscodeError) -> ApiError {
-    match err {
-        rustfin_transcoder::TranscodeError::MaxTranscodesReached(n) => {
-            ApiError::BadRequest(format!("max concurrent transcodes reached ({n})"))
-        }
-        rustfin_transcoder::TranscodeError::FfmpegFailed(msg) => {
-            let lower = msg.to_lowercase();
-            if lower.contains("spawn")
-                && (lower.contains("no such file") || lower.contains("not found"))
-            {
-                ApiError::Internal(
-                    "ffmpeg is not available; configure RUSTFIN_FFMPEG_PATH or install ffmpeg"
-                        .into(),
-                )
-            } else if lower.contains("permission denied") {
-                ApiError::Internal(
-                    "transcode directory is not writable by the server process".into(),
-                )
-            } else {
-                ApiError::Internal(format!("ffmpeg failed: {msg}"))
-            }
+    let item_ids = load_play_queue(&auth, &state, &queue_id).await?;
+    let start = match &query.after {
+        Some(after) => {
+            item_ids
+                .iter()
+                .position(|id| id == after)
+                .ok_or_else(|| ApiError::NotFound("item is not in this play queue".into()))?
+                + 1
         }
-        rustfin_transcoder::TranscodeError::Io(e) => {
-            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                ApiError::Internal(
-                    "transcode directory is not writable by the server process".into(),
-                )
-            } else if e.kind() == std::io::ErrorKind::NotFound {
-                ApiError::Internal("input media file is not readable or no longer exists".into())
-            } else {
-                ApiError::Internal(format!("transcoder IO error: {e}"))
-            }
+        None => 0,
+    };
+
+    for (position, item_id) in item_ids.into_iter().enumerate().skip(start) {
+        let Some(item) = rustfin_db::repo::items::get_item(&state.db, &item_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        else {
+            continue;
+        };
+        match ensure_library_access(&auth, &state, &item.library_id).await {
+            Ok(()) => {}
+            Err(AppError(ApiError::Forbidden(_) | ApiError::NotFound(_))) => continue,
+            Err(e) => return Err(e),
         }
-        other => ApiError::Internal(format!("transcode error: {other}")),
+        let show_images =
+            rustfin_db::repo::libraries::get_library_settings(&state.db, &item.library_id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+                .map(|s| s.show_images)
+                .unwrap_or(true);
+        let mut response = [item_to_response(item, show_images)];
+        add_episode_context(&state, &mut response).await?;
+        let [item] = response;
+        let playback = playback_descriptor(&auth, &state, item_id).await?;
+        return Ok(Json(PlayQueueNextResponse {
+            queue_id,
+            position,
+            item,
+            playback,
+        })
+        .into_response());
     }
+    Ok(axum::http::StatusCode::NO_CONTENT.into_response())
 }
 
-#[derive(Deserialize)]
-struct CreateSessionRequest {
-    file_id: String,
-    #[serde(default)]
-    start_time_secs: Option<f64>,
+#[derive(Serialize)]
+struct PlaylistResponse {
+    id: String,
+    name: String,
+    library_id: Option<String>,
+    /// Mirrors an `.m3u` file in a library folder and is replaced when it changes.
+    imported: bool,
+    created_ts: i64,
+    updated_ts: i64,
 }
 
 #[derive(Serialize)]
-struct SessionResponse {
-    session_id: String,
-    hls_url: String,
+struct PlaylistDetailResponse {
+    #[serde(flatten)]
+    playlist: PlaylistResponse,
+    /// In playlist order; items the caller cannot access are left out.
+    items: Vec<ItemResponse>,
 }
 
-async fn create_playback_session(
+fn playlist_to_response(row: rustfin_db::repo::playlists::PlaylistRow) -> PlaylistResponse {
+    PlaylistResponse {
+        id: row.id,
+        name: row.name,
+        library_id: row.library_id,
+        imported: row.source_path.is_some(),
+        created_ts: row.created_ts,
+        updated_ts: row.updated_ts,
+    }
+}
+
+/// The caller's playlists. Playlists imported from `.m3u` files belong to the
+/// oldest admin account.
+async fn list_playlists(
     auth: AuthUser,
     State(state): State<AppState>,
-    Json(body): Json<CreateSessionRequest>,
-) -> Result<Json<SessionResponse>, AppError> {
-    if auth.role != "admin" {
-        let item_id = rustfin_db::repo::items::get_item_id_by_file_id(&state.db, &body.file_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .ok_or_else(|| ApiError::Forbidden("file is not playable for this account".into()))?;
-
-        let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .ok_or_else(|| ApiError::Forbidden("file is not playable for this account".into()))?;
-        ensure_library_access(&auth, &state, &item.library_id).await?;
-    }
+) -> Result<Json<Vec<PlaylistResponse>>, AppError> {
+    let rows = rustfin_db::repo::playlists::list_user_playlists(&state.db, &auth.user_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(Json(rows.into_iter().map(playlist_to_response).collect()))
+}
 
-    // Look up the media file
-    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &body.file_id)
+async fn get_playlist(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(playlist_id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
+) -> Result<Json<PlaylistDetailResponse>, AppError> {
+    let playlist = rustfin_db::repo::playlists::get_playlist(&state.db, &playlist_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or(ApiError::NotFound("media file not found".into()))?;
+        .filter(|p| p.user_id == auth.user_id)
+        .ok_or_else(|| ApiError::NotFound("playlist not found".into()))?;
+    let item_ids = rustfin_db::repo::playlists::get_playlist_items(&state.db, &playlist_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    let input_path = std::path::PathBuf::from(&file.path);
-    if !input_path.exists() {
-        return Err(ApiError::NotFound("media file does not exist on disk".into()).into());
-    }
-    if !input_path.is_file() {
-        return Err(ApiError::BadRequest("media path is not a regular file".into()).into());
-    }
-    if std::fs::File::open(&input_path).is_err() {
-        return Err(ApiError::BadRequest(
-            "media file is not readable by the server process".into(),
-        )
-        .into());
+    let mut show_images = ShowImages::default();
+    let mut items = Vec::with_capacity(item_ids.len());
+    for item_id in item_ids {
+        let Some(item) = rustfin_db::repo::items::get_item(&state.db, &item_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        else {
+            continue;
+        };
+        match ensure_library_access(&auth, &state, &item.library_id).await {
+            Ok(()) => {}
+            Err(AppError(ApiError::Forbidden(_) | ApiError::NotFound(_))) => continue,
+            Err(e) => return Err(e),
+        }
+        let show_images = show_images.get(&state, &item.library_id).await?;
+        items.push(item_to_response(item, show_images));
     }
+    add_episode_context(&state, &mut items).await?;
+    dates.apply_to_items(&state, &mut items).await?;
+    Ok(Json(PlaylistDetailResponse {
+        playlist: playlist_to_response(playlist),
+        items,
+    }))
+}
 
-    let session_id = state
-        .transcoder
-        .create_session(
-            input_path,
-            body.start_time_secs,
-            None,
-            auth.user_id.clone(),
-            body.file_id.clone(),
-        )
-        .await
-        .map_err(map_transcode_session_error)?;
-
-    let stream_token = issue_stream_token(
-        &auth.user_id,
-        &auth.role,
-        Some(&body.file_id),
-        Some(&session_id),
-        STREAM_TOKEN_TTL_SECONDS,
-        &state.jwt_secret,
-    )?;
-    let hls_url = format!("/stream/hls/{session_id}/master.m3u8?st={stream_token}");
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct PlaybackInfoQuery {
+    /// Comma-separated; fields left out take the `device_profile` defaults. Without
+    /// any field the client is treated as a plain HLS player.
+    containers: Option<String>,
+    video_codecs: Option<String>,
+    audio_codecs: Option<String>,
+    max_bitrate_kbps: Option<u32>,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+}
 
-    Ok(Json(SessionResponse {
-        session_id,
-        hls_url,
-    }))
+impl PlaybackInfoQuery {
+    fn device_profile(&self) -> Option<DeviceProfile> {
+        let list = |v: &Option<String>| {
+            v.as_ref().map(|v| {
+                v.split(',')
+                    .map(|c| c.trim().to_ascii_lowercase())
+                    .filter(|c| !c.is_empty())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let (containers, video_codecs, audio_codecs) = (
+            list(&self.containers),
+            list(&self.video_codecs),
+            list(&self.audio_codecs),
+        );
+        if containers.is_none()
+            && video_codecs.is_none()
+            && audio_codecs.is_none()
+            && self.max_bitrate_kbps.is_none()
+            && self.max_width.is_none()
+            && self.max_height.is_none()
+        {
+            return None;
+        }
+        let defaults = DeviceProfile::default();
+        Some(DeviceProfile {
+            containers: containers.unwrap_or(defaults.containers),
+            video_codecs: video_codecs.unwrap_or(defaults.video_codecs),
+            audio_codecs: audio_codecs.unwrap_or(defaults.audio_codecs),
+            max_bitrate_kbps: self.max_bitrate_kbps,
+            max_width: self.max_width,
+            max_height: self.max_height,
+        })
+    }
 }
 
-async fn stop_playback_session(
-    _auth: AuthUser,
-    State(state): State<AppState>,
-    Path(sid): Path<String>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    state
-        .transcoder
-        .stop_session(&sid)
-        .await
-        .map_err(|e| match e {
-            rustfin_transcoder::TranscodeError::SessionNotFound(_) => {
-                ApiError::NotFound("session not found".into())
-            }
-            other => ApiError::Internal(format!("transcode error: {other}")),
-        })?;
+#[derive(Serialize)]
+struct MediaSourceResponse {
+    file_id: String,
+    container: Option<String>,
+    size_bytes: i64,
+    /// A `.strm` URL; `direct_url` redirects to it.
+    is_remote: bool,
+    duration_secs: Option<f64>,
+    bitrate_kbps: Option<u32>,
+    video: Option<rustfin_transcoder::ffprobe::VideoStream>,
+    audio: Vec<rustfin_transcoder::ffprobe::AudioStream>,
+    subtitles: Vec<SubtitleInfo>,
+    /// Recommended method for the supplied profile; unset when the file could not be probed.
+    decision: Option<PlaybackDecisionResponse>,
+    /// `file` when `direct_url` can be played as-is, so no HLS session is needed. Judged
+    /// against the supplied profile, or against what browsers play natively without one.
+    delivery: Option<Delivery>,
+    /// Set when the supplied profile can't take the file's codecs or container.
+    direct_play_unsupported: Option<DirectPlayUnsupportedResponse>,
+    /// Byte-range stream URL carrying a short-lived stream token.
+    direct_url: String,
+}
 
-    Ok(Json(serde_json::json!({ "ok": true })))
+#[derive(Serialize)]
+struct DirectPlayUnsupportedResponse {
+    #[serde(flatten)]
+    unsupported: rustfin_transcoder::decision::DirectPlayUnsupported,
+    /// POST `{ file_id, device_profile }` here to start the transcode.
+    transcode_url: String,
 }
 
-// ---------------------------------------------------------------------------
-// Media info (ffprobe)
-// ---------------------------------------------------------------------------
+#[derive(Serialize)]
+struct PlaybackInfoResponse {
+    item_id: String,
+    /// Every file of the item, primary version first.
+    media_sources: Vec<MediaSourceResponse>,
+    /// POST `{ file_id, device_profile }` here when a source is not direct played.
+    hls_start_url: String,
+}
 
-async fn get_media_info(
+/// Everything a client needs to start playing an item, in one call.
+async fn get_item_playback_info(
     auth: AuthUser,
     State(state): State<AppState>,
-    Path(file_id): Path<String>,
-) -> Result<Json<serde_json::Value>, AppError> {
-    if auth.role != "admin" {
-        let item_id = rustfin_db::repo::items::get_item_id_by_file_id(&state.db, &file_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .ok_or_else(|| ApiError::Forbidden("file is not accessible for this account".into()))?;
-        let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .ok_or_else(|| ApiError::Forbidden("file is not accessible for this account".into()))?;
-        ensure_library_access(&auth, &state, &item.library_id).await?;
-    }
-
-    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+    Path(id): Path<String>,
+    Query(query): Query<PlaybackInfoQuery>,
+) -> Result<Json<PlaybackInfoResponse>, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, &id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or(ApiError::NotFound("media file not found".into()))?;
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
 
-    let media_path = std::path::Path::new(&file.path);
-    if !media_path.exists() {
-        return Err(ApiError::NotFound("media file does not exist on disk".into()).into());
-    }
-    if !media_path.is_file() {
-        return Err(ApiError::BadRequest("media path is not a regular file".into()).into());
-    }
-    if std::fs::File::open(media_path).is_err() {
-        return Err(ApiError::BadRequest(
-            "media file is not readable by the server process".into(),
-        )
-        .into());
+    let file_ids = rustfin_db::repo::items::get_item_file_ids(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if file_ids.is_empty() {
+        return Err(ApiError::Conflict(
+            "No playable file mapped to this item; rescan library.".into(),
+        )
+        .into());
     }
 
-    let info = rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), media_path)
+    let device = query.device_profile();
+    let prefs = load_prefs(&state, &auth.user_id).await?;
+    let direct_play_allowed = crate::playback_policy::direct_play_allowed(&state.db, &auth.role)
         .await
-        .map_err(|e| {
-            let message = e.to_string().to_lowercase();
-            if message.contains("spawn failed")
-                && (message.contains("no such file") || message.contains("not found"))
-            {
-                ApiError::Internal(
-                    "ffprobe is not available; configure RUSTFIN_FFPROBE_PATH or install ffprobe"
-                        .into(),
-                )
-            } else if message.contains("permission denied") {
-                ApiError::Internal("media file is not readable by ffprobe".into())
-            } else {
-                ApiError::Internal(format!("ffprobe error: {e}"))
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut media_sources = Vec::with_capacity(file_ids.len());
+    for file_id in file_ids {
+        let Some(file) = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        else {
+            continue;
+        };
+
+        let info = if file.is_remote {
+            None
+        } else {
+            match readable_media_path(&file) {
+                Ok(path) => {
+                    let info =
+                        rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), &path)
+                            .await
+                            .inspect_err(|e| {
+                                tracing::debug!(file_id = %file_id, error = %e, "probe failed");
+                            })
+                            .ok();
+                    if let Some(info) = &info {
+                        crate::probe_cache::record(&state.db, &file_id, info).await;
+                    }
+                    info
+                }
+                Err(_) => None,
             }
-        })?;
+        };
+        let subtitles = if file.is_remote {
+            Vec::new()
+        } else {
+            list_subtitles(
+                &file_id,
+                std::path::Path::new(&file.path),
+                info.as_ref(),
+                &prefs,
+            )
+        };
 
-    Ok(Json(serde_json::to_value(&info).unwrap()))
+        let plan = info.as_ref().map(|i| PlaybackPlan::new(i, device.as_ref()));
+        let delivery = info.as_ref().zip(plan.as_ref()).map(|(info, plan)| {
+            match (&device, plan.direct_play) {
+                _ if !direct_play_allowed && !file.is_remote => Delivery::Hls,
+                (None, _) => rustfin_transcoder::decision::recommend_delivery(info),
+                (Some(_), true) => Delivery::File,
+                (Some(_), false) => Delivery::Hls,
+            }
+        });
+
+        let direct_play_unsupported = plan
+            .as_ref()
+            .filter(|_| device.is_some())
+            .and_then(|plan| plan.decision.direct_play_unsupported())
+            .map(|unsupported| DirectPlayUnsupportedResponse {
+                unsupported,
+                transcode_url: "/api/v1/playback/sessions".to_string(),
+            });
+
+        let token = issue_stream_token(
+            &auth.user_id,
+            &auth.role,
+            Some(&file_id),
+            None,
+            STREAM_TOKEN_TTL_SECONDS,
+            &state.jwt_secret,
+        )?;
+        media_sources.push(MediaSourceResponse {
+            direct_url: format!("/stream/file/{file_id}?st={token}"),
+            file_id,
+            container: info
+                .as_ref()
+                .map(|i| i.container.clone())
+                .or(file.container),
+            size_bytes: file.size_bytes,
+            is_remote: file.is_remote,
+            duration_secs: info
+                .as_ref()
+                .map(|i| i.duration_secs)
+                .filter(|d| *d > 0.0)
+                .or(file.duration_ms.map(|ms| ms as f64 / 1000.0)),
+            bitrate_kbps: info.as_ref().and_then(|i| i.bitrate_kbps),
+            decision: plan.as_ref().map(PlaybackPlan::decision_response),
+            delivery,
+            direct_play_unsupported,
+            video: info.as_ref().and_then(|i| i.video.clone()),
+            audio: info.map(|i| i.audio).unwrap_or_default(),
+            subtitles,
+        });
+    }
+
+    Ok(Json(PlaybackInfoResponse {
+        item_id: id,
+        media_sources,
+        hls_start_url: "/api/v1/playback/sessions".to_string(),
+    }))
 }
 
-// ---------------------------------------------------------------------------
-// HLS serving
-// ---------------------------------------------------------------------------
+async fn get_item_children(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Query(counts): Query<ItemCountsQuery>,
+) -> Result<Json<Vec<ItemResponse>>, AppError> {
+    let parent = rustfin_db::repo::items::get_item(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &parent.library_id).await?;
 
-#[derive(Debug, Default, Deserialize)]
-struct HlsAuthQuery {
-    st: Option<String>,
+    let children = rustfin_db::repo::items::get_children(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let show_images =
+        rustfin_db::repo::libraries::get_library_settings(&state.db, &parent.library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .map(|s| s.show_images)
+            .unwrap_or(true);
+
+    let mut responses: Vec<ItemResponse> = children
+        .into_iter()
+        .map(|item| item_to_response(item, show_images))
+        .collect();
+    add_episode_context(&state, &mut responses).await?;
+    dates.apply_to_items(&state, &mut responses).await?;
+    counts.apply_to_items(&state, &mut responses).await?;
+    Ok(Json(responses))
 }
 
-#[derive(Debug)]
-struct AuthorizedHlsSession {
-    user_id: String,
-    role: String,
-    file_id: String,
-    stream_token: Option<String>,
+#[derive(Serialize)]
+struct SeriesEpisodeResponse {
+    id: String,
+    title: String,
+    overview: Option<String>,
+    thumb_url: Option<String>,
+    season_id: Option<String>,
+    season_number: Option<i64>,
+    episode_number: Option<i64>,
+    file_id: Option<String>,
+    play_state: EpisodePlayStateResponse,
 }
 
-fn attach_stream_token_to_playlist(playlist: &str, token: &str) -> String {
-    let mut out = String::with_capacity(playlist.len() + 64);
-    let mut first = true;
-    for line in playlist.lines() {
-        if !first {
-            out.push('\n');
-        }
-        first = false;
+#[derive(Serialize)]
+struct EpisodePlayStateResponse {
+    played: bool,
+    progress_ms: i64,
+    last_played_ts: Option<i64>,
+    favorite: bool,
+}
 
-        let trimmed = line.trim();
-        if trimmed.is_empty() || trimmed.starts_with('#') {
-            out.push_str(line);
-            continue;
-        }
+/// Every episode of a series in one list, ordered by season then episode.
+async fn get_series_episodes(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<SeriesEpisodeResponse>>, AppError> {
+    let series = rustfin_db::repo::items::get_item(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &series.library_id).await?;
+    if series.kind != "series" {
+        return Err(ApiError::BadRequest("item is not a series".into()).into());
+    }
 
-        if trimmed.contains("st=") {
-            out.push_str(trimmed);
-            continue;
-        }
+    let episodes = rustfin_db::repo::items::list_series_episodes(&state.db, &id, &auth.user_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let show_images =
+        rustfin_db::repo::libraries::get_library_settings(&state.db, &series.library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .map(|s| s.show_images)
+            .unwrap_or(true);
 
-        let sep = if trimmed.contains('?') { "&" } else { "?" };
-        out.push_str(trimmed);
-        out.push_str(sep);
-        out.push_str("st=");
-        out.push_str(token);
-    }
-    if playlist.ends_with('\n') {
-        out.push('\n');
-    }
-    out
+    Ok(Json(
+        episodes
+            .into_iter()
+            .map(|ep| SeriesEpisodeResponse {
+                thumb_url: ep
+                    .item
+                    .thumb_url
+                    .as_ref()
+                    .and_then(|_| item_image_url(&ep.item.id, "thumb", show_images)),
+                id: ep.item.id,
+                title: ep.item.title,
+                overview: ep.item.overview,
+                season_id: ep.item.parent_id,
+                season_number: ep.season_number,
+                episode_number: ep.episode_number,
+                file_id: ep.file_id,
+                play_state: EpisodePlayStateResponse {
+                    played: ep.played,
+                    progress_ms: ep.progress_ms,
+                    last_played_ts: ep.last_played_ts,
+                    favorite: ep.favorite,
+                },
+            })
+            .collect(),
+    ))
 }
 
-async fn authorize_hls_session_request(
-    state: &AppState,
-    sid: &str,
-    headers: &axum::http::HeaderMap,
-    query: &HlsAuthQuery,
-) -> Result<AuthorizedHlsSession, AppError> {
-    let identity = resolve_stream_request_identity(state, headers, query.st.as_deref())?;
+// ---------------------------------------------------------------------------
+// Search
+// ---------------------------------------------------------------------------
 
-    let session = state
-        .transcoder
-        .get_session_access(sid)
-        .await
-        .ok_or_else(|| ApiError::NotFound("HLS session not found".into()))?;
+const SEARCH_DEFAULT_LIMIT: i64 = 50;
+const SEARCH_MAX_LIMIT: i64 = 200;
 
-    if session.owner_user_id != identity.user_id {
-        return Err(
-            ApiError::Forbidden("HLS session does not belong to this account".into()).into(),
-        );
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: Option<String>,
+    limit: Option<i64>,
+}
+
+/// Items whose title, original title or one of its aliases contains `q`, in the
+/// libraries the caller can see.
+async fn search_items(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Query(counts): Query<ItemCountsQuery>,
+) -> Result<Json<Vec<ItemResponse>>, AppError> {
+    let q = query.q.as_deref().map(str::trim).unwrap_or_default();
+    if q.is_empty() {
+        return Err(ApiError::BadRequest("q is required".into()).into());
     }
+    let limit = query
+        .limit
+        .unwrap_or(SEARCH_DEFAULT_LIMIT)
+        .clamp(1, SEARCH_MAX_LIMIT);
 
-    if let Some(claims) = &identity.stream_claims {
-        if claims.session_id.as_deref() != Some(sid) {
-            return Err(ApiError::Forbidden(
-                "stream token is not scoped to this HLS session".into(),
-            )
-            .into());
-        }
-        if claims.file_id.as_deref() != Some(session.file_id.as_str()) {
-            return Err(ApiError::Forbidden(
-                "stream token is not scoped to this media file".into(),
-            )
-            .into());
-        }
+    let library_ids = if auth.role == "admin" {
+        None
+    } else {
+        Some(
+            rustfin_db::repo::users::get_library_access(&state.db, &auth.user_id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        )
+    };
+    let items = rustfin_db::repo::items::search_items(&state.db, q, library_ids.as_deref(), limit)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let mut show_images = ShowImages::default();
+    let mut responses = Vec::with_capacity(items.len());
+    for item in items {
+        let show_images = show_images.get(&state, &item.library_id).await?;
+        responses.push(item_to_response(item, show_images));
     }
+    add_episode_context(&state, &mut responses).await?;
+    dates.apply_to_items(&state, &mut responses).await?;
+    counts.apply_to_items(&state, &mut responses).await?;
+    Ok(Json(responses))
+}
 
-    Ok(AuthorizedHlsSession {
-        user_id: identity.user_id,
-        role: identity.role,
-        file_id: session.file_id,
-        stream_token: query.st.clone(),
-    })
+const RECENT_RELEASES_DEFAULT_DAYS: i64 = 30;
+const RECENT_RELEASES_MAX_DAYS: i64 = 3_650;
+
+#[derive(Deserialize)]
+struct RecentReleasesQuery {
+    days: Option<i64>,
+    limit: Option<i64>,
 }
 
-async fn hls_master(
+/// Movies, series and episodes that premiered in the last `days` days (up to and
+/// including today in the server time zone), newest first, in the libraries the
+/// caller can see.
+async fn list_recent_releases(
+    auth: AuthUser,
     State(state): State<AppState>,
-    Path(sid): Path<String>,
-    Query(query): Query<HlsAuthQuery>,
-    headers: axum::http::HeaderMap,
-) -> Result<axum::response::Response, AppError> {
-    use axum::body::Body;
-    use axum::http::header;
-    use axum::response::IntoResponse;
+    Query(query): Query<RecentReleasesQuery>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Query(counts): Query<ItemCountsQuery>,
+) -> Result<Json<Vec<ItemResponse>>, AppError> {
+    let days = query.days.unwrap_or(RECENT_RELEASES_DEFAULT_DAYS);
+    if !(1..=RECENT_RELEASES_MAX_DAYS).contains(&days) {
+        return Err(ApiError::validation(json!({
+            "days": [format!("must be between 1 and {RECENT_RELEASES_MAX_DAYS}")]
+        }))
+        .into());
+    }
+    let limit = query
+        .limit
+        .unwrap_or(SEARCH_DEFAULT_LIMIT)
+        .clamp(1, SEARCH_MAX_LIMIT);
 
-    let authorized = authorize_hls_session_request(&state, &sid, &headers, &query).await?;
+    let time_zone = crate::time_zone::ServerTimeZone::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let now = chrono::Utc::now().timestamp();
+    let today = chrono::DateTime::from_timestamp(now + i64::from(time_zone.offset_at(now)), 0)
+        .unwrap_or_default()
+        .date_naive();
+    let earliest = today - chrono::Duration::days(days);
 
-    // Ping the session
-    if !state.transcoder.ping(&sid).await {
-        return Err(ApiError::NotFound("HLS session not found".into()).into());
+    let library_ids = if auth.role == "admin" {
+        None
+    } else {
+        Some(
+            rustfin_db::repo::users::get_library_access(&state.db, &auth.user_id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        )
+    };
+    // Every date on or after `earliest` sorts at or after its year, however it is written.
+    let candidates = rustfin_db::repo::items::list_items_premiered_since(
+        &state.db,
+        &earliest.format("%Y").to_string(),
+        library_ids.as_deref(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut released: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|(item, raw)| {
+            let premiered = crate::release_dates::parse_premiere_date(&raw)?;
+            (earliest..=today)
+                .contains(&premiered)
+                .then_some((premiered, item))
+        })
+        .collect();
+    released.sort_by(|(a_date, a), (b_date, b)| {
+        b_date
+            .cmp(a_date)
+            .then_with(|| a.title.cmp(&b.title))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    released.truncate(limit as usize);
+
+    let mut show_images = ShowImages::default();
+    let mut responses = Vec::with_capacity(released.len());
+    for (_, item) in released {
+        let show_images = show_images.get(&state, &item.library_id).await?;
+        responses.push(item_to_response(item, show_images));
     }
+    add_episode_context(&state, &mut responses).await?;
+    dates.apply_to_items(&state, &mut responses).await?;
+    counts.apply_to_items(&state, &mut responses).await?;
+    Ok(Json(responses))
+}
 
-    let path = state
-        .transcoder
-        .get_file_path(&sid, "master.m3u8")
-        .await
-        .map_err(|e| ApiError::NotFound(format!("session error: {e}")))?;
+// ---------------------------------------------------------------------------
+// Delta sync
+// ---------------------------------------------------------------------------
 
-    // Wait for ffmpeg to write the playlist (up to 10s)
-    for _ in 0..50 {
-        if path.exists() {
-            break;
+const SYNC_DEFAULT_LIMIT: i64 = 500;
+const SYNC_MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize)]
+struct SyncQuery {
+    since: Option<i64>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// Keyset position `(timestamp, id)` for each change stream.
+#[derive(Debug, Clone, PartialEq)]
+struct SyncCursor {
+    items: (i64, String),
+    play_states: (i64, String),
+    deleted: (i64, String),
+}
+
+impl SyncCursor {
+    /// Changes strictly after `since`: an empty id sorts before every row, so the
+    /// position is the first second past it.
+    fn from_since(since: i64) -> Self {
+        let start = (since + 1, String::new());
+        Self {
+            items: start.clone(),
+            play_states: start.clone(),
+            deleted: start,
         }
-        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
     }
 
-    if !path.exists() {
-        return Err(ApiError::Internal("playlist not ready yet".into()).into());
+    fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.split(',').map(|part| {
+            let (ts, id) = part.split_once(':')?;
+            Some((ts.parse::<i64>().ok()?, id.to_string()))
+        });
+        let cursor = Self {
+            items: parts.next()??,
+            play_states: parts.next()??,
+            deleted: parts.next()??,
+        };
+        parts.next().is_none().then_some(cursor)
     }
 
-    let content = tokio::fs::read_to_string(&path)
-        .await
-        .map_err(|e| ApiError::Internal(format!("read playlist: {e}")))?;
-    let stream_token = match authorized.stream_token {
-        Some(t) => t,
-        None => issue_stream_token(
-            &authorized.user_id,
-            &authorized.role,
-            Some(&authorized.file_id),
-            Some(&sid),
-            STREAM_TOKEN_TTL_SECONDS,
-            &state.jwt_secret,
-        )?,
-    };
-    let content = attach_stream_token_to_playlist(&content, &stream_token);
-
-    Ok((
-        [
-            (
-                header::CONTENT_TYPE,
-                rustfin_transcoder::hls::PLAYLIST_CONTENT_TYPE,
-            ),
-            (header::CACHE_CONTROL, "no-store"),
-            (
-                header::HeaderName::from_static("referrer-policy"),
-                "no-referrer",
-            ),
-            (
-                header::HeaderName::from_static("x-content-type-options"),
-                "nosniff",
-            ),
-        ],
-        Body::from(content),
-    )
-        .into_response())
+    fn encode(&self) -> String {
+        format!(
+            "{}:{},{}:{},{}:{}",
+            self.items.0,
+            self.items.1,
+            self.play_states.0,
+            self.play_states.1,
+            self.deleted.0,
+            self.deleted.1
+        )
+    }
 }
 
-async fn hls_segment(
-    State(state): State<AppState>,
-    Path((sid, filename)): Path<(String, String)>,
-    Query(query): Query<HlsAuthQuery>,
-    headers: axum::http::HeaderMap,
-) -> Result<axum::response::Response, AppError> {
-    use axum::body::Body;
-    use axum::http::header;
-    use axum::response::IntoResponse;
+#[derive(Serialize)]
+struct SyncPlayStateResponse {
+    item_id: String,
+    played: bool,
+    progress_ms: i64,
+    last_played_ts: Option<i64>,
+    favorite: bool,
+    updated_ts: i64,
+}
 
-    let _authorized = authorize_hls_session_request(&state, &sid, &headers, &query).await?;
+#[derive(Serialize)]
+struct SyncDeletedResponse {
+    item_id: String,
+    library_id: String,
+    deleted_ts: i64,
+}
 
-    // Ping the session
-    if !state.transcoder.ping(&sid).await {
-        return Err(ApiError::NotFound("HLS session not found".into()).into());
-    }
+#[derive(Serialize)]
+struct SyncResponse {
+    items: Vec<ItemResponse>,
+    play_states: Vec<SyncPlayStateResponse>,
+    deleted: Vec<SyncDeletedResponse>,
+    /// Pass back as `cursor` to continue (or to poll for the next delta).
+    cursor: String,
+    has_more: bool,
+}
 
-    // Validate filename (prevent traversal)
-    if filename.contains("..") || filename.contains('/') || filename.contains('\\') {
-        return Err(ApiError::BadRequest("invalid filename".into()).into());
+/// Keep at most `limit` rows and return the next keyset position for the stream.
+fn sync_page<T>(
+    rows: &mut Vec<T>,
+    limit: i64,
+    until: i64,
+    key: impl Fn(&T) -> (i64, String),
+) -> ((i64, String), bool) {
+    if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        let last = rows.last().map(&key).unwrap_or((until, String::new()));
+        (last, true)
+    } else {
+        // Everything up to and including `until` has been sent.
+        ((until + 1, String::new()), false)
     }
+}
 
-    let path = state
-        .transcoder
-        .get_file_path(&sid, &filename)
-        .await
-        .map_err(|e| ApiError::NotFound(format!("session error: {e}")))?;
-
-    // Wait for segment to appear (up to 5s)
-    for _ in 0..25 {
-        if path.exists() {
-            break;
+async fn get_sync_delta(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Query(query): Query<SyncQuery>,
+) -> Result<Json<SyncResponse>, AppError> {
+    let cursor = match (&query.cursor, query.since) {
+        (Some(raw), _) => SyncCursor::parse(raw).ok_or_else(|| {
+            ApiError::validation(json!({ "cursor": ["is not a valid sync cursor"] }))
+        })?,
+        (None, Some(since)) => SyncCursor::from_since(since),
+        (None, None) => {
+            return Err(ApiError::validation(json!({
+                "since": ["either since or cursor is required"]
+            }))
+            .into());
         }
-        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
-    }
+    };
+    let limit = query
+        .limit
+        .unwrap_or(SYNC_DEFAULT_LIMIT)
+        .clamp(1, SYNC_MAX_LIMIT);
 
-    if !path.exists() {
-        return Err(ApiError::NotFound("segment not ready".into()).into());
-    }
+    // Rows written during the current second may still be in flight; stop one
+    // second short so the next call picks them up.
+    let until = chrono::Utc::now().timestamp() - 1;
 
-    let content_type = if filename.ends_with(".m3u8") {
-        rustfin_transcoder::hls::PLAYLIST_CONTENT_TYPE
+    let library_ids = if auth.role == "admin" {
+        None
     } else {
-        rustfin_transcoder::hls::segment_content_type(&filename)
+        Some(
+            rustfin_db::repo::users::get_library_access(&state.db, &auth.user_id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        )
     };
 
-    let data = tokio::fs::read(&path)
-        .await
-        .map_err(|e| ApiError::Internal(format!("read segment: {e}")))?;
-
-    Ok((
-        [
-            (header::CONTENT_TYPE, content_type),
-            (header::CACHE_CONTROL, "no-store"),
-            (
-                header::HeaderName::from_static("referrer-policy"),
-                "no-referrer",
-            ),
-            (
-                header::HeaderName::from_static("x-content-type-options"),
-                "nosniff",
-            ),
-        ],
-        Body::from(data),
+    let mut items = rustfin_db::repo::items::get_items_changed_since(
+        &state.db,
+        cursor.items.0,
+        &cursor.items.1,
+        until,
+        library_ids.as_deref(),
+        limit + 1,
     )
-        .into_response())
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut play_states = rustfin_db::repo::sync::get_play_states_changed_since(
+        &state.db,
+        &auth.user_id,
+        cursor.play_states.0,
+        &cursor.play_states.1,
+        until,
+        library_ids.as_deref(),
+        limit + 1,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut deleted = rustfin_db::repo::sync::get_tombstones_since(
+        &state.db,
+        cursor.deleted.0,
+        &cursor.deleted.1,
+        until,
+        library_ids.as_deref(),
+        limit + 1,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let (items_pos, items_more) =
+        sync_page(&mut items, limit, until, |i| (i.updated_ts, i.id.clone()));
+    let (states_pos, states_more) = sync_page(&mut play_states, limit, until, |s| {
+        (s.updated_ts, s.item_id.clone())
+    });
+    let (deleted_pos, deleted_more) = sync_page(&mut deleted, limit, until, |d| {
+        (d.deleted_ts, d.item_id.clone())
+    });
+
+    let mut show_images = ShowImages::default();
+    let mut item_responses = Vec::with_capacity(items.len());
+    for item in items {
+        let show_images = show_images.get(&state, &item.library_id).await?;
+        item_responses.push(item_to_response(item, show_images));
+    }
+    add_episode_context(&state, &mut item_responses).await?;
+
+    let next = SyncCursor {
+        items: items_pos,
+        play_states: states_pos,
+        deleted: deleted_pos,
+    };
+
+    Ok(Json(SyncResponse {
+        items: item_responses,
+        play_states: play_states
+            .into_iter()
+            .map(|s| SyncPlayStateResponse {
+                item_id: s.item_id,
+                played: s.played,
+                progress_ms: s.progress_ms,
+                last_played_ts: s.last_played_ts,
+                favorite: s.favorite,
+                updated_ts: s.updated_ts,
+            })
+            .collect(),
+        deleted: deleted
+            .into_iter()
+            .map(|d| SyncDeletedResponse {
+                item_id: d.item_id,
+                library_id: d.library_id,
+                deleted_ts: d.deleted_ts,
+            })
+            .collect(),
+        cursor: next.encode(),
+        has_more: items_more || states_more || deleted_more,
+    }))
 }
 
 // ---------------------------------------------------------------------------
-// Artwork / Images
+// Playback progress
 // ---------------------------------------------------------------------------
 
 #[derive(Deserialize)]
-struct ImageQuery {
-    w: Option<u32>,
-    h: Option<u32>,
-    format: Option<String>,
+struct ProgressRequest {
+    item_id: String,
+    progress_ms: i64,
+    #[serde(default)]
+    played: bool,
+    /// When the client observed this position (unix seconds). Defaults to now;
+    /// a report older than the stored one is ignored.
+    played_at: Option<i64>,
 }
 
-async fn get_item_image(
+async fn update_progress(
     auth: AuthUser,
     State(state): State<AppState>,
-    Path((item_id, img_type)): Path<(String, String)>,
-    axum::extract::Query(query): axum::extract::Query<ImageQuery>,
-) -> Result<axum::response::Response, AppError> {
-    use axum::http::{StatusCode, header};
-    use axum::response::IntoResponse;
-    use std::io::Read;
+    Json(body): Json<ProgressRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, &body.item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
 
-    let valid_types = ["poster", "backdrop", "logo", "thumb"];
-    if !valid_types.contains(&img_type.as_str()) {
-        return Err(ApiError::BadRequest(format!(
-            "invalid image type '{img_type}', must be one of: {valid_types:?}"
-        ))
-        .into());
+    // Clients may report a little late, but never from the future.
+    let now = chrono::Utc::now().timestamp();
+    let played_at = body.played_at.map_or(now, |ts| ts.min(now));
+    let applied = rustfin_db::repo::playstate::update_progress(
+        &state.db,
+        &auth.user_id,
+        &body.item_id,
+        body.progress_ms,
+        body.played,
+        played_at,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if !applied {
+        return Ok(Json(serde_json::json!({ "ok": true, "applied": false })));
     }
 
+    // Lets the user's other devices pick up the new resume point.
+    let _ = state
+        .events
+        .send(crate::state::ServerEvent::PlaybackProgress {
+            user_id: auth.user_id.clone(),
+            item_id: body.item_id.clone(),
+            progress_ms: body.progress_ms,
+            played: body.played,
+        });
+
+    Ok(Json(serde_json::json!({ "ok": true, "applied": true })))
+}
+
+#[derive(Deserialize)]
+struct ResetProgressQuery {
+    /// Also drop the item from the user's favorites.
+    #[serde(default)]
+    clear_favorite: bool,
+}
+
+async fn reset_item_progress(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    Query(query): Query<ResetProgressQuery>,
+) -> Result<Json<serde_json::Value>, AppError> {
     let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
     ensure_library_access(&auth, &state, &item.library_id).await?;
-    let show_images =
-        rustfin_db::repo::libraries::get_library_settings(&state.db, &item.library_id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-            .map(|s| s.show_images)
-            .unwrap_or(true);
-    if !show_images {
-        return Err(ApiError::NotFound("images are disabled for this library".into()).into());
-    }
 
-    // Get the image URL from DB
-    let image_url = rustfin_db::repo::items::get_item_image_url(&state.db, &item_id, &img_type)
+    rustfin_db::repo::playstate::reset_progress(
+        &state.db,
+        &auth.user_id,
+        &item_id,
+        query.clear_favorite,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let _ = state
+        .events
+        .send(crate::state::ServerEvent::PlaybackProgress {
+            user_id: auth.user_id.clone(),
+            item_id,
+            progress_ms: 0,
+            played: false,
+        });
+
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+#[derive(Deserialize)]
+struct WatchedThroughRequest {
+    season: i64,
+    episode: i64,
+}
+
+/// Mark a series' episodes played up to and including `season`/`episode`, by their
+/// stored numbers, for the caller. Later episodes are left as they are, as are
+/// episodes without numbers and, unless `season` is 0, the specials.
+async fn mark_watched_through(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(series_id): Path<String>,
+    Json(body): Json<WatchedThroughRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, &series_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or_else(|| ApiError::NotFound(format!("no {img_type} image for item")))?;
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    if item.kind != "series" {
+        return Err(ApiError::BadRequest(format!(
+            "watched-through needs a series, not '{}'",
+            item.kind
+        ))
+        .into());
+    }
+    if body.season < 0 || body.episode < 0 {
+        return Err(ApiError::validation(json!({
+            "season": ["season and episode must not be negative"]
+        }))
+        .into());
+    }
 
-    // Build cache key from item_id + type + resize params
-    let cache_key = format!(
-        "{}_{}_{}_{}",
-        item_id,
-        img_type,
-        query.w.unwrap_or(0),
-        query.h.unwrap_or(0)
-    );
-    let images_dir = state.cache_dir.join("images");
-    std::fs::create_dir_all(&images_dir)
-        .map_err(|e| ApiError::Internal(format!("cache dir error: {e}")))?;
+    let episodes =
+        rustfin_db::repo::items::list_series_episodes(&state.db, &series_id, &auth.user_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let through: Vec<String> = episodes
+        .into_iter()
+        .filter(|ep| match (ep.season_number, ep.episode_number) {
+            // Specials only count when they are the target.
+            (Some(0), _) if body.season != 0 => false,
+            (Some(season), Some(episode)) => (season, episode) <= (body.season, body.episode),
+            _ => false,
+        })
+        .map(|ep| ep.item.id)
+        .collect();
 
-    let ext = if let Some(ref fmt) = query.format {
-        fmt.clone()
-    } else if image_url.contains(".png") {
-        "png".to_string()
-    } else {
-        "jpg".to_string()
-    };
-    let cache_path = images_dir.join(format!("{cache_key}.{ext}"));
+    rustfin_db::repo::playstate::mark_played(
+        &state.db,
+        &auth.user_id,
+        &through,
+        chrono::Utc::now().timestamp(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    // Check cache
-    if !cache_path.exists() {
-        // Download the image
-        if image_url.starts_with("http://") || image_url.starts_with("https://") {
-            let client = reqwest::Client::new();
-            let resp = client
-                .get(&image_url)
-                .send()
-                .await
-                .map_err(|e| ApiError::Internal(format!("download error: {e}")))?;
+    for item_id in &through {
+        let _ = state
+            .events
+            .send(crate::state::ServerEvent::PlaybackProgress {
+                user_id: auth.user_id.clone(),
+                item_id: item_id.clone(),
+                progress_ms: 0,
+                played: true,
+            });
+    }
 
-            if !resp.status().is_success() {
-                return Err(ApiError::Internal(format!(
-                    "image download failed: {}",
-                    resp.status()
-                ))
-                .into());
-            }
+    Ok(Json(json!({ "ok": true, "marked": through.len() })))
+}
 
-            let bytes = resp
-                .bytes()
-                .await
-                .map_err(|e| ApiError::Internal(format!("download error: {e}")))?;
-
-            std::fs::write(&cache_path, &bytes)
-                .map_err(|e| ApiError::Internal(format!("cache write error: {e}")))?;
-        } else if std::path::Path::new(&image_url).exists() {
-            // Local file — copy to cache
-            std::fs::copy(&image_url, &cache_path)
-                .map_err(|e| ApiError::Internal(format!("copy error: {e}")))?;
-        } else {
-            return Err(ApiError::NotFound("image source not available".into()).into());
-        }
-    }
-
-    // Read the cached file
-    let mut file = std::fs::File::open(&cache_path)
-        .map_err(|e| ApiError::Internal(format!("cache read error: {e}")))?;
-    let metadata = file
-        .metadata()
-        .map_err(|e| ApiError::Internal(format!("metadata error: {e}")))?;
-    let mut buf = Vec::with_capacity(metadata.len() as usize);
-    file.read_to_end(&mut buf)
-        .map_err(|e| ApiError::Internal(format!("read error: {e}")))?;
-
-    // ETag from file size + modified time
-    let etag = format!(
-        "\"{:x}-{:x}\"",
-        metadata.len(),
-        metadata
-            .modified()
-            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-            .duration_since(std::time::SystemTime::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs()
-    );
-
-    let content_type = match ext.as_str() {
-        "png" => "image/png",
-        "webp" => "image/webp",
-        _ => "image/jpeg",
-    };
-
-    Ok((
-        StatusCode::OK,
-        [
-            (header::CONTENT_TYPE, content_type.to_string()),
-            (header::ETAG, etag),
-            (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
-        ],
-        buf,
-    )
-        .into_response())
+#[derive(Serialize)]
+struct PlayStateResponse {
+    item_id: String,
+    played: bool,
+    progress_ms: i64,
+    last_played_ts: Option<i64>,
+    favorite: bool,
+    /// `progress_ms` as a share of the runtime, 0-100. Unset when the runtime is unknown.
+    played_percentage: Option<f64>,
 }
 
-// ---------------------------------------------------------------------------
-// Subtitles
-// ---------------------------------------------------------------------------
-
-#[derive(Serialize)]
-struct SubtitleInfo {
-    #[serde(rename = "type")]
-    sub_type: String, // "sidecar" or "embedded"
-    format: String,
-    language: Option<String>,
-    title: Option<String>,
-    forced: bool,
-    sdh: bool,
-    /// For sidecar: URL to serve the file. For embedded: stream index.
-    source: String,
+/// Progress through an item of `runtime_ms`, as a percentage clamped to 0-100.
+fn played_percentage(progress_ms: i64, runtime_ms: Option<i64>) -> Option<f64> {
+    let runtime_ms = runtime_ms.filter(|ms| *ms > 0)?;
+    Some((progress_ms as f64 * 100.0 / runtime_ms as f64).clamp(0.0, 100.0))
 }
 
-async fn get_item_subtitles(
+async fn get_play_state(
     auth: AuthUser,
     State(state): State<AppState>,
     Path(item_id): Path<String>,
-) -> Result<Json<Vec<SubtitleInfo>>, AppError> {
+) -> Result<Json<PlayStateResponse>, AppError> {
     let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
     ensure_library_access(&auth, &state, &item.library_id).await?;
 
-    // Get the media file for this item
-    let file_id = rustfin_db::repo::items::get_item_file_id(&state.db, &item_id)
+    let state_row = rustfin_db::repo::playstate::get_play_state(&state.db, &auth.user_id, &item_id)
         .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or(ApiError::NotFound("item has no media file".into()))?;
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+    let runtime_ms = rustfin_db::repo::items::get_item_runtime_ms(&state.db, &item_id)
         .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .ok_or(ApiError::NotFound("media file not found".into()))?;
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    let media_path = std::path::Path::new(&file.path);
-    let mut subtitles = Vec::new();
+    match state_row {
+        Some(s) => Ok(Json(PlayStateResponse {
+            item_id: s.item_id,
+            played: s.played,
+            played_percentage: played_percentage(s.progress_ms, runtime_ms),
+            progress_ms: s.progress_ms,
+            last_played_ts: s.last_played_ts,
+            favorite: s.favorite,
+        })),
+        None => Ok(Json(PlayStateResponse {
+            item_id,
+            played: false,
+            progress_ms: 0,
+            last_played_ts: None,
+            favorite: false,
+            played_percentage: played_percentage(0, runtime_ms),
+        })),
+    }
+}
 
-    // 1. Sidecar subtitles
-    let sidecars = rustfin_scanner::subtitles::discover_sidecars(media_path);
-    for sub in &sidecars {
-        let encoded_path = base64_url_encode(&sub.path.to_string_lossy());
-        subtitles.push(SubtitleInfo {
-            sub_type: "sidecar".into(),
-            format: format!("{:?}", sub.format).to_lowercase(),
-            language: sub.language.clone(),
-            title: sub.title.clone(),
-            forced: sub.forced,
-            sdh: sub.sdh,
-            source: format!("/stream/subtitles/{encoded_path}"),
-        });
+// ---------------------------------------------------------------------------
+// Playback sessions (HLS transcode)
+// ---------------------------------------------------------------------------
+
+/// 503 with a clear message when the startup check could not run `name`.
+fn ensure_tool_available(
+    status: &rustfin_transcoder::tools::ToolStatus,
+    name: &str,
+    feature: &str,
+) -> Result<(), ApiError> {
+    if status.is_missing() {
+        return Err(ApiError::ServiceUnavailable(format!(
+            "{feature} unavailable: {name} not found"
+        )));
     }
+    Ok(())
+}
 
-    // 2. Embedded subtitles (via ffprobe)
-    if media_path.exists() {
-        if let Ok(info) =
-            rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), media_path).await
-        {
-            for sub in &info.subtitles {
-                subtitles.push(SubtitleInfo {
-                    sub_type: "embedded".into(),
-                    format: sub.codec.clone(),
-                    language: sub.language.clone(),
-                    title: sub.title.clone(),
-                    forced: sub.is_forced,
-                    sdh: false,
-                    source: format!("stream:{}", sub.index),
-                });
+fn map_transcode_session_error(err: rustfin_transcoder::TranscodeError) -> ApiError {
+    match err {
+        rustfin_transcoder::TranscodeError::MaxTranscodesReached(n) => {
+            ApiError::BadRequest(format!("max concurrent transcodes reached ({n})"))
+        }
+        rustfin_transcoder::TranscodeError::SessionNotFound(_) => {
+            ApiError::NotFound("session not found".into())
+        }
+        rustfin_transcoder::TranscodeError::InsufficientDiskSpace { .. } => {
+            ApiError::ServiceUnavailable(
+                "not enough free disk space in the transcode directory to start a session".into(),
+            )
+        }
+        rustfin_transcoder::TranscodeError::FfmpegFailed(msg) => {
+            let lower = msg.to_lowercase();
+            if lower.contains("spawn")
+                && (lower.contains("no such file") || lower.contains("not found"))
+            {
+                ApiError::Internal(
+                    "ffmpeg is not available; configure RUSTFIN_FFMPEG_PATH or install ffmpeg"
+                        .into(),
+                )
+            } else if lower.contains("permission denied") {
+                ApiError::Internal(
+                    "transcode directory is not writable by the server process".into(),
+                )
+            } else {
+                ApiError::Internal(format!("ffmpeg failed: {msg}"))
+            }
+        }
+        rustfin_transcoder::TranscodeError::Io(e) => {
+            if e.kind() == std::io::ErrorKind::PermissionDenied {
+                ApiError::Internal(
+                    "transcode directory is not writable by the server process".into(),
+                )
+            } else if e.kind() == std::io::ErrorKind::NotFound {
+                ApiError::Internal("input media file is not readable or no longer exists".into())
+            } else {
+                ApiError::Internal(format!("transcoder IO error: {e}"))
             }
         }
+        other => ApiError::Internal(format!("transcode error: {other}")),
     }
-
-    Ok(Json(subtitles))
 }
 
-fn base64_url_encode(s: &str) -> String {
-    use std::fmt::Write;
-    let mut out = String::new();
-    for b in s.bytes() {
-        write!(&mut out, "{b:02x}").unwrap();
+/// Non-admins may only touch files of items in libraries they can access.
+async fn ensure_file_access(
+    auth: &AuthUser,
+    state: &AppState,
+    file_id: &str,
+) -> Result<(), AppError> {
+    if auth.role == "admin" {
+        return Ok(());
     }
-    out
+    let item_id = rustfin_db::repo::items::get_item_id_by_file_id(&state.db, file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::Forbidden("file is not accessible for this account".into()))?;
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::Forbidden("file is not accessible for this account".into()))?;
+    ensure_library_access(auth, state, &item.library_id).await
 }
 
-fn hex_decode(s: &str) -> Option<String> {
-    let bytes: Result<Vec<u8>, _> = (0..s.len())
-        .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
-        .collect();
-    bytes.ok().and_then(|b| String::from_utf8(b).ok())
+/// Local path of a media file, checked to be a readable regular file.
+fn readable_media_path(
+    file: &rustfin_db::repo::media_files::MediaFileRow,
+) -> Result<std::path::PathBuf, AppError> {
+    let path = std::path::PathBuf::from(&file.path);
+    if !path.exists() {
+        return Err(ApiError::NotFound("media file does not exist on disk".into()).into());
+    }
+    if !path.is_file() {
+        return Err(ApiError::BadRequest("media path is not a regular file".into()).into());
+    }
+    if std::fs::File::open(&path).is_err() {
+        return Err(ApiError::BadRequest(
+            "media file is not readable by the server process".into(),
+        )
+        .into());
+    }
+    Ok(path)
 }
 
-async fn serve_subtitle(
-    State(state): State<AppState>,
-    Path(sub_path): Path<String>,
-) -> Result<axum::response::Response, AppError> {
-    use axum::body::Body;
-    use axum::response::IntoResponse;
-
-    let decoded =
-        hex_decode(&sub_path).ok_or(ApiError::BadRequest("invalid subtitle path".into()))?;
-
-    let path = std::path::Path::new(&decoded);
-
-    // Security: verify the path is under a library root
-    let canonical = path
-        .canonicalize()
-        .map_err(|_| ApiError::NotFound("subtitle file not found".into()))?;
-
-    let lib_paths = rustfin_db::repo::libraries::get_all_library_paths(&state.db)
-        .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+/// How one probed file should reach a client.
+struct PlaybackPlan {
+    decision: rustfin_transcoder::decision::PlayDecision,
+    /// Per-stream plan for an HLS session.
+    hls_plan: rustfin_transcoder::decision::TranscodePlan,
+    /// The HLS session re-encodes HDR video, which then needs tone-mapping.
+    hls_tone_map: bool,
+    renditions: rustfin_transcoder::hls::Renditions,
+    /// The client sent a profile and can take the file as-is.
+    direct_play: bool,
+}
 
-    let allowed = lib_paths.iter().any(|lp| {
-        if let Ok(root) = std::path::Path::new(lp).canonicalize() {
-            canonical.starts_with(&root)
-        } else {
-            false
+impl PlaybackPlan {
+    /// Without a profile the client is assumed to play exactly what the HLS output carries.
+    fn new(info: &rustfin_transcoder::ffprobe::MediaInfo, device: Option<&DeviceProfile>) -> Self {
+        let profile = device.cloned().unwrap_or_else(DeviceProfile::hls);
+        let decision = rustfin_transcoder::decision::decide(info, &profile);
+        // Streams are only copied if both the device and the HLS output take them.
+        let hls_decision =
+            rustfin_transcoder::decision::decide(info, &profile.through(&DeviceProfile::hls()));
+        Self {
+            direct_play: device.is_some() && decision.method == PlayMethod::DirectPlay,
+            hls_plan: hls_decision.plan(),
+            hls_tone_map: hls_decision.tone_map,
+            renditions: rustfin_transcoder::hls::Renditions::from_media_info(info, &hls_decision),
+            decision,
         }
-    });
-
-    if !allowed {
-        return Err(ApiError::Forbidden("path not in allowed library".into()).into());
     }
 
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("srt");
-
-    let content_type = rustfin_scanner::subtitles::SubtitleFormat::from_extension(ext)
-        .map(|f| f.mime_type())
-        .unwrap_or("application/octet-stream");
-
-    let data = tokio::fs::read(&canonical)
-        .await
-        .map_err(|e| ApiError::Internal(format!("read subtitle: {e}")))?;
-
-    Ok((
-        [(axum::http::header::CONTENT_TYPE, content_type)],
-        Body::from(data),
-    )
-        .into_response())
+    fn decision_response(&self) -> PlaybackDecisionResponse {
+        if self.direct_play {
+            return PlaybackDecisionResponse {
+                method: PlayMethod::DirectPlay,
+                reasons: Vec::new(),
+                video: StreamAction::Copy,
+                audio: StreamAction::Copy,
+                video_bitrate_kbps: None,
+                tone_map: false,
+            };
+        }
+        let plan = self.hls_plan;
+        PlaybackDecisionResponse {
+            // An HLS session always repackages, so a file playable as-is counts as a remux.
+            method: if self.decision.method == PlayMethod::DirectPlay {
+                PlayMethod::Remux
+            } else {
+                self.decision.method.clone()
+            },
+            reasons: self.decision.reasons.clone(),
+            video: plan.video,
+            audio: plan.audio,
+            video_bitrate_kbps: plan
+                .video_bitrate_kbps
+                .filter(|_| plan.video == StreamAction::Transcode),
+            tone_map: self.hls_tone_map,
+        }
+    }
 }
 
-// ---------------------------------------------------------------------------
-// System / GPU
-// ---------------------------------------------------------------------------
+#[derive(Deserialize)]
+struct CreateSessionRequest {
+    file_id: String,
+    #[serde(default)]
+    start_time_secs: Option<f64>,
+    /// What the client can play. With a profile, files it can play as-is are
+    /// answered with a `direct_url` instead of an HLS session.
+    #[serde(default)]
+    device_profile: Option<DeviceProfile>,
+}
 
 #[derive(Serialize)]
-struct PickDirectoryResponse {
-    path: String,
+struct PlaybackDecisionResponse {
+    method: PlayMethod,
+    /// Why the file can't be direct played.
+    reasons: Vec<TranscodeReason>,
+    video: StreamAction,
+    audio: StreamAction,
+    video_bitrate_kbps: Option<u32>,
+    /// HDR video is re-encoded, so it has to be tone-mapped to SDR.
+    tone_map: bool,
 }
 
-async fn pick_directory(_admin: AdminUser) -> Result<Json<PickDirectoryResponse>, AppError> {
+#[derive(Serialize)]
+struct SessionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hls_url: Option<String>,
+    /// Set instead of an HLS session when the device can play the file as-is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    direct_url: Option<String>,
+    /// Source position the playlist starts at; add it to the player's clock.
+    start_time_secs: f64,
+    /// Full source duration from ffprobe, so the scrubber can be sized up front.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_secs: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    decision: Option<PlaybackDecisionResponse>,
+}
+
+fn session_response(
+    auth: &AuthUser,
+    state: &AppState,
+    session_id: String,
+    file_id: &str,
+    start_time_secs: f64,
+) -> Result<SessionResponse, AppError> {
+    let stream_token = issue_stream_token(
+        &auth.user_id,
+        &auth.role,
+        Some(file_id),
+        Some(&session_id),
+        STREAM_TOKEN_TTL_SECONDS,
+        &state.jwt_secret,
+    )?;
+    let hls_url = format!("/stream/hls/{session_id}/master.m3u8?st={stream_token}");
+
+    Ok(SessionResponse {
+        session_id: Some(session_id),
+        hls_url: Some(hls_url),
+        direct_url: None,
+        start_time_secs,
+        duration_secs: None,
+        decision: None,
+    })
+}
+
+async fn create_playback_session(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    client: crate::client_ip::ClientIp,
+    Json(body): Json<CreateSessionRequest>,
+) -> Result<Json<SessionResponse>, AppError> {
+    ensure_file_access(&auth, &state, &body.file_id).await?;
+    crate::streaming::ensure_remote_access(&state, client, &auth.role).await?;
+    // Reserved up front and handed to the session below, so concurrent requests
+    // can't both pass the limit.
+    let slot = crate::streaming::reserve_stream(
+        &state,
+        &auth.user_id,
+        &auth.role,
+        &format!("session:{}", uuid::Uuid::new_v4()),
+    )
+    .await?;
+
+    // Look up the media file
+    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &body.file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or(ApiError::NotFound("media file not found".into()))?;
+
+    if file.is_remote {
+        return Err(
+            ApiError::BadRequest("remote (.strm) media can only be direct played".into()).into(),
+        );
+    }
+    let input_path = readable_media_path(&file)?;
+
+    let mut duration_secs = None;
+    let playback = match rustfin_transcoder::ffprobe::probe(
+        state.transcoder.ffprobe_path(),
+        &input_path,
+    )
+    .await
+    {
+        Ok(info) => {
+            crate::probe_cache::record(&state.db, &file.id, &info).await;
+            duration_secs = Some(info.duration_secs).filter(|d| *d > 0.0);
+            Some(PlaybackPlan::new(&info, body.device_profile.as_ref()))
+        }
+        Err(e) => {
+            tracing::debug!(file_id = %body.file_id, error = %e, "probe failed; transcoding all streams");
+            None
+        }
+    };
+
+    let direct_play_allowed = crate::playback_policy::direct_play_allowed(&state.db, &auth.role)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if let Some(playback) = playback
+        .as_ref()
+        .filter(|p| p.direct_play && direct_play_allowed)
+    {
+        let stream_token = issue_stream_token(
+            &auth.user_id,
+            &auth.role,
+            Some(&body.file_id),
+            None,
+            STREAM_TOKEN_TTL_SECONDS,
+            &state.jwt_secret,
+        )?;
+        return Ok(Json(SessionResponse {
+            session_id: None,
+            hls_url: None,
+            direct_url: Some(format!("/stream/file/{}?st={stream_token}", body.file_id)),
+            start_time_secs: body.start_time_secs.unwrap_or(0.0).max(0.0),
+            duration_secs,
+            decision: Some(playback.decision_response()),
+        }));
+    }
+    let plan = playback.as_ref().map(|p| p.hls_plan).unwrap_or_default();
+    let renditions = playback
+        .as_ref()
+        .map(|p| p.renditions.clone())
+        .unwrap_or_default();
+
+    ensure_tool_available(&state.media_tools.ffmpeg, "ffmpeg", "transcoding")?;
+    let session_id = state
+        .transcoder
+        .create_session(
+            input_path,
+            body.start_time_secs,
+            None,
+            plan,
+            renditions,
+            duration_secs,
+            auth.user_id.clone(),
+            body.file_id.clone(),
+        )
+        .await
+        .map_err(map_transcode_session_error)?;
+    state
+        .transcoder
+        .hold_for_session(&session_id, Box::new(slot))
+        .await;
+    let start_time_secs = state
+        .transcoder
+        .session_start_time(&session_id)
+        .await
+        .unwrap_or(0.0);
+
+    let mut response = session_response(&auth, &state, session_id, &body.file_id, start_time_secs)?;
+    response.duration_secs = duration_secs;
+    response.decision = Some(match playback {
+        Some(playback) => playback.decision_response(),
+        None => PlaybackDecisionResponse {
+            method: PlayMethod::Transcode,
+            reasons: Vec::new(),
+            video: plan.video,
+            audio: plan.audio,
+            video_bitrate_kbps: None,
+            tone_map: false,
+        },
+    });
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+struct SeekSessionRequest {
+    start_time_secs: f64,
+}
+
+/// Restart an HLS session at a new position (requires `RUSTFIN_HLS_SEEK_RESTART`).
+async fn seek_playback_session(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(sid): Path<String>,
+    Json(body): Json<SeekSessionRequest>,
+) -> Result<Json<SessionResponse>, AppError> {
+    if !state.transcoder.seek_restart_enabled() {
+        return Err(ApiError::Conflict(
+            "seek restarts are disabled; create a new session with start_time_secs".into(),
+        )
+        .into());
+    }
+    if !body.start_time_secs.is_finite() || body.start_time_secs < 0.0 {
+        return Err(ApiError::validation(json!({
+            "start_time_secs": ["must be a non-negative number"]
+        }))
+        .into());
+    }
+
+    let access = state
+        .transcoder
+        .get_session_access(&sid)
+        .await
+        .ok_or_else(|| ApiError::NotFound("session not found".into()))?;
+    if auth.role != "admin" && access.owner_user_id != auth.user_id {
+        return Err(ApiError::Forbidden("session belongs to another user".into()).into());
+    }
+
+    let session_id = state
+        .transcoder
+        .restart_session_at(&sid, body.start_time_secs)
+        .await
+        .map_err(map_transcode_session_error)?;
+
+    let mut response = session_response(
+        &auth,
+        &state,
+        session_id.clone(),
+        &access.file_id,
+        body.start_time_secs,
+    )?;
+    response.duration_secs = state
+        .transcoder
+        .playlist_hints(&session_id)
+        .await
+        .and_then(|hints| hints.source_duration_secs);
+    Ok(Json(response))
+}
+
+async fn stop_playback_session(
+    _auth: AuthUser,
+    State(state): State<AppState>,
+    Path(sid): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    state
+        .transcoder
+        .stop_session(&sid)
+        .await
+        .map_err(|e| match e {
+            rustfin_transcoder::TranscodeError::SessionNotFound(_) => {
+                ApiError::NotFound("session not found".into())
+            }
+            other => ApiError::Internal(format!("transcode error: {other}")),
+        })?;
+
+    Ok(Json(serde_json::json!({ "ok": true })))
+}
+
+// ---------------------------------------------------------------------------
+// Media info (ffprobe)
+// ---------------------------------------------------------------------------
+
+async fn get_media_info(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(file_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    ensure_file_access(&auth, &state, &file_id).await?;
+
+    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or(ApiError::NotFound("media file not found".into()))?;
+
+    if file.is_remote {
+        return Err(ApiError::BadRequest("remote (.strm) media cannot be probed".into()).into());
+    }
+    let media_path = readable_media_path(&file)?;
+    ensure_tool_available(&state.media_tools.ffprobe, "ffprobe", "media probing")?;
+
+    let info = rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), &media_path)
+        .await
+        .map_err(|e| {
+            let message = e.to_string().to_lowercase();
+            if message.contains("spawn failed")
+                && (message.contains("no such file") || message.contains("not found"))
+            {
+                ApiError::Internal(
+                    "ffprobe is not available; configure RUSTFIN_FFPROBE_PATH or install ffprobe"
+                        .into(),
+                )
+            } else if message.contains("permission denied") {
+                ApiError::Internal("media file is not readable by ffprobe".into())
+            } else {
+                ApiError::Internal(format!("ffprobe error: {e}"))
+            }
+        })?;
+    crate::probe_cache::record(&state.db, &file_id, &info).await;
+
+    Ok(Json(serde_json::to_value(&info).unwrap()))
+}
+
+// ---------------------------------------------------------------------------
+// HLS serving
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Default, Deserialize)]
+struct HlsAuthQuery {
+    st: Option<String>,
+}
+
+#[derive(Debug)]
+struct AuthorizedHlsSession {
+    user_id: String,
+    role: String,
+    file_id: String,
+    stream_token: Option<String>,
+}
+
+fn attach_stream_token_to_playlist(playlist: &str, token: &str) -> String {
+    let mut out = String::with_capacity(playlist.len() + 64);
+    let mut first = true;
+    for line in playlist.lines() {
+        if !first {
+            out.push('\n');
+        }
+        first = false;
+
+        let trimmed = line.trim();
+        if trimmed.starts_with('#') {
+            out.push_str(&attach_stream_token_to_uri_attribute(line, token));
+            continue;
+        }
+        if trimmed.is_empty() {
+            out.push_str(line);
+            continue;
+        }
+
+        if trimmed.contains("st=") {
+            out.push_str(trimmed);
+            continue;
+        }
+
+        let sep = if trimmed.contains('?') { "&" } else { "?" };
+        out.push_str(trimmed);
+        out.push_str(sep);
+        out.push_str("st=");
+        out.push_str(token);
+    }
+    if playlist.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// Tags like `#EXT-X-MEDIA` carry their playlist in a `URI="..."` attribute.
+fn attach_stream_token_to_uri_attribute(line: &str, token: &str) -> String {
+    const ATTR: &str = "URI=\"";
+    let Some(start) = line.find(ATTR).map(|i| i + ATTR.len()) else {
+        return line.to_string();
+    };
+    let Some(len) = line[start..].find('"') else {
+        return line.to_string();
+    };
+    let uri = &line[start..start + len];
+    if uri.contains("st=") {
+        return line.to_string();
+    }
+    let sep = if uri.contains('?') { "&" } else { "?" };
+    format!(
+        "{}{uri}{sep}st={token}{}",
+        &line[..start],
+        &line[start + len..]
+    )
+}
+
+/// Prepare a session playlist for a client: annotate media playlists and point
+/// every URI at the session with a stream token.
+async fn render_session_playlist(
+    state: &AppState,
+    sid: &str,
+    authorized: AuthorizedHlsSession,
+    content: String,
+) -> Result<String, AppError> {
+    let stream_token = match authorized.stream_token {
+        Some(t) => t,
+        None => issue_stream_token(
+            &authorized.user_id,
+            &authorized.role,
+            Some(&authorized.file_id),
+            Some(sid),
+            STREAM_TOKEN_TTL_SECONDS,
+            &state.jwt_secret,
+        )?,
+    };
+    // Master playlists only list other playlists.
+    let is_master = content.contains("#EXT-X-STREAM-INF");
+    let content = match state.transcoder.playlist_hints(sid).await {
+        Some(hints) if !is_master => {
+            rustfin_transcoder::hls::annotate_media_playlist(&content, &hints)
+        }
+        _ => content,
+    };
+    Ok(attach_stream_token_to_playlist(&content, &stream_token))
+}
+
+async fn authorize_hls_session_request(
+    state: &AppState,
+    sid: &str,
+    headers: &axum::http::HeaderMap,
+    query: &HlsAuthQuery,
+) -> Result<AuthorizedHlsSession, AppError> {
+    let identity = resolve_stream_request_identity(state, headers, query.st.as_deref())?;
+
+    let session = state
+        .transcoder
+        .get_session_access(sid)
+        .await
+        .ok_or_else(|| ApiError::NotFound("HLS session not found".into()))?;
+
+    if session.owner_user_id != identity.user_id {
+        return Err(
+            ApiError::Forbidden("HLS session does not belong to this account".into()).into(),
+        );
+    }
+
+    if let Some(claims) = &identity.stream_claims {
+        if claims.session_id.as_deref() != Some(sid) {
+            return Err(ApiError::Forbidden(
+                "stream token is not scoped to this HLS session".into(),
+            )
+            .into());
+        }
+        if claims.file_id.as_deref() != Some(session.file_id.as_str()) {
+            return Err(ApiError::Forbidden(
+                "stream token is not scoped to this media file".into(),
+            )
+            .into());
+        }
+    }
+
+    Ok(AuthorizedHlsSession {
+        user_id: identity.user_id,
+        role: identity.role,
+        file_id: session.file_id,
+        stream_token: query.st.clone(),
+    })
+}
+
+async fn hls_master(
+    State(state): State<AppState>,
+    Path(sid): Path<String>,
+    Query(query): Query<HlsAuthQuery>,
+    headers: axum::http::HeaderMap,
+) -> Result<axum::response::Response, AppError> {
+    use axum::body::Body;
+
+    let authorized = authorize_hls_session_request(&state, &sid, &headers, &query).await?;
+
+    // Ping the session
+    if !state.transcoder.ping(&sid).await {
+        return Err(ApiError::NotFound("HLS session not found".into()).into());
+    }
+
+    let path = state
+        .transcoder
+        .get_file_path(&sid, "master.m3u8")
+        .await
+        .map_err(|e| ApiError::NotFound(format!("session error: {e}")))?;
+
+    // Wait for ffmpeg to write the playlist (up to 10s)
+    for _ in 0..50 {
+        if path.exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+
+    if !path.exists() {
+        return Err(ApiError::Internal("playlist not ready yet".into()).into());
+    }
+
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("read playlist: {e}")))?;
+    let content = render_session_playlist(&state, &sid, authorized, content).await?;
+
+    Ok(hls_response(
+        rustfin_transcoder::hls::PLAYLIST_CONTENT_TYPE,
+        Body::from(content),
+    ))
+}
+
+async fn hls_segment(
+    State(state): State<AppState>,
+    Path((sid, filename)): Path<(String, String)>,
+    Query(query): Query<HlsAuthQuery>,
+    headers: axum::http::HeaderMap,
+) -> Result<axum::response::Response, AppError> {
+    use axum::body::Body;
+    use axum::http::header;
+
+    let authorized = authorize_hls_session_request(&state, &sid, &headers, &query).await?;
+
+    // Ping the session
+    if !state.transcoder.ping(&sid).await {
+        return Err(ApiError::NotFound("HLS session not found".into()).into());
+    }
+
+    // Validate filename (prevent traversal)
+    if filename.contains("..") || filename.contains('/') || filename.contains('\\') {
+        return Err(ApiError::BadRequest("invalid filename".into()).into());
+    }
+
+    // Subtitle playlists follow ffmpeg's progress, so they are rendered, not read.
+    if let Some(n) = rustfin_transcoder::hls::subtitle_playlist_index(&filename) {
+        let content = state
+            .transcoder
+            .subtitle_playlist(&sid, n)
+            .await
+            .map_err(|e| ApiError::NotFound(format!("session error: {e}")))?;
+        let content = render_session_playlist(&state, &sid, authorized, content).await?;
+        return Ok(hls_response(
+            rustfin_transcoder::hls::PLAYLIST_CONTENT_TYPE,
+            Body::from(content),
+        ));
+    }
+
+    let path = state
+        .transcoder
+        .get_file_path(&sid, &filename)
+        .await
+        .map_err(|e| ApiError::NotFound(format!("session error: {e}")))?;
+
+    // Wait for segment to appear (up to 5s)
+    for _ in 0..25 {
+        if path.exists() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+
+    if !path.exists() {
+        return Err(ApiError::NotFound("segment not ready".into()).into());
+    }
+
+    // Single-file sessions address segments as byte ranges of one growing file.
+    if !filename.ends_with(".m3u8")
+        && let Some(range) = headers
+            .get(header::RANGE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    {
+        return hls_segment_range(&state, &path, &filename, &range).await;
+    }
+
+    // Media playlists get the same treatment as master.m3u8.
+    if filename.ends_with(".m3u8") {
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .map_err(|e| ApiError::Internal(format!("read playlist: {e}")))?;
+        let content = render_session_playlist(&state, &sid, authorized, content).await?;
+        return Ok(hls_response(
+            rustfin_transcoder::hls::PLAYLIST_CONTENT_TYPE,
+            Body::from(content),
+        ));
+    }
+
+    // Segments are streamed; single-file ones can run to gigabytes.
+    let len = tokio::fs::metadata(&path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("read segment: {e}")))?
+        .len();
+    let body = crate::streaming::file_range_body(&path, 0, len, (), &state.streams).await?;
+    Ok(hls_response(
+        rustfin_transcoder::hls::segment_content_type(&filename),
+        body,
+    ))
+}
+
+/// A session playlist or segment; nothing a session serves may be cached.
+fn hls_response(content_type: &'static str, body: axum::body::Body) -> axum::response::Response {
+    use axum::http::header;
+    use axum::response::IntoResponse;
+
+    (
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "no-store"),
+            (
+                header::HeaderName::from_static("referrer-policy"),
+                "no-referrer",
+            ),
+            (
+                header::HeaderName::from_static("x-content-type-options"),
+                "nosniff",
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Serve `range` of a single-file HLS segment file. The playlist only lists a
+/// byte range once ffmpeg has written it, but a request racing the write waits
+/// (up to 5s) for the file to reach the range's end.
+async fn hls_segment_range(
+    state: &AppState,
+    path: &std::path::Path,
+    filename: &str,
+    range: &str,
+) -> Result<axum::response::Response, AppError> {
+    use axum::body::Body;
+    use axum::http::{StatusCode, header};
+
+    let file_len = || async {
+        tokio::fs::metadata(path)
+            .await
+            .map(|m| m.len())
+            .map_err(|e| ApiError::Internal(format!("read segment: {e}")))
+    };
+    // The last byte of an explicit `bytes=start-end` range.
+    let wanted_end = range
+        .trim()
+        .strip_prefix("bytes=")
+        .and_then(|spec| spec.split_once('-'))
+        .filter(|(start, _)| !start.trim().is_empty())
+        .and_then(|(_, end)| end.trim().parse::<u64>().ok());
+    let mut size = file_len().await?;
+    for _ in 0..25 {
+        if wanted_end.is_none_or(|end| end < size) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        size = file_len().await?;
+    }
+
+    let Ok(range) = crate::streaming::parse_range_header(range, size) else {
+        return Ok(axum::response::Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{size}"))
+            .body(Body::empty())
+            .unwrap());
+    };
+    let len = range.end_inclusive - range.start + 1;
+    let body =
+        crate::streaming::file_range_body(path, range.start, len, (), &state.streams).await?;
+
+    Ok(axum::response::Response::builder()
+        .status(StatusCode::PARTIAL_CONTENT)
+        .header(
+            header::CONTENT_TYPE,
+            rustfin_transcoder::hls::segment_content_type(filename),
+        )
+        .header(header::CONTENT_LENGTH, len.to_string())
+        .header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{size}", range.start, range.end_inclusive),
+        )
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header("Referrer-Policy", "no-referrer")
+        .header("X-Content-Type-Options", "nosniff")
+        .body(body)
+        .unwrap())
+}
+
+// ---------------------------------------------------------------------------
+// Artwork / Images
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct ImageQuery {
+    w: Option<u32>,
+    h: Option<u32>,
+    format: Option<String>,
+    /// Backdrops only: `1..` picks an image from the item's `backdrops/` folder.
+    index: Option<usize>,
+}
+
+async fn get_item_image(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((item_id, img_type)): Path<(String, String)>,
+    axum::extract::Query(query): axum::extract::Query<ImageQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::{StatusCode, header};
+    use axum::response::IntoResponse;
+    use std::io::Read;
+
+    if !IMAGE_TYPES.contains(&img_type.as_str()) {
+        return Err(ApiError::BadRequest(format!(
+            "invalid image type '{img_type}', must be one of: {IMAGE_TYPES:?}"
+        ))
+        .into());
+    }
+
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    let show_images =
+        rustfin_db::repo::libraries::get_library_settings(&state.db, &item.library_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .map(|s| s.show_images)
+            .unwrap_or(true);
+    if !show_images {
+        return Err(ApiError::NotFound("images are disabled for this library".into()).into());
+    }
+
+    // Get the image URL from DB
+    let extra_index = query.index.filter(|i| *i > 0);
+    let image_url = match extra_index {
+        Some(index) if img_type == "backdrop" => {
+            rustfin_db::repo::extras::list_item_extras(&state.db, &item_id, "backdrop")
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+                .into_iter()
+                .nth(index - 1)
+                .map(|extra| extra.path)
+                .ok_or_else(|| ApiError::NotFound(format!("no backdrop {index} for item")))?
+        }
+        Some(_) => {
+            return Err(
+                ApiError::BadRequest("index is only supported for backdrops".into()).into(),
+            );
+        }
+        None => rustfin_db::repo::items::get_item_image_url(&state.db, &item_id, &img_type)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .ok_or_else(|| ApiError::NotFound(format!("no {img_type} image for item")))?,
+    };
+
+    let images_dir = state.cache_dir.join("images");
+    std::fs::create_dir_all(&images_dir)
+        .map_err(|e| ApiError::Internal(format!("cache dir error: {e}")))?;
+    let crate::image_cache::CacheEntry {
+        fetch_url,
+        path: cache_path,
+    } = crate::image_cache::cache_entry(
+        &images_dir,
+        &item_id,
+        &img_type,
+        &image_url,
+        extra_index,
+        query.w,
+        query.h,
+        query.format.as_deref(),
+    );
+    let ext = cache_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("jpg")
+        .to_string();
+
+    // Check cache
+    if !cache_path.exists() {
+        // Download the image
+        if image_url.starts_with("http://") || image_url.starts_with("https://") {
+            let bytes = crate::image_cache::download(fetch_url)
+                .await
+                .map_err(|e| ApiError::Internal(format!("download error: {e:#}")))?;
+
+            std::fs::write(&cache_path, &bytes)
+                .map_err(|e| ApiError::Internal(format!("cache write error: {e}")))?;
+        } else if std::path::Path::new(&image_url).exists() {
+            // Local file — copy to cache
+            std::fs::copy(&image_url, &cache_path)
+                .map_err(|e| ApiError::Internal(format!("copy error: {e}")))?;
+        } else {
+            return Err(ApiError::NotFound("image source not available".into()).into());
+        }
+    }
+
+    // Read the cached file
+    let mut file = std::fs::File::open(&cache_path)
+        .map_err(|e| ApiError::Internal(format!("cache read error: {e}")))?;
+    let metadata = file
+        .metadata()
+        .map_err(|e| ApiError::Internal(format!("metadata error: {e}")))?;
+    let mut buf = Vec::with_capacity(metadata.len() as usize);
+    file.read_to_end(&mut buf)
+        .map_err(|e| ApiError::Internal(format!("read error: {e}")))?;
+
+    // ETag from file size + modified time
+    let etag = format!(
+        "\"{:x}-{:x}\"",
+        metadata.len(),
+        metadata
+            .modified()
+            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    );
+
+    let cache_control = crate::cache_policy::image_cache_control(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let content_type = match ext.as_str() {
+        "png" => "image/png",
+        "webp" => "image/webp",
+        _ => "image/jpeg",
+    };
+
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        buf,
+    )
+        .into_response())
+}
+
+const IMAGE_TYPES: [&str; 4] = ["poster", "backdrop", "logo", "thumb"];
+
+#[derive(Deserialize)]
+struct ImageProxyQuery {
+    url: String,
+}
+
+/// A remote image fetched through the server and cached. Only allowlisted hosts
+/// are fetched; see [`crate::image_proxy`].
+async fn get_proxied_image(
+    _auth: AuthUser,
+    State(state): State<AppState>,
+    axum::extract::Query(query): axum::extract::Query<ImageProxyQuery>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::http::header;
+    use axum::response::IntoResponse;
+
+    let hosts = crate::image_proxy::allowed_hosts(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let Some(url) = crate::image_proxy::allowed_url(&query.url, &hosts) else {
+        return Err(ApiError::Forbidden("image host is not allowed".into()).into());
+    };
+
+    let images_dir = state.cache_dir.join("images");
+    let (data, content_type) = match crate::image_proxy::cached(&images_dir, &url) {
+        Some((path, content_type)) => {
+            let data = tokio::fs::read(&path)
+                .await
+                .map_err(|e| ApiError::Internal(format!("cache read error: {e}")))?;
+            (data, content_type)
+        }
+        None => {
+            let resp = crate::image_proxy::client()
+                .map_err(|e| ApiError::Internal(format!("http client error: {e}")))?
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| ApiError::Internal(format!("download error: {e}")))?;
+            if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(ApiError::NotFound("image not found upstream".into()).into());
+            }
+            if !resp.status().is_success() {
+                return Err(ApiError::Internal(format!(
+                    "image download failed: {}",
+                    resp.status()
+                ))
+                .into());
+            }
+            let (ext, content_type) = resp
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(crate::image_proxy::image_type_for)
+                .ok_or_else(|| ApiError::Internal("upstream did not return an image".into()))?;
+            let bytes = crate::image_proxy::read_limited(resp)
+                .await
+                .map_err(|e| match e {
+                    crate::image_proxy::FetchError::TooLarge => {
+                        ApiError::Internal("upstream image is too large".into())
+                    }
+                    crate::image_proxy::FetchError::Http(e) => {
+                        ApiError::Internal(format!("download error: {e}"))
+                    }
+                })?;
+            tokio::fs::create_dir_all(&images_dir)
+                .await
+                .map_err(|e| ApiError::Internal(format!("cache dir error: {e}")))?;
+            let budget = crate::image_cache::PrefetchOptions::from_env().max_cache_bytes;
+            if crate::image_cache::cache_size(&images_dir).await + bytes.len() as u64 <= budget {
+                let path = crate::image_proxy::cache_path(&images_dir, &url, ext);
+                crate::image_cache::write_cached(&path, &bytes)
+                    .await
+                    .map_err(|e| ApiError::Internal(format!("cache write error: {e}")))?;
+            }
+            (bytes, content_type)
+        }
+    };
+
+    let cache_control = crate::cache_policy::image_cache_control(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CACHE_CONTROL, cache_control),
+            (
+                header::HeaderName::from_static("referrer-policy"),
+                "no-referrer".to_string(),
+            ),
+            (
+                header::HeaderName::from_static("x-content-type-options"),
+                "nosniff".to_string(),
+            ),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+struct CreateImageUploadRequest {
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct UploadedImageInfo {
+    format: crate::image_upload::ImageFormat,
+    width: u32,
+    height: u32,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct ImageUploadResponse {
+    upload_id: String,
+    item_id: String,
+    img_type: String,
+    offset: u64,
+    size: u64,
+    complete: bool,
+    /// Set once the last chunk has arrived and the image has been accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<UploadedImageInfo>,
+}
+
+impl ImageUploadResponse {
+    fn new(session: &crate::image_upload::UploadSession, offset: u64) -> Self {
+        Self {
+            upload_id: session.id.clone(),
+            item_id: session.item_id.clone(),
+            img_type: session.img_type.clone(),
+            offset,
+            size: session.size,
+            complete: false,
+            image: None,
+        }
+    }
+}
+
+/// Start a chunked upload of new artwork for an item. Chunks are then sent with
+/// `PUT /uploads/images/{upload_id}`.
+async fn create_image_upload(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((item_id, img_type)): Path<(String, String)>,
+    Json(body): Json<CreateImageUploadRequest>,
+) -> Result<(axum::http::StatusCode, Json<ImageUploadResponse>), AppError> {
+    if !IMAGE_TYPES.contains(&img_type.as_str()) {
+        return Err(ApiError::BadRequest(format!(
+            "invalid image type '{img_type}', must be one of: {IMAGE_TYPES:?}"
+        ))
+        .into());
+    }
+    if body.size == 0 || body.size > crate::image_upload::MAX_UPLOAD_BYTES {
+        return Err(ApiError::validation(json!({
+            "size": [format!(
+                "must be between 1 and {} bytes",
+                crate::image_upload::MAX_UPLOAD_BYTES
+            )]
+        }))
+        .into());
+    }
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    ensure_tool_available(&state.media_tools.ffmpeg, "ffmpeg", "image uploads")?;
+
+    let pending = crate::image_upload::UploadSession::sweep(
+        &state.cache_dir,
+        crate::image_upload::ABANDONED_AFTER,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+    let pending_bytes: u64 = pending
+        .iter()
+        .filter(|s| s.user_id == auth.user_id)
+        .map(|s| s.size)
+        .sum();
+    if pending_bytes + body.size > crate::image_upload::MAX_PENDING_BYTES_PER_USER {
+        return Err(ApiError::Conflict(format!(
+            "uploads in progress already hold {pending_bytes} bytes; finish or cancel them first"
+        ))
+        .into());
+    }
+
+    let session = crate::image_upload::UploadSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: auth.user_id.clone(),
+        item_id,
+        img_type,
+        size: body.size,
+    };
+    session
+        .create(&state.cache_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+    Ok((
+        axum::http::StatusCode::CREATED,
+        Json(ImageUploadResponse::new(&session, 0)),
+    ))
+}
+
+/// The caller's upload session with this id.
+async fn load_image_upload(
+    auth: &AuthUser,
+    state: &AppState,
+    upload_id: &str,
+) -> Result<crate::image_upload::UploadSession, AppError> {
+    crate::image_upload::UploadSession::load(&state.cache_dir, upload_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?
+        .filter(|s| s.user_id == auth.user_id)
+        .ok_or_else(|| ApiError::NotFound("upload not found".into()).into())
+}
+
+/// How much of an upload has arrived, so an interrupted client knows where to
+/// resume.
+async fn get_image_upload(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<ImageUploadResponse>, AppError> {
+    let session = load_image_upload(&auth, &state, &upload_id).await?;
+    let offset = session
+        .offset(&state.cache_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+    Ok(Json(ImageUploadResponse::new(&session, offset)))
+}
+
+async fn cancel_image_upload(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let upload = state.uploads.share(&upload_id);
+    let _guard = upload.lock.lock().await;
+    let session = load_image_upload(&auth, &state, &upload_id).await?;
+    session.remove(&state.cache_dir).await;
+    Ok(Json(json!({ "ok": true })))
+}
+
+/// Append a chunk at the offset given in `Upload-Offset`. The chunk that
+/// completes the upload also checks the image and, if it is accepted, makes it
+/// the item's artwork and locks the field against metadata refreshes.
+async fn put_image_upload_chunk(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+    headers: axum::http::HeaderMap,
+    chunk: axum::body::Bytes,
+) -> Result<Json<ImageUploadResponse>, AppError> {
+    // Held until the chunk is written, so two chunks sent at once cannot both pass
+    // the offset check.
+    let upload = state.uploads.share(&upload_id);
+    let _guard = upload.lock.lock().await;
+    let session = load_image_upload(&auth, &state, &upload_id).await?;
+    let claimed_offset = headers
+        .get("upload-offset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .ok_or_else(|| ApiError::BadRequest("Upload-Offset header is required".into()))?;
+    let offset = session
+        .offset(&state.cache_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+    if claimed_offset != offset {
+        return Err(ApiError::Conflict(format!(
+            "upload is at offset {offset}, not {claimed_offset}"
+        ))
+        .into());
+    }
+    let offset = offset + chunk.len() as u64;
+    if offset > session.size {
+        return Err(ApiError::validation(json!({
+            "body": [format!("chunk runs past the declared size of {} bytes", session.size)]
+        }))
+        .into());
+    }
+    session
+        .append(&state.cache_dir, &chunk)
+        .await
+        .map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+
+    let mut response = ImageUploadResponse::new(&session, offset);
+    if offset < session.size {
+        return Ok(Json(response));
+    }
+
+    let received = tokio::fs::read(session.part_path(&state.cache_dir)).await;
+    session.remove(&state.cache_dir).await;
+    let received = received.map_err(|e| ApiError::Internal(format!("upload dir error: {e}")))?;
+    let image = crate::image_upload::normalize(
+        state.transcoder.ffmpeg_path(),
+        &received,
+        &crate::image_upload::UploadSession::dir(&state.cache_dir).join(&session.id),
+    )
+    .await
+    .map_err(|e| match e {
+        crate::image_upload::NormalizeError::Rejected(e) => {
+            ApiError::validation(json!({ "file": [e.to_string()] }))
+        }
+        crate::image_upload::NormalizeError::Transcode(e) => {
+            ApiError::Internal(format!("image encoding error: {e}"))
+        }
+    })?;
+
+    let artwork_dir = state.cache_dir.join("artwork");
+    tokio::fs::create_dir_all(&artwork_dir)
+        .await
+        .map_err(|e| ApiError::Internal(format!("artwork dir error: {e}")))?;
+    let stem = format!("{}_{}", session.item_id, session.img_type);
+    let path = artwork_dir.join(format!("{stem}.{}", image.format.extension()));
+    tokio::fs::write(&path, &image.bytes)
+        .await
+        .map_err(|e| ApiError::Internal(format!("artwork write error: {e}")))?;
+    // An earlier upload in another format would otherwise linger.
+    for format in ["jpg", "png", "webp"] {
+        if format != image.format.extension() {
+            let _ = tokio::fs::remove_file(artwork_dir.join(format!("{stem}.{format}"))).await;
+        }
+    }
+
+    rustfin_db::repo::items::set_item_image_url(
+        &state.db,
+        &session.item_id,
+        &session.img_type,
+        &path.to_string_lossy(),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    rustfin_metadata::merge::lock_field(
+        &state.db,
+        &session.item_id,
+        &format!("{}_url", session.img_type),
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    // Drop sizes cached from the previous image.
+    if let Ok(mut entries) = tokio::fs::read_dir(state.cache_dir.join("images")).await {
+        let prefix = format!("{stem}_");
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                let _ = tokio::fs::remove_file(entry.path()).await;
+            }
+        }
+    }
+
+    response.complete = true;
+    response.image = Some(UploadedImageInfo {
+        format: image.format,
+        width: image.width,
+        height: image.height,
+        url: format!(
+            "/api/v1/items/{}/images/{}",
+            session.item_id, session.img_type
+        ),
+    });
+    Ok(Json(response))
+}
+
+// ---------------------------------------------------------------------------
+// Subtitles
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct SubtitleInfo {
+    #[serde(rename = "type")]
+    sub_type: String, // "sidecar" or "embedded"
+    format: String,
+    language: Option<String>,
+    title: Option<String>,
+    forced: bool,
+    sdh: bool,
+    /// Players should turn this track on by default: a forced subtitle matching the
+    /// original-language audio the user's preferences select.
+    auto_select: bool,
+    /// The one track players should select when the user has not picked one; see
+    /// [`crate::subtitle_selection`].
+    default: bool,
+    /// For sidecar: URL to serve the file. For embedded: stream index.
+    source: String,
+    /// Where to fetch the track: the sidecar file, or the embedded track as WebVTT.
+    url: String,
+}
+
+/// Sidecar subtitles next to `media_path`, then the embedded tracks `info` lists.
+fn list_subtitles(
+    file_id: &str,
+    media_path: &std::path::Path,
+    info: Option<&rustfin_transcoder::ffprobe::MediaInfo>,
+    prefs: &UserPreferences,
+) -> Vec<SubtitleInfo> {
+    let mut subtitles = Vec::new();
+    let audio = info.map(|i| i.audio.as_slice()).unwrap_or_default();
+    let preferred_audio = &prefs.preferred_audio_languages;
+    let auto_select = |forced: bool, language: Option<&str>| {
+        rustfin_transcoder::tracks::auto_select_forced(forced, language, audio, preferred_audio)
+    };
+
+    // 1. Sidecar subtitles
+    let sidecars = rustfin_scanner::subtitles::discover_sidecars(media_path);
+    for sub in &sidecars {
+        let encoded_path = base64_url_encode(&sub.path.to_string_lossy());
+        let url = format!("/stream/subtitles/{encoded_path}");
+        subtitles.push(SubtitleInfo {
+            sub_type: "sidecar".into(),
+            format: format!("{:?}", sub.format).to_lowercase(),
+            language: sub.language.clone(),
+            title: sub.title.clone(),
+            forced: sub.forced,
+            sdh: sub.sdh,
+            auto_select: auto_select(sub.forced, sub.language.as_deref()),
+            default: false,
+            source: url.clone(),
+            url,
+        });
+    }
+
+    // 2. Embedded subtitles (via ffprobe)
+    for sub in info.map(|i| i.subtitles.as_slice()).unwrap_or_default() {
+        subtitles.push(SubtitleInfo {
+            sub_type: "embedded".into(),
+            format: sub.codec.clone(),
+            language: sub.language.clone(),
+            title: sub.title.clone(),
+            forced: sub.is_forced,
+            sdh: false,
+            auto_select: auto_select(sub.is_forced, sub.language.as_deref()),
+            default: false,
+            source: format!("stream:{}", sub.index),
+            url: format!("/stream/file/{file_id}/subtitles/{}", sub.index),
+        });
+    }
+
+    let candidates: Vec<_> = subtitles
+        .iter()
+        .map(|s| crate::subtitle_selection::Candidate {
+            language: s.language.as_deref(),
+            forced: s.forced,
+            embedded: s.sub_type == "embedded",
+            auto_select: s.auto_select,
+        })
+        .collect();
+    let audio_language = rustfin_transcoder::tracks::choose_audio(audio, preferred_audio)
+        .and_then(|a| a.language.as_deref());
+    if let Some(i) = crate::subtitle_selection::default_subtitle(&candidates, prefs, audio_language)
+    {
+        subtitles[i].default = true;
+    }
+    subtitles
+}
+
+/// An item the caller may access, with its media file.
+async fn load_item_media_file(
+    auth: &AuthUser,
+    state: &AppState,
+    item_id: &str,
+) -> Result<
+    (
+        rustfin_db::repo::items::ItemRow,
+        rustfin_db::repo::media_files::MediaFileRow,
+    ),
+    AppError,
+> {
+    let item = rustfin_db::repo::items::get_item(&state.db, item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(auth, state, &item.library_id).await?;
+
+    // Get the media file for this item
+    let file_id = rustfin_db::repo::items::get_item_file_id(&state.db, item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or(ApiError::NotFound("item has no media file".into()))?;
+
+    let file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or(ApiError::NotFound("media file not found".into()))?;
+    Ok((item, file))
+}
+
+/// Subtitles for a media file, probing embedded tracks when the file is present.
+async fn load_file_subtitles(
+    auth: &AuthUser,
+    state: &AppState,
+    file: &rustfin_db::repo::media_files::MediaFileRow,
+) -> Result<Vec<SubtitleInfo>, AppError> {
+    let media_path = std::path::Path::new(&file.path);
+    let info = if media_path.exists() {
+        rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), media_path)
+            .await
+            .ok()
+    } else {
+        None
+    };
+    let prefs = load_prefs(state, &auth.user_id).await?;
+    Ok(list_subtitles(&file.id, media_path, info.as_ref(), &prefs))
+}
+
+async fn get_item_subtitles(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<Vec<SubtitleInfo>>, AppError> {
+    let (_, file) = load_item_media_file(&auth, &state, &item_id).await?;
+    Ok(Json(load_file_subtitles(&auth, &state, &file).await?))
+}
+
+#[derive(Serialize)]
+struct AttachmentInfo {
+    index: u32,
+    filename: Option<String>,
+    mime_type: Option<String>,
+    /// `font`, `image` or `other`.
+    kind: &'static str,
+    url: String,
+}
+
+/// Fonts and images embedded in the item's media file. Clients rendering ASS
+/// subtitles load the fonts from here.
+async fn get_item_attachments(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<Vec<AttachmentInfo>>, AppError> {
+    let (_, file) = load_item_media_file(&auth, &state, &item_id).await?;
+    let media_path = std::path::Path::new(&file.path);
+    if file.is_remote || !media_path.is_file() {
+        return Ok(Json(Vec::new()));
+    }
+    let info = rustfin_transcoder::ffprobe::probe(state.transcoder.ffprobe_path(), media_path)
+        .await
+        .map_err(|e| ApiError::ServiceUnavailable(format!("could not probe media file: {e}")))?;
+    Ok(Json(
+        info.attachments
+            .into_iter()
+            .filter(|a| !a.attached_pic)
+            .map(|a| AttachmentInfo {
+                kind: if a.is_font() {
+                    "font"
+                } else if a.is_image() {
+                    "image"
+                } else {
+                    "other"
+                },
+                url: format!("/stream/file/{}/attachments/{}", file.id, a.index),
+                index: a.index,
+                filename: a.filename,
+                mime_type: a.mime_type,
+            })
+            .collect(),
+    ))
+}
+
+async fn opensubtitles_client(
+    state: &AppState,
+) -> Result<rustfin_metadata::opensubtitles::OpenSubtitlesClient, AppError> {
+    crate::opensubtitles::client(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::Conflict("OpenSubtitles API key is not configured".into()).into())
+}
+
+fn opensubtitles_error(e: rustfin_metadata::MetadataError) -> AppError {
+    match e {
+        rustfin_metadata::MetadataError::NotFound => {
+            ApiError::NotFound("subtitle not found on OpenSubtitles".into()).into()
+        }
+        e => ApiError::ServiceUnavailable(format!("OpenSubtitles request failed: {e}")).into(),
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct SubtitleSearchRequest {
+    /// Defaults to the caller's preferred subtitle languages, else English.
+    languages: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct SubtitleSearchResponse {
+    item_id: String,
+    languages: Vec<String>,
+    candidates: Vec<rustfin_metadata::opensubtitles::SubtitleCandidate>,
+}
+
+async fn search_item_subtitles(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    body: Option<Json<SubtitleSearchRequest>>,
+) -> Result<Json<SubtitleSearchResponse>, AppError> {
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    let (item, _) = load_item_media_file(&auth, &state, &item_id).await?;
+    let client = opensubtitles_client(&state).await?;
+
+    let mut languages = match body.languages {
+        Some(languages) => languages,
+        None => {
+            load_prefs(&state, &auth.user_id)
+                .await?
+                .preferred_subtitle_languages
+        }
+    };
+    languages.retain(|l| !l.trim().is_empty());
+    if languages.is_empty() {
+        languages.push("en".into());
+    }
+
+    let mut query = rustfin_metadata::opensubtitles::SubtitleQuery {
+        title: item.title.clone(),
+        year: item.year.map(|y| y as i32),
+        languages: languages.clone(),
+        ..Default::default()
+    };
+    // Episodes are looked up by the series' TMDB id and their numbering.
+    let mut provider_item = item.id.clone();
+    if item.kind == "episode" {
+        let context = rustfin_db::repo::items::get_episode_contexts(
+            &state.db,
+            std::slice::from_ref(&item.id),
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .remove(&item.id);
+        if let Some(context) = context {
+            query.season_number = context.season_number;
+            query.episode_number = context.episode_number;
+            if let Some(series_id) = context.series_id {
+                provider_item = series_id;
+            }
+            if let Some(series_title) = context.series_title {
+                query.title = series_title;
+                query.year = None;
+            }
+        }
+    }
+    query.tmdb_id = rustfin_metadata::merge::get_provider_ids(&state.db, &provider_item)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .into_iter()
+        .find(|(provider, _)| provider == "tmdb")
+        .map(|(_, id)| id);
+
+    let candidates = client.search(&query).await.map_err(opensubtitles_error)?;
+    Ok(Json(SubtitleSearchResponse {
+        item_id,
+        languages,
+        candidates,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubtitleDownloadRequest {
+    /// `file_id` of a search candidate.
+    file_id: i64,
+    language: String,
+    #[serde(default)]
+    forced: bool,
+    #[serde(default)]
+    hearing_impaired: bool,
+}
+
+async fn download_item_subtitle(
+    admin: AdminUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    Json(body): Json<SubtitleDownloadRequest>,
+) -> Result<Json<Vec<SubtitleInfo>>, AppError> {
+    // Downloads write into the library folder, so only admins may fetch them.
+    let auth = AuthUser {
+        user_id: admin.user_id,
+        username: admin.username,
+        role: "admin".into(),
+    };
+    let (_, file) = load_item_media_file(&auth, &state, &item_id).await?;
+    let media_path = std::path::Path::new(&file.path);
+    // Check the name before spending a download on it.
+    let sidecar_for = |format| {
+        rustfin_scanner::subtitles::sidecar_path(
+            media_path,
+            &body.language,
+            body.forced,
+            body.hearing_impaired,
+            format,
+        )
+        .ok_or_else(|| -> AppError {
+            ApiError::validation(json!({ "language": ["not a language code"] })).into()
+        })
+    };
+    sidecar_for(rustfin_scanner::subtitles::SubtitleFormat::Srt)?;
+
+    let client = opensubtitles_client(&state).await?;
+    let downloaded = client
+        .download(body.file_id)
+        .await
+        .map_err(opensubtitles_error)?;
+    let format = downloaded
+        .file_name
+        .as_deref()
+        .and_then(|name| std::path::Path::new(name).extension())
+        .and_then(|ext| ext.to_str())
+        .and_then(rustfin_scanner::subtitles::SubtitleFormat::from_extension)
+        .unwrap_or(rustfin_scanner::subtitles::SubtitleFormat::Srt);
+    let sidecar = sidecar_for(format)?;
+
+    let written = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&sidecar)
+        .await;
+    let mut out = match written {
+        Ok(out) => out,
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(ApiError::Conflict(format!(
+                "a subtitle named {} already exists",
+                sidecar.file_name().unwrap_or_default().to_string_lossy()
+            ))
+            .into());
+        }
+        Err(e)
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+            ) =>
+        {
+            return Err(ApiError::Conflict("the library folder is not writable".into()).into());
+        }
+        Err(e) => {
+            return Err(ApiError::Internal(format!("failed to write subtitle: {e}")).into());
+        }
+    };
+    {
+        use tokio::io::AsyncWriteExt;
+        if let Err(e) = out.write_all(&downloaded.bytes).await {
+            drop(out);
+            let _ = tokio::fs::remove_file(&sidecar).await;
+            return Err(ApiError::Internal(format!("failed to write subtitle: {e}")).into());
+        }
+    }
+    tracing::info!(item_id = %item_id, path = %sidecar.display(), "saved downloaded subtitle");
+
+    Ok(Json(load_file_subtitles(&auth, &state, &file).await?))
+}
+
+fn base64_url_encode(s: &str) -> String {
+    use std::fmt::Write;
+    let mut out = String::new();
+    for b in s.bytes() {
+        write!(&mut out, "{b:02x}").unwrap();
+    }
+    out
+}
+
+fn hex_decode(s: &str) -> Option<String> {
+    let bytes: Result<Vec<u8>, _> = (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+        .collect();
+    bytes.ok().and_then(|b| String::from_utf8(b).ok())
+}
+
+async fn serve_subtitle(
+    State(state): State<AppState>,
+    Path(sub_path): Path<String>,
+) -> Result<axum::response::Response, AppError> {
+    use axum::body::Body;
+    use axum::response::IntoResponse;
+
+    let decoded =
+        hex_decode(&sub_path).ok_or(ApiError::BadRequest("invalid subtitle path".into()))?;
+
+    let path = std::path::Path::new(&decoded);
+
+    // Security: verify the path is under a library root
+    let canonical = path
+        .canonicalize()
+        .map_err(|_| ApiError::NotFound("subtitle file not found".into()))?;
+
+    let lib_paths = rustfin_db::repo::libraries::get_all_library_paths(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let allowed = lib_paths.iter().any(|lp| {
+        if let Ok(root) = std::path::Path::new(lp).canonicalize() {
+            canonical.starts_with(&root)
+        } else {
+            false
+        }
+    });
+
+    if !allowed {
+        return Err(ApiError::Forbidden("path not in allowed library".into()).into());
+    }
+
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("srt");
+    let format = rustfin_scanner::subtitles::SubtitleFormat::from_extension(ext);
+
+    let data = tokio::fs::read(&canonical)
+        .await
+        .map_err(|e| ApiError::Internal(format!("read subtitle: {e}")))?;
+
+    // Text formats are re-encoded to UTF-8 so legacy/BOM-prefixed files render cleanly.
+    if let Some(format) = format.filter(|f| f.is_text()) {
+        if let Some(text) = rustfin_scanner::subtitles::decode_text_subtitle(&data) {
+            let content_type = format!("{}; charset=utf-8", format.mime_type());
+            return Ok((
+                [(axum::http::header::CONTENT_TYPE, content_type)],
+                Body::from(text),
+            )
+                .into_response());
+        }
+    }
+
+    let content_type = format
+        .map(|f| f.mime_type())
+        .unwrap_or("application/octet-stream");
+
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, content_type)],
+        Body::from(data),
+    )
+        .into_response())
+}
+
+// ---------------------------------------------------------------------------
+// System / GPU
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct PickDirectoryResponse {
+    path: String,
+}
+
+async fn pick_directory(_admin: AdminUser) -> Result<Json<PickDirectoryResponse>, AppError> {
     let path = tokio::task::spawn_blocking(open_directory_picker)
         .await
         .map_err(|e| ApiError::Internal(format!("directory picker task failed: {e}")))??;
@@ -2076,291 +5508,1297 @@ POSIX path of chosenFolder"#;
         .arg("-e")
         .arg(script)
         .output()
-        .map_err(|e| ApiError::Internal(format!("failed to launch folder picker: {e}")))?;
+        .map_err(|e| ApiError::Internal(format!("failed to launch folder picker: {e}")))?;
+
+    if output.status.success() {
+        let path = String::from_utf8(output.stdout).map_err(|e| {
+            ApiError::Internal(format!("folder picker returned invalid UTF-8: {e}"))
+        })?;
+        let path = path.trim().to_string();
+        if path.is_empty() {
+            return Err(ApiError::BadRequest("no directory selected".into()));
+        }
+        return Ok(path);
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("User canceled") || stderr.contains("(-128)") {
+        return Err(ApiError::BadRequest("directory selection cancelled".into()));
+    }
+
+    let detail = stderr.trim();
+    if detail.is_empty() {
+        return Err(ApiError::Internal(
+            "folder picker failed with an unknown error".into(),
+        ));
+    }
+
+    Err(ApiError::Internal(format!(
+        "folder picker failed: {detail}"
+    )))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn open_directory_picker_native() -> Result<String, ApiError> {
+    // Containerized builds cannot show host desktop pickers.
+    if std::path::Path::new("/.dockerenv").exists() {
+        return Err(ApiError::BadRequest(
+            "native directory picker is unavailable in this container; use ./scripts/start.sh so Browse can open the host file picker".into(),
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        open_directory_picker_linux()
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        open_directory_picker_windows()
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        Err(ApiError::BadRequest(
+            "directory picker is unavailable on this OS in this build; enter the path manually"
+                .into(),
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_directory_picker_linux() -> Result<String, ApiError> {
+    let mut last_err: Option<ApiError> = None;
+
+    if command_exists("zenity") {
+        match run_linux_folder_picker(
+            "zenity",
+            &[
+                "--file-selection",
+                "--directory",
+                "--title=Select a media directory for Rustyfin",
+            ],
+        ) {
+            Ok(path) => return Ok(path),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    if command_exists("kdialog") {
+        match run_linux_folder_picker(
+            "kdialog",
+            &[
+                "--getexistingdirectory",
+                ".",
+                "Select a media directory for Rustyfin",
+            ],
+        ) {
+            Ok(path) => return Ok(path),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    if std::path::Path::new("/media").exists() {
+        return Ok("/media".into());
+    }
+
+    if let Some(err) = last_err {
+        return Err(err);
+    }
+
+    Err(ApiError::BadRequest(
+        "directory picker is unavailable: install zenity or kdialog, or enter the path manually"
+            .into(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn command_exists(cmd: &str) -> bool {
+    std::process::Command::new(cmd)
+        .arg("--version")
+        .output()
+        .is_ok()
+}
+
+#[cfg(target_os = "linux")]
+fn run_linux_folder_picker(cmd: &str, args: &[&str]) -> Result<String, ApiError> {
+    let output = std::process::Command::new(cmd)
+        .args(args)
+        .output()
+        .map_err(|e| ApiError::Internal(format!("failed to launch {cmd}: {e}")))?;
+
+    if output.status.success() {
+        let path = String::from_utf8(output.stdout)
+            .map_err(|e| ApiError::Internal(format!("{cmd} returned invalid UTF-8: {e}")))?;
+        let path = path.trim().to_string();
+        if path.is_empty() {
+            return Err(ApiError::BadRequest("no directory selected".into()));
+        }
+        return Ok(path);
+    }
+
+    if output.status.code() == Some(1) {
+        return Err(ApiError::BadRequest("directory selection cancelled".into()));
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr.trim();
+    if detail.is_empty() {
+        return Err(ApiError::Internal(format!(
+            "{cmd} folder picker failed with unknown error"
+        )));
+    }
+    Err(ApiError::Internal(format!(
+        "{cmd} folder picker failed: {detail}"
+    )))
+}
+
+#[cfg(target_os = "windows")]
+fn open_directory_picker_windows() -> Result<String, ApiError> {
+    let script = r#"
+Add-Type -AssemblyName System.Windows.Forms
+$dialog = New-Object System.Windows.Forms.FolderBrowserDialog
+$dialog.Description = 'Select a media directory for Rustyfin'
+$result = $dialog.ShowDialog()
+if ($result -eq [System.Windows.Forms.DialogResult]::OK) {
+  Write-Output $dialog.SelectedPath
+}
+"#;
+
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .output()
+        .map_err(|e| {
+            ApiError::Internal(format!("failed to launch PowerShell folder picker: {e}"))
+        })?;
 
     if output.status.success() {
         let path = String::from_utf8(output.stdout).map_err(|e| {
-            ApiError::Internal(format!("folder picker returned invalid UTF-8: {e}"))
+            ApiError::Internal(format!(
+                "PowerShell folder picker returned invalid UTF-8: {e}"
+            ))
         })?;
         let path = path.trim().to_string();
         if path.is_empty() {
-            return Err(ApiError::BadRequest("no directory selected".into()));
+            return Err(ApiError::BadRequest("directory selection cancelled".into()));
         }
         return Ok(path);
     }
 
     let stderr = String::from_utf8_lossy(&output.stderr);
-    if stderr.contains("User canceled") || stderr.contains("(-128)") {
-        return Err(ApiError::BadRequest("directory selection cancelled".into()));
-    }
-
     let detail = stderr.trim();
     if detail.is_empty() {
         return Err(ApiError::Internal(
-            "folder picker failed with an unknown error".into(),
+            "PowerShell folder picker failed with unknown error".into(),
         ));
     }
-
     Err(ApiError::Internal(format!(
-        "folder picker failed: {detail}"
+        "PowerShell folder picker failed: {detail}"
     )))
 }
 
-#[cfg(not(target_os = "macos"))]
-fn open_directory_picker_native() -> Result<String, ApiError> {
-    // Containerized builds cannot show host desktop pickers.
-    if std::path::Path::new("/.dockerenv").exists() {
-        return Err(ApiError::BadRequest(
-            "native directory picker is unavailable in this container; use ./scripts/start.sh so Browse can open the host file picker".into(),
-        ));
+#[derive(Serialize)]
+struct SystemInfoResponse {
+    server_name: String,
+    version: &'static str,
+    /// Whether ffmpeg/ffprobe were found at startup, and their versions.
+    transcoding: rustfin_transcoder::tools::MediaTools,
+}
+
+async fn get_system_info(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<SystemInfoResponse>, AppError> {
+    let server_name = rustfin_db::repo::settings::get(&state.db, "server_name")
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .unwrap_or_else(|| "Rustyfin".to_string());
+    Ok(Json(SystemInfoResponse {
+        server_name,
+        version: env!("CARGO_PKG_VERSION"),
+        transcoding: state.media_tools.clone(),
+    }))
+}
+
+async fn get_gpu_caps(_auth: AdminUser) -> Result<Json<serde_json::Value>, AppError> {
+    let caps = rustfin_transcoder::gpu::detect(std::path::Path::new("ffmpeg")).await;
+    Ok(Json(serde_json::to_value(&caps).unwrap()))
+}
+
+#[derive(Serialize)]
+struct TmdbConfigResponse {
+    configured: bool,
+    key_preview: Option<String>,
+    source: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateTmdbConfigRequest {
+    api_key: String,
+}
+
+fn normalize_secret(input: &str) -> Option<String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed.to_string())
     }
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        return open_directory_picker_linux();
+fn secret_preview(secret: &str) -> String {
+    let len = secret.chars().count();
+    let suffix: String = secret
+        .chars()
+        .rev()
+        .take(4)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    if len <= 4 {
+        "****".to_string()
+    } else {
+        format!("****{suffix}")
     }
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        open_directory_picker_windows()
+async fn resolve_tmdb_key_for_admin(
+    state: &AppState,
+) -> Result<(Option<String>, Option<String>), AppError> {
+    let db_key = rustfin_db::repo::settings::get(&state.db, "tmdb_api_key")
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .and_then(|value| normalize_secret(&value));
+    if let Some(key) = db_key {
+        return Ok((Some(key), Some("database".to_string())));
     }
 
-    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-    {
-        Err(ApiError::BadRequest(
-            "directory picker is unavailable on this OS in this build; enter the path manually"
-                .into(),
-        ))
+    let env_key = std::env::var("RUSTFIN_TMDB_KEY")
+        .ok()
+        .and_then(|value| normalize_secret(&value));
+    if let Some(key) = env_key {
+        return Ok((Some(key), Some("environment".to_string())));
     }
+
+    Ok((None, None))
 }
 
-#[cfg(target_os = "linux")]
-fn open_directory_picker_linux() -> Result<String, ApiError> {
-    let mut last_err: Option<ApiError> = None;
+async fn get_tmdb_config(
+    _auth: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<TmdbConfigResponse>, AppError> {
+    let (key, source) = resolve_tmdb_key_for_admin(&state).await?;
+    Ok(Json(TmdbConfigResponse {
+        configured: key.is_some(),
+        key_preview: key.as_deref().map(secret_preview),
+        source,
+    }))
+}
 
-    if command_exists("zenity") {
-        match run_linux_folder_picker(
-            "zenity",
-            &[
-                "--file-selection",
-                "--directory",
-                "--title=Select a media directory for Rustyfin",
-            ],
-        ) {
-            Ok(path) => return Ok(path),
-            Err(err) => last_err = Some(err),
+async fn update_tmdb_config(
+    auth: AdminUser,
+    State(state): State<AppState>,
+    Json(body): Json<UpdateTmdbConfigRequest>,
+) -> Result<Json<TmdbConfigResponse>, AppError> {
+    let summary = if let Some(key) = normalize_secret(&body.api_key) {
+        rustfin_db::repo::settings::set(&state.db, "tmdb_api_key", &key)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        "set TMDB API key"
+    } else {
+        let _ = rustfin_db::repo::settings::delete(&state.db, "tmdb_api_key")
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        "cleared TMDB API key"
+    };
+    crate::audit::record(
+        &state.db,
+        &auth.user_id,
+        "system.tmdb_update",
+        Some("tmdb_api_key"),
+        summary,
+    )
+    .await;
+
+    let (key, source) = resolve_tmdb_key_for_admin(&state).await?;
+    Ok(Json(TmdbConfigResponse {
+        configured: key.is_some(),
+        key_preview: key.as_deref().map(secret_preview),
+        source,
+    }))
+}
+
+#[derive(Serialize)]
+struct MetadataConfigResponse {
+    tmdb_configured: bool,
+    /// `database` or `environment`; the key itself is never returned.
+    tmdb_key_source: Option<String>,
+    opensubtitles_configured: bool,
+    opensubtitles_key_source: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MetadataConfigPatchRequest {
+    /// Checked against TMDB before it is saved. An empty string clears the stored key.
+    tmdb_api_key: Option<String>,
+    /// Used for subtitle search/download. An empty string clears the stored key.
+    opensubtitles_api_key: Option<String>,
+}
+
+async fn load_metadata_config(state: &AppState) -> Result<MetadataConfigResponse, AppError> {
+    let (key, source) = resolve_tmdb_key_for_admin(state).await?;
+    let opensubtitles = crate::opensubtitles::resolve_api_key(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(MetadataConfigResponse {
+        tmdb_configured: key.is_some(),
+        tmdb_key_source: source,
+        opensubtitles_configured: opensubtitles.is_some(),
+        opensubtitles_key_source: opensubtitles.map(|(_, source)| source.to_string()),
+    })
+}
+
+async fn get_metadata_config(
+    _auth: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<MetadataConfigResponse>, AppError> {
+    Ok(Json(load_metadata_config(&state).await?))
+}
+
+async fn update_metadata_config(
+    auth: AdminUser,
+    State(state): State<AppState>,
+    Json(body): Json<MetadataConfigPatchRequest>,
+) -> Result<Json<MetadataConfigResponse>, AppError> {
+    if let Some(api_key) = body.opensubtitles_api_key {
+        let setting = crate::opensubtitles::API_KEY_SETTING;
+        let summary = if let Some(key) = normalize_secret(&api_key) {
+            rustfin_db::repo::settings::set(&state.db, setting, &key)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+            "set OpenSubtitles API key"
+        } else {
+            rustfin_db::repo::settings::delete(&state.db, setting)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+            "cleared OpenSubtitles API key"
+        };
+        crate::audit::record(
+            &state.db,
+            &auth.user_id,
+            "system.metadata_config_update",
+            Some(setting),
+            summary,
+        )
+        .await;
+    }
+
+    let Some(api_key) = body.tmdb_api_key else {
+        return Ok(Json(load_metadata_config(&state).await?));
+    };
+
+    let summary = if let Some(key) = normalize_secret(&api_key) {
+        let client = crate::artwork::tmdb_client_with_key(&state.db, key.clone())
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        match client.validate_key().await {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(ApiError::validation(json!({
+                    "tmdb_api_key": ["TMDB rejected this API key"]
+                }))
+                .into());
+            }
+            Err(e) => {
+                return Err(ApiError::ServiceUnavailable(format!(
+                    "could not reach TMDB to validate the key: {e}"
+                ))
+                .into());
+            }
         }
+        rustfin_db::repo::settings::set(&state.db, "tmdb_api_key", &key)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        "set TMDB API key"
+    } else {
+        rustfin_db::repo::settings::delete(&state.db, "tmdb_api_key")
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        "cleared TMDB API key"
+    };
+    crate::audit::record(
+        &state.db,
+        &auth.user_id,
+        "system.metadata_config_update",
+        Some("tmdb_api_key"),
+        summary,
+    )
+    .await;
+
+    Ok(Json(load_metadata_config(&state).await?))
+}
+
+// ---------------------------------------------------------------------------
+// Server configuration (post-setup)
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct SystemConfigResponse {
+    server_name: String,
+    default_ui_locale: String,
+    default_region: String,
+    default_time_zone: Option<String>,
+    metadata_language: String,
+    metadata_region: String,
+    image_cache_max_age_secs: u32,
+    media_cacheable: bool,
+    direct_play_enabled: bool,
+    direct_play_admin_override: bool,
+    allow_remote_access: bool,
+    remote_access_admin_override: bool,
+    provider_id_conflicts: String,
+    scan_on_startup: String,
+    library_symlinks: String,
+    max_streams_per_user: u32,
+    max_streams_per_admin: u32,
+    password_min_length: u32,
+    password_require_mixed: bool,
+    password_block_common: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SystemConfigPatchRequest {
+    server_name: Option<String>,
+    default_ui_locale: Option<String>,
+    default_region: Option<String>,
+    /// An empty string clears the time zone.
+    default_time_zone: Option<String>,
+    metadata_language: Option<String>,
+    metadata_region: Option<String>,
+    /// `0` tells clients to revalidate images on every use.
+    image_cache_max_age_secs: Option<u32>,
+    media_cacheable: Option<bool>,
+    /// `false` forces every client onto transcoding sessions.
+    direct_play_enabled: Option<bool>,
+    /// Lets admins keep direct streaming while `direct_play_enabled` is off.
+    direct_play_admin_override: Option<bool>,
+    /// `false` keeps streaming to clients on the local network.
+    allow_remote_access: Option<bool>,
+    /// Lets admins stream remotely while `allow_remote_access` is off.
+    remote_access_admin_override: Option<bool>,
+    /// `allow`, `warn` or `reject` an item's provider id held by another item.
+    provider_id_conflicts: Option<String>,
+    /// `off`, `all` or `flagged` libraries scanned when the server boots.
+    scan_on_startup: Option<String>,
+    /// `resolve` or `follow` symlinks that lead out of a library when streaming.
+    library_symlinks: Option<String>,
+    /// Streams one non-admin account may have open at once; `0` means no limit.
+    max_streams_per_user: Option<u32>,
+    /// The same for admins, who have no limit by default.
+    max_streams_per_admin: Option<u32>,
+    /// Applies to accounts created or set up from now on.
+    password_min_length: Option<u32>,
+    /// Require three of lowercase, uppercase, digits and symbols.
+    password_require_mixed: Option<bool>,
+    /// Refuse well-known passwords.
+    password_block_common: Option<bool>,
+}
+
+async fn setting_or(state: &AppState, key: &str, default: &str) -> Result<String, AppError> {
+    Ok(rustfin_db::repo::settings::get(&state.db, key)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .unwrap_or_else(|| default.to_string()))
+}
+
+async fn load_system_config(state: &AppState) -> Result<SystemConfigResponse, AppError> {
+    let time_zone = setting_or(state, "default_time_zone", "").await?;
+    let password_policy = crate::password_policy::PasswordPolicy::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(SystemConfigResponse {
+        server_name: setting_or(state, "server_name", "Rustyfin").await?,
+        default_ui_locale: setting_or(state, "default_ui_locale", "en").await?,
+        default_region: setting_or(state, "default_region", "US").await?,
+        default_time_zone: Some(time_zone).filter(|tz| !tz.is_empty()),
+        metadata_language: setting_or(state, "metadata_language", "en").await?,
+        metadata_region: setting_or(state, "metadata_region", "US").await?,
+        image_cache_max_age_secs: crate::cache_policy::image_cache_max_age(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        media_cacheable: crate::cache_policy::media_cacheable(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        direct_play_enabled: crate::playback_policy::direct_play_enabled(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        direct_play_admin_override: crate::playback_policy::direct_play_admin_override(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        allow_remote_access: crate::playback_policy::allow_remote_access(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        remote_access_admin_override: crate::playback_policy::remote_access_admin_override(
+            &state.db,
+        )
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        provider_id_conflicts: ProviderIdConflictPolicy::load(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .as_str()
+            .to_string(),
+        scan_on_startup: crate::startup_scan::ScanOnStartup::load(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .as_str()
+            .to_string(),
+        library_symlinks: crate::path_policy::LibrarySymlinks::load(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .as_str()
+            .to_string(),
+        max_streams_per_user: crate::playback_policy::max_streams_per_user(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        max_streams_per_admin: crate::playback_policy::max_streams_per_admin(&state.db)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?,
+        password_min_length: password_policy.min_length,
+        password_require_mixed: password_policy.require_mixed,
+        password_block_common: password_policy.block_common,
+    })
+}
+
+fn bool_setting(value: bool) -> &'static str {
+    if value { "true" } else { "false" }
+}
+
+/// Before setup completes, these values belong to the setup wizard.
+async fn ensure_setup_completed(state: &AppState) -> Result<(), AppError> {
+    if setting_or(state, "setup_completed", "false").await? != "true" {
+        return Err(ApiError::Conflict("setup is not complete".into()).into());
     }
+    Ok(())
+}
 
-    if command_exists("kdialog") {
-        match run_linux_folder_picker(
-            "kdialog",
-            &[
-                "--getexistingdirectory",
-                ".",
-                "Select a media directory for Rustyfin",
-            ],
-        ) {
-            Ok(path) => return Ok(path),
-            Err(err) => last_err = Some(err),
+async fn get_system_config(
+    _auth: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<SystemConfigResponse>, AppError> {
+    ensure_setup_completed(&state).await?;
+    Ok(Json(load_system_config(&state).await?))
+}
+
+async fn update_system_config(
+    auth: AdminUser,
+    State(state): State<AppState>,
+    Json(body): Json<SystemConfigPatchRequest>,
+) -> Result<Json<SystemConfigResponse>, AppError> {
+    ensure_setup_completed(&state).await?;
+    let current = load_system_config(&state).await?;
+    let changed: Vec<&str> = [
+        ("server_name", body.server_name.is_some()),
+        ("default_ui_locale", body.default_ui_locale.is_some()),
+        ("default_region", body.default_region.is_some()),
+        ("default_time_zone", body.default_time_zone.is_some()),
+        ("metadata_language", body.metadata_language.is_some()),
+        ("metadata_region", body.metadata_region.is_some()),
+        (
+            "image_cache_max_age_secs",
+            body.image_cache_max_age_secs.is_some(),
+        ),
+        ("media_cacheable", body.media_cacheable.is_some()),
+        ("direct_play_enabled", body.direct_play_enabled.is_some()),
+        (
+            "direct_play_admin_override",
+            body.direct_play_admin_override.is_some(),
+        ),
+        ("allow_remote_access", body.allow_remote_access.is_some()),
+        (
+            "remote_access_admin_override",
+            body.remote_access_admin_override.is_some(),
+        ),
+        (
+            "provider_id_conflicts",
+            body.provider_id_conflicts.is_some(),
+        ),
+        ("scan_on_startup", body.scan_on_startup.is_some()),
+        ("library_symlinks", body.library_symlinks.is_some()),
+        ("max_streams_per_user", body.max_streams_per_user.is_some()),
+        (
+            "max_streams_per_admin",
+            body.max_streams_per_admin.is_some(),
+        ),
+        ("password_min_length", body.password_min_length.is_some()),
+        (
+            "password_require_mixed",
+            body.password_require_mixed.is_some(),
+        ),
+        (
+            "password_block_common",
+            body.password_block_common.is_some(),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(field, set)| set.then_some(field))
+    .collect();
+
+    let merged = SystemConfigResponse {
+        server_name: body
+            .server_name
+            .map(|name| name.trim().to_string())
+            .unwrap_or(current.server_name),
+        default_ui_locale: body.default_ui_locale.unwrap_or(current.default_ui_locale),
+        default_region: body.default_region.unwrap_or(current.default_region),
+        default_time_zone: match body.default_time_zone {
+            Some(tz) => Some(tz).filter(|tz| !tz.is_empty()),
+            None => current.default_time_zone,
+        },
+        metadata_language: body.metadata_language.unwrap_or(current.metadata_language),
+        metadata_region: body.metadata_region.unwrap_or(current.metadata_region),
+        image_cache_max_age_secs: body
+            .image_cache_max_age_secs
+            .unwrap_or(current.image_cache_max_age_secs),
+        media_cacheable: body.media_cacheable.unwrap_or(current.media_cacheable),
+        direct_play_enabled: body
+            .direct_play_enabled
+            .unwrap_or(current.direct_play_enabled),
+        direct_play_admin_override: body
+            .direct_play_admin_override
+            .unwrap_or(current.direct_play_admin_override),
+        allow_remote_access: body
+            .allow_remote_access
+            .unwrap_or(current.allow_remote_access),
+        remote_access_admin_override: body
+            .remote_access_admin_override
+            .unwrap_or(current.remote_access_admin_override),
+        provider_id_conflicts: body
+            .provider_id_conflicts
+            .map(|p| p.trim().to_string())
+            .unwrap_or(current.provider_id_conflicts),
+        scan_on_startup: body
+            .scan_on_startup
+            .map(|p| p.trim().to_string())
+            .unwrap_or(current.scan_on_startup),
+        library_symlinks: body
+            .library_symlinks
+            .map(|p| p.trim().to_string())
+            .unwrap_or(current.library_symlinks),
+        max_streams_per_user: body
+            .max_streams_per_user
+            .unwrap_or(current.max_streams_per_user),
+        max_streams_per_admin: body
+            .max_streams_per_admin
+            .unwrap_or(current.max_streams_per_admin),
+        password_min_length: body
+            .password_min_length
+            .unwrap_or(current.password_min_length),
+        password_require_mixed: body
+            .password_require_mixed
+            .unwrap_or(current.password_require_mixed),
+        password_block_common: body
+            .password_block_common
+            .unwrap_or(current.password_block_common),
+    };
+
+    let mut errors = serde_json::Map::new();
+    for fields in [
+        crate::setup::validation::validate_config(
+            &merged.server_name,
+            &merged.default_ui_locale,
+            &merged.default_region,
+            &merged.default_time_zone,
+        ),
+        crate::setup::validation::validate_metadata(
+            &merged.metadata_language,
+            &merged.metadata_region,
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if let serde_json::Value::Object(fields) = fields {
+            errors.extend(fields);
         }
     }
-
-    if std::path::Path::new("/media").exists() {
-        return Ok("/media".into());
+    if merged.image_cache_max_age_secs > crate::cache_policy::MAX_IMAGE_CACHE_MAX_AGE_SECS {
+        errors.insert(
+            "image_cache_max_age_secs".to_string(),
+            json!([format!(
+                "must be at most {} seconds",
+                crate::cache_policy::MAX_IMAGE_CACHE_MAX_AGE_SECS
+            )]),
+        );
+    }
+    if ProviderIdConflictPolicy::parse(&merged.provider_id_conflicts).is_none() {
+        let allowed: Vec<&str> = ProviderIdConflictPolicy::ALL
+            .iter()
+            .map(|p| p.as_str())
+            .collect();
+        errors.insert(
+            "provider_id_conflicts".to_string(),
+            json!([format!("must be one of: {}", allowed.join(", "))]),
+        );
+    }
+    if crate::startup_scan::ScanOnStartup::parse(&merged.scan_on_startup).is_none() {
+        let allowed: Vec<&str> = crate::startup_scan::ScanOnStartup::ALL
+            .iter()
+            .map(|p| p.as_str())
+            .collect();
+        errors.insert(
+            "scan_on_startup".to_string(),
+            json!([format!("must be one of: {}", allowed.join(", "))]),
+        );
+    }
+    if crate::path_policy::LibrarySymlinks::parse(&merged.library_symlinks).is_none() {
+        let allowed: Vec<&str> = crate::path_policy::LibrarySymlinks::ALL
+            .iter()
+            .map(|p| p.as_str())
+            .collect();
+        errors.insert(
+            "library_symlinks".to_string(),
+            json!([format!("must be one of: {}", allowed.join(", "))]),
+        );
+    }
+    if !crate::password_policy::MIN_LENGTH_RANGE.contains(&merged.password_min_length) {
+        errors.insert(
+            "password_min_length".to_string(),
+            json!([format!(
+                "must be between {} and {}",
+                crate::password_policy::MIN_LENGTH_RANGE.start(),
+                crate::password_policy::MIN_LENGTH_RANGE.end()
+            )]),
+        );
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::validation(serde_json::Value::Object(errors)).into());
     }
 
-    if let Some(err) = last_err {
-        return Err(err);
+    let image_cache_max_age = merged.image_cache_max_age_secs.to_string();
+    let max_streams_per_user = merged.max_streams_per_user.to_string();
+    let max_streams_per_admin = merged.max_streams_per_admin.to_string();
+    let password_min_length = merged.password_min_length.to_string();
+    for (key, value) in [
+        ("server_name", merged.server_name.as_str()),
+        ("default_ui_locale", merged.default_ui_locale.as_str()),
+        ("default_region", merged.default_region.as_str()),
+        (
+            "default_time_zone",
+            merged.default_time_zone.as_deref().unwrap_or(""),
+        ),
+        ("metadata_language", merged.metadata_language.as_str()),
+        ("metadata_region", merged.metadata_region.as_str()),
+        (
+            crate::cache_policy::IMAGE_CACHE_MAX_AGE_KEY,
+            image_cache_max_age.as_str(),
+        ),
+        (
+            crate::cache_policy::MEDIA_CACHEABLE_KEY,
+            bool_setting(merged.media_cacheable),
+        ),
+        (
+            crate::playback_policy::DIRECT_PLAY_ENABLED_KEY,
+            bool_setting(merged.direct_play_enabled),
+        ),
+        (
+            crate::playback_policy::DIRECT_PLAY_ADMIN_OVERRIDE_KEY,
+            bool_setting(merged.direct_play_admin_override),
+        ),
+        (
+            crate::playback_policy::ALLOW_REMOTE_ACCESS_KEY,
+            bool_setting(merged.allow_remote_access),
+        ),
+        (
+            crate::playback_policy::REMOTE_ACCESS_ADMIN_OVERRIDE_KEY,
+            bool_setting(merged.remote_access_admin_override),
+        ),
+        (
+            crate::provider_policy::PROVIDER_ID_CONFLICTS_KEY,
+            merged.provider_id_conflicts.as_str(),
+        ),
+        (
+            crate::startup_scan::SCAN_ON_STARTUP_KEY,
+            merged.scan_on_startup.as_str(),
+        ),
+        (
+            crate::path_policy::LIBRARY_SYMLINKS_KEY,
+            merged.library_symlinks.as_str(),
+        ),
+        (
+            crate::playback_policy::MAX_STREAMS_PER_USER_KEY,
+            max_streams_per_user.as_str(),
+        ),
+        (
+            crate::playback_policy::MAX_STREAMS_PER_ADMIN_KEY,
+            max_streams_per_admin.as_str(),
+        ),
+        (
+            crate::password_policy::PASSWORD_MIN_LENGTH_KEY,
+            password_min_length.as_str(),
+        ),
+        (
+            crate::password_policy::PASSWORD_REQUIRE_MIXED_KEY,
+            bool_setting(merged.password_require_mixed),
+        ),
+        (
+            crate::password_policy::PASSWORD_BLOCK_COMMON_KEY,
+            bool_setting(merged.password_block_common),
+        ),
+    ] {
+        rustfin_db::repo::settings::set(&state.db, key, value)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
     }
+    crate::audit::record(
+        &state.db,
+        &auth.user_id,
+        "system.config_update",
+        None,
+        &format!("updated server config: {}", changed.join(", ")),
+    )
+    .await;
 
-    Err(ApiError::BadRequest(
-        "directory picker is unavailable: install zenity or kdialog, or enter the path manually"
-            .into(),
-    ))
+    Ok(Json(merged))
 }
 
-#[cfg(target_os = "linux")]
-fn command_exists(cmd: &str) -> bool {
-    std::process::Command::new(cmd)
-        .arg("--version")
-        .output()
-        .is_ok()
+// ---------------------------------------------------------------------------
+// Audit log
+// ---------------------------------------------------------------------------
+
+const AUDIT_DEFAULT_LIMIT: i64 = 50;
+const AUDIT_MAX_LIMIT: i64 = 200;
+
+#[derive(Deserialize)]
+struct AuditQuery {
+    actor: Option<String>,
+    action: Option<String>,
+    target: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
 }
 
-#[cfg(target_os = "linux")]
-fn run_linux_folder_picker(cmd: &str, args: &[&str]) -> Result<String, ApiError> {
-    let output = std::process::Command::new(cmd)
-        .args(args)
-        .output()
-        .map_err(|e| ApiError::Internal(format!("failed to launch {cmd}: {e}")))?;
+#[derive(Serialize)]
+struct AuditEntryResponse {
+    id: String,
+    actor_user_id: Option<String>,
+    action: String,
+    target: Option<String>,
+    summary: String,
+    ts: i64,
+}
 
-    if output.status.success() {
-        let path = String::from_utf8(output.stdout)
-            .map_err(|e| ApiError::Internal(format!("{cmd} returned invalid UTF-8: {e}")))?;
-        let path = path.trim().to_string();
-        if path.is_empty() {
-            return Err(ApiError::BadRequest("no directory selected".into()));
-        }
-        return Ok(path);
-    }
+#[derive(Serialize)]
+struct AuditPageResponse {
+    entries: Vec<AuditEntryResponse>,
+    total: i64,
+    limit: i64,
+    offset: i64,
+}
 
-    if output.status.code() == Some(1) {
-        return Err(ApiError::BadRequest("directory selection cancelled".into()));
-    }
+async fn list_audit_log(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Json<AuditPageResponse>, AppError> {
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_DEFAULT_LIMIT)
+        .clamp(1, AUDIT_MAX_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let filter = rustfin_db::repo::audit::AuditFilter {
+        actor_user_id: query.actor.as_deref().filter(|s| !s.is_empty()),
+        action: query.action.as_deref().filter(|s| !s.is_empty()),
+        target: query.target.as_deref().filter(|s| !s.is_empty()),
+    };
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let detail = stderr.trim();
-    if detail.is_empty() {
-        return Err(ApiError::Internal(format!(
-            "{cmd} folder picker failed with unknown error"
-        )));
+    let (entries, total) =
+        rustfin_db::repo::audit::list_audit_page(&state.db, &filter, limit, offset)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    Ok(Json(AuditPageResponse {
+        entries: entries
+            .into_iter()
+            .map(|e| AuditEntryResponse {
+                id: e.id,
+                actor_user_id: e.actor_user_id,
+                action: e.action,
+                target: e.target,
+                summary: e.summary,
+                ts: e.ts,
+            })
+            .collect(),
+        total,
+        limit,
+        offset,
+    }))
+}
+
+// ---------------------------------------------------------------------------
+// Database maintenance
+// ---------------------------------------------------------------------------
+
+/// Held while the database is being optimized, so two runs never overlap.
+static OPTIMIZE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+#[derive(Serialize)]
+struct OptimizeDatabaseResponse {
+    size_before_bytes: i64,
+    size_after_bytes: i64,
+    duration_ms: u64,
+}
+
+/// Compact the database and refresh its statistics. Refused while background jobs
+/// are queued or running, since `VACUUM` would hold up their writes.
+async fn optimize_database(
+    admin: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<OptimizeDatabaseResponse>, AppError> {
+    let Ok(_running) = OPTIMIZE_LOCK.try_lock() else {
+        return Err(ApiError::Conflict("database optimization is already running".into()).into());
+    };
+    let active = rustfin_db::repo::jobs::count_active_jobs(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if active > 0 {
+        return Err(ApiError::Conflict(format!(
+            "{active} background job(s) are queued or running; try again once they finish"
+        ))
+        .into());
     }
-    Err(ApiError::Internal(format!(
-        "{cmd} folder picker failed: {detail}"
-    )))
+
+    let started = std::time::Instant::now();
+    let size_before_bytes = rustfin_db::repo::maintenance::database_size(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    rustfin_db::repo::maintenance::optimize(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let size_after_bytes = rustfin_db::repo::maintenance::database_size(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let duration_ms = started.elapsed().as_millis() as u64;
+
+    tracing::info!(
+        size_before_bytes,
+        size_after_bytes,
+        duration_ms,
+        "database optimized"
+    );
+    crate::audit::record(
+        &state.db,
+        &admin.user_id,
+        "system.optimize",
+        None,
+        &format!("optimized database: {size_before_bytes} -> {size_after_bytes} bytes"),
+    )
+    .await;
+
+    Ok(Json(OptimizeDatabaseResponse {
+        size_before_bytes,
+        size_after_bytes,
+        duration_ms,
+    }))
 }
 
-#[cfg(target_os = "windows")]
-fn open_directory_picker_windows() -> Result<String, ApiError> {
-    let script = r#"
-Add-Type -AssemblyName System.Windows.Forms
-$dialog = New-Object System.Windows.Forms.FolderBrowserDialog
-$dialog.Description = 'Select a media directory for Rustyfin'
-$result = $dialog.ShowDialog()
-if ($result -eq [System.Windows.Forms.DialogResult]::OK) {
-  Write-Output $dialog.SelectedPath
+// ---------------------------------------------------------------------------
+// Duplicate detection
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize)]
+struct DuplicateItemResponse {
+    item_id: String,
+    library_id: String,
+    title: String,
+    year: Option<i64>,
+    tmdb_id: Option<String>,
+    file_ids: Vec<String>,
 }
-"#;
 
-    let output = std::process::Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", script])
-        .output()
-        .map_err(|e| {
-            ApiError::Internal(format!("failed to launch PowerShell folder picker: {e}"))
-        })?;
+#[derive(Serialize)]
+struct DuplicateGroupResponse {
+    kind: String,
+    key: String,
+    items: Vec<DuplicateItemResponse>,
+}
 
-    if output.status.success() {
-        let path = String::from_utf8(output.stdout).map_err(|e| {
-            ApiError::Internal(format!(
-                "PowerShell folder picker returned invalid UTF-8: {e}"
-            ))
-        })?;
-        let path = path.trim().to_string();
-        if path.is_empty() {
-            return Err(ApiError::BadRequest("directory selection cancelled".into()));
+async fn list_duplicates(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<DuplicateGroupResponse>>, AppError> {
+    let groups = rustfin_db::repo::duplicates::find_duplicate_groups(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    let mut result = Vec::with_capacity(groups.len());
+    for group in groups {
+        let mut items = Vec::with_capacity(group.items.len());
+        for member in group.items {
+            let file_ids = rustfin_db::repo::items::get_item_file_ids(&state.db, &member.item_id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+            items.push(DuplicateItemResponse {
+                item_id: member.item_id,
+                library_id: member.library_id,
+                title: member.title,
+                year: member.year,
+                tmdb_id: member.tmdb_id,
+                file_ids,
+            });
         }
-        return Ok(path);
+        result.push(DuplicateGroupResponse {
+            kind: group.kind,
+            key: group.key,
+            items,
+        });
     }
 
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let detail = stderr.trim();
-    if detail.is_empty() {
-        return Err(ApiError::Internal(
-            "PowerShell folder picker failed with unknown error".into(),
-        ));
-    }
-    Err(ApiError::Internal(format!(
-        "PowerShell folder picker failed: {detail}"
-    )))
+    Ok(Json(result))
 }
 
-async fn get_gpu_caps(_auth: AdminUser) -> Result<Json<serde_json::Value>, AppError> {
-    let caps = rustfin_transcoder::gpu::detect(std::path::Path::new("ffmpeg")).await;
-    Ok(Json(serde_json::to_value(&caps).unwrap()))
+#[derive(Serialize)]
+struct ProviderIdConflictItemResponse {
+    item_id: String,
+    title: String,
+    year: Option<i64>,
 }
 
 #[derive(Serialize)]
-struct TmdbConfigResponse {
-    configured: bool,
-    key_preview: Option<String>,
-    source: Option<String>,
+struct ProviderIdConflictResponse {
+    library_id: String,
+    kind: String,
+    provider: String,
+    provider_id: String,
+    items: Vec<ProviderIdConflictItemResponse>,
+}
+
+/// Provider ids held by more than one item of the same kind within a library.
+async fn list_provider_id_conflicts(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ProviderIdConflictResponse>>, AppError> {
+    let conflicts = rustfin_db::repo::duplicates::find_provider_id_conflicts(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(Json(
+        conflicts
+            .into_iter()
+            .map(|c| ProviderIdConflictResponse {
+                library_id: c.library_id,
+                kind: c.kind,
+                provider: c.provider,
+                provider_id: c.provider_id,
+                items: c
+                    .items
+                    .into_iter()
+                    .map(|m| ProviderIdConflictItemResponse {
+                        item_id: m.item_id,
+                        title: m.title,
+                        year: m.year,
+                    })
+                    .collect(),
+            })
+            .collect(),
+    ))
 }
 
 #[derive(Deserialize)]
-struct UpdateTmdbConfigRequest {
-    api_key: String,
+struct ParsePreviewRequest {
+    path: String,
+    /// Library kind to parse as: `movies`, `tv_shows` or `mixed`.
+    kind: String,
 }
 
-fn normalize_secret(input: &str) -> Option<String> {
-    let trimmed = input.trim();
-    if trimmed.is_empty() {
-        None
-    } else {
-        Some(trimmed.to_string())
-    }
+#[derive(Serialize)]
+struct ParsePreviewSubtitle {
+    path: String,
+    format: String,
+    language: Option<String>,
+    forced: bool,
+    sdh: bool,
+    title: Option<String>,
 }
 
-fn secret_preview(secret: &str) -> String {
-    let len = secret.chars().count();
-    let suffix: String = secret
-        .chars()
-        .rev()
-        .take(4)
-        .collect::<Vec<_>>()
-        .into_iter()
-        .rev()
-        .collect();
-    if len <= 4 {
-        "****".to_string()
-    } else {
-        format!("****{suffix}")
-    }
+#[derive(Serialize)]
+struct ParsePreviewResponse {
+    /// `movie`, `episode`, or `unknown` when the scanner would skip the file.
+    kind: &'static str,
+    /// The path the parser saw, relative to the library root containing it.
+    relative_path: String,
+    /// Movie title, or the series title for an episode.
+    title: Option<String>,
+    year: Option<u16>,
+    season: Option<u32>,
+    episode: Option<u32>,
+    episode_title: Option<String>,
+    /// `[provider=id]` tags found anywhere in the path.
+    provider_ids: std::collections::BTreeMap<String, String>,
+    resolution: Option<String>,
+    source: Option<String>,
+    subtitles: Vec<ParsePreviewSubtitle>,
 }
 
-async fn resolve_tmdb_key_for_admin(
-    state: &AppState,
-) -> Result<(Option<String>, Option<String>), AppError> {
-    let db_key = rustfin_db::repo::settings::get(&state.db, "tmdb_api_key")
+/// How a scan would interpret a file path, without touching the library. Paths
+/// under a library root are parsed relative to it, as the scanner does, and only
+/// those get their sidecar subtitles listed.
+async fn preview_path_parse(
+    _admin: AdminUser,
+    State(state): State<AppState>,
+    Json(req): Json<ParsePreviewRequest>,
+) -> Result<Json<ParsePreviewResponse>, AppError> {
+    use rustfin_scanner::parser::{self, ParsedMedia};
+
+    let kind = LibraryKind::parse(&req.kind)
+        .filter(|k| *k != LibraryKind::Music)
+        .ok_or_else(|| {
+            ApiError::BadRequest("kind must be one of: movies, tv_shows, mixed".into())
+        })?;
+    if req.path.trim().is_empty() {
+        return Err(ApiError::BadRequest("path is required".into()).into());
+    }
+
+    let path = std::path::Path::new(&req.path);
+    let roots = rustfin_db::repo::libraries::get_all_library_paths(&state.db)
         .await
-        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
-        .and_then(|value| normalize_secret(&value));
-    if let Some(key) = db_key {
-        return Ok((Some(key), Some("database".to_string())));
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let rel: std::path::PathBuf = roots
+        .iter()
+        .filter_map(|root| path.strip_prefix(root).ok())
+        .min_by_key(|rel| rel.components().count())
+        .unwrap_or(path)
+        .components()
+        .filter(|c| matches!(c, std::path::Component::Normal(_)))
+        .collect();
+    let parsed = rustfin_scanner::scan::parse_library_path(kind.as_str(), &rel)
+        .ok_or_else(|| ApiError::BadRequest(format!("{kind} libraries are not scanned")))?;
+
+    let mut provider_ids = std::collections::BTreeMap::new();
+    for component in rel.iter() {
+        for (provider, id) in parser::extract_provider_ids(&component.to_string_lossy()) {
+            provider_ids.entry(provider).or_insert(id);
+        }
     }
+    let file_name = rel.file_name().unwrap_or_default().to_string_lossy();
+    let quality = parser::parse_quality(&file_name);
+    // Sidecars are only listed for files inside a library, never an arbitrary dir.
+    let policy = crate::path_policy::LibrarySymlinks::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let media = path.to_path_buf();
+    let sidecars = tokio::task::spawn_blocking(move || {
+        let in_library = roots.iter().any(|root| {
+            crate::path_policy::is_within_root(&media, std::path::Path::new(root), policy)
+        });
+        if in_library {
+            rustfin_scanner::subtitles::discover_sidecars(&media)
+        } else {
+            Vec::new()
+        }
+    })
+    .await
+    .map_err(|e| ApiError::Internal(format!("sidecar lookup task failed: {e}")))?;
+    let subtitles = sidecars
+        .into_iter()
+        .map(|sub| ParsePreviewSubtitle {
+            path: sub.path.to_string_lossy().into_owned(),
+            format: format!("{:?}", sub.format).to_lowercase(),
+            language: sub.language,
+            forced: sub.forced,
+            sdh: sub.sdh,
+            title: sub.title,
+        })
+        .collect();
 
-    let env_key = std::env::var("RUSTFIN_TMDB_KEY")
-        .ok()
-        .and_then(|value| normalize_secret(&value));
-    if let Some(key) = env_key {
-        return Ok((Some(key), Some("environment".to_string())));
+    let mut response = ParsePreviewResponse {
+        kind: "unknown",
+        relative_path: rel.to_string_lossy().into_owned(),
+        title: None,
+        year: None,
+        season: None,
+        episode: None,
+        episode_title: None,
+        provider_ids,
+        resolution: quality.resolution,
+        source: quality.source,
+        subtitles,
+    };
+    match parsed {
+        ParsedMedia::Movie(movie) => {
+            response.kind = "movie";
+            response.title = Some(movie.title);
+            response.year = movie.year;
+        }
+        ParsedMedia::Episode(ep) => {
+            response.kind = "episode";
+            response.title = Some(ep.series_title);
+            response.year = ep.series_year;
+            response.season = Some(ep.season);
+            response.episode = Some(ep.episode);
+            response.episode_title = ep.episode_title;
+        }
+        ParsedMedia::Unknown(_) => {}
     }
-
-    Ok((None, None))
+    Ok(Json(response))
 }
 
-async fn get_tmdb_config(
-    _auth: AdminUser,
-    State(state): State<AppState>,
-) -> Result<Json<TmdbConfigResponse>, AppError> {
-    let (key, source) = resolve_tmdb_key_for_admin(&state).await?;
-    Ok(Json(TmdbConfigResponse {
-        configured: key.is_some(),
-        key_preview: key.as_deref().map(secret_preview),
-        source,
-    }))
+#[derive(Serialize)]
+struct MergeItemResponse {
+    item_id: String,
+    merged_item_id: String,
+    file_ids: Vec<String>,
 }
 
-async fn update_tmdb_config(
-    _auth: AdminUser,
+async fn merge_item_into(
+    _admin: AdminUser,
     State(state): State<AppState>,
-    Json(body): Json<UpdateTmdbConfigRequest>,
-) -> Result<Json<TmdbConfigResponse>, AppError> {
-    if let Some(key) = normalize_secret(&body.api_key) {
-        rustfin_db::repo::settings::set(&state.db, "tmdb_api_key", &key)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
-    } else {
-        let _ = rustfin_db::repo::settings::delete(&state.db, "tmdb_api_key")
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Path((id, target_id)): Path<(String, String)>,
+) -> Result<Json<MergeItemResponse>, AppError> {
+    if id == target_id {
+        return Err(ApiError::BadRequest("cannot merge an item into itself".into()).into());
     }
 
-    let (key, source) = resolve_tmdb_key_for_admin(&state).await?;
-    Ok(Json(TmdbConfigResponse {
-        configured: key.is_some(),
-        key_preview: key.as_deref().map(secret_preview),
-        source,
+    let source = rustfin_db::repo::items::get_item(&state.db, &id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    let target = rustfin_db::repo::items::get_item(&state.db, &target_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("target item not found".into()))?;
+
+    if source.kind != target.kind {
+        return Err(ApiError::BadRequest(format!(
+            "cannot merge a {} into a {}",
+            source.kind, target.kind
+        ))
+        .into());
+    }
+    if !matches!(source.kind.as_str(), "movie" | "episode" | "series") {
+        return Err(ApiError::BadRequest(
+            "only movie, series and episode items can be merged".into(),
+        )
+        .into());
+    }
+
+    let merged = rustfin_db::repo::duplicates::merge_items(&state.db, &source.id, &target.id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if !merged {
+        return Err(ApiError::NotFound("item not found".into()).into());
+    }
+
+    let file_ids = rustfin_db::repo::items::get_item_file_ids(&state.db, &target.id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+
+    Ok(Json(MergeItemResponse {
+        item_id: target.id,
+        merged_item_id: source.id,
+        file_ids,
     }))
 }
 
@@ -2390,17 +6828,65 @@ async fn refresh_item_metadata(
     ensure_library_access(&auth, &state, &_item.library_id).await?;
 
     // If provider_id given, store it
+    let mut conflicts = Vec::new();
     if let (Some(provider), Some(pid)) = (&body.provider, &body.provider_id) {
-        rustfin_metadata::merge::set_provider_id(&state.db, &item_id, provider, pid)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        let assignment =
+            crate::provider_policy::assign_provider_id(&state.db, &item_id, provider, pid)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        if !assignment.stored {
+            return Err(ApiError::Conflict(format!(
+                "{provider} id {pid} is already used by item(s) {} in this library",
+                assignment.conflicts.join(", ")
+            ))
+            .into());
+        }
+        conflicts = assignment.conflicts;
     }
 
-    Ok(Json(serde_json::json!({
+    let mut response = serde_json::json!({
         "status": "metadata refresh queued",
         "item_id": item_id,
         "note": "TMDB API key required for provider fetch. Configure in Admin or set RUSTFIN_TMDB_KEY."
-    })))
+    });
+    if !conflicts.is_empty() {
+        response["provider_id_conflicts"] = serde_json::json!(conflicts);
+    }
+    Ok(Json(response))
+}
+
+/// Re-pick an item's artwork from local files and its provider match, leaving its
+/// text metadata as it is.
+async fn refresh_item_images(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
+) -> Result<Json<ItemResponse>, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    if rustfin_db::repo::items::is_item_locked(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+    {
+        return Err(ApiError::Conflict("item is locked".into()).into());
+    }
+
+    crate::artwork::refresh_item_images(&state.db, &item)
+        .await
+        .map_err(|e| ApiError::Internal(format!("{e:#}")))?;
+
+    get_item(
+        auth,
+        State(state),
+        Path(item_id),
+        Query(dates),
+        Query(ItemCountsQuery::default()),
+    )
+    .await
 }
 
 async fn get_item_providers(
@@ -2426,6 +6912,299 @@ async fn get_item_providers(
     Ok(Json(serde_json::Value::Object(map)))
 }
 
+#[derive(Deserialize)]
+struct MatchCandidatesQuery {
+    /// Title to search for; defaults to the item's title.
+    query: Option<String>,
+    /// Release year to narrow the search; defaults to the item's year.
+    year: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct MatchCandidatesResponse {
+    item_id: String,
+    provider: String,
+    /// Pick one and POST its `provider_id` to `/items/{id}/metadata/refresh`.
+    candidates: Vec<rustfin_metadata::provider::SearchResult>,
+}
+
+/// Provider search results for manually matching a movie or series.
+async fn get_match_candidates(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    Query(query): Query<MatchCandidatesQuery>,
+) -> Result<Json<MatchCandidatesResponse>, AppError> {
+    use rustfin_metadata::provider::MetadataProvider;
+
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    if item.kind != "movie" && item.kind != "series" {
+        return Err(ApiError::BadRequest(format!(
+            "only movies and series can be matched, not '{}'",
+            item.kind
+        ))
+        .into());
+    }
+
+    let client = crate::artwork::tmdb_client_for_library(&state.db, &item.library_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("{e:#}")))?
+        .ok_or_else(|| ApiError::Conflict("TMDB API key is not configured".into()))?;
+
+    let title = query
+        .query
+        .map(|q| q.trim().to_string())
+        .filter(|q| !q.is_empty())
+        .unwrap_or_else(|| item.title.clone());
+    let year = query.year.or(item.year.map(|y| y as i32));
+    let candidates = if item.kind == "movie" {
+        client.search_movie(&title, year).await
+    } else {
+        client.search_series(&title, year).await
+    }
+    .map_err(|e| ApiError::Internal(format!("provider search failed: {e}")))?;
+    let candidates = candidates
+        .into_iter()
+        .map(|candidate| rustfin_metadata::provider::SearchResult {
+            poster_url: candidate
+                .poster_url
+                .and_then(|url| crate::image_proxy::proxy_url(&url)),
+            ..candidate
+        })
+        .collect();
+
+    Ok(Json(MatchCandidatesResponse {
+        item_id,
+        provider: client.name().to_string(),
+        candidates,
+    }))
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct IdentifyRequest {
+    provider: String,
+    provider_id: String,
+    /// Fetch and merge the provider's metadata and artwork right away.
+    #[serde(default = "default_true")]
+    refresh: bool,
+}
+
+/// Match a movie or series to a known provider id, skipping the search step.
+/// The id is checked against the provider before it is stored.
+async fn identify_item(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+    Query(dates): Query<FormattedDatesQuery>,
+    Json(body): Json<IdentifyRequest>,
+) -> Result<Json<ItemResponse>, AppError> {
+    use rustfin_metadata::provider::MetadataProvider;
+
+    let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(&auth, &state, &item.library_id).await?;
+    if item.kind != "movie" && item.kind != "series" {
+        return Err(ApiError::BadRequest(format!(
+            "only movies and series can be identified, not '{}'",
+            item.kind
+        ))
+        .into());
+    }
+    if body.provider != "tmdb" {
+        return Err(
+            ApiError::BadRequest(format!("unsupported provider '{}'", body.provider)).into(),
+        );
+    }
+    let provider_id = body.provider_id.trim();
+    if provider_id.is_empty() {
+        return Err(ApiError::BadRequest("provider_id must not be empty".into()).into());
+    }
+    if rustfin_db::repo::items::is_item_locked(&state.db, &item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+    {
+        return Err(ApiError::Conflict("item is locked".into()).into());
+    }
+
+    let client = crate::artwork::tmdb_client_for_library(&state.db, &item.library_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("{e:#}")))?
+        .ok_or_else(|| ApiError::Conflict("TMDB API key is not configured".into()))?;
+    let metadata = if item.kind == "movie" {
+        client.get_movie(provider_id).await
+    } else {
+        client.get_series(provider_id).await
+    }
+    .map_err(|e| match e {
+        rustfin_metadata::MetadataError::NotFound => ApiError::BadRequest(format!(
+            "{} id {provider_id} does not match any {} on the provider",
+            body.provider,
+            if item.kind == "movie" {
+                "movie"
+            } else {
+                "series"
+            }
+        )),
+        other => ApiError::Internal(format!("provider fetch failed: {other}")),
+    })?;
+
+    let assignment = crate::provider_policy::assign_provider_id(
+        &state.db,
+        &item_id,
+        &body.provider,
+        provider_id,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if !assignment.stored {
+        return Err(ApiError::Conflict(format!(
+            "{} id {provider_id} is already used by item(s) {} in this library",
+            body.provider,
+            assignment.conflicts.join(", ")
+        ))
+        .into());
+    }
+
+    if body.refresh {
+        crate::artwork::apply_identified_metadata(&state.db, &item, &metadata)
+            .await
+            .map_err(|e| ApiError::Internal(format!("{e:#}")))?;
+    }
+
+    get_item(
+        auth,
+        State(state),
+        Path(item_id),
+        Query(dates),
+        Query(ItemCountsQuery::default()),
+    )
+    .await
+}
+
+#[derive(Serialize)]
+struct PersonResponse {
+    provider: String,
+    provider_id: String,
+    name: String,
+    biography: Option<String>,
+    birthday: Option<String>,
+    deathday: Option<String>,
+    place_of_birth: Option<String>,
+    image_url: Option<String>,
+}
+
+impl From<rustfin_db::repo::people::PersonRow> for PersonResponse {
+    fn from(row: rustfin_db::repo::people::PersonRow) -> Self {
+        Self {
+            provider: row.provider,
+            provider_id: row.provider_id,
+            name: row.name,
+            biography: row.biography,
+            birthday: row.birthday,
+            deathday: row.deathday,
+            place_of_birth: row.place_of_birth,
+            image_url: row
+                .image_url
+                .and_then(|url| crate::image_proxy::proxy_url(&url)),
+        }
+    }
+}
+
+/// How long fetched person details are served before they are fetched again.
+const PERSON_MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Person lookups that reach the provider, per user per minute. They spend the
+/// server's API key, so one account cannot drain its quota. Cache hits are free.
+static PERSON_LOOKUPS: std::sync::LazyLock<RateLimiter> =
+    std::sync::LazyLock::new(|| RateLimiter::new(30, 60));
+
+/// A cast or crew member's biography and photo, by the provider id found in item
+/// credits. Fetched from the provider on first request and cached for
+/// [`PERSON_MAX_AGE_SECS`]; stale details are still served while the provider
+/// can't be reached or the user is over [`PERSON_LOOKUPS`].
+async fn get_person(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path((provider, provider_id)): Path<(String, String)>,
+) -> Result<Json<PersonResponse>, AppError> {
+    // TMDB is the only provider with people; its ids are numeric.
+    if provider != "tmdb"
+        || provider_id.is_empty()
+        || !provider_id.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(ApiError::NotFound("person not found".into()).into());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let cached = rustfin_db::repo::people::get_person(&state.db, &provider, &provider_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if let Some(fresh) = cached
+        .as_ref()
+        .filter(|p| now - p.fetched_ts < PERSON_MAX_AGE_SECS)
+    {
+        return Ok(Json(fresh.clone().into()));
+    }
+
+    let fetched = match PERSON_LOOKUPS.check(&auth.user_id).await {
+        Ok(_) => fetch_person(&state, &provider_id).await,
+        Err(retry_after_seconds) => Err(ApiError::TooManyRequests {
+            retry_after_seconds,
+        }),
+    };
+    let details = match (fetched, cached) {
+        (Ok(details), _) => details,
+        (Err(ApiError::NotFound(msg)), _) => return Err(ApiError::NotFound(msg).into()),
+        (Err(e), Some(stale)) => {
+            tracing::debug!(provider_id = %provider_id, error = %e, "serving stale person details");
+            return Ok(Json(stale.into()));
+        }
+        (Err(e), None) => return Err(e.into()),
+    };
+
+    let row = rustfin_db::repo::people::PersonRow {
+        provider,
+        provider_id,
+        name: details.name,
+        biography: details.biography,
+        birthday: details.birthday,
+        deathday: details.deathday,
+        place_of_birth: details.place_of_birth,
+        image_url: details.image_url,
+        fetched_ts: now,
+    };
+    rustfin_db::repo::people::upsert_person(&state.db, &row)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(Json(row.into()))
+}
+
+async fn fetch_person(
+    state: &AppState,
+    provider_id: &str,
+) -> Result<rustfin_metadata::PersonDetails, ApiError> {
+    use rustfin_metadata::provider::MetadataProvider;
+
+    let client = crate::artwork::tmdb_client(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("{e:#}")))?
+        .ok_or_else(|| ApiError::Conflict("TMDB API key is not configured".into()))?;
+    client.get_person(provider_id).await.map_err(|e| match e {
+        rustfin_metadata::MetadataError::NotFound => ApiError::NotFound("person not found".into()),
+        other => ApiError::Internal(format!("provider lookup failed: {other}")),
+    })
+}
+
 #[derive(Deserialize)]
 struct FieldLockRequest {
     field: String,
@@ -2471,24 +7250,79 @@ async fn unlock_item_field(
     ))
 }
 
+async fn set_item_lock(
+    auth: &AuthUser,
+    state: &AppState,
+    item_id: &str,
+    locked: bool,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let item = rustfin_db::repo::items::get_item(&state.db, item_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
+    ensure_library_access(auth, state, &item.library_id).await?;
+
+    rustfin_db::repo::items::set_item_locked(&state.db, item_id, locked)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    Ok(Json(
+        serde_json::json!({ "ok": true, "item_id": item_id, "locked": locked }),
+    ))
+}
+
+/// Lock the whole item: scans and metadata refreshes leave it as it is.
+async fn lock_item(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    set_item_lock(&auth, &state, &item_id, true).await
+}
+
+async fn unlock_item(
+    auth: AuthUser,
+    State(state): State<AppState>,
+    Path(item_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    set_item_lock(&auth, &state, &item_id, false).await
+}
+
 // ---------------------------------------------------------------------------
 // TV expected / missing episodes
 // ---------------------------------------------------------------------------
 
+#[derive(Deserialize)]
+struct ExpectedEpisodesQuery {
+    /// `season` (default) lists season by season with specials first; `aired`
+    /// interleaves specials where the provider says they aired.
+    order: Option<String>,
+}
+
 async fn get_expected_episodes(
     auth: AuthUser,
     State(state): State<AppState>,
     Path(item_id): Path<String>,
+    Query(query): Query<ExpectedEpisodesQuery>,
 ) -> Result<Json<Vec<rustfin_db::repo::episodes::ExpectedEpisodeRow>>, AppError> {
+    let aired = match query.order.as_deref() {
+        None | Some("season") => false,
+        Some("aired") => true,
+        Some(_) => {
+            return Err(ApiError::BadRequest("order must be 'season' or 'aired'".into()).into());
+        }
+    };
     let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("item not found".into()))?;
     ensure_library_access(&auth, &state, &item.library_id).await?;
 
-    let episodes = rustfin_db::repo::episodes::get_expected_episodes(&state.db, &item_id)
+    let mut episodes = rustfin_db::repo::episodes::get_expected_episodes(&state.db, &item_id)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if aired {
+        rustfin_db::repo::episodes::sort_in_aired_order(&mut episodes);
+    }
     Ok(Json(episodes))
 }
 
@@ -2514,36 +7348,81 @@ async fn get_missing_episodes(
 // ---------------------------------------------------------------------------
 
 async fn sse_events(
-    _auth: AuthUser,
+    auth: AuthUser,
     State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
 ) -> axum::response::Sse<
     impl futures::Stream<Item = Result<axum::response::sse::Event, std::convert::Infallible>>,
 > {
     use axum::response::sse::Event;
     use std::time::Duration;
 
-    let mut rx = state.events.subscribe();
+    fn to_sse(evt: &crate::state::SequencedEvent) -> Option<Event> {
+        let event_type = match &evt.event {
+            crate::state::ServerEvent::ScanProgress { .. } => "scan_progress",
+            crate::state::ServerEvent::ScanComplete { .. } => "scan_complete",
+            crate::state::ServerEvent::MetadataRefresh { .. } => "metadata_refresh",
+            crate::state::ServerEvent::JobUpdate { .. } => "job_update",
+            crate::state::ServerEvent::Heartbeat { .. } => "heartbeat",
+            crate::state::ServerEvent::PlaybackProgress { .. } => "playback_progress",
+        };
+        let data = serde_json::to_string(&evt.event).ok()?;
+        Some(
+            Event::default()
+                .event(event_type)
+                .id(evt.id.to_string())
+                .data(data),
+        )
+    }
+
+    // A reconnecting EventSource sends the id of the last event it saw.
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let (replay, mut rx) = state.events.subscribe_after(last_event_id, &auth.user_id);
 
+    let events = state.events.clone();
     let stream = async_stream::stream! {
+        if replay.dropped > 0 {
+            yield Ok(Event::default()
+                .event("error")
+                .data(format!(r#"{{"lagged":{}}}"#, replay.dropped)));
+        }
+        for evt in &replay.events {
+            if let Some(event) = to_sse(evt) {
+                yield Ok(event);
+            }
+        }
+        // The id of the last event received, visible or not. After a lag, the
+        // events between it and the next one are counted for this user only.
+        let mut last_id = replay.live_after;
+        let mut lagged = false;
         loop {
             match rx.recv().await {
                 Ok(evt) => {
-                    let event_type = match &evt {
-                        crate::state::ServerEvent::ScanProgress { .. } => "scan_progress",
-                        crate::state::ServerEvent::ScanComplete { .. } => "scan_complete",
-                        crate::state::ServerEvent::MetadataRefresh { .. } => "metadata_refresh",
-                        crate::state::ServerEvent::JobUpdate { .. } => "job_update",
-                        crate::state::ServerEvent::Heartbeat { .. } => "heartbeat",
-                    };
-                    if let Ok(data) = serde_json::to_string(&evt) {
-                        yield Ok(Event::default().event(event_type).data(data));
+                    if std::mem::take(&mut lagged) {
+                        // A heartbeat carries the id of the last real event,
+                        // which may be one of those missed.
+                        let heartbeat =
+                            matches!(evt.event, crate::state::ServerEvent::Heartbeat { .. });
+                        let before = evt.id + u64::from(heartbeat);
+                        let missed = events.missed(last_id, before, &auth.user_id);
+                        if missed > 0 {
+                            yield Ok(Event::default()
+                                .event("error")
+                                .data(format!(r#"{{"lagged":{missed}}}"#)));
+                        }
+                    }
+                    last_id = evt.id;
+                    if !evt.event.is_visible_to(&auth.user_id) {
+                        continue;
+                    }
+                    if let Some(event) = to_sse(&evt) {
+                        yield Ok(event);
                     }
                 }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                    yield Ok(Event::default()
-                        .event("error")
-                        .data(format!(r#"{{"lagged":{n}}}"#)));
-                }
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => lagged = true,
                 Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
             }
         }
@@ -0,0 +1,146 @@
+//! Listener bootstrap: plain HTTP/1.1, or TLS with HTTP/2 negotiated via ALPN.
+//!
+//! TLS is enabled by pointing `RUSTFIN_TLS_CERT` / `RUSTFIN_TLS_KEY` at PEM files.
+//! Both modes share the same keep-alive tuning.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo, TokioTimer};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use rustls::ServerConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+
+/// Time allowed for a client to send request headers on an HTTP/1.1 connection.
+const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often idle HTTP/2 connections are pinged, and how long to wait for the ack.
+const H2_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+const H2_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(20);
+/// Players fetch many segments and images in parallel over one HTTP/2 connection.
+const H2_MAX_CONCURRENT_STREAMS: u32 = 256;
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build a rustls config from `RUSTFIN_TLS_CERT` / `RUSTFIN_TLS_KEY`.
+///
+/// Returns `None` when neither is set; setting only one of them is an error.
+pub fn tls_config_from_env() -> anyhow::Result<Option<Arc<ServerConfig>>> {
+    let cert = std::env::var("RUSTFIN_TLS_CERT")
+        .ok()
+        .filter(|v| !v.is_empty());
+    let key = std::env::var("RUSTFIN_TLS_KEY")
+        .ok()
+        .filter(|v| !v.is_empty());
+    match (cert, key) {
+        (None, None) => Ok(None),
+        (Some(cert), Some(key)) => load_tls_config(Path::new(&cert), Path::new(&key)).map(Some),
+        _ => anyhow::bail!("RUSTFIN_TLS_CERT and RUSTFIN_TLS_KEY must be set together"),
+    }
+}
+
+/// Load a PEM certificate chain and private key, advertising `h2` and `http/1.1` via ALPN.
+pub fn load_tls_config(cert_path: &Path, key_path: &Path) -> anyhow::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read TLS certificate {}", cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("failed to read TLS key {}", key_path.display()))?;
+
+    let mut config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .context("unsupported TLS protocol configuration")?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("invalid TLS certificate/key pair")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+fn connection_builder() -> auto::Builder<TokioExecutor> {
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    builder
+        .http1()
+        .keep_alive(true)
+        .timer(TokioTimer::new())
+        .header_read_timeout(HEADER_READ_TIMEOUT);
+    builder
+        .http2()
+        .timer(TokioTimer::new())
+        .keep_alive_interval(H2_KEEP_ALIVE_INTERVAL)
+        .keep_alive_timeout(H2_KEEP_ALIVE_TIMEOUT)
+        .max_concurrent_streams(H2_MAX_CONCURRENT_STREAMS);
+    builder
+}
+
+/// Accept connections forever, serving `app` over TLS when `tls` is set.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<Arc<ServerConfig>>,
+) -> std::io::Result<()> {
+    let builder = Arc::new(connection_builder());
+    let acceptor = tls.map(TlsAcceptor::from);
+
+    loop {
+        let (stream, remote) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // Usually EMFILE or a connection reset before accept; back off briefly.
+                tracing::warn!(error = %e, "failed to accept connection");
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            }
+        };
+        let _ = stream.set_nodelay(true);
+
+        let builder = builder.clone();
+        let acceptor = acceptor.clone();
+        // Handlers read the peer through `ConnectInfo`, as with `axum::serve`.
+        let service = TowerToHyperService::new(app.clone().map_request(
+            move |mut req: axum::http::Request<hyper::body::Incoming>| {
+                req.extensions_mut()
+                    .insert(axum::extract::ConnectInfo(remote));
+                req
+            },
+        ));
+        tokio::spawn(async move {
+            let result = match acceptor {
+                Some(acceptor) => {
+                    let stream =
+                        match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+                            .await
+                        {
+                            Ok(Ok(stream)) => stream,
+                            Ok(Err(e)) => {
+                                tracing::debug!(%remote, error = %e, "TLS handshake failed");
+                                return;
+                            }
+                            Err(_) => {
+                                tracing::debug!(%remote, "TLS handshake timed out");
+                                return;
+                            }
+                        };
+                    builder
+                        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                        .await
+                }
+                None => {
+                    builder
+                        .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                tracing::debug!(%remote, error = %e, "connection closed with error");
+            }
+        });
+    }
+}
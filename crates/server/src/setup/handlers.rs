@@ -406,7 +406,13 @@ pub async fn create_admin(
     }
 
     // Validate
-    if let Some(fields) = validation::validate_admin(&body.username, &body.password) {
+    let policy = match crate::password_policy::PasswordPolicy::load(&state.db).await {
+        Ok(policy) => policy,
+        Err(e) => {
+            return AppError::from(ApiError::Internal(format!("db error: {e}"))).into_response();
+        }
+    };
+    if let Some(fields) = validation::validate_admin(&body.username, &body.password, &policy) {
         return AppError::from(ApiError::validation(fields)).into_response();
     }
 
@@ -1036,7 +1042,7 @@ pub struct ResetSetupResponse {
 }
 
 pub async fn reset_setup(
-    _admin: AdminUser,
+    admin: AdminUser,
     State(state): State<AppState>,
     Json(body): Json<ResetSetupRequest>,
 ) -> Response {
@@ -1078,6 +1084,17 @@ pub async fn reset_setup(
     let _ = rustfin_db::repo::idempotency::delete_all(db).await;
 
     info!("setup reset performed");
+    crate::audit::record(
+        db,
+        &admin.user_id,
+        "setup.reset",
+        None,
+        &format!(
+            "reset setup (delete_users: {}, delete_settings: {})",
+            body.delete_users, body.delete_settings
+        ),
+    )
+    .await;
 
     (
         StatusCode::OK,
@@ -5,8 +5,12 @@ use std::sync::LazyLock;
 static REGION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^[A-Z]{2}$").unwrap());
 
 /// Validate create admin request fields. Returns field errors or None.
-pub fn validate_admin(username: &str, password: &str) -> Option<Value> {
-    crate::user_pipeline::validate_username_password(username, password)
+pub fn validate_admin(
+    username: &str,
+    password: &str,
+    policy: &crate::password_policy::PasswordPolicy,
+) -> Option<Value> {
+    crate::user_pipeline::validate_username_password(username, password, policy)
 }
 
 /// Validate setup config fields.
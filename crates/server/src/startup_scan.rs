@@ -0,0 +1,93 @@
+//! Library scans queued when the server boots.
+//!
+//! `scan_on_startup` is `off` (the default), `all` (every library) or `flagged`
+//! (only libraries whose `scan_on_startup` setting is on). The scans go through the
+//! usual `library_scan` jobs, so booting doesn't wait for them.
+
+use rustfin_core::error::ApiError;
+use sqlx::SqlitePool;
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+pub const SCAN_ON_STARTUP_KEY: &str = "scan_on_startup";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanOnStartup {
+    #[default]
+    Off,
+    All,
+    Flagged,
+}
+
+impl ScanOnStartup {
+    pub const ALL: [Self; 3] = [Self::Off, Self::All, Self::Flagged];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::All => "all",
+            Self::Flagged => "flagged",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+
+    /// The stored setting, falling back to the default if unset or unknown.
+    pub async fn load(pool: &SqlitePool) -> Result<Self, sqlx::Error> {
+        Ok(rustfin_db::repo::settings::get(pool, SCAN_ON_STARTUP_KEY)
+            .await?
+            .and_then(|v| Self::parse(v.trim()))
+            .unwrap_or_default())
+    }
+}
+
+/// Enqueue a scan for each library the `scan_on_startup` setting selects.
+pub async fn enqueue_startup_scans(
+    state: &AppState,
+) -> Result<Vec<rustfin_db::repo::jobs::JobRow>, AppError> {
+    let policy = ScanOnStartup::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if policy == ScanOnStartup::Off {
+        return Ok(Vec::new());
+    }
+
+    let libraries = rustfin_db::repo::libraries::list_libraries(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let mut jobs = Vec::new();
+    for lib in libraries {
+        if policy == ScanOnStartup::Flagged
+            && !rustfin_db::repo::libraries::get_library_scan_on_startup(&state.db, &lib.id)
+                .await
+                .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        {
+            continue;
+        }
+        tracing::info!(library_id = %lib.id, "starting startup scan");
+        jobs.push(crate::library_scan::enqueue_library_scan(state, &lib.id, &lib.kind).await?);
+    }
+    Ok(jobs)
+}
+
+/// Run [`enqueue_startup_scans`] in the background during bootstrap, so readiness
+/// doesn't wait on it.
+pub fn spawn_startup_scans(state: AppState) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        match enqueue_startup_scans(&state).await {
+            Ok(jobs) if !jobs.is_empty() => {
+                tracing::info!(scans = jobs.len(), "queued startup scans");
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(
+                    status = e.0.status_code(),
+                    "failed to enqueue startup scans"
+                );
+            }
+        }
+    })
+}
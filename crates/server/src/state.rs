@@ -1,4 +1,6 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use sqlx::SqlitePool;
 
@@ -18,6 +20,8 @@ pub enum ServerEvent {
         library_id: String,
         job_id: String,
         items_added: u64,
+        /// Files that could not be scanned; they are retried by a `retry_failed` scan.
+        files_failed: u64,
     },
     #[serde(rename = "metadata_refresh")]
     MetadataRefresh { item_id: String, status: String },
@@ -29,6 +33,263 @@ pub enum ServerEvent {
     },
     #[serde(rename = "heartbeat")]
     Heartbeat { seq: u64 },
+    /// Delivered only to `user_id`'s own streams.
+    #[serde(rename = "playback_progress")]
+    PlaybackProgress {
+        user_id: String,
+        item_id: String,
+        progress_ms: i64,
+        played: bool,
+    },
+}
+
+impl ServerEvent {
+    /// The only user allowed to receive this event, or `None` for broadcast events.
+    pub fn target_user(&self) -> Option<&str> {
+        match self {
+            Self::PlaybackProgress { user_id, .. } => Some(user_id),
+            _ => None,
+        }
+    }
+
+    /// Whether a stream authenticated as `user_id` should receive this event.
+    pub fn is_visible_to(&self, user_id: &str) -> bool {
+        self.target_user().is_none_or(|target| target == user_id)
+    }
+}
+
+/// A [`ServerEvent`] with its place in the server's event sequence; sent to SSE
+/// clients as the event `id`.
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    pub id: u64,
+    pub event: ServerEvent,
+}
+
+/// Broadcasts [`ServerEvent`]s, numbering each one and keeping the most recent so
+/// SSE clients that reconnect with `Last-Event-ID` can catch up on what they missed.
+#[derive(Clone, Debug)]
+pub struct EventBus {
+    tx: tokio::sync::broadcast::Sender<SequencedEvent>,
+    recent: Arc<Mutex<RecentEvents>>,
+}
+
+#[derive(Debug, Default)]
+struct RecentEvents {
+    last_id: u64,
+    events: VecDeque<SequencedEvent>,
+    capacity: usize,
+    /// Id and target user of events that fell out of `events`, oldest first, so
+    /// a client that missed them can be told how many were meant for it.
+    evicted: VecDeque<(u64, Option<String>)>,
+}
+
+impl RecentEvents {
+    /// How many more evicted events are remembered than are buffered.
+    const EVICTED_PER_BUFFERED: usize = 16;
+
+    /// Events with `after < id < before` that `user_id` could have received. Ids
+    /// no longer on record are counted, since nothing says they weren't.
+    fn missed(&self, after: u64, before: u64, user_id: &str) -> u64 {
+        let in_range = |id: u64| id > after && id < before;
+        let (mut known, mut visible) = (0, 0);
+        let evicted = self.evicted.iter().map(|(id, t)| (*id, t.as_deref()));
+        let buffered = self.events.iter().map(|e| (e.id, e.event.target_user()));
+        for (_, target) in evicted.chain(buffered).filter(|(id, _)| in_range(*id)) {
+            known += 1;
+            if target.is_none_or(|t| t == user_id) {
+                visible += 1;
+            }
+        }
+        visible + before.saturating_sub(after + 1).saturating_sub(known)
+    }
+}
+
+/// Events kept for catch-up, and missed events that could not be replayed.
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// Buffered events after `Last-Event-ID` that the subscriber may see.
+    pub events: Vec<SequencedEvent>,
+    /// Events after `Last-Event-ID` meant for the subscriber that already fell
+    /// out of the buffer.
+    pub dropped: u64,
+    /// Id of the last event sent before the receiver subscribed.
+    pub live_after: u64,
+}
+
+impl EventBus {
+    pub const DEFAULT_CAPACITY: usize = 256;
+
+    /// `capacity` bounds both the live channel and the replay buffer.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            tx: tokio::sync::broadcast::channel(capacity).0,
+            recent: Arc::new(Mutex::new(RecentEvents {
+                capacity,
+                ..RecentEvents::default()
+            })),
+        }
+    }
+
+    /// Publish `event` and return its id. Heartbeats are not worth replaying: they
+    /// reuse the latest id and are not buffered.
+    pub fn send(&self, event: ServerEvent) -> u64 {
+        let mut recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        let heartbeat = matches!(event, ServerEvent::Heartbeat { .. });
+        if !heartbeat {
+            recent.last_id += 1;
+        }
+        let sequenced = SequencedEvent {
+            id: recent.last_id,
+            event,
+        };
+        if !heartbeat {
+            if recent.events.len() == recent.capacity
+                && let Some(old) = recent.events.pop_front()
+            {
+                let target = old.event.target_user().map(str::to_string);
+                if recent.evicted.len() == recent.capacity * RecentEvents::EVICTED_PER_BUFFERED {
+                    recent.evicted.pop_front();
+                }
+                recent.evicted.push_back((old.id, target));
+            }
+            recent.events.push_back(sequenced.clone());
+        }
+        // Sent under the lock so a concurrent `subscribe_after` sees each event
+        // either in the replay or on the receiver, never both or neither.
+        let id = sequenced.id;
+        let _ = self.tx.send(sequenced);
+        id
+    }
+
+    /// Subscribe `user_id` to live events, plus the buffered events newer than
+    /// `last_id` that it may see.
+    ///
+    /// An id ahead of the sequence comes from before a restart, so everything
+    /// buffered is replayed.
+    pub fn subscribe_after(
+        &self,
+        last_id: Option<u64>,
+        user_id: &str,
+    ) -> (Replay, tokio::sync::broadcast::Receiver<SequencedEvent>) {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        let rx = self.tx.subscribe();
+        let live_after = recent.last_id;
+        let Some(last_id) = last_id else {
+            return (
+                Replay {
+                    live_after,
+                    ..Replay::default()
+                },
+                rx,
+            );
+        };
+        let after = if last_id > recent.last_id { 0 } else { last_id };
+        let events: Vec<_> = recent
+            .events
+            .iter()
+            .filter(|e| e.id > after && e.event.is_visible_to(user_id))
+            .cloned()
+            .collect();
+        let dropped = recent
+            .events
+            .front()
+            .map_or(0, |first| recent.missed(after, first.id, user_id));
+        let replay = Replay {
+            events,
+            dropped,
+            live_after,
+        };
+        (replay, rx)
+    }
+
+    /// Events with `after < id < before` that `user_id` could have received, for
+    /// a receiver that lagged between the two.
+    pub fn missed(&self, after: u64, before: u64, user_id: &str) -> u64 {
+        let recent = self.recent.lock().unwrap_or_else(|e| e.into_inner());
+        recent.missed(after, before, user_id)
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+/// Startup gate reported by `/health/ready`. Starts out not ready; `main` marks it
+/// ready once migrations have run and defaults are in place.
+#[derive(Clone, Debug, Default)]
+pub struct Readiness(Arc<AtomicBool>);
+
+impl Readiness {
+    pub fn mark_ready(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// Bounds applied to every `/api/v1` request.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestLimits {
+    /// Largest request body accepted; bigger ones get 413.
+    pub max_body_bytes: usize,
+    /// Time a handler has to produce a response; slower ones get 408.
+    pub timeout: std::time::Duration,
+    /// [`timeout`](Self::timeout) for admin maintenance routes (database optimize,
+    /// provider key checks), which can legitimately run for minutes.
+    pub maintenance_timeout: std::time::Duration,
+    /// Requests slower than this are logged at `warn`.
+    pub slow_request: std::time::Duration,
+}
+
+impl RequestLimits {
+    pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+    pub const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+    pub const DEFAULT_MAINTENANCE_TIMEOUT: std::time::Duration =
+        std::time::Duration::from_secs(30 * 60);
+    pub const DEFAULT_SLOW_REQUEST: std::time::Duration = std::time::Duration::from_secs(2);
+
+    /// `RUSTFIN_MAX_BODY_KIB`, `RUSTFIN_REQUEST_TIMEOUT_SECS`,
+    /// `RUSTFIN_MAINTENANCE_TIMEOUT_SECS` and `RUSTFIN_SLOW_REQUEST_MS`, else the
+    /// defaults.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|&v| v > 0)
+        };
+        Self {
+            max_body_bytes: var("RUSTFIN_MAX_BODY_KIB")
+                .map(|kib| kib as usize * 1024)
+                .unwrap_or(Self::DEFAULT_MAX_BODY_BYTES),
+            timeout: var("RUSTFIN_REQUEST_TIMEOUT_SECS")
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(Self::DEFAULT_TIMEOUT),
+            maintenance_timeout: var("RUSTFIN_MAINTENANCE_TIMEOUT_SECS")
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(Self::DEFAULT_MAINTENANCE_TIMEOUT),
+            slow_request: var("RUSTFIN_SLOW_REQUEST_MS")
+                .map(std::time::Duration::from_millis)
+                .unwrap_or(Self::DEFAULT_SLOW_REQUEST),
+        }
+    }
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: Self::DEFAULT_MAX_BODY_BYTES,
+            timeout: Self::DEFAULT_TIMEOUT,
+            maintenance_timeout: Self::DEFAULT_MAINTENANCE_TIMEOUT,
+            slow_request: Self::DEFAULT_SLOW_REQUEST,
+        }
+    }
 }
 
 /// Shared application state passed to all handlers.
@@ -38,5 +299,64 @@ pub struct AppState {
     pub jwt_secret: String,
     pub transcoder: Arc<rustfin_transcoder::session::SessionManager>,
     pub cache_dir: std::path::PathBuf,
-    pub events: tokio::sync::broadcast::Sender<ServerEvent>,
+    pub events: EventBus,
+    pub ready: Readiness,
+    /// Limits concurrent direct-stream reads.
+    pub streams: crate::streaming::StreamLimiter,
+    /// Embedded subtitle tracks extracted to WebVTT.
+    pub subtitles: crate::subtitle_cache::SubtitleCache,
+    /// One lock per image upload, so a chunk's offset check and append happen together.
+    pub uploads: crate::keyed_lock::KeyedLocks<String>,
+    pub limits: RequestLimits,
+    /// ffmpeg/ffprobe as found at startup; `/health` reports `degraded` without them.
+    pub media_tools: rustfin_transcoder::tools::MediaTools,
+    /// Worker slots for background jobs; queued jobs wait here.
+    pub jobs: crate::job_queue::JobQueue,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(user_id: &str) -> ServerEvent {
+        ServerEvent::PlaybackProgress {
+            user_id: user_id.to_string(),
+            item_id: "i_1".to_string(),
+            progress_ms: 1000,
+            played: false,
+        }
+    }
+
+    fn job_update() -> ServerEvent {
+        ServerEvent::JobUpdate {
+            job_id: "j_1".to_string(),
+            status: "running".to_string(),
+            progress: 0.5,
+        }
+    }
+
+    #[test]
+    fn dropped_events_count_only_those_the_subscriber_could_see() {
+        let bus = EventBus::new(2);
+        bus.send(progress("bob"));
+        bus.send(job_update());
+        bus.send(progress("bob"));
+        bus.send(progress("alice"));
+        bus.send(progress("bob"));
+        bus.send(progress("bob"));
+
+        // Events 1-4 fell out of the buffer; alice could see the job update and
+        // her own progress, bob everything but hers.
+        let (replay, _rx) = bus.subscribe_after(Some(0), "alice");
+        assert_eq!(replay.dropped, 2);
+        assert!(replay.events.is_empty());
+        let (replay, _rx) = bus.subscribe_after(Some(0), "bob");
+        assert_eq!(replay.dropped, 3);
+        assert_eq!(replay.events.len(), 2);
+
+        assert_eq!(bus.missed(1, 5, "alice"), 2);
+        assert_eq!(bus.missed(4, 6, "alice"), 0);
+        // Ids no longer on record are counted.
+        assert_eq!(bus.missed(0, 10, "alice"), 5);
+    }
 }
@@ -2,16 +2,272 @@ use axum::body::Body;
 use axum::extract::{Path, Query, State};
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::Response;
+use futures::StreamExt;
 use rustfin_core::error::ApiError;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncSeekExt;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 use crate::auth::{validate_stream_token, validate_token};
 use crate::error::AppError;
 use crate::state::AppState;
 
+/// Slots held per playback key, per user id.
+type OpenPlaybacks = HashMap<String, HashMap<String, usize>>;
+
+/// Caps concurrent `/stream/file` reads so many simultaneous seeks can't exhaust
+/// file descriptors. Each response holds a permit until its body is dropped.
+#[derive(Clone, Debug)]
+pub struct StreamLimiter {
+    permits: Arc<Semaphore>,
+    max_concurrent: usize,
+    buffer_size: usize,
+    /// Responses up to this many bytes are read into memory and sent as one body.
+    inline_max_bytes: usize,
+    /// Open playbacks per user id; see [`StreamLimiter::try_acquire_for_user`].
+    per_user: Arc<Mutex<OpenPlaybacks>>,
+}
+
+impl StreamLimiter {
+    pub const DEFAULT_MAX_CONCURRENT: usize = 256;
+    pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+    pub const DEFAULT_INLINE_MAX_BYTES: usize = 64 * 1024;
+
+    pub fn new(max_concurrent: usize, buffer_size: usize) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            buffer_size: buffer_size.max(1),
+            inline_max_bytes: Self::DEFAULT_INLINE_MAX_BYTES,
+            per_user: Arc::default(),
+        }
+    }
+
+    /// Buffer responses of at most `bytes` instead of streaming them; 0 streams
+    /// everything.
+    pub fn with_inline_max_bytes(mut self, bytes: usize) -> Self {
+        self.inline_max_bytes = bytes;
+        self
+    }
+
+    /// `RUSTFIN_STREAM_MAX_CONCURRENT`, `RUSTFIN_STREAM_BUFFER_KIB` and
+    /// `RUSTFIN_STREAM_INLINE_MAX_KIB`, else the defaults.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|&v| v > 0)
+        };
+        Self::new(
+            var("RUSTFIN_STREAM_MAX_CONCURRENT").unwrap_or(Self::DEFAULT_MAX_CONCURRENT),
+            var("RUSTFIN_STREAM_BUFFER_KIB")
+                .map(|kib| kib * 1024)
+                .unwrap_or(Self::DEFAULT_BUFFER_SIZE),
+        )
+        .with_inline_max_bytes(
+            std::env::var("RUSTFIN_STREAM_INLINE_MAX_KIB")
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .map(|kib| kib * 1024)
+                .unwrap_or(Self::DEFAULT_INLINE_MAX_BYTES),
+        )
+    }
+
+    /// A read slot, or `None` when `max_concurrent` streams are already open.
+    pub fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        self.permits.clone().try_acquire_owned().ok()
+    }
+
+    /// A slot for `playback` counted against `user_id`, or `None` when the user
+    /// already has `limit` other playbacks open. Slots sharing a playback key (the
+    /// overlapping range requests of one player) count once. `None` as the limit
+    /// always grants a slot.
+    pub fn try_acquire_for_user(
+        &self,
+        user_id: &str,
+        playback: &str,
+        limit: Option<usize>,
+    ) -> Option<UserStreamSlot> {
+        let mut per_user = self.per_user.lock().unwrap_or_else(|e| e.into_inner());
+        let playbacks = per_user.entry(user_id.to_string()).or_default();
+        if !playbacks.contains_key(playback) && limit.is_some_and(|limit| playbacks.len() >= limit)
+        {
+            if playbacks.is_empty() {
+                per_user.remove(user_id);
+            }
+            return None;
+        }
+        *playbacks.entry(playback.to_string()).or_default() += 1;
+        Some(UserStreamSlot {
+            per_user: self.per_user.clone(),
+            user_id: user_id.to_string(),
+            playback: playback.to_string(),
+        })
+    }
+
+    /// Playbacks `user_id` has open.
+    pub fn open_for_user(&self, user_id: &str) -> usize {
+        self.per_user
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(user_id)
+            .map_or(0, HashMap::len)
+    }
+
+    pub fn max_concurrent(&self) -> usize {
+        self.max_concurrent
+    }
+
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    pub fn inline_max_bytes(&self) -> usize {
+        self.inline_max_bytes
+    }
+}
+
+impl Default for StreamLimiter {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_CONCURRENT, Self::DEFAULT_BUFFER_SIZE)
+    }
+}
+
+/// A hold on one of a user's open playbacks, released when dropped.
+#[derive(Debug)]
+pub struct UserStreamSlot {
+    per_user: Arc<Mutex<OpenPlaybacks>>,
+    user_id: String,
+    playback: String,
+}
+
+impl Drop for UserStreamSlot {
+    fn drop(&mut self) {
+        let mut per_user = self.per_user.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(playbacks) = per_user.get_mut(&self.user_id) else {
+            return;
+        };
+        if let Some(holds) = playbacks.get_mut(&self.playback) {
+            *holds = holds.saturating_sub(1);
+            if *holds == 0 {
+                playbacks.remove(&self.playback);
+            }
+        }
+        if playbacks.is_empty() {
+            per_user.remove(&self.user_id);
+        }
+    }
+}
+
+fn stream_limit_reached() -> AppError {
+    ApiError::TooManyRequests {
+        retry_after_seconds: crate::playback_policy::STREAM_LIMIT_RETRY_AFTER_SECS,
+    }
+    .into()
+}
+
+/// Refuse with 403 when `client` is outside the local network and an account with
+/// `role` may not stream remotely.
+pub async fn ensure_remote_access(
+    state: &AppState,
+    client: crate::client_ip::ClientIp,
+    role: &str,
+) -> Result<(), AppError> {
+    if client.is_local() {
+        return Ok(());
+    }
+    let allowed = crate::playback_policy::remote_streaming_allowed(&state.db, role)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if !allowed {
+        return Err(ApiError::Forbidden(
+            crate::playback_policy::REMOTE_ACCESS_DISABLED_MESSAGE.into(),
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// A slot for `playback` counted against `user_id`, or 429 when an account with
+/// `role` already has as many streams open as its limit allows. Transcoding
+/// sessions hold theirs for as long as they run, so the slot is reserved before
+/// the session starts rather than checked against a count taken earlier.
+pub async fn reserve_stream(
+    state: &AppState,
+    user_id: &str,
+    role: &str,
+    playback: &str,
+) -> Result<UserStreamSlot, AppError> {
+    let limit = crate::playback_policy::stream_limit(&state.db, role)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    state
+        .streams
+        .try_acquire_for_user(user_id, playback, limit)
+        .ok_or_else(stream_limit_reached)
+}
+
+/// Chunks of at most `buffer_size` bytes read from `reader`.
+fn reader_stream<R: tokio::io::AsyncRead>(
+    reader: R,
+    buffer_size: usize,
+) -> tokio_util::io::ReaderStream<R> {
+    tokio_util::io::ReaderStream::with_capacity(reader, buffer_size)
+}
+
+/// Response body streaming `reader`, holding `permit` until the body is dropped.
+fn limited_body<R, P>(reader: R, permit: P, buffer_size: usize) -> Body
+where
+    R: tokio::io::AsyncRead + Send + 'static,
+    P: Send + 'static,
+{
+    Body::from_stream(reader_stream(reader, buffer_size).map(move |chunk| {
+        let _held = &permit;
+        chunk
+    }))
+}
+
+/// Body for `len` bytes of `file_path` starting at `start`. Small reads are done
+/// up front and sent as a single body of known length, which saves the per-chunk
+/// overhead and keeps clients that mishandle chunked transfers happy; larger ones
+/// stream while holding `permit`.
+pub(crate) async fn file_range_body<P>(
+    file_path: &std::path::Path,
+    start: u64,
+    len: u64,
+    permit: P,
+    limiter: &StreamLimiter,
+) -> Result<Body, ApiError>
+where
+    P: Send + 'static,
+{
+    let mut file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("file open error: {e}")))?;
+    if start > 0 {
+        file.seek(std::io::SeekFrom::Start(start))
+            .await
+            .map_err(|e| ApiError::Internal(format!("seek error: {e}")))?;
+    }
+    if len <= limiter.inline_max_bytes() as u64 {
+        let mut bytes = Vec::with_capacity(len as usize);
+        file.take(len)
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| ApiError::Internal(format!("file read error: {e}")))?;
+        if bytes.len() as u64 != len {
+            return Err(ApiError::Internal("file is shorter than recorded".into()));
+        }
+        return Ok(Body::from(bytes));
+    }
+    Ok(limited_body(file.take(len), permit, limiter.buffer_size()))
+}
+
 /// Parse an HTTP Range header per RFC 7233.
 /// Only supports single byte ranges: `bytes=start-end` or `bytes=start-`.
 pub struct ByteRange {
@@ -27,6 +283,11 @@ pub fn parse_range_header(range_str: &str, file_size: u64) -> Result<ByteRange,
 
     let spec = &range_str["bytes=".len()..];
 
+    // No range of an empty file is satisfiable.
+    if file_size == 0 {
+        return Err(ApiError::BadRequest("range of an empty file".into()));
+    }
+
     // Reject multi-range
     if spec.contains(',') {
         return Err(ApiError::BadRequest("multi-range not supported".into()));
@@ -41,6 +302,10 @@ pub fn parse_range_header(range_str: &str, file_size: u64) -> Result<ByteRange,
         let suffix: u64 = end_s
             .parse()
             .map_err(|_| ApiError::BadRequest("bad range suffix".into()))?;
+        // bytes=-0 asks for no bytes at all, which RFC 7233 treats as unsatisfiable.
+        if suffix == 0 {
+            return Err(ApiError::BadRequest("zero-length range suffix".into()));
+        }
         let start = file_size.saturating_sub(suffix);
         return Ok(ByteRange {
             start,
@@ -101,6 +366,11 @@ fn content_type_for_path(path: &std::path::Path) -> &'static str {
         Some("3gp") => "video/3gpp",
         Some("3g2") => "video/3gpp2",
         Some("mxf") => "application/mxf",
+        Some("mp3") => "audio/mpeg",
+        Some("flac") => "audio/flac",
+        Some("m4a" | "aac") => "audio/mp4",
+        Some("ogg" | "oga" | "opus") => "audio/ogg",
+        Some("wav") => "audio/wav",
         _ => "application/octet-stream",
     }
 }
@@ -113,12 +383,14 @@ pub struct StreamAuthQuery {
     token: Option<String>, // legacy, intentionally rejected
 }
 
-pub async fn stream_file_range(
-    State(state): State<AppState>,
-    Path(file_id): Path<String>,
-    Query(query): Query<StreamAuthQuery>,
-    headers: HeaderMap,
-) -> Result<Response, AppError> {
+/// The `(user_id, role)` a stream request for `file_id` is made as, from a bearer
+/// token or a stream token scoped to that file.
+fn authorize_stream(
+    state: &AppState,
+    file_id: &str,
+    query: &StreamAuthQuery,
+    headers: &HeaderMap,
+) -> Result<(String, String), AppError> {
     let bearer_token = headers
         .get(axum::http::header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
@@ -152,6 +424,18 @@ pub async fn stream_file_range(
             );
         }
     }
+    Ok((user_id, role))
+}
+
+pub async fn stream_file_range(
+    State(state): State<AppState>,
+    Path(file_id): Path<String>,
+    Query(query): Query<StreamAuthQuery>,
+    client: crate::client_ip::ClientIp,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let (user_id, role) = authorize_stream(&state, &file_id, &query, &headers)?;
+    ensure_remote_access(&state, client, &role).await?;
 
     // Look up media file
     let media_file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
@@ -159,6 +443,20 @@ pub async fn stream_file_range(
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
         .ok_or_else(|| ApiError::NotFound("media file not found".into()))?;
 
+    if media_file.is_remote {
+        return redirect_to_remote_file(&state, &media_file, &user_id, &role).await;
+    }
+
+    let direct_play_allowed = crate::playback_policy::direct_play_allowed(&state.db, &role)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if !direct_play_allowed {
+        return Err(ApiError::Forbidden(
+            crate::playback_policy::DIRECT_PLAY_DISABLED_MESSAGE.into(),
+        )
+        .into());
+    }
+
     let file_path = PathBuf::from(&media_file.path);
 
     // Security: verify path exists and is a regular file
@@ -173,8 +471,217 @@ pub async fn stream_file_range(
         validate_path_in_user_libraries(&state, &file_path, &user_id).await?;
     }
 
-    let file_size = media_file.size_bytes as u64;
-    let content_type = content_type_for_path(&file_path);
+    // A player's overlapping range requests for one file are a single stream.
+    let slot = reserve_stream(&state, &user_id, &role, &format!("file:{file_id}")).await?;
+
+    let cache_control = crate::cache_policy::media_cache_control(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    serve_file(
+        &state,
+        &file_path,
+        media_file.size_bytes as u64,
+        &headers,
+        &cache_control,
+        Some(slot),
+    )
+    .await
+}
+
+/// An embedded subtitle track as WebVTT, extracted once and then served from
+/// the subtitle cache.
+/// GET /stream/file/{file_id}/subtitles/{index}
+pub async fn stream_embedded_subtitle(
+    State(state): State<AppState>,
+    Path((file_id, index)): Path<(String, u32)>,
+    Query(query): Query<StreamAuthQuery>,
+    client: crate::client_ip::ClientIp,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let (user_id, role) = authorize_stream(&state, &file_id, &query, &headers)?;
+    ensure_remote_access(&state, client, &role).await?;
+
+    let media_file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("media file not found".into()))?;
+    if media_file.is_remote {
+        return Err(ApiError::BadRequest(
+            "embedded subtitles cannot be extracted from remote media".into(),
+        )
+        .into());
+    }
+    let file_path = PathBuf::from(&media_file.path);
+    if !file_path.is_file() {
+        return Err(ApiError::NotFound("file not found on disk".into()).into());
+    }
+    if role == "admin" {
+        validate_path_in_library(&state, &file_path).await?;
+    } else {
+        validate_path_in_user_libraries(&state, &file_path, &user_id).await?;
+    }
+    if state.media_tools.ffmpeg.is_missing() {
+        return Err(ApiError::ServiceUnavailable(
+            "subtitle extraction unavailable: ffmpeg not found".into(),
+        )
+        .into());
+    }
+
+    let track = state
+        .subtitles
+        .get_or_extract(
+            &state.cache_dir,
+            state.transcoder.ffmpeg_path(),
+            &file_id,
+            &file_path,
+            index,
+        )
+        .await
+        .map_err(|e| match e {
+            rustfin_transcoder::TranscodeError::FfmpegFailed(msg) => {
+                tracing::debug!(file_id = %file_id, index, error = %msg, "subtitle extraction failed");
+                ApiError::NotFound(format!("subtitle track {index} could not be extracted"))
+            }
+            other => ApiError::Internal(format!("subtitle extraction error: {other}")),
+        })?;
+
+    let not_modified = headers
+        .get(axum::http::header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(',').any(|tag| tag.trim() == track.etag));
+    let builder = Response::builder().header("ETag", &track.etag).header(
+        "Cache-Control",
+        crate::subtitle_cache::SUBTITLE_CACHE_CONTROL,
+    );
+    if not_modified {
+        return Ok(builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap());
+    }
+
+    let data = tokio::fs::read(&track.path)
+        .await
+        .map_err(|e| ApiError::Internal(format!("read subtitle: {e}")))?;
+    Ok(builder
+        .status(StatusCode::OK)
+        .header("Content-Type", "text/vtt; charset=utf-8")
+        .header("X-Content-Type-Options", "nosniff")
+        .body(Body::from(data))
+        .unwrap())
+}
+
+/// Serve one attachment (usually a font) embedded in a media file.
+pub async fn stream_attachment(
+    State(state): State<AppState>,
+    Path((file_id, index)): Path<(String, u32)>,
+    Query(query): Query<StreamAuthQuery>,
+    client: crate::client_ip::ClientIp,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let (user_id, role) = authorize_stream(&state, &file_id, &query, &headers)?;
+    ensure_remote_access(&state, client, &role).await?;
+
+    let media_file = rustfin_db::repo::media_files::get_media_file(&state.db, &file_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .ok_or_else(|| ApiError::NotFound("media file not found".into()))?;
+    if media_file.is_remote {
+        return Err(ApiError::BadRequest(
+            "attachments cannot be extracted from remote media".into(),
+        )
+        .into());
+    }
+    let file_path = PathBuf::from(&media_file.path);
+    if !file_path.is_file() {
+        return Err(ApiError::NotFound("file not found on disk".into()).into());
+    }
+    if role == "admin" {
+        validate_path_in_library(&state, &file_path).await?;
+    } else {
+        validate_path_in_user_libraries(&state, &file_path, &user_id).await?;
+    }
+    if state.media_tools.ffmpeg.is_missing() {
+        return Err(ApiError::ServiceUnavailable(
+            "attachment extraction unavailable: ffmpeg not found".into(),
+        )
+        .into());
+    }
+
+    let (dir, attachments) =
+        crate::attachments::extracted_attachments(&state, &file_id, &file_path)
+            .await
+            .map_err(|e| match e {
+                rustfin_transcoder::TranscodeError::ProbeFailed(e) => {
+                    ApiError::ServiceUnavailable(format!("could not probe media file: {e}"))
+                }
+                e => ApiError::Internal(format!("attachment extraction error: {e}")),
+            })?;
+    let attachment = attachments
+        .iter()
+        .find(|a| a.index == index)
+        .ok_or_else(|| ApiError::NotFound(format!("no attachment {index} in file")))?;
+    let data = tokio::fs::read(dir.join(attachment.file_name()))
+        .await
+        .map_err(|_| ApiError::NotFound(format!("attachment {index} could not be extracted")))?;
+
+    // The MIME type comes from the file; only pass through ones that can't run script.
+    let content_type = match attachment.mime_type.as_deref().map(str::to_ascii_lowercase) {
+        Some(m)
+            if matches!(
+                m.as_str(),
+                "image/jpeg"
+                    | "image/png"
+                    | "image/webp"
+                    | "font/ttf"
+                    | "font/otf"
+                    | "font/woff"
+                    | "font/woff2"
+                    | "font/collection"
+                    | "application/x-truetype-font"
+                    | "application/vnd.ms-opentype"
+                    | "application/font-sfnt"
+            ) =>
+        {
+            m
+        }
+        _ => "application/octet-stream".to_string(),
+    };
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", content_type)
+        .header("X-Content-Type-Options", "nosniff")
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", attachment.file_name()),
+        )
+        .header(
+            "Cache-Control",
+            crate::subtitle_cache::SUBTITLE_CACHE_CONTROL,
+        )
+        .body(Body::from(data))
+        .unwrap())
+}
+
+/// Serve a local file with HTTP Range support, holding a stream permit and the
+/// user's `slot`, if any, while the body is read.
+pub(crate) async fn serve_file(
+    state: &AppState,
+    file_path: &std::path::Path,
+    file_size: u64,
+    headers: &HeaderMap,
+    cache_control: &str,
+    slot: Option<UserStreamSlot>,
+) -> Result<Response, AppError> {
+    let permit = state.streams.try_acquire().ok_or_else(|| {
+        ApiError::ServiceUnavailable(format!(
+            "too many concurrent streams (limit {}); retry shortly",
+            state.streams.max_concurrent()
+        ))
+    })?;
+    let permit = (permit, slot);
+
+    let content_type = content_type_for_path(file_path);
 
     // Check for Range header
     if let Some(range_header) = headers.get("range").and_then(|v| v.to_str().ok()) {
@@ -191,17 +698,14 @@ pub async fn stream_file_range(
         };
 
         let content_length = range.end_inclusive - range.start + 1;
-
-        // Open file and seek
-        let mut file = tokio::fs::File::open(&file_path)
-            .await
-            .map_err(|e| ApiError::Internal(format!("file open error: {e}")))?;
-        file.seek(std::io::SeekFrom::Start(range.start))
-            .await
-            .map_err(|e| ApiError::Internal(format!("seek error: {e}")))?;
-
-        // Stream the requested range
-        let stream = tokio_util::io::ReaderStream::new(file.take(content_length));
+        let body = file_range_body(
+            file_path,
+            range.start,
+            content_length,
+            permit,
+            &state.streams,
+        )
+        .await?;
 
         Ok(Response::builder()
             .status(StatusCode::PARTIAL_CONTENT)
@@ -215,54 +719,114 @@ pub async fn stream_file_range(
                 ),
             )
             .header("Accept-Ranges", "bytes")
-            .header("Cache-Control", "no-store")
+            .header("Cache-Control", cache_control)
             .header("Referrer-Policy", "no-referrer")
             .header("X-Content-Type-Options", "nosniff")
-            .body(Body::from_stream(stream))
+            .body(body)
             .unwrap())
     } else {
         // Full file response (200)
-        let file = tokio::fs::File::open(&file_path)
-            .await
-            .map_err(|e| ApiError::Internal(format!("file open error: {e}")))?;
-
-        let stream = tokio_util::io::ReaderStream::new(file);
+        let body = file_range_body(file_path, 0, file_size, permit, &state.streams).await?;
 
         Ok(Response::builder()
             .status(StatusCode::OK)
             .header("Content-Type", content_type)
             .header("Content-Length", file_size.to_string())
             .header("Accept-Ranges", "bytes")
-            .header("Cache-Control", "no-store")
+            .header("Cache-Control", cache_control)
             .header("Referrer-Policy", "no-referrer")
             .header("X-Content-Type-Options", "nosniff")
-            .body(Body::from_stream(stream))
+            .body(body)
             .unwrap())
     }
 }
 
+/// Hosts `.strm` URLs may point at (`RUSTFIN_STRM_ALLOWED_HOSTS`, comma-separated).
+/// Empty means any http(s) host is allowed.
+fn strm_allowed_hosts() -> Vec<String> {
+    std::env::var("RUSTFIN_STRM_ALLOWED_HOSTS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|h| h.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|h| !h.is_empty())
+        .collect()
+}
+
+/// Whether a remote media URL may be handed to clients: http(s) only, and when an
+/// allowlist is configured the host must match an entry or be a subdomain of one.
+pub fn is_allowed_remote_url(url: &str, allowed_hosts: &[String]) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(parsed.scheme(), "http" | "https") || !parsed.username().is_empty() {
+        return false;
+    }
+    let Some(host) = parsed.host_str().map(|h| h.to_ascii_lowercase()) else {
+        return false;
+    };
+    allowed_hosts.is_empty()
+        || allowed_hosts
+            .iter()
+            .any(|allowed| host == *allowed || host.ends_with(&format!(".{allowed}")))
+}
+
+/// Direct play for `.strm` files: send the client to the remote URL.
+async fn redirect_to_remote_file(
+    state: &AppState,
+    media_file: &rustfin_db::repo::media_files::MediaFileRow,
+    user_id: &str,
+    role: &str,
+) -> Result<Response, AppError> {
+    if role != "admin" {
+        let item_id = rustfin_db::repo::items::get_item_id_by_file_id(&state.db, &media_file.id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .ok_or_else(|| ApiError::Forbidden("library access denied".into()))?;
+        let item = rustfin_db::repo::items::get_item(&state.db, &item_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+            .ok_or_else(|| ApiError::Forbidden("library access denied".into()))?;
+        let allowed = rustfin_db::repo::users::get_library_access(&state.db, user_id)
+            .await
+            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+        if !allowed.contains(&item.library_id) {
+            return Err(ApiError::Forbidden("library access denied".into()).into());
+        }
+    }
+
+    let url = media_file
+        .remote_url
+        .as_deref()
+        .filter(|url| is_allowed_remote_url(url, &strm_allowed_hosts()))
+        .ok_or_else(|| ApiError::Forbidden("remote media URL is not allowed".into()))?;
+
+    Ok(Response::builder()
+        .status(StatusCode::FOUND)
+        .header("Location", url)
+        .header("Cache-Control", "no-store")
+        .header("Referrer-Policy", "no-referrer")
+        .body(Body::empty())
+        .unwrap())
+}
+
 /// Verify that a file path is under one of the configured library paths.
 async fn validate_path_in_library(state: &AppState, file_path: &PathBuf) -> Result<(), AppError> {
-    let canonical = file_path
-        .canonicalize()
-        .map_err(|e| ApiError::Internal(format!("canonicalize error: {e}")))?;
+    let policy = crate::path_policy::LibrarySymlinks::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
     let libs = rustfin_db::repo::libraries::list_libraries(&state.db)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    for lib in &libs {
-        let paths = rustfin_db::repo::libraries::get_library_paths(&state.db, &lib.id)
-            .await
-            .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    let library_ids: Vec<String> = libs.into_iter().map(|lib| lib.id).collect();
+    let paths = rustfin_db::repo::libraries::get_library_paths_for(&state.db, &library_ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-        for lp in &paths {
-            let lib_root = PathBuf::from(&lp.path);
-            if let Ok(lib_canonical) = lib_root.canonicalize() {
-                if canonical.starts_with(&lib_canonical) {
-                    return Ok(());
-                }
-            }
+    for lp in paths.values().flatten() {
+        if crate::path_policy::is_within_root(file_path, std::path::Path::new(&lp.path), policy) {
+            return Ok(());
         }
     }
 
@@ -275,29 +839,35 @@ async fn validate_path_in_user_libraries(
     file_path: &PathBuf,
     user_id: &str,
 ) -> Result<(), AppError> {
-    let canonical = file_path
-        .canonicalize()
-        .map_err(|e| ApiError::Internal(format!("canonicalize error: {e}")))?;
-
-    let allowed_library_ids = rustfin_db::repo::users::get_library_access(&state.db, user_id)
+    let policy = crate::path_policy::LibrarySymlinks::load(&state.db)
         .await
         .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
+    let mut cache = crate::access_cache::AccessCache::new(&state.db);
+    let allowed_library_ids = cache
+        .library_access(user_id)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?
+        .to_vec();
+
     if allowed_library_ids.is_empty() {
         return Err(ApiError::Forbidden("library access denied".into()).into());
     }
+    cache
+        .load_paths(&allowed_library_ids)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
-    for library_id in allowed_library_ids {
-        let paths = rustfin_db::repo::libraries::get_library_paths(&state.db, &library_id)
+    for library_id in &allowed_library_ids {
+        let paths = cache
+            .library_paths(library_id)
             .await
             .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
 
         for lp in paths {
-            let lib_root = PathBuf::from(lp.path);
-            if let Ok(lib_canonical) = lib_root.canonicalize() {
-                if canonical.starts_with(&lib_canonical) {
-                    return Ok(());
-                }
+            if crate::path_policy::is_within_root(file_path, std::path::Path::new(&lp.path), policy)
+            {
+                return Ok(());
             }
         }
     }
@@ -311,6 +881,30 @@ async fn validate_path_in_user_libraries(
 mod tests {
     use super::*;
 
+    #[test]
+    fn remote_url_allowlist() {
+        let any: Vec<String> = Vec::new();
+        assert!(is_allowed_remote_url("https://cdn.example.com/a.mp4", &any));
+        assert!(!is_allowed_remote_url("file:///etc/passwd", &any));
+        assert!(!is_allowed_remote_url("ftp://cdn.example.com/a.mp4", &any));
+        assert!(!is_allowed_remote_url(
+            "http://user:pw@cdn.example.com/",
+            &any
+        ));
+
+        let allowed = vec!["example.com".to_string()];
+        assert!(is_allowed_remote_url("https://example.com/a.mp4", &allowed));
+        assert!(is_allowed_remote_url(
+            "https://cdn.example.com/a.mp4",
+            &allowed
+        ));
+        assert!(!is_allowed_remote_url(
+            "https://evilexample.com/a.mp4",
+            &allowed
+        ));
+        assert!(!is_allowed_remote_url("http://169.254.169.254/", &allowed));
+    }
+
     #[test]
     fn parse_range_basic() {
         let r = parse_range_header("bytes=0-999", 5000).unwrap();
@@ -345,6 +939,41 @@ mod tests {
         assert!(r.is_err());
     }
 
+    #[test]
+    fn parse_range_single_first_byte() {
+        let r = parse_range_header("bytes=0-0", 5000).unwrap();
+        assert_eq!(r.start, 0);
+        assert_eq!(r.end_inclusive, 0);
+    }
+
+    #[test]
+    fn parse_range_zero_length_suffix_rejected() {
+        assert!(parse_range_header("bytes=-0", 5000).is_err());
+    }
+
+    #[test]
+    fn parse_range_last_byte() {
+        for spec in ["bytes=4999-4999", "bytes=4999-", "bytes=-1"] {
+            let r = parse_range_header(spec, 5000).unwrap();
+            assert_eq!((r.start, r.end_inclusive), (4999, 4999), "{spec}");
+        }
+    }
+
+    #[test]
+    fn parse_range_suffix_covering_whole_file() {
+        for spec in ["bytes=-5000", "bytes=-9000"] {
+            let r = parse_range_header(spec, 5000).unwrap();
+            assert_eq!((r.start, r.end_inclusive), (0, 4999), "{spec}");
+        }
+    }
+
+    #[test]
+    fn parse_range_empty_file_rejected() {
+        for spec in ["bytes=0-0", "bytes=0-", "bytes=-1"] {
+            assert!(parse_range_header(spec, 0).is_err(), "{spec}");
+        }
+    }
+
     #[test]
     fn parse_range_multi_rejected() {
         let r = parse_range_header("bytes=0-100, 200-300", 5000);
@@ -366,4 +995,108 @@ mod tests {
             "video/webm"
         );
     }
+
+    #[tokio::test]
+    async fn reader_stream_honors_buffer_size() {
+        let data = vec![7u8; 10_000];
+        let chunks: Vec<usize> = reader_stream(std::io::Cursor::new(data), 4096)
+            .map(|chunk| chunk.unwrap().len())
+            .collect()
+            .await;
+        assert_eq!(chunks, vec![4096, 4096, 1808]);
+    }
+
+    #[test]
+    fn limiter_rejects_beyond_max_concurrent() {
+        let limiter = StreamLimiter::new(2, 1024);
+        let first = limiter.try_acquire().unwrap();
+        let _second = limiter.try_acquire().unwrap();
+        assert!(limiter.try_acquire().is_none());
+        drop(first);
+        assert!(limiter.try_acquire().is_some());
+    }
+
+    #[test]
+    fn user_slots_count_playbacks_and_free_on_drop() {
+        let limiter = StreamLimiter::new(8, 1024);
+        let first = limiter.try_acquire_for_user("u1", "a", Some(2)).unwrap();
+        let second = limiter.try_acquire_for_user("u1", "b", Some(2)).unwrap();
+        assert!(limiter.try_acquire_for_user("u1", "c", Some(2)).is_none());
+        assert_eq!(limiter.open_for_user("u1"), 2);
+        // Other users and unlimited accounts are unaffected.
+        assert!(limiter.try_acquire_for_user("u2", "c", Some(2)).is_some());
+        assert!(limiter.try_acquire_for_user("u1", "c", None).is_some());
+        drop(first);
+        assert!(limiter.try_acquire_for_user("u1", "c", Some(2)).is_some());
+        drop(second);
+        assert_eq!(limiter.open_for_user("u1"), 0);
+    }
+
+    #[test]
+    fn overlapping_reads_of_one_playback_share_a_slot() {
+        let limiter = StreamLimiter::new(8, 1024);
+        let reads: Vec<_> = (0..5)
+            .map(|_| {
+                limiter
+                    .try_acquire_for_user("u1", "file:f1", Some(1))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(limiter.open_for_user("u1"), 1);
+        assert!(
+            limiter
+                .try_acquire_for_user("u1", "file:f2", Some(1))
+                .is_none()
+        );
+        let mut reads = reads.into_iter();
+        drop(reads.next());
+        assert_eq!(limiter.open_for_user("u1"), 1);
+        drop(reads);
+        assert_eq!(limiter.open_for_user("u1"), 0);
+        assert!(
+            limiter
+                .try_acquire_for_user("u1", "file:f2", Some(1))
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn small_ranges_are_buffered_with_an_exact_length() {
+        use axum::body::HttpBody;
+
+        let path = std::env::temp_dir().join(format!("rf_inline_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, b"tiny clip bytes").unwrap();
+        let limiter = StreamLimiter::new(1, 4).with_inline_max_bytes(64);
+
+        let full = file_range_body(&path, 0, 15, (), &limiter).await.unwrap();
+        assert_eq!(full.size_hint().exact(), Some(15));
+        let bytes = axum::body::to_bytes(full, usize::MAX).await.unwrap();
+        assert_eq!(bytes.as_ref(), b"tiny clip bytes");
+
+        let ranged = file_range_body(&path, 5, 4, (), &limiter).await.unwrap();
+        assert_eq!(ranged.size_hint().exact(), Some(4));
+        let bytes = axum::body::to_bytes(ranged, usize::MAX).await.unwrap();
+        assert_eq!(bytes.as_ref(), b"clip");
+
+        // Above the threshold the file is streamed in `buffer_size` chunks.
+        let limiter = limiter.with_inline_max_bytes(8);
+        let streamed = file_range_body(&path, 0, 15, (), &limiter).await.unwrap();
+        assert_eq!(streamed.size_hint().exact(), None);
+        let bytes = axum::body::to_bytes(streamed, usize::MAX).await.unwrap();
+        assert_eq!(bytes.as_ref(), b"tiny clip bytes");
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn limited_body_holds_its_permit_until_dropped() {
+        let limiter = StreamLimiter::new(1, 1024);
+        let body = limited_body(
+            std::io::Cursor::new(vec![0u8; 16]),
+            limiter.try_acquire().unwrap(),
+            limiter.buffer_size(),
+        );
+        assert!(limiter.try_acquire().is_none());
+        drop(body);
+        assert!(limiter.try_acquire().is_some());
+    }
 }
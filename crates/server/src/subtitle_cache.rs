@@ -0,0 +1,108 @@
+//! Embedded subtitle tracks extracted to WebVTT and kept on disk.
+//!
+//! A track is cached at `cache_dir/subtitles/{file_id}_{index}.vtt` with the source
+//! file's mtime copied onto it; when the source's mtime no longer matches, the track
+//! is extracted again. Concurrent requests for one track share a single ffmpeg run.
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use rustfin_transcoder::TranscodeError;
+
+use crate::keyed_lock::KeyedLocks;
+
+/// Clients may reuse a track for an hour, then revalidate it with its ETag.
+pub const SUBTITLE_CACHE_CONTROL: &str = "private, max-age=3600";
+
+#[derive(Clone, Debug, Default)]
+pub struct SubtitleCache {
+    /// One lock per track being extracted, keyed by its cache path; later
+    /// requests wait on it and then find the finished file.
+    extracting: KeyedLocks<PathBuf>,
+}
+
+/// A cached track ready to serve.
+#[derive(Debug, Clone)]
+pub struct CachedSubtitle {
+    pub path: PathBuf,
+    pub etag: String,
+}
+
+impl SubtitleCache {
+    /// Where the track is cached, whether or not it exists yet.
+    pub fn track_path(cache_dir: &Path, file_id: &str, stream_index: u32) -> PathBuf {
+        cache_dir
+            .join("subtitles")
+            .join(format!("{file_id}_{stream_index}.vtt"))
+    }
+
+    /// The cached track for `source`, extracting it with ffmpeg first if it is
+    /// missing or was made from an older version of the source.
+    pub async fn get_or_extract(
+        &self,
+        cache_dir: &Path,
+        ffmpeg_path: &Path,
+        file_id: &str,
+        source: &Path,
+        stream_index: u32,
+    ) -> Result<CachedSubtitle, TranscodeError> {
+        let path = Self::track_path(cache_dir, file_id, stream_index);
+        let source_mtime = tokio::fs::metadata(source).await?.modified()?;
+
+        let track = self.extracting.share(&path);
+        let _guard = track.lock.lock().await;
+        match cached(&path, source_mtime).await {
+            Some(hit) => Ok(hit),
+            None => extract(ffmpeg_path, source, stream_index, &path, source_mtime).await,
+        }
+    }
+}
+
+/// The cached track at `path` if it was made from the source at `source_mtime`.
+async fn cached(path: &Path, source_mtime: SystemTime) -> Option<CachedSubtitle> {
+    let metadata = tokio::fs::metadata(path).await.ok()?;
+    (metadata.modified().ok()? == source_mtime).then(|| CachedSubtitle {
+        path: path.to_path_buf(),
+        etag: etag(metadata.len(), source_mtime),
+    })
+}
+
+async fn extract(
+    ffmpeg_path: &Path,
+    source: &Path,
+    stream_index: u32,
+    path: &Path,
+    source_mtime: SystemTime,
+) -> Result<CachedSubtitle, TranscodeError> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    rustfin_transcoder::subtitles::extract_vtt(ffmpeg_path, source, stream_index, path).await?;
+    let file = tokio::fs::File::options()
+        .write(true)
+        .open(path)
+        .await?
+        .into_std()
+        .await;
+    let len = tokio::task::spawn_blocking(move || {
+        file.set_modified(source_mtime)?;
+        Ok::<_, std::io::Error>(file.metadata()?.len())
+    })
+    .await
+    .map_err(std::io::Error::other)??;
+    Ok(CachedSubtitle {
+        path: path.to_path_buf(),
+        etag: etag(len, source_mtime),
+    })
+}
+
+fn etag(len: u64, mtime: SystemTime) -> String {
+    format!(
+        "\"{:x}-{:x}\"",
+        len,
+        mtime
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    )
+}
@@ -0,0 +1,147 @@
+//! The subtitle a player should turn on by default.
+//!
+//! Forced subtitles for the original-language audio come first. Otherwise, unless
+//! the user's subtitle mode says not to, the first full subtitle in their most
+//! preferred subtitle language is picked. When several tracks qualify, the user's
+//! [`SubtitleSource`] decides between embedded and sidecar copies.
+
+use rustfin_core::language::same_language;
+use rustfin_core::preferences::{SubtitleMode, SubtitleSource, UserPreferences};
+
+/// What [`default_subtitle`] needs to know about a listed subtitle.
+#[derive(Debug, Clone, Copy)]
+pub struct Candidate<'a> {
+    pub language: Option<&'a str>,
+    pub forced: bool,
+    /// Embedded in the container rather than a sidecar file.
+    pub embedded: bool,
+    /// Forced and in the language of the original audio the user plays
+    /// (see `rustfin_transcoder::tracks::auto_select_forced`).
+    pub auto_select: bool,
+}
+
+/// Index into `candidates` of the subtitle to turn on, if any. `audio_language` is
+/// the language of the audio track the player will start with.
+pub fn default_subtitle(
+    candidates: &[Candidate<'_>],
+    prefs: &UserPreferences,
+    audio_language: Option<&str>,
+) -> Option<usize> {
+    if prefs.subtitle_mode == SubtitleMode::None {
+        return None;
+    }
+    let forced = preferred_source(
+        candidates.iter().enumerate().filter(|(_, c)| c.auto_select),
+        prefs.subtitle_source,
+    );
+    if forced.is_some() || prefs.subtitle_mode == SubtitleMode::OnlyForced {
+        return forced;
+    }
+
+    let languages = &prefs.preferred_subtitle_languages;
+    // In default mode, audio the user already understands needs no subtitles.
+    let understood =
+        audio_language.is_some_and(|audio| languages.iter().any(|lang| same_language(audio, lang)));
+    if prefs.subtitle_mode == SubtitleMode::Default && understood {
+        return None;
+    }
+    languages.iter().find_map(|lang| {
+        preferred_source(
+            candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| !c.forced && c.language.is_some_and(|l| same_language(l, lang))),
+            prefs.subtitle_source,
+        )
+    })
+}
+
+/// The first of `matches` from the preferred source, else the first of any source.
+fn preferred_source<'a, 'c: 'a>(
+    matches: impl Iterator<Item = (usize, &'a Candidate<'c>)>,
+    source: SubtitleSource,
+) -> Option<usize> {
+    let matches: Vec<_> = matches.collect();
+    let preferred = matches.iter().find(|(_, c)| match source {
+        SubtitleSource::Any => true,
+        SubtitleSource::Embedded => c.embedded,
+        SubtitleSource::Sidecar => !c.embedded,
+    });
+    preferred.or(matches.first()).map(|(i, _)| *i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sub(language: &str, forced: bool, embedded: bool) -> Candidate<'_> {
+        Candidate {
+            language: Some(language),
+            forced,
+            embedded,
+            auto_select: false,
+        }
+    }
+
+    fn prefs(mode: SubtitleMode, source: SubtitleSource, languages: &[&str]) -> UserPreferences {
+        UserPreferences {
+            subtitle_mode: mode,
+            subtitle_source: source,
+            preferred_subtitle_languages: languages.iter().map(|l| l.to_string()).collect(),
+            ..UserPreferences::default()
+        }
+    }
+
+    #[test]
+    fn source_preference_breaks_ties_between_copies() {
+        let subs = [sub("en", false, false), sub("eng", false, true)];
+        let pick = |source| {
+            default_subtitle(
+                &subs,
+                &prefs(SubtitleMode::Always, source, &["en"]),
+                Some("eng"),
+            )
+        };
+        assert_eq!(pick(SubtitleSource::Any), Some(0));
+        assert_eq!(pick(SubtitleSource::Sidecar), Some(0));
+        assert_eq!(pick(SubtitleSource::Embedded), Some(1));
+    }
+
+    #[test]
+    fn forced_original_language_subtitles_win() {
+        let mut forced = sub("eng", true, true);
+        forced.auto_select = true;
+        let mut sidecar_forced = sub("en", true, false);
+        sidecar_forced.auto_select = true;
+        let subs = [sub("fr", false, false), sidecar_forced, forced];
+        let embedded = prefs(SubtitleMode::Always, SubtitleSource::Embedded, &["fr"]);
+        assert_eq!(default_subtitle(&subs, &embedded, Some("eng")), Some(2));
+        let only_forced = prefs(SubtitleMode::OnlyForced, SubtitleSource::Any, &["fr"]);
+        assert_eq!(default_subtitle(&subs, &only_forced, Some("eng")), Some(1));
+        let none = prefs(SubtitleMode::None, SubtitleSource::Any, &["fr"]);
+        assert_eq!(default_subtitle(&subs, &none, Some("eng")), None);
+    }
+
+    #[test]
+    fn preferred_languages_are_tried_in_order() {
+        let subs = [
+            sub("de", false, false),
+            sub("fr", true, false),
+            sub("fr", false, true),
+        ];
+        let always = prefs(SubtitleMode::Always, SubtitleSource::Any, &["fr", "de"]);
+        assert_eq!(default_subtitle(&subs, &always, Some("jpn")), Some(2));
+        let only_forced = prefs(SubtitleMode::OnlyForced, SubtitleSource::Any, &["fr"]);
+        assert_eq!(default_subtitle(&subs, &only_forced, Some("jpn")), None);
+    }
+
+    #[test]
+    fn default_mode_skips_audio_in_a_preferred_language() {
+        let subs = [sub("en", false, false)];
+        let default = prefs(SubtitleMode::Default, SubtitleSource::Any, &["en"]);
+        assert_eq!(default_subtitle(&subs, &default, Some("eng")), None);
+        assert_eq!(default_subtitle(&subs, &default, Some("jpn")), Some(0));
+        let always = prefs(SubtitleMode::Always, SubtitleSource::Any, &["en"]);
+        assert_eq!(default_subtitle(&subs, &always, Some("eng")), Some(0));
+    }
+}
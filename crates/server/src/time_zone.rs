@@ -0,0 +1,499 @@
+//! The server's `default_time_zone`, for formatting timestamps in responses.
+//!
+//! Zones are read from the system's compiled tz database (`TZDIR`, else
+//! `/usr/share/zoneinfo`). An unset or unknown zone means UTC. Instants after a
+//! zone file's last transition follow the POSIX TZ rule in its footer, which is
+//! all a "slim" file has for current daylight saving time. A version 1 file has
+//! no footer, so its last transition's offset is kept.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Datelike, FixedOffset, NaiveDate, SecondsFormat, TimeZone};
+use sqlx::SqlitePool;
+
+pub const DEFAULT_TIME_ZONE_KEY: &str = "default_time_zone";
+
+/// Parsed zones by name, so responses don't re-read zone files.
+static ZONES: Mutex<Option<HashMap<String, Arc<ServerTimeZone>>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerTimeZone {
+    /// Offset from UTC in seconds before the first transition.
+    initial_offset: i32,
+    /// `(unix seconds, offset)` pairs in ascending order.
+    transitions: Vec<(i64, i32)>,
+    /// The footer's rule for instants after the last transition.
+    rule: Option<PosixTz>,
+}
+
+impl ServerTimeZone {
+    pub fn utc() -> Self {
+        Self {
+            initial_offset: 0,
+            transitions: Vec::new(),
+            rule: None,
+        }
+    }
+
+    /// The zone described by a TZif file (RFC 8536), or `None` if it is malformed.
+    pub fn from_tzif(data: &[u8]) -> Option<Self> {
+        let header = TzifHeader::parse(data)?;
+        // Version 2+ files repeat the data with 64-bit times after the v1 block.
+        let (header, body, time_size) = if header.version >= b'2' {
+            let rest = data.get(header.block_len(4)..)?;
+            (TzifHeader::parse(rest)?, rest, 8)
+        } else {
+            (header, data, 4)
+        };
+        // Version 2+ files end with `\n<POSIX TZ string>\n`; an empty one means none.
+        let rule = if time_size == 8 {
+            let footer = body.get(header.block_len(8)..)?;
+            let footer = std::str::from_utf8(footer.strip_prefix(b"\n")?).ok()?;
+            let footer = footer.strip_suffix('\n').unwrap_or(footer);
+            if footer.is_empty() {
+                None
+            } else {
+                Some(PosixTz::parse(footer)?)
+            }
+        } else {
+            None
+        };
+        let mut pos = TzifHeader::LEN;
+        let times = body.get(pos..pos + header.timecnt * time_size)?;
+        pos += header.timecnt * time_size;
+        let indices = body.get(pos..pos + header.timecnt)?;
+        pos += header.timecnt;
+        let types = body.get(pos..pos + header.typecnt * 6)?;
+
+        let offsets: Vec<i32> = types
+            .chunks_exact(6)
+            .map(|t| i32::from_be_bytes([t[0], t[1], t[2], t[3]]))
+            .collect();
+        let initial_offset = *offsets.first()?;
+        let mut transitions = Vec::with_capacity(header.timecnt);
+        for (time, &index) in times.chunks_exact(time_size).zip(indices) {
+            let at = if time_size == 8 {
+                i64::from_be_bytes(time.try_into().ok()?)
+            } else {
+                i32::from_be_bytes(time.try_into().ok()?) as i64
+            };
+            transitions.push((at, *offsets.get(index as usize)?));
+        }
+        Some(Self {
+            initial_offset,
+            transitions,
+            rule,
+        })
+    }
+
+    /// The IANA zone `name` from the system tz database.
+    pub fn named(name: &str) -> Option<Arc<Self>> {
+        let valid = !name.is_empty()
+            && !name.starts_with('/')
+            && name
+                .split('/')
+                .all(|part| !part.is_empty() && part != "." && part != "..")
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '-' | '+'));
+        if !valid {
+            return None;
+        }
+        let mut zones = ZONES.lock().unwrap();
+        let zones = zones.get_or_insert_with(HashMap::new);
+        if let Some(zone) = zones.get(name) {
+            return Some(zone.clone());
+        }
+        let dir = std::env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/usr/share/zoneinfo"));
+        let zone = Arc::new(Self::from_tzif(&std::fs::read(dir.join(name)).ok()?)?);
+        zones.insert(name.to_string(), zone.clone());
+        Some(zone)
+    }
+
+    /// The configured server zone; UTC when unset or not in the tz database.
+    pub async fn load(pool: &SqlitePool) -> Result<Arc<Self>, sqlx::Error> {
+        let name = rustfin_db::repo::settings::get(pool, DEFAULT_TIME_ZONE_KEY)
+            .await?
+            .unwrap_or_default();
+        let name = name.trim();
+        if name.is_empty() {
+            return Ok(Arc::new(Self::utc()));
+        }
+        Ok(Self::named(name).unwrap_or_else(|| {
+            tracing::debug!(time_zone = %name, "unknown time zone; formatting dates in UTC");
+            Arc::new(Self::utc())
+        }))
+    }
+
+    /// Offset from UTC in seconds at unix time `ts`.
+    pub fn offset_at(&self, ts: i64) -> i32 {
+        let n = self.transitions.partition_point(|&(at, _)| at <= ts);
+        if n == self.transitions.len()
+            && let Some(rule) = &self.rule
+        {
+            return rule.offset_at(ts);
+        }
+        match n {
+            0 => self.initial_offset,
+            n => self.transitions[n - 1].1,
+        }
+    }
+
+    /// `ts` as an RFC 3339 timestamp in this zone, e.g. `2024-03-10T03:30:00-04:00`.
+    pub fn format(&self, ts: i64) -> String {
+        let offset = FixedOffset::east_opt(self.offset_at(ts))
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        match offset.timestamp_opt(ts, 0).single() {
+            Some(at) => at.to_rfc3339_opts(SecondsFormat::Secs, true),
+            None => ts.to_string(),
+        }
+    }
+}
+
+struct TzifHeader {
+    version: u8,
+    isutcnt: usize,
+    isstdcnt: usize,
+    leapcnt: usize,
+    timecnt: usize,
+    typecnt: usize,
+    charcnt: usize,
+}
+
+impl TzifHeader {
+    const LEN: usize = 44;
+
+    fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < Self::LEN || &data[..4] != b"TZif" {
+            return None;
+        }
+        let count = |i: usize| {
+            let at = 20 + i * 4;
+            u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]) as usize
+        };
+        Some(Self {
+            version: data[4],
+            isutcnt: count(0),
+            isstdcnt: count(1),
+            leapcnt: count(2),
+            timecnt: count(3),
+            typecnt: count(4),
+            charcnt: count(5),
+        })
+    }
+
+    /// Length of the header plus its data block when times are `time_size` bytes.
+    fn block_len(&self, time_size: usize) -> usize {
+        Self::LEN
+            + self.timecnt * (time_size + 1)
+            + self.typecnt * 6
+            + self.charcnt
+            + self.leapcnt * (time_size + 4)
+            + self.isstdcnt
+            + self.isutcnt
+    }
+}
+
+/// A POSIX TZ string such as `EST5EDT,M3.2.0,M11.1.0`, as found in TZif footers.
+#[derive(Debug, Clone, PartialEq)]
+struct PosixTz {
+    /// Standard time's offset east of UTC, in seconds.
+    std_offset: i32,
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    /// Daylight time's offset east of UTC, in seconds.
+    offset: i32,
+    /// When daylight time starts, in local standard time.
+    start: (RuleDay, i32),
+    /// When it ends, in local daylight time.
+    end: (RuleDay, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleDay {
+    /// `Jn`: day 1–365, never counting February 29.
+    Julian1(u16),
+    /// `n`: day 0–365, counting February 29.
+    Julian0(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` (5 is the last) of month `m`.
+    Month { month: u32, week: u32, weekday: u32 },
+}
+
+impl PosixTz {
+    fn parse(s: &str) -> Option<Self> {
+        let mut p = PosixParser(s.as_bytes());
+        p.name()?;
+        // POSIX offsets count hours west of UTC.
+        let std_offset = -p.time()?;
+        if p.0.is_empty() {
+            return Some(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+        p.name()?;
+        let offset = if p.0.first() == Some(&b',') {
+            std_offset + 3600
+        } else {
+            -p.time()?
+        };
+        // Without rules, the US rules of the day are implied.
+        let (start, end) = if p.0.is_empty() {
+            let start = RuleDay::Month {
+                month: 3,
+                week: 2,
+                weekday: 0,
+            };
+            let end = RuleDay::Month {
+                month: 11,
+                week: 1,
+                weekday: 0,
+            };
+            ((start, 7200), (end, 7200))
+        } else {
+            p.expect(b',')?;
+            let start = p.rule()?;
+            p.expect(b',')?;
+            (start, p.rule()?)
+        };
+        p.0.is_empty().then_some(Self {
+            std_offset,
+            dst: Some(DstRule { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, ts: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        let Some(year) = chrono::DateTime::from_timestamp(ts + self.std_offset as i64, 0)
+            .map(|local| local.year())
+        else {
+            return self.std_offset;
+        };
+        let at = |(day, time): (RuleDay, i32), offset: i32| {
+            day.date(year)
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|midnight| midnight.and_utc().timestamp() + (time - offset) as i64)
+        };
+        let (Some(start), Some(end)) = (at(dst.start, self.std_offset), at(dst.end, dst.offset))
+        else {
+            return self.std_offset;
+        };
+        // Southern zones start daylight time late in the year and end it early.
+        let in_dst = if start <= end {
+            start <= ts && ts < end
+        } else {
+            !(end <= ts && ts < start)
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+}
+
+impl RuleDay {
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        let jan1 = NaiveDate::from_ymd_opt(year, 1, 1)?;
+        match self {
+            Self::Julian1(n) => {
+                let leap_skip = (jan1.leap_year() && n >= 60) as u64;
+                jan1.checked_add_days(chrono::Days::new(u64::from(n) - 1 + leap_skip))
+            }
+            Self::Julian0(n) => jan1.checked_add_days(chrono::Days::new(u64::from(n))),
+            Self::Month {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let first_weekday = first.weekday().num_days_from_sunday();
+                let mut day = 1 + (weekday + 7 - first_weekday) % 7 + (week - 1) * 7;
+                let days_in_month = first
+                    .checked_add_months(chrono::Months::new(1))?
+                    .pred_opt()?
+                    .day();
+                while day > days_in_month {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day)
+            }
+        }
+    }
+}
+
+struct PosixParser<'a>(&'a [u8]);
+
+impl PosixParser<'_> {
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.0 = self.0.strip_prefix(&[byte])?;
+        Some(())
+    }
+
+    /// A zone abbreviation: `EST`, or `<+0330>` for one that isn't alphabetic.
+    fn name(&mut self) -> Option<()> {
+        let len = if self.0.first() == Some(&b'<') {
+            self.0.iter().position(|&b| b == b'>')? + 1
+        } else {
+            self.0
+                .iter()
+                .take_while(|b| b.is_ascii_alphabetic())
+                .count()
+        };
+        if len < 3 {
+            return None;
+        }
+        self.0 = &self.0[len..];
+        Some(())
+    }
+
+    fn number(&mut self) -> Option<i32> {
+        let len = self.0.iter().take_while(|b| b.is_ascii_digit()).count();
+        let (digits, rest) = self.0.split_at(len);
+        self.0 = rest;
+        std::str::from_utf8(digits).ok()?.parse().ok()
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds; TZif footers allow hours up to 167.
+    fn time(&mut self) -> Option<i32> {
+        let sign = match self.0.first() {
+            Some(b'-') => -1,
+            Some(b'+') => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            self.0 = &self.0[1..];
+        }
+        let mut secs = self.number()?.checked_mul(3600)?;
+        for scale in [60, 1] {
+            if self.expect(b':').is_none() {
+                break;
+            }
+            secs += self.number()? * scale;
+        }
+        Some(if sign == -1 { -secs } else { secs })
+    }
+
+    /// `date[/time]`, where the time of day defaults to 02:00.
+    fn rule(&mut self) -> Option<(RuleDay, i32)> {
+        let day = match self.0.first()? {
+            b'J' => {
+                self.0 = &self.0[1..];
+                let n = u16::try_from(self.number()?).ok()?;
+                (1..=365).contains(&n).then_some(RuleDay::Julian1(n))?
+            }
+            b'M' => {
+                self.0 = &self.0[1..];
+                let month = u32::try_from(self.number()?).ok()?;
+                self.expect(b'.')?;
+                let week = u32::try_from(self.number()?).ok()?;
+                self.expect(b'.')?;
+                let weekday = u32::try_from(self.number()?).ok()?;
+                ((1..=12).contains(&month) && (1..=5).contains(&week) && weekday <= 6).then_some(
+                    RuleDay::Month {
+                        month,
+                        week,
+                        weekday,
+                    },
+                )?
+            }
+            _ => {
+                let n = u16::try_from(self.number()?).ok()?;
+                (n <= 365).then_some(RuleDay::Julian0(n))?
+            }
+        };
+        let time = if self.expect(b'/').is_some() {
+            self.time()?
+        } else {
+            7200
+        };
+        Some((day, time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A v1 TZif file: UTC-5 ("EST"), then UTC-4 ("EDT") from `dst_at`.
+    fn tzif_v1(dst_at: i32) -> Vec<u8> {
+        let mut data = b"TZif\0".to_vec();
+        data.extend([0u8; 15]);
+        for count in [0u32, 0, 0, 1, 2, 8] {
+            data.extend(count.to_be_bytes());
+        }
+        data.extend(dst_at.to_be_bytes());
+        data.push(1);
+        data.extend((-5 * 3600i32).to_be_bytes());
+        data.extend([0, 0]);
+        data.extend((-4 * 3600i32).to_be_bytes());
+        data.extend([1, 4]);
+        data.extend(b"EST\0EDT\0");
+        data
+    }
+
+    #[test]
+    fn tzif_offsets_follow_transitions() {
+        let zone = ServerTimeZone::from_tzif(&tzif_v1(1_000_000)).unwrap();
+        assert_eq!(zone.offset_at(0), -5 * 3600);
+        assert_eq!(zone.offset_at(999_999), -5 * 3600);
+        assert_eq!(zone.offset_at(1_000_000), -4 * 3600);
+        assert_eq!(zone.format(1_000_000), "1970-01-12T09:46:40-04:00");
+        assert!(ServerTimeZone::from_tzif(b"not a zone file").is_none());
+    }
+
+    /// America/New_York as `zic -b slim` writes it: transitions stop in 2007 and
+    /// the footer carries the current rule.
+    const SLIM_NEW_YORK: &[u8] = include_bytes!("../tests/fixtures/zoneinfo/America/New_York");
+
+    #[test]
+    fn slim_zone_follows_its_footer_after_the_last_transition() {
+        let zone = ServerTimeZone::from_tzif(SLIM_NEW_YORK).unwrap();
+        assert_eq!(zone.format(1_700_000_000), "2023-11-14T17:13:20-05:00");
+        assert_eq!(zone.format(1_690_000_000), "2023-07-22T00:26:40-04:00");
+        // 2024-03-10 02:00 EST and 2024-11-03 02:00 EDT.
+        assert_eq!(zone.offset_at(1_710_054_000 - 1), -5 * 3600);
+        assert_eq!(zone.offset_at(1_710_054_000), -4 * 3600);
+        assert_eq!(zone.offset_at(1_730_613_600 - 1), -4 * 3600);
+        assert_eq!(zone.offset_at(1_730_613_600), -5 * 3600);
+        // Before the footer takes over, the transitions still apply.
+        assert_eq!(zone.format(1_000_000_000), "2001-09-08T21:46:40-04:00");
+    }
+
+    #[test]
+    fn posix_rules_cover_southern_and_fixed_zones() {
+        // Sydney: daylight time from the first Sunday of October to the first of April.
+        let sydney = PosixTz::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset_at(1_700_000_000), 11 * 3600);
+        assert_eq!(sydney.offset_at(1_690_000_000), 10 * 3600);
+
+        let tehran = PosixTz::parse("<+0330>-3:30").unwrap();
+        assert_eq!(tehran.offset_at(1_700_000_000), 3 * 3600 + 1800);
+
+        let julian = PosixTz::parse("XST3XDT,J60/0,300").unwrap();
+        assert_eq!(julian.offset_at(1_690_000_000), -2 * 3600);
+
+        assert!(PosixTz::parse("E5").is_none());
+        assert!(PosixTz::parse("EST5EDT,M13.1.0,M11.1.0").is_none());
+    }
+
+    #[test]
+    fn utc_formats_with_z() {
+        assert_eq!(
+            ServerTimeZone::utc().format(1_700_000_000),
+            "2023-11-14T22:13:20Z"
+        );
+    }
+
+    #[test]
+    fn zone_names_cannot_leave_the_database() {
+        assert!(ServerTimeZone::named("../etc/passwd").is_none());
+        assert!(ServerTimeZone::named("/etc/localtime").is_none());
+        assert!(ServerTimeZone::named("Europe/../../etc").is_none());
+    }
+}
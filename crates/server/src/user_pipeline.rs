@@ -5,15 +5,18 @@ use std::collections::HashSet;
 use std::sync::LazyLock;
 
 use crate::error::AppError;
+use crate::password_policy::PasswordPolicy;
 use crate::state::AppState;
 
-pub const MIN_PASSWORD_LEN: usize = 12;
-
 static USERNAME_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^[a-zA-Z0-9._-]{3,32}$").unwrap());
 
-/// Validate username and password. Returns field-level errors or `None`.
-pub fn validate_username_password(username: &str, password: &str) -> Option<Value> {
+/// Validate username and password against `policy`. Returns field-level errors or `None`.
+pub fn validate_username_password(
+    username: &str,
+    password: &str,
+    policy: &PasswordPolicy,
+) -> Option<Value> {
     let mut fields = serde_json::Map::new();
 
     if username.len() < 3 || username.len() > 32 || !USERNAME_RE.is_match(username) {
@@ -23,13 +26,9 @@ pub fn validate_username_password(username: &str, password: &str) -> Option<Valu
         );
     }
 
-    if password.len() < MIN_PASSWORD_LEN || password.len() > 1024 {
-        fields.insert(
-            "password".to_string(),
-            json!([format!(
-                "must be between {MIN_PASSWORD_LEN} and 1024 characters"
-            )]),
-        );
+    let password_problems = policy.check(password);
+    if !password_problems.is_empty() {
+        fields.insert("password".to_string(), json!(password_problems));
     }
 
     if fields.is_empty() {
@@ -83,7 +82,10 @@ pub async fn create_user_with_access(
     role: &str,
     library_ids: &[String],
 ) -> Result<String, AppError> {
-    if let Some(fields) = validate_username_password(username, password) {
+    let policy = PasswordPolicy::load(&state.db)
+        .await
+        .map_err(|e| ApiError::Internal(format!("db error: {e}")))?;
+    if let Some(fields) = validate_username_password(username, password, &policy) {
         return Err(ApiError::validation(fields).into());
     }
 
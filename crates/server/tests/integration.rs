@@ -1,13 +1,18 @@
 use axum_test::TestServer;
+use rustfin_server::job_queue::JobQueue;
+use rustfin_server::keyed_lock::KeyedLocks;
 use rustfin_server::routes::build_router;
-use rustfin_server::state::AppState;
+use rustfin_server::state::{AppState, EventBus, Readiness, RequestLimits};
+use rustfin_server::streaming::StreamLimiter;
+use rustfin_server::subtitle_cache::SubtitleCache;
+use rustfin_transcoder::tools::MediaTools;
 use serde_json::{Value, json};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
 /// Create a test server with an in-memory SQLite database.
 async fn test_app() -> TestServer {
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+    let pool = test_pool().await;
 
     // Ensure setup defaults exist
     rustfin_db::repo::settings::insert_defaults(&pool)
@@ -15,9 +20,7 @@ async fn test_app() -> TestServer {
         .unwrap();
 
     // Bootstrap admin user and mark setup as completed for existing tests
-    rustfin_db::repo::users::create_user(&pool, "admin", "admin_secure_123", "admin")
-        .await
-        .unwrap();
+    admin_user(&pool).await;
     rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
         .await
         .unwrap();
@@ -33,19 +36,138 @@ async fn test_app() -> TestServer {
     let transcoder =
         std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
 
-    let (events_tx, _) = tokio::sync::broadcast::channel(64);
     let state = AppState {
         db: pool,
         jwt_secret: "test-secret-key".to_string(),
         transcoder,
         cache_dir: std::env::temp_dir().join(format!("rf_cache_{}", std::process::id())),
-        events: events_tx,
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
     };
 
     let app = build_router(state);
     TestServer::new(app).unwrap()
 }
 
+/// Create a test server around an already-populated pool (admin user must exist).
+fn test_server_for_pool(pool: sqlx::SqlitePool) -> TestServer {
+    TestServer::new(build_router(test_state_for_pool(pool))).unwrap()
+}
+
+/// App state around an already-populated pool, for driving background tasks directly.
+fn test_state_for_pool(pool: sqlx::SqlitePool) -> AppState {
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        transcode_dir: std::env::temp_dir().join(format!("rf_test_{}", std::process::id())),
+        max_concurrent: 2,
+        ..Default::default()
+    };
+    let transcoder =
+        std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
+
+    AppState {
+        db: pool,
+        jwt_secret: "test-secret-key".to_string(),
+        transcoder,
+        cache_dir: std::env::temp_dir().join(format!("rf_cache_{}", std::process::id())),
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
+    }
+}
+
+/// A fresh in-memory database with every migration applied.
+async fn test_pool() -> sqlx::SqlitePool {
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    pool
+}
+
+/// Create the `admin` / `admin_secure_123` account most tests log in with.
+async fn admin_user(pool: &sqlx::SqlitePool) -> String {
+    rustfin_db::repo::users::create_user(pool, "admin", "admin_secure_123", "admin")
+        .await
+        .unwrap()
+}
+
+/// Every job, newest first.
+async fn all_jobs(pool: &sqlx::SqlitePool) -> Vec<rustfin_db::repo::jobs::JobRow> {
+    let filter = rustfin_db::repo::jobs::JobFilter::default();
+    rustfin_db::repo::jobs::list_jobs_page(pool, &filter, i64::MAX, 0)
+        .await
+        .unwrap()
+        .0
+}
+
+/// Create a library over `root` and scan it.
+async fn scanned_library(
+    pool: &sqlx::SqlitePool,
+    name: &str,
+    kind: &str,
+    root: &Path,
+) -> rustfin_db::repo::libraries::LibraryRow {
+    let lib = rustfin_db::repo::libraries::create_library(
+        pool,
+        name,
+        kind,
+        &[root.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_scanner::scan::run_library_scan(pool, &lib.id, kind)
+        .await
+        .unwrap();
+    lib
+}
+
+/// A uniquely named directory under the system temp dir, removed with everything
+/// in it when dropped, so a failing test doesn't leave it behind.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(prefix: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4())))
+    }
+
+    /// Write a placeholder file at `rel`, creating the directories above it.
+    fn touch(&self, rel: impl AsRef<Path>) -> PathBuf {
+        let path = self.0.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"fake").unwrap();
+        path
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = PathBuf;
+
+    fn deref(&self) -> &PathBuf {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
 /// Helper: login and return JWT token.
 async fn login(server: &TestServer, username: &str, password: &str) -> String {
     let resp = server
@@ -78,7 +200,8 @@ for ((i=1; i<=$#; i++)); do
 done
 
 mkdir -p "$(dirname "$out")"
-cat > "$out" <<'EOF'
+# Write then rename, as ffmpeg's HLS muxer does, so readers never see a partial playlist.
+cat > "$out.tmp" <<'EOF'
 #EXTM3U
 #EXT-X-VERSION:3
 #EXT-X-TARGETDURATION:4
@@ -86,6 +209,7 @@ cat > "$out" <<'EOF'
 #EXTINF:4.0,
 seg_00000.ts
 EOF
+mv "$out.tmp" "$out"
 
 if [[ -n "$seg_pattern" ]]; then
   seg="${seg_pattern//%05d/00000}"
@@ -158,14 +282,35 @@ Start-Sleep -Seconds 30
 }
 
 async fn test_app_with_fake_ffmpeg() -> TestServer {
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+    test_app_with_fake_ffmpeg_and_ffprobe(PathBuf::from("ffprobe")).await
+}
+
+async fn test_app_with_fake_ffmpeg_and_ffprobe(ffprobe_path: PathBuf) -> TestServer {
+    test_app_with_transcoder_config(rustfin_transcoder::TranscoderConfig {
+        ffprobe_path,
+        ..fake_ffmpeg_config()
+    })
+    .await
+}
+
+fn fake_ffmpeg_config() -> rustfin_transcoder::TranscoderConfig {
+    rustfin_transcoder::TranscoderConfig {
+        ffmpeg_path: create_fake_ffmpeg_script(),
+        ffprobe_path: PathBuf::from("ffprobe"),
+        transcode_dir: std::env::temp_dir().join(format!("rf_test_hls_{}", uuid::Uuid::new_v4())),
+        max_concurrent: 2,
+        ..Default::default()
+    }
+}
+
+async fn test_app_with_transcoder_config(
+    tc_config: rustfin_transcoder::TranscoderConfig,
+) -> TestServer {
+    let pool = test_pool().await;
     rustfin_db::repo::settings::insert_defaults(&pool)
         .await
         .unwrap();
-    rustfin_db::repo::users::create_user(&pool, "admin", "admin_secure_123", "admin")
-        .await
-        .unwrap();
+    admin_user(&pool).await;
     rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
         .await
         .unwrap();
@@ -173,30 +318,79 @@ async fn test_app_with_fake_ffmpeg() -> TestServer {
         .await
         .unwrap();
 
-    let fake_ffmpeg = create_fake_ffmpeg_script();
-    let tc_config = rustfin_transcoder::TranscoderConfig {
-        ffmpeg_path: fake_ffmpeg,
-        ffprobe_path: PathBuf::from("ffprobe"),
-        transcode_dir: std::env::temp_dir().join(format!("rf_test_hls_{}", uuid::Uuid::new_v4())),
-        max_concurrent: 2,
-        ..Default::default()
-    };
     let transcoder =
         std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
 
-    let (events_tx, _) = tokio::sync::broadcast::channel(64);
     let state = AppState {
         db: pool,
         jwt_secret: "test-secret-key".to_string(),
         transcoder,
         cache_dir: std::env::temp_dir().join(format!("rf_cache_{}", std::process::id())),
-        events: events_tx,
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
     };
 
     let app = build_router(state);
     TestServer::new(app).unwrap()
 }
 
+/// Fake ffprobe that reports `probe` (ffprobe's `-print_format json` shape) for any file.
+#[cfg(unix)]
+fn create_fake_ffprobe_script(probe: &Value) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rf_fake_ffprobe_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("probe.json"), probe.to_string()).unwrap();
+
+    let script = dir.join("fake_ffprobe.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/usr/bin/env bash\ncat '{}'\n",
+            dir.join("probe.json").display()
+        ),
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = std::fs::metadata(&script).unwrap().permissions();
+    perms.set_mode(0o755);
+    std::fs::set_permissions(&script, perms).unwrap();
+    script
+}
+
+/// A stand-in for ffmpeg re-encoding a still image: copies the `-i` input to the
+/// output.
+fn create_copying_ffmpeg_script() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rf_copy_ffmpeg_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("fake_ffmpeg.sh");
+    std::fs::write(
+        &script,
+        r#"#!/usr/bin/env bash
+set -euo pipefail
+out="${@: -1}"
+for ((i=1; i<=$#; i++)); do
+  if [[ "${!i}" == "-i" ]]; then
+    j=$((i+1))
+    input="${!j}"
+  fi
+done
+cp "$input" "$out"
+"#,
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    let mut perms = std::fs::metadata(&script).unwrap().permissions();
+    perms.set_mode(0o755);
+    std::fs::set_permissions(&script, perms).unwrap();
+    script
+}
+
 #[tokio::test]
 async fn health_endpoint_returns_ok() {
     let server = test_app().await;
@@ -207,320 +401,6917 @@ async fn health_endpoint_returns_ok() {
 }
 
 #[tokio::test]
-async fn login_with_valid_credentials() {
-    let server = test_app().await;
-    let resp = server
-        .post("/api/v1/auth/login")
-        .json(&json!({ "username": "admin", "password": "admin_secure_123" }))
-        .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert!(body["token"].as_str().is_some());
-    assert_eq!(body["username"], "admin");
-    assert_eq!(body["role"], "admin");
-}
-
-#[tokio::test]
-async fn login_with_invalid_credentials() {
-    let server = test_app().await;
-    let resp = server
-        .post("/api/v1/auth/login")
-        .json(&json!({ "username": "admin", "password": "wrong" }))
-        .await;
-    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
-    let body: Value = resp.json();
-    assert_eq!(body["error"]["code"], "unauthorized");
-}
+async fn missing_ffmpeg_makes_sessions_and_probing_unavailable() {
+    let tmp = TempDir::new("rf_no_ffmpeg");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Heat (1995).mkv");
 
-#[tokio::test]
-async fn users_me_requires_auth() {
-    let server = test_app().await;
-    let resp = server.get("/api/v1/users/me").await;
-    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
-}
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &item_id)
+        .await
+        .unwrap()
+        .unwrap();
 
-#[tokio::test]
-async fn users_me_with_valid_token() {
-    let server = test_app().await;
+    let bogus = std::path::Path::new("/nonexistent/rustfin-test/ffmpeg");
+    let state = AppState {
+        media_tools: MediaTools::detect(bogus, bogus).await,
+        jobs: JobQueue::default(),
+        ..test_state_for_pool(pool)
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
     let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
 
-    let resp = server
-        .get("/api/v1/users/me")
-        .add_header(
-            axum::http::header::AUTHORIZATION,
-            format!("Bearer {token}")
-                .parse::<axum::http::HeaderValue>()
-                .unwrap(),
-        )
-        .await;
+    let resp = server.get("/health").await;
     resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["username"], "admin");
-    assert_eq!(body["role"], "admin");
-}
-
-#[tokio::test]
-async fn preferences_crud() {
-    let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let auth_header = format!("Bearer {token}");
+    assert_eq!(resp.json::<Value>(), json!({ "status": "degraded" }));
 
-    // GET default prefs
     let resp = server
-        .get("/api/v1/users/me/preferences")
-        .add_header(
-            axum::http::header::AUTHORIZATION,
-            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
-        )
+        .get("/api/v1/system/info")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body, json!({}));
+    assert_eq!(body["transcoding"]["ffmpeg"]["status"], "missing");
+    assert!(!resp.text().contains("nonexistent"));
+    server
+        .get("/api/v1/system/info")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
 
-    // PATCH prefs
-    let new_prefs = json!({ "show_missing_episodes": true, "theme": "dark" });
     let resp = server
-        .patch("/api/v1/users/me/preferences")
-        .add_header(
-            axum::http::header::AUTHORIZATION,
-            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
-        )
-        .json(&new_prefs)
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "file_id": file_id }))
         .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["show_missing_episodes"], true);
-    assert_eq!(body["theme"], "dark");
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert!(
+        resp.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("transcoding unavailable: ffmpeg not found")
+    );
 
-    // GET updated prefs
     let resp = server
-        .get("/api/v1/users/me/preferences")
-        .add_header(
-            axum::http::header::AUTHORIZATION,
-            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
-        )
+        .get(&format!("/api/v1/playback/info/{file_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["show_missing_episodes"], true);
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert!(
+        resp.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap()
+            .ends_with("media probing unavailable: ffprobe not found")
+    );
 }
 
-#[tokio::test]
-async fn migrations_are_idempotent() {
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    // Run migrations twice — should not error
-    rustfin_db::migrate::run(&pool).await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+/// Fake ffmpeg that counts its runs in `runs` and writes a small WebVTT file to its
+/// last argument, slowly enough for concurrent requests to overlap.
+#[cfg(unix)]
+fn create_counting_vtt_ffmpeg(runs: &std::path::Path) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rf_vtt_ffmpeg_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("ffmpeg.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/usr/bin/env bash\necho run >> '{}'\nsleep 0.3\n\
+             printf 'WEBVTT\\n\\n00:00.000 --> 00:01.000\\nHello\\n' > \"${{@: -1}}\"\n",
+            runs.display()
+        ),
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script
 }
 
-// ---------------------------------------------------------------------------
-// Library tests
-// ---------------------------------------------------------------------------
-
-fn auth_hdr(token: &str) -> (axum::http::HeaderName, axum::http::HeaderValue) {
-    (
-        axum::http::header::AUTHORIZATION,
-        format!("Bearer {token}")
-            .parse::<axum::http::HeaderValue>()
-            .unwrap(),
+/// Fake ffmpeg that writes `attachment N` for each `-dump_attachment:N path` and
+/// `cover` for an `image2` output.
+#[cfg(unix)]
+fn create_attachment_ffmpeg() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rf_attach_ffmpeg_{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let script = dir.join("ffmpeg.sh");
+    std::fs::write(
+        &script,
+        r#"#!/usr/bin/env bash
+args=("$@")
+for ((i=0; i<${#args[@]}; i++)); do
+  case "${args[$i]}" in
+    -dump_attachment:*) printf 'attachment %s' "${args[$i]#-dump_attachment:}" > "${args[$((i+1))]}" ;;
+    image2) printf 'cover' > "${args[$((i+1))]}" ;;
+  esac
+done
+"#,
     )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    script
 }
 
+#[cfg(unix)]
 #[tokio::test]
-async fn create_library_requires_admin() {
-    let server = test_app().await;
-    // Test that unauthenticated requests fail
-    let resp = server
-        .post("/api/v1/libraries")
-        .json(&json!({ "name": "Movies", "kind": "movies", "paths": ["/media/movies"] }))
-        .await;
-    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
-}
+async fn embedded_fonts_are_served_and_cover_art_backs_up_the_poster() {
+    let tmp = TempDir::new("rf_attach");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Fansub (2010).mkv");
 
-#[tokio::test]
-async fn library_crud_flow() {
-    let server = test_app().await;
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &item_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "60.0" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720 },
+            { "index": 1, "codec_type": "subtitle", "codec_name": "ass", "tags": { "language": "eng" } },
+            { "index": 2, "codec_type": "attachment", "codec_name": "ttf",
+              "tags": { "filename": "Fansub Font.ttf", "mimetype": "application/x-truetype-font" } },
+            { "index": 3, "codec_type": "video", "codec_name": "mjpeg", "disposition": { "attached_pic": 1 } }
+        ]
+    }));
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        ffmpeg_path: create_attachment_ffmpeg(),
+        ffprobe_path: ffprobe.clone(),
+        transcode_dir: tmp.join("transcode"),
+        ..Default::default()
+    };
+    let cache_dir = tmp.join("cache");
+    let state = AppState {
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            tc_config,
+        )),
+        cache_dir: cache_dir.clone(),
+        ..test_state_for_pool(pool.clone())
+    };
+    let server = TestServer::new(build_router(state.clone())).unwrap();
     let token = login(&server, "admin", "admin_secure_123").await;
     let (hdr_name, hdr_val) = auth_hdr(&token);
 
-    let tmp = std::env::temp_dir().join(format!("rf_test_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp).unwrap();
+    let attachments: Value = server
+        .get(&format!("/api/v1/items/{item_id}/attachments"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(
+        attachments,
+        json!([{
+            "index": 2,
+            "filename": "Fansub Font.ttf",
+            "mime_type": "application/x-truetype-font",
+            "kind": "font",
+            "url": format!("/stream/file/{file_id}/attachments/2"),
+        }])
+    );
 
-    // Create library
     let resp = server
-        .post("/api/v1/libraries")
+        .get(&format!("/stream/file/{file_id}/attachments/2"))
         .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({ "name": "Movies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let body: Value = resp.json();
-    assert_eq!(body["name"], "Movies");
-    assert_eq!(body["kind"], "movies");
-    assert_eq!(body["item_count"], 0);
-    assert_eq!(body["paths"][0]["path"], tmp.to_str().unwrap());
-    let lib_id = body["id"].as_str().unwrap().to_string();
-
-    // List libraries
+    resp.assert_status_ok();
+    assert_eq!(resp.text(), "attachment 2");
+    assert_eq!(
+        resp.header("content-type").to_str().unwrap(),
+        "application/x-truetype-font"
+    );
+    assert!(
+        cache_dir
+            .join(format!("attachments/{file_id}/2_Fansub_Font.ttf"))
+            .is_file()
+    );
+    // Later requests are served from the extracted list, without probing again.
+    let probe_json = ffprobe.with_file_name("probe.json");
+    std::fs::rename(&probe_json, tmp.join("probe.json")).unwrap();
     let resp = server
-        .get("/api/v1/libraries")
+        .get(&format!("/stream/file/{file_id}/attachments/2"))
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(resp.text(), "attachment 2");
+    std::fs::rename(tmp.join("probe.json"), &probe_json).unwrap();
+    // The cover is not an attachment to download; the subtitle track isn't either.
+    server
+        .get(&format!("/stream/file/{file_id}/attachments/3"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
 
-    // Get single library
-    let resp = server
-        .get(&format!("/api/v1/libraries/{lib_id}"))
+    // No poster anywhere: the embedded cover is used.
+    let poster = server
+        .get(&format!("/api/v1/items/{item_id}/images/poster"))
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["name"], "Movies");
+    poster.assert_status(axum::http::StatusCode::NOT_FOUND);
+    let applied = rustfin_server::attachments::apply_embedded_cover_art(&state, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(applied, 1);
+    let poster = server
+        .get(&format!("/api/v1/items/{item_id}/images/poster"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    poster.assert_status_ok();
+    assert_eq!(poster.as_bytes().as_ref(), b"cover");
+    // Items that have a poster are left alone.
+    assert_eq!(
+        rustfin_server::attachments::apply_embedded_cover_art(&state, &lib.id)
+            .await
+            .unwrap(),
+        0
+    );
+}
 
-    // Update library name
+#[cfg(unix)]
+#[tokio::test]
+async fn embedded_subtitles_are_extracted_once_and_cached_until_the_source_changes() {
+    let tmp = TempDir::new("rf_sub_cache");
+    std::fs::create_dir_all(&tmp).unwrap();
+    let media = tmp.join("Amelie (2001).mkv");
+    std::fs::write(&media, b"fake").unwrap();
+    let runs = tmp.join("ffmpeg_runs");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &item_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        ffmpeg_path: create_counting_vtt_ffmpeg(&runs),
+        transcode_dir: tmp.join("transcode"),
+        ..Default::default()
+    };
+    let cache_dir = tmp.join("cache");
+    let state = AppState {
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            tc_config,
+        )),
+        cache_dir: cache_dir.clone(),
+        ..test_state_for_pool(pool)
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let url = format!("/stream/file/{file_id}/subtitles/2");
+    let run_count = || {
+        std::fs::read_to_string(&runs)
+            .unwrap_or_default()
+            .lines()
+            .count()
+    };
+
+    // Two requests at once share one extraction.
+    let (first, second) = tokio::join!(
+        server
+            .get(&url)
+            .add_header(hdr_name.clone(), hdr_val.clone()),
+        server
+            .get(&url)
+            .add_header(hdr_name.clone(), hdr_val.clone()),
+    );
+    first.assert_status_ok();
+    second.assert_status_ok();
+    assert_eq!(run_count(), 1);
+    assert!(first.text().starts_with("WEBVTT"));
+    assert_eq!(
+        first.header("content-type").to_str().unwrap(),
+        "text/vtt; charset=utf-8"
+    );
+    assert!(
+        first
+            .header("cache-control")
+            .to_str()
+            .unwrap()
+            .contains("max-age")
+    );
+    assert!(
+        cache_dir
+            .join(format!("subtitles/{file_id}_2.vtt"))
+            .is_file()
+    );
+    let etag = first.header("etag").to_str().unwrap().to_string();
+
+    // Repeat requests come from the cache, and a matching ETag gets a 304.
     let resp = server
-        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .get(&url)
         .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({ "name": "My Movies" }))
         .await;
     resp.assert_status_ok();
-
-    // Verify update
+    assert_eq!(resp.header("etag").to_str().unwrap(), etag);
     let resp = server
-        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .get(&url)
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(
+            axum::http::header::IF_NONE_MATCH,
+            axum::http::HeaderValue::from_str(&etag).unwrap(),
+        )
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_MODIFIED);
+    assert_eq!(run_count(), 1);
+
+    // A source file with a new mtime is extracted again.
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(3600);
+    std::fs::File::options()
+        .write(true)
+        .open(&media)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    let resp = server
+        .get(&url)
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
+    assert_eq!(run_count(), 2);
+    assert_ne!(resp.header("etag").to_str().unwrap(), etag);
+
+    // Like direct play, the track needs a token.
+    server
+        .get(&url)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn readiness_follows_startup_gate_and_liveness_does_not() {
+    let pool = test_pool().await;
+    let state = test_state_for_pool(pool);
+    let ready = state.ready.clone();
+    let server = TestServer::new(build_router(state)).unwrap();
+
+    let resp = server.get("/health/ready").await;
+    resp.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
     let body: Value = resp.json();
-    assert_eq!(body["name"], "My Movies");
+    assert_eq!(body["status"], "not_ready");
+    assert_eq!(body["checks"]["startup"], false);
+    assert_eq!(body["checks"]["database"], true);
+    assert_eq!(body["checks"]["transcode_dir"], true);
+
+    let resp = server.get("/health/live").await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["status"], "ok");
 
-    std::fs::remove_dir_all(&tmp).ok();
+    ready.mark_ready();
+    let resp = server.get("/health/ready").await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["status"], "ready");
+
+    let resp = server.get("/health/live").await;
+    resp.assert_status_ok();
 }
 
 #[tokio::test]
-async fn create_library_validates_kind() {
+async fn unknown_routes_return_json_error_envelope() {
     let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
 
+    let resp = server.get("/api/v1/nope").await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+    assert_eq!(resp.header("content-type"), "application/json");
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "not_found");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("/api/v1/nope")
+    );
+
+    // Known path, wrong method.
+    let resp = server.delete("/api/v1/auth/login").await;
+    resp.assert_status(axum::http::StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(resp.header("content-type"), "application/json");
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "method_not_allowed");
+}
+
+#[tokio::test]
+async fn openapi_document_is_served_without_auth() {
+    let server = test_app().await;
+
+    let resp = server.get("/api/v1/openapi.json").await;
+    resp.assert_status_ok();
+    assert_eq!(resp.header("content-type"), "application/json");
+    let doc: Value = resp.json();
+    assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+
+    let paths = &doc["paths"];
+    let login = &paths["/api/v1/auth/login"]["post"];
+    assert_eq!(
+        login["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/LoginRequest"
+    );
+    assert!(paths["/api/v1/libraries"]["get"].is_object());
+    assert!(paths["/api/v1/libraries"]["post"]["responses"]["201"].is_object());
+    assert!(doc["components"]["schemas"]["ErrorEnvelope"].is_object());
+}
+
+#[tokio::test]
+async fn login_with_valid_credentials() {
+    let server = test_app().await;
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(hdr_name, hdr_val)
-        .json(&json!({ "name": "Bad", "kind": "invalid", "paths": ["/x"] }))
+        .post("/api/v1/auth/login")
+        .json(&json!({ "username": "admin", "password": "admin_secure_123" }))
         .await;
-    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert!(body["token"].as_str().is_some());
+    assert_eq!(body["username"], "admin");
+    assert_eq!(body["role"], "admin");
 }
 
 #[tokio::test]
-async fn get_nonexistent_library_returns_404() {
+async fn login_with_invalid_credentials() {
     let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
     let resp = server
-        .get("/api/v1/libraries/nonexistent-id")
-        .add_header(hdr_name, hdr_val)
+        .post("/api/v1/auth/login")
+        .json(&json!({ "username": "admin", "password": "wrong" }))
         .await;
-    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "unauthorized");
 }
 
-// ---------------------------------------------------------------------------
-// Job + scan tests
-// ---------------------------------------------------------------------------
+#[tokio::test]
+async fn login_upgrades_weak_password_hashes() {
+    use rustfin_db::repo::users::{PasswordHashParams, create_user_with_params, find_by_username};
+
+    let pool = test_pool().await;
+    let weak = PasswordHashParams {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+    create_user_with_params(&pool, "legacy", "legacy_secure_123", "user", &weak)
+        .await
+        .unwrap();
+    let current = PasswordHashParams::default();
+    let old_hash = find_by_username(&pool, "legacy")
+        .await
+        .unwrap()
+        .unwrap()
+        .password_hash;
+    assert!(old_hash.contains("m=1024,t=1,p=1"));
+    assert!(current.needs_rehash(&old_hash));
+    assert!(!weak.needs_rehash(&old_hash));
+
+    // A wrong password never touches the stored hash.
+    let server = test_server_for_pool(pool.clone());
+    server
+        .post("/api/v1/auth/login")
+        .json(&json!({ "username": "legacy", "password": "wrong" }))
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    let user = find_by_username(&pool, "legacy").await.unwrap().unwrap();
+    assert_eq!(user.password_hash, old_hash);
+
+    // A successful login re-hashes with the current parameters.
+    login(&server, "legacy", "legacy_secure_123").await;
+    let new_hash = find_by_username(&pool, "legacy")
+        .await
+        .unwrap()
+        .unwrap()
+        .password_hash;
+    assert_ne!(new_hash, old_hash);
+    assert!(new_hash.starts_with("$argon2id$v=19$"));
+    assert!(!current.needs_rehash(&new_hash));
+    assert!(rustfin_db::repo::users::verify_password("legacy_secure_123", &new_hash).unwrap());
+
+    // The upgraded hash keeps working, and is left alone from then on.
+    login(&server, "legacy", "legacy_secure_123").await;
+    let user = find_by_username(&pool, "legacy").await.unwrap().unwrap();
+    assert_eq!(user.password_hash, new_hash);
+}
 
 #[tokio::test]
-async fn scan_library_creates_job() {
+async fn users_me_requires_auth() {
     let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
-    let tmp = std::env::temp_dir().join(format!("rf_test_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp).unwrap();
+    let resp = server.get("/api/v1/users/me").await;
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
 
-    // Create library first
-    let resp = server
-        .post("/api/v1/libraries")
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({ "name": "TV", "kind": "tv_shows", "paths": [tmp.to_str().unwrap()] }))
-        .await;
-    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+#[tokio::test]
+async fn users_me_with_valid_token() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
 
-    // Trigger scan
     let resp = server
-        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get("/api/v1/users/me")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            format!("Bearer {token}")
+                .parse::<axum::http::HeaderValue>()
+                .unwrap(),
+        )
         .await;
-    resp.assert_status(axum::http::StatusCode::ACCEPTED);
+    resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["kind"], "library_scan");
-    // Status is "queued" at creation time
-    assert_eq!(body["status"], "queued");
-    let job_id = body["id"].as_str().unwrap().to_string();
+    assert_eq!(body["username"], "admin");
+    assert_eq!(body["role"], "admin");
+}
 
-    // List jobs — should have at least 1
+#[tokio::test]
+async fn preferences_crud() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let auth_header = format!("Bearer {token}");
+
+    // GET default prefs
     let resp = server
-        .get("/api/v1/jobs")
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get("/api/v1/users/me/preferences")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
+        )
         .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert!(!body.as_array().unwrap().is_empty());
+    assert_eq!(body["theme"], "system");
+    assert_eq!(body["autoplay_next_episode"], true);
+    assert_eq!(body["show_missing_episodes"], false);
 
-    // Get job by ID — should exist regardless of status
+    // PATCH prefs
+    let new_prefs = json!({ "show_missing_episodes": true, "theme": "dark" });
     let resp = server
-        .get(&format!("/api/v1/jobs/{job_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .patch("/api/v1/users/me/preferences")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
+        )
+        .json(&new_prefs)
         .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["kind"], "library_scan");
+    assert_eq!(body["show_missing_episodes"], true);
+    assert_eq!(body["theme"], "dark");
 
-    // Wait briefly for background task, then check final state
-    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    // GET updated prefs
     let resp = server
-        .get(&format!("/api/v1/jobs/{job_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get("/api/v1/users/me/preferences")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            auth_header.parse::<axum::http::HeaderValue>().unwrap(),
+        )
         .await;
+    resp.assert_status_ok();
     let body: Value = resp.json();
-    // Job should have reached a terminal state (completed, since path doesn't exist = no-op scan)
-    let status = body["status"].as_str().unwrap();
-    assert!(
-        status == "completed" || status == "running" || status == "queued",
-        "unexpected job status: {status}"
-    );
+    assert_eq!(body["show_missing_episodes"], true);
 }
 
 #[tokio::test]
-async fn scan_nonexistent_library_returns_404() {
-    let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
-    let resp = server
-        .post("/api/v1/libraries/nonexistent/scan")
-        .add_header(hdr_name, hdr_val)
-        .await;
-    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+async fn migrations_are_idempotent() {
+    let pool = rustfin_db::connect(":memory:").await.unwrap();
+    // Run migrations twice — should not error
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
 }
 
 // ---------------------------------------------------------------------------
-// Scanner integration tests
+// Library tests
 // ---------------------------------------------------------------------------
 
 #[tokio::test]
-async fn scan_movie_library_creates_items() {
-    // Create temp dir with movie files
-    let tmp = std::env::temp_dir().join(format!("rustfin_test_movies_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(tmp.join("The Matrix (1999)")).unwrap();
-    std::fs::write(tmp.join("The Matrix (1999)/The Matrix (1999).mkv"), b"fake").unwrap();
+async fn oversized_request_bodies_are_rejected() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let state = AppState {
+        limits: RequestLimits {
+            max_body_bytes: 16 * 1024,
+            ..RequestLimits::default()
+        },
+        ..test_state_for_pool(pool)
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "preferred_audio_languages": vec!["en"; 10_000] }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "payload_too_large");
+
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name, hdr_val)
+        .json(&json!({ "preferred_audio_languages": ["en"] }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        resp.json::<Value>()["preferred_audio_languages"],
+        json!(["en"])
+    );
+}
+
+#[tokio::test]
+async fn maintenance_routes_outlast_the_request_timeout() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // A TMDB that takes 300ms to answer anything.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let stub = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = sock.read(&mut buf).await;
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                let body = r#"{"images":{}}"#;
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            });
+        }
+    });
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    for (key, value) in [
+        ("tmdb_base_url", stub.as_str()),
+        ("tmdb_api_key", "old-key"),
+    ] {
+        rustfin_db::repo::settings::set(&pool, key, value)
+            .await
+            .unwrap();
+    }
+    let state = AppState {
+        limits: RequestLimits {
+            timeout: std::time::Duration::from_millis(100),
+            ..RequestLimits::default()
+        },
+        ..test_state_for_pool(pool)
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // A regular route waiting on TMDB runs out of time...
+    server
+        .get("/api/v1/people/tmdb/287")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::REQUEST_TIMEOUT);
+
+    // ...while checking a new key against it does not.
+    let resp = server
+        .patch("/api/v1/system/metadata-config")
+        .add_header(hdr_name, hdr_val)
+        .json(&json!({ "tmdb_api_key": "new-tmdb-key-5678" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["tmdb_configured"], true);
+}
+
+#[tokio::test]
+async fn preferences_patch_merges_and_validates() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // Partial nested PATCH merges with defaults.
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "preferred_audio_languages": ["ja", "en"],
+            "subtitle_appearance": { "size": "large" }
+        }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["preferred_audio_languages"], json!(["ja", "en"]));
+    assert_eq!(body["subtitle_appearance"]["size"], "large");
+    assert_eq!(body["subtitle_appearance"]["text_color"], "#FFFFFF");
+    assert_eq!(body["autoplay_next_episode"], true);
+
+    // A second PATCH keeps earlier values.
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "autoplay_next_episode": false }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["autoplay_next_episode"], false);
+    assert_eq!(body["preferred_audio_languages"], json!(["ja", "en"]));
+    assert_eq!(body["subtitle_appearance"]["size"], "large");
+
+    // Wrong value types and unknown keys are rejected without changing anything.
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "autoplay_next_episode": "yes", "colour": "red" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    let fields = &body["error"]["details"]["fields"];
+    assert!(fields["autoplay_next_episode"].is_array());
+    assert!(fields["colour"].is_array());
+
+    let resp = server
+        .patch("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "subtitle_appearance": { "text_color": "white" } }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    let resp = server
+        .get("/api/v1/users/me/preferences")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["autoplay_next_episode"], false);
+    assert_eq!(body["subtitle_appearance"]["text_color"], "#FFFFFF");
+}
+
+fn auth_hdr(token: &str) -> (axum::http::HeaderName, axum::http::HeaderValue) {
+    (
+        axum::http::header::AUTHORIZATION,
+        format!("Bearer {token}")
+            .parse::<axum::http::HeaderValue>()
+            .unwrap(),
+    )
+}
+
+/// Counts the statements run by pools whose worker threads are named `query-count-*`.
+struct QueryCounter;
+
+static COUNTED_QUERIES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for QueryCounter {
+    fn on_event(
+        &self,
+        _event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        // sqlx logs each statement from the connection's worker thread.
+        if std::thread::current()
+            .name()
+            .is_some_and(|name| name.starts_with("query-count-"))
+        {
+            COUNTED_QUERIES.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+}
+
+/// Records `warn` events as `(thread name, rendered fields)`.
+struct WarnRecorder;
+
+static RECORDED_WARNINGS: std::sync::Mutex<Vec<(String, String)>> =
+    std::sync::Mutex::new(Vec::new());
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for WarnRecorder {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        struct Fields(String);
+        impl tracing::field::Visit for Fields {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                self.0.push_str(&format!("{}={value:?} ", field.name()));
+            }
+        }
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        let thread = std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string();
+        RECORDED_WARNINGS.lock().unwrap().push((thread, fields.0));
+    }
+}
+
+/// Route `sqlx::query` events to [`QueryCounter`] and `warn` events to
+/// [`WarnRecorder`] for the rest of the test run.
+fn install_test_subscriber() {
+    use tracing_subscriber::Layer;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    static INSTALL: std::sync::Once = std::sync::Once::new();
+    INSTALL.call_once(|| {
+        let _ = tracing::subscriber::set_global_default(
+            tracing_subscriber::registry()
+                .with(
+                    QueryCounter.with_filter(tracing_subscriber::filter::filter_fn(|metadata| {
+                        metadata.target() == "sqlx::query"
+                    })),
+                )
+                .with(WarnRecorder.with_filter(tracing_subscriber::filter::LevelFilter::WARN)),
+        );
+    });
+}
+
+/// Warnings recorded so far on threads whose name contains `thread`.
+fn recorded_warnings(thread: &str) -> Vec<String> {
+    RECORDED_WARNINGS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(name, _)| name.contains(thread))
+        .map(|(_, fields)| fields.clone())
+        .collect()
+}
+
+/// A migrated in-memory pool whose statements [`QueryCounter`] counts.
+async fn query_counted_pool() -> sqlx::SqlitePool {
+    use std::str::FromStr;
+
+    install_test_subscriber();
+    let opts = sqlx::sqlite::SqliteConnectOptions::from_str(":memory:")
+        .unwrap()
+        .foreign_keys(true)
+        .thread_name(|id| format!("query-count-{id}"));
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect_with(opts)
+        .await
+        .unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_state", "Completed")
+        .await
+        .unwrap();
+    pool
+}
+
+/// Statements run while serving a GET of `path`.
+async fn queries_for_get(server: &TestServer, token: &str, path: &str) -> (usize, Value) {
+    let before = COUNTED_QUERIES.load(std::sync::atomic::Ordering::SeqCst);
+    let resp = server
+        .get(path)
+        .add_header(auth_hdr(token).0, auth_hdr(token).1)
+        .await;
+    resp.assert_status_ok();
+    let after = COUNTED_QUERIES.load(std::sync::atomic::Ordering::SeqCst);
+    (after - before, resp.json())
+}
+
+#[tokio::test]
+async fn listings_use_a_bounded_number_of_queries() {
+    let pool = query_counted_pool().await;
+    let viewer = rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let server = test_server_for_pool(pool.clone());
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let viewer_token = login(&server, "viewer", "viewer_secure_123").await;
+
+    let mut library_ids = Vec::new();
+    let mut add_libraries = async |count: usize| {
+        for _ in 0..count {
+            let n = library_ids.len();
+            let lib = rustfin_db::repo::libraries::create_library(
+                &pool,
+                &format!("Library {n:02}"),
+                "movies",
+                &[format!("/media/lib{n}/a"), format!("/media/lib{n}/b")],
+            )
+            .await
+            .unwrap();
+            library_ids.push(lib.id);
+        }
+        rustfin_db::repo::users::set_library_access(&pool, &viewer, &library_ids)
+            .await
+            .unwrap();
+        for n in library_ids.len() - count..library_ids.len() {
+            rustfin_db::repo::users::create_user(
+                &pool,
+                &format!("user{n:02}"),
+                "user_secure_1234",
+                "user",
+            )
+            .await
+            .unwrap();
+        }
+    };
+
+    add_libraries(2).await;
+    let (admin_few, _) = queries_for_get(&server, &admin_token, "/api/v1/libraries").await;
+    let (viewer_few, _) = queries_for_get(&server, &viewer_token, "/api/v1/libraries").await;
+    let (users_few, _) = queries_for_get(&server, &admin_token, "/api/v1/users").await;
+
+    add_libraries(18).await;
+    rustfin_db::repo::libraries::set_library_scan_interval(&pool, &library_ids[3], Some(3600))
+        .await
+        .unwrap();
+    rustfin_db::repo::libraries::set_library_specials_policy(&pool, &library_ids[3], Some("skip"))
+        .await
+        .unwrap();
+    let (admin_many, listed) = queries_for_get(&server, &admin_token, "/api/v1/libraries").await;
+    let (viewer_many, viewer_listed) =
+        queries_for_get(&server, &viewer_token, "/api/v1/libraries").await;
+    let (users_many, users) = queries_for_get(&server, &admin_token, "/api/v1/users").await;
+
+    assert!(admin_few > 0, "no statements were counted");
+    assert_eq!(
+        admin_many, admin_few,
+        "library listing grew with the library count"
+    );
+    assert_eq!(
+        viewer_many, viewer_few,
+        "library listing grew with the library count"
+    );
+    assert_eq!(
+        users_many, users_few,
+        "user listing grew with the user count"
+    );
+
+    let listed = listed.as_array().unwrap();
+    assert_eq!(listed.len(), 20);
+    assert_eq!(viewer_listed.as_array().unwrap().len(), 20);
+    // Every listed library matches what fetching it on its own returns.
+    for lib in listed {
+        let id = lib["id"].as_str().unwrap();
+        let (_, single) =
+            queries_for_get(&server, &admin_token, &format!("/api/v1/libraries/{id}")).await;
+        assert_eq!(lib, &single);
+        assert_eq!(lib["paths"].as_array().unwrap().len(), 2);
+    }
+    let tuned = listed.iter().find(|l| l["id"] == library_ids[3]).unwrap();
+    assert_eq!(tuned["settings"]["scan_interval_secs"], 3600);
+    assert_eq!(tuned["settings"]["specials_policy"], "skip");
+
+    let viewer_entry = users
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|u| u["username"] == "viewer")
+        .unwrap();
+    let mut granted: Vec<String> = library_ids.clone();
+    granted.sort();
+    assert_eq!(viewer_entry["library_ids"], json!(granted));
+}
+
+#[tokio::test]
+async fn create_library_requires_admin() {
+    let server = test_app().await;
+    // Test that unauthenticated requests fail
+    let resp = server
+        .post("/api/v1/libraries")
+        .json(&json!({ "name": "Movies", "kind": "movies", "paths": ["/media/movies"] }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn library_crud_flow() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_test");
+    std::fs::create_dir_all(&tmp).unwrap();
+
+    // Create library
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Movies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let body: Value = resp.json();
+    assert_eq!(body["name"], "Movies");
+    assert_eq!(body["kind"], "movies");
+    assert_eq!(body["item_count"], 0);
+    assert_eq!(body["paths"][0]["path"], tmp.to_str().unwrap());
+    let lib_id = body["id"].as_str().unwrap().to_string();
+
+    // List libraries
+    let resp = server
+        .get("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body.as_array().unwrap().len(), 1);
+
+    // Get single library
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["name"], "Movies");
+
+    // Update library name
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "My Movies" }))
+        .await;
+    resp.assert_status_ok();
+
+    // Verify update
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["name"], "My Movies");
+}
+
+#[tokio::test]
+async fn create_library_validates_kind() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Bad", "kind": "invalid", "paths": ["/x"] }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
+
+    // The kinds the setup wizard offers are accepted here too.
+    for kind in ["music", "mixed"] {
+        let tmp = TempDir::new(&format!("rf_kind_{kind}"));
+        std::fs::create_dir_all(&tmp).unwrap();
+        let resp = server
+            .post("/api/v1/libraries")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "name": kind, "kind": kind, "paths": [tmp.to_str().unwrap()] }))
+            .await;
+        resp.assert_status(axum::http::StatusCode::CREATED);
+        assert_eq!(resp.json::<Value>()["kind"], kind);
+    }
+}
+
+#[tokio::test]
+async fn library_paths_must_not_overlap_other_libraries() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let root = TempDir::new("rf_overlap");
+    let movies = root.join("movies");
+    let shows = root.join("shows");
+    let other = TempDir::new("rf_overlap_other");
+    for dir in [&movies.join("4k"), &shows, &other] {
+        std::fs::create_dir_all(dir).unwrap();
+    }
+    let create = |name: &str, path: &std::path::Path, force: bool| {
+        server
+            .post("/api/v1/libraries")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({
+                "name": name,
+                "kind": "movies",
+                "paths": [path.to_str().unwrap()],
+                "force": force
+            }))
+    };
+
+    let resp = create("Movies", &movies, false).await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let movies_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    // A parent of an existing library path is rejected.
+    let resp = create("Everything", &root, false).await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    let msg = body["error"]["details"]["fields"]["paths[0]"][0]
+        .as_str()
+        .unwrap();
+    assert!(msg.contains("library 'Movies'"), "{msg}");
+
+    // So is a child of one, even when spelled with a trailing `..` hop.
+    let resp = create(
+        "Nested",
+        &movies.join("..").join("movies").join("4k"),
+        false,
+    )
+    .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Siblings and unrelated paths are fine.
+    create("Shows", &shows, false)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+    create("Other", &other, false)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+
+    // Replacing paths is checked against other libraries, not the library itself.
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{movies_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "paths": [movies.to_str().unwrap()] }))
+        .await;
+    resp.assert_status_ok();
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{movies_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "paths": [root.to_str().unwrap()] }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // `force` overrides the check.
+    create("Everything", &root, true)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn library_metadata_locale_override() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_test");
+    std::fs::create_dir_all(&tmp).unwrap();
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Films",
+            "kind": "movies",
+            "paths": [tmp.to_str().unwrap()],
+            "settings": { "metadata_language": "fr", "metadata_region": "FR" }
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let body: Value = resp.json();
+    assert_eq!(body["settings"]["metadata_language"], "fr");
+    assert_eq!(body["settings"]["metadata_region"], "FR");
+    let lib_id = body["id"].as_str().unwrap().to_string();
+
+    // Invalid region is rejected
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "metadata_region": "france" } }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Empty string clears the override
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "metadata_language": "", "metadata_region": "" } }))
+        .await;
+    resp.assert_status_ok();
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert!(body["settings"]["metadata_language"].is_null());
+    assert!(body["settings"]["metadata_region"].is_null());
+}
+
+#[tokio::test]
+async fn formatted_dates_follow_the_server_time_zone() {
+    let tmp = TempDir::new("rf_dates");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Dated (2023).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Dated", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+    // One winter and one summer instant, so New York is on EST and then EDT.
+    for table in ["library", "item"] {
+        sqlx::query(&format!(
+            "UPDATE {table} SET created_ts = 1700000000, updated_ts = 1690000000"
+        ))
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let get = async |path: String| {
+        let resp = server
+            .get(&path)
+            .add_header(auth_hdr(&token).0, auth_hdr(&token).1)
+            .await;
+        resp.assert_status_ok();
+        resp.json::<Value>()
+    };
+
+    // Off unless asked for.
+    let plain = get(format!("/api/v1/libraries/{}", lib.id)).await;
+    assert_eq!(plain["created_ts"], 1_700_000_000);
+    assert!(plain.get("created_at").is_none());
+    assert!(
+        get(format!("/api/v1/items/{item_id}"))
+            .await
+            .get("updated_at")
+            .is_none()
+    );
+
+    // No zone configured means UTC.
+    let library = get(format!("/api/v1/libraries/{}?formatted_dates=true", lib.id)).await;
+    assert_eq!(library["created_at"], "2023-11-14T22:13:20Z");
+    assert_eq!(library["updated_at"], "2023-07-22T04:26:40Z");
+    assert_eq!(library["created_ts"], 1_700_000_000);
+
+    // The bundled zone is a slim file, so its 2023 offsets come from the footer.
+    // SAFETY: only zone lookups read TZDIR, and every test that sets it sets it
+    // to this same directory.
+    unsafe {
+        std::env::set_var(
+            "TZDIR",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/zoneinfo"),
+        );
+    }
+    rustfin_db::repo::settings::set(&pool, "default_time_zone", "America/New_York")
+        .await
+        .unwrap();
+    let libraries = get("/api/v1/libraries?formatted_dates=true".to_string()).await;
+    assert_eq!(libraries[0]["created_at"], "2023-11-14T17:13:20-05:00");
+    assert_eq!(libraries[0]["updated_at"], "2023-07-22T00:26:40-04:00");
+    let item = get(format!("/api/v1/items/{item_id}?formatted_dates=true")).await;
+    assert_eq!(item["created_at"], "2023-11-14T17:13:20-05:00");
+    assert_eq!(item["updated_at"], "2023-07-22T00:26:40-04:00");
+    let items = get(format!(
+        "/api/v1/libraries/{}/items?formatted_dates=true",
+        lib.id
+    ))
+    .await;
+    assert_eq!(items[0]["created_at"], "2023-11-14T17:13:20-05:00");
+
+    // A zone missing from the tz database falls back to UTC.
+    rustfin_db::repo::settings::set(&pool, "default_time_zone", "Mars/Olympus_Mons")
+        .await
+        .unwrap();
+    let item = get(format!("/api/v1/items/{item_id}?formatted_dates=true")).await;
+    assert_eq!(item["created_at"], "2023-11-14T22:13:20Z");
+}
+
+#[tokio::test]
+async fn get_nonexistent_library_returns_404() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .get("/api/v1/libraries/nonexistent-id")
+        .add_header(hdr_name, hdr_val)
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+// ---------------------------------------------------------------------------
+// Job + scan tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn scan_library_creates_job() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_test");
+    std::fs::create_dir_all(&tmp).unwrap();
+
+    // Create library first
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "TV", "kind": "tv_shows", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    // Let the scan queued by creating the library finish; until then a scan
+    // request would be answered with that job.
+    for _ in 0..100 {
+        let jobs: Value = server
+            .get("/api/v1/jobs")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await
+            .json();
+        if jobs["jobs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|j| j["status"] == "completed")
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    // Trigger scan
+    let resp = server
+        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::ACCEPTED);
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "library_scan");
+    // Status is "queued" at creation time
+    assert_eq!(body["status"], "queued");
+    let job_id = body["id"].as_str().unwrap().to_string();
+
+    // List jobs — should have at least 1
+    let resp = server
+        .get("/api/v1/jobs")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert!(!body["jobs"].as_array().unwrap().is_empty());
+    assert!(body["total"].as_i64().unwrap() >= 1);
+
+    // Get job by ID — should exist regardless of status
+    let resp = server
+        .get(&format!("/api/v1/jobs/{job_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "library_scan");
+
+    // Wait briefly for background task, then check final state
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let resp = server
+        .get(&format!("/api/v1/jobs/{job_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    // Job should have reached a terminal state (completed, since path doesn't exist = no-op scan)
+    let status = body["status"].as_str().unwrap();
+    assert!(
+        status == "completed" || status == "running" || status == "queued",
+        "unexpected job status: {status}"
+    );
+}
+
+#[tokio::test]
+async fn jobs_stay_queued_with_a_position_until_a_worker_frees() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let state = AppState {
+        jobs: JobQueue::new(1),
+        ..test_state_for_pool(pool.clone())
+    };
+    let server = TestServer::new(build_router(state.clone())).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // Saturate the pool so every job has to wait.
+    let busy = state.jobs.acquire("busy").await;
+
+    let mut job_ids = Vec::new();
+    let mut roots = Vec::new();
+    for name in ["First", "Second"] {
+        let tmp = TempDir::new("rf_test");
+        std::fs::create_dir_all(&tmp).unwrap();
+        let resp = server
+            .post("/api/v1/libraries")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "name": name, "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+            .await;
+        roots.push(tmp);
+        resp.assert_status(axum::http::StatusCode::CREATED);
+        let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+        // The creation scan registers with the queue from its own task.
+        let mut job_id = None;
+        for _ in 0..100 {
+            let jobs = all_jobs(&pool).await;
+            job_id = jobs
+                .into_iter()
+                .find(|j| {
+                    j.payload_json
+                        .as_deref()
+                        .is_some_and(|p| p.contains(&lib_id))
+                })
+                .map(|j| j.id)
+                .filter(|id| state.jobs.position(id).is_some());
+            if job_id.is_some() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        job_ids.push(job_id.expect("scan job should be waiting for a worker"));
+    }
+
+    // Still queued well after creation, in enqueue order.
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    for (i, job_id) in job_ids.iter().enumerate() {
+        let resp = server
+            .get(&format!("/api/v1/jobs/{job_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+        let body: Value = resp.json();
+        assert_eq!(body["status"], "queued");
+        assert_eq!(body["queue_position"], i + 1);
+    }
+
+    // A job cancelled while queued never starts.
+    let resp = server
+        .post(&format!("/api/v1/jobs/{}/cancel", job_ids[1]))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+
+    drop(busy);
+    let mut first = Value::Null;
+    for _ in 0..100 {
+        let resp = server
+            .get(&format!("/api/v1/jobs/{}", job_ids[0]))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        first = resp.json();
+        if first["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(first["status"], "completed");
+    assert!(first.get("queue_position").is_none());
+
+    for _ in 0..100 {
+        if state.jobs.position(&job_ids[1]).is_none() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let second = rustfin_db::repo::jobs::get_job(&pool, &job_ids[1])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(second.status, "cancelled");
+}
+
+#[tokio::test]
+async fn scan_nonexistent_library_returns_404() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post("/api/v1/libraries/nonexistent/scan")
+        .add_header(hdr_name, hdr_val)
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn scheduled_scans_run_after_interval_without_stacking() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let state = test_state_for_pool(pool.clone());
+    let server = TestServer::new(build_router(state.clone())).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_test");
+    std::fs::create_dir_all(&tmp).unwrap();
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Scheduled",
+            "kind": "movies",
+            "paths": [tmp.to_str().unwrap()],
+            "settings": { "scan_interval_secs": 3600 }
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let body: Value = resp.json();
+    assert_eq!(body["settings"]["scan_interval_secs"], 3600);
+    let lib_id = body["id"].as_str().unwrap().to_string();
+
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "scan_interval_secs": 5 } }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Let the scan started on creation finish.
+    for _ in 0..100 {
+        let jobs = all_jobs(&pool).await;
+        if jobs
+            .iter()
+            .all(|j| j.status == "completed" || j.status == "failed")
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let queued = rustfin_server::library_scan::enqueue_due_scans(&state, now)
+        .await
+        .unwrap();
+    assert!(queued.is_empty(), "interval has not elapsed yet");
+
+    // Purging the job history doesn't forget when a long-standing library was
+    // last scanned.
+    sqlx::query("UPDATE library SET created_ts = created_ts - 86400")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(
+        rustfin_db::repo::jobs::purge_finished_jobs(&pool, now + 1)
+            .await
+            .unwrap()
+            > 0
+    );
+    let queued = rustfin_server::library_scan::enqueue_due_scans(&state, now + 60)
+        .await
+        .unwrap();
+    assert!(
+        queued.is_empty(),
+        "purged jobs must not make the library overdue"
+    );
+
+    // A scan still running blocks the next one even once the interval is up.
+    let payload = json!({ "library_id": lib_id }).to_string();
+    let running = rustfin_db::repo::jobs::create_job(&pool, "library_scan", Some(&payload))
+        .await
+        .unwrap();
+    rustfin_db::repo::jobs::update_job_status(&pool, &running.id, "running", 0.5, None)
+        .await
+        .unwrap();
+    let later = now + 3600 + 5;
+    let queued = rustfin_server::library_scan::enqueue_due_scans(&state, later)
+        .await
+        .unwrap();
+    assert!(queued.is_empty(), "must not stack on a running scan");
+
+    rustfin_db::repo::jobs::update_job_status(&pool, &running.id, "completed", 1.0, None)
+        .await
+        .unwrap();
+    let queued = rustfin_server::library_scan::enqueue_due_scans(&state, later)
+        .await
+        .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].kind, "library_scan");
+    assert!(queued[0].payload_json.as_deref().unwrap().contains(&lib_id));
+
+    // Turning the schedule off stops further scans.
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "scan_interval_secs": 0 } }))
+        .await;
+    resp.assert_status_ok();
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert!(body["settings"]["scan_interval_secs"].is_null());
+    let queued = rustfin_server::library_scan::enqueue_due_scans(&state, later + 86_400)
+        .await
+        .unwrap();
+    assert!(queued.is_empty());
+}
+
+#[tokio::test]
+async fn startup_scans_follow_the_scan_on_startup_setting() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let tmp = TempDir::new("rf_startup");
+    let mut libs = Vec::new();
+    for name in ["Movies", "Shows"] {
+        let dir = tmp.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let lib = rustfin_db::repo::libraries::create_library(
+            &pool,
+            name,
+            "movies",
+            &[dir.to_string_lossy().to_string()],
+        )
+        .await
+        .unwrap();
+        libs.push(lib.id);
+    }
+    let state = test_state_for_pool(pool.clone());
+    let server = TestServer::new(build_router(state.clone())).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let scan_jobs = || async {
+        all_jobs(&pool)
+            .await
+            .into_iter()
+            .filter(|j| j.kind == "library_scan")
+            .map(|j| j.payload_json.unwrap_or_default())
+            .collect::<Vec<_>>()
+    };
+
+    // Off by default: booting queues nothing.
+    rustfin_server::startup_scan::spawn_startup_scans(state.clone())
+        .await
+        .unwrap();
+    assert!(scan_jobs().await.is_empty());
+
+    // `all` scans every library.
+    rustfin_db::repo::settings::set(&pool, "scan_on_startup", "all")
+        .await
+        .unwrap();
+    rustfin_server::startup_scan::spawn_startup_scans(state.clone())
+        .await
+        .unwrap();
+    let jobs = scan_jobs().await;
+    assert_eq!(jobs.len(), 2);
+    for lib_id in &libs {
+        assert!(jobs.iter().any(|p| p.contains(lib_id.as_str())));
+    }
+    // A scan still queued or running would be handed back instead of a new one.
+    for _ in 0..200 {
+        let jobs = all_jobs(&pool).await;
+        if jobs
+            .iter()
+            .all(|j| !matches!(j.status.as_str(), "queued" | "running"))
+        {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+
+    // `flagged` only scans libraries that opted in.
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{}", libs[1]))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "scan_on_startup": true } }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = server
+        .get(&format!("/api/v1/libraries/{}", libs[1]))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(body["settings"]["scan_on_startup"], true);
+    rustfin_db::repo::settings::set(&pool, "scan_on_startup", "flagged")
+        .await
+        .unwrap();
+    let queued = rustfin_server::startup_scan::enqueue_startup_scans(&state)
+        .await
+        .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert!(
+        queued[0]
+            .payload_json
+            .as_deref()
+            .unwrap()
+            .contains(libs[1].as_str())
+    );
+
+    rustfin_db::repo::settings::set(&pool, "scan_on_startup", "off")
+        .await
+        .unwrap();
+    rustfin_server::startup_scan::spawn_startup_scans(state.clone())
+        .await
+        .unwrap();
+    assert_eq!(scan_jobs().await.len(), 3);
+}
+
+#[tokio::test]
+async fn jobs_listing_filters_and_paginates() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+
+    let mut completed_ids = Vec::new();
+    for i in 0..5 {
+        let job = rustfin_db::repo::jobs::create_job(&pool, "library_scan", None)
+            .await
+            .unwrap();
+        let status = if i % 2 == 0 { "completed" } else { "failed" };
+        rustfin_db::repo::jobs::update_job_status(&pool, &job.id, status, 1.0, None)
+            .await
+            .unwrap();
+        if status == "completed" {
+            completed_ids.push(job.id);
+        }
+    }
+    rustfin_db::repo::jobs::create_job(&pool, "metadata_refresh", None)
+        .await
+        .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .get("/api/v1/jobs?status=completed")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["total"], 3);
+    let jobs = body["jobs"].as_array().unwrap();
+    assert_eq!(jobs.len(), 3);
+    assert!(jobs.iter().all(|j| j["status"] == "completed"));
+    let mut ids: Vec<String> = jobs
+        .iter()
+        .map(|j| j["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    completed_ids.sort();
+    assert_eq!(ids, completed_ids);
+
+    let resp = server
+        .get("/api/v1/jobs?kind=library_scan&limit=2")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let first: Value = resp.json();
+    assert_eq!(first["total"], 5);
+    assert_eq!(first["jobs"].as_array().unwrap().len(), 2);
+
+    let resp = server
+        .get("/api/v1/jobs?kind=library_scan&limit=2&offset=4")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let last: Value = resp.json();
+    assert_eq!(last["jobs"].as_array().unwrap().len(), 1);
+    assert_ne!(last["jobs"][0]["id"], first["jobs"][0]["id"]);
+
+    // Finished jobs older than the cutoff are purged; queued ones are kept.
+    let purged =
+        rustfin_db::repo::jobs::purge_finished_jobs(&pool, chrono::Utc::now().timestamp() + 1)
+            .await
+            .unwrap();
+    assert_eq!(purged, 5);
+    let resp = server
+        .get("/api/v1/jobs")
+        .add_header(hdr_name, hdr_val)
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["jobs"][0]["kind"], "metadata_refresh");
+}
+
+/// Minimal TMDB stand-in: answers every request with `respond(path)` as JSON.
+/// A `null` response is served as a 404.
+async fn spawn_tmdb_stub(respond: fn(&str) -> Value) -> String {
+    spawn_tmdb_stub_with_latency(respond, std::time::Duration::ZERO).await
+}
+
+/// Like [`spawn_tmdb_stub`], but every response is delayed by `latency`.
+/// Connections are served concurrently.
+async fn spawn_tmdb_stub_with_latency(
+    respond: fn(&str) -> Value,
+    latency: std::time::Duration,
+) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let n = sock.read(&mut buf).await.unwrap_or(0);
+                let req = String::from_utf8_lossy(&buf[..n]).to_string();
+                let target = req
+                    .lines()
+                    .next()
+                    .and_then(|l| l.split_whitespace().nth(1))
+                    .unwrap_or("");
+                let path = target.split('?').next().unwrap_or("").to_string();
+                let body = respond(&path);
+                let status = if body.is_null() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let body = body.to_string();
+                tokio::time::sleep(latency).await;
+                let resp = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = sock.write_all(resp.as_bytes()).await;
+            });
+        }
+    });
+    format!("http://{addr}")
+}
+
+/// TMDB stand-in that accepts only `valid_key` and answers 401 for anything else.
+async fn spawn_tmdb_key_check_stub(valid_key: &'static str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            let mut buf = vec![0u8; 8192];
+            let n = sock.read(&mut buf).await.unwrap_or(0);
+            let req = String::from_utf8_lossy(&buf[..n]).to_string();
+            let target = req
+                .lines()
+                .next()
+                .and_then(|l| l.split_whitespace().nth(1))
+                .unwrap_or("");
+            let (status, body) = if target.contains(&format!("api_key={valid_key}")) {
+                ("200 OK", r#"{"images":{}}"#)
+            } else {
+                ("401 Unauthorized", r#"{"status_code":7}"#)
+            };
+            let resp = format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        }
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+async fn metadata_config_validates_and_never_returns_the_tmdb_key() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let stub = spawn_tmdb_key_check_stub("good-tmdb-key-1234").await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .patch("/api/v1/system/metadata-config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "tmdb_api_key": "bogus-key" }))
+        .await;
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert!(
+        rustfin_db::repo::settings::get(&pool, "tmdb_api_key")
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let resp = server
+        .patch("/api/v1/system/metadata-config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "tmdb_api_key": " good-tmdb-key-1234 " }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        rustfin_db::repo::settings::get(&pool, "tmdb_api_key")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("good-tmdb-key-1234")
+    );
+
+    let resp = server
+        .get("/api/v1/system/metadata-config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert!(!resp.text().contains("1234"));
+    let body: Value = resp.json();
+    assert_eq!(body["tmdb_configured"], true);
+    assert_eq!(body["tmdb_key_source"], "database");
+
+    // Clearing needs no TMDB round-trip.
+    let resp = server
+        .patch("/api/v1/system/metadata-config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "tmdb_api_key": "" }))
+        .await;
+    resp.assert_status_ok();
+    if std::env::var("RUSTFIN_TMDB_KEY").is_err() {
+        assert_eq!(resp.json::<Value>()["tmdb_configured"], false);
+    }
+}
+
+#[tokio::test]
+async fn library_default_sort_applies_unless_overridden() {
+    let tmp = TempDir::new("rf_sort");
+    std::fs::create_dir_all(&tmp).unwrap();
+    for name in ["Alpha (2001).mkv", "Bravo (2002).mkv", "Charlie (2003).mkv"] {
+        tmp.touch(name);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    // Added order: Alpha, then Charlie, then Bravo.
+    for (title, ts) in [("Alpha", 100), ("Charlie", 200), ("Bravo", 300)] {
+        sqlx::query("UPDATE item SET created_ts = ? WHERE library_id = ? AND title = ?")
+            .bind(ts)
+            .bind(&lib.id)
+            .bind(title)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let titles = |resp: axum_test::TestResponse| -> Vec<String> {
+        resp.assert_status_ok();
+        resp.json::<Vec<Value>>()
+            .iter()
+            .map(|i| i["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "default_sort": "popularity" } }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    let resp = server
+        .patch(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "default_sort": "date_added", "default_order": "desc" } }))
+        .await;
+    resp.assert_status_ok();
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["settings"]["default_sort"], "date_added");
+    assert_eq!(body["settings"]["default_order"], "desc");
+
+    let items_url = format!("/api/v1/libraries/{}/items", lib.id);
+    let resp = server
+        .get(&items_url)
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(titles(resp), vec!["Bravo", "Charlie", "Alpha"]);
+
+    let resp = server
+        .get(&format!("{items_url}?sort_by=title"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(titles(resp), vec!["Alpha", "Bravo", "Charlie"]);
+
+    let resp = server
+        .get(&format!("{items_url}?sort_order=asc"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(titles(resp), vec!["Alpha", "Charlie", "Bravo"]);
+
+    let resp = server
+        .get(&format!("{items_url}?sort_by=year&sort_order=desc"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(titles(resp), vec!["Charlie", "Bravo", "Alpha"]);
+
+    let resp = server
+        .get(&format!("{items_url}?sort_by=bogus"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn library_studios_are_listed_and_filterable() {
+    let tmp = TempDir::new("rf_studios");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Up (2009).mkv");
+    tmp.touch("Coco (2017).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    for item in &items {
+        let studios = if item.title == "Up" {
+            vec!["Pixar".to_string(), "Walt Disney Pictures".to_string()]
+        } else {
+            vec!["pixar".to_string()]
+        };
+        let meta = rustfin_metadata::ItemMetadata {
+            studios: Some(studios),
+            ..Default::default()
+        };
+        rustfin_metadata::merge::merge_metadata(&pool, &item.id, &meta)
+            .await
+            .unwrap();
+    }
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}/studios", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let studios: Vec<Value> = resp.json();
+    assert_eq!(studios.len(), 2);
+    // One row per studio regardless of spelling.
+    assert!(
+        studios[0]["name"]
+            .as_str()
+            .unwrap()
+            .eq_ignore_ascii_case("pixar")
+    );
+    assert_eq!(studios[0]["item_count"], 2);
+    assert_eq!(studios[1]["name"], "Walt Disney Pictures");
+    assert_eq!(studios[1]["item_count"], 1);
+
+    let resp = server
+        .get(&format!(
+            "/api/v1/libraries/{}/items?studio=walt%20disney%20pictures",
+            lib.id
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let filtered: Vec<Value> = resp.json();
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0]["title"], "Up");
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}/items?studio=PIXAR", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let filtered: Vec<Value> = resp.json();
+    assert_eq!(filtered.len(), 2);
+}
+
+#[tokio::test]
+async fn library_metadata_refresh_job_enriches_bare_items() {
+    let tmp = TempDir::new("rf_refresh");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("The Matrix (1999).mkv");
+    tmp.touch("Heat (1995).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    // Scanned before any TMDB key exists: items are bare.
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert!(
+        items
+            .iter()
+            .all(|i| i.overview.is_none() && i.poster_url.is_none())
+    );
+    let heat_id = items.iter().find(|i| i.title == "Heat").unwrap().id.clone();
+    let matrix_id = items
+        .iter()
+        .find(|i| i.title == "The Matrix")
+        .unwrap()
+        .id
+        .clone();
+    // A user-edited, locked overview must survive the refresh.
+    sqlx::query("UPDATE item SET overview = 'My notes' WHERE id = ?")
+        .bind(&heat_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    rustfin_metadata::merge::lock_field(&pool, &heat_id, "overview")
+        .await
+        .unwrap();
+
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/search/movie" => json!({ "results": [
+            { "id": 603, "title": "The Matrix", "release_date": "1999-03-31" },
+            { "id": 949, "title": "Heat", "release_date": "1995-12-15" }
+        ]}),
+        "/movie/603" => json!({
+            "title": "The Matrix",
+            "overview": "A hacker learns the truth about reality.",
+            "release_date": "1999-03-31",
+            "poster_path": "/matrix.jpg"
+        }),
+        "/movie/949" => json!({
+            "title": "Heat",
+            "overview": "A cop hunts a crew of thieves.",
+            "release_date": "1995-12-15",
+            "poster_path": "/heat.jpg"
+        }),
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post(&format!("/api/v1/libraries/{}/refresh-metadata", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "replace": false }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::ACCEPTED);
+    let job: Value = resp.json();
+    assert_eq!(job["kind"], "metadata_refresh");
+    let job_id = job["id"].as_str().unwrap().to_string();
+
+    let mut status = String::new();
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let resp = server
+            .get(&format!("/api/v1/jobs/{job_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        status = resp.json::<Value>()["status"].as_str().unwrap().to_string();
+        if status == "completed" || status == "failed" {
+            break;
+        }
+    }
+    assert_eq!(status, "completed");
+
+    let matrix = rustfin_db::repo::items::get_item(&pool, &matrix_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        matrix.overview.as_deref(),
+        Some("A hacker learns the truth about reality.")
+    );
+    assert!(matrix.poster_url.unwrap().ends_with("/matrix.jpg"));
+
+    let heat = rustfin_db::repo::items::get_item(&pool, &heat_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(heat.overview.as_deref(), Some("My notes"));
+    assert!(heat.poster_url.unwrap().ends_with("/heat.jpg"));
+
+    let resp = server
+        .post("/api/v1/libraries/nonexistent/refresh-metadata")
+        .add_header(hdr_name, hdr_val)
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn locked_items_are_skipped_by_refresh_and_enrichment() {
+    let tmp = TempDir::new("rf_locked");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("The Matrix (1999).mkv");
+    tmp.touch("Heat (1995).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let heat_id = items.iter().find(|i| i.title == "Heat").unwrap().id.clone();
+    let matrix_id = items
+        .iter()
+        .find(|i| i.title == "The Matrix")
+        .unwrap()
+        .id
+        .clone();
+    // Hand-curated: no provider should overwrite this.
+    sqlx::query(
+        "UPDATE item SET overview = 'Director notes', poster_url = '/mine.jpg' WHERE id = ?",
+    )
+    .bind(&heat_id)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/search/movie" => json!({ "results": [
+            { "id": 603, "title": "The Matrix", "release_date": "1999-03-31" },
+            { "id": 949, "title": "Heat", "release_date": "1995-12-15" }
+        ]}),
+        "/movie/603" => json!({
+            "title": "The Matrix",
+            "overview": "A hacker learns the truth about reality.",
+            "release_date": "1999-03-31",
+            "poster_path": "/matrix.jpg"
+        }),
+        "/movie/949" => json!({
+            "title": "Heat",
+            "overview": "A cop hunts a crew of thieves.",
+            "release_date": "1995-12-15",
+            "poster_path": "/heat.jpg"
+        }),
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post(&format!("/api/v1/items/{heat_id}/lock"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["locked"], true);
+    let item: Value = server
+        .get(&format!("/api/v1/items/{heat_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(item["locked"], true);
+
+    // A replacing metadata refresh leaves the locked item untouched.
+    let resp = server
+        .post(&format!("/api/v1/libraries/{}/refresh-metadata", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "replace": true }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::ACCEPTED);
+    let job_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut status = String::new();
+    for _ in 0..50 {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let resp = server
+            .get(&format!("/api/v1/jobs/{job_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        status = resp.json::<Value>()["status"].as_str().unwrap().to_string();
+        if status == "completed" || status == "failed" {
+            break;
+        }
+    }
+    assert_eq!(status, "completed");
+
+    let matrix = rustfin_db::repo::items::get_item(&pool, &matrix_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matrix.poster_url.unwrap().ends_with("/matrix.jpg"));
+    let heat = rustfin_db::repo::items::get_item(&pool, &heat_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(heat.overview.as_deref(), Some("Director notes"));
+    assert_eq!(heat.poster_url.as_deref(), Some("/mine.jpg"));
+    assert!(
+        rustfin_metadata::merge::get_provider_ids(&pool, &heat_id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // Post-scan enrichment skips it too.
+    rustfin_server::artwork::enrich_library_artwork(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let heat = rustfin_db::repo::items::get_item(&pool, &heat_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(heat.overview.as_deref(), Some("Director notes"));
+    assert_eq!(heat.poster_url.as_deref(), Some("/mine.jpg"));
+
+    // Unlocked, the next enrichment fills it in as usual.
+    let resp = server
+        .post(&format!("/api/v1/items/{heat_id}/unlock"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["locked"], false);
+    rustfin_server::artwork::enrich_library_artwork(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let heat = rustfin_db::repo::items::get_item(&pool, &heat_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        heat.overview.as_deref(),
+        Some("A cop hunts a crew of thieves.")
+    );
+
+    let resp = server
+        .post("/api/v1/items/nonexistent/lock")
+        .add_header(hdr_name, hdr_val)
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn concurrent_metadata_refresh_matches_sequential_and_is_faster() {
+    const TITLES: [&str; 8] = [
+        "Alpha", "Bravo", "Charlie", "Delta", "Echo", "Foxtrot", "Golf", "Hotel",
+    ];
+
+    let tmp = TempDir::new("rf_tmdb_conc");
+    std::fs::create_dir_all(&tmp).unwrap();
+    for (i, title) in TITLES.iter().enumerate() {
+        tmp.touch(format!("{title} ({}).mkv", 2001 + i));
+    }
+
+    let stub = spawn_tmdb_stub_with_latency(
+        |path| {
+            if path == "/search/movie" {
+                let results: Vec<Value> = TITLES
+                    .iter()
+                    .enumerate()
+                    .map(|(i, title)| json!({ "id": i + 1, "title": title }))
+                    .collect();
+                return json!({ "results": results });
+            }
+            match path
+                .strip_prefix("/movie/")
+                .and_then(|id| id.parse::<usize>().ok())
+            {
+                Some(id) => json!({
+                    "title": TITLES[id - 1],
+                    "overview": format!("Overview {id}"),
+                    "poster_path": format!("/poster{id}.jpg"),
+                }),
+                None => json!({}),
+            }
+        },
+        std::time::Duration::from_millis(100),
+    )
+    .await;
+
+    // Refresh the same library once per concurrency limit and snapshot the results.
+    let mut runs = Vec::new();
+    for concurrency in [1, 8] {
+        let pool = test_pool().await;
+        let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+        rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+            .await
+            .unwrap();
+        rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+            .await
+            .unwrap();
+
+        let mut progress = Vec::new();
+        let started = std::time::Instant::now();
+        rustfin_server::artwork::refresh_library_metadata_with_concurrency(
+            &pool,
+            &lib.id,
+            "movies",
+            false,
+            concurrency,
+            |done, total| {
+                progress.push((done, total));
+                async {}
+            },
+        )
+        .await
+        .unwrap();
+        let elapsed = started.elapsed();
+
+        assert_eq!(progress.first(), Some(&(0, TITLES.len())));
+        assert_eq!(progress.last(), Some(&(TITLES.len(), TITLES.len())));
+
+        let mut items: Vec<(String, Option<String>, Option<String>)> =
+            rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|i| (i.title, i.overview, i.poster_url))
+                .collect();
+        items.sort();
+        runs.push((elapsed, items));
+    }
+
+    let (sequential_time, sequential_items) = &runs[0];
+    let (concurrent_time, concurrent_items) = &runs[1];
+    assert_eq!(sequential_items, concurrent_items);
+    assert!(
+        sequential_items
+            .iter()
+            .all(|(_, overview, poster)| overview.is_some() && poster.is_some())
+    );
+    // Two stubbed requests per item: ~1.6s one at a time, ~0.2s with all eight in flight.
+    assert!(
+        *concurrent_time * 2 < *sequential_time,
+        "concurrent {concurrent_time:?} vs sequential {sequential_time:?}"
+    );
+}
+
+// ---------------------------------------------------------------------------
+// Scanner integration tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn scan_movie_library_creates_items() {
+    // Create temp dir with movie files
+    let tmp = TempDir::new("rustfin_test_movies");
+    std::fs::create_dir_all(tmp.join("The Matrix (1999)")).unwrap();
+    tmp.touch("The Matrix (1999)/The Matrix (1999).mkv");
     std::fs::create_dir_all(tmp.join("Inception (2010)")).unwrap();
-    std::fs::write(tmp.join("Inception (2010)/Inception.2010.mkv"), b"fake").unwrap();
+    tmp.touch("Inception (2010)/Inception.2010.mkv");
+
+    let pool = test_pool().await;
+
+    // Create library pointing to tmp dir
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+
+    // Run scan directly
+    let result = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    assert_eq!(result.added, 2);
+
+    // Verify items created
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 2);
+
+    let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
+    assert!(titles.contains(&"The Matrix"));
+    assert!(titles.contains(&"Inception"));
+
+    // Verify year is set
+    let matrix = items.iter().find(|i| i.title == "The Matrix").unwrap();
+    assert_eq!(matrix.year, Some(1999));
+    assert_eq!(matrix.kind, "movie");
+
+    // Cleanup
+}
+
+#[tokio::test]
+async fn scan_tv_library_creates_series_hierarchy() {
+    // Create temp dir with TV show structure
+    let tmp = TempDir::new("rustfin_test_tv");
+    std::fs::create_dir_all(tmp.join("Breaking Bad/Season 01")).unwrap();
+    std::fs::write(
+        tmp.join("Breaking Bad/Season 01/Breaking.Bad.S01E01.Pilot.mkv"),
+        b"fake",
+    )
+    .unwrap();
+    std::fs::write(
+        tmp.join("Breaking Bad/Season 01/Breaking.Bad.S01E02.Cat's.in.the.Bag.mkv"),
+        b"fake",
+    )
+    .unwrap();
+    std::fs::create_dir_all(tmp.join("Breaking Bad/Season 02")).unwrap();
+    std::fs::write(
+        tmp.join("Breaking Bad/Season 02/Breaking.Bad.S02E01.Seven.Thirty.Seven.mkv"),
+        b"fake",
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV Shows",
+        "tv_shows",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+
+    let result = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "tv_shows")
+        .await
+        .unwrap();
+    assert_eq!(result.added, 3);
+
+    // Top-level items should be series only
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].kind, "series");
+    assert_eq!(items[0].title, "Breaking Bad");
+
+    // Series should have seasons as children
+    let seasons = rustfin_db::repo::items::get_children(&pool, &items[0].id)
+        .await
+        .unwrap();
+    assert_eq!(seasons.len(), 2);
+    assert!(seasons.iter().all(|s| s.kind == "season"));
+
+    // Season 1 should have 2 episodes
+    let s1 = seasons.iter().find(|s| s.title == "Season 1").unwrap();
+    let episodes = rustfin_db::repo::items::get_children(&pool, &s1.id)
+        .await
+        .unwrap();
+    assert_eq!(episodes.len(), 2);
+    assert!(episodes.iter().all(|e| e.kind == "episode"));
+
+    // Cleanup
+}
+
+#[tokio::test]
+async fn series_episodes_are_listed_flat_in_season_and_episode_order() {
+    let tmp = TempDir::new("rf_flat_eps");
+    for rel in [
+        "Severance/Season 02/Severance.S02E01.mkv",
+        "Severance/Season 01/Severance.S01E10.mkv",
+        "Severance/Season 01/Severance.S01E02.mkv",
+        "Severance/Season 01/Severance.S01E01.mkv",
+    ] {
+        tmp.touch(rel);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+    let series = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let seasons = rustfin_db::repo::items::get_children(&pool, &series.id)
+        .await
+        .unwrap();
+    let season_id = |title: &str| {
+        seasons
+            .iter()
+            .find(|s| s.title == title)
+            .map(|s| s.id.clone())
+            .unwrap()
+    };
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let admin_id = server
+        .get("/api/v1/users/me")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json::<Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .get(&format!("/api/v1/items/{}/episodes", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let episodes: Vec<Value> = resp.json();
+    let order: Vec<(i64, i64)> = episodes
+        .iter()
+        .map(|e| {
+            (
+                e["season_number"].as_i64().unwrap(),
+                e["episode_number"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(order, vec![(1, 1), (1, 2), (1, 10), (2, 1)]);
+    assert!(
+        episodes[..3]
+            .iter()
+            .all(|e| e["season_id"] == season_id("Season 1").as_str())
+    );
+    assert_eq!(episodes[3]["season_id"], season_id("Season 2").as_str());
+    assert!(episodes.iter().all(|e| e["file_id"].is_string()));
+    assert!(episodes.iter().all(|e| e["play_state"]["played"] == false));
+
+    // Play state is the caller's own.
+    let watched = episodes[1]["id"].as_str().unwrap();
+    rustfin_db::repo::playstate::update_progress(&pool, &admin_id, watched, 1_200_000, true, 100)
+        .await
+        .unwrap();
+    let episodes: Vec<Value> = server
+        .get(&format!("/api/v1/items/{}/episodes", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(episodes[1]["play_state"]["played"], true);
+    assert_eq!(episodes[1]["play_state"]["progress_ms"], 1_200_000);
+    assert_eq!(episodes[0]["play_state"]["played"], false);
+
+    // Only series have a flat episode list.
+    let resp = server
+        .get(&format!("/api/v1/items/{}/episodes", season_id("Season 1")))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn episode_items_carry_series_and_numbers_while_movies_do_not() {
+    let tmp = TempDir::new("rf_ep_ctx");
+    for rel in [
+        "tv/Andor/Season 02/Andor.S02E03.mkv",
+        "movies/Arrival (2016)/Arrival (2016).mkv",
+    ] {
+        tmp.touch(rel);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let tv = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tmp.join("tv").to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let movies = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.join("movies").to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &tv.id, "tv_shows")
+        .await
+        .unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &movies.id, "movies")
+        .await
+        .unwrap();
+    let series = rustfin_db::repo::items::get_library_items(&pool, &tv.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let season = rustfin_db::repo::items::get_children(&pool, &series.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let episode = rustfin_db::repo::items::get_children(&pool, &season.id)
+        .await
+        .unwrap()
+        .remove(0);
+    let movie = rustfin_db::repo::items::get_library_items(&pool, &movies.id)
+        .await
+        .unwrap()
+        .remove(0);
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .get(&format!("/api/v1/items/{}", episode.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["series_id"], series.id.as_str());
+    assert_eq!(body["series_title"], "Andor");
+    assert_eq!(body["season_number"], 2);
+    assert_eq!(body["episode_number"], 3);
+
+    // Listings carry the same context.
+    let children: Vec<Value> = server
+        .get(&format!("/api/v1/items/{}/children", season.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(children[0]["series_title"], "Andor");
+    assert_eq!(children[0]["episode_number"], 3);
+
+    let resp = server
+        .get(&format!("/api/v1/items/{}", movie.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "movie");
+    for field in [
+        "series_id",
+        "series_title",
+        "season_number",
+        "episode_number",
+    ] {
+        assert!(body[field].is_null(), "{field} should be null for a movie");
+    }
+}
+
+#[tokio::test]
+async fn search_matches_original_title_alias() {
+    let tmp = TempDir::new("rf_alias");
+    let movie_dir = tmp.join("Parasite (2019)");
+    std::fs::create_dir_all(&movie_dir).unwrap();
+    std::fs::write(movie_dir.join("Parasite (2019).mkv"), b"fake").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let movie = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()
+        .remove(0);
+    rustfin_metadata::merge::merge_metadata(
+        &pool,
+        &movie.id,
+        &rustfin_metadata::ItemMetadata {
+            title: Some("Parasite".into()),
+            original_title: Some("기생충".into()),
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    for q in ["기생충", "parasite"] {
+        let resp = server
+            .get("/api/v1/search")
+            .add_query_param("q", q)
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+        let body: Vec<Value> = resp.json();
+        assert_eq!(body.len(), 1, "search for {q}");
+        assert_eq!(body[0]["id"], movie.id.as_str());
+    }
+
+    let body: Vec<Value> = server
+        .get("/api/v1/search")
+        .add_query_param("q", "Snowpiercer")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert!(body.is_empty());
+
+    server
+        .get("/api/v1/search")
+        .add_query_param("q", "  ")
+        .add_header(hdr_name, hdr_val)
+        .await
+        .assert_status_bad_request();
+}
+
+#[tokio::test]
+async fn search_only_returns_items_from_accessible_libraries() {
+    let tmp = TempDir::new("rf_search_acl");
+    let open_dir = tmp.join("open");
+    let hidden_dir = tmp.join("hidden");
+    std::fs::create_dir_all(open_dir.join("Heat (1995)")).unwrap();
+    std::fs::write(open_dir.join("Heat (1995)/Heat (1995).mkv"), b"fake").unwrap();
+    std::fs::create_dir_all(hidden_dir.join("Heat Wave (2011)")).unwrap();
+    std::fs::write(
+        hidden_dir.join("Heat Wave (2011)/Heat Wave (2011).mkv"),
+        b"fake",
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let viewer_id =
+        rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+            .await
+            .unwrap();
+    let mut libs = Vec::new();
+    for (name, dir) in [("Open", &open_dir), ("Hidden", &hidden_dir)] {
+        let lib = scanned_library(&pool, name, "movies", dir).await;
+        libs.push(lib);
+    }
+    rustfin_db::repo::users::set_library_access(
+        &pool,
+        &viewer_id,
+        std::slice::from_ref(&libs[0].id),
+    )
+    .await
+    .unwrap();
+
+    let server = test_server_for_pool(pool);
+    let search = |token: String| {
+        let (hdr_name, hdr_val) = auth_hdr(&token);
+        server
+            .get("/api/v1/search")
+            .add_query_param("q", "heat")
+            .add_header(hdr_name, hdr_val)
+    };
+
+    let admin = login(&server, "admin", "admin_secure_123").await;
+    let resp = search(admin).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Vec<Value>>().len(), 2);
+
+    let viewer = login(&server, "viewer", "viewer_secure_123").await;
+    let resp = search(viewer).await;
+    resp.assert_status_ok();
+    let body: Vec<Value> = resp.json();
+    assert_eq!(body.len(), 1);
+    assert_eq!(body[0]["library_id"], libs[0].id.as_str());
+    assert_eq!(body[0]["title"], "Heat");
+}
+
+#[tokio::test]
+async fn artwork_upload_is_chunked_resumable_and_validated() {
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+    // 1x1 PNG with a tEXt chunk, and one whose IHDR claims 100000x100000.
+    let png = hex(
+        "89504e470d0a1a0a0000000d4948445200000001000000010802000000907753de\
+         0000001774455874436f6d6d656e7400736563726574206c6f636174696f6e33ee7c68\
+         0000000c49444154789c63f8cfc0000003010100c9fe92ef0000000049454e44ae426082",
+    );
+    let bomb = hex(
+        "89504e470d0a1a0a0000000d49484452000186a0000186a0080200000027309c9f\
+         0000000c49444154789c63f8cfc0000003010100c9fe92ef0000000049454e44ae426082",
+    );
+
+    let tmp = TempDir::new("rf_upload");
+    let movie_dir = tmp.join("media/Arrival (2016)");
+    std::fs::create_dir_all(&movie_dir).unwrap();
+    std::fs::write(movie_dir.join("Arrival (2016).mkv"), b"fake").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp.join("media")).await;
+    let movie = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()
+        .remove(0);
+
+    let state = AppState {
+        cache_dir: tmp.join("cache"),
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            rustfin_transcoder::TranscoderConfig {
+                ffmpeg_path: create_copying_ffmpeg_script(),
+                ..Default::default()
+            },
+        )),
+        ..test_state_for_pool(pool.clone())
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let start = |size: usize| {
+        server
+            .post(&format!("/api/v1/items/{}/images/poster/uploads", movie.id))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "size": size }))
+    };
+    let send = |upload_id: &str, offset: usize, chunk: &[u8]| {
+        server
+            .put(&format!("/api/v1/uploads/images/{upload_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .add_header("upload-offset", offset.to_string())
+            .bytes(chunk.to_vec().into())
+    };
+
+    // A valid image sent in two chunks, resuming from the offset the server reports.
+    let resp = start(png.len()).await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let upload_id = resp.json::<Value>()["upload_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let resp = send(&upload_id, 0, &png[..40]).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["complete"], false);
+    let status: Value = server
+        .get(&format!("/api/v1/uploads/images/{upload_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert_eq!(status["offset"], 40);
+    send(&upload_id, 0, &png[..40])
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    // The same chunk sent twice at once is only appended once.
+    let (first, second) = tokio::join!(
+        send(&upload_id, 40, &png[40..60]),
+        send(&upload_id, 40, &png[40..60])
+    );
+    let mut statuses = [first.status_code(), second.status_code()];
+    statuses.sort();
+    assert_eq!(
+        statuses,
+        [axum::http::StatusCode::OK, axum::http::StatusCode::CONFLICT]
+    );
+    let resp = send(&upload_id, 60, &png[60..]).await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["complete"], true);
+    assert_eq!(body["image"]["format"], "png");
+    assert_eq!(body["image"]["width"], 1);
+    assert_eq!(body["image"]["height"], 1);
+
+    // Stored without the text chunk, and served as the item's poster.
+    let resp = server
+        .get(&format!("/api/v1/items/{}/images/poster", movie.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.header("content-type"), "image/png");
+    let served = resp.as_bytes().to_vec();
+    assert!(served.starts_with(b"\x89PNG"));
+    assert!(served.len() < png.len());
+    assert!(!served.windows(6).any(|w| w == b"secret"));
+    let poster = rustfin_db::repo::items::get_item_image_url(&pool, &movie.id, "poster")
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Text named like a PNG, and a header claiming a huge canvas, are both refused
+    // and leave the poster alone.
+    let text = b"this is not really poster.png\n";
+    for bytes in [&text[..], &bomb[..]] {
+        let resp = start(bytes.len()).await;
+        let upload_id = resp.json::<Value>()["upload_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        send(&upload_id, 0, bytes)
+            .await
+            .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        server
+            .get(&format!("/api/v1/uploads/images/{upload_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await
+            .assert_status_not_found();
+    }
+    assert_eq!(
+        rustfin_db::repo::items::get_item_image_url(&pool, &movie.id, "poster")
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(poster.as_str())
+    );
+
+    start(0)
+        .await
+        .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Uploads left unfinished count against the user until they are cancelled.
+    let max = rustfin_server::image_upload::MAX_UPLOAD_BYTES as usize;
+    let mut pending = Vec::new();
+    for _ in 0..4 {
+        let resp = start(max).await;
+        resp.assert_status(axum::http::StatusCode::CREATED);
+        pending.push(
+            resp.json::<Value>()["upload_id"]
+                .as_str()
+                .unwrap()
+                .to_string(),
+        );
+    }
+    start(1)
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    server
+        .delete(&format!("/api/v1/uploads/images/{}", pending[0]))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status_ok();
+    start(1)
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn deterministic_ids_are_a_library_setting() {
+    let tmp = TempDir::new("rf_det_ids");
+    let movie_dir = tmp.join("Arrival (2016)");
+    std::fs::create_dir_all(&movie_dir).unwrap();
+    std::fs::write(movie_dir.join("Arrival (2016).mkv"), b"fake").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Movies",
+            "kind": "movies",
+            "paths": [tmp.to_string_lossy()],
+            "settings": { "deterministic_ids": true }
+        }))
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["settings"]["deterministic_ids"], true);
+    let lib_id = body["id"].as_str().unwrap().to_string();
+
+    rustfin_scanner::scan::run_library_scan(&pool, &lib_id, "movies")
+        .await
+        .unwrap();
+    let movie = rustfin_db::repo::items::get_library_items(&pool, &lib_id)
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        movie.id,
+        rustfin_scanner::scan::deterministic_item_id(&lib_id, "movie", None, "Arrival")
+    );
+
+    server
+        .patch(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "deterministic_ids": false } }))
+        .await
+        .assert_status_ok();
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}"))
+        .add_header(hdr_name, hdr_val)
+        .await;
+    assert_eq!(resp.json::<Value>()["settings"]["deterministic_ids"], false);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn library_languages_come_from_recorded_probes_and_filter_items() {
+    let tmp = TempDir::new("rf_languages");
+    for movie in ["Your Name (2016)", "Arrival (2016)"] {
+        std::fs::create_dir_all(tmp.join("media").join(movie)).unwrap();
+        std::fs::write(
+            tmp.join("media").join(movie).join(format!("{movie}.mkv")),
+            b"fake",
+        )
+        .unwrap();
+    }
+    // Japanese audio with English subtitles for "Your Name", English audio otherwise.
+    let probe = |audio: &str, subtitles: &[&str]| {
+        let mut streams = vec![
+            json!({ "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 }),
+            json!({ "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+                    "tags": { "language": audio } }),
+        ];
+        for (n, language) in subtitles.iter().enumerate() {
+            streams.push(
+                json!({ "index": 2 + n, "codec_type": "subtitle", "codec_name": "subrip",
+                                 "tags": { "language": language } }),
+            );
+        }
+        json!({ "format": { "format_name": "matroska,webm", "duration": "6000.0" }, "streams": streams })
+    };
+    std::fs::write(tmp.join("jpn.json"), probe("jpn", &["eng"]).to_string()).unwrap();
+    std::fs::write(tmp.join("eng.json"), probe("eng", &["und"]).to_string()).unwrap();
+    let script = tmp.join("ffprobe.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/usr/bin/env bash\ncase \"$*\" in\n  *\"Your Name\"*) cat '{0}/jpn.json' ;;\n  *) cat '{0}/eng.json' ;;\nesac\n",
+            tmp.display()
+        ),
+    )
+    .unwrap();
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let state = AppState {
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            rustfin_transcoder::TranscoderConfig {
+                ffprobe_path: script,
+                ..Default::default()
+            },
+        )),
+        ..test_state_for_pool(pool.clone())
+    };
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp.join("media")).await;
+    // The post-scan pass probes each new file once.
+    assert_eq!(
+        rustfin_server::probe_cache::probe_new_files(&state, &lib.id)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        rustfin_server::probe_cache::probe_new_files(&state, &lib.id)
+            .await
+            .unwrap(),
+        0
+    );
+
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}/languages", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        resp.json::<Value>(),
+        json!({
+            "audio": [
+                { "language": "eng", "item_count": 1 },
+                { "language": "jpn", "item_count": 1 }
+            ],
+            "subtitles": [{ "language": "eng", "item_count": 1 }]
+        })
+    );
+
+    let titles = |query: &'static str| {
+        let server = &server;
+        let (hdr_name, hdr_val) = (hdr_name.clone(), hdr_val.clone());
+        let path = format!("/api/v1/libraries/{}/items?{query}", lib.id);
+        async move {
+            let resp = server.get(&path).add_header(hdr_name, hdr_val).await;
+            resp.assert_status_ok();
+            resp.json::<Vec<Value>>()
+                .iter()
+                .map(|item| item["title"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(titles("audio_lang=jpn").await, ["Your Name"]);
+    assert_eq!(titles("audio_lang=ENG").await, ["Arrival"]);
+    assert_eq!(titles("subtitle_lang=eng").await, ["Your Name"]);
+    assert!(titles("audio_lang=jpn&subtitle_lang=fre").await.is_empty());
+    assert_eq!(titles("audio_lang=").await, ["Arrival", "Your Name"]);
+
+    // Episodes count towards their series.
+    let tv = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tmp.join("tv").to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    for episode in ["Shogun.S01E01.mkv", "Shogun.S01E02.mkv"] {
+        tmp.touch(format!("tv/Shogun/Season 01/{episode}"));
+    }
+    // Scan jobs probe the files they add.
+    server
+        .post(&format!("/api/v1/libraries/{}/scan", tv.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let mut languages = Value::Null;
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{}/languages", tv.id))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        languages = resp.json::<Value>();
+        if languages["audio"].as_array().is_some_and(|a| !a.is_empty()) {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(
+        languages["audio"],
+        json!([{ "language": "eng", "item_count": 1 }])
+    );
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}/items?audio_lang=eng", tv.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.json::<Vec<Value>>()[0]["title"], "Shogun");
+}
+
+#[tokio::test]
+async fn person_details_are_fetched_once_and_then_served_from_cache() {
+    static PERSON_FETCHES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/person/6193" => {
+            PERSON_FETCHES.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            json!({
+                "id": 6193,
+                "name": "Leonardo DiCaprio",
+                "biography": "An American actor and film producer.",
+                "birthday": "1974-11-11",
+                "deathday": null,
+                "place_of_birth": "Los Angeles, California, USA",
+                "profile_path": "/leo.jpg"
+            })
+        }
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    for _ in 0..2 {
+        let resp = server
+            .get("/api/v1/people/tmdb/6193")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+        let body: Value = resp.json();
+        assert_eq!(body["name"], "Leonardo DiCaprio");
+        assert_eq!(body["biography"], "An American actor and film producer.");
+        assert_eq!(body["birthday"], "1974-11-11");
+        assert!(body["deathday"].is_null());
+        assert_eq!(
+            body["image_url"],
+            "/api/v1/images/proxy?url=https%3A%2F%2Fimage.tmdb.org%2Ft%2Fp%2Foriginal%2Fleo.jpg"
+        );
+    }
+    assert_eq!(PERSON_FETCHES.load(std::sync::atomic::Ordering::SeqCst), 1);
+    let cached = rustfin_db::repo::people::get_person(&pool, "tmdb", "6193")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        cached.place_of_birth.as_deref(),
+        Some("Los Angeles, California, USA")
+    );
+
+    // Details older than the cache lifetime are fetched again.
+    let stale = rustfin_db::repo::people::PersonRow {
+        fetched_ts: cached.fetched_ts - 31 * 24 * 60 * 60,
+        ..cached
+    };
+    rustfin_db::repo::people::upsert_person(&pool, &stale)
+        .await
+        .unwrap();
+    server
+        .get("/api/v1/people/tmdb/6193")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status_ok();
+    assert_eq!(PERSON_FETCHES.load(std::sync::atomic::Ordering::SeqCst), 2);
+    let refreshed = rustfin_db::repo::people::get_person(&pool, "tmdb", "6193")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(refreshed.fetched_ts > stale.fetched_ts);
+
+    for url in [
+        "/api/v1/people/imdb/nm0000138",
+        "/api/v1/people/tmdb/..%2Fmovie",
+    ] {
+        let resp = server
+            .get(url)
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status(axum::http::StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn identifying_a_series_by_tmdb_id_populates_its_metadata() {
+    let tmp = TempDir::new("rustfin_identify");
+    tmp.touch("Bad Show/Season 01/Bad.Show.S01E01.mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/tv/1396" => json!({
+            "id": 1396,
+            "name": "Breaking Bad",
+            "overview": "A chemistry teacher turns to crime.",
+            "first_air_date": "2008-01-20",
+            "poster_path": "/bb-poster.jpg"
+        }),
+        "/tv/999999" => Value::Null,
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+    let tv = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+    let series = rustfin_db::repo::items::get_library_items(&pool, &tv.id)
+        .await
+        .unwrap()
+        .remove(0);
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // An id the provider does not know is rejected and nothing is stored.
+    let resp = server
+        .post(&format!("/api/v1/items/{}/identify", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "provider": "tmdb", "provider_id": "999999" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let body: Value = resp.json();
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap_or_default()
+            .contains("999999"),
+        "{body}"
+    );
+    let providers = rustfin_metadata::merge::get_provider_ids(&pool, &series.id)
+        .await
+        .unwrap();
+    assert!(providers.is_empty());
+
+    let resp = server
+        .post(&format!("/api/v1/items/{}/identify", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "provider": "tmdb", "provider_id": "1396", "refresh": true }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["id"], series.id);
+    assert_eq!(body["overview"], "A chemistry teacher turns to crime.");
+    let (poster, ..) = rustfin_db::repo::items::get_item_artwork(&pool, &series.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        poster.as_deref(),
+        Some("https://image.tmdb.org/t/p/original/bb-poster.jpg")
+    );
+    assert!(body["poster_url"].is_string(), "{body}");
+    let providers = rustfin_metadata::merge::get_provider_ids(&pool, &series.id)
+        .await
+        .unwrap();
+    assert_eq!(providers, vec![("tmdb".to_string(), "1396".to_string())]);
+}
+
+#[tokio::test]
+async fn remote_images_are_proxied_and_cached_for_allowlisted_hosts_only() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    static FETCHES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            let mut buf = vec![0u8; 8192];
+            let n = sock.read(&mut buf).await.unwrap_or(0);
+            let req = String::from_utf8_lossy(&buf[..n]).to_ascii_lowercase();
+            assert!(!req.contains("\r\nreferer:"), "{req}");
+            FETCHES.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if req.starts_with("get /huge") {
+                // No length up front: the proxy has to notice while reading.
+                let head =
+                    "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\nconnection: close\r\n\r\n";
+                let _ = sock.write_all(head.as_bytes()).await;
+                let chunk = vec![0u8; 1024 * 1024];
+                for _ in 0..32 {
+                    if sock.write_all(&chunk).await.is_err() {
+                        break;
+                    }
+                }
+                continue;
+            }
+            assert!(!req.contains('#'), "{req}");
+            let body = b"\x89PNG-proxied";
+            let head = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: image/png\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            );
+            let _ = sock.write_all(head.as_bytes()).await;
+            let _ = sock.write_all(body).await;
+        }
+    });
+    let image_url = format!("http://{addr}/t/p/w342/poster.png");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // Loopback addresses are refused until an admin allowlists them.
+    for url in [image_url.as_str(), "http://example.com/poster.png"] {
+        let resp = server
+            .get("/api/v1/images/proxy")
+            .add_query_param("url", url)
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+    }
+    assert_eq!(FETCHES.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+    server
+        .get("/api/v1/images/proxy")
+        .add_query_param("url", &image_url)
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+
+    rustfin_db::repo::settings::set(&pool, "image_proxy_hosts", "127.0.0.1")
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let resp = server
+            .get("/api/v1/images/proxy")
+            .add_query_param("url", &image_url)
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+        assert_eq!(resp.header("content-type"), "image/png");
+        assert_eq!(resp.header("referrer-policy"), "no-referrer");
+        assert_eq!(resp.as_bytes().as_ref(), b"\x89PNG-proxied");
+    }
+    assert_eq!(FETCHES.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+    // A fragment doesn't make a new cache entry; a query does.
+    let resp = server
+        .get("/api/v1/images/proxy")
+        .add_query_param("url", format!("{image_url}#top"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"\x89PNG-proxied");
+    assert_eq!(FETCHES.load(std::sync::atomic::Ordering::SeqCst), 1);
+    for _ in 0..2 {
+        let resp = server
+            .get("/api/v1/images/proxy")
+            .add_query_param("url", format!("{image_url}?variant=2"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+    }
+    assert_eq!(FETCHES.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    let resp = server
+        .get("/api/v1/images/proxy")
+        .add_query_param("url", format!("http://{addr}/huge.png"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+    let resp = server
+        .get("/api/v1/images/proxy")
+        .add_query_param("url", "http://example.com/poster.png")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn watched_through_marks_episodes_up_to_the_given_one() {
+    let tmp = TempDir::new("rf_watched_through");
+    let mut files = Vec::new();
+    for ep in 1..=3 {
+        files.push(format!("Show/Season 01/Show.S01E{ep:02}.mkv"));
+    }
+    // Numbered past 9 so string ordering would put E10 before E05.
+    for ep in [1, 2, 5, 6, 10] {
+        files.push(format!("Show/Season 02/Show.S02E{ep:02}.mkv"));
+    }
+    files.push("Show/Season 03/Show.S03E01.mkv".to_string());
+    files.push("Show/Season 00/Show.S00E01.mkv".to_string());
+    files.push("Show/Season 00/Show.S00E02.mkv".to_string());
+    for rel in &files {
+        tmp.touch(rel);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let tv = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+    let series = rustfin_db::repo::items::get_library_items(&pool, &tv.id)
+        .await
+        .unwrap()
+        .remove(0);
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post(&format!("/api/v1/items/{}/watched-through", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "season": 2, "episode": 5 }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["marked"], 6);
+
+    let resp = server
+        .get(&format!("/api/v1/items/{}/episodes", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let played: Vec<(i64, i64, bool)> = resp
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|ep| {
+            (
+                ep["season_number"].as_i64().unwrap(),
+                ep["episode_number"].as_i64().unwrap(),
+                ep["play_state"]["played"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        played,
+        vec![
+            (0, 1, false),
+            (0, 2, false),
+            (1, 1, true),
+            (1, 2, true),
+            (1, 3, true),
+            (2, 1, true),
+            (2, 2, true),
+            (2, 5, true),
+            (2, 6, false),
+            (2, 10, false),
+            (3, 1, false),
+        ]
+    );
+
+    // Specials are only marked when the request targets them.
+    let resp = server
+        .post(&format!("/api/v1/items/{}/watched-through", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "season": 0, "episode": 1 }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["marked"], 1);
+
+    // Only series have episodes to mark.
+    let episode_id = server
+        .get(&format!("/api/v1/items/{}/episodes", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json::<Vec<Value>>()[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    server
+        .post(&format!("/api/v1/items/{episode_id}/watched-through"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "season": 1, "episode": 1 }))
+        .await
+        .assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn play_queue_steps_through_episodes_with_next() {
+    let tmp = TempDir::new("rf_play_queue");
+    for ep in 1..=3 {
+        tmp.touch(format!("Show/Season 01/Show.S01E{ep:02}.mkv"));
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "other", "other_secure_123", "admin")
+        .await
+        .unwrap();
+    let tv = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+    let series = rustfin_db::repo::items::get_library_items(&pool, &tv.id)
+        .await
+        .unwrap()
+        .remove(0);
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let episodes: Vec<String> = server
+        .get(&format!("/api/v1/items/{}/episodes", series.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json::<Vec<Value>>()
+        .iter()
+        .map(|ep| ep["id"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(episodes.len(), 3);
+
+    let resp = server
+        .post("/api/v1/playback/queue")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "item_ids": episodes }))
+        .await;
+    resp.assert_status_ok();
+    let queue_id = resp.json::<Value>()["queue_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let next = |after: Option<&str>| {
+        let mut req = server
+            .get(&format!("/api/v1/playback/queue/{queue_id}/next"))
+            .add_header(hdr_name.clone(), hdr_val.clone());
+        if let Some(after) = after {
+            req = req.add_query_param("after", after);
+        }
+        req
+    };
+
+    let resp = next(None).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["item"]["id"], episodes[0].as_str());
+
+    for (position, (previous, expected)) in episodes.iter().zip(&episodes[1..]).enumerate() {
+        let resp = next(Some(previous)).await;
+        resp.assert_status_ok();
+        let body: Value = resp.json();
+        assert_eq!(body["position"], position + 1);
+        assert_eq!(body["item"]["id"], expected.as_str());
+        assert_eq!(body["item"]["episode_number"], position as i64 + 2);
+        assert_eq!(body["playback"]["item_id"], expected.as_str());
+        let file_id = body["playback"]["file_id"].as_str().unwrap();
+        assert!(
+            body["playback"]["direct_url"]
+                .as_str()
+                .unwrap()
+                .starts_with(&format!("/stream/file/{file_id}?st="))
+        );
+    }
+
+    next(Some(&episodes[2]))
+        .await
+        .assert_status(axum::http::StatusCode::NO_CONTENT);
+    next(Some(&series.id))
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    // The queue survives a reload but belongs to its creator.
+    let resp = server
+        .get(&format!("/api/v1/playback/queue/{queue_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["item_ids"], json!(episodes));
+    let other_token = login(&server, "other", "other_secure_123").await;
+    let (other_name, other_val) = auth_hdr(&other_token);
+    server
+        .get(&format!("/api/v1/playback/queue/{queue_id}/next"))
+        .add_header(other_name.clone(), other_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    server
+        .post("/api/v1/playback/queue")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "item_ids": [episodes[0], episodes[0]] }))
+        .await
+        .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Only the creator can delete a queue.
+    server
+        .delete(&format!("/api/v1/playback/queue/{queue_id}"))
+        .add_header(other_name.clone(), other_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    // Each user keeps their newest 20 queues.
+    let mut newest = String::new();
+    for _ in 0..20 {
+        let resp = server
+            .post("/api/v1/playback/queue")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "item_ids": [episodes[0]] }))
+            .await;
+        resp.assert_status_ok();
+        newest = resp.json::<Value>()["queue_id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+    }
+    server
+        .get(&format!("/api/v1/playback/queue/{queue_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    for status in [
+        axum::http::StatusCode::OK,
+        axum::http::StatusCode::NOT_FOUND,
+    ] {
+        server
+            .delete(&format!("/api/v1/playback/queue/{newest}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await
+            .assert_status(status);
+    }
+}
+
+#[tokio::test]
+async fn slow_requests_are_logged_by_route_template_only() {
+    install_test_subscriber();
+    let api = axum::Router::new()
+        .route(
+            "/slow/{name}",
+            axum::routing::get(|| async {
+                tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+                "done"
+            }),
+        )
+        .route("/fast/{name}", axum::routing::get(|| async { "done" }))
+        .layer(axum::middleware::from_fn_with_state(
+            std::time::Duration::from_millis(50),
+            rustfin_server::request_log::log_slow_requests,
+        ));
+    let server = TestServer::new(axum::Router::new().nest("/api/v1", api)).unwrap();
+    let thread = "slow_requests_are_logged_by_route_template_only";
+
+    server
+        .get("/api/v1/fast/secret-media-path")
+        .await
+        .assert_status_ok();
+    assert!(recorded_warnings(thread).is_empty());
+
+    server
+        .get("/api/v1/slow/secret-media-path?token=hunter2")
+        .await
+        .assert_status_ok();
+    let warnings = recorded_warnings(thread);
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].contains("slow request"), "{}", warnings[0]);
+    assert!(
+        warnings[0].contains("/api/v1/slow/{name}"),
+        "{}",
+        warnings[0]
+    );
+    assert!(warnings[0].contains("status=200"), "{}", warnings[0]);
+    assert!(
+        !warnings[0].contains("secret-media-path"),
+        "{}",
+        warnings[0]
+    );
+    assert!(!warnings[0].contains("hunter2"), "{}", warnings[0]);
+}
+
+#[tokio::test]
+async fn slow_queries_are_logged_without_their_parameters() {
+    install_test_subscriber();
+    let opts = rustfin_db::connect_options(":memory:", std::time::Duration::from_millis(50))
+        .unwrap()
+        .thread_name(|id| format!("slow-query-log-{id}"));
+    let pool = rustfin_db::connect_with(opts).await.unwrap();
+
+    sqlx::query("SELECT ?")
+        .bind("/srv/media/secret.mkv")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert!(recorded_warnings("slow-query-log-").is_empty());
+
+    // Counting to a few million in a recursive CTE takes well over 50ms.
+    sqlx::query(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 5000000) \
+         SELECT count(*), ? FROM n",
+    )
+    .bind("/srv/media/secret.mkv")
+    .execute(&pool)
+    .await
+    .unwrap();
+    let warnings = recorded_warnings("slow-query-log-");
+    assert_eq!(warnings.len(), 1, "{warnings:?}");
+    assert!(warnings[0].contains("slow statement"), "{}", warnings[0]);
+    assert!(warnings[0].contains("WITH RECURSIVE"), "{}", warnings[0]);
+    assert!(!warnings[0].contains("secret.mkv"), "{}", warnings[0]);
+}
+
+#[tokio::test]
+async fn m3u_files_are_imported_as_admin_playlists() {
+    let tmp = TempDir::new("rf_m3u");
+    std::fs::create_dir_all(tmp.join("Lists")).unwrap();
+    for name in ["Alpha (2001).mkv", "Bravo (2002).mkv", "Charlie (2003).mkv"] {
+        tmp.touch(name);
+    }
+    let playlist = tmp.join("Lists/Favourites.m3u");
+    std::fs::write(
+        &playlist,
+        format!(
+            "#EXTM3U\n#EXTINF:100,Bravo\n../Bravo (2002).mkv\nmissing.mkv\n{}\nhttps://cdn.example.com/x.mp4\n",
+            tmp.join("Alpha (2001).mkv").display()
+        ),
+    )
+    .unwrap();
+    // HLS manifests are streams, not playlists of library files.
+    std::fs::write(
+        tmp.join("Lists/stream.m3u8"),
+        "#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXTINF:6.0,\n../Charlie (2003).mkv\n",
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let list_playlists = async || {
+        server
+            .get("/api/v1/playlists")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await
+            .json::<Vec<Value>>()
+    };
+    let playlist_titles = async |id: &str| {
+        let resp = server
+            .get(&format!("/api/v1/playlists/{id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        resp.assert_status_ok();
+        resp.json::<Value>()["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["title"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let playlists = list_playlists().await;
+    assert_eq!(playlists.len(), 1, "{playlists:?}");
+    assert_eq!(playlists[0]["name"], "Favourites");
+    assert_eq!(playlists[0]["imported"], true);
+    assert_eq!(playlists[0]["library_id"], lib.id.as_str());
+    let playlist_id = playlists[0]["id"].as_str().unwrap().to_string();
+    assert_eq!(playlist_titles(&playlist_id).await, vec!["Bravo", "Alpha"]);
+
+    // An edited file is re-read on the next scan and keeps its playlist id.
+    std::fs::write(&playlist, "Charlie (2003).mkv\n../Charlie (2003).mkv\n").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&playlist)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))
+        .unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let playlists = list_playlists().await;
+    assert_eq!(playlists.len(), 1);
+    assert_eq!(playlists[0]["id"], playlist_id.as_str());
+    assert_eq!(playlist_titles(&playlist_id).await, vec!["Charlie"]);
+
+    // Other users do not see the admin's playlists.
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let viewer_token = login(&server, "viewer", "viewer_secure_123").await;
+    let (viewer_name, viewer_val) = auth_hdr(&viewer_token);
+    server
+        .get(&format!("/api/v1/playlists/{playlist_id}"))
+        .add_header(viewer_name, viewer_val)
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+
+    std::fs::remove_file(&playlist).unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    assert!(list_playlists().await.is_empty());
+}
+
+#[tokio::test]
+async fn remote_clients_cannot_stream_while_remote_access_is_off() {
+    let tmp = TempDir::new("rf_remote");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Local (2020).mkv");
+
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    let viewer_id =
+        rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+            .await
+            .unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_db::repo::users::set_library_access(&pool, &viewer_id, std::slice::from_ref(&lib.id))
+        .await
+        .unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+
+    // Each server sees every request as coming from `client`.
+    let server_from = |client: &str| {
+        let peer = std::net::SocketAddr::new(client.parse().unwrap(), 50_000);
+        TestServer::new(
+            build_router(test_state_for_pool(pool.clone()))
+                .layer(axum::Extension(axum::extract::ConnectInfo(peer))),
+        )
+        .unwrap()
+    };
+    let local = server_from("127.0.0.1");
+    let lan = server_from("192.168.1.40");
+    let remote = server_from("203.0.113.5");
+
+    let admin = login(&local, "admin", "admin_secure_123").await;
+    let viewer = login(&local, "viewer", "viewer_secure_123").await;
+    let descriptor: Value = local
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(auth_hdr(&viewer).0, auth_hdr(&viewer).1)
+        .await
+        .json();
+    let file_id = descriptor["file_id"].as_str().unwrap().to_string();
+    let direct_url = descriptor["direct_url"].as_str().unwrap().to_string();
+    let start_session = |server: &TestServer, token: &str| {
+        server
+            .post("/api/v1/playback/sessions")
+            .add_header(auth_hdr(token).0, auth_hdr(token).1)
+            .json(&json!({ "file_id": file_id }))
+    };
+
+    // Remote access is off by default: remote clients are refused, local ones are not.
+    let resp = remote.get(&direct_url).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+    assert!(
+        resp.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("remote access is disabled")
+    );
+    assert_eq!(
+        start_session(&remote, &viewer).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        start_session(&remote, &admin).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+    local.get(&direct_url).await.assert_status_ok();
+    lan.get(&direct_url).await.assert_status_ok();
+    assert_ne!(
+        start_session(&local, &viewer).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+
+    // A trusted proxy on loopback forwards the remote client's address.
+    rustfin_db::repo::settings::set(&pool, "trusted_proxies", r#"["127.0.0.0/8"]"#)
+        .await
+        .unwrap();
+    local
+        .get(&direct_url)
+        .add_header("x-forwarded-for", "203.0.113.5")
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    // The admin override only opens remote streaming to admins.
+    rustfin_db::repo::settings::set(&pool, "remote_access_admin_override", "true")
+        .await
+        .unwrap();
+    assert_ne!(
+        start_session(&remote, &admin).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        start_session(&remote, &viewer).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+
+    rustfin_db::repo::settings::set(&pool, "allow_remote_access", "true")
+        .await
+        .unwrap();
+    remote.get(&direct_url).await.assert_status_ok();
+    assert_ne!(
+        start_session(&remote, &viewer).await.status_code(),
+        axum::http::StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn optimize_compacts_the_database_and_is_admin_only() {
+    let dir = TempDir::new("rf_optimize");
+    let db_path = dir.join("rustfin.db");
+    let pool = rustfin_db::connect(db_path.to_str().unwrap())
+        .await
+        .unwrap();
+    rustfin_db::migrate::run(&pool).await.unwrap();
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+
+    // Bloat the file, then free most of it.
+    let filler = "x".repeat(4096);
+    for _ in 0..500 {
+        rustfin_db::repo::audit::record_audit(&pool, None, "test.fill", None, &filler)
+            .await
+            .unwrap();
+    }
+    sqlx::query("DELETE FROM audit_log WHERE action = 'test.fill'")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let admin = login(&server, "admin", "admin_secure_123").await;
+    let viewer = login(&server, "viewer", "viewer_secure_123").await;
+
+    let (name, value) = auth_hdr(&viewer);
+    server
+        .post("/api/v1/system/maintenance/optimize")
+        .add_header(name, value)
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    // Refused while a job is queued.
+    let job = rustfin_db::repo::jobs::create_job(&pool, "library_scan", None)
+        .await
+        .unwrap();
+    let (name, value) = auth_hdr(&admin);
+    server
+        .post("/api/v1/system/maintenance/optimize")
+        .add_header(name.clone(), value.clone())
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+    // A restart fails jobs the previous run left behind, so they stop blocking it.
+    assert_eq!(
+        rustfin_db::repo::jobs::fail_interrupted_jobs(&pool)
+            .await
+            .unwrap(),
+        1
+    );
+    let job = rustfin_db::repo::jobs::get_job(&pool, &job.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(job.status, "failed");
+
+    let resp = server
+        .post("/api/v1/system/maintenance/optimize")
+        .add_header(name, value)
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    let before = body["size_before_bytes"].as_i64().unwrap();
+    let after = body["size_after_bytes"].as_i64().unwrap();
+    assert!(before > 2_000_000, "{body}");
+    assert!(after > 0 && after < before / 2, "{body}");
+    pool.close().await;
+}
+
+#[tokio::test]
+async fn parse_preview_reports_how_a_scan_reads_a_path() {
+    let tmp = TempDir::new("rf_parse_preview");
+    let movie_dir = tmp.join("movies/Arrival (2016) [tmdb=329865]");
+    std::fs::create_dir_all(&movie_dir).unwrap();
+    let movie = movie_dir.join("Arrival (2016) 2160p BluRay.mkv");
+    std::fs::write(&movie, b"fake").unwrap();
+    std::fs::write(
+        movie_dir.join("Arrival (2016) 2160p BluRay.eng.forced.srt"),
+        b"1",
+    )
+    .unwrap();
+    let tv_root = tmp.join("tv");
+    let outside = tmp.touch("downloads/Arrival (2016).mkv");
+    tmp.touch("downloads/Arrival (2016).en.srt");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tv_root.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.join("movies").to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+
+    let server = test_server_for_pool(pool);
+    let admin = login(&server, "admin", "admin_secure_123").await;
+    let viewer = login(&server, "viewer", "viewer_secure_123").await;
+    let (name, value) = auth_hdr(&admin);
+    let preview = |path: String, kind: &str| {
+        server
+            .post("/api/v1/system/parse-preview")
+            .add_header(name.clone(), value.clone())
+            .json(&json!({ "path": path, "kind": kind }))
+    };
+
+    // Outside any library the whole path is parsed and sidecars are not listed.
+    let resp = preview(outside.to_string_lossy().to_string(), "movies").await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "movie", "{body}");
+    assert_eq!(body["title"], "Arrival");
+    assert_eq!(body["subtitles"], json!([]));
+
+    // Under a library root the folder supplies the year.
+    let resp = preview(movie.to_string_lossy().to_string(), "movies").await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "movie", "{body}");
+    assert_eq!(body["title"], "Arrival");
+    assert_eq!(body["year"], 2016);
+    assert!(body["season"].is_null());
+    assert_eq!(body["provider_ids"], json!({ "tmdb": "329865" }));
+    assert_eq!(body["resolution"], "2160p");
+    assert_eq!(body["source"], "BluRay");
+    let subtitles = body["subtitles"].as_array().unwrap();
+    assert_eq!(subtitles.len(), 1);
+    assert_eq!(subtitles[0]["language"], "en");
+    assert_eq!(subtitles[0]["forced"], true);
+    assert_eq!(subtitles[0]["format"], "srt");
+
+    // Under a library root the series folder is the first component.
+    let episode = tv_root.join("The Expanse (2015-2021)/Season 02/03 - Static.mkv");
+    let resp = preview(episode.to_string_lossy().to_string(), "tv_shows").await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["kind"], "episode", "{body}");
+    assert_eq!(
+        body["relative_path"],
+        std::path::Path::new("The Expanse (2015-2021)/Season 02/03 - Static.mkv")
+            .to_string_lossy()
+            .as_ref()
+    );
+    assert_eq!(body["title"], "The Expanse");
+    assert_eq!(body["year"], 2015);
+    assert_eq!(body["season"], 2);
+    assert_eq!(body["episode"], 3);
+    assert_eq!(body["episode_title"], "Static");
+    assert_eq!(body["subtitles"], json!([]));
+
+    preview("Show/S01E01.mkv".into(), "music")
+        .await
+        .assert_status(axum::http::StatusCode::BAD_REQUEST);
+    let (name, value) = auth_hdr(&viewer);
+    server
+        .post("/api/v1/system/parse-preview")
+        .add_header(name, value)
+        .json(&json!({ "path": "Show/S01E01.mkv", "kind": "tv_shows" }))
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn series_and_seasons_report_child_counts_on_request() {
+    let tmp = TempDir::new("rf_child_counts");
+    for rel in [
+        "Severance/Season 01/Severance.S01E01.mkv",
+        "Severance/Season 01/Severance.S01E02.mkv",
+        "Severance/Season 01/Severance.S01E03.mkv",
+        "Severance/Season 02/Severance.S02E01.mkv",
+    ] {
+        tmp.touch(rel);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let get = |path: String| {
+        server
+            .get(&path)
+            .add_header(hdr_name.clone(), hdr_val.clone())
+    };
+
+    // Off by default.
+    let items: Vec<Value> = get(format!("/api/v1/libraries/{}/items", lib.id))
+        .await
+        .json();
+    let series = &items[0];
+    assert_eq!(series["kind"], "series");
+    assert!(series.get("child_count").is_none());
+    assert!(series.get("recursive_item_count").is_none());
+    let series_id = series["id"].as_str().unwrap().to_string();
+
+    let items: Vec<Value> = get(format!(
+        "/api/v1/libraries/{}/items?include_counts=true",
+        lib.id
+    ))
+    .await
+    .json();
+    assert_eq!(items[0]["child_count"], 2);
+    assert_eq!(items[0]["recursive_item_count"], 4);
+
+    let item: Value = get(format!("/api/v1/items/{series_id}?include_counts=true"))
+        .await
+        .json();
+    assert_eq!(
+        (&item["child_count"], &item["recursive_item_count"]),
+        (&json!(2), &json!(4))
+    );
+
+    let mut seasons: Vec<Value> = get(format!(
+        "/api/v1/items/{series_id}/children?include_counts=true"
+    ))
+    .await
+    .json();
+    seasons.sort_by_key(|s| s["title"].as_str().unwrap().to_string());
+    let counts: Vec<(i64, i64)> = seasons
+        .iter()
+        .map(|s| {
+            (
+                s["child_count"].as_i64().unwrap(),
+                s["recursive_item_count"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(counts, [(3, 3), (1, 1)]);
+
+    // Episodes have nothing to count.
+    let season_id = seasons[0]["id"].as_str().unwrap();
+    let episodes: Vec<Value> = get(format!(
+        "/api/v1/items/{season_id}/children?include_counts=true"
+    ))
+    .await
+    .json();
+    assert_eq!(episodes.len(), 3);
+    assert!(episodes.iter().all(|e| e.get("child_count").is_none()));
+}
+
+#[tokio::test]
+async fn scan_requests_for_a_busy_library_share_its_job() {
+    let tmp = TempDir::new("rf_scan_once");
+    for rel in [
+        "Severance/Season 01/Severance.S01E01.mkv",
+        "Severance/Season 01/Severance.S01E02.mkv",
+        "Heat (1995)/Heat (1995).mkv",
+    ] {
+        tmp.touch(rel);
+    }
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Mixed",
+        "mixed",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let state = AppState {
+        jobs: JobQueue::new(1),
+        ..test_state_for_pool(pool.clone())
+    };
+    let server = TestServer::new(build_router(state.clone())).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let scan = |query: &'static str| {
+        server
+            .post(&format!("/api/v1/libraries/{}/scan{query}", lib.id))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+    };
+
+    // Keep the scan queued while more requests come in.
+    let busy = state.jobs.acquire("busy").await;
+    let (first, second) = tokio::join!(scan(""), scan(""));
+    // One request queues the scan; the other shares its job, or is turned away
+    // when it lands while that job is still being created.
+    let (queued, other) = if first.status_code() == axum::http::StatusCode::ACCEPTED {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    queued.assert_status(axum::http::StatusCode::ACCEPTED);
+    let job_id = queued.json::<Value>()["id"].as_str().unwrap().to_string();
+    if other.status_code() == axum::http::StatusCode::ACCEPTED {
+        assert_eq!(other.json::<Value>()["id"], job_id.as_str());
+    } else {
+        other.assert_status(axum::http::StatusCode::CONFLICT);
+    }
+    // A retry of failed files is covered by the scan already queued.
+    assert_eq!(
+        scan("?retry_failed=true").await.json::<Value>()["id"],
+        job_id.as_str()
+    );
+    assert_eq!(all_jobs(&pool).await.len(), 1);
+
+    drop(busy);
+    for _ in 0..100 {
+        let job = rustfin_db::repo::jobs::get_job(&pool, &job_id)
+            .await
+            .unwrap()
+            .unwrap();
+        if job.status == "completed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let (items,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM item")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    // A series, its season, two episodes and a movie.
+    assert_eq!(items, 5);
+
+    // Once it has finished, the library can be scanned again.
+    let mut next = Value::Null;
+    for _ in 0..100 {
+        next = scan("").await.json();
+        if next["id"] != job_id.as_str() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_ne!(next["id"], job_id.as_str());
+}
+
+#[tokio::test]
+async fn refreshing_images_updates_artwork_but_not_text_metadata() {
+    let tmp = TempDir::new("rustfin_refresh_images");
+    for name in ["Alpha (2001)/Alpha.mkv", "Beta (2002)/Beta.mkv"] {
+        tmp.touch(name);
+    }
+    // A local backdrop for Beta wins over the provider's with prefer_local_artwork.
+    std::fs::write(tmp.join("Beta (2002)/fanart.jpg"), b"jpg").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/movie/11" => json!({
+            "id": 11,
+            "title": "Provider Alpha",
+            "overview": "The provider's overview.",
+            "poster_path": "/alpha-poster.jpg",
+            "backdrop_path": "/alpha-backdrop.jpg"
+        }),
+        "/movie/12" => json!({
+            "id": 12,
+            "title": "Provider Beta",
+            "overview": "The provider's overview.",
+            "poster_path": "/beta-poster.jpg",
+            "backdrop_path": "/beta-backdrop.jpg"
+        }),
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let mut movies = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    movies.sort_by(|a, b| a.title.cmp(&b.title));
+    let (alpha, beta) = (movies[0].clone(), movies[1].clone());
+    for (movie, tmdb_id) in [(&alpha, "11"), (&beta, "12")] {
+        rustfin_metadata::merge::set_provider_id(&pool, &movie.id, "tmdb", tmdb_id)
+            .await
+            .unwrap();
+        rustfin_metadata::merge::merge_metadata(
+            &pool,
+            &movie.id,
+            &rustfin_metadata::ItemMetadata {
+                overview: Some("Written by hand.".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        rustfin_db::repo::items::update_item_artwork(
+            &pool,
+            &movie.id,
+            Some("https://example.com/old-poster.jpg"),
+            Some("https://example.com/old-backdrop.jpg"),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    }
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .post(&format!("/api/v1/items/{}/refresh-images", alpha.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["title"], "Alpha");
+    assert_eq!(body["overview"], "Written by hand.");
+    assert_eq!(
+        body["poster_url"],
+        format!("/api/v1/items/{}/images/poster", alpha.id)
+    );
+    let (poster, backdrop, ..) = rustfin_db::repo::items::get_item_artwork(&pool, &alpha.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        poster.as_deref(),
+        Some("https://image.tmdb.org/t/p/original/alpha-poster.jpg")
+    );
+    assert_eq!(
+        backdrop.as_deref(),
+        Some("https://image.tmdb.org/t/p/original/alpha-backdrop.jpg")
+    );
+
+    // A locked poster keeps its value; the other artwork is still refreshed.
+    server
+        .post(&format!("/api/v1/items/{}/field-locks", beta.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "field": "poster_url" }))
+        .await
+        .assert_status_ok();
+    let resp = server
+        .post(&format!("/api/v1/items/{}/refresh-images", beta.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["title"], "Beta");
+    assert_eq!(body["overview"], "Written by hand.");
+    let (poster, backdrop, ..) = rustfin_db::repo::items::get_item_artwork(&pool, &beta.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        poster.as_deref(),
+        Some("https://example.com/old-poster.jpg")
+    );
+    assert!(
+        backdrop
+            .as_deref()
+            .unwrap_or_default()
+            .ends_with("fanart.jpg"),
+        "{backdrop:?}"
+    );
+
+    // A locked item is left alone.
+    server
+        .post(&format!("/api/v1/items/{}/lock", beta.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status_ok();
+    server
+        .post(&format!("/api/v1/items/{}/refresh-images", beta.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn libraries_are_listed_in_their_display_order() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let mut ids = HashMap::new();
+    for (name, kind) in [
+        ("Anime", "tv_shows"),
+        ("Movies", "movies"),
+        ("TV", "tv_shows"),
+    ] {
+        let lib = rustfin_db::repo::libraries::create_library(&pool, name, kind, &[])
+            .await
+            .unwrap();
+        ids.insert(name, lib.id);
+    }
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let listed_names = |body: Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|lib| lib["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let resp = server
+        .get("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(listed_names(resp.json()), ["Anime", "Movies", "TV"]);
+
+    let resp = server
+        .patch("/api/v1/libraries/order")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "library_ids": [ids["TV"], ids["Anime"]] }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body[0]["display_order"], 0);
+    assert!(body[2]["display_order"].is_null());
+    assert_eq!(listed_names(body), ["TV", "Anime", "Movies"]);
+
+    // A new library has no position, so it comes after the ordered ones, by name.
+    rustfin_db::repo::libraries::create_library(&pool, "Documentaries", "movies", &[])
+        .await
+        .unwrap();
+    let resp = server
+        .get("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(
+        listed_names(resp.json()),
+        ["TV", "Anime", "Documentaries", "Movies"]
+    );
+
+    for library_ids in [
+        json!([ids["TV"], "no-such-library"]),
+        json!([ids["TV"], ids["TV"]]),
+    ] {
+        server
+            .patch("/api/v1/libraries/order")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "library_ids": library_ids }))
+            .await
+            .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let viewer = login(&server, "viewer", "viewer_secure_123").await;
+    let (viewer_name, viewer_val) = auth_hdr(&viewer);
+    server
+        .patch("/api/v1/libraries/order")
+        .add_header(viewer_name, viewer_val)
+        .json(&json!({ "library_ids": [ids["Movies"]] }))
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn recent_releases_lists_items_premiered_in_the_window_newest_first() {
+    let tmp = TempDir::new("rustfin_recent_releases");
+    let today = chrono::Utc::now().date_naive();
+    let day = |offset: i64| {
+        (today + chrono::Duration::days(offset))
+            .format("%Y-%m-%d")
+            .to_string()
+    };
+    let this_month = today.format("%Y-%m").to_string();
+    let premieres = [
+        ("Last Week", day(-7)),
+        ("Yesterday", format!("{}T20:00:00Z", day(-1))),
+        ("Three Weeks Ago", day(-21)),
+        ("Last Season", day(-45)),
+        ("Coming Soon", day(10)),
+        ("This Month", this_month.clone()),
+        ("Someday", "soon".to_string()),
+    ];
+    for (title, _) in &premieres {
+        tmp.touch(format!("{title}/{title}.mkv"));
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    for item in rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()
+    {
+        let (_, premiere_date) = premieres
+            .iter()
+            .find(|(title, _)| *title == item.title)
+            .unwrap();
+        rustfin_metadata::merge::merge_metadata(
+            &pool,
+            &item.id,
+            &rustfin_metadata::ItemMetadata {
+                premiere_date: Some(premiere_date.clone()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let titles = |body: Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    // A month-only date stands for the first of the month, always within 30 days.
+    let mut expected = [
+        ("Yesterday", day(-1)),
+        ("Last Week", day(-7)),
+        ("Three Weeks Ago", day(-21)),
+        ("This Month", format!("{this_month}-01")),
+    ];
+    expected.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let resp = server
+        .get("/api/v1/items/recent-releases")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        titles(resp.json()),
+        expected.iter().map(|(title, _)| *title).collect::<Vec<_>>()
+    );
+
+    let resp = server
+        .get("/api/v1/items/recent-releases?days=60&limit=5")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let listed = titles(resp.json());
+    assert_eq!(listed.len(), 5);
+    assert!(listed.contains(&"Last Season".to_string()), "{listed:?}");
+    assert!(!listed.contains(&"Coming Soon".to_string()), "{listed:?}");
+
+    server
+        .get("/api/v1/items/recent-releases?days=0")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+
+    // Libraries the caller cannot see are left out.
+    let viewer = login(&server, "viewer", "viewer_secure_123").await;
+    let (viewer_name, viewer_val) = auth_hdr(&viewer);
+    let resp = server
+        .get("/api/v1/items/recent-releases")
+        .add_header(viewer_name, viewer_val)
+        .await;
+    resp.assert_status_ok();
+    assert!(titles(resp.json()).is_empty());
+}
+
+#[tokio::test]
+async fn image_prefetch_warms_the_cache_at_common_sizes() {
+    let tmp = TempDir::new("rf_prefetch");
+    let movie_dir = tmp.join("media/Arrival (2016)");
+    std::fs::create_dir_all(&movie_dir).unwrap();
+    std::fs::write(movie_dir.join("Arrival (2016).mkv"), b"fake").unwrap();
+    let local_backdrop = tmp.join("backdrop.jpg");
+    std::fs::write(&local_backdrop, b"local-backdrop").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let cache_dir = tmp.join("cache");
+    let server = TestServer::new(build_router(AppState {
+        cache_dir: cache_dir.clone(),
+        ..test_state_for_pool(pool.clone())
+    }))
+    .unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp.join("media")).await;
+
+    // Opt-in per library.
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.json::<Value>()["settings"]["prefetch_images"], false);
+    server
+        .patch(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "settings": { "prefetch_images": true } }))
+        .await
+        .assert_status_ok();
+    let resp = server
+        .get(&format!("/api/v1/libraries/{}", lib.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.json::<Value>()["settings"]["prefetch_images"], true);
+
+    let movie = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()
+        .remove(0);
+    rustfin_db::repo::items::update_item_artwork(
+        &pool,
+        &movie.id,
+        Some("https://image.tmdb.org/t/p/original/arrival.jpg"),
+        Some(local_backdrop.to_str().unwrap()),
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+
+    let fetched = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let stub = |fetched: std::sync::Arc<std::sync::Mutex<Vec<String>>>| {
+        move |url: String| {
+            let fetched = fetched.clone();
+            async move {
+                let body = format!("bytes-of:{url}").into_bytes();
+                fetched.lock().unwrap().push(url);
+                Ok(body)
+            }
+        }
+    };
+    let options = rustfin_server::image_cache::PrefetchOptions {
+        min_interval: std::time::Duration::ZERO,
+        ..Default::default()
+    };
+
+    let summary = rustfin_server::image_cache::prefetch_library_images(
+        &pool,
+        &cache_dir,
+        &lib.id,
+        options,
+        stub(fetched.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.cached, 4);
+    assert!(!summary.over_budget);
+    assert_eq!(
+        *fetched.lock().unwrap(),
+        vec![
+            "https://image.tmdb.org/t/p/original/arrival.jpg".to_string(),
+            "https://image.tmdb.org/t/p/w342/arrival.jpg".to_string(),
+        ]
+    );
+    let images = cache_dir.join("images");
+    for name in [
+        "poster_0_0",
+        "poster_w342",
+        "backdrop_0_0",
+        "backdrop_1280_0",
+    ] {
+        assert!(
+            images.join(format!("{}_{name}.jpg", movie.id)).exists(),
+            "{name} should be cached"
+        );
+    }
+
+    // Served straight from the cache; the stub URL was never reachable.
+    let resp = server
+        .get(&format!("/api/v1/items/{}/images/poster?w=300", movie.id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        resp.as_bytes().as_ref(),
+        b"bytes-of:https://image.tmdb.org/t/p/w342/arrival.jpg"
+    );
+
+    // A second run only fetches what is missing.
+    let summary = rustfin_server::image_cache::prefetch_library_images(
+        &pool,
+        &cache_dir,
+        &lib.id,
+        options,
+        stub(fetched.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!((summary.cached, summary.skipped), (0, 4));
+    assert_eq!(fetched.lock().unwrap().len(), 2);
+
+    // The cache budget caps what is downloaded.
+    let small_cache = tmp.join("small_cache");
+    let summary = rustfin_server::image_cache::prefetch_library_images(
+        &pool,
+        &small_cache,
+        &lib.id,
+        rustfin_server::image_cache::PrefetchOptions {
+            max_cache_bytes: 60,
+            ..options
+        },
+        stub(fetched.clone()),
+    )
+    .await
+    .unwrap();
+    assert!(summary.over_budget);
+    assert_eq!(summary.cached, 1);
+    assert_eq!(
+        std::fs::read_dir(small_cache.join("images"))
+            .unwrap()
+            .count(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn series_theme_music_and_backdrops_are_linked_and_served() {
+    let tmp = TempDir::new("rf_theme");
+    let show = tmp.join("Firefly");
+    std::fs::create_dir_all(show.join("Season 01")).unwrap();
+    std::fs::create_dir_all(show.join("backdrops")).unwrap();
+    std::fs::write(show.join("Season 01/Firefly.S01E01.mkv"), b"fake").unwrap();
+    std::fs::write(show.join("theme.mp3"), b"theme-song-bytes").unwrap();
+    std::fs::write(show.join("backdrops/b.jpg"), b"second-backdrop").unwrap();
+    std::fs::write(show.join("backdrops/a.jpg"), b"first-backdrop").unwrap();
+    std::fs::write(show.join("backdrops/theme.mkv"), b"not-an-episode").unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "TV",
+        "tv_shows",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    let result = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "tv_shows")
+        .await
+        .unwrap();
+    // Only the episode became a library item.
+    assert_eq!(result.added, 1);
+    let series = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(series.len(), 1);
+    let series_id = series[0].id.clone();
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let resp = server
+        .get(&format!("/api/v1/items/{series_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(
+        body["theme_url"],
+        format!("/api/v1/items/{series_id}/theme")
+    );
+    assert_eq!(
+        body["extra_backdrop_urls"],
+        json!([
+            format!("/api/v1/items/{series_id}/images/backdrop?index=1"),
+            format!("/api/v1/items/{series_id}/images/backdrop?index=2"),
+        ])
+    );
+
+    let resp = server
+        .get(&format!("/api/v1/items/{series_id}/theme"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.header("content-type"), "audio/mpeg");
+    assert_eq!(resp.as_bytes().as_ref(), b"theme-song-bytes");
+
+    let resp = server
+        .get(&format!("/api/v1/items/{series_id}/theme?kind=video"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::NOT_FOUND);
+
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{series_id}/images/backdrop?index=2"
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"second-backdrop");
+
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{series_id}/images/backdrop?index=3"
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn expected_episodes_interleave_specials_in_aired_order() {
+    use rustfin_db::repo::episodes::{SpecialPlacement, upsert_expected_episode};
+
+    let tmp = TempDir::new("rf_specials");
+    std::fs::create_dir_all(tmp.join("Doctor Who/Season 02")).unwrap();
+    std::fs::write(
+        tmp.join("Doctor Who/Season 02/Doctor.Who.S02E01.mkv"),
+        b"fake",
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "TV", "tv_shows", &tmp).await;
+    let series_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+
+    let none = SpecialPlacement::default();
+    for (season, episode, placement) in [
+        (1, 1, none),
+        (1, 2, none),
+        (2, 7, none),
+        (2, 8, none),
+        // Airs between S02E07 and S02E08.
+        (
+            0,
+            5,
+            SpecialPlacement {
+                airs_before_season: Some(2),
+                airs_before_episode: Some(8),
+                ..none
+            },
+        ),
+        // Airs after the season 1 finale.
+        (
+            0,
+            1,
+            SpecialPlacement {
+                airs_after_season: Some(1),
+                ..none
+            },
+        ),
+        // No placement data.
+        (0, 2, none),
+    ] {
+        upsert_expected_episode(
+            &pool, &series_id, season, episode, None, None, None, placement,
+        )
+        .await
+        .unwrap();
+    }
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hn, hv) = auth_hdr(&token);
+    let order = |body: Value| -> Vec<(i64, i64)> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|ep| {
+                (
+                    ep["season_number"].as_i64().unwrap(),
+                    ep["episode_number"].as_i64().unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    let resp = server
+        .get(&format!("/api/v1/items/{series_id}/expected-episodes"))
+        .add_header(hn.clone(), hv.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(
+        order(resp.json()),
+        vec![(0, 1), (0, 2), (0, 5), (1, 1), (1, 2), (2, 7), (2, 8)]
+    );
+
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{series_id}/expected-episodes?order=aired"
+        ))
+        .add_header(hn.clone(), hv.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body[4]["airs_before_episode"], 8);
+    assert_eq!(
+        order(body),
+        vec![(1, 1), (1, 2), (0, 1), (2, 7), (0, 5), (2, 8), (0, 2)]
+    );
+
+    server
+        .get(&format!(
+            "/api/v1/items/{series_id}/expected-episodes?order=random"
+        ))
+        .add_header(hn, hv)
+        .await
+        .assert_status(axum::http::StatusCode::BAD_REQUEST);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn scan_records_unreadable_files_and_retries_only_those() {
+    let tmp = TempDir::new("rf_scan_errors");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Heat (1995).mkv");
+    tmp.touch("Ronin (1998).mkv");
+    // A file that vanished between the directory listing and the stat.
+    let ghost_target = tmp.join("ghost-target.bin");
+    let ghost = tmp.join("Ghost (2001).mkv");
+    std::os::unix::fs::symlink(&ghost_target, &ghost).unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let run_job = |query: &'static str| {
+        let server = &server;
+        let (hdr_name, hdr_val) = (hdr_name.clone(), hdr_val.clone());
+        let lib_id = lib.id.clone();
+        async move {
+            let resp = server
+                .post(&format!("/api/v1/libraries/{lib_id}/scan{query}"))
+                .add_header(hdr_name.clone(), hdr_val.clone())
+                .await;
+            resp.assert_status(axum::http::StatusCode::ACCEPTED);
+            let job_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+            for _ in 0..100 {
+                let job: Value = server
+                    .get(&format!("/api/v1/jobs/{job_id}"))
+                    .add_header(hdr_name.clone(), hdr_val.clone())
+                    .await
+                    .json();
+                if job["status"] != "queued" && job["status"] != "running" {
+                    return job;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            panic!("scan job {job_id} did not finish");
+        }
+    };
+
+    // The unreadable file is counted and recorded; the others are still added.
+    let job = run_job("").await;
+    assert_eq!(job["status"], "completed");
+    assert_eq!(job["error"], "1 file(s) could not be scanned");
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 2);
+    let failed = rustfin_db::repo::scan_errors::list_scan_errors(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].path, ghost.to_string_lossy());
+    assert_eq!(failed[0].attempts, 1);
+
+    // A retry that fails again keeps the record and bumps its attempts.
+    let result = rustfin_scanner::scan::retry_failed_files(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    assert_eq!((result.added, result.errors), (0, 1));
+    let failed = rustfin_db::repo::scan_errors::list_scan_errors(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(failed[0].attempts, 2);
+
+    // Once the file is back, a retry adds just it and clears the record.
+    std::fs::write(&ghost_target, b"fake").unwrap();
+    let job = run_job("?retry_failed=true").await;
+    assert_eq!(job["status"], "completed");
+    assert!(job["error"].is_null());
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 3);
+    assert!(
+        rustfin_db::repo::scan_errors::list_scan_errors(&pool, &lib.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // An inaccessible library root still fails the whole scan.
+    std::fs::remove_dir_all(&tmp).unwrap();
+    assert!(matches!(
+        rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies").await,
+        Err(rustfin_scanner::scan::ScanError::RootUnavailable { .. })
+    ));
+    let job = run_job("").await;
+    assert_eq!(job["status"], "failed");
+    assert!(job["error"].as_str().unwrap().contains("not accessible"));
+}
+
+#[tokio::test]
+async fn scan_is_idempotent() {
+    let tmp = TempDir::new("rustfin_test_idem");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Movie (2020).mkv");
+
+    let pool = test_pool().await;
+
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Test",
+        "movies",
+        &[tmp.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+
+    // Scan twice
+    let r1 = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    assert_eq!(r1.added, 1);
+
+    let r2 = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    assert_eq!(r2.added, 0);
+    assert_eq!(r2.skipped, 1);
+
+    // Still only 1 item
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+}
+
+// ---------------------------------------------------------------------------
+// Range streaming tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn stream_file_with_range_returns_206() {
+    // Create temp dir with a movie file containing known data
+    let tmp = TempDir::new("rustfin_test_stream");
+    std::fs::create_dir_all(&tmp).unwrap();
+
+    // Create a 5000-byte test file with known content
+    let test_data: Vec<u8> = (0u8..=255).cycle().take(5000).collect();
+    std::fs::write(tmp.join("TestMovie (2020).mkv"), &test_data).unwrap();
+
+    // Set up DB + scan
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+
+    // Find the media file ID
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+        .await
+        .unwrap()
+        .expect("should have a file linked");
+
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        transcode_dir: std::env::temp_dir().join(format!("rf_stream_{}", std::process::id())),
+        max_concurrent: 2,
+        ..Default::default()
+    };
+    let transcoder =
+        std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
+
+    let state = AppState {
+        db: pool,
+        jwt_secret: "test-secret-key".to_string(),
+        transcoder,
+        cache_dir: std::env::temp_dir().join(format!("rf_cache_stream_{}", std::process::id())),
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
+    };
+    let app = rustfin_server::routes::build_router(state);
+    let server = TestServer::new(app).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // Unauthenticated stream requests are rejected.
+    let resp = server.get(&format!("/stream/file/{file_id}")).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+
+    // Request Range: bytes=0-999 (first 1000 bytes)
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(
+            axum::http::header::RANGE,
+            "bytes=0-999".parse::<axum::http::HeaderValue>().unwrap(),
+        )
+        .await;
+
+    assert_eq!(resp.status_code(), axum::http::StatusCode::PARTIAL_CONTENT);
+    let body = resp.as_bytes().to_vec();
+    assert_eq!(body.len(), 1000);
+    assert_eq!(&body[..], &test_data[0..1000]);
+
+    // Check Content-Range header
+    let cr = resp
+        .headers()
+        .get("content-range")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert_eq!(cr, "bytes 0-999/5000");
+
+    // Check Accept-Ranges header
+    let ar = resp
+        .headers()
+        .get("accept-ranges")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert_eq!(ar, "bytes");
+
+    // Request full file (no Range header)
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
+    assert_eq!(resp.as_bytes().len(), 5000);
+
+    // Request open-ended range: bytes=4000-
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(
+            axum::http::header::RANGE,
+            "bytes=4000-".parse::<axum::http::HeaderValue>().unwrap(),
+        )
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.as_bytes().len(), 1000);
+    let cr = resp
+        .headers()
+        .get("content-range")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert_eq!(cr, "bytes 4000-4999/5000");
+
+    // Single-byte ranges at either end of the file.
+    for (range, expected_cr, offset) in [
+        ("bytes=0-0", "bytes 0-0/5000", 0),
+        ("bytes=4999-4999", "bytes 4999-4999/5000", 4999),
+    ] {
+        let resp = server
+            .get(&format!("/stream/file/{file_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .add_header(
+                axum::http::header::RANGE,
+                range.parse::<axum::http::HeaderValue>().unwrap(),
+            )
+            .await;
+        assert_eq!(resp.status_code(), axum::http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.header("content-length"), "1");
+        assert_eq!(resp.as_bytes().as_ref(), &test_data[offset..=offset]);
+        assert_eq!(resp.header("content-range"), expected_cr);
+    }
+
+    // A zero-length suffix is unsatisfiable.
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(
+            axum::http::header::RANGE,
+            "bytes=-0".parse::<axum::http::HeaderValue>().unwrap(),
+        )
+        .await;
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::RANGE_NOT_SATISFIABLE
+    );
+    assert_eq!(resp.header("content-range"), "bytes */5000");
+
+    // Cleanup
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn symlinked_library_trees_stream_only_when_symlinks_are_followed() {
+    let tmp = TempDir::new("rustfin_test_symlinks");
+    let library = tmp.join("library");
+    let nas = tmp.join("nas").join("Heat (1995)");
+    std::fs::create_dir_all(&library).unwrap();
+    std::fs::create_dir_all(&nas).unwrap();
+    std::fs::write(nas.join("Heat (1995).mkv"), vec![7u8; 2048]).unwrap();
+    std::fs::write(tmp.join("nas").join("secret.mkv"), vec![1u8; 16]).unwrap();
+    std::os::unix::fs::symlink(&nas, library.join("Heat (1995)")).unwrap();
+
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    let viewer = rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let lib = rustfin_db::repo::libraries::create_library(
+        &pool,
+        "Movies",
+        "movies",
+        &[library.to_string_lossy().to_string()],
+    )
+    .await
+    .unwrap();
+    rustfin_db::repo::users::set_library_access(&pool, &viewer, std::slice::from_ref(&lib.id))
+        .await
+        .unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+        .await
+        .unwrap()
+        .expect("should have a file linked");
+
+    let server = test_server_for_pool(pool.clone());
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let viewer_token = login(&server, "viewer", "viewer_secure_123").await;
+    let stream_status = async |token: &str| {
+        let (name, value) = auth_hdr(token);
+        server
+            .get(&format!("/stream/file/{file_id}"))
+            .add_header(name, value)
+            .await
+            .status_code()
+    };
+
+    // By default the symlink resolves outside the library root.
+    let resp = server
+        .get("/api/v1/system/config")
+        .add_header(auth_hdr(&admin_token).0, auth_hdr(&admin_token).1)
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["library_symlinks"], "resolve");
+    for token in [&admin_token, &viewer_token] {
+        assert_eq!(
+            stream_status(token).await,
+            axum::http::StatusCode::FORBIDDEN
+        );
+    }
+
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(auth_hdr(&admin_token).0, auth_hdr(&admin_token).1)
+        .json(&json!({ "library_symlinks": "sometimes" }))
+        .await;
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(auth_hdr(&admin_token).0, auth_hdr(&admin_token).1)
+        .json(&json!({ "library_symlinks": "follow" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["library_symlinks"], "follow");
+    for token in [&admin_token, &viewer_token] {
+        assert_eq!(stream_status(token).await, axum::http::StatusCode::OK);
+    }
+
+    // Climbing out of the root with `..` is still refused.
+    let escape = library.join("..").join("nas").join("secret.mkv");
+    sqlx::query("UPDATE media_file SET path = ? WHERE id = ?")
+        .bind(escape.to_string_lossy().to_string())
+        .bind(&file_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    for token in [&admin_token, &viewer_token] {
+        assert_eq!(
+            stream_status(token).await,
+            axum::http::StatusCode::FORBIDDEN
+        );
+    }
+}
+
+#[tokio::test]
+async fn playback_descriptor_returns_file_id_and_reports_unmapped_items() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // Movies fixture with a mapped playable file.
+    let movies_tmp = TempDir::new("rf_playback_desc_movies");
+    movies_tmp.touch("Sample Movie (2020).mp4");
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Playback Movies",
+            "kind": "movies",
+            "paths": [movies_tmp.to_str().unwrap()]
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let movies_lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/v1/libraries/{movies_lib_id}/scan"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{movies_lib_id}/items"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let items: Value = resp.json();
+    let movie_item_id = items.as_array().unwrap()[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .get(&format!("/api/v1/items/{movie_item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let playback: Value = resp.json();
+    let file_id = playback["file_id"].as_str().unwrap().to_string();
+    let direct_url = playback["direct_url"].as_str().unwrap();
+    assert!(direct_url.contains(&format!("/stream/file/{file_id}?st=")));
+    assert!(!direct_url.contains("?token="));
+
+    // TV fixture where top-level series item has no direct file mapping.
+    let tv_tmp = TempDir::new("rf_playback_desc_tv");
+    let season_dir = tv_tmp.join("Example Show/Season 01");
+    std::fs::create_dir_all(&season_dir).unwrap();
+    std::fs::write(season_dir.join("Example.Show.S01E01.mp4"), b"fake").unwrap();
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Playback TV",
+            "kind": "tv_shows",
+            "paths": [tv_tmp.to_str().unwrap()]
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let tv_lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/v1/libraries/{tv_lib_id}/scan"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{tv_lib_id}/items"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let tv_items: Value = resp.json();
+    let series_item_id = tv_items.as_array().unwrap()[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .get(&format!("/api/v1/items/{series_item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "conflict");
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("No playable file mapped to this item")
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn forced_subtitles_matching_original_audio_are_auto_selected() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "5400.0" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+              "tags": { "language": "eng" }, "disposition": { "default": 1 } },
+            { "index": 2, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+              "tags": { "language": "ger" }, "disposition": { "default": 0 } },
+            { "index": 3, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "eng" }, "disposition": { "default": 0, "forced": 1 } },
+            { "index": 4, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "eng" }, "disposition": { "default": 0, "forced": 0 } }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hn, hv) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_forced_subs");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Foreign Parts (2018).mkv");
+    let cue = "1\n00:00:01,000 --> 00:00:02,000\nHi\n";
+    std::fs::write(tmp.join("Foreign Parts (2018).en.forced.srt"), cue).unwrap();
+    std::fs::write(tmp.join("Foreign Parts (2018).en.srt"), cue).unwrap();
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "name": "Forced", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        items = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hn.clone(), hv.clone())
+            .await
+            .json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+
+    // (source, auto_select) for every listed subtitle.
+    let auto_selected = |subs: &Value| -> Vec<(String, bool)> {
+        subs.as_array()
+            .unwrap()
+            .iter()
+            .map(|s| {
+                let forced = if s["forced"].as_bool().unwrap() {
+                    "forced"
+                } else {
+                    "full"
+                };
+                (
+                    format!("{}:{forced}", s["type"].as_str().unwrap()),
+                    s["auto_select"].as_bool().unwrap(),
+                )
+            })
+            .collect()
+    };
+    let set_audio_pref = |langs: Value| {
+        server
+            .patch("/api/v1/users/me/preferences")
+            .add_header(hn.clone(), hv.clone())
+            .json(&json!({ "preferred_audio_languages": langs }))
+    };
+
+    // English audio (the original) chosen: forced English tracks switch on.
+    set_audio_pref(json!(["en"])).await.assert_status_ok();
+    let subs: Value = server
+        .get(&format!("/api/v1/items/{item_id}/subtitles"))
+        .add_header(hn.clone(), hv.clone())
+        .await
+        .json();
+    let mut got = auto_selected(&subs);
+    got.sort();
+    assert_eq!(
+        got,
+        vec![
+            ("embedded:forced".to_string(), true),
+            ("embedded:full".to_string(), false),
+            ("sidecar:forced".to_string(), true),
+            ("sidecar:full".to_string(), false),
+        ]
+    );
+
+    let info: Value = server
+        .get(&format!("/api/v1/items/{item_id}/playback-info"))
+        .add_header(hn.clone(), hv.clone())
+        .await
+        .json();
+    assert_eq!(
+        auto_selected(&info["media_sources"][0]["subtitles"])
+            .iter()
+            .filter(|(_, on)| *on)
+            .count(),
+        2
+    );
+
+    // German dub chosen: nothing is switched on.
+    set_audio_pref(json!(["de"])).await.assert_status_ok();
+    let subs: Value = server
+        .get(&format!("/api/v1/items/{item_id}/subtitles"))
+        .add_header(hn.clone(), hv.clone())
+        .await
+        .json();
+    assert!(auto_selected(&subs).iter().all(|(_, on)| !on));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn subtitle_listing_marks_the_default_for_the_source_policy() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "5400.0" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+              "tags": { "language": "eng" }, "disposition": { "default": 1 } },
+            { "index": 2, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+              "tags": { "language": "ger" }, "disposition": { "default": 0 } },
+            { "index": 3, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "eng" }, "disposition": { "default": 0, "forced": 1 } },
+            { "index": 4, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "eng" }, "disposition": { "default": 0, "forced": 0 } }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hn, hv) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_default_subs");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Home Ground (2019).mkv");
+    let cue = "1\n00:00:01,000 --> 00:00:02,000\nHi\n";
+    std::fs::write(tmp.join("Home Ground (2019).en.forced.srt"), cue).unwrap();
+    std::fs::write(tmp.join("Home Ground (2019).en.srt"), cue).unwrap();
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "name": "Defaults", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        items = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hn.clone(), hv.clone())
+            .await
+            .json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+
+    // The `type:forced|full` of every subtitle marked default under `prefs`.
+    let defaults = |prefs: Value| {
+        let server = &server;
+        let (hn, hv) = (hn.clone(), hv.clone());
+        let url = format!("/api/v1/items/{item_id}/subtitles");
+        async move {
+            server
+                .patch("/api/v1/users/me/preferences")
+                .add_header(hn.clone(), hv.clone())
+                .json(&prefs)
+                .await
+                .assert_status_ok();
+            let subs: Value = server.get(&url).add_header(hn, hv).await.json();
+            subs.as_array()
+                .unwrap()
+                .iter()
+                .filter(|s| s["default"].as_bool().unwrap())
+                .map(|s| {
+                    let forced = if s["forced"].as_bool().unwrap() {
+                        "forced"
+                    } else {
+                        "full"
+                    };
+                    format!("{}:{forced}", s["type"].as_str().unwrap())
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Original English audio: the forced English track, from the preferred source.
+    assert_eq!(
+        defaults(json!({
+            "preferred_audio_languages": ["en"],
+            "preferred_subtitle_languages": ["en"],
+            "subtitle_source": "embedded"
+        }))
+        .await,
+        ["embedded:forced"]
+    );
+    assert_eq!(
+        defaults(json!({ "subtitle_source": "sidecar" })).await,
+        ["sidecar:forced"]
+    );
+    assert_eq!(
+        defaults(json!({ "subtitle_source": "any" })).await,
+        ["sidecar:forced"]
+    );
+
+    // German dub: full subtitles in the preferred subtitle language instead.
+    assert_eq!(
+        defaults(json!({
+            "preferred_audio_languages": ["de"],
+            "subtitle_source": "embedded"
+        }))
+        .await,
+        ["embedded:full"]
+    );
+    assert_eq!(
+        defaults(json!({ "subtitle_source": "sidecar" })).await,
+        ["sidecar:full"]
+    );
+    // ...unless the user understands German or wants no subtitles.
+    assert!(
+        defaults(json!({ "preferred_subtitle_languages": ["de", "en"] }))
+            .await
+            .is_empty()
+    );
+    assert!(
+        defaults(json!({ "preferred_subtitle_languages": ["en"], "subtitle_mode": "none" }))
+            .await
+            .is_empty()
+    );
+}
+
+/// Minimal OpenSubtitles API: `/subtitles` lists one English and one French file,
+/// `/download` links to `/file/{id}.srt`. Request targets are sent to the channel.
+async fn spawn_opensubtitles_stub() -> (String, tokio::sync::mpsc::UnboundedReceiver<String>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut sock, _)) = listener.accept().await {
+            // Read the whole request so closing the socket doesn't reset it.
+            let mut req = Vec::new();
+            let mut buf = vec![0u8; 8192];
+            loop {
+                let n = sock.read(&mut buf).await.unwrap_or(0);
+                req.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&req).to_string();
+                let Some(head_end) = text.find("\r\n\r\n") else {
+                    if n == 0 {
+                        break;
+                    }
+                    continue;
+                };
+                let content_length = text[..head_end]
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                    })
+                    .unwrap_or(0);
+                if n == 0 || req.len() >= head_end + 4 + content_length {
+                    break;
+                }
+            }
+            let req = String::from_utf8_lossy(&req).to_string();
+            let target = req
+                .lines()
+                .next()
+                .and_then(|l| l.split_whitespace().nth(1))
+                .unwrap_or("")
+                .to_string();
+            let _ = tx.send(target.clone());
+            let (content_type, body) = if target.starts_with("/subtitles") {
+                (
+                    "application/json",
+                    r#"{"data":[
+                        {"attributes":{"language":"fr","download_count":5,"files":[{"file_id":22,"file_name":"Arrival.fr.srt"}]}},
+                        {"attributes":{"language":"en","download_count":900,"release":"Arrival.2016.1080p","files":[{"file_id":11,"file_name":"Arrival.2016.en.srt"}]}}
+                    ]}"#
+                        .to_string(),
+                )
+            } else if target == "/download" {
+                let id = if req.contains("22") { 22 } else { 11 };
+                (
+                    "application/json",
+                    format!(
+                        r#"{{"link":"http://{addr}/file/{id}.srt","file_name":"Arrival.2016.srt"}}"#
+                    ),
+                )
+            } else {
+                (
+                    "application/x-subrip",
+                    "1\n00:00:01,000 --> 00:00:02,000\nHello\n".to_string(),
+                )
+            };
+            let resp = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            let _ = sock.write_all(resp.as_bytes()).await;
+        }
+    });
+    (format!("http://{addr}"), rx)
+}
+
+#[tokio::test]
+async fn opensubtitles_search_and_download_write_a_sidecar() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let (stub, mut requests) = spawn_opensubtitles_stub().await;
+    rustfin_db::repo::settings::set(&pool, "opensubtitles_base_url", &stub)
+        .await
+        .unwrap();
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hn, hv) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_opensubs");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Arrival (2016).mkv");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "name": "Subs", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        items = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hn.clone(), hv.clone())
+            .await
+            .json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+    let search_url = format!("/api/v1/items/{item_id}/subtitles/search");
+    let download_url = format!("/api/v1/items/{item_id}/subtitles/download");
+
+    // Without an API key nothing is sent.
+    let resp = server
+        .post(&search_url)
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({}))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let resp = server
+        .patch("/api/v1/system/metadata-config")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "opensubtitles_api_key": "os-key-123456" }))
+        .await;
+    resp.assert_status_ok();
+    let config: Value = resp.json();
+    assert_eq!(config["opensubtitles_configured"], true);
+    assert_eq!(config["opensubtitles_key_source"], "database");
+    assert!(!config.to_string().contains("os-key-123456"));
+
+    let resp = server
+        .post(&search_url)
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "languages": ["FR", "en"] }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    let candidates = body["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0]["language"], "en");
+    assert_eq!(candidates[0]["file_id"], 11);
+    assert_eq!(candidates[0]["release"], "Arrival.2016.1080p");
+    assert_eq!(candidates[1]["language"], "fr");
+    let target = requests.recv().await.unwrap();
+    assert!(target.starts_with("/subtitles?"), "{target}");
+    assert!(target.contains("languages=en%2Cfr"), "{target}");
+    assert!(target.contains("query=Arrival"), "{target}");
+    assert!(target.contains("year=2016"), "{target}");
+
+    // Downloads write into the library, so a user who can see the item still may not.
+    let resp = server
+        .post("/api/v1/users")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({
+            "username": "viewer",
+            "password": "viewer_pass_123",
+            "role": "user",
+            "library_ids": [lib_id]
+        }))
+        .await;
+    resp.assert_status_ok();
+    let viewer_token = login(&server, "viewer", "viewer_pass_123").await;
+    let (vn, vv) = auth_hdr(&viewer_token);
+    let resp = server
+        .post(&download_url)
+        .add_header(vn, vv)
+        .json(&json!({ "file_id": 11, "language": "en" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+
+    let resp = server
+        .post(&download_url)
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "file_id": 11, "language": "en" }))
+        .await;
+    resp.assert_status_ok();
+    let sidecar = tmp.join("Arrival (2016).en.srt");
+    assert!(std::fs::read_to_string(&sidecar).unwrap().contains("Hello"));
+    let subs: Value = resp.json();
+    assert!(
+        subs.as_array()
+            .unwrap()
+            .iter()
+            .any(|s| s["type"] == "sidecar" && s["language"] == "en")
+    );
+
+    // Discovery is live, so the plain listing sees it too.
+    let subs: Value = server
+        .get(&format!("/api/v1/items/{item_id}/subtitles"))
+        .add_header(hn.clone(), hv.clone())
+        .await
+        .json();
+    let sidecars: Vec<&Value> = subs
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|s| s["type"] == "sidecar")
+        .collect();
+    assert_eq!(sidecars.len(), 1);
+    assert_eq!(sidecars[0]["language"], "en");
+    assert_eq!(sidecars[0]["format"], "srt");
+
+    // An existing sidecar is never overwritten.
+    let resp = server
+        .post(&download_url)
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "file_id": 11, "language": "en" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let resp = server
+        .post(&download_url)
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "file_id": 22, "language": "../fr" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn playback_info_describes_sources_tracks_and_stream_url() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "7200.0", "bit_rate": "5000000" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+            { "index": 1, "codec_type": "audio", "codec_name": "ac3", "channels": 6,
+              "tags": { "language": "eng" } },
+            { "index": 2, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "fra" } }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_playinfo");
+    std::fs::create_dir_all(&tmp).unwrap();
+    std::fs::write(tmp.join("Described (2019).mkv"), b"fake movie bytes").unwrap();
+    std::fs::write(
+        tmp.join("Described (2019).en.srt"),
+        "1\n00:00:01,000 --> 00:00:02,000\nHi\n",
+    )
+    .unwrap();
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Described", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        items = resp.json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+
+    // No profile: a plain HLS player, which needs the AC-3 track re-encoded.
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback-info"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["item_id"], item_id.as_str());
+    assert_eq!(body["hls_start_url"], "/api/v1/playback/sessions");
+    let sources = body["media_sources"].as_array().unwrap();
+    assert_eq!(sources.len(), 1);
+    let source = &sources[0];
+    assert_eq!(source["container"], "matroska,webm");
+    assert_eq!(source["size_bytes"], 16);
+    assert_eq!(source["duration_secs"], 7200.0);
+    assert_eq!(source["video"]["width"], 1920);
+    assert_eq!(source["audio"][0]["codec"], "ac3");
+    assert_eq!(source["audio"][0]["language"], "eng");
+    let subtitles = source["subtitles"].as_array().unwrap();
+    assert!(
+        subtitles
+            .iter()
+            .any(|s| s["type"] == "sidecar" && s["language"] == "en")
+    );
+    assert!(
+        subtitles
+            .iter()
+            .any(|s| s["type"] == "embedded" && s["source"] == "stream:2")
+    );
+    let embedded = subtitles.iter().find(|s| s["type"] == "embedded").unwrap();
+    assert_eq!(
+        embedded["url"],
+        format!(
+            "/stream/file/{}/subtitles/2",
+            source["file_id"].as_str().unwrap()
+        )
+    );
+    assert_eq!(source["decision"]["method"], "transcode");
+    assert_eq!(source["decision"]["video"], "copy");
+    assert_eq!(source["decision"]["audio"], "transcode");
+    // Browsers can't take Matroska as-is either.
+    assert_eq!(source["delivery"], "hls");
+    // Without a profile nothing was asked for, so nothing is unsupported.
+    assert!(source["direct_play_unsupported"].is_null());
+
+    // The stream URL works without the bearer header.
+    let direct_url = source["direct_url"].as_str().unwrap();
+    assert!(direct_url.contains("?st="));
+    let resp = server.get(direct_url).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"fake movie bytes");
+
+    // A client that takes the file as-is is told to direct play it.
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{item_id}/playback-info?containers=matroska&video_codecs=h264&audio_codecs=aac,ac3"
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(
+        body["media_sources"][0]["decision"]["method"],
+        "direct_play"
+    );
+    assert_eq!(body["media_sources"][0]["delivery"], "file");
+    assert!(body["media_sources"][0]["direct_play_unsupported"].is_null());
+
+    // One that can't decode AC-3 is told to transcode, and where to start it.
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{item_id}/playback-info?containers=matroska&video_codecs=h264&audio_codecs=aac"
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(
+        body["media_sources"][0]["direct_play_unsupported"],
+        json!({
+            "playback_method": "transcode",
+            "reason": "audio_codec_unsupported",
+            "transcode_url": "/api/v1/playback/sessions",
+        })
+    );
+
+    // Unknown items are a 404.
+    server
+        .get("/api/v1/items/nope/playback-info")
+        .add_header(hdr_name, hdr_val)
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn hls_endpoints_require_auth_and_enforce_session_owner() {
+    let server = test_app_with_fake_ffmpeg().await;
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let admin_hdr = auth_hdr(&admin_token);
+
+    let tmp = TempDir::new("rf_hls_auth");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Auth Movie (2020).mp4");
+
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({
+            "name": "HLS Auth Movies",
+            "kind": "movies",
+            "paths": [tmp.to_str().unwrap()]
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .await;
+    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}/items"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .await;
+    let items: Value = resp.json();
+    let item_id = items.as_array().unwrap()[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .await;
+    resp.assert_status_ok();
+    let playback: Value = resp.json();
+    let file_id = playback["file_id"].as_str().unwrap().to_string();
+
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "file_id": file_id }))
+        .await;
+    resp.assert_status_ok();
+    let session: Value = resp.json();
+    let sid = session["session_id"].as_str().unwrap().to_string();
+
+    // Unauthenticated master request is rejected.
+    let resp = server.get(&format!("/stream/hls/{sid}/master.m3u8")).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+
+    // Session owner can fetch HLS resources.
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/master.m3u8"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
+    let master_playlist = String::from_utf8(resp.as_bytes().to_vec()).unwrap();
+    let first_child = master_playlist
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .expect("master playlist should include at least one child URI")
+        .to_string();
+    let first_child_path = if first_child.starts_with('/') {
+        first_child
+    } else {
+        format!("/stream/hls/{sid}/{first_child}")
+    };
+    assert!(first_child_path.contains("st="));
+
+    let resp = server
+        .get(&first_child_path)
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
+    assert!(!resp.as_bytes().is_empty());
+
+    // Derive a concrete segment/media URL so we can verify auth there as well.
+    let child_body = String::from_utf8(resp.as_bytes().to_vec()).unwrap_or_default();
+    let maybe_segment = if first_child_path.contains(".m3u8") {
+        child_body
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+    } else {
+        Some(first_child_path.clone())
+    };
+    let segment_path = maybe_segment
+        .map(|uri| {
+            if uri.starts_with('/') {
+                uri
+            } else {
+                format!("/stream/hls/{sid}/{uri}")
+            }
+        })
+        .unwrap_or_else(|| format!("/stream/hls/{sid}/seg_00000.ts"));
+    let segment_path_no_query = segment_path
+        .split('?')
+        .next()
+        .unwrap_or(&segment_path)
+        .to_string();
+
+    let resp = server.get(&segment_path_no_query).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+
+    // Create a non-owner user and ensure they cannot access this session.
+    let resp = server
+        .post("/api/v1/users")
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({
+            "username": "otheruser",
+            "password": "otheruser_pass_123",
+            "role": "user",
+            "library_ids": [lib_id]
+        }))
+        .await;
+    resp.assert_status_ok();
+    let other_token = login(&server, "otheruser", "otheruser_pass_123").await;
+    let other_hdr = auth_hdr(&other_token);
+
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/master.m3u8"))
+        .add_header(other_hdr.0.clone(), other_hdr.1.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+
+    let resp = server
+        .get(&segment_path_no_query)
+        .add_header(other_hdr.0.clone(), other_hdr.1.clone())
+        .await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn single_file_hls_segments_are_served_as_byte_ranges() {
+    let server = test_app_with_transcoder_config(rustfin_transcoder::TranscoderConfig {
+        hls_single_file: true,
+        ..fake_ffmpeg_config()
+    })
+    .await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_hls_byterange");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Range Movie (2020).mp4");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "name": "Range Movies",
+            "kind": "movies",
+            "paths": [tmp.to_str().unwrap()]
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let job_id = server
+        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json::<Value>()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    for _ in 0..100 {
+        let job: Value = server
+            .get(&format!("/api/v1/jobs/{job_id}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await
+            .json();
+        if job["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let items: Value = server
+        .get(&format!("/api/v1/libraries/{lib_id}/items"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+    let playback: Value = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "file_id": playback["file_id"] }))
+        .await;
+    resp.assert_status_ok();
+    let sid = resp.json::<Value>()["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The fake ffmpeg writes `FAKE_TS` to the single segment file.
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/seg.ts"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(
+            axum::http::header::RANGE,
+            axum::http::HeaderValue::from_static("bytes=2-4"),
+        )
+        .await;
+    resp.assert_status(axum::http::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.as_bytes().as_ref(), b"KE_");
+    assert_eq!(resp.header("content-range"), "bytes 2-4/7");
+    assert_eq!(resp.header("content-type"), "video/mp2t");
+
+    // Without a range the whole file is served, as in multi-file mode.
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/seg.ts"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"FAKE_TS");
+
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/seg.ts"))
+        .add_header(hdr_name, hdr_val)
+        .add_header(
+            axum::http::header::RANGE,
+            axum::http::HeaderValue::from_static("bytes=50-"),
+        )
+        .await;
+    resp.assert_status(axum::http::StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(resp.header("content-range"), "bytes */7");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn playback_session_follows_device_profile() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "5400.0", "bit_rate": "6000000" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 },
+            { "index": 1, "codec_type": "audio", "codec_name": "dts", "channels": 6 }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_profile");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Profiled (2020).mkv");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Profiled", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        items = resp.json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let file_id = resp.json::<Value>()["file_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // A TV that takes matroska/h264/dts plays the file as-is.
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "file_id": file_id,
+            "device_profile": {
+                "containers": ["matroska"],
+                "video_codecs": ["h264", "hevc"],
+                "audio_codecs": ["aac", "ac3", "dts"]
+            }
+        }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["decision"]["method"], "direct_play");
+    assert_eq!(body["duration_secs"], 5400.0);
+    assert!(body["session_id"].is_null());
+    assert!(
+        body["direct_url"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("/stream/file/{file_id}?st="))
+    );
+
+    // A browser without DTS gets an HLS session that only re-encodes audio.
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "file_id": file_id,
+            "device_profile": {
+                "containers": ["mp4"],
+                "video_codecs": ["h264"],
+                "audio_codecs": ["aac"]
+            }
+        }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert!(body["session_id"].as_str().is_some());
+    assert!(body["direct_url"].is_null());
+    let decision = &body["decision"];
+    assert_eq!(decision["method"], "transcode");
+    assert_eq!(decision["video"], "copy");
+    assert_eq!(decision["audio"], "transcode");
+    let reasons = decision["reasons"].as_array().unwrap();
+    assert!(reasons.contains(&json!("container_not_supported")));
+    assert!(reasons.contains(&json!("audio_codec_not_supported")));
+    assert_eq!(body["duration_secs"], 5400.0);
+
+    // The master playlist names the one variant and what it holds.
+    let resp = server.get(body["hls_url"].as_str().unwrap()).await;
+    resp.assert_status_ok();
+    let master = resp.text();
+    assert!(master.contains("CODECS=\""), "{master}");
+    let variant = master
+        .lines()
+        .find(|l| l.starts_with("stream_video.m3u8"))
+        .unwrap()
+        .to_string();
+
+    // The media playlist carries the probed duration and a program date time.
+    let sid = body["session_id"].as_str().unwrap();
+    let resp = server.get(&format!("/stream/hls/{sid}/{variant}")).await;
+    resp.assert_status_ok();
+    let playlist = resp.text();
+    assert!(playlist.contains("#EXT-X-RUSTFIN-SOURCE-DURATION:5400.000"));
+    assert!(playlist.contains("#EXT-X-PROGRAM-DATE-TIME:"));
+    assert!(playlist.contains("#EXT-X-PLAYLIST-TYPE:EVENT"));
+
+    server
+        .post(&format!("/api/v1/playback/sessions/{sid}/stop"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn concurrent_streams_are_limited_per_user() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "600.0", "bit_rate": "4000000" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720 },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2 }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let admin = auth_hdr(&admin_token);
+
+    let tmp = TempDir::new("rf_stream_limit");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Limited (2022).mkv");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(admin.0.clone(), admin.1.clone())
+        .json(&json!({ "name": "Limited", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(admin.0.clone(), admin.1.clone())
+            .await;
+        items = resp.json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(admin.0.clone(), admin.1.clone())
+        .await;
+    let file_id = resp.json::<Value>()["file_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    server
+        .post("/api/v1/users")
+        .add_header(admin.0.clone(), admin.1.clone())
+        .json(&json!({
+            "username": "sharer",
+            "password": "sharer_pass_123",
+            "role": "user",
+            "library_ids": [lib_id]
+        }))
+        .await
+        .assert_status_ok();
+    let viewer_token = login(&server, "sharer", "sharer_pass_123").await;
+    let viewer = auth_hdr(&viewer_token);
+
+    let resp = server
+        .get("/api/v1/system/config")
+        .add_header(admin.0.clone(), admin.1.clone())
+        .await;
+    let config: Value = resp.json();
+    assert_eq!(config["max_streams_per_user"], 3);
+    assert_eq!(config["max_streams_per_admin"], 0);
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(admin.0.clone(), admin.1.clone())
+        .json(&json!({ "max_streams_per_user": 1 }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["max_streams_per_user"], 1);
+
+    // An mp4-only client forces an HLS session for the mkv file.
+    let start_session = async |hdr: &(axum::http::HeaderName, axum::http::HeaderValue)| {
+        server
+            .post("/api/v1/playback/sessions")
+            .add_header(hdr.0.clone(), hdr.1.clone())
+            .json(&json!({
+                "file_id": file_id,
+                "device_profile": {
+                    "containers": ["mp4"],
+                    "video_codecs": ["h264"],
+                    "audio_codecs": ["aac"]
+                }
+            }))
+            .await
+    };
+    let stop_session = async |hdr: &(axum::http::HeaderName, axum::http::HeaderValue),
+                              sid: &str| {
+        server
+            .post(&format!("/api/v1/playback/sessions/{sid}/stop"))
+            .add_header(hdr.0.clone(), hdr.1.clone())
+            .await
+    };
+
+    let resp = start_session(&viewer).await;
+    resp.assert_status_ok();
+    let first = resp.json::<Value>()["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // The second concurrent stream, session or direct read, is one too many.
+    let resp = start_session(&viewer).await;
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::TOO_MANY_REQUESTS
+    );
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(viewer.0.clone(), viewer.1.clone())
+        .await;
+    assert_eq!(
+        resp.status_code(),
+        axum::http::StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Admins have no limit unless one is set.
+    let resp = start_session(&admin).await;
+    resp.assert_status_ok();
+    let admin_sid = resp.json::<Value>()["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    stop_session(&admin, &admin_sid).await;
+
+    // Stopping the session frees the slot.
+    stop_session(&viewer, &first).await;
+    let resp = server
+        .get(&format!("/stream/file/{file_id}"))
+        .add_header(viewer.0.clone(), viewer.1.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"fake");
+    let resp = start_session(&viewer).await;
+    resp.assert_status_ok();
+    let sid = resp.json::<Value>()["session_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    stop_session(&viewer, &sid).await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn disabling_direct_play_forces_hls_sessions() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "600.0", "bit_rate": "4000000" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720 },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2 }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_no_direct");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Transcoded (2021).mkv");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Transcoded", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        items = resp.json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "direct_play_enabled": false }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["direct_play_enabled"], false);
+    assert_eq!(body["direct_play_admin_override"], false);
+
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let descriptor: Value = resp.json();
+    let file_id = descriptor["file_id"].as_str().unwrap().to_string();
+    let direct_url = descriptor["direct_url"].as_str().unwrap().to_string();
+
+    let resp = server.get(&direct_url).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+    assert!(
+        resp.json::<Value>()["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("/api/v1/playback/sessions")
+    );
+
+    // A profile that would otherwise direct play gets an HLS session instead.
+    let profile = json!({
+        "containers": ["matroska"],
+        "video_codecs": ["h264"],
+        "audio_codecs": ["aac"]
+    });
+    let resp = server
+        .get(&format!(
+            "/api/v1/items/{item_id}/playback-info?containers=matroska&video_codecs=h264&audio_codecs=aac"
+        ))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["media_sources"][0]["delivery"], "hls");
 
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "file_id": file_id, "device_profile": profile }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert!(body["direct_url"].is_null());
+    let sid = body["session_id"].as_str().unwrap().to_string();
+    let resp = server.get(body["hls_url"].as_str().unwrap()).await;
+    resp.assert_status_ok();
+    server
+        .post(&format!("/api/v1/playback/sessions/{sid}/stop"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
 
-    // Create library pointing to tmp dir
+    // The admin override reopens direct streaming for admins only.
+    server
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "direct_play_admin_override": true }))
+        .await
+        .assert_status_ok();
+    server.get(&direct_url).await.assert_status_ok();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn multi_audio_session_advertises_renditions_in_master_playlist() {
+    let ffprobe = create_fake_ffprobe_script(&json!({
+        "format": { "format_name": "matroska,webm", "duration": "1200.0", "bit_rate": "4000000" },
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720,
+              "profile": "Main", "level": 31 },
+            { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2,
+              "tags": { "language": "eng" }, "disposition": { "default": 1 } },
+            { "index": 2, "codec_type": "audio", "codec_name": "ac3", "channels": 6,
+              "tags": { "language": "jpn", "title": "Japanese" } },
+            { "index": 3, "codec_type": "subtitle", "codec_name": "subrip",
+              "tags": { "language": "eng" } }
+        ]
+    }));
+    let server = test_app_with_fake_ffmpeg_and_ffprobe(ffprobe).await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let tmp = TempDir::new("rf_renditions");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Dubbed (2021).mkv");
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "Dubbed", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let mut items = Vec::new();
+    for _ in 0..50 {
+        let resp = server
+            .get(&format!("/api/v1/libraries/{lib_id}/items"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .await;
+        items = resp.json::<Vec<Value>>();
+        if !items.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    let item_id = items[0]["id"].as_str().unwrap().to_string();
+    let resp = server
+        .get(&format!("/api/v1/items/{item_id}/playback"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let file_id = resp.json::<Value>()["file_id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "file_id": file_id }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    let sid = body["session_id"].as_str().unwrap().to_string();
+
+    let resp = server.get(body["hls_url"].as_str().unwrap()).await;
+    resp.assert_status_ok();
+    let master = resp.text();
+    let audio: Vec<&str> = master
+        .lines()
+        .filter(|l| l.starts_with("#EXT-X-MEDIA:TYPE=AUDIO"))
+        .collect();
+    assert_eq!(audio.len(), 2, "{master}");
+    assert!(audio[0].contains("LANGUAGE=\"eng\",DEFAULT=YES"));
+    assert!(audio[1].contains("NAME=\"Japanese\",LANGUAGE=\"jpn\""));
+    assert!(audio.iter().all(|l| l.contains(".m3u8?st=")));
+    assert!(master.contains("#EXT-X-MEDIA:TYPE=SUBTITLES"));
+    assert!(master.contains("AUDIO=\"audio\",SUBTITLES=\"subs\""));
+    // Copied Main@3.1 video; the AC-3 track is re-encoded to AAC like the first.
+    assert!(
+        master.contains("CODECS=\"avc1.4d001f,mp4a.40.2\",RESOLUTION=1280x720"),
+        "{master}"
+    );
+    assert!(!master.contains("#EXT-X-PROGRAM-DATE-TIME"));
+
+    // The subtitle playlist is served through the session, and stays open while
+    // ffmpeg is still writing the WebVTT file.
+    let st = body["hls_url"]
+        .as_str()
+        .unwrap()
+        .split("st=")
+        .nth(1)
+        .unwrap();
+    let resp = server
+        .get(&format!("/stream/hls/{sid}/subs_0.m3u8?st={st}"))
+        .await;
+    resp.assert_status_ok();
+    let subs = resp.text();
+    assert!(subs.contains("#EXT-X-PLAYLIST-TYPE:EVENT"), "{subs}");
+    assert!(subs.contains("#EXT-X-TARGETDURATION:1200"), "{subs}");
+    assert!(!subs.contains("#EXTINF") && !subs.contains("#EXT-X-ENDLIST"));
+    server
+        .get(&format!("/stream/hls/{sid}/subs_1.m3u8?st={st}"))
+        .await
+        .assert_status_not_found();
+
+    server
+        .post(&format!("/api/v1/playback/sessions/{sid}/stop"))
+        .add_header(hdr_name, hdr_val)
+        .await;
+}
+
+#[tokio::test]
+async fn strm_file_is_scanned_as_remote_and_redirects() {
+    let tmp = TempDir::new("rf_strm");
+    std::fs::create_dir_all(&tmp).unwrap();
+    std::fs::write(
+        tmp.join("Remote Movie (2020).strm"),
+        "https://cdn.example.com/remote-movie.mp4\n",
+    )
+    .unwrap();
+    std::fs::write(tmp.join("Local File (2019).strm"), "file:///etc/passwd\n").unwrap();
+    // A second pointer at the same URL is its own file.
+    std::fs::write(
+        tmp.join("Mirror Movie (2021).strm"),
+        "https://cdn.example.com/remote-movie.mp4\n",
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
     let lib = rustfin_db::repo::libraries::create_library(
         &pool,
         "Movies",
@@ -529,919 +7320,1381 @@ async fn scan_movie_library_creates_items() {
     )
     .await
     .unwrap();
-
-    // Run scan directly
     let result = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
         .await
         .unwrap();
+    // The file:// pointer is rejected by the scanner.
     assert_eq!(result.added, 2);
 
-    // Verify items created
-    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+    let mut items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
         .await
         .unwrap();
+    items.sort_by(|a, b| b.title.cmp(&a.title));
     assert_eq!(items.len(), 2);
+    assert_eq!(items[0].title, "Remote Movie");
+    assert_eq!(items[1].title, "Mirror Movie");
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+    let file = rustfin_db::repo::media_files::get_media_file(&pool, &file_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(file.is_remote);
+    assert_eq!(
+        std::path::Path::new(&file.path),
+        tmp.join("Remote Movie (2020).strm")
+    );
+    assert_eq!(
+        file.remote_url.as_deref(),
+        Some("https://cdn.example.com/remote-movie.mp4")
+    );
 
-    let titles: Vec<&str> = items.iter().map(|i| i.title.as_str()).collect();
-    assert!(titles.contains(&"The Matrix"));
-    assert!(titles.contains(&"Inception"));
+    // Rescans follow an edited pointer and drop a deleted one.
+    std::fs::write(
+        tmp.join("Remote Movie (2020).strm"),
+        "https://cdn.example.com/remote-movie-v2.mp4\n",
+    )
+    .unwrap();
+    std::fs::remove_file(tmp.join("Mirror Movie (2021).strm")).unwrap();
+    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+        .await
+        .unwrap();
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].title, "Remote Movie");
+    assert_eq!(
+        rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(file_id.as_str())
+    );
 
-    // Verify year is set
-    let matrix = items.iter().find(|i| i.title == "The Matrix").unwrap();
-    assert_eq!(matrix.year, Some(1999));
-    assert_eq!(matrix.kind, "movie");
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
 
-    // Cleanup
-    std::fs::remove_dir_all(&tmp).ok();
+    let resp = server
+        .get(&format!("/api/v1/items/{}/playback", items[0].id))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let direct_url = resp.json::<Value>()["direct_url"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let resp = server.get(&direct_url).await;
+    assert_eq!(resp.status_code(), axum::http::StatusCode::FOUND);
+    assert_eq!(
+        resp.header("location"),
+        "https://cdn.example.com/remote-movie-v2.mp4"
+    );
+
+    // Remote media cannot be fed to the transcoder.
+    let resp = server
+        .post("/api/v1/playback/sessions")
+        .add_header(hdr_name, hdr_val)
+        .json(&json!({ "file_id": file_id }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
 }
 
+// ---------------------------------------------------------------------------
+// Playback progress tests
+// ---------------------------------------------------------------------------
+
 #[tokio::test]
-async fn scan_tv_library_creates_series_hierarchy() {
-    // Create temp dir with TV show structure
-    let tmp = std::env::temp_dir().join(format!("rustfin_test_tv_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(tmp.join("Breaking Bad/Season 01")).unwrap();
-    std::fs::write(
-        tmp.join("Breaking Bad/Season 01/Breaking.Bad.S01E01.Pilot.mkv"),
-        b"fake",
-    )
-    .unwrap();
-    std::fs::write(
-        tmp.join("Breaking Bad/Season 01/Breaking.Bad.S01E02.Cat's.in.the.Bag.mkv"),
-        b"fake",
-    )
-    .unwrap();
-    std::fs::create_dir_all(tmp.join("Breaking Bad/Season 02")).unwrap();
-    std::fs::write(
-        tmp.join("Breaking Bad/Season 02/Breaking.Bad.S02E01.Seven.Thirty.Seven.mkv"),
-        b"fake",
-    )
-    .unwrap();
+async fn playback_progress_update_and_get() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
 
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+    // Create a library with a real temp dir and scan it for playback tests
+    let tmp = std::env::temp_dir().join(format!("rf_play_{}", std::process::id()));
+    std::fs::create_dir_all(&tmp).unwrap();
+    std::fs::write(tmp.join("Inception (2010).mkv"), "fake video data").unwrap();
 
-    let lib = rustfin_db::repo::libraries::create_library(
-        &pool,
-        "TV Shows",
-        "tv_shows",
-        &[tmp.to_string_lossy().to_string()],
-    )
-    .await
-    .unwrap();
+    // Create library with real path
+    let resp = server
+        .post("/api/v1/libraries")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "name": "PlayMovies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .await;
+    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
 
-    let result = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "tv_shows")
-        .await
-        .unwrap();
-    assert_eq!(result.added, 3);
+    // Scan
+    server
+        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+
+    // Wait for scan
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    // Get items
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_id}/items"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let items: Value = resp.json();
+    let item_id = items.as_array().unwrap()[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Get play state — should be default (no progress)
+    let resp = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["progress_ms"], 0);
+    assert_eq!(body["played"], false);
+
+    // Update progress
+    let resp = server
+        .post("/api/v1/playback/progress")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "item_id": item_id,
+            "progress_ms": 120000,
+            "played": false
+        }))
+        .await;
+    resp.assert_status_ok();
+
+    // Verify updated
+    let resp = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["progress_ms"], 120000);
+    assert_eq!(body["played"], false);
+
+    // Mark as played
+    let resp = server
+        .post("/api/v1/playback/progress")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({
+            "item_id": item_id,
+            "progress_ms": 120000,
+            "played": true
+        }))
+        .await;
+    resp.assert_status_ok();
+
+    let resp = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    let body: Value = resp.json();
+    assert_eq!(body["played"], true);
+    assert!(body["last_played_ts"].as_i64().unwrap() > 0);
+
+    // Cleanup
+}
+
+#[tokio::test]
+async fn scan_records_quality_tags_without_polluting_titles() {
+    let tmp = TempDir::new("rf_quality");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Tagged.2160p.HDR.BluRay.x265.mkv");
+
+    let pool = test_pool().await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
 
-    // Top-level items should be series only
     let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
         .await
         .unwrap();
     assert_eq!(items.len(), 1);
-    assert_eq!(items[0].kind, "series");
-    assert_eq!(items[0].title, "Breaking Bad");
-
-    // Series should have seasons as children
-    let seasons = rustfin_db::repo::items::get_children(&pool, &items[0].id)
+    assert_eq!(items[0].title, "Tagged");
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
         .await
+        .unwrap()
         .unwrap();
-    assert_eq!(seasons.len(), 2);
-    assert!(seasons.iter().all(|s| s.kind == "season"));
-
-    // Season 1 should have 2 episodes
-    let s1 = seasons.iter().find(|s| s.title == "Season 1").unwrap();
-    let episodes = rustfin_db::repo::items::get_children(&pool, &s1.id)
+    let file = rustfin_db::repo::media_files::get_media_file(&pool, &file_id)
         .await
+        .unwrap()
         .unwrap();
-    assert_eq!(episodes.len(), 2);
-    assert!(episodes.iter().all(|e| e.kind == "episode"));
-
-    // Cleanup
-    std::fs::remove_dir_all(&tmp).ok();
+    assert_eq!(file.resolution.as_deref(), Some("2160p"));
+    assert_eq!(file.source.as_deref(), Some("BluRay"));
 }
 
 #[tokio::test]
-async fn scan_is_idempotent() {
-    let tmp = std::env::temp_dir().join(format!("rustfin_test_idem_{}", uuid::Uuid::new_v4()));
+async fn play_state_reports_played_percentage_of_known_runtime() {
+    let tmp = TempDir::new("rf_played_pct");
     std::fs::create_dir_all(&tmp).unwrap();
-    std::fs::write(tmp.join("Movie (2020).mkv"), b"fake").unwrap();
-
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
-
-    let lib = rustfin_db::repo::libraries::create_library(
-        &pool,
-        "Test",
-        "movies",
-        &[tmp.to_string_lossy().to_string()],
-    )
-    .await
-    .unwrap();
+    tmp.touch("Halfway (2010).mkv");
+    tmp.touch("Unknown (2011).mkv");
 
-    // Scan twice
-    let r1 = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
         .await
         .unwrap();
-    assert_eq!(r1.added, 1);
-
-    let r2 = rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
         .await
         .unwrap();
-    assert_eq!(r2.added, 0);
-    assert_eq!(r2.skipped, 1);
-
-    // Still only 1 item
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
     let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
         .await
         .unwrap();
-    assert_eq!(items.len(), 1);
+    let id_of = |title: &str| items.iter().find(|i| i.title == title).unwrap().id.clone();
+    let (halfway, unknown) = (id_of("Halfway"), id_of("Unknown"));
+    sqlx::query("UPDATE item SET runtime_minutes = 120 WHERE id = ?")
+        .bind(&halfway)
+        .execute(&pool)
+        .await
+        .unwrap();
 
-    std::fs::remove_dir_all(&tmp).ok();
-}
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
 
-// ---------------------------------------------------------------------------
-// Range streaming tests
-// ---------------------------------------------------------------------------
+    let state = |item_id: String| {
+        server
+            .get(&format!("/api/v1/playback/state/{item_id}"))
+            .add_header(h.clone(), v.clone())
+    };
+    let body: Value = state(halfway.clone()).await.json();
+    assert_eq!(body["played_percentage"], 0.0);
+
+    for item_id in [&halfway, &unknown] {
+        server
+            .post("/api/v1/playback/progress")
+            .add_header(h.clone(), v.clone())
+            .json(&json!({ "item_id": item_id, "progress_ms": 3_600_000 }))
+            .await
+            .assert_status_ok();
+    }
 
-#[tokio::test]
-async fn stream_file_with_range_returns_206() {
-    // Create temp dir with a movie file containing known data
-    let tmp = std::env::temp_dir().join(format!("rustfin_test_stream_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp).unwrap();
+    let body: Value = state(halfway.clone()).await.json();
+    let pct = body["played_percentage"].as_f64().unwrap();
+    assert!((pct - 50.0).abs() < 0.01, "{pct}");
 
-    // Create a 5000-byte test file with known content
-    let test_data: Vec<u8> = (0u8..=255).cycle().take(5000).collect();
-    std::fs::write(tmp.join("TestMovie (2020).mkv"), &test_data).unwrap();
+    let body: Value = state(unknown).await.json();
+    assert_eq!(body["progress_ms"], 3_600_000);
+    assert!(body["played_percentage"].is_null());
 
-    // Set up DB + scan
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
-    rustfin_db::repo::users::create_user(&pool, "admin", "admin_secure_123", "admin")
+    // Progress past the runtime is capped.
+    server
+        .post("/api/v1/playback/progress")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "item_id": halfway, "progress_ms": 9_000_000 }))
         .await
-        .unwrap();
+        .assert_status_ok();
+    let body: Value = state(halfway).await.json();
+    assert_eq!(body["played_percentage"], 100.0);
+}
 
-    let lib = rustfin_db::repo::libraries::create_library(
-        &pool,
-        "Movies",
-        "movies",
-        &[tmp.to_string_lossy().to_string()],
-    )
-    .await
-    .unwrap();
+#[tokio::test]
+async fn concurrent_progress_reports_keep_the_newest() {
+    let tmp = TempDir::new("rf_progress_race");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Race (2020).mkv");
 
-    rustfin_scanner::scan::run_library_scan(&pool, &lib.id, "movies")
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
         .await
-        .unwrap();
-
-    // Find the media file ID
-    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .unwrap()[0]
+        .id
+        .clone();
+    let user_id = rustfin_db::repo::users::find_by_username(&pool, "admin")
         .await
-        .unwrap();
-    assert_eq!(items.len(), 1);
-
-    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+        .unwrap()
+        .unwrap()
+        .id;
+
+    // Two devices racing at the repo level: every write lands in the same upsert, so
+    // whichever report is newest wins regardless of arrival order.
+    let base = chrono::Utc::now().timestamp() - 1_000;
+    let tasks: Vec<_> = (0..20i64)
+        .rev()
+        .map(|i| {
+            let (pool, user_id, item_id) = (pool.clone(), user_id.clone(), item_id.clone());
+            tokio::spawn(async move {
+                rustfin_db::repo::playstate::update_progress(
+                    &pool,
+                    &user_id,
+                    &item_id,
+                    i * 60_000,
+                    i >= 15,
+                    base + i,
+                )
+                .await
+                .unwrap()
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let row = rustfin_db::repo::playstate::get_play_state(&pool, &user_id, &item_id)
         .await
         .unwrap()
-        .expect("should have a file linked");
-
-    let tc_config = rustfin_transcoder::TranscoderConfig {
-        transcode_dir: std::env::temp_dir().join(format!("rf_stream_{}", std::process::id())),
-        max_concurrent: 2,
-        ..Default::default()
-    };
-    let transcoder =
-        std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
+        .unwrap();
+    assert_eq!(row.last_played_ts, Some(base + 19));
+    assert_eq!(row.progress_ms, 19 * 60_000);
+    assert!(row.played);
 
-    let (events_tx, _) = tokio::sync::broadcast::channel(64);
-    let state = AppState {
-        db: pool,
-        jwt_secret: "test-secret-key".to_string(),
-        transcoder,
-        cache_dir: std::env::temp_dir().join(format!("rf_cache_stream_{}", std::process::id())),
-        events: events_tx,
-    };
-    let app = rustfin_server::routes::build_router(state);
-    let server = TestServer::new(app).unwrap();
+    // Over HTTP, a late report from another device cannot rewind the position or
+    // un-mark the item as played.
+    let server = test_server_for_pool(pool);
     let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
-    // Unauthenticated stream requests are rejected.
-    let resp = server.get(&format!("/stream/file/{file_id}")).await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+    let (h, v) = auth_hdr(&token);
+    let reports = [(base + 5, 300_000, false), (base + 25, 1_500_000, true)].map(
+        |(played_at, progress_ms, played)| {
+            server
+                .post("/api/v1/playback/progress")
+                .add_header(h.clone(), v.clone())
+                .json(&json!({
+                    "item_id": item_id,
+                    "progress_ms": progress_ms,
+                    "played": played,
+                    "played_at": played_at,
+                }))
+        },
+    );
+    let applied: Vec<Value> =
+        futures::future::join_all(reports.map(std::future::IntoFuture::into_future))
+            .await
+            .into_iter()
+            .map(|resp| resp.json::<Value>()["applied"].clone())
+            .collect();
+    assert_eq!(applied, vec![json!(false), json!(true)]);
+
+    let body: Value = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(h.clone(), v.clone())
+        .await
+        .json();
+    assert_eq!(body["last_played_ts"], base + 25);
+    assert_eq!(body["progress_ms"], 1_500_000);
+    assert_eq!(body["played"], true);
+}
 
-    // Request Range: bytes=0-999 (first 1000 bytes)
-    let resp = server
-        .get(&format!("/stream/file/{file_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .add_header(
-            axum::http::header::RANGE,
-            "bytes=0-999".parse::<axum::http::HeaderValue>().unwrap(),
-        )
-        .await;
+#[tokio::test]
+async fn resetting_progress_restores_defaults_but_keeps_favorite() {
+    let tmp = TempDir::new("rf_reset_progress");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Rewatch (2015).mkv");
 
-    assert_eq!(resp.status_code(), axum::http::StatusCode::PARTIAL_CONTENT);
-    let body = resp.as_bytes().to_vec();
-    assert_eq!(body.len(), 1000);
-    assert_eq!(&body[..], &test_data[0..1000]);
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
 
-    // Check Content-Range header
-    let cr = resp
-        .headers()
-        .get("content-range")
-        .unwrap()
-        .to_str()
-        .unwrap();
-    assert_eq!(cr, "bytes 0-999/5000");
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
 
-    // Check Accept-Ranges header
-    let ar = resp
-        .headers()
-        .get("accept-ranges")
-        .unwrap()
-        .to_str()
+    server
+        .post("/api/v1/playback/progress")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "item_id": item_id, "progress_ms": 1_234_000, "played": true }))
+        .await
+        .assert_status_ok();
+    sqlx::query("UPDATE user_item_state SET favorite = 1 WHERE item_id = ?")
+        .bind(&item_id)
+        .execute(&pool)
+        .await
         .unwrap();
-    assert_eq!(ar, "bytes");
 
-    // Request full file (no Range header)
-    let resp = server
-        .get(&format!("/stream/file/{file_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
-    assert_eq!(resp.as_bytes().len(), 5000);
+    server
+        .delete(&format!("/api/v1/items/{item_id}/progress"))
+        .add_header(h.clone(), v.clone())
+        .await
+        .assert_status_ok();
+    let body: Value = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(h.clone(), v.clone())
+        .await
+        .json();
+    assert_eq!(body["progress_ms"], 0);
+    assert_eq!(body["played"], false);
+    assert!(body["last_played_ts"].is_null());
+    assert_eq!(body["favorite"], true);
 
-    // Request open-ended range: bytes=4000-
-    let resp = server
-        .get(&format!("/stream/file/{file_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .add_header(
-            axum::http::header::RANGE,
-            "bytes=4000-".parse::<axum::http::HeaderValue>().unwrap(),
-        )
-        .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::PARTIAL_CONTENT);
-    assert_eq!(resp.as_bytes().len(), 1000);
-    let cr = resp
-        .headers()
-        .get("content-range")
-        .unwrap()
-        .to_str()
-        .unwrap();
-    assert_eq!(cr, "bytes 4000-4999/5000");
+    server
+        .delete(&format!(
+            "/api/v1/items/{item_id}/progress?clear_favorite=true"
+        ))
+        .add_header(h.clone(), v.clone())
+        .await
+        .assert_status_ok();
+    let body: Value = server
+        .get(&format!("/api/v1/playback/state/{item_id}"))
+        .add_header(h.clone(), v.clone())
+        .await
+        .json();
+    assert_eq!(body["favorite"], false);
 
-    // Cleanup
-    std::fs::remove_dir_all(&tmp).ok();
+    server
+        .delete("/api/v1/items/does-not-exist/progress")
+        .add_header(h, v)
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
 }
 
 #[tokio::test]
-async fn playback_descriptor_returns_file_id_and_reports_unmapped_items() {
+async fn user_management_crud() {
     let server = test_app().await;
     let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
-    // Movies fixture with a mapped playable file.
-    let movies_tmp =
-        std::env::temp_dir().join(format!("rf_playback_desc_movies_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&movies_tmp).unwrap();
-    std::fs::write(movies_tmp.join("Sample Movie (2020).mp4"), b"fake").unwrap();
+    let hdr_name = axum::http::header::AUTHORIZATION;
+    let hdr_val = axum::http::HeaderValue::from_str(&format!("Bearer {token}")).unwrap();
 
+    // Create a library that can be assigned to regular users
+    let tmp = TempDir::new("rf_user_mgmt");
+    std::fs::create_dir_all(&tmp).unwrap();
     let resp = server
         .post("/api/v1/libraries")
         .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({
-            "name": "Playback Movies",
-            "kind": "movies",
-            "paths": [movies_tmp.to_str().unwrap()]
-        }))
+        .json(&json!({ "name": "User Movies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
         .await;
     resp.assert_status(axum::http::StatusCode::CREATED);
-    let movies_lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
-
-    server
-        .post(&format!("/api/v1/libraries/{movies_lib_id}/scan"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .await;
-    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
-
-    let resp = server
-        .get(&format!("/api/v1/libraries/{movies_lib_id}/items"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .await;
-    resp.assert_status_ok();
-    let items: Value = resp.json();
-    let movie_item_id = items.as_array().unwrap()[0]["id"]
-        .as_str()
-        .unwrap()
-        .to_string();
+    let library_body: Value = resp.json();
+    let library_id = library_body["id"].as_str().unwrap().to_string();
 
+    // List users — should have the bootstrap admin
     let resp = server
-        .get(&format!("/api/v1/items/{movie_item_id}/playback"))
+        .get("/api/v1/users")
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
-    let playback: Value = resp.json();
-    let file_id = playback["file_id"].as_str().unwrap().to_string();
-    let direct_url = playback["direct_url"].as_str().unwrap();
-    assert!(direct_url.contains(&format!("/stream/file/{file_id}?st=")));
-    assert!(!direct_url.contains("?token="));
-
-    // TV fixture where top-level series item has no direct file mapping.
-    let tv_tmp = std::env::temp_dir().join(format!("rf_playback_desc_tv_{}", uuid::Uuid::new_v4()));
-    let season_dir = tv_tmp.join("Example Show/Season 01");
-    std::fs::create_dir_all(&season_dir).unwrap();
-    std::fs::write(season_dir.join("Example.Show.S01E01.mp4"), b"fake").unwrap();
+    let users: Vec<Value> = resp.json();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0]["username"], "admin");
 
+    // Create a new user
     let resp = server
-        .post("/api/v1/libraries")
+        .post("/api/v1/users")
         .add_header(hdr_name.clone(), hdr_val.clone())
         .json(&json!({
-            "name": "Playback TV",
-            "kind": "tv_shows",
-            "paths": [tv_tmp.to_str().unwrap()]
+            "username": "testuser",
+            "password": "testpass_secure",
+            "role": "user",
+            "library_ids": [library_id]
         }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let tv_lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    let new_user_id = body["id"].as_str().unwrap().to_string();
+    assert_eq!(body["username"], "testuser");
+    assert_eq!(body["role"], "user");
 
-    server
-        .post(&format!("/api/v1/libraries/{tv_lib_id}/scan"))
+    // List again — should have 2 users
+    let resp = server
+        .get("/api/v1/users")
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+    let users: Vec<Value> = resp.json();
+    assert_eq!(users.len(), 2);
 
+    // New user can login
+    let _user_token = login(&server, "testuser", "testpass_secure").await;
+
+    // Delete the new user
     let resp = server
-        .get(&format!("/api/v1/libraries/{tv_lib_id}/items"))
+        .delete(&format!("/api/v1/users/{new_user_id}"))
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
-    let tv_items: Value = resp.json();
-    let series_item_id = tv_items.as_array().unwrap()[0]["id"]
-        .as_str()
-        .unwrap()
-        .to_string();
 
+    // List again — should have 1 user
     let resp = server
-        .get(&format!("/api/v1/items/{series_item_id}/playback"))
+        .get("/api/v1/users")
         .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::CONFLICT);
-    let body: Value = resp.json();
-    assert_eq!(body["error"]["code"], "conflict");
-    assert!(
-        body["error"]["message"]
-            .as_str()
-            .unwrap()
-            .contains("No playable file mapped to this item")
-    );
-
-    std::fs::remove_dir_all(&movies_tmp).ok();
-    std::fs::remove_dir_all(&tv_tmp).ok();
+    let users: Vec<Value> = resp.json();
+    assert_eq!(users.len(), 1);
 }
 
 #[tokio::test]
-async fn hls_endpoints_require_auth_and_enforce_session_owner() {
-    let server = test_app_with_fake_ffmpeg().await;
-    let admin_token = login(&server, "admin", "admin_secure_123").await;
-    let admin_hdr = auth_hdr(&admin_token);
-
-    let tmp = std::env::temp_dir().join(format!("rf_hls_auth_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp).unwrap();
-    std::fs::write(tmp.join("Auth Movie (2020).mp4"), b"fake").unwrap();
-
+async fn admin_user_changes_are_written_to_the_audit_log() {
+    let server = test_app().await;
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({
-            "name": "HLS Auth Movies",
-            "kind": "movies",
-            "paths": [tmp.to_str().unwrap()]
-        }))
+        .post("/api/v1/auth/login")
+        .json(&json!({ "username": "admin", "password": "admin_secure_123" }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    let body: Value = resp.json();
+    let admin_id = body["user_id"].as_str().unwrap().to_string();
+    let (hn, hv) = auth_hdr(body["token"].as_str().unwrap());
 
-    server
-        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+    let resp = server
+        .post("/api/v1/users")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({ "username": "audited", "password": "audited_secret_pw", "role": "admin" }))
         .await;
-    tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+    resp.assert_status_ok();
+    let user_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    server
+        .delete(&format!("/api/v1/users/{user_id}"))
+        .add_header(hn.clone(), hv.clone())
+        .await
+        .assert_status_ok();
 
     let resp = server
-        .get(&format!("/api/v1/libraries/{lib_id}/items"))
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .get(&format!("/api/v1/system/audit?target={user_id}"))
+        .add_header(hn.clone(), hv.clone())
         .await;
-    let items: Value = resp.json();
-    let item_id = items.as_array().unwrap()[0]["id"]
-        .as_str()
-        .unwrap()
-        .to_string();
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["total"], 2);
+    let entries = body["entries"].as_array().unwrap();
+    // Newest first.
+    assert_eq!(entries[0]["action"], "user.delete");
+    assert_eq!(entries[1]["action"], "user.create");
+    for entry in entries {
+        assert_eq!(entry["actor_user_id"], admin_id.as_str());
+        assert_eq!(entry["target"], user_id.as_str());
+        assert!(entry["summary"].as_str().unwrap().contains("audited"));
+    }
+    assert!(!body.to_string().contains("audited_secret_pw"));
 
     let resp = server
-        .get(&format!("/api/v1/items/{item_id}/playback"))
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .get("/api/v1/system/audit?action=user.create&limit=1")
+        .add_header(hn.clone(), hv.clone())
         .await;
-    resp.assert_status_ok();
-    let playback: Value = resp.json();
-    let file_id = playback["file_id"].as_str().unwrap().to_string();
+    let body: Value = resp.json();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["limit"], 1);
 
+    // Regular users cannot read the log.
+    let tmp = TempDir::new("rf_audit");
+    std::fs::create_dir_all(&tmp).unwrap();
     let resp = server
-        .post("/api/v1/playback/sessions")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "file_id": file_id }))
+        .post("/api/v1/libraries")
+        .add_header(hn.clone(), hv.clone())
+        .json(
+            &json!({ "name": "Audit Movies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }),
+        )
         .await;
-    resp.assert_status_ok();
-    let session: Value = resp.json();
-    let sid = session["session_id"].as_str().unwrap().to_string();
+    let library_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
+    server
+        .post("/api/v1/users")
+        .add_header(hn.clone(), hv.clone())
+        .json(&json!({
+            "username": "viewer",
+            "password": "viewer_secure_pw",
+            "role": "user",
+            "library_ids": [library_id]
+        }))
+        .await
+        .assert_status_ok();
+    let user_token = login(&server, "viewer", "viewer_secure_pw").await;
+    let (hn, hv) = auth_hdr(&user_token);
+    server
+        .get("/api/v1/system/audit")
+        .add_header(hn, hv)
+        .await
+        .assert_status(axum::http::StatusCode::FORBIDDEN);
+    server
+        .get("/api/v1/system/audit")
+        .await
+        .assert_status(axum::http::StatusCode::UNAUTHORIZED);
+}
 
-    // Unauthenticated master request is rejected.
-    let resp = server.get(&format!("/stream/hls/{sid}/master.m3u8")).await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+// ---------------------------------------------------------------------------
+// Setup wizard tests
+// ---------------------------------------------------------------------------
+
+/// Create a test server in fresh (uncompleted setup) state.
+async fn test_app_fresh() -> TestServer {
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    test_app_fresh_for_pool(pool)
+}
+
+/// Like [`test_app_fresh`], for a pool the caller has already migrated.
+fn test_app_fresh_for_pool(pool: sqlx::SqlitePool) -> TestServer {
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        transcode_dir: std::env::temp_dir().join(format!("rf_setup_{}", std::process::id())),
+        max_concurrent: 2,
+        ..Default::default()
+    };
+    let transcoder =
+        std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
+
+    let state = AppState {
+        db: pool,
+        jwt_secret: "test-secret-key".to_string(),
+        transcoder,
+        cache_dir: std::env::temp_dir().join(format!("rf_cache_setup_{}", std::process::id())),
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
+    };
+
+    let app = build_router(state);
+    TestServer::new(app).unwrap()
+}
+
+#[tokio::test]
+async fn user_library_access_is_enforced() {
+    let server = test_app().await;
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let admin_hdr = auth_hdr(&admin_token);
+
+    // Create two libraries
+    let tmp_a = TempDir::new("rf_access_a");
+    std::fs::create_dir_all(&tmp_a).unwrap();
+    let tmp_b = TempDir::new("rf_access_b");
+    std::fs::create_dir_all(&tmp_b).unwrap();
 
-    // Session owner can fetch HLS resources.
     let resp = server
-        .get(&format!("/stream/hls/{sid}/master.m3u8"))
+        .post("/api/v1/libraries")
         .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "name": "Movies A", "kind": "movies", "paths": [tmp_a.to_str().unwrap()] }))
         .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
-    let master_playlist = String::from_utf8(resp.as_bytes().to_vec()).unwrap();
-    let first_child = master_playlist
-        .lines()
-        .map(str::trim)
-        .find(|line| !line.is_empty() && !line.starts_with('#'))
-        .expect("master playlist should include at least one child URI")
-        .to_string();
-    let first_child_path = if first_child.starts_with('/') {
-        first_child
-    } else {
-        format!("/stream/hls/{sid}/{first_child}")
-    };
-    assert!(first_child_path.contains("st="));
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib_a: Value = resp.json();
+    let lib_a_id = lib_a["id"].as_str().unwrap().to_string();
 
     let resp = server
-        .get(&first_child_path)
+        .post("/api/v1/libraries")
         .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "name": "Movies B", "kind": "movies", "paths": [tmp_b.to_str().unwrap()] }))
         .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::OK);
-    assert!(!resp.as_bytes().is_empty());
-
-    // Derive a concrete segment/media URL so we can verify auth there as well.
-    let child_body = String::from_utf8(resp.as_bytes().to_vec()).unwrap_or_default();
-    let maybe_segment = if first_child_path.contains(".m3u8") {
-        child_body
-            .lines()
-            .map(str::trim)
-            .find(|line| !line.is_empty() && !line.starts_with('#'))
-            .map(str::to_string)
-    } else {
-        Some(first_child_path.clone())
-    };
-    let segment_path = maybe_segment
-        .map(|uri| {
-            if uri.starts_with('/') {
-                uri
-            } else {
-                format!("/stream/hls/{sid}/{uri}")
-            }
-        })
-        .unwrap_or_else(|| format!("/stream/hls/{sid}/seg_00000.ts"));
-    let segment_path_no_query = segment_path
-        .split('?')
-        .next()
-        .unwrap_or(&segment_path)
-        .to_string();
-
-    let resp = server.get(&segment_path_no_query).await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::UNAUTHORIZED);
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib_b: Value = resp.json();
+    let lib_b_id = lib_b["id"].as_str().unwrap().to_string();
 
-    // Create a non-owner user and ensure they cannot access this session.
+    // Create simple user with access only to library A
     let resp = server
         .post("/api/v1/users")
         .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
         .json(&json!({
-            "username": "otheruser",
-            "password": "otheruser_pass_123",
+            "username": "viewer",
+            "password": "viewerpass_sec",
             "role": "user",
-            "library_ids": [lib_id]
+            "library_ids": [lib_a_id]
         }))
         .await;
     resp.assert_status_ok();
-    let other_token = login(&server, "otheruser", "otheruser_pass_123").await;
-    let other_hdr = auth_hdr(&other_token);
 
+    let viewer_token = login(&server, "viewer", "viewerpass_sec").await;
+    let viewer_hdr = auth_hdr(&viewer_token);
+
+    // Viewer sees only one library
     let resp = server
-        .get(&format!("/stream/hls/{sid}/master.m3u8"))
-        .add_header(other_hdr.0.clone(), other_hdr.1.clone())
+        .get("/api/v1/libraries")
+        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
         .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+    resp.assert_status_ok();
+    let libs: Vec<Value> = resp.json();
+    assert_eq!(libs.len(), 1);
+    assert_eq!(libs[0]["id"], lib_a["id"]);
 
+    // Viewer can access assigned library
     let resp = server
-        .get(&segment_path_no_query)
-        .add_header(other_hdr.0.clone(), other_hdr.1.clone())
+        .get(&format!("/api/v1/libraries/{lib_a_id}"))
+        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
         .await;
-    assert_eq!(resp.status_code(), axum::http::StatusCode::FORBIDDEN);
+    resp.assert_status_ok();
 
-    std::fs::remove_dir_all(&tmp).ok();
+    // Viewer cannot access unassigned library
+    let resp = server
+        .get(&format!("/api/v1/libraries/{lib_b_id}"))
+        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
 }
 
-// ---------------------------------------------------------------------------
-// Playback progress tests
-// ---------------------------------------------------------------------------
-
 #[tokio::test]
-async fn playback_progress_update_and_get() {
+async fn admin_can_modify_user_permissions() {
     let server = test_app().await;
-    let token = login(&server, "admin", "admin_secure_123").await;
-    let (hdr_name, hdr_val) = auth_hdr(&token);
-
-    // Create a library with a real temp dir and scan it for playback tests
-    let tmp = std::env::temp_dir().join(format!("rf_play_{}", std::process::id()));
-    std::fs::create_dir_all(&tmp).unwrap();
-    std::fs::write(tmp.join("Inception (2010).mkv"), "fake video data").unwrap();
+    let admin_token = login(&server, "admin", "admin_secure_123").await;
+    let admin_hdr = auth_hdr(&admin_token);
+
+    // Create two libraries
+    let tmp_1 = TempDir::new("rf_perm_1");
+    std::fs::create_dir_all(&tmp_1).unwrap();
+    let tmp_2 = TempDir::new("rf_perm_2");
+    std::fs::create_dir_all(&tmp_2).unwrap();
 
-    // Create library with real path
     let resp = server
         .post("/api/v1/libraries")
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({ "name": "PlayMovies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
-        .await;
-    let lib_id = resp.json::<Value>()["id"].as_str().unwrap().to_string();
-
-    // Scan
-    server
-        .post(&format!("/api/v1/libraries/{lib_id}/scan"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "name": "Lib 1", "kind": "movies", "paths": [tmp_1.to_str().unwrap()] }))
         .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib1: Value = resp.json();
+    let lib1_id = lib1["id"].as_str().unwrap().to_string();
 
-    // Wait for scan
-    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-
-    // Get items
     let resp = server
-        .get(&format!("/api/v1/libraries/{lib_id}/items"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .post("/api/v1/libraries")
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "name": "Lib 2", "kind": "movies", "paths": [tmp_2.to_str().unwrap()] }))
         .await;
-    let items: Value = resp.json();
-    let item_id = items.as_array().unwrap()[0]["id"]
-        .as_str()
-        .unwrap()
-        .to_string();
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let lib2: Value = resp.json();
+    let lib2_id = lib2["id"].as_str().unwrap().to_string();
 
-    // Get play state — should be default (no progress)
+    // Create user with Lib1 access
     let resp = server
-        .get(&format!("/api/v1/playback/state/{item_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .post("/api/v1/users")
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({
+            "username": "limited",
+            "password": "limitedpass_sec",
+            "role": "user",
+            "library_ids": [lib1_id]
+        }))
         .await;
     resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["progress_ms"], 0);
-    assert_eq!(body["played"], false);
+    let created: Value = resp.json();
+    let user_id = created["id"].as_str().unwrap().to_string();
 
-    // Update progress
+    // Move user access to Lib2
     let resp = server
-        .post("/api/v1/playback/progress")
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .patch(&format!("/api/v1/users/{user_id}"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
         .json(&json!({
-            "item_id": item_id,
-            "progress_ms": 120000,
-            "played": false
+            "role": "user",
+            "library_ids": [lib2_id]
         }))
         .await;
     resp.assert_status_ok();
+    let patched: Value = resp.json();
+    assert_eq!(patched["role"], "user");
+    assert_eq!(patched["library_ids"][0], lib2["id"]);
 
-    // Verify updated
+    let limited_token = login(&server, "limited", "limitedpass_sec").await;
+    let limited_hdr = auth_hdr(&limited_token);
     let resp = server
-        .get(&format!("/api/v1/playback/state/{item_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get("/api/v1/libraries")
+        .add_header(limited_hdr.0.clone(), limited_hdr.1.clone())
         .await;
-    let body: Value = resp.json();
-    assert_eq!(body["progress_ms"], 120000);
-    assert_eq!(body["played"], false);
+    resp.assert_status_ok();
+    let libs: Vec<Value> = resp.json();
+    assert_eq!(libs.len(), 1);
+    assert_eq!(libs[0]["id"], lib2["id"]);
 
-    // Mark as played
+    // Promote to admin; admin should see both libraries
     let resp = server
-        .post("/api/v1/playback/progress")
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({
-            "item_id": item_id,
-            "progress_ms": 120000,
-            "played": true
-        }))
+        .patch(&format!("/api/v1/users/{user_id}"))
+        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .json(&json!({ "role": "admin" }))
         .await;
     resp.assert_status_ok();
+    let patched: Value = resp.json();
+    assert_eq!(patched["role"], "admin");
 
+    let limited_token = login(&server, "limited", "limitedpass_sec").await;
+    let limited_hdr = auth_hdr(&limited_token);
     let resp = server
-        .get(&format!("/api/v1/playback/state/{item_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get("/api/v1/libraries")
+        .add_header(limited_hdr.0.clone(), limited_hdr.1.clone())
         .await;
+    resp.assert_status_ok();
+    let libs: Vec<Value> = resp.json();
+    assert_eq!(libs.len(), 2);
+}
+
+#[tokio::test]
+async fn public_info_shows_setup_incomplete_on_fresh_db() {
+    let server = test_app_fresh().await;
+    let resp = server.get("/api/v1/system/info/public").await;
+    resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["played"], true);
-    assert!(body["last_played_ts"].as_i64().unwrap() > 0);
+    assert_eq!(body["setup_completed"], false);
+    assert_eq!(body["setup_state"], "NotStarted");
+    assert_eq!(body["server_name"], "Rustyfin");
+    assert!(body["version"].as_str().is_some());
+}
 
-    // Cleanup
-    std::fs::remove_dir_all(&tmp).ok();
+#[tokio::test]
+async fn public_info_shows_completed_on_existing_install() {
+    let server = test_app().await;
+    let resp = server.get("/api/v1/system/info/public").await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["setup_completed"], true);
+    assert_eq!(body["setup_state"], "Completed");
 }
 
 #[tokio::test]
-async fn user_management_crud() {
+async fn system_config_update_is_reflected_in_public_info() {
     let server = test_app().await;
     let token = login(&server, "admin", "admin_secure_123").await;
-    let hdr_name = axum::http::header::AUTHORIZATION;
-    let hdr_val = axum::http::HeaderValue::from_str(&format!("Bearer {token}")).unwrap();
+    let (h, v) = auth_hdr(&token);
 
-    // Create a library that can be assigned to regular users
-    let tmp = std::env::temp_dir().join(format!("rf_user_mgmt_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp).unwrap();
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({ "name": "User Movies", "kind": "movies", "paths": [tmp.to_str().unwrap()] }))
+        .get("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let library_body: Value = resp.json();
-    let library_id = library_body["id"].as_str().unwrap().to_string();
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["server_name"], "Rustyfin");
 
-    // List users — should have the bootstrap admin
     let resp = server
-        .get("/api/v1/users")
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "server_name": "  Living Room  ", "default_region": "GB" }))
         .await;
     resp.assert_status_ok();
-    let users: Vec<Value> = resp.json();
-    assert_eq!(users.len(), 1);
-    assert_eq!(users[0]["username"], "admin");
+    let body: Value = resp.json();
+    assert_eq!(body["server_name"], "Living Room");
+    assert_eq!(body["default_region"], "GB");
+    assert_eq!(body["default_ui_locale"], "en");
 
-    // Create a new user
     let resp = server
-        .post("/api/v1/users")
-        .add_header(hdr_name.clone(), hdr_val.clone())
-        .json(&json!({
-            "username": "testuser",
-            "password": "testpass_secure",
-            "role": "user",
-            "library_ids": [library_id]
-        }))
+        .get("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
         .await;
+    let body: Value = resp.json();
+    assert_eq!(body["server_name"], "Living Room");
+    assert_eq!(body["default_region"], "GB");
+
+    let resp = server.get("/api/v1/system/info/public").await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    let new_user_id = body["id"].as_str().unwrap().to_string();
-    assert_eq!(body["username"], "testuser");
-    assert_eq!(body["role"], "user");
+    assert_eq!(body["server_name"], "Living Room");
 
-    // List again — should have 2 users
+    // Invalid values are rejected with field errors and nothing is saved.
     let resp = server
-        .get("/api/v1/users")
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "server_name": "Den", "default_region": "gb" }))
         .await;
-    let users: Vec<Value> = resp.json();
-    assert_eq!(users.len(), 2);
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    assert!(body["error"]["details"]["fields"]["default_region"].is_array());
+    let resp = server.get("/api/v1/system/info/public").await;
+    let body: Value = resp.json();
+    assert_eq!(body["server_name"], "Living Room");
+}
 
-    // New user can login
-    let _user_token = login(&server, "testuser", "testpass_secure").await;
+#[tokio::test]
+async fn direct_streams_beyond_the_limit_are_rejected() {
+    let tmp = TempDir::new("rf_stream_limit");
+    std::fs::create_dir_all(&tmp).unwrap();
+    std::fs::write(tmp.join("Limited (2020).mkv"), b"limited bytes").unwrap();
 
-    // Delete the new user
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &items[0].id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let limiter = StreamLimiter::new(1, 4);
+    let state = AppState {
+        streams: limiter.clone(),
+        ..test_state_for_pool(pool)
+    };
+    let server = TestServer::new(build_router(state)).unwrap();
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
+    let url = format!("/stream/file/{file_id}");
+
+    // Another stream holds the only slot.
+    let held = limiter.try_acquire().unwrap();
+    let resp = server.get(&url).add_header(h.clone(), v.clone()).await;
+    resp.assert_status(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(resp.json::<Value>()["error"]["code"], "service_unavailable");
+
+    drop(held);
+    let resp = server.get(&url).add_header(h.clone(), v.clone()).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.as_bytes().as_ref(), b"limited bytes");
+
+    // The finished response gave its slot back.
     let resp = server
-        .delete(&format!("/api/v1/users/{new_user_id}"))
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get(&url)
+        .add_header(h, v)
+        .add_header(
+            axum::http::header::RANGE,
+            axum::http::HeaderValue::from_static("bytes=0-6"),
+        )
         .await;
+    resp.assert_status(axum::http::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(resp.as_bytes().as_ref(), b"limited");
+}
+
+#[tokio::test]
+async fn match_candidates_list_provider_results_for_ambiguous_titles() {
+    let tmp = TempDir::new("rf_match");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Crash.mkv");
+
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "guest", "guest_secure_123", "user")
+        .await
+        .unwrap();
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let item_id = items[0].id.clone();
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
+    let url = format!("/api/v1/items/{item_id}/match-candidates");
+
+    // No provider key yet.
+    let resp = server.get(&url).add_header(h.clone(), v.clone()).await;
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+
+    let stub = spawn_tmdb_stub(|path| match path {
+        "/search/movie" => json!({ "results": [
+            { "id": 20076, "title": "Crash", "release_date": "1996-10-04",
+              "overview": "Car crash fetishists.", "poster_path": "/crash96.jpg" },
+            { "id": 1640, "title": "Crash", "release_date": "2004-09-10",
+              "overview": "Los Angeles intersecting lives." }
+        ]}),
+        _ => json!({}),
+    })
+    .await;
+    rustfin_db::repo::settings::set(&pool, "tmdb_api_key", "test-key")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "tmdb_base_url", &stub)
+        .await
+        .unwrap();
+
+    let resp = server.get(&url).add_header(h.clone(), v.clone()).await;
     resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["item_id"], item_id.as_str());
+    assert_eq!(body["provider"], "tmdb");
+    let candidates = body["candidates"].as_array().unwrap();
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates[0]["provider_id"], "20076");
+    assert_eq!(candidates[0]["year"], 1996);
+    let poster = candidates[0]["poster_url"].as_str().unwrap();
+    assert!(poster.starts_with("/api/v1/images/proxy?url=https%3A%2F%2F"));
+    assert!(poster.ends_with("%2Fcrash96.jpg"));
+    assert_eq!(candidates[1]["provider_id"], "1640");
+    assert_eq!(candidates[1]["year"], 2004);
+    assert_eq!(candidates[1]["title"], "Crash");
 
-    // List again — should have 1 user
     let resp = server
-        .get("/api/v1/users")
-        .add_header(hdr_name.clone(), hdr_val.clone())
+        .get(&format!("{url}?query=Crash&year=2004"))
+        .add_header(h.clone(), v.clone())
         .await;
-    let users: Vec<Value> = resp.json();
-    assert_eq!(users.len(), 1);
+    resp.assert_status_ok();
+
+    // Users without access to the library can't search for its items.
+    let guest = login(&server, "guest", "guest_secure_123").await;
+    let (gh, gv) = auth_hdr(&guest);
+    let resp = server.get(&url).add_header(gh, gv).await;
+    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
 
-    std::fs::remove_dir_all(&tmp).ok();
+    server
+        .get("/api/v1/items/nope/match-candidates")
+        .add_header(h, v)
+        .await
+        .assert_status(axum::http::StatusCode::NOT_FOUND);
 }
 
-// ---------------------------------------------------------------------------
-// Setup wizard tests
-// ---------------------------------------------------------------------------
+#[tokio::test]
+async fn tmdb_images_are_fetched_and_cached_per_size_variant() {
+    // Echo the requested path back as the image body.
+    let base = spawn_tmdb_stub(|path| json!(path)).await;
 
-/// Create a test server in fresh (uncompleted setup) state.
-async fn test_app_fresh() -> TestServer {
-    let pool = rustfin_db::connect(":memory:").await.unwrap();
-    rustfin_db::migrate::run(&pool).await.unwrap();
+    let tmp = TempDir::new("rf_tmdb_sizes");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Sized (2020).mkv");
+
+    let pool = test_pool().await;
     rustfin_db::repo::settings::insert_defaults(&pool)
         .await
         .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let item_id = items[0].id.clone();
+    rustfin_db::repo::items::update_item_artwork(
+        &pool,
+        &item_id,
+        Some(&format!("{base}/t/p/original/sized.jpg")),
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
 
-    let tc_config = rustfin_transcoder::TranscoderConfig {
-        transcode_dir: std::env::temp_dir().join(format!("rf_setup_{}", std::process::id())),
-        max_concurrent: 2,
-        ..Default::default()
-    };
-    let transcoder =
-        std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(tc_config));
-
-    let (events_tx, _) = tokio::sync::broadcast::channel(64);
-    let state = AppState {
-        db: pool,
-        jwt_secret: "test-secret-key".to_string(),
-        transcoder,
-        cache_dir: std::env::temp_dir().join(format!("rf_cache_setup_{}", std::process::id())),
-        events: events_tx,
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
+    let fetch = |query: &'static str| {
+        server
+            .get(&format!("/api/v1/items/{item_id}/images/poster{query}"))
+            .add_header(h.clone(), v.clone())
     };
 
-    let app = build_router(state);
-    TestServer::new(app).unwrap()
+    // A small poster is fetched as TMDB's small variant, not the original.
+    let resp = fetch("?w=150").await;
+    resp.assert_status_ok();
+    assert_eq!(resp.text(), "\"/t/p/w154/sized.jpg\"");
+    let resp = fetch("?w=600").await;
+    assert_eq!(resp.text(), "\"/t/p/w780/sized.jpg\"");
+    let resp = fetch("").await;
+    assert_eq!(resp.text(), "\"/t/p/original/sized.jpg\"");
+
+    // Each variant has its own cache entry; sizes sharing a variant share it.
+    let resp = fetch("?w=120").await;
+    assert_eq!(resp.text(), "\"/t/p/w154/sized.jpg\"");
+    let images_dir = std::env::temp_dir().join(format!("rf_cache_{}/images", std::process::id()));
+    let mut cached: Vec<String> = std::fs::read_dir(&images_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with(&item_id))
+        .collect();
+    cached.sort();
+    assert_eq!(
+        cached,
+        vec![
+            format!("{item_id}_poster_0_0.jpg"),
+            format!("{item_id}_poster_w154.jpg"),
+            format!("{item_id}_poster_w780.jpg"),
+        ]
+    );
 }
 
 #[tokio::test]
-async fn user_library_access_is_enforced() {
-    let server = test_app().await;
-    let admin_token = login(&server, "admin", "admin_secure_123").await;
-    let admin_hdr = auth_hdr(&admin_token);
+async fn cache_control_follows_configured_policy() {
+    let tmp = TempDir::new("rf_cache_policy");
+    std::fs::create_dir_all(&tmp).unwrap();
+    std::fs::write(tmp.join("Cached (2020).mkv"), vec![0u8; 2048]).unwrap();
+    let poster = tmp.join("poster.jpg");
+    std::fs::write(&poster, b"fake jpeg").unwrap();
 
-    // Create two libraries
-    let tmp_a = std::env::temp_dir().join(format!("rf_access_a_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp_a).unwrap();
-    let tmp_b = std::env::temp_dir().join(format!("rf_access_b_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp_b).unwrap();
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    admin_user(&pool).await;
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let item_id = items[0].id.clone();
+    rustfin_db::repo::items::update_item_artwork(
+        &pool,
+        &item_id,
+        Some(poster.to_str().unwrap()),
+        None,
+        None,
+        None,
+    )
+    .await
+    .unwrap();
+    let file_id = rustfin_db::repo::items::get_item_file_id(&pool, &item_id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (h, v) = auth_hdr(&token);
+    let image_url = format!("/api/v1/items/{item_id}/images/poster");
+    let stream_url = format!("/stream/file/{file_id}");
 
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "name": "Movies A", "kind": "movies", "paths": [tmp_a.to_str().unwrap()] }))
+        .get(&image_url)
+        .add_header(h.clone(), v.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let lib_a: Value = resp.json();
-    let lib_a_id = lib_a["id"].as_str().unwrap().to_string();
-
+    resp.assert_status_ok();
+    assert_eq!(resp.header("cache-control"), "public, max-age=86400");
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "name": "Movies B", "kind": "movies", "paths": [tmp_b.to_str().unwrap()] }))
+        .get(&stream_url)
+        .add_header(h.clone(), v.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let lib_b: Value = resp.json();
-    let lib_b_id = lib_b["id"].as_str().unwrap().to_string();
+    resp.assert_status_ok();
+    assert_eq!(resp.header("cache-control"), "no-store");
 
-    // Create simple user with access only to library A
     let resp = server
-        .post("/api/v1/users")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({
-            "username": "viewer",
-            "password": "viewerpass_sec",
-            "role": "user",
-            "library_ids": [lib_a_id]
-        }))
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "image_cache_max_age_secs": 600, "media_cacheable": false }))
         .await;
     resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["image_cache_max_age_secs"], 600);
+    assert_eq!(body["media_cacheable"], false);
 
-    let viewer_token = login(&server, "viewer", "viewerpass_sec").await;
-    let viewer_hdr = auth_hdr(&viewer_token);
-
-    // Viewer sees only one library
     let resp = server
-        .get("/api/v1/libraries")
-        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
+        .get(&image_url)
+        .add_header(h.clone(), v.clone())
         .await;
-    resp.assert_status_ok();
-    let libs: Vec<Value> = resp.json();
-    assert_eq!(libs.len(), 1);
-    assert_eq!(libs[0]["id"], lib_a["id"]);
+    assert_eq!(resp.header("cache-control"), "public, max-age=600");
+    let resp = server
+        .get(&stream_url)
+        .add_header(h.clone(), v.clone())
+        .await;
+    assert_eq!(resp.header("cache-control"), "no-store");
 
-    // Viewer can access assigned library
     let resp = server
-        .get(&format!("/api/v1/libraries/{lib_a_id}"))
-        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "media_cacheable": true }))
         .await;
     resp.assert_status_ok();
-
-    // Viewer cannot access unassigned library
     let resp = server
-        .get(&format!("/api/v1/libraries/{lib_b_id}"))
-        .add_header(viewer_hdr.0.clone(), viewer_hdr.1.clone())
+        .get(&stream_url)
+        .add_header(h.clone(), v.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::FORBIDDEN);
+    assert_eq!(resp.header("cache-control"), "private, max-age=86400");
 
-    std::fs::remove_dir_all(&tmp_a).ok();
-    std::fs::remove_dir_all(&tmp_b).ok();
+    // Out-of-range and mistyped values are rejected.
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "image_cache_max_age_secs": 100_000_000 }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    assert!(body["error"]["details"]["fields"]["image_cache_max_age_secs"].is_array());
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(h.clone(), v.clone())
+        .json(&json!({ "image_cache_max_age_secs": -1 }))
+        .await;
+    assert!(resp.status_code().is_client_error());
+    let resp = server.get(&image_url).add_header(h, v).await;
+    assert_eq!(resp.header("cache-control"), "public, max-age=600");
 }
 
 #[tokio::test]
-async fn admin_can_modify_user_permissions() {
-    let server = test_app().await;
-    let admin_token = login(&server, "admin", "admin_secure_123").await;
-    let admin_hdr = auth_hdr(&admin_token);
+async fn setup_claim_and_release_session() {
+    let server = test_app_fresh().await;
 
-    // Create two libraries
-    let tmp_1 = std::env::temp_dir().join(format!("rf_perm_1_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp_1).unwrap();
-    let tmp_2 = std::env::temp_dir().join(format!("rf_perm_2_{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&tmp_2).unwrap();
+    // Claim session
+    let resp = server
+        .post("/api/v1/setup/session/claim")
+        .json(&json!({
+            "client_name": "TestUI",
+            "force": false,
+            "confirm_takeover": false
+        }))
+        .await;
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    let token = body["owner_token"].as_str().unwrap().to_string();
+    assert_eq!(body["claimed_by"], "TestUI");
+    assert!(!token.is_empty());
 
+    // Second claim without force should 409
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "name": "Lib 1", "kind": "movies", "paths": [tmp_1.to_str().unwrap()] }))
+        .post("/api/v1/setup/session/claim")
+        .json(&json!({
+            "client_name": "OtherUI",
+            "force": false,
+            "confirm_takeover": false
+        }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let lib1: Value = resp.json();
-    let lib1_id = lib1["id"].as_str().unwrap().to_string();
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+    let body: Value = resp.json();
+    assert_eq!(body["error"]["code"], "setup_claimed");
 
+    // Release session
     let resp = server
-        .post("/api/v1/libraries")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "name": "Lib 2", "kind": "movies", "paths": [tmp_2.to_str().unwrap()] }))
+        .post("/api/v1/setup/session/release")
+        .add_header(
+            axum::http::HeaderName::from_static("x-setup-owner-token"),
+            token.parse::<axum::http::HeaderValue>().unwrap(),
+        )
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let lib2: Value = resp.json();
-    let lib2_id = lib2["id"].as_str().unwrap().to_string();
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["released"], true);
+}
 
-    // Create user with Lib1 access
+#[tokio::test]
+async fn setup_full_wizard_flow() {
+    let server = test_app_fresh().await;
+
+    // Step 1: Claim session
     let resp = server
-        .post("/api/v1/users")
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .post("/api/v1/setup/session/claim")
         .json(&json!({
-            "username": "limited",
-            "password": "limitedpass_sec",
-            "role": "user",
-            "library_ids": [lib1_id]
+            "client_name": "TestUI",
+            "force": false,
+            "confirm_takeover": false
         }))
         .await;
     resp.assert_status_ok();
-    let created: Value = resp.json();
-    let user_id = created["id"].as_str().unwrap().to_string();
+    let body: Value = resp.json();
+    let token = body["owner_token"].as_str().unwrap().to_string();
 
-    // Move user access to Lib2
+    let owner_hdr = axum::http::HeaderName::from_static("x-setup-owner-token");
+    let owner_val: axum::http::HeaderValue = token.parse().unwrap();
+
+    // Step 2: PUT config
     let resp = server
-        .patch(&format!("/api/v1/users/{user_id}"))
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
+        .put("/api/v1/setup/config")
+        .add_header(owner_hdr.clone(), owner_val.clone())
         .json(&json!({
-            "role": "user",
-            "library_ids": [lib2_id]
+            "server_name": "My Rustyfin",
+            "default_ui_locale": "en-US",
+            "default_region": "US",
+            "default_time_zone": "America/New_York"
         }))
         .await;
     resp.assert_status_ok();
-    let patched: Value = resp.json();
-    assert_eq!(patched["role"], "user");
-    assert_eq!(patched["library_ids"][0], lib2["id"]);
+    let body: Value = resp.json();
+    assert_eq!(body["ok"], true);
+    assert_eq!(body["setup_state"], "ServerConfigSaved");
 
-    let limited_token = login(&server, "limited", "limitedpass_sec").await;
-    let limited_hdr = auth_hdr(&limited_token);
+    // Step 3: Create admin
     let resp = server
-        .get("/api/v1/libraries")
-        .add_header(limited_hdr.0.clone(), limited_hdr.1.clone())
+        .post("/api/v1/setup/admin")
+        .add_header(owner_hdr.clone(), owner_val.clone())
+        .add_header(
+            axum::http::HeaderName::from_static("idempotency-key"),
+            "test-idem-key-12345678"
+                .parse::<axum::http::HeaderValue>()
+                .unwrap(),
+        )
+        .json(&json!({
+            "username": "myadmin",
+            "password": "supersecurepassword123"
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+    let body: Value = resp.json();
+    assert!(body["user_id"].as_str().is_some());
+    assert_eq!(body["setup_state"], "AdminCreated");
+
+    // Step 3b: Idempotent replay with same key
+    let resp = server
+        .post("/api/v1/setup/admin")
+        .add_header(owner_hdr.clone(), owner_val.clone())
+        .add_header(
+            axum::http::HeaderName::from_static("idempotency-key"),
+            "test-idem-key-12345678"
+                .parse::<axum::http::HeaderValue>()
+                .unwrap(),
+        )
+        .json(&json!({
+            "username": "myadmin",
+            "password": "supersecurepassword123"
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::CREATED);
+
+    // Step 4: PUT metadata (skipping libraries since they're optional)
+    let resp = server
+        .put("/api/v1/setup/metadata")
+        .add_header(owner_hdr.clone(), owner_val.clone())
+        .json(&json!({
+            "metadata_language": "en",
+            "metadata_region": "US"
+        }))
         .await;
     resp.assert_status_ok();
-    let libs: Vec<Value> = resp.json();
-    assert_eq!(libs.len(), 1);
-    assert_eq!(libs[0]["id"], lib2["id"]);
+    let body: Value = resp.json();
+    assert_eq!(body["setup_state"], "MetadataSaved");
 
-    // Promote to admin; admin should see both libraries
+    // Step 5: PUT network
     let resp = server
-        .patch(&format!("/api/v1/users/{user_id}"))
-        .add_header(admin_hdr.0.clone(), admin_hdr.1.clone())
-        .json(&json!({ "role": "admin" }))
+        .put("/api/v1/setup/network")
+        .add_header(owner_hdr.clone(), owner_val.clone())
+        .json(&json!({
+            "allow_remote_access": false,
+            "enable_automatic_port_mapping": false,
+            "trusted_proxies": []
+        }))
         .await;
     resp.assert_status_ok();
-    let patched: Value = resp.json();
-    assert_eq!(patched["role"], "admin");
+    let body: Value = resp.json();
+    assert_eq!(body["setup_state"], "NetworkSaved");
 
-    let limited_token = login(&server, "limited", "limitedpass_sec").await;
-    let limited_hdr = auth_hdr(&limited_token);
+    // Step 6: Complete
     let resp = server
-        .get("/api/v1/libraries")
-        .add_header(limited_hdr.0.clone(), limited_hdr.1.clone())
+        .post("/api/v1/setup/complete")
+        .add_header(owner_hdr.clone(), owner_val.clone())
+        .json(&json!({ "confirm": true }))
         .await;
     resp.assert_status_ok();
-    let libs: Vec<Value> = resp.json();
-    assert_eq!(libs.len(), 2);
-
-    std::fs::remove_dir_all(&tmp_1).ok();
-    std::fs::remove_dir_all(&tmp_2).ok();
-}
+    let body: Value = resp.json();
+    assert_eq!(body["setup_completed"], true);
+    assert_eq!(body["setup_state"], "Completed");
 
-#[tokio::test]
-async fn public_info_shows_setup_incomplete_on_fresh_db() {
-    let server = test_app_fresh().await;
+    // Verify: public info now shows completed
     let resp = server.get("/api/v1/system/info/public").await;
+    let body: Value = resp.json();
+    assert_eq!(body["setup_completed"], true);
+    assert_eq!(body["server_name"], "My Rustyfin");
+
+    // Verify: admin can login
+    let resp = server
+        .post("/api/v1/auth/login")
+        .json(&json!({ "username": "myadmin", "password": "supersecurepassword123" }))
+        .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["setup_completed"], false);
-    assert_eq!(body["setup_state"], "NotStarted");
-    assert_eq!(body["server_name"], "Rustyfin");
-    assert!(body["version"].as_str().is_some());
+    assert_eq!(body["role"], "admin");
 }
 
 #[tokio::test]
-async fn public_info_shows_completed_on_existing_install() {
-    let server = test_app().await;
-    let resp = server.get("/api/v1/system/info/public").await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["setup_completed"], true);
-    assert_eq!(body["setup_state"], "Completed");
+async fn setup_state_machine_enforces_order() {
+    let server = test_app_fresh().await;
+
+    // Try to put config without claiming session first — should fail (no token)
+    let resp = server
+        .put("/api/v1/setup/config")
+        .json(&json!({
+            "server_name": "Test",
+            "default_ui_locale": "en",
+            "default_region": "US"
+        }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
 }
 
 #[tokio::test]
-async fn setup_claim_and_release_session() {
+async fn setup_validation_rejects_weak_password() {
     let server = test_app_fresh().await;
 
     // Claim session
@@ -1453,43 +8706,58 @@ async fn setup_claim_and_release_session() {
             "confirm_takeover": false
         }))
         .await;
-    resp.assert_status_ok();
     let body: Value = resp.json();
     let token = body["owner_token"].as_str().unwrap().to_string();
-    assert_eq!(body["claimed_by"], "TestUI");
-    assert!(!token.is_empty());
+    let owner_hdr = axum::http::HeaderName::from_static("x-setup-owner-token");
+    let owner_val: axum::http::HeaderValue = token.parse().unwrap();
 
-    // Second claim without force should 409
+    // Put config first
     let resp = server
-        .post("/api/v1/setup/session/claim")
+        .put("/api/v1/setup/config")
+        .add_header(owner_hdr.clone(), owner_val.clone())
         .json(&json!({
-            "client_name": "OtherUI",
-            "force": false,
-            "confirm_takeover": false
+            "server_name": "Test",
+            "default_ui_locale": "en",
+            "default_region": "US"
         }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CONFLICT);
-    let body: Value = resp.json();
-    assert_eq!(body["error"]["code"], "setup_claimed");
+    resp.assert_status_ok();
 
-    // Release session
+    // Try to create admin with short password
     let resp = server
-        .post("/api/v1/setup/session/release")
+        .post("/api/v1/setup/admin")
+        .add_header(owner_hdr.clone(), owner_val.clone())
         .add_header(
-            axum::http::HeaderName::from_static("x-setup-owner-token"),
-            token.parse::<axum::http::HeaderValue>().unwrap(),
+            axum::http::HeaderName::from_static("idempotency-key"),
+            "validate-test-key123"
+                .parse::<axum::http::HeaderValue>()
+                .unwrap(),
         )
+        .json(&json!({
+            "username": "admin",
+            "password": "short"
+        }))
         .await;
-    resp.assert_status_ok();
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
     let body: Value = resp.json();
-    assert_eq!(body["released"], true);
+    assert_eq!(body["error"]["code"], "validation_failed");
+    assert!(body["error"]["details"]["fields"]["password"].is_array());
 }
 
 #[tokio::test]
-async fn setup_full_wizard_flow() {
-    let server = test_app_fresh().await;
+async fn setup_admin_password_follows_the_configured_policy() {
+    let pool = test_pool().await;
+    rustfin_db::repo::settings::insert_defaults(&pool)
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "password_min_length", "16")
+        .await
+        .unwrap();
+    rustfin_db::repo::settings::set(&pool, "password_require_mixed", "true")
+        .await
+        .unwrap();
+    let server = test_app_fresh_for_pool(pool);
 
-    // Step 1: Claim session
     let resp = server
         .post("/api/v1/setup/session/claim")
         .json(&json!({
@@ -1498,212 +8766,791 @@ async fn setup_full_wizard_flow() {
             "confirm_takeover": false
         }))
         .await;
-    resp.assert_status_ok();
     let body: Value = resp.json();
     let token = body["owner_token"].as_str().unwrap().to_string();
-
     let owner_hdr = axum::http::HeaderName::from_static("x-setup-owner-token");
     let owner_val: axum::http::HeaderValue = token.parse().unwrap();
-
-    // Step 2: PUT config
-    let resp = server
+    server
         .put("/api/v1/setup/config")
         .add_header(owner_hdr.clone(), owner_val.clone())
         .json(&json!({
-            "server_name": "My Rustyfin",
-            "default_ui_locale": "en-US",
-            "default_region": "US",
-            "default_time_zone": "America/New_York"
+            "server_name": "Test",
+            "default_ui_locale": "en",
+            "default_region": "US"
         }))
+        .await
+        .assert_status_ok();
+
+    let create_admin = |key: &'static str, password: &'static str| {
+        server
+            .post("/api/v1/setup/admin")
+            .add_header(owner_hdr.clone(), owner_val.clone())
+            .add_header(
+                axum::http::HeaderName::from_static("idempotency-key"),
+                axum::http::HeaderValue::from_static(key),
+            )
+            .json(&json!({ "username": "admin", "password": password }))
+    };
+
+    // Long enough for the default policy, but too short and all lowercase here.
+    let resp = create_admin("policy-weak-key1", "lowercaseonly").await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    assert_eq!(
+        body["error"]["details"]["fields"]["password"]
+            .as_array()
+            .unwrap()
+            .len(),
+        2
+    );
+
+    create_admin("policy-strong-key", "Mixed-Case-And-42")
+        .await
+        .assert_status(axum::http::StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn user_management_password_follows_the_configured_policy() {
+    let server = test_app().await;
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let create_user = |username: &'static str, password: &'static str| {
+        server
+            .post("/api/v1/users")
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "username": username, "password": password, "role": "admin" }))
+    };
+
+    create_user("default_ok", "lowercaseonly")
+        .await
+        .assert_status_ok();
+    let resp = create_user("common_pw", "password1234").await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = resp.json();
+    assert_eq!(
+        body["error"]["details"]["fields"]["password"],
+        json!(["is too common"])
+    );
+
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "password_min_length": 16, "password_require_mixed": true }))
         .await;
     resp.assert_status_ok();
+    let config: Value = resp.json();
+    assert_eq!(config["password_min_length"], 16);
+    assert_eq!(config["password_require_mixed"], true);
+    assert_eq!(config["password_block_common"], true);
+
+    let resp = create_user("strict_weak", "lowercaseonly").await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
     let body: Value = resp.json();
-    assert_eq!(body["ok"], true);
-    assert_eq!(body["setup_state"], "ServerConfigSaved");
+    assert!(body["error"]["details"]["fields"]["password"].is_array());
+    create_user("strict_ok", "Mixed-Case-And-42")
+        .await
+        .assert_status_ok();
 
-    // Step 3: Create admin
     let resp = server
-        .post("/api/v1/setup/admin")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .add_header(
-            axum::http::HeaderName::from_static("idempotency-key"),
-            "test-idem-key-12345678"
-                .parse::<axum::http::HeaderValue>()
-                .unwrap(),
-        )
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "password_min_length": 4 }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn setup_force_takeover() {
+    let server = test_app_fresh().await;
+
+    // First claim
+    let resp = server
+        .post("/api/v1/setup/session/claim")
         .json(&json!({
-            "username": "myadmin",
-            "password": "supersecurepassword123"
+            "client_name": "Browser1",
+            "force": false,
+            "confirm_takeover": false
         }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
-    let body: Value = resp.json();
-    assert!(body["user_id"].as_str().is_some());
-    assert_eq!(body["setup_state"], "AdminCreated");
+    resp.assert_status_ok();
 
-    // Step 3b: Idempotent replay with same key
+    // Force takeover
     let resp = server
-        .post("/api/v1/setup/admin")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .add_header(
-            axum::http::HeaderName::from_static("idempotency-key"),
-            "test-idem-key-12345678"
-                .parse::<axum::http::HeaderValue>()
-                .unwrap(),
-        )
+        .post("/api/v1/setup/session/claim")
         .json(&json!({
-            "username": "myadmin",
-            "password": "supersecurepassword123"
+            "client_name": "Browser2",
+            "force": true,
+            "confirm_takeover": true
         }))
         .await;
-    resp.assert_status(axum::http::StatusCode::CREATED);
+    resp.assert_status_ok();
+    let body: Value = resp.json();
+    assert_eq!(body["claimed_by"], "Browser2");
+}
+
+// ---------------------------------------------------------------------------
+// Duplicate detection tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn duplicate_provider_ids_within_a_library_are_flagged_and_reported() {
+    let tmp = TempDir::new("rf_pid");
+    for name in ["Heat (1995)", "Heat 2 (1995)", "Ronin (1998)"] {
+        std::fs::create_dir_all(tmp.join(name)).unwrap();
+        tmp.touch(format!("{name}/{name}.mkv"));
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::settings::set(&pool, "setup_completed", "true")
+        .await
+        .unwrap();
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let items = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap();
+    let id_of = |title: &str| {
+        items
+            .iter()
+            .find(|i| i.title == title)
+            .map(|i| i.id.clone())
+            .unwrap()
+    };
+    let (heat, heat2, ronin) = (id_of("Heat"), id_of("Heat 2"), id_of("Ronin"));
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let assign = |item_id: String| {
+        server
+            .post(&format!("/api/v1/items/{item_id}/metadata/refresh"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+            .json(&json!({ "provider": "tmdb", "provider_id": "949" }))
+    };
+
+    // Warn (the default): stored, with the clash reported.
+    let resp = assign(heat.clone()).await;
+    resp.assert_status_ok();
+    assert!(resp.json::<Value>().get("provider_id_conflicts").is_none());
+    let resp = assign(heat2.clone()).await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["provider_id_conflicts"], json!([heat]));
+
+    let resp = server
+        .get("/api/v1/system/provider-id-conflicts")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let conflicts: Value = resp.json();
+    assert_eq!(conflicts.as_array().unwrap().len(), 1);
+    assert_eq!(conflicts[0]["library_id"], lib.id.as_str());
+    assert_eq!(conflicts[0]["kind"], "movie");
+    assert_eq!(conflicts[0]["provider"], "tmdb");
+    assert_eq!(conflicts[0]["provider_id"], "949");
+    let mut ids: Vec<&str> = conflicts[0]["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|i| i["item_id"].as_str().unwrap())
+        .collect();
+    ids.sort();
+    let mut expected = vec![heat.as_str(), heat2.as_str()];
+    expected.sort();
+    assert_eq!(ids, expected);
+
+    // Reject: the item keeps its provider ids.
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "provider_id_conflicts": "reject" }))
+        .await;
+    resp.assert_status_ok();
+    assert_eq!(resp.json::<Value>()["provider_id_conflicts"], "reject");
+    let resp = assign(ronin.clone()).await;
+    resp.assert_status(axum::http::StatusCode::CONFLICT);
+    let providers: Value = server
+        .get(&format!("/api/v1/items/{ronin}/providers"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    assert!(providers.get("tmdb").is_none());
+
+    let resp = server
+        .patch("/api/v1/system/config")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "provider_id_conflicts": "sometimes" }))
+        .await;
+    resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn duplicates_across_libraries_are_reported_and_mergeable() {
+    let tmp_a = TempDir::new("rf_dup_a");
+    let tmp_b = TempDir::new("rf_dup_b");
+    std::fs::create_dir_all(tmp_a.join("Heat (1995)")).unwrap();
+    std::fs::create_dir_all(tmp_b.join("Heat (1995)")).unwrap();
+    tmp_a.touch("Heat (1995)/Heat (1995) 1080p.mkv");
+    tmp_b.touch("Heat (1995)/Heat.1995.2160p.mkv");
+    tmp_b.touch("Ronin (1998).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+
+    let mut lib_ids = Vec::new();
+    for (name, dir) in [("HD", &tmp_a), ("UHD", &tmp_b)] {
+        let lib = scanned_library(&pool, name, "movies", dir).await;
+        lib_ids.push(lib.id);
+    }
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
 
-    // Step 4: PUT metadata (skipping libraries since they're optional)
     let resp = server
-        .put("/api/v1/setup/metadata")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .json(&json!({
-            "metadata_language": "en",
-            "metadata_region": "US"
-        }))
+        .get("/api/v1/system/duplicates")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["setup_state"], "MetadataSaved");
+    let groups: Value = resp.json();
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 1, "only Heat is duplicated: {groups:?}");
+    assert_eq!(groups[0]["kind"], "movie");
+    assert_eq!(groups[0]["key"], "title:heat|1995");
+    let members = groups[0]["items"].as_array().unwrap();
+    assert_eq!(members.len(), 2);
+    let libs: HashSet<&str> = members
+        .iter()
+        .map(|m| m["library_id"].as_str().unwrap())
+        .collect();
+    assert_eq!(libs.len(), 2);
+
+    let source = members[0]["item_id"].as_str().unwrap().to_string();
+    let target = members[1]["item_id"].as_str().unwrap().to_string();
 
-    // Step 5: PUT network
     let resp = server
-        .put("/api/v1/setup/network")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .json(&json!({
-            "allow_remote_access": false,
-            "enable_automatic_port_mapping": false,
-            "trusted_proxies": []
-        }))
+        .post(&format!("/api/v1/items/{source}/merge-into/{source}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["setup_state"], "NetworkSaved");
+    resp.assert_status(axum::http::StatusCode::BAD_REQUEST);
 
-    // Step 6: Complete
     let resp = server
-        .post("/api/v1/setup/complete")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .json(&json!({ "confirm": true }))
+        .post(&format!("/api/v1/items/{source}/merge-into/{target}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["setup_completed"], true);
-    assert_eq!(body["setup_state"], "Completed");
+    assert_eq!(body["item_id"], target.as_str());
+    assert_eq!(body["file_ids"].as_array().unwrap().len(), 2);
 
-    // Verify: public info now shows completed
-    let resp = server.get("/api/v1/system/info/public").await;
-    let body: Value = resp.json();
-    assert_eq!(body["setup_completed"], true);
-    assert_eq!(body["server_name"], "My Rustyfin");
+    // Source item is gone, target now has both versions, and no duplicates remain.
+    let resp = server
+        .get(&format!("/api/v1/items/{source}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status(axum::http::StatusCode::NOT_FOUND);
 
-    // Verify: admin can login
     let resp = server
-        .post("/api/v1/auth/login")
-        .json(&json!({ "username": "myadmin", "password": "supersecurepassword123" }))
+        .get("/api/v1/system/duplicates")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status_ok();
-    let body: Value = resp.json();
-    assert_eq!(body["role"], "admin");
+    assert!(resp.json::<Value>().as_array().unwrap().is_empty());
 }
 
 #[tokio::test]
-async fn setup_state_machine_enforces_order() {
-    let server = test_app_fresh().await;
+async fn duplicate_series_from_the_report_merge_season_by_season() {
+    let tmp_a = TempDir::new("rf_dup_series_a");
+    let tmp_b = TempDir::new("rf_dup_series_b");
+    for rel in [
+        "Lost/Season 01/Lost.S01E01.mkv",
+        "Lost/Season 01/Lost.S01E02.mkv",
+    ] {
+        tmp_a.touch(rel);
+    }
+    // The second copy repeats one episode and adds a season.
+    for rel in [
+        "Lost/Season 01/Lost.S01E02.2160p.mkv",
+        "Lost/Season 01/Lost.S01E03.mkv",
+        "Lost/Season 02/Lost.S02E01.mkv",
+    ] {
+        tmp_b.touch(rel);
+    }
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    for (name, dir) in [("Shows", &tmp_a), ("Shows UHD", &tmp_b)] {
+        scanned_library(&pool, name, "tv_shows", dir).await;
+    }
+
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    let groups: Value = server
+        .get("/api/v1/system/duplicates")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .json();
+    let groups = groups.as_array().unwrap();
+    assert_eq!(groups.len(), 1, "{groups:?}");
+    assert_eq!(groups[0]["kind"], "series");
+    let members = groups[0]["items"].as_array().unwrap();
+    let source = members[0]["item_id"].as_str().unwrap().to_string();
+    let target = members[1]["item_id"].as_str().unwrap().to_string();
+    let target_library = members[1]["library_id"].as_str().unwrap().to_string();
+
+    server
+        .post(&format!("/api/v1/items/{source}/merge-into/{target}"))
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await
+        .assert_status_ok();
+
+    // One series with both seasons; the repeated episode has both versions.
+    let episodes: Vec<(i64, i64, i64)> = sqlx::query_as(
+        "SELECT s.index_number, e.index_number, COUNT(m.file_id) FROM item s \
+         JOIN item e ON e.parent_id = s.id \
+         JOIN episode_file_map m ON m.episode_item_id = e.id \
+         WHERE s.parent_id = ?1 AND s.library_id = ?2 AND e.library_id = ?2 \
+         GROUP BY e.id ORDER BY 1, 2",
+    )
+    .bind(&target)
+    .bind(&target_library)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(episodes, vec![(1, 1, 1), (1, 2, 2), (1, 3, 1), (2, 1, 1)]);
+    assert!(
+        rustfin_db::repo::items::get_item(&pool, &source)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let (items,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM item")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(items, 1 + 2 + 4);
 
-    // Try to put config without claiming session first — should fail (no token)
     let resp = server
-        .put("/api/v1/setup/config")
-        .json(&json!({
-            "server_name": "Test",
-            "default_ui_locale": "en",
-            "default_region": "US"
-        }))
+        .get("/api/v1/system/duplicates")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    resp.assert_status(axum::http::StatusCode::UNAUTHORIZED);
+    assert!(resp.json::<Value>().as_array().unwrap().is_empty());
 }
 
+// ---------------------------------------------------------------------------
+// Delta sync tests
+// ---------------------------------------------------------------------------
+
 #[tokio::test]
-async fn setup_validation_rejects_weak_password() {
-    let server = test_app_fresh().await;
+async fn sync_delta_returns_only_changes_after_cursor() {
+    let tmp = TempDir::new("rf_sync");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Alien (1979).mkv");
+    tmp.touch("Aliens (1986).mkv");
 
-    // Claim session
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    scanned_library(&pool, "Movies", "movies", &tmp).await;
+
+    let server = test_server_for_pool(pool);
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+
+    // The sync snapshot excludes the current second, so let the scan settle.
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    // Full sync, paged one item at a time.
     let resp = server
-        .post("/api/v1/setup/session/claim")
-        .json(&json!({
-            "client_name": "TestUI",
-            "force": false,
-            "confirm_takeover": false
-        }))
+        .get("/api/v1/sync?since=0&limit=1")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
-    let body: Value = resp.json();
-    let token = body["owner_token"].as_str().unwrap().to_string();
-    let owner_hdr = axum::http::HeaderName::from_static("x-setup-owner-token");
-    let owner_val: axum::http::HeaderValue = token.parse().unwrap();
+    resp.assert_status_ok();
+    let page1: Value = resp.json();
+    assert_eq!(page1["items"].as_array().unwrap().len(), 1);
+    assert_eq!(page1["has_more"], true);
 
-    // Put config first
     let resp = server
-        .put("/api/v1/setup/config")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .json(&json!({
-            "server_name": "Test",
-            "default_ui_locale": "en",
-            "default_region": "US"
-        }))
+        .get("/api/v1/sync")
+        .add_query_param("cursor", page1["cursor"].as_str().unwrap())
+        .add_query_param("limit", 1)
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
+    let page2: Value = resp.json();
+    assert_eq!(page2["items"].as_array().unwrap().len(), 1);
+    assert_eq!(page2["has_more"], false);
+    assert_ne!(page1["items"][0]["id"], page2["items"][0]["id"]);
+    let cursor = page2["cursor"].as_str().unwrap().to_string();
+
+    // Change one item's progress after the sync point.
+    let item_id = page2["items"][0]["id"].as_str().unwrap().to_string();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+    server
+        .post("/api/v1/playback/progress")
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .json(&json!({ "item_id": item_id, "progress_ms": 60000 }))
+        .await
+        .assert_status_ok();
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
 
-    // Try to create admin with short password
     let resp = server
-        .post("/api/v1/setup/admin")
-        .add_header(owner_hdr.clone(), owner_val.clone())
-        .add_header(
-            axum::http::HeaderName::from_static("idempotency-key"),
-            "validate-test-key123"
-                .parse::<axum::http::HeaderValue>()
-                .unwrap(),
-        )
-        .json(&json!({
-            "username": "admin",
-            "password": "short"
-        }))
+        .get("/api/v1/sync")
+        .add_query_param("cursor", &cursor)
+        .add_header(hdr_name.clone(), hdr_val.clone())
+        .await;
+    resp.assert_status_ok();
+    let delta: Value = resp.json();
+    assert!(delta["items"].as_array().unwrap().is_empty());
+    assert!(delta["deleted"].as_array().unwrap().is_empty());
+    let states = delta["play_states"].as_array().unwrap();
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0]["item_id"], item_id.as_str());
+    assert_eq!(states[0]["progress_ms"], 60000);
+
+    // Garbage cursors are rejected.
+    let resp = server
+        .get("/api/v1/sync?cursor=nope")
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status(axum::http::StatusCode::UNPROCESSABLE_ENTITY);
-    let body: Value = resp.json();
-    assert_eq!(body["error"]["code"], "validation_failed");
-    assert!(body["error"]["details"]["fields"]["password"].is_array());
 }
 
 #[tokio::test]
-async fn setup_force_takeover() {
-    let server = test_app_fresh().await;
+async fn sync_excludes_rows_at_the_since_and_snapshot_boundaries() {
+    let tmp = TempDir::new("rf_sync_edge");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Alien (1979).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let set_updated = |ts: i64| {
+        sqlx::query("UPDATE item SET updated_ts = ?")
+            .bind(ts)
+            .execute(&pool)
+    };
 
-    // First claim
-    let resp = server
-        .post("/api/v1/setup/session/claim")
-        .json(&json!({
-            "client_name": "Browser1",
-            "force": false,
-            "confirm_takeover": false
-        }))
-        .await;
-    resp.assert_status_ok();
+    let server = test_server_for_pool(pool.clone());
+    let token = login(&server, "admin", "admin_secure_123").await;
+    let (hdr_name, hdr_val) = auth_hdr(&token);
+    let sync = |query: String| {
+        server
+            .get(&format!("/api/v1/sync?{query}"))
+            .add_header(hdr_name.clone(), hdr_val.clone())
+    };
 
-    // Force takeover
+    // `since` is exclusive: a row stamped exactly at it was already seen.
+    let since = chrono::Utc::now().timestamp() - 100;
+    set_updated(since).await.unwrap();
+    let body: Value = sync(format!("since={since}")).await.json();
+    assert!(body["items"].as_array().unwrap().is_empty(), "{body}");
+    let body: Value = sync(format!("since={}", since - 1)).await.json();
+    assert_eq!(body["items"].as_array().unwrap().len(), 1, "{body}");
+
+    // A finished page ends past the snapshot bound, so a row stamped at the bound
+    // is not sent again.
+    let cursor = body["cursor"].as_str().unwrap().to_string();
+    let bound: i64 = cursor.split(':').next().unwrap().parse::<i64>().unwrap() - 1;
+    set_updated(bound).await.unwrap();
     let resp = server
-        .post("/api/v1/setup/session/claim")
-        .json(&json!({
-            "client_name": "Browser2",
-            "force": true,
-            "confirm_takeover": true
-        }))
+        .get("/api/v1/sync")
+        .add_query_param("cursor", &cursor)
+        .add_header(hdr_name.clone(), hdr_val.clone())
         .await;
     resp.assert_status_ok();
     let body: Value = resp.json();
-    assert_eq!(body["claimed_by"], "Browser2");
+    assert!(body["items"].as_array().unwrap().is_empty(), "{body}");
+}
+
+// ---------------------------------------------------------------------------
+// SSE event scoping tests
+// ---------------------------------------------------------------------------
+
+/// Read from an SSE response until `needle` shows up or `wait` elapses.
+async fn sse_contains(
+    resp: &mut reqwest::Response,
+    needle: &str,
+    wait: std::time::Duration,
+) -> bool {
+    let deadline = tokio::time::Instant::now() + wait;
+    let mut seen = String::new();
+    loop {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        match tokio::time::timeout(remaining, resp.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                seen.push_str(&String::from_utf8_lossy(&chunk));
+                if seen.contains(needle) {
+                    return true;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
+#[tokio::test]
+async fn playback_progress_events_are_scoped_to_the_user() {
+    let tmp = TempDir::new("rf_sse");
+    std::fs::create_dir_all(&tmp).unwrap();
+    tmp.touch("Arrival (2016).mkv");
+
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    rustfin_db::repo::users::create_user(&pool, "viewer", "viewer_secure_123", "user")
+        .await
+        .unwrap();
+    let lib = scanned_library(&pool, "Movies", "movies", &tmp).await;
+    let item_id = rustfin_db::repo::items::get_library_items(&pool, &lib.id)
+        .await
+        .unwrap()[0]
+        .id
+        .clone();
+
+    let tc_config = rustfin_transcoder::TranscoderConfig {
+        transcode_dir: std::env::temp_dir().join(format!("rf_sse_tc_{}", std::process::id())),
+        ..Default::default()
+    };
+    let state = AppState {
+        db: pool,
+        jwt_secret: "test-secret-key".to_string(),
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            tc_config,
+        )),
+        cache_dir: std::env::temp_dir().join(format!("rf_cache_sse_{}", std::process::id())),
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
+    };
+
+    // SSE responses never finish, so serve over a real socket and stream them.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, build_router(state)).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let login = |username: &'static str, password: &'static str| {
+        let client = client.clone();
+        let base = base.clone();
+        async move {
+            let body: Value = client
+                .post(format!("{base}/api/v1/auth/login"))
+                .json(&json!({ "username": username, "password": password }))
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            body["token"].as_str().unwrap().to_string()
+        }
+    };
+    let admin_token = login("admin", "admin_secure_123").await;
+    let viewer_token = login("viewer", "viewer_secure_123").await;
+
+    let mut admin_stream = client
+        .get(format!("{base}/api/v1/events"))
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    let mut viewer_stream = client
+        .get(format!("{base}/api/v1/events"))
+        .bearer_auth(&viewer_token)
+        .send()
+        .await
+        .unwrap();
+    assert!(admin_stream.status().is_success());
+    assert!(viewer_stream.status().is_success());
+
+    let resp = client
+        .post(format!("{base}/api/v1/playback/progress"))
+        .bearer_auth(&admin_token)
+        .json(&json!({ "item_id": item_id, "progress_ms": 42000 }))
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success());
+
+    assert!(
+        sse_contains(
+            &mut admin_stream,
+            "event: playback_progress",
+            std::time::Duration::from_secs(5)
+        )
+        .await,
+        "owner should receive their progress event"
+    );
+    assert!(
+        !sse_contains(
+            &mut viewer_stream,
+            "playback_progress",
+            std::time::Duration::from_millis(750)
+        )
+        .await,
+        "other users must not receive the progress event"
+    );
+}
+
+#[tokio::test]
+async fn reconnecting_with_last_event_id_replays_missed_events() {
+    let pool = test_pool().await;
+    admin_user(&pool).await;
+    let state = test_state_for_pool(pool);
+    let events = state.events.clone();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, build_router(state)).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let body: Value = client
+        .post(format!("{base}/api/v1/auth/login"))
+        .json(&json!({ "username": "admin", "password": "admin_secure_123" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let token = body["token"].as_str().unwrap().to_string();
+    let job_update = |job_id: &str| rustfin_server::state::ServerEvent::JobUpdate {
+        job_id: job_id.to_string(),
+        status: "running".to_string(),
+        progress: 0.5,
+    };
+
+    let mut stream = client
+        .get(format!("{base}/api/v1/events"))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(stream.status().is_success());
+    let seen_id = events.send(job_update("job-seen"));
+    assert!(
+        sse_contains(
+            &mut stream,
+            &format!("id: {seen_id}"),
+            std::time::Duration::from_secs(5)
+        )
+        .await,
+        "live events carry their sequence id"
+    );
+    drop(stream);
+
+    // Emitted while the client is disconnected.
+    let first_missed = events.send(job_update("job-missed-1"));
+    let second_missed = events.send(job_update("job-missed-2"));
+
+    let mut stream = client
+        .get(format!("{base}/api/v1/events"))
+        .bearer_auth(&token)
+        .header("Last-Event-ID", seen_id.to_string())
+        .send()
+        .await
+        .unwrap();
+    assert!(stream.status().is_success());
+    let mut seen = String::new();
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !seen.contains("job-missed-2") {
+        let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+        match tokio::time::timeout(remaining, stream.chunk()).await {
+            Ok(Ok(Some(chunk))) => seen.push_str(&String::from_utf8_lossy(&chunk)),
+            _ => panic!("missed events were not replayed; got {seen:?}"),
+        }
+    }
+    assert!(seen.contains(&format!("id: {first_missed}")));
+    assert!(seen.contains(&format!("id: {second_missed}")));
+    assert!(
+        seen.find("job-missed-1").unwrap() < seen.find("job-missed-2").unwrap(),
+        "replay keeps the original order"
+    );
+    assert!(
+        !seen.contains("job-seen"),
+        "events up to Last-Event-ID are not sent again"
+    );
+
+    // Live events keep flowing after the replay.
+    let live_id = events.send(job_update("job-live"));
+    assert!(
+        sse_contains(
+            &mut stream,
+            &format!("id: {live_id}"),
+            std::time::Duration::from_secs(5)
+        )
+        .await
+    );
+}
+
+// ---------------------------------------------------------------------------
+// TLS bootstrap tests
+// ---------------------------------------------------------------------------
+
+#[tokio::test]
+async fn health_is_reachable_over_https_with_http2() {
+    let fixtures = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let tls = rustfin_server::serve::load_tls_config(
+        &fixtures.join("tls-cert.pem"),
+        &fixtures.join("tls-key.pem"),
+    )
+    .unwrap();
+
+    let pool = test_pool().await;
+    let state = AppState {
+        db: pool,
+        jwt_secret: "test-secret-key".to_string(),
+        transcoder: std::sync::Arc::new(rustfin_transcoder::session::SessionManager::new(
+            rustfin_transcoder::TranscoderConfig::default(),
+        )),
+        cache_dir: std::env::temp_dir().join(format!("rf_cache_tls_{}", std::process::id())),
+        events: EventBus::default(),
+        ready: Readiness::default(),
+        streams: StreamLimiter::default(),
+        subtitles: SubtitleCache::default(),
+        uploads: KeyedLocks::default(),
+        limits: RequestLimits::default(),
+        media_tools: MediaTools::default(),
+        jobs: JobQueue::default(),
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(rustfin_server::serve::serve(
+        listener,
+        build_router(state),
+        Some(tls),
+    ));
+
+    // The fixture certificate is self-signed for localhost.
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+    let resp = client
+        .get(format!("https://localhost:{port}/health"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.version(), reqwest::Version::HTTP_2);
+    let body: Value = resp.json().await.unwrap();
+    assert_eq!(body["status"], "ok");
+
+    // Plain HTTP against the TLS listener must not be served.
+    assert!(
+        reqwest::get(format!("http://localhost:{port}/health"))
+            .await
+            .map(|r| !r.status().is_success())
+            .unwrap_or(true)
+    );
 }
@@ -0,0 +1,379 @@
+//! Extraction of container attachments: fonts for ASS subtitles and cover art.
+
+use std::path::{Path, PathBuf};
+
+use crate::TranscodeError;
+use crate::ffprobe::{AttachmentStream, MediaInfo};
+
+const FONT_EXTENSIONS: [&str; 5] = ["ttf", "otf", "ttc", "woff", "woff2"];
+
+impl AttachmentStream {
+    /// A font libass can load, judged by MIME type or, failing that, extension.
+    pub fn is_font(&self) -> bool {
+        if let Some(mime) = self.mime_type.as_deref() {
+            let mime = mime.to_ascii_lowercase();
+            if mime.starts_with("font/")
+                || mime.starts_with("application/font")
+                || mime.starts_with("application/x-font")
+                || mime == "application/x-truetype-font"
+                || mime == "application/vnd.ms-opentype"
+            {
+                return true;
+            }
+        }
+        self.extension()
+            .is_some_and(|ext| FONT_EXTENSIONS.contains(&ext.as_str()))
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|m| m.to_ascii_lowercase().starts_with("image/"))
+    }
+
+    fn extension(&self) -> Option<String> {
+        let name = self.filename.as_deref()?;
+        Path::new(name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+    }
+
+    /// Name to store the attachment under: its own file name reduced to safe
+    /// characters and prefixed with the stream index, so names never collide or
+    /// leave the directory they are written to.
+    pub fn file_name(&self) -> String {
+        let own = self
+            .filename
+            .as_deref()
+            .and_then(|n| Path::new(n).file_name())
+            .and_then(|n| n.to_str())
+            .map(|n| {
+                n.chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .filter(|n| !n.trim_matches('.').is_empty());
+        match own {
+            Some(name) => format!("{}_{name}", self.index),
+            None => format!("{}.{}", self.index, image_extension(self).unwrap_or("bin")),
+        }
+    }
+}
+
+/// File extension for an image attachment's MIME type.
+fn image_extension(attachment: &AttachmentStream) -> Option<&'static str> {
+    match attachment
+        .mime_type
+        .as_deref()?
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "image/jpeg" | "image/jpg" => Some("jpg"),
+        "image/png" => Some("png"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+/// The cover art in `info`, if any: a Matroska image attachment named `cover*`
+/// first, then any JPEG/PNG/WebP attachment, then an MP4 `attached_pic` stream.
+pub fn cover_art(info: &MediaInfo) -> Option<&AttachmentStream> {
+    let images = || {
+        info.attachments
+            .iter()
+            .filter(|a| a.is_image() && image_extension(a).is_some())
+    };
+    let named_cover = |a: &&AttachmentStream| {
+        a.filename
+            .as_deref()
+            .is_some_and(|n| n.to_ascii_lowercase().starts_with("cover"))
+    };
+    images()
+        .filter(|a| !a.attached_pic)
+        .find(named_cover)
+        .or_else(|| images().find(|a| !a.attached_pic))
+        .or_else(|| images().find(|a| a.attached_pic))
+}
+
+/// File extension the extracted cover art gets.
+pub fn cover_art_extension(cover: &AttachmentStream) -> &'static str {
+    image_extension(cover).unwrap_or("jpg")
+}
+
+/// Run a short ffmpeg job to completion; `what` names it in the error.
+pub(crate) async fn run_ffmpeg(
+    ffmpeg_path: &Path,
+    what: &str,
+    args: Vec<std::ffi::OsString>,
+) -> Result<(), TranscodeError> {
+    let result = tokio::process::Command::new(ffmpeg_path)
+        .args(["-nostdin", "-v", "error", "-y"])
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await;
+    let out = match result {
+        Ok(out) => out,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(TranscodeError::BinaryNotFound(ffmpeg_path.to_path_buf()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !out.status.success() {
+        return Err(TranscodeError::FfmpegFailed(format!(
+            "{what} exited with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// Dump every (non-`attached_pic`) attachment of `input` into `output_dir`,
+/// named by [`AttachmentStream::file_name`]. Returns the written paths.
+///
+/// The files are written to a sibling `.partial` directory which is renamed into
+/// place, so a reader never sees half the fonts.
+pub async fn extract_attachments(
+    ffmpeg_path: &Path,
+    input: &Path,
+    attachments: &[AttachmentStream],
+    output_dir: &Path,
+) -> Result<Vec<PathBuf>, TranscodeError> {
+    let attachments: Vec<&AttachmentStream> =
+        attachments.iter().filter(|a| !a.attached_pic).collect();
+    let partial = output_dir.with_extension("partial");
+    let _ = tokio::fs::remove_dir_all(&partial).await;
+    tokio::fs::create_dir_all(&partial).await?;
+
+    if !attachments.is_empty() {
+        let mut args: Vec<std::ffi::OsString> = Vec::new();
+        for attachment in &attachments {
+            args.push(format!("-dump_attachment:{}", attachment.index).into());
+            args.push(partial.join(attachment.file_name()).into());
+        }
+        // Attachments are dumped while the input is opened; nothing is decoded.
+        args.extend(["-i".into(), input.into()]);
+        args.extend(["-t", "0", "-f", "null", "-"].map(Into::into));
+        if let Err(e) = run_ffmpeg(ffmpeg_path, "attachment extraction", args).await {
+            let _ = tokio::fs::remove_dir_all(&partial).await;
+            return Err(e);
+        }
+    }
+
+    let _ = tokio::fs::remove_dir_all(output_dir).await;
+    tokio::fs::rename(&partial, output_dir).await?;
+    Ok(attachments
+        .iter()
+        .map(|a| output_dir.join(a.file_name()))
+        .filter(|p| p.is_file())
+        .collect())
+}
+
+/// Write `cover` from `input` to `output`, through a temporary file next to it.
+pub async fn extract_cover_art(
+    ffmpeg_path: &Path,
+    input: &Path,
+    cover: &AttachmentStream,
+    output: &Path,
+) -> Result<(), TranscodeError> {
+    let partial = output.with_extension(format!("partial.{}", cover_art_extension(cover)));
+    let args: Vec<std::ffi::OsString> = if cover.attached_pic {
+        vec![
+            "-i".into(),
+            input.into(),
+            "-map".into(),
+            format!("0:{}", cover.index).into(),
+            "-c".into(),
+            "copy".into(),
+            "-frames:v".into(),
+            "1".into(),
+            "-f".into(),
+            "image2".into(),
+            partial.clone().into(),
+        ]
+    } else {
+        vec![
+            format!("-dump_attachment:{}", cover.index).into(),
+            partial.clone().into(),
+            "-i".into(),
+            input.into(),
+            "-t".into(),
+            "0".into(),
+            "-f".into(),
+            "null".into(),
+            "-".into(),
+        ]
+    };
+    if let Err(e) = run_ffmpeg(ffmpeg_path, "attachment extraction", args).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&partial, output).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attachment(index: u32, filename: Option<&str>, mime: Option<&str>) -> AttachmentStream {
+        AttachmentStream {
+            index,
+            filename: filename.map(Into::into),
+            mime_type: mime.map(Into::into),
+            attached_pic: false,
+        }
+    }
+
+    fn media(attachments: Vec<AttachmentStream>) -> MediaInfo {
+        MediaInfo {
+            container: "matroska,webm".into(),
+            duration_secs: 1.0,
+            bitrate_kbps: None,
+            video: None,
+            audio: vec![],
+            subtitles: vec![],
+            attachments,
+        }
+    }
+
+    #[test]
+    fn fonts_are_recognised_by_mime_or_extension() {
+        assert!(attachment(1, None, Some("application/x-truetype-font")).is_font());
+        assert!(attachment(1, None, Some("font/otf")).is_font());
+        assert!(attachment(1, Some("Arial.TTF"), Some("application/octet-stream")).is_font());
+        assert!(!attachment(1, Some("cover.jpg"), Some("image/jpeg")).is_font());
+        assert!(!attachment(1, None, None).is_font());
+    }
+
+    #[test]
+    fn file_names_stay_inside_the_output_dir() {
+        assert_eq!(
+            attachment(3, Some("Noto Sans.ttf"), None).file_name(),
+            "3_Noto_Sans.ttf"
+        );
+        assert_eq!(
+            attachment(4, Some("../../etc/passwd"), None).file_name(),
+            "4_passwd"
+        );
+        assert_eq!(attachment(5, Some(".."), None).file_name(), "5.bin");
+        assert_eq!(attachment(6, None, Some("image/png")).file_name(), "6.png");
+    }
+
+    #[test]
+    fn cover_art_prefers_named_cover_then_any_image_then_attached_pic() {
+        let pic = AttachmentStream {
+            attached_pic: true,
+            ..attachment(9, None, Some("image/jpeg"))
+        };
+        let info = media(vec![
+            attachment(1, Some("font.ttf"), Some("font/ttf")),
+            pic.clone(),
+            attachment(2, Some("small_cover.png"), Some("image/png")),
+            attachment(3, Some("cover.jpg"), Some("image/jpeg")),
+        ]);
+        assert_eq!(cover_art(&info).unwrap().index, 3);
+
+        let info = media(vec![
+            pic.clone(),
+            attachment(2, Some("small_cover.png"), Some("image/png")),
+        ]);
+        assert_eq!(cover_art(&info).unwrap().index, 2);
+
+        let info = media(vec![pic]);
+        let cover = cover_art(&info).unwrap();
+        assert!(cover.attached_pic);
+        assert_eq!(cover_art_extension(cover), "jpg");
+
+        assert!(cover_art(&media(vec![attachment(1, None, Some("font/ttf"))])).is_none());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs ffmpeg on PATH"]
+    async fn attached_font_and_cover_are_extracted_with_ffmpeg() {
+        let ffmpeg = PathBuf::from("ffmpeg");
+        let dir = std::env::temp_dir().join(format!("rf_attach_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let font = dir.join("Test Font.ttf");
+        std::fs::write(&font, b"\x00\x01\x00\x00fake font tables").unwrap();
+        let cover = dir.join("cover.png");
+        let status = std::process::Command::new(&ffmpeg)
+            .args([
+                "-v",
+                "error",
+                "-y",
+                "-f",
+                "lavfi",
+                "-i",
+                "color=c=red:s=8x8",
+                "-frames:v",
+                "1",
+            ])
+            .arg(&cover)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let mkv = dir.join("movie.mkv");
+        let status = std::process::Command::new(&ffmpeg)
+            .args([
+                "-v",
+                "error",
+                "-y",
+                "-f",
+                "lavfi",
+                "-i",
+                "color=c=black:s=64x64:d=1",
+            ])
+            .arg("-attach")
+            .arg(&font)
+            .arg("-attach")
+            .arg(&cover)
+            .args([
+                "-metadata:s:t:0",
+                "mimetype=application/x-truetype-font",
+                "-metadata:s:t:1",
+                "mimetype=image/png",
+            ])
+            .arg(&mkv)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let info = media(vec![
+            attachment(
+                1,
+                Some("Test Font.ttf"),
+                Some("application/x-truetype-font"),
+            ),
+            attachment(2, Some("cover.png"), Some("image/png")),
+        ]);
+        let out = dir.join("attachments");
+        let written = extract_attachments(&ffmpeg, &mkv, &info.attachments, &out)
+            .await
+            .unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(
+            std::fs::read(out.join("1_Test_Font.ttf")).unwrap(),
+            std::fs::read(&font).unwrap()
+        );
+
+        let cover_out = dir.join("poster.png");
+        extract_cover_art(&ffmpeg, &mkv, cover_art(&info).unwrap(), &cover_out)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read(&cover_out).unwrap(),
+            std::fs::read(&cover).unwrap()
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
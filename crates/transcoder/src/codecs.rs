@@ -0,0 +1,287 @@
+//! RFC 6381 codec strings for the `CODECS` attribute of HLS master playlists.
+//!
+//! Browsers playing HLS through Media Source Extensions create their source
+//! buffers from `CODECS` before the first segment arrives; a missing or wrong
+//! string makes playback fail without an error. Copied streams are described
+//! from their probed profile and level. Re-encoded video is described as what
+//! the encoders produce by default: H.264 High, HEVC Main or AV1 Main, at the
+//! lowest level that fits the output resolution and frame rate.
+
+use crate::ffprobe::VideoStream;
+
+/// Frame rate assumed when the probe has none.
+const DEFAULT_FRAMERATE: f64 = 30.0;
+
+/// Video codec families a session can output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFamily {
+    H264,
+    Hevc,
+    Av1,
+}
+
+impl VideoFamily {
+    /// Family of an ffprobe codec name or an ffmpeg encoder name
+    /// (`h264`, `libx264`, `hevc_nvenc`, `libsvtav1`, ...).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "h264" || name.starts_with("h264_") || name == "libx264" {
+            Some(Self::H264)
+        } else if ["hevc", "h265", "libx265"].contains(&name.as_str()) || name.starts_with("hevc_")
+        {
+            Some(Self::Hevc)
+        } else if name.contains("av1") || name == "librav1e" {
+            Some(Self::Av1)
+        } else {
+            None
+        }
+    }
+}
+
+/// Codec string of the video a session outputs.
+///
+/// `source` is the probed stream; `output` is `None` when it is copied, else the
+/// family it is encoded to and the frame size after scaling.
+pub fn video_codec_string(
+    source: &VideoStream,
+    output: Option<(VideoFamily, u32, u32)>,
+) -> Option<String> {
+    let framerate = source
+        .framerate
+        .filter(|f| f.is_finite() && *f > 0.0)
+        .unwrap_or(DEFAULT_FRAMERATE);
+    match output {
+        Some((family, width, height)) => {
+            Some(encoded_codec_string(family, width, height, framerate))
+        }
+        None => copied_codec_string(source, framerate),
+    }
+}
+
+fn encoded_codec_string(family: VideoFamily, width: u32, height: u32, framerate: f64) -> String {
+    match family {
+        VideoFamily::H264 => format!("avc1.6400{:02x}", h264_level(width, height, framerate)),
+        VideoFamily::Hevc => format!("hvc1.1.6.L{}.B0", hevc_level(width, height, framerate)),
+        VideoFamily::Av1 => format!("av01.0.{:02}M.08", av1_level(width, height, framerate)),
+    }
+}
+
+fn copied_codec_string(source: &VideoStream, framerate: f64) -> Option<String> {
+    let family = VideoFamily::from_name(&source.codec)?;
+    let profile = source.profile.as_deref().unwrap_or("").to_ascii_lowercase();
+    let level = source.level.filter(|l| *l > 0);
+    let ten_bit = source.pix_fmt.as_deref().is_some_and(|f| f.contains("10"));
+    Some(match family {
+        VideoFamily::H264 => {
+            let (profile_idc, constraints) = match profile.as_str() {
+                "constrained baseline" => (0x42, 0xe0),
+                "baseline" => (0x42, 0x00),
+                "main" => (0x4d, 0x00),
+                "high 10" => (0x6e, 0x00),
+                "high 4:2:2" => (0x7a, 0x00),
+                "high 4:4:4 predictive" => (0xf4, 0x00),
+                _ => (0x64, 0x00),
+            };
+            let level =
+                level.unwrap_or_else(|| h264_level(source.width, source.height, framerate).into());
+            format!("avc1.{profile_idc:02x}{constraints:02x}{level:02x}")
+        }
+        VideoFamily::Hevc => {
+            let level =
+                level.unwrap_or_else(|| hevc_level(source.width, source.height, framerate).into());
+            if profile == "main 10" || ten_bit {
+                format!("hvc1.2.4.L{level}.B0")
+            } else {
+                format!("hvc1.1.6.L{level}.B0")
+            }
+        }
+        VideoFamily::Av1 => {
+            let level =
+                level.unwrap_or_else(|| av1_level(source.width, source.height, framerate).into());
+            let depth = if ten_bit { 10 } else { 8 };
+            format!("av01.0.{level:02}M.{depth:02}")
+        }
+    })
+}
+
+/// Lowest level in `table`, as `(level, max frame size, max samples per second)`,
+/// that fits the frame size and rate; the highest level if none does.
+fn level_for(table: &[(u8, u64, u64)], frame_size: u64, rate: u64) -> u8 {
+    table
+        .iter()
+        .find(|(_, max_size, max_rate)| frame_size <= *max_size && rate <= *max_rate)
+        .or(table.last())
+        .map(|(level, _, _)| *level)
+        .unwrap_or_default()
+}
+
+/// `level_idc` (level × 10), from macroblocks per frame and per second (H.264 table A-1).
+fn h264_level(width: u32, height: u32, framerate: f64) -> u8 {
+    const LEVELS: &[(u8, u64, u64)] = &[
+        (30, 1_620, 40_500),
+        (31, 3_600, 108_000),
+        (32, 5_120, 216_000),
+        (40, 8_192, 245_760),
+        (42, 8_704, 522_240),
+        (50, 22_080, 589_824),
+        (51, 36_864, 983_040),
+        (52, 36_864, 2_073_600),
+        (60, 139_264, 4_177_920),
+        (61, 139_264, 8_355_840),
+        (62, 139_264, 16_711_680),
+    ];
+    let macroblocks = u64::from(width.div_ceil(16)) * u64::from(height.div_ceil(16));
+    level_for(LEVELS, macroblocks, (macroblocks as f64 * framerate) as u64)
+}
+
+/// `general_level_idc` (level × 30), from luma samples (HEVC table A.8).
+fn hevc_level(width: u32, height: u32, framerate: f64) -> u8 {
+    const LEVELS: &[(u8, u64, u64)] = &[
+        (30, 36_864, 552_960),
+        (60, 122_880, 3_686_400),
+        (63, 245_760, 7_372_800),
+        (90, 552_960, 16_588_800),
+        (93, 983_040, 33_177_600),
+        (120, 2_228_224, 66_846_720),
+        (123, 2_228_224, 133_693_440),
+        (150, 8_912_896, 267_386_880),
+        (153, 8_912_896, 534_773_760),
+        (156, 8_912_896, 1_069_547_520),
+        (180, 35_651_584, 1_069_547_520),
+        (183, 35_651_584, 2_139_095_040),
+        (186, 35_651_584, 4_278_190_080),
+    ];
+    let samples = u64::from(width) * u64::from(height);
+    level_for(LEVELS, samples, (samples as f64 * framerate) as u64)
+}
+
+/// `seq_level_idx`, from luma samples (AV1 annex A.3).
+fn av1_level(width: u32, height: u32, framerate: f64) -> u8 {
+    const LEVELS: &[(u8, u64, u64)] = &[
+        (0, 147_456, 4_423_680),
+        (1, 278_784, 8_363_520),
+        (4, 665_856, 19_975_680),
+        (5, 1_065_024, 31_950_720),
+        (8, 2_359_296, 70_778_880),
+        (9, 2_359_296, 141_557_760),
+        (12, 8_912_896, 267_386_880),
+        (13, 8_912_896, 534_773_760),
+        (14, 8_912_896, 1_069_547_520),
+        (16, 35_651_584, 1_069_547_520),
+        (17, 35_651_584, 2_139_095_040),
+        (18, 35_651_584, 4_278_190_080),
+    ];
+    let samples = u64::from(width) * u64::from(height);
+    level_for(LEVELS, samples, (samples as f64 * framerate) as u64)
+}
+
+/// Codec string of an audio rendition: AAC-LC when re-encoded, else the source
+/// codec's. `None` for codecs without a registered string.
+pub fn audio_codec_string(source_codec: &str, copy: bool) -> Option<&'static str> {
+    if !copy {
+        return Some("mp4a.40.2");
+    }
+    Some(match source_codec.to_ascii_lowercase().as_str() {
+        "aac" => "mp4a.40.2",
+        "mp3" => "mp4a.40.34",
+        "ac3" => "ac-3",
+        "eac3" => "ec-3",
+        "opus" => "Opus",
+        "flac" => "fLaC",
+        "alac" => "alac",
+        _ => return None,
+    })
+}
+
+/// `width`×`height` scaled down into the bounding box, keeping the aspect ratio
+/// and even dimensions, the way the session's scale filter does.
+pub fn scaled_resolution(
+    width: u32,
+    height: u32,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+) -> (u32, u32) {
+    let (w, h) = (f64::from(width), f64::from(height));
+    let factor = [
+        max_width.map(|m| f64::from(m) / w),
+        max_height.map(|m| f64::from(m) / h),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(1.0_f64, f64::min);
+    if factor >= 1.0 || width == 0 || height == 0 {
+        return (width, height);
+    }
+    let even = |v: f64| ((v as u32) / 2 * 2).max(2);
+    (even(w * factor), even(h * factor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(codec: &str, width: u32, height: u32) -> VideoStream {
+        VideoStream {
+            index: 0,
+            codec: codec.into(),
+            width,
+            height,
+            bitrate_kbps: None,
+            framerate: Some(23.976),
+            profile: None,
+            level: None,
+            pix_fmt: None,
+            hdr_format: None,
+            dolby_vision_profile: None,
+        }
+    }
+
+    #[test]
+    fn encoded_video_strings_follow_family_and_resolution() {
+        let source = video("mpeg2video", 1920, 1080);
+        let encode = |encoder: &str, width, height| {
+            let family = VideoFamily::from_name(encoder).unwrap();
+            video_codec_string(&source, Some((family, width, height))).unwrap()
+        };
+        assert_eq!(encode("libx264", 1920, 1080), "avc1.640028");
+        assert_eq!(encode("h264_nvenc", 1280, 720), "avc1.64001f");
+        assert_eq!(encode("libx264", 3840, 2160), "avc1.640033");
+        assert_eq!(encode("libx265", 1920, 1080), "hvc1.1.6.L120.B0");
+        assert_eq!(encode("hevc_vaapi", 3840, 2160), "hvc1.1.6.L150.B0");
+        assert_eq!(encode("libsvtav1", 1920, 1080), "av01.0.08M.08");
+        assert_eq!(encode("av1_nvenc", 3840, 2160), "av01.0.12M.08");
+        assert_eq!(audio_codec_string("dts", false), Some("mp4a.40.2"));
+    }
+
+    #[test]
+    fn copied_streams_use_probed_profile_and_level() {
+        let mut h264 = video("h264", 1920, 1080);
+        h264.profile = Some("Main".into());
+        h264.level = Some(41);
+        assert_eq!(video_codec_string(&h264, None).unwrap(), "avc1.4d0029");
+
+        let mut hevc = video("hevc", 3840, 2160);
+        hevc.profile = Some("Main 10".into());
+        hevc.level = Some(153);
+        assert_eq!(video_codec_string(&hevc, None).unwrap(), "hvc1.2.4.L153.B0");
+
+        let mut av1 = video("av1", 1920, 1080);
+        av1.pix_fmt = Some("yuv420p10le".into());
+        assert_eq!(video_codec_string(&av1, None).unwrap(), "av01.0.08M.10");
+
+        assert_eq!(video_codec_string(&video("vc1", 1920, 1080), None), None);
+        assert_eq!(audio_codec_string("aac", true), Some("mp4a.40.2"));
+        assert_eq!(audio_codec_string("eac3", true), Some("ec-3"));
+        assert_eq!(audio_codec_string("dts", true), None);
+    }
+
+    #[test]
+    fn scaling_keeps_aspect_and_even_sizes() {
+        assert_eq!(scaled_resolution(1920, 1080, Some(1280), None), (1280, 720));
+        assert_eq!(
+            scaled_resolution(1920, 800, Some(1280), Some(720)),
+            (1280, 532)
+        );
+        assert_eq!(scaled_resolution(1280, 720, Some(1920), None), (1280, 720));
+    }
+}
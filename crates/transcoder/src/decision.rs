@@ -2,9 +2,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::ffprobe::MediaInfo;
 
-/// What a client can handle.
+/// What a client device can play. Omitted fields fall back to a broadly capable client.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ClientCaps {
+#[serde(default)]
+pub struct DeviceProfile {
     pub containers: Vec<String>,
     pub video_codecs: Vec<String>,
     pub audio_codecs: Vec<String>,
@@ -13,7 +14,7 @@ pub struct ClientCaps {
     pub max_height: Option<u32>,
 }
 
-impl Default for ClientCaps {
+impl Default for DeviceProfile {
     fn default() -> Self {
         Self {
             containers: vec!["mp4".into(), "matroska".into(), "webm".into(), "mov".into()],
@@ -33,7 +34,54 @@ impl Default for ClientCaps {
     }
 }
 
+impl DeviceProfile {
+    /// What the server's own HLS output (MPEG-TS segments) can carry without re-encoding.
+    pub fn hls() -> Self {
+        Self {
+            containers: vec!["mpegts".into()],
+            video_codecs: vec!["h264".into()],
+            audio_codecs: vec!["aac".into(), "mp3".into()],
+            ..Self::default()
+        }
+    }
+
+    /// What any current browser's `<video>` element plays from a byte-range URL:
+    /// progressive MP4 with H.264 video and AAC or MP3 audio.
+    pub fn browser() -> Self {
+        Self {
+            containers: vec!["mp4".into()],
+            video_codecs: vec!["h264".into()],
+            audio_codecs: vec!["aac".into(), "mp3".into()],
+            ..Self::default()
+        }
+    }
+
+    /// What this device can take through `output`: codecs both accept, `output`'s
+    /// containers, and the tighter of each limit.
+    pub fn through(&self, output: &DeviceProfile) -> DeviceProfile {
+        let both = |ours: &[String], theirs: &[String]| -> Vec<String> {
+            ours.iter()
+                .filter(|c| theirs.iter().any(|t| t.eq_ignore_ascii_case(c)))
+                .cloned()
+                .collect()
+        };
+        let tighter = |a: Option<u32>, b: Option<u32>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        DeviceProfile {
+            containers: output.containers.clone(),
+            video_codecs: both(&self.video_codecs, &output.video_codecs),
+            audio_codecs: both(&self.audio_codecs, &output.audio_codecs),
+            max_bitrate_kbps: tighter(self.max_bitrate_kbps, output.max_bitrate_kbps),
+            max_width: tighter(self.max_width, output.max_width),
+            max_height: tighter(self.max_height, output.max_height),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayMethod {
     DirectPlay,
     Remux,
@@ -41,6 +89,7 @@ pub enum PlayMethod {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
 pub enum TranscodeReason {
     ContainerNotSupported,
     VideoCodecNotSupported,
@@ -49,16 +98,136 @@ pub enum TranscodeReason {
     VideoResolutionTooHigh,
 }
 
+/// A codec or container the client's profile can't take, ruling out direct play.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedReason {
+    VideoCodecUnsupported,
+    AudioCodecUnsupported,
+    ContainerUnsupported,
+}
+
+/// What a client that can't direct play a file should do instead.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DirectPlayUnsupported {
+    /// Always `transcode`: start an HLS session, which re-encodes only what it must.
+    pub playback_method: PlayMethod,
+    pub reason: UnsupportedReason,
+}
+
+/// How a source is best delivered to a client that sent no profile of its own.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    /// Serve the file itself over byte ranges (`/stream/file`).
+    File,
+    /// Start an HLS session; the file needs at least a remux.
+    Hls,
+}
+
+/// Whether the probed file can go straight to a browser, skipping HLS entirely.
+pub fn recommend_delivery(media: &MediaInfo) -> Delivery {
+    if decide(media, &DeviceProfile::browser()).method == PlayMethod::DirectPlay {
+        Delivery::File
+    } else {
+        Delivery::Hls
+    }
+}
+
+/// Whether a client can take a single source track as-is.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TrackCompat {
+    pub index: u32,
+    pub codec: String,
+    pub compatible: bool,
+}
+
+/// How one stream type is carried into the output.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamAction {
+    Copy,
+    #[default]
+    Transcode,
+}
+
+/// Per-stream ffmpeg plan for a transcode session. The default re-encodes both.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TranscodePlan {
+    pub video: StreamAction,
+    pub audio: StreamAction,
+    /// Cap for re-encoded video.
+    pub video_bitrate_kbps: Option<u32>,
+    /// Bounding box re-encoded video is scaled down into.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayDecision {
     pub method: PlayMethod,
     pub reasons: Vec<TranscodeReason>,
     pub transcode_video: bool,
     pub transcode_audio: bool,
+    pub video_track: Option<TrackCompat>,
+    /// Every audio track; only the first one is played.
+    pub audio_tracks: Vec<TrackCompat>,
+    /// Limits re-encoded video must respect, taken from the profile.
+    pub target_bitrate_kbps: Option<u32>,
+    pub target_max_width: Option<u32>,
+    pub target_max_height: Option<u32>,
+    /// Video is re-encoded from an HDR source, so it needs tone-mapping to SDR.
+    pub tone_map: bool,
 }
 
-/// Decide how to play a media file given client capabilities.
-pub fn decide(media: &MediaInfo, caps: &ClientCaps) -> PlayDecision {
+impl PlayDecision {
+    /// Why the client can't take the file as-is, naming the video codec first, then
+    /// the audio codec, then the container. `None` when direct play works, or when
+    /// only a bitrate or resolution limit is exceeded.
+    pub fn direct_play_unsupported(&self) -> Option<DirectPlayUnsupported> {
+        let reason = [
+            (
+                TranscodeReason::VideoCodecNotSupported,
+                UnsupportedReason::VideoCodecUnsupported,
+            ),
+            (
+                TranscodeReason::AudioCodecNotSupported,
+                UnsupportedReason::AudioCodecUnsupported,
+            ),
+            (
+                TranscodeReason::ContainerNotSupported,
+                UnsupportedReason::ContainerUnsupported,
+            ),
+        ]
+        .into_iter()
+        .find(|(found, _)| self.reasons.contains(found))
+        .map(|(_, reason)| reason)?;
+        Some(DirectPlayUnsupported {
+            playback_method: PlayMethod::Transcode,
+            reason,
+        })
+    }
+
+    /// Copy video the client accepts, so e.g. H.264 with DTS audio only has its
+    /// audio converted. Once video is re-encoded anyway, audio is too.
+    pub fn plan(&self) -> TranscodePlan {
+        let (video, audio) = match (self.transcode_video, self.transcode_audio) {
+            (true, _) => (StreamAction::Transcode, StreamAction::Transcode),
+            (false, true) => (StreamAction::Copy, StreamAction::Transcode),
+            (false, false) => (StreamAction::Copy, StreamAction::Copy),
+        };
+        TranscodePlan {
+            video,
+            audio,
+            video_bitrate_kbps: self.target_bitrate_kbps,
+            max_width: self.target_max_width,
+            max_height: self.target_max_height,
+        }
+    }
+}
+
+/// Decide how to play a media file on a device.
+pub fn decide(media: &MediaInfo, caps: &DeviceProfile) -> PlayDecision {
     let mut reasons = Vec::new();
     let mut transcode_video = false;
     let mut transcode_audio = false;
@@ -71,11 +240,16 @@ pub fn decide(media: &MediaInfo, caps: &ClientCaps) -> PlayDecision {
     }
 
     // Check video
-    if let Some(ref v) = media.video {
-        let codec_ok = caps
+    let video_track = media.video.as_ref().map(|v| TrackCompat {
+        index: v.index,
+        codec: v.codec.clone(),
+        compatible: caps
             .video_codecs
             .iter()
-            .any(|c| c.eq_ignore_ascii_case(&v.codec));
+            .any(|c| c.eq_ignore_ascii_case(&v.codec)),
+    });
+    if let Some(ref v) = media.video {
+        let codec_ok = video_track.as_ref().is_some_and(|t| t.compatible);
         if !codec_ok {
             reasons.push(TranscodeReason::VideoCodecNotSupported);
             transcode_video = true;
@@ -105,15 +279,21 @@ pub fn decide(media: &MediaInfo, caps: &ClientCaps) -> PlayDecision {
     }
 
     // Check audio
-    if let Some(a) = media.audio.first() {
-        let codec_ok = caps
-            .audio_codecs
-            .iter()
-            .any(|c| c.eq_ignore_ascii_case(&a.codec));
-        if !codec_ok {
-            reasons.push(TranscodeReason::AudioCodecNotSupported);
-            transcode_audio = true;
-        }
+    let audio_tracks: Vec<TrackCompat> = media
+        .audio
+        .iter()
+        .map(|a| TrackCompat {
+            index: a.index,
+            codec: a.codec.clone(),
+            compatible: caps
+                .audio_codecs
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(&a.codec)),
+        })
+        .collect();
+    if audio_tracks.first().is_some_and(|a| !a.compatible) {
+        reasons.push(TranscodeReason::AudioCodecNotSupported);
+        transcode_audio = true;
     }
 
     let method = if reasons.is_empty() {
@@ -130,6 +310,12 @@ pub fn decide(media: &MediaInfo, caps: &ClientCaps) -> PlayDecision {
         reasons,
         transcode_video,
         transcode_audio,
+        video_track,
+        audio_tracks,
+        target_bitrate_kbps: caps.max_bitrate_kbps,
+        target_max_width: caps.max_width,
+        target_max_height: caps.max_height,
+        tone_map: transcode_video && media.video.as_ref().is_some_and(|v| v.is_hdr()),
     }
 }
 
@@ -150,6 +336,11 @@ mod tests {
                 height: 1080,
                 bitrate_kbps: Some(4000),
                 framerate: Some(23.976),
+                profile: None,
+                level: None,
+                pix_fmt: None,
+                hdr_format: None,
+                dolby_vision_profile: None,
             }),
             audio: vec![AudioStream {
                 index: 1,
@@ -160,13 +351,14 @@ mod tests {
                 is_default: true,
             }],
             subtitles: vec![],
+            attachments: vec![],
         }
     }
 
     #[test]
     fn direct_play_when_all_compatible() {
         let media = test_media();
-        let caps = ClientCaps::default();
+        let caps = DeviceProfile::default();
         let d = decide(&media, &caps);
         assert_eq!(d.method, PlayMethod::DirectPlay);
         assert!(d.reasons.is_empty());
@@ -176,7 +368,7 @@ mod tests {
     fn transcode_when_codec_unsupported() {
         let mut media = test_media();
         media.video.as_mut().unwrap().codec = "mpeg2video".into();
-        let caps = ClientCaps::default();
+        let caps = DeviceProfile::default();
         let d = decide(&media, &caps);
         assert_eq!(d.method, PlayMethod::Transcode);
         assert!(d.transcode_video);
@@ -187,18 +379,233 @@ mod tests {
     fn remux_when_only_container_mismatch() {
         let mut media = test_media();
         media.container = "avi".into();
-        let caps = ClientCaps::default();
+        let caps = DeviceProfile::default();
         let d = decide(&media, &caps);
         assert_eq!(d.method, PlayMethod::Remux);
         assert!(d.reasons.contains(&TranscodeReason::ContainerNotSupported));
     }
 
+    fn unsupported_reason(media: &MediaInfo) -> Option<UnsupportedReason> {
+        let unsupported = decide(media, &DeviceProfile::default()).direct_play_unsupported()?;
+        assert_eq!(unsupported.playback_method, PlayMethod::Transcode);
+        Some(unsupported.reason)
+    }
+
+    #[test]
+    fn each_unsupported_dimension_recommends_a_transcode() {
+        assert_eq!(unsupported_reason(&test_media()), None);
+
+        let mut media = test_media();
+        media.video.as_mut().unwrap().codec = "mpeg2video".into();
+        assert_eq!(
+            unsupported_reason(&media),
+            Some(UnsupportedReason::VideoCodecUnsupported)
+        );
+
+        let mut media = test_media();
+        media.audio[0].codec = "dts".into();
+        assert_eq!(
+            unsupported_reason(&media),
+            Some(UnsupportedReason::AudioCodecUnsupported)
+        );
+
+        let mut media = test_media();
+        media.container = "avi".into();
+        assert_eq!(
+            unsupported_reason(&media),
+            Some(UnsupportedReason::ContainerUnsupported)
+        );
+
+        // The video codec is named first when several things are unsupported.
+        media.video.as_mut().unwrap().codec = "vc1".into();
+        media.audio[0].codec = "dts".into();
+        assert_eq!(
+            unsupported_reason(&media),
+            Some(UnsupportedReason::VideoCodecUnsupported)
+        );
+    }
+
+    #[test]
+    fn exceeded_limits_are_not_reported_as_unsupported() {
+        let caps = DeviceProfile {
+            max_bitrate_kbps: Some(1000),
+            ..DeviceProfile::default()
+        };
+        let d = decide(&test_media(), &caps);
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert_eq!(d.direct_play_unsupported(), None);
+    }
+
+    #[test]
+    fn incompatible_audio_only_transcodes_audio() {
+        let mut media = test_media();
+        media.audio[0].codec = "dts".into();
+        let d = decide(&media, &DeviceProfile::hls());
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert!(d.video_track.as_ref().unwrap().compatible);
+        assert!(!d.audio_tracks[0].compatible);
+        assert_eq!(
+            d.plan(),
+            TranscodePlan {
+                video: StreamAction::Copy,
+                audio: StreamAction::Transcode,
+                ..TranscodePlan::default()
+            }
+        );
+    }
+
+    #[test]
+    fn incompatible_video_is_a_full_transcode() {
+        let mut media = test_media();
+        media.video.as_mut().unwrap().codec = "hevc".into();
+        let caps = DeviceProfile {
+            video_codecs: vec!["h264".into()],
+            ..DeviceProfile::default()
+        };
+        let d = decide(&media, &caps);
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert!(!d.video_track.as_ref().unwrap().compatible);
+        assert!(d.audio_tracks[0].compatible);
+        assert_eq!(d.plan(), TranscodePlan::default());
+        assert_eq!(d.plan().audio, StreamAction::Transcode);
+    }
+
+    #[test]
+    fn compatible_tracks_in_foreign_container_are_remuxed() {
+        let d = decide(&test_media(), &DeviceProfile::hls());
+        assert_eq!(d.method, PlayMethod::Remux);
+        assert_eq!(
+            d.plan(),
+            TranscodePlan {
+                video: StreamAction::Copy,
+                audio: StreamAction::Copy,
+                ..TranscodePlan::default()
+            }
+        );
+    }
+
+    fn tv() -> DeviceProfile {
+        DeviceProfile {
+            containers: vec!["matroska".into(), "mp4".into(), "mpegts".into()],
+            video_codecs: vec!["h264".into(), "hevc".into()],
+            audio_codecs: vec!["aac".into(), "ac3".into(), "eac3".into(), "dts".into()],
+            max_width: Some(3840),
+            max_height: Some(2160),
+            ..DeviceProfile::default()
+        }
+    }
+
+    fn uhd_hevc_media() -> MediaInfo {
+        let mut media = test_media();
+        let video = media.video.as_mut().unwrap();
+        video.codec = "hevc".into();
+        video.width = 3840;
+        video.height = 2160;
+        media.audio[0].codec = "eac3".into();
+        media
+    }
+
+    #[test]
+    fn browser_remuxes_h264_aac_matroska() {
+        let d = decide(&test_media(), &DeviceProfile::browser());
+        assert_eq!(d.method, PlayMethod::Remux);
+        assert_eq!(d.reasons, vec![TranscodeReason::ContainerNotSupported]);
+    }
+
+    #[test]
+    fn browser_transcodes_hevc_eac3() {
+        let d = decide(&uhd_hevc_media(), &DeviceProfile::browser());
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert!(d.reasons.contains(&TranscodeReason::VideoCodecNotSupported));
+        assert!(d.reasons.contains(&TranscodeReason::AudioCodecNotSupported));
+        assert_eq!(d.plan().video, StreamAction::Transcode);
+    }
+
+    #[test]
+    fn capable_tv_direct_plays_hevc_eac3() {
+        let d = decide(&uhd_hevc_media(), &tv());
+        assert_eq!(d.method, PlayMethod::DirectPlay);
+        assert!(d.reasons.is_empty());
+    }
+
+    #[test]
+    fn tv_through_hls_output_keeps_only_shared_codecs() {
+        let profile = tv().through(&DeviceProfile::hls());
+        assert_eq!(profile.containers, vec!["mpegts".to_string()]);
+        assert_eq!(profile.video_codecs, vec!["h264".to_string()]);
+        assert_eq!(profile.audio_codecs, vec!["aac".to_string()]);
+        assert_eq!(profile.max_width, Some(3840));
+
+        let d = decide(&uhd_hevc_media(), &profile);
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert_eq!(d.plan().video, StreamAction::Transcode);
+    }
+
+    #[test]
+    fn bitrate_limited_profile_targets_its_cap() {
+        let profile = DeviceProfile {
+            max_bitrate_kbps: Some(3000),
+            max_width: Some(1280),
+            max_height: Some(720),
+            ..DeviceProfile::browser()
+        };
+        let d = decide(&test_media(), &profile);
+        assert_eq!(d.method, PlayMethod::Transcode);
+        assert!(d.reasons.contains(&TranscodeReason::VideoBitrateTooHigh));
+        let plan = d.plan();
+        assert_eq!(plan.video, StreamAction::Transcode);
+        assert_eq!(plan.video_bitrate_kbps, Some(3000));
+        assert_eq!((plan.max_width, plan.max_height), (Some(1280), Some(720)));
+    }
+
+    #[test]
+    fn partial_profile_json_uses_defaults() {
+        let profile: DeviceProfile =
+            serde_json::from_str(r#"{ "video_codecs": ["h264"], "max_bitrate_kbps": 8000 }"#)
+                .unwrap();
+        assert_eq!(profile.video_codecs, vec!["h264".to_string()]);
+        assert_eq!(profile.max_bitrate_kbps, Some(8000));
+        assert_eq!(profile.audio_codecs, DeviceProfile::default().audio_codecs);
+    }
+
+    #[test]
+    fn mp4_h264_aac_is_delivered_as_a_file() {
+        let mut media = test_media();
+        media.container = "mov,mp4,m4a,3gp,3g2,mj2".into();
+        assert_eq!(recommend_delivery(&media), Delivery::File);
+
+        media.audio[0].codec = "mp3".into();
+        assert_eq!(recommend_delivery(&media), Delivery::File);
+    }
+
+    #[test]
+    fn matroska_needs_hls_for_browsers() {
+        // Same tracks as an MP4 that plays as-is; only the container is foreign.
+        assert_eq!(recommend_delivery(&test_media()), Delivery::Hls);
+        assert_eq!(
+            decide(&test_media(), &DeviceProfile::browser()).method,
+            PlayMethod::Remux
+        );
+    }
+
+    #[test]
+    fn mp4_with_browser_unfriendly_tracks_needs_hls() {
+        let mut media = test_media();
+        media.container = "mov,mp4,m4a,3gp,3g2,mj2".into();
+        media.audio[0].codec = "ac3".into();
+        assert_eq!(recommend_delivery(&media), Delivery::Hls);
+
+        let mut media = uhd_hevc_media();
+        media.container = "mov,mp4,m4a,3gp,3g2,mj2".into();
+        assert_eq!(recommend_delivery(&media), Delivery::Hls);
+    }
+
     #[test]
     fn transcode_when_bitrate_too_high() {
         let media = test_media();
-        let caps = ClientCaps {
+        let caps = DeviceProfile {
             max_bitrate_kbps: Some(2000),
-            ..ClientCaps::default()
+            ..DeviceProfile::default()
         };
         let d = decide(&media, &caps);
         assert_eq!(d.method, PlayMethod::Transcode);
@@ -208,13 +615,37 @@ mod tests {
     #[test]
     fn transcode_when_resolution_too_high() {
         let media = test_media();
-        let caps = ClientCaps {
+        let caps = DeviceProfile {
             max_width: Some(1280),
             max_height: Some(720),
-            ..ClientCaps::default()
+            ..DeviceProfile::default()
         };
         let d = decide(&media, &caps);
         assert_eq!(d.method, PlayMethod::Transcode);
         assert!(d.reasons.contains(&TranscodeReason::VideoResolutionTooHigh));
     }
+
+    #[test]
+    fn hdr_video_is_tone_mapped_only_when_re_encoded() {
+        let mut media = test_media();
+        media.video.as_mut().unwrap().hdr_format = Some(crate::ffprobe::HDR_HDR10.into());
+
+        let d = decide(&media, &DeviceProfile::default());
+        assert!(!d.transcode_video);
+        assert!(!d.tone_map);
+
+        let d = decide(
+            &media,
+            &DeviceProfile::default().through(&DeviceProfile::hls()),
+        );
+        assert!(!d.tone_map);
+
+        media.video.as_mut().unwrap().codec = "hevc".into();
+        let d = decide(&media, &DeviceProfile::hls());
+        assert!(d.transcode_video);
+        assert!(d.tone_map);
+
+        media.video.as_mut().unwrap().hdr_format = None;
+        assert!(!decide(&media, &DeviceProfile::hls()).tone_map);
+    }
 }
@@ -13,9 +13,12 @@ pub struct MediaInfo {
     pub video: Option<VideoStream>,
     pub audio: Vec<AudioStream>,
     pub subtitles: Vec<SubtitleStream>,
+    /// Attached fonts and images, and `attached_pic` cover art.
+    #[serde(default)]
+    pub attachments: Vec<AttachmentStream>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VideoStream {
     pub index: u32,
     pub codec: String,
@@ -23,8 +26,33 @@ pub struct VideoStream {
     pub height: u32,
     pub bitrate_kbps: Option<u32>,
     pub framerate: Option<f64>,
+    /// Codec profile as ffprobe names it, e.g. `High` or `Main 10`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Codec level as ffprobe reports it: `level_idc` for H.264, `general_level_idc`
+    /// for HEVC, `seq_level_idx` for AV1.
+    #[serde(default)]
+    pub level: Option<i64>,
+    #[serde(default)]
+    pub pix_fmt: Option<String>,
+    /// `dolby_vision`, `hdr10` or `hlg`; unset for SDR video.
+    #[serde(default)]
+    pub hdr_format: Option<String>,
+    /// Dolby Vision profile from the stream's configuration record, e.g. `8`.
+    #[serde(default)]
+    pub dolby_vision_profile: Option<u32>,
+}
+
+impl VideoStream {
+    pub fn is_hdr(&self) -> bool {
+        self.hdr_format.is_some()
+    }
 }
 
+pub const HDR_DOLBY_VISION: &str = "dolby_vision";
+pub const HDR_HDR10: &str = "hdr10";
+pub const HDR_HLG: &str = "hlg";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioStream {
     pub index: u32,
@@ -48,6 +76,19 @@ pub struct SubtitleStream {
     pub is_default: bool,
 }
 
+/// A file carried inside the container: a Matroska attachment (fonts for ASS
+/// subtitles, cover images) or an MP4/MP3 `attached_pic` cover.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AttachmentStream {
+    pub index: u32,
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    /// A single-frame video stream rather than an attachment; extracted by
+    /// copying the frame instead of dumping the attachment.
+    #[serde(default)]
+    pub attached_pic: bool,
+}
+
 /// Run ffprobe on a file and parse the JSON output.
 pub async fn probe(ffprobe_path: &Path, file: &Path) -> Result<MediaInfo, TranscodeError> {
     let output = tokio::process::Command::new(ffprobe_path)
@@ -107,6 +148,7 @@ fn parse_probe_output(raw: &serde_json::Value) -> Result<MediaInfo, TranscodeErr
     let mut video = None;
     let mut audio = Vec::new();
     let mut subtitles = Vec::new();
+    let mut attachments = Vec::new();
 
     for s in &streams {
         let codec_type = s.get("codec_type").and_then(|v| v.as_str()).unwrap_or("");
@@ -137,30 +179,56 @@ fn parse_probe_output(raw: &serde_json::Value) -> Result<MediaInfo, TranscodeErr
             .unwrap_or(0)
             == 1;
 
+        let attached_pic = disposition
+            .and_then(|d| d.get("attached_pic"))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            == 1;
+
         match codec_type {
-            "video" => {
-                if video.is_none() {
-                    let width = s.get("width").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                    let height = s.get("height").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
-                    let stream_bitrate = s
-                        .get("bit_rate")
-                        .and_then(|v| v.as_str())
-                        .and_then(|b| b.parse::<u64>().ok())
-                        .map(|b| (b / 1000) as u32);
-                    let framerate = s
-                        .get("r_frame_rate")
-                        .and_then(|v| v.as_str())
-                        .and_then(|fr| parse_fraction(fr));
-
-                    video = Some(VideoStream {
-                        index,
-                        codec,
-                        width,
-                        height,
-                        bitrate_kbps: stream_bitrate,
-                        framerate,
-                    });
-                }
+            "video" if attached_pic => {
+                let mime_type = match codec.as_str() {
+                    "mjpeg" => "image/jpeg".to_string(),
+                    other => format!("image/{other}"),
+                };
+                attachments.push(AttachmentStream {
+                    index,
+                    filename: None,
+                    mime_type: Some(mime_type),
+                    attached_pic: true,
+                });
+            }
+            // Only the first video stream is the main picture.
+            "video" if video.is_none() => {
+                let width = s.get("width").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+                let height = s.get("height").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+                let stream_bitrate = s
+                    .get("bit_rate")
+                    .and_then(|v| v.as_str())
+                    .and_then(|b| b.parse::<u64>().ok())
+                    .map(|b| (b / 1000) as u32);
+                let framerate = s
+                    .get("r_frame_rate")
+                    .and_then(|v| v.as_str())
+                    .and_then(|fr| parse_fraction(fr));
+
+                let text = |key: &str| s.get(key).and_then(|v| v.as_str()).map(str::to_string);
+                let (hdr_format, dolby_vision_profile) = hdr_format(s);
+
+                video = Some(VideoStream {
+                    index,
+                    codec,
+                    width,
+                    height,
+                    bitrate_kbps: stream_bitrate,
+                    framerate,
+                    profile: text("profile"),
+                    // ffprobe reports -99 when the level is unknown.
+                    level: s.get("level").and_then(|v| v.as_i64()).filter(|l| *l > 0),
+                    pix_fmt: text("pix_fmt"),
+                    hdr_format: hdr_format.map(str::to_string),
+                    dolby_vision_profile,
+                });
             }
             "audio" => {
                 let channels = s.get("channels").and_then(|v| v.as_u64()).unwrap_or(2) as u32;
@@ -183,6 +251,19 @@ fn parse_probe_output(raw: &serde_json::Value) -> Result<MediaInfo, TranscodeErr
                     is_default,
                 });
             }
+            "attachment" => {
+                let tag = |key: &str| {
+                    tags.and_then(|t| t.get(key))
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.to_string())
+                };
+                attachments.push(AttachmentStream {
+                    index,
+                    filename: tag("filename"),
+                    mime_type: tag("mimetype"),
+                    attached_pic: false,
+                });
+            }
             _ => {}
         }
     }
@@ -194,9 +275,38 @@ fn parse_probe_output(raw: &serde_json::Value) -> Result<MediaInfo, TranscodeErr
         video,
         audio,
         subtitles,
+        attachments,
     })
 }
 
+/// HDR format of a video stream, with its Dolby Vision profile if it has one.
+/// Dolby Vision shows up as a configuration record in the stream's side data;
+/// HDR10 and HLG by their transfer characteristics.
+fn hdr_format(stream: &serde_json::Value) -> (Option<&'static str>, Option<u32>) {
+    let dovi = stream
+        .get("side_data_list")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .find(|sd| {
+            sd.get("side_data_type")
+                .and_then(|v| v.as_str())
+                .is_some_and(|t| t.starts_with("DOVI configuration"))
+        });
+    if let Some(dovi) = dovi {
+        let profile = dovi
+            .get("dv_profile")
+            .and_then(|v| v.as_u64())
+            .map(|p| p as u32);
+        return (Some(HDR_DOLBY_VISION), profile);
+    }
+    match stream.get("color_transfer").and_then(|v| v.as_str()) {
+        Some("smpte2084") => (Some(HDR_HDR10), None),
+        Some("arib-std-b67") => (Some(HDR_HLG), None),
+        _ => (None, None),
+    }
+}
+
 fn parse_fraction(s: &str) -> Option<f64> {
     if let Some((num, den)) = s.split_once('/') {
         let n: f64 = num.parse().ok()?;
@@ -278,6 +388,125 @@ mod tests {
         assert!(info.subtitles[1].is_forced);
     }
 
+    #[test]
+    fn attachments_and_cover_art_are_not_video() {
+        let json = serde_json::json!({
+            "format": { "format_name": "matroska,webm", "duration": "60.0" },
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720 },
+                { "index": 1, "codec_type": "attachment", "codec_name": "ttf",
+                  "tags": { "filename": "Roboto.ttf", "mimetype": "application/x-truetype-font" } },
+                { "index": 2, "codec_type": "video", "codec_name": "mjpeg", "width": 600, "height": 900,
+                  "disposition": { "attached_pic": 1 } }
+            ]
+        });
+
+        let info = parse_probe_output(&json).unwrap();
+        assert_eq!(info.video.unwrap().index, 0);
+        assert_eq!(
+            info.attachments,
+            vec![
+                AttachmentStream {
+                    index: 1,
+                    filename: Some("Roboto.ttf".into()),
+                    mime_type: Some("application/x-truetype-font".into()),
+                    attached_pic: false,
+                },
+                AttachmentStream {
+                    index: 2,
+                    filename: None,
+                    mime_type: Some("image/jpeg".into()),
+                    attached_pic: true,
+                },
+            ]
+        );
+
+        // A cover listed before the main video doesn't take its place.
+        let json = serde_json::json!({
+            "format": { "format_name": "mov,mp4,m4a,3gp,3g2,mj2" },
+            "streams": [
+                { "index": 0, "codec_type": "video", "codec_name": "png", "disposition": { "attached_pic": 1 } },
+                { "index": 1, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080 }
+            ]
+        });
+        let info = parse_probe_output(&json).unwrap();
+        assert_eq!(info.video.unwrap().index, 1);
+        assert_eq!(info.attachments[0].mime_type.as_deref(), Some("image/png"));
+    }
+
+    fn probe_video(stream: serde_json::Value) -> VideoStream {
+        let mut stream = stream;
+        stream["index"] = 0.into();
+        stream["codec_type"] = "video".into();
+        stream["codec_name"] = "hevc".into();
+        let json = serde_json::json!({
+            "format": { "format_name": "matroska,webm" },
+            "streams": [stream]
+        });
+        parse_probe_output(&json).unwrap().video.unwrap()
+    }
+
+    #[test]
+    fn hdr10_is_detected_from_pq_transfer() {
+        let video = probe_video(serde_json::json!({
+            "pix_fmt": "yuv420p10le",
+            "color_space": "bt2020nc",
+            "color_transfer": "smpte2084",
+            "color_primaries": "bt2020"
+        }));
+        assert_eq!(video.hdr_format.as_deref(), Some(HDR_HDR10));
+        assert_eq!(video.dolby_vision_profile, None);
+        assert!(video.is_hdr());
+
+        let hlg = probe_video(serde_json::json!({
+            "color_transfer": "arib-std-b67",
+            "color_primaries": "bt2020"
+        }));
+        assert_eq!(hlg.hdr_format.as_deref(), Some(HDR_HLG));
+    }
+
+    #[test]
+    fn dolby_vision_is_detected_from_side_data() {
+        let video = probe_video(serde_json::json!({
+            "color_transfer": "smpte2084",
+            "color_primaries": "bt2020",
+            "side_data_list": [{
+                "side_data_type": "DOVI configuration record",
+                "dv_version_major": 1,
+                "dv_version_minor": 0,
+                "dv_profile": 8,
+                "dv_level": 6,
+                "rpu_present_flag": 1,
+                "el_present_flag": 0,
+                "bl_present_flag": 1,
+                "dv_bl_signal_compatibility_id": 1
+            }]
+        }));
+        assert_eq!(video.hdr_format.as_deref(), Some(HDR_DOLBY_VISION));
+        assert_eq!(video.dolby_vision_profile, Some(8));
+    }
+
+    #[test]
+    fn sdr_video_has_no_hdr_format() {
+        let video = probe_video(serde_json::json!({
+            "pix_fmt": "yuv420p",
+            "color_space": "bt709",
+            "color_transfer": "bt709",
+            "color_primaries": "bt709",
+            "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": 0 }]
+        }));
+        assert_eq!(video.hdr_format, None);
+        assert_eq!(video.dolby_vision_profile, None);
+        assert!(!video.is_hdr());
+
+        // A 10-bit BT.2020 stream without a PQ or HLG transfer is not HDR either.
+        let wide_gamut = probe_video(serde_json::json!({
+            "pix_fmt": "yuv420p10le",
+            "color_primaries": "bt2020"
+        }));
+        assert_eq!(wide_gamut.hdr_format, None);
+    }
+
     #[test]
     fn parse_fraction_works() {
         assert!((parse_fraction("24000/1001").unwrap() - 23.976).abs() < 0.01);
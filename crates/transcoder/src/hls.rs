@@ -1,4 +1,15 @@
 //! HLS playlist and segment content-type helpers.
+//!
+//! Every session's `master.m3u8` is rendered here and names the video variant
+//! with its `CODECS`. A session with one audio track and no text subtitles muxes
+//! the audio into that variant. With several audio tracks or any text subtitles,
+//! each track is listed as an `#EXT-X-MEDIA` rendition next to the video variant.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::codecs::VideoFamily;
+use crate::decision::PlayDecision;
+use crate::ffprobe::{MediaInfo, VideoStream};
 
 /// Content-Type for HLS master/variant playlists.
 pub const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
@@ -9,15 +20,376 @@ pub const SEGMENT_CONTENT_TYPE_TS: &str = "video/mp2t";
 /// Content-Type for fMP4 segments.
 pub const SEGMENT_CONTENT_TYPE_MP4: &str = "video/mp4";
 
+/// Content-Type for WebVTT subtitle segments.
+pub const SEGMENT_CONTENT_TYPE_VTT: &str = "text/vtt; charset=utf-8";
+
 /// Determine segment content type from filename extension.
 pub fn segment_content_type(filename: &str) -> &'static str {
     if filename.ends_with(".m4s") || filename.ends_with(".mp4") {
         SEGMENT_CONTENT_TYPE_MP4
+    } else if filename.ends_with(".vtt") {
+        SEGMENT_CONTENT_TYPE_VTT
     } else {
         SEGMENT_CONTENT_TYPE_TS
     }
 }
 
+/// Non-standard tag carrying the source duration in seconds. Players ignore
+/// unknown tags; ours use it to size the scrubber before the playlist is complete.
+pub const SOURCE_DURATION_TAG: &str = "#EXT-X-RUSTFIN-SOURCE-DURATION";
+
+/// What [`annotate_media_playlist`] adds to the playlist ffmpeg writes.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistHints {
+    /// Full duration of the source, from ffprobe.
+    pub source_duration_secs: Option<f64>,
+    /// Wall-clock time of the first segment.
+    pub program_date_time: Option<DateTime<Utc>>,
+}
+
+/// Post-process a media playlist written by ffmpeg.
+///
+/// Adds `#EXT-X-PLAYLIST-TYPE:EVENT` if it is missing, raises
+/// `#EXT-X-TARGETDURATION` to cover the longest segment, and inserts the
+/// program date time and [`SOURCE_DURATION_TAG`] ahead of the first segment.
+/// Tags that are already present are left alone.
+pub fn annotate_media_playlist(playlist: &str, hints: &PlaylistHints) -> String {
+    let longest_segment = playlist
+        .lines()
+        .filter_map(|line| line.strip_prefix("#EXTINF:"))
+        .filter_map(|rest| rest.split(',').next()?.trim().parse::<f64>().ok())
+        .fold(0.0_f64, f64::max);
+    let has_tag = |tag: &str| playlist.lines().any(|line| line.starts_with(tag));
+
+    let mut header = Vec::new();
+    if !has_tag("#EXT-X-PLAYLIST-TYPE:") {
+        header.push("#EXT-X-PLAYLIST-TYPE:EVENT".to_string());
+    }
+    if let Some(duration) = hints.source_duration_secs.filter(|d| *d > 0.0)
+        && !has_tag(SOURCE_DURATION_TAG)
+    {
+        header.push(format!("{SOURCE_DURATION_TAG}:{duration:.3}"));
+    }
+    if let Some(start) = hints.program_date_time
+        && !has_tag("#EXT-X-PROGRAM-DATE-TIME:")
+    {
+        header.push(format!(
+            "#EXT-X-PROGRAM-DATE-TIME:{}",
+            start.to_rfc3339_opts(SecondsFormat::Millis, true)
+        ));
+    }
+
+    let mut out = Vec::new();
+    let mut header = Some(header);
+    for line in playlist.lines() {
+        if let Some(target) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+            let target = target.trim().parse::<u64>().unwrap_or(0);
+            let needed = longest_segment.round() as u64;
+            out.push(format!("#EXT-X-TARGETDURATION:{}", target.max(needed)));
+            continue;
+        }
+        if (line.starts_with("#EXTINF:") || line == "#EXT-X-ENDLIST")
+            && let Some(header) = header.take()
+        {
+            out.extend(header);
+        }
+        out.push(line.to_string());
+    }
+    if let Some(header) = header {
+        out.extend(header);
+    }
+
+    let mut annotated = out.join("\n");
+    annotated.push('\n');
+    annotated
+}
+
+/// Playlist ffmpeg writes the video rendition to; single-rendition sessions mux
+/// their audio into it too.
+pub const VIDEO_PLAYLIST: &str = "stream_video.m3u8";
+
+const AUDIO_GROUP: &str = "audio";
+const SUBTITLE_GROUP: &str = "subs";
+/// Subtitle codecs ffmpeg can convert to WebVTT; bitmap formats are left out.
+const TEXT_SUBTITLE_CODECS: &[&str] =
+    &["subrip", "srt", "ass", "ssa", "webvtt", "mov_text", "text"];
+
+/// An alternate audio track advertised with `#EXT-X-MEDIA:TYPE=AUDIO`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioRendition {
+    /// Source stream index (`-map 0:<index>`).
+    pub stream_index: u32,
+    pub name: String,
+    pub language: Option<String>,
+    pub default: bool,
+    /// Pass the track through instead of re-encoding it to AAC.
+    pub copy: bool,
+    /// Source codec, as ffprobe names it.
+    pub codec: String,
+}
+
+/// A text subtitle track converted to WebVTT and advertised with
+/// `#EXT-X-MEDIA:TYPE=SUBTITLES`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleRendition {
+    pub stream_index: u32,
+    pub name: String,
+    pub language: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+}
+
+/// Alternate renditions of a session. With a single audio track and no text
+/// subtitles the session keeps the plain one-playlist layout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Renditions {
+    pub audio: Vec<AudioRendition>,
+    pub subtitles: Vec<SubtitleRendition>,
+    /// Source bitrate, used for `BANDWIDTH` when video is copied.
+    pub source_bitrate_kbps: Option<u32>,
+    /// Source video stream, described by the variant's `CODECS` and `RESOLUTION`.
+    pub video: Option<VideoStream>,
+}
+
+impl Renditions {
+    /// Renditions for every audio track and text subtitle of `media`.
+    ///
+    /// `decision` is the one made against the HLS output: an audio track is
+    /// copied only if video is copied too and the track itself is compatible.
+    pub fn from_media_info(media: &MediaInfo, decision: &PlayDecision) -> Self {
+        let audio = media
+            .audio
+            .iter()
+            .enumerate()
+            .map(|(n, track)| AudioRendition {
+                stream_index: track.index,
+                name: rendition_name(
+                    track.title.as_deref(),
+                    track.language.as_deref(),
+                    "Audio",
+                    n,
+                ),
+                language: track.language.clone(),
+                default: n == 0,
+                copy: !decision.transcode_video
+                    && decision
+                        .audio_tracks
+                        .iter()
+                        .any(|t| t.index == track.index && t.compatible),
+                codec: track.codec.clone(),
+            })
+            .collect();
+        // A subtitle playlist needs the duration for its single segment.
+        let subtitles = if media.duration_secs > 0.0 {
+            media
+                .subtitles
+                .iter()
+                .filter(|s| {
+                    TEXT_SUBTITLE_CODECS
+                        .iter()
+                        .any(|c| c.eq_ignore_ascii_case(&s.codec))
+                })
+                .enumerate()
+                .map(|(n, track)| SubtitleRendition {
+                    stream_index: track.index,
+                    name: rendition_name(
+                        track.title.as_deref(),
+                        track.language.as_deref(),
+                        "Subtitles",
+                        n,
+                    ),
+                    language: track.language.clone(),
+                    default: track.is_default,
+                    forced: track.is_forced,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            audio,
+            subtitles,
+            source_bitrate_kbps: media.bitrate_kbps,
+            video: media.video.clone(),
+        }
+    }
+
+    /// Whether the session needs a master playlist with `#EXT-X-MEDIA` groups.
+    pub fn is_multi(&self) -> bool {
+        self.audio.len() > 1 || !self.subtitles.is_empty()
+    }
+
+    /// `CODECS` and `RESOLUTION` of the video variant when video is written by
+    /// `video_encoder` (`copy` passes it through) and scaled into `max_width` ×
+    /// `max_height`. `CODECS` is left out unless every stream has a codec string.
+    pub fn variant_info(
+        &self,
+        video_encoder: &str,
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    ) -> VariantInfo {
+        let Some(video) = &self.video else {
+            return VariantInfo::default();
+        };
+        let copy = video_encoder == "copy";
+        let resolution = if copy {
+            (video.width, video.height)
+        } else {
+            crate::codecs::scaled_resolution(video.width, video.height, max_width, max_height)
+        };
+        let output = if copy {
+            None
+        } else {
+            VideoFamily::from_name(video_encoder).map(|family| (family, resolution.0, resolution.1))
+        };
+        let video_codec = if copy || output.is_some() {
+            crate::codecs::video_codec_string(video, output)
+        } else {
+            None
+        };
+
+        let mut codecs = video_codec.into_iter().collect::<Vec<_>>();
+        let mut complete = !codecs.is_empty();
+        for audio in &self.audio {
+            match crate::codecs::audio_codec_string(&audio.codec, audio.copy) {
+                Some(codec) if !codecs.iter().any(|c| c == codec) => codecs.push(codec.into()),
+                Some(_) => {}
+                None => complete = false,
+            }
+        }
+        VariantInfo {
+            codecs: complete.then(|| codecs.join(",")),
+            resolution: Some(resolution).filter(|(w, h)| *w > 0 && *h > 0),
+        }
+    }
+}
+
+/// What the master playlist declares about the video variant. Players using Media
+/// Source Extensions need `CODECS` to set up decoding before the first segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VariantInfo {
+    /// RFC 6381 codec strings of the video and every audio rendition.
+    pub codecs: Option<String>,
+    pub resolution: Option<(u32, u32)>,
+}
+
+fn rendition_name(title: Option<&str>, language: Option<&str>, kind: &str, n: usize) -> String {
+    title
+        .or(language)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{kind} {}", n + 1))
+        .replace('"', "'")
+}
+
+/// Playlist an audio rendition is written to.
+pub fn audio_playlist_name(n: usize) -> String {
+    format!("stream_audio_{n}.m3u8")
+}
+
+/// WebVTT file a subtitle rendition is extracted to.
+pub fn subtitle_vtt_name(n: usize) -> String {
+    format!("subs_{n}.vtt")
+}
+
+/// Playlist wrapping [`subtitle_vtt_name`] as a single segment.
+pub fn subtitle_playlist_name(n: usize) -> String {
+    format!("subs_{n}.m3u8")
+}
+
+/// The rendition index of a [`subtitle_playlist_name`], if `filename` is one.
+pub fn subtitle_playlist_index(filename: &str) -> Option<usize> {
+    filename
+        .strip_prefix("subs_")?
+        .strip_suffix(".m3u8")?
+        .parse()
+        .ok()
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "YES" } else { "NO" }
+}
+
+/// Master playlist of a session: one video variant with its `CODECS`. In a
+/// multi-rendition session the variant references an audio group and, if there
+/// are subtitles, a subtitle group; otherwise its audio is muxed in.
+pub fn render_master_playlist(
+    renditions: &Renditions,
+    bandwidth_bps: u64,
+    variant: &VariantInfo,
+) -> String {
+    let mut out = vec![
+        "#EXTM3U".to_string(),
+        "#EXT-X-VERSION:4".to_string(),
+        "#EXT-X-INDEPENDENT-SEGMENTS".to_string(),
+    ];
+    let multi = renditions.is_multi();
+    let audio_group = if multi { &renditions.audio[..] } else { &[] };
+    for (n, audio) in audio_group.iter().enumerate() {
+        let language = audio
+            .language
+            .as_deref()
+            .map(|l| format!(",LANGUAGE=\"{l}\""))
+            .unwrap_or_default();
+        out.push(format!(
+            "#EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"{AUDIO_GROUP}\",NAME=\"{}\"{language},DEFAULT={},AUTOSELECT=YES,URI=\"{}\"",
+            audio.name,
+            yes_no(audio.default),
+            audio_playlist_name(n)
+        ));
+    }
+    for (n, subtitle) in renditions.subtitles.iter().enumerate() {
+        let language = subtitle
+            .language
+            .as_deref()
+            .map(|l| format!(",LANGUAGE=\"{l}\""))
+            .unwrap_or_default();
+        out.push(format!(
+            "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"{SUBTITLE_GROUP}\",NAME=\"{}\"{language},DEFAULT={},AUTOSELECT=YES,FORCED={},URI=\"{}\"",
+            subtitle.name,
+            yes_no(subtitle.default),
+            yes_no(subtitle.forced),
+            subtitle_playlist_name(n)
+        ));
+    }
+
+    let mut stream_inf = format!("#EXT-X-STREAM-INF:BANDWIDTH={bandwidth_bps}");
+    if let Some(codecs) = &variant.codecs {
+        stream_inf.push_str(&format!(",CODECS=\"{codecs}\""));
+    }
+    if let Some((width, height)) = variant.resolution {
+        stream_inf.push_str(&format!(",RESOLUTION={width}x{height}"));
+    }
+    if !audio_group.is_empty() {
+        stream_inf.push_str(&format!(",AUDIO=\"{AUDIO_GROUP}\""));
+    }
+    if !renditions.subtitles.is_empty() {
+        stream_inf.push_str(&format!(",SUBTITLES=\"{SUBTITLE_GROUP}\""));
+    }
+    out.push(stream_inf);
+    out.push(VIDEO_PLAYLIST.to_string());
+
+    let mut playlist = out.join("\n");
+    playlist.push('\n');
+    playlist
+}
+
+/// Media playlist serving a whole WebVTT file as one segment of `span_secs`.
+///
+/// ffmpeg writes the file while the session runs, so until it is `finished`
+/// this is an open EVENT playlist with no segments; the file and
+/// `#EXT-X-ENDLIST` are appended once it is complete.
+pub fn render_subtitle_playlist(vtt_file: &str, span_secs: f64, finished: bool) -> String {
+    let span = span_secs.max(1.0);
+    let mut playlist = format!(
+        "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:EVENT\n",
+        span.ceil() as u64
+    );
+    if finished {
+        playlist.push_str(&format!("#EXTINF:{span:.3},\n{vtt_file}\n#EXT-X-ENDLIST\n"));
+    }
+    playlist
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,5 +405,183 @@ mod tests {
             SEGMENT_CONTENT_TYPE_MP4
         );
         assert_eq!(segment_content_type("init.mp4"), SEGMENT_CONTENT_TYPE_MP4);
+        assert_eq!(segment_content_type("subs_0.vtt"), SEGMENT_CONTENT_TYPE_VTT);
+    }
+
+    #[test]
+    fn annotates_event_playlist_with_duration_and_date() {
+        let playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:4\n#EXT-X-MEDIA-SEQUENCE:0\n#EXTINF:4.0,\nseg_00000.ts\n#EXTINF:5.6,\nseg_00001.ts\n";
+        let start = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let hints = PlaylistHints {
+            source_duration_secs: Some(5400.5),
+            program_date_time: Some(start),
+        };
+
+        let annotated = annotate_media_playlist(playlist, &hints);
+        assert_eq!(
+            annotated,
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXT-X-PLAYLIST-TYPE:EVENT\n#EXT-X-RUSTFIN-SOURCE-DURATION:5400.500\n\
+             #EXT-X-PROGRAM-DATE-TIME:2024-05-01T12:00:00.000Z\n\
+             #EXTINF:4.0,\nseg_00000.ts\n#EXTINF:5.6,\nseg_00001.ts\n"
+        );
+
+        // Running it again changes nothing.
+        assert_eq!(annotate_media_playlist(&annotated, &hints), annotated);
+    }
+
+    #[test]
+    fn keeps_existing_playlist_type_and_annotates_empty_playlist() {
+        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PLAYLIST-TYPE:VOD\n";
+        let hints = PlaylistHints {
+            source_duration_secs: Some(60.0),
+            program_date_time: None,
+        };
+
+        let annotated = annotate_media_playlist(playlist, &hints);
+        assert_eq!(
+            annotated,
+            "#EXTM3U\n#EXT-X-TARGETDURATION:4\n#EXT-X-PLAYLIST-TYPE:VOD\n\
+             #EXT-X-RUSTFIN-SOURCE-DURATION:60.000\n"
+        );
+    }
+
+    fn multi_track_media() -> MediaInfo {
+        use crate::ffprobe::{AudioStream, SubtitleStream, VideoStream};
+        let audio = |index, codec: &str, language: &str| AudioStream {
+            index,
+            codec: codec.into(),
+            channels: 2,
+            language: Some(language.into()),
+            title: None,
+            is_default: index == 1,
+        };
+        let subtitle = |index, codec: &str, language: &str| SubtitleStream {
+            index,
+            codec: codec.into(),
+            language: Some(language.into()),
+            title: None,
+            is_forced: false,
+            is_default: false,
+        };
+        MediaInfo {
+            container: "matroska,webm".into(),
+            duration_secs: 5400.0,
+            bitrate_kbps: Some(6000),
+            video: Some(VideoStream {
+                index: 0,
+                codec: "h264".into(),
+                width: 1920,
+                height: 1080,
+                bitrate_kbps: None,
+                framerate: None,
+                profile: None,
+                level: None,
+                pix_fmt: None,
+                hdr_format: None,
+                dolby_vision_profile: None,
+            }),
+            audio: vec![audio(1, "aac", "eng"), audio(2, "dts", "fre")],
+            subtitles: vec![
+                subtitle(3, "subrip", "eng"),
+                subtitle(4, "hdmv_pgs_subtitle", "ger"),
+            ],
+            attachments: vec![],
+        }
+    }
+
+    #[test]
+    fn multi_audio_source_gets_audio_and_subtitle_groups() {
+        let media = multi_track_media();
+        let decision = crate::decision::decide(&media, &crate::decision::DeviceProfile::hls());
+        let renditions = Renditions::from_media_info(&media, &decision);
+        assert!(renditions.is_multi());
+        // Only the AAC track can be passed through; PGS has no WebVTT form.
+        assert_eq!(
+            renditions.audio.iter().map(|a| a.copy).collect::<Vec<_>>(),
+            [true, false]
+        );
+        assert_eq!(renditions.subtitles.len(), 1);
+
+        let master = render_master_playlist(&renditions, 6_128_000, &VariantInfo::default());
+        let audio_entries: Vec<&str> = master
+            .lines()
+            .filter(|l| l.starts_with("#EXT-X-MEDIA:TYPE=AUDIO"))
+            .collect();
+        assert_eq!(audio_entries.len(), 2);
+        assert!(audio_entries[0].contains("NAME=\"eng\",LANGUAGE=\"eng\",DEFAULT=YES"));
+        assert!(audio_entries[1].contains("LANGUAGE=\"fre\",DEFAULT=NO"));
+        assert!(audio_entries[1].ends_with("URI=\"stream_audio_1.m3u8\""));
+        assert!(master.contains(
+            "#EXT-X-MEDIA:TYPE=SUBTITLES,GROUP-ID=\"subs\",NAME=\"eng\",LANGUAGE=\"eng\",DEFAULT=NO,AUTOSELECT=YES,FORCED=NO,URI=\"subs_0.m3u8\""
+        ));
+        assert!(master.ends_with(
+            "#EXT-X-STREAM-INF:BANDWIDTH=6128000,AUDIO=\"audio\",SUBTITLES=\"subs\"\nstream_video.m3u8\n"
+        ));
+    }
+
+    #[test]
+    fn master_declares_codecs_and_resolution_of_the_output() {
+        let media = multi_track_media();
+        let decision = crate::decision::decide(&media, &crate::decision::DeviceProfile::hls());
+        let renditions = Renditions::from_media_info(&media, &decision);
+
+        // Copied H.264 with the AAC track passed through and DTS re-encoded to AAC.
+        let copied = renditions.variant_info("copy", None, None);
+        assert_eq!(copied.codecs.as_deref(), Some("avc1.640028,mp4a.40.2"));
+        let master = render_master_playlist(&renditions, 6_128_000, &copied);
+        assert!(master.contains(
+            "#EXT-X-STREAM-INF:BANDWIDTH=6128000,CODECS=\"avc1.640028,mp4a.40.2\",RESOLUTION=1920x1080,AUDIO=\"audio\""
+        ));
+
+        let hevc = renditions.variant_info("libx265", Some(1280), None);
+        assert_eq!(hevc.codecs.as_deref(), Some("hvc1.1.6.L93.B0,mp4a.40.2"));
+        assert_eq!(hevc.resolution, Some((1280, 720)));
+        let av1 = renditions.variant_info("libsvtav1", None, None);
+        assert_eq!(av1.codecs.as_deref(), Some("av01.0.08M.08,mp4a.40.2"));
+
+        // An audio track without a codec string leaves CODECS out rather than lying.
+        let mut passthrough = renditions.clone();
+        passthrough.audio[1].copy = true;
+        let info = passthrough.variant_info("copy", None, None);
+        assert_eq!(info.codecs, None);
+        assert_eq!(info.resolution, Some((1920, 1080)));
+    }
+
+    #[test]
+    fn single_audio_source_keeps_plain_layout() {
+        let mut media = multi_track_media();
+        media.audio.truncate(1);
+        media.subtitles.clear();
+        let decision = crate::decision::decide(&media, &crate::decision::DeviceProfile::hls());
+        let renditions = Renditions::from_media_info(&media, &decision);
+        assert!(!renditions.is_multi());
+
+        // Still a master playlist, so players learn CODECS; the audio is muxed in.
+        let variant = renditions.variant_info("libx264", None, None);
+        let master = render_master_playlist(&renditions, 6_128_000, &variant);
+        assert!(!master.contains("#EXT-X-MEDIA"));
+        assert!(master.ends_with(
+            "#EXT-X-STREAM-INF:BANDWIDTH=6128000,CODECS=\"avc1.640028,mp4a.40.2\",RESOLUTION=1920x1080\nstream_video.m3u8\n"
+        ));
+    }
+
+    #[test]
+    fn subtitle_playlist_lists_the_file_once_it_is_complete() {
+        assert_eq!(
+            render_subtitle_playlist("subs_0.vtt", 95.5, false),
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:96\n#EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXT-X-PLAYLIST-TYPE:EVENT\n"
+        );
+        assert_eq!(
+            render_subtitle_playlist("subs_0.vtt", 95.5, true),
+            "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:96\n#EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXT-X-PLAYLIST-TYPE:EVENT\n#EXTINF:95.500,\nsubs_0.vtt\n#EXT-X-ENDLIST\n"
+        );
+        assert_eq!(subtitle_playlist_index("subs_3.m3u8"), Some(3));
+        assert_eq!(subtitle_playlist_index("subs_3.vtt"), None);
+        assert_eq!(subtitle_playlist_index("stream_audio_0.m3u8"), None);
     }
 }
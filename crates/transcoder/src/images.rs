@@ -0,0 +1,54 @@
+//! Still images decoded and encoded afresh, so nothing but their pixels is kept.
+
+use std::path::Path;
+
+use crate::TranscodeError;
+use crate::attachments::run_ffmpeg;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageCodec {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ImageCodec {
+    fn encoder_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Jpeg => &["-c:v", "mjpeg", "-q:v", "2"],
+            Self::Png => &["-c:v", "png"],
+            Self::Webp => &["-c:v", "libwebp", "-quality", "90"],
+        }
+    }
+}
+
+/// Decode the first frame of `input` and encode it to `output` with no metadata,
+/// through a temporary file next to it. A file ffmpeg cannot decode fails with
+/// [`TranscodeError::FfmpegFailed`].
+pub async fn reencode_image(
+    ffmpeg_path: &Path,
+    input: &Path,
+    codec: ImageCodec,
+    output: &Path,
+) -> Result<(), TranscodeError> {
+    let extension = output
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let partial = output.with_extension(format!("partial.{extension}"));
+    let mut args: Vec<std::ffi::OsString> = vec!["-i".into(), input.into()];
+    args.extend(
+        ["-map", "0:v:0", "-frames:v", "1", "-map_metadata", "-1"]
+            .into_iter()
+            .chain(codec.encoder_args().iter().copied())
+            .chain(["-f", "image2"])
+            .map(Into::into),
+    );
+    args.push(partial.clone().into());
+    if let Err(e) = run_ffmpeg(ffmpeg_path, "image encoding", args).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&partial, output).await?;
+    Ok(())
+}
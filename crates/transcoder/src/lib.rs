@@ -3,11 +3,17 @@
     clippy::redundant_closure,
     clippy::unused_async
 )]
+pub mod attachments;
+pub mod codecs;
 pub mod decision;
 pub mod ffprobe;
 pub mod gpu;
 pub mod hls;
+pub mod images;
 pub mod session;
+pub mod subtitles;
+pub mod tools;
+pub mod tracks;
 
 use std::path::PathBuf;
 use thiserror::Error;
@@ -24,6 +30,8 @@ pub enum TranscodeError {
     SessionNotFound(String),
     #[error("max transcodes reached ({0})")]
     MaxTranscodesReached(usize),
+    #[error("only {available} bytes free in the transcode dir, {required} required")]
+    InsufficientDiskSpace { available: u64, required: u64 },
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
@@ -38,6 +46,26 @@ pub struct TranscoderConfig {
     pub segment_secs: u32,
     pub idle_timeout_secs: u64,
     pub hw_accel: Option<HwAccel>,
+    /// Serve far seeks by restarting ffmpeg at the seek point instead of waiting
+    /// for a single long-running session to catch up.
+    ///
+    /// Trade-offs: a restart discards segments already produced and costs an
+    /// ffmpeg startup (plus a keyframe search) per seek, but playback resumes
+    /// within a segment or two of the target. A single session keeps every
+    /// segment it has produced, so backwards seeks are free, but forward seeks
+    /// past the transcode head stall until ffmpeg reaches that point.
+    pub seek_restart: bool,
+    /// Free space the transcode dir's filesystem must keep. New sessions are refused
+    /// below it, and while it stays below the least recently used session that has
+    /// stopped pinging is stopped (see [`session::LOW_SPACE_IDLE_SECS`]). 0 disables
+    /// the check.
+    pub min_free_bytes: u64,
+    /// Leftover session directories untouched for this long are deleted at
+    /// startup (see [`session::SessionManager::remove_stale_session_dirs`]).
+    pub stale_dir_age_secs: u64,
+    /// Write each session's segments into one file, addressed by
+    /// `#EXT-X-BYTERANGE` in the playlist, instead of one `.ts` file per segment.
+    pub hls_single_file: bool,
 }
 
 impl Default for TranscoderConfig {
@@ -50,6 +78,10 @@ impl Default for TranscoderConfig {
             segment_secs: 4,
             idle_timeout_secs: 60,
             hw_accel: None,
+            seek_restart: false,
+            min_free_bytes: 1024 * 1024 * 1024,
+            stale_dir_age_secs: 60 * 60,
+            hls_single_file: false,
         }
     }
 }
@@ -7,6 +7,9 @@ use tokio::process::Child;
 use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
 use tracing::{info, warn};
 
+use crate::codecs::VideoFamily;
+use crate::decision::{StreamAction, TranscodePlan};
+use crate::hls::{PlaylistHints, Renditions, VariantInfo};
 use crate::{HwAccel, TranscodeError, TranscoderConfig};
 
 #[derive(Debug, Clone)]
@@ -22,9 +25,21 @@ pub struct TranscodeSession {
     pub file_id: String,
     pub owner_user_id: String,
     pub output_dir: PathBuf,
+    /// Source timestamp the session's first segment starts at.
+    pub start_time_secs: f64,
+    pub plan: TranscodePlan,
+    /// Alternate audio/subtitle tracks advertised in the master playlist.
+    pub renditions: Renditions,
+    /// Full source duration from ffprobe, if it was probed.
+    pub source_duration_secs: Option<f64>,
     pub started_at: Instant,
+    /// Wall-clock counterpart of `started_at`, used for `#EXT-X-PROGRAM-DATE-TIME`.
+    pub started_wall: chrono::DateTime<chrono::Utc>,
     pub last_ping: Instant,
-    _permit: OwnedSemaphorePermit,
+    /// Concurrency slot; handed on to the replacement when the session is restarted.
+    permit: Option<OwnedSemaphorePermit>,
+    /// Released with the session; see [`SessionManager::hold_for_session`].
+    held: Option<Box<dyn std::any::Any + Send + Sync>>,
     child: Option<Child>,
 }
 
@@ -33,7 +48,7 @@ impl TranscodeSession {
         self.last_ping = Instant::now();
     }
 
-    /// Check if master.m3u8 exists (ffmpeg started writing).
+    /// The session's master playlist, written before ffmpeg starts.
     pub fn master_playlist_path(&self) -> PathBuf {
         self.output_dir.join("master.m3u8")
     }
@@ -57,11 +72,52 @@ impl Drop for TranscodeSession {
     }
 }
 
+/// Returns the bytes available to unprivileged users on the filesystem holding a path.
+pub type FreeSpaceProbe = fn(&Path) -> std::io::Result<u64>;
+
+/// Free space on the filesystem holding `path`, via `statvfs`.
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` is only read after success.
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> std::io::Result<u64> {
+    Ok(u64::MAX)
+}
+
+/// Deletes a session's output directory and everything in it.
+pub type DirRemover = fn(&Path) -> std::io::Result<()>;
+
+fn remove_dir(path: &Path) -> std::io::Result<()> {
+    std::fs::remove_dir_all(path)
+}
+
+/// How long a session must go without a ping before it may be stopped to free disk
+/// space, ahead of its idle timeout.
+pub const LOW_SPACE_IDLE_SECS: u64 = 15;
+
 /// Manages all active transcode sessions.
 pub struct SessionManager {
     config: TranscoderConfig,
     sessions: Arc<Mutex<HashMap<String, TranscodeSession>>>,
     semaphore: Arc<Semaphore>,
+    free_space: FreeSpaceProbe,
+    remove_dir: DirRemover,
+    /// Output dirs of stopped sessions that could not be deleted (e.g. `EBUSY`);
+    /// [`SessionManager::cleanup_idle`] retries them until they are gone.
+    deferred_cleanup: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl SessionManager {
@@ -71,16 +127,55 @@ impl SessionManager {
             config,
             sessions: Arc::new(Mutex::new(HashMap::new())),
             semaphore,
+            free_space: available_space,
+            remove_dir,
+            deferred_cleanup: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Measure free space with `probe` instead of the filesystem (tests).
+    pub fn with_free_space_probe(mut self, probe: FreeSpaceProbe) -> Self {
+        self.free_space = probe;
+        self
+    }
+
+    /// Delete output dirs with `remover` instead of the filesystem (tests).
+    pub fn with_dir_remover(mut self, remover: DirRemover) -> Self {
+        self.remove_dir = remover;
+        self
+    }
+
+    /// Free bytes under the transcode dir when below [`TranscoderConfig::min_free_bytes`].
+    /// A probe that fails is logged and treated as enough space.
+    fn low_disk_space(&self) -> Option<u64> {
+        if self.config.min_free_bytes == 0 {
+            return None;
+        }
+        match (self.free_space)(&self.config.transcode_dir) {
+            Ok(available) if available < self.config.min_free_bytes => Some(available),
+            Ok(_) => None,
+            Err(e) => {
+                warn!(error = %e, dir = ?self.config.transcode_dir, "could not check free space");
+                None
+            }
         }
     }
 
     /// Create a new HLS transcode session. Returns the session ID.
     /// Blocks if max concurrent transcodes are running.
+    ///
+    /// `plan` says which streams can be copied rather than re-encoded. When
+    /// `renditions` has several audio tracks or any subtitles, the session gets
+    /// a master playlist with `#EXT-X-MEDIA` groups (see [`crate::hls`]).
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_session(
         &self,
         input_path: PathBuf,
         start_time_secs: Option<f64>,
         video_codec_override: Option<&str>,
+        plan: TranscodePlan,
+        renditions: Renditions,
+        source_duration_secs: Option<f64>,
         owner_user_id: String,
         file_id: String,
     ) -> Result<String, TranscodeError> {
@@ -91,30 +186,30 @@ impl SessionManager {
             .try_acquire_owned()
             .map_err(|_| TranscodeError::MaxTranscodesReached(self.config.max_concurrent))?;
 
-        let session_id = uuid::Uuid::new_v4().to_string();
-        let output_dir = self.config.transcode_dir.join(&session_id);
-        tokio::fs::create_dir_all(&output_dir).await?;
-
-        let child = spawn_ffmpeg(
-            &self.config.ffmpeg_path,
-            &input_path,
-            &output_dir,
-            self.config.segment_secs,
-            start_time_secs,
-            video_codec_override,
-            self.config.hw_accel.as_ref(),
-        )
-        .await?;
-
+        let (session_id, output_dir, child) = self
+            .launch(
+                &input_path,
+                start_time_secs,
+                video_codec_override,
+                plan,
+                &renditions,
+            )
+            .await?;
         let session = TranscodeSession {
             id: session_id.clone(),
             input_path,
             file_id,
             owner_user_id,
             output_dir,
+            start_time_secs: start_time_secs.unwrap_or(0.0).max(0.0),
+            plan,
+            renditions,
+            source_duration_secs,
             started_at: Instant::now(),
+            started_wall: chrono::Utc::now(),
             last_ping: Instant::now(),
-            _permit: permit,
+            permit: Some(permit),
+            held: None,
             child: Some(child),
         };
 
@@ -127,6 +222,197 @@ impl SessionManager {
         Ok(session_id)
     }
 
+    /// Create a session's output dir and start ffmpeg in it. Returns the new
+    /// session ID, the dir, and the ffmpeg process; the dir is removed again if
+    /// ffmpeg cannot be started.
+    async fn launch(
+        &self,
+        input_path: &Path,
+        start_time_secs: Option<f64>,
+        video_codec_override: Option<&str>,
+        plan: TranscodePlan,
+        renditions: &Renditions,
+    ) -> Result<(String, PathBuf, Child), TranscodeError> {
+        tokio::fs::create_dir_all(&self.config.transcode_dir).await?;
+        if let Some(available) = self.low_disk_space() {
+            return Err(TranscodeError::InsufficientDiskSpace {
+                available,
+                required: self.config.min_free_bytes,
+            });
+        }
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let output_dir = self.config.transcode_dir.join(&session_id);
+        tokio::fs::create_dir_all(&output_dir).await?;
+
+        let started = async {
+            let hw_accel = self.config.hw_accel.as_ref();
+            let encoder = video_encoder(plan, video_codec_override, hw_accel);
+            // Hardware pipelines skip the scale filter (see `build_ffmpeg_args`).
+            let scaled = encoder != "copy" && hw_accel.is_none();
+            let variant = renditions.variant_info(
+                &encoder,
+                plan.max_width.filter(|_| scaled),
+                plan.max_height.filter(|_| scaled),
+            );
+            write_master_playlist(&output_dir, renditions, plan, &variant).await?;
+
+            let args = build_ffmpeg_args(
+                input_path,
+                &output_dir,
+                self.config.segment_secs,
+                start_time_secs,
+                video_codec_override,
+                plan,
+                renditions,
+                self.config.hw_accel.as_ref(),
+                self.config.seek_restart,
+                self.config.hls_single_file,
+            );
+            spawn_ffmpeg(&self.config.ffmpeg_path, &output_dir, args).await
+        };
+        match started.await {
+            Ok(child) => Ok((session_id, output_dir, child)),
+            Err(e) => {
+                self.remove_output_dir(&session_id, output_dir).await;
+                Err(e)
+            }
+        }
+    }
+
+    /// Keep `guard` alive until the session stops. Returns `false`, dropping the
+    /// guard, when there is no such session.
+    pub async fn hold_for_session(
+        &self,
+        session_id: &str,
+        guard: Box<dyn std::any::Any + Send + Sync>,
+    ) -> bool {
+        match self.sessions.lock().await.get_mut(session_id) {
+            Some(session) => {
+                session.held = Some(guard);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replace a session with a fresh one that starts at `start_time_secs`.
+    ///
+    /// Only available when [`TranscoderConfig::seek_restart`] is enabled. The new
+    /// ffmpeg is started before the old one is stopped, so a failed restart leaves
+    /// the old session playing. The new session takes over the old one's
+    /// concurrency permit and anything it held. Returns the new session ID.
+    pub async fn restart_session_at(
+        &self,
+        session_id: &str,
+        start_time_secs: f64,
+    ) -> Result<String, TranscodeError> {
+        let (input_path, plan, renditions) = {
+            let sessions = self.sessions.lock().await;
+            let session = sessions
+                .get(session_id)
+                .ok_or_else(|| TranscodeError::SessionNotFound(session_id.into()))?;
+            (
+                session.input_path.clone(),
+                session.plan,
+                session.renditions.clone(),
+            )
+        };
+
+        let (new_id, output_dir, child) = self
+            .launch(&input_path, Some(start_time_secs), None, plan, &renditions)
+            .await?;
+
+        let mut sessions = self.sessions.lock().await;
+        let Some(mut old) = sessions.remove(session_id) else {
+            // Stopped while the new ffmpeg was starting.
+            drop(sessions);
+            let mut child = child;
+            let _ = child.start_kill();
+            let _ = child.wait().await;
+            self.remove_output_dir(&new_id, output_dir).await;
+            return Err(TranscodeError::SessionNotFound(session_id.into()));
+        };
+        let session = TranscodeSession {
+            id: new_id.clone(),
+            input_path,
+            file_id: old.file_id.clone(),
+            owner_user_id: old.owner_user_id.clone(),
+            output_dir,
+            start_time_secs: start_time_secs.max(0.0),
+            plan,
+            renditions,
+            source_duration_secs: old.source_duration_secs,
+            started_at: Instant::now(),
+            started_wall: chrono::Utc::now(),
+            last_ping: Instant::now(),
+            permit: old.permit.take(),
+            held: old.held.take(),
+            child: Some(child),
+        };
+        sessions.insert(new_id.clone(), session);
+        drop(sessions);
+
+        if let Some(ref mut child) = old.child {
+            let _ = child.start_kill();
+            let _ = child.wait().await;
+        }
+        self.remove_output_dir(session_id, old.output_dir.clone())
+            .await;
+        info!(session_id, new_session_id = %new_id, "HLS session restarted");
+        Ok(new_id)
+    }
+
+    /// Source timestamp a session starts at (0 unless it was created seeked).
+    pub async fn session_start_time(&self, session_id: &str) -> Option<f64> {
+        self.sessions
+            .lock()
+            .await
+            .get(session_id)
+            .map(|s| s.start_time_secs)
+    }
+
+    /// Subtitle playlist `n` of a session, spanning the source from the session's
+    /// start offset to its end. It stays open until ffmpeg has exited cleanly,
+    /// since the WebVTT file is written alongside the video.
+    pub async fn subtitle_playlist(
+        &self,
+        session_id: &str,
+        n: usize,
+    ) -> Result<String, TranscodeError> {
+        let mut sessions = self.sessions.lock().await;
+        let session = sessions
+            .get_mut(session_id)
+            .filter(|s| n < s.renditions.subtitles.len())
+            .ok_or_else(|| TranscodeError::SessionNotFound(session_id.into()))?;
+        let finished = session
+            .child
+            .as_mut()
+            .is_some_and(|child| matches!(child.try_wait(), Ok(Some(status)) if status.success()));
+        let span = session.source_duration_secs.unwrap_or(0.0) - session.start_time_secs;
+        Ok(crate::hls::render_subtitle_playlist(
+            &crate::hls::subtitle_vtt_name(n),
+            span,
+            finished,
+        ))
+    }
+
+    /// Hints for annotating a session's playlist (see [`crate::hls::annotate_media_playlist`]).
+    pub async fn playlist_hints(&self, session_id: &str) -> Option<PlaylistHints> {
+        self.sessions
+            .lock()
+            .await
+            .get(session_id)
+            .map(|s| PlaylistHints {
+                source_duration_secs: s.source_duration_secs,
+                program_date_time: Some(s.started_wall),
+            })
+    }
+
+    pub fn seek_restart_enabled(&self) -> bool {
+        self.config.seek_restart
+    }
+
     /// Ping a session (update last_ping) and return if it exists.
     pub async fn ping(&self, session_id: &str) -> bool {
         if let Some(session) = self.sessions.lock().await.get_mut(session_id) {
@@ -160,12 +446,9 @@ impl SessionManager {
                 let _ = child.start_kill();
                 let _ = child.wait().await;
             }
-            // Clean up files
-            if session.output_dir.exists() {
-                if let Err(e) = tokio::fs::remove_dir_all(&session.output_dir).await {
-                    warn!(session_id, error = %e, "failed to clean up transcode dir");
-                }
-            }
+            drop(sessions);
+            self.remove_output_dir(session_id, session.output_dir.clone())
+                .await;
             info!(session_id, "HLS session stopped and cleaned up");
             Ok(())
         } else {
@@ -174,7 +457,32 @@ impl SessionManager {
     }
 
     /// Clean up idle sessions. Call this periodically.
+    ///
+    /// While free space stays below [`TranscoderConfig::min_free_bytes`], the session
+    /// that has gone longest without a ping is stopped as well, one per call, provided
+    /// it has been quiet for [`LOW_SPACE_IDLE_SECS`]. Sessions a player is still
+    /// pinging are never stopped for space.
     pub async fn cleanup_idle(&self) {
+        self.retry_deferred_cleanup().await;
+        self.cleanup_timed_out().await;
+
+        if let Some(available) = self.low_disk_space() {
+            let oldest = self
+                .sessions
+                .lock()
+                .await
+                .values()
+                .filter(|s| s.is_idle(LOW_SPACE_IDLE_SECS))
+                .min_by_key(|s| s.last_ping)
+                .map(|s| s.id.clone());
+            if let Some(id) = oldest {
+                warn!(session_id = %id, available, "transcode dir is low on space; stopping least recently used session");
+                let _ = self.stop_session(&id).await;
+            }
+        }
+    }
+
+    async fn cleanup_timed_out(&self) {
         let timeout = self.config.idle_timeout_secs;
         let mut sessions = self.sessions.lock().await;
         let idle_ids: Vec<String> = sessions
@@ -189,19 +497,116 @@ impl SessionManager {
                     let _ = child.start_kill();
                     let _ = child.wait().await;
                 }
-                if session.output_dir.exists() {
-                    let _ = tokio::fs::remove_dir_all(&session.output_dir).await;
-                }
+                self.remove_output_dir(id, session.output_dir.clone()).await;
                 info!(session_id = %id, "cleaned up idle HLS session");
             }
         }
     }
 
+    /// Delete a stopped session's output dir, queueing it for
+    /// [`Self::retry_deferred_cleanup`] if that fails.
+    async fn remove_output_dir(&self, session_id: &str, dir: PathBuf) {
+        if let Err(e) = self.try_remove_dir(dir.clone()).await {
+            warn!(session_id, error = %e, dir = ?dir, "failed to clean up transcode dir; will retry");
+            self.deferred_cleanup.lock().await.push(dir);
+        }
+    }
+
+    /// Retry deleting output dirs that failed to delete earlier.
+    async fn retry_deferred_cleanup(&self) {
+        let pending = std::mem::take(&mut *self.deferred_cleanup.lock().await);
+        let mut still_pending = Vec::new();
+        for dir in pending {
+            match self.try_remove_dir(dir.clone()).await {
+                Ok(()) => info!(dir = ?dir, "removed transcode dir on retry"),
+                Err(e) => {
+                    tracing::debug!(error = %e, dir = ?dir, "transcode dir still cannot be removed");
+                    still_pending.push(dir);
+                }
+            }
+        }
+        self.deferred_cleanup.lock().await.extend(still_pending);
+    }
+
+    /// Output dirs waiting for a cleanup retry.
+    pub async fn deferred_cleanup_count(&self) -> usize {
+        self.deferred_cleanup.lock().await.len()
+    }
+
+    /// Delete `dir`; one that is already gone counts as removed.
+    async fn try_remove_dir(&self, dir: PathBuf) -> std::io::Result<()> {
+        let remove = self.remove_dir;
+        match tokio::task::spawn_blocking(move || remove(&dir)).await {
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Ok(result) => result,
+            Err(e) => Err(std::io::Error::other(e)),
+        }
+    }
+
+    /// Delete session directories left in the transcode dir by a previous run, e.g.
+    /// after a crash, or by a session whose teardown failed. Call at startup and
+    /// then periodically; returns how many were removed.
+    ///
+    /// The transcode dir may be shared, so only directories named like a session
+    /// id, not belonging to a live session and not modified for
+    /// [`TranscoderConfig::stale_dir_age_secs`] are touched.
+    pub async fn remove_stale_session_dirs(&self) -> usize {
+        let dir = &self.config.transcode_dir;
+        let mut entries = match tokio::fs::read_dir(dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return 0,
+            Err(e) => {
+                warn!(error = %e, dir = ?dir, "could not list transcode dir");
+                return 0;
+            }
+        };
+        let max_age = std::time::Duration::from_secs(self.config.stale_dir_age_secs);
+        let live: Vec<String> = self.list_sessions().await;
+        let mut removed = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if uuid::Uuid::try_parse(&name).is_err() || live.contains(&name) {
+                continue;
+            }
+            let Ok(meta) = entry.metadata().await else {
+                continue;
+            };
+            let age = meta
+                .modified()
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .unwrap_or_default();
+            if !meta.is_dir() || age < max_age {
+                continue;
+            }
+            match tokio::fs::remove_dir_all(entry.path()).await {
+                Ok(()) => removed += 1,
+                Err(e) => {
+                    warn!(error = %e, session_id = %name, "could not remove stale session dir")
+                }
+            }
+        }
+        if removed > 0 {
+            info!(removed, dir = ?dir, "removed stale transcode session dirs");
+        }
+        removed
+    }
+
     /// Get active session count.
     pub async fn active_count(&self) -> usize {
         self.sessions.lock().await.len()
     }
 
+    /// Number of active sessions owned by `user_id`.
+    pub async fn active_count_for_user(&self, user_id: &str) -> usize {
+        self.sessions
+            .lock()
+            .await
+            .values()
+            .filter(|s| s.owner_user_id == user_id)
+            .count()
+    }
+
     /// List active session IDs.
     pub async fn list_sessions(&self) -> Vec<String> {
         self.sessions.lock().await.keys().cloned().collect()
@@ -222,22 +627,77 @@ impl SessionManager {
         &self.config.ffmpeg_path
     }
 
+    pub fn transcode_dir(&self) -> &Path {
+        &self.config.transcode_dir
+    }
+
     pub fn ffprobe_path(&self) -> &Path {
         &self.config.ffprobe_path
     }
 }
 
-/// Build and spawn ffmpeg for HLS output.
-async fn spawn_ffmpeg(
-    ffmpeg_path: &Path,
+/// Write the master playlist of a session. ffmpeg writes the audio and video
+/// media playlists; subtitle playlists are rendered on request (see
+/// [`SessionManager::subtitle_playlist`]).
+async fn write_master_playlist(
+    output_dir: &Path,
+    renditions: &Renditions,
+    plan: TranscodePlan,
+    variant: &VariantInfo,
+) -> Result<(), TranscodeError> {
+    let video_kbps = match plan.video {
+        StreamAction::Transcode => plan.video_bitrate_kbps,
+        StreamAction::Copy => None,
+    }
+    .or(renditions.source_bitrate_kbps)
+    .unwrap_or(DEFAULT_BANDWIDTH_KBPS);
+    let bandwidth_bps = (u64::from(video_kbps) + AUDIO_BANDWIDTH_KBPS) * 1000;
+
+    tokio::fs::write(
+        output_dir.join("master.m3u8"),
+        crate::hls::render_master_playlist(renditions, bandwidth_bps, variant),
+    )
+    .await?;
+    Ok(())
+}
+
+/// `BANDWIDTH` assumed for video when neither the plan nor the probe gives a bitrate.
+const DEFAULT_BANDWIDTH_KBPS: u32 = 8_000;
+/// Headroom added to the video bitrate for the audio rendition.
+const AUDIO_BANDWIDTH_KBPS: u64 = 192;
+
+/// Build the ffmpeg argument list for HLS output.
+///
+/// With `offset_aware` set, a seeked session keeps source timestamps
+/// (`-output_ts_offset`) and numbers its segments from the seek point
+/// (`-start_number`), so the playlist lines up with the source timeline.
+///
+/// Streams the `plan` marks as [`StreamAction::Copy`] are passed through
+/// untouched; a video codec override always forces a video encode.
+///
+/// Multi-rendition sessions map every audio track into its own variant
+/// (`-var_stream_map`) and extract each subtitle track to a WebVTT file.
+///
+/// With `single_file` set, each variant's segments go into one `.ts` file that
+/// the playlist addresses with `#EXT-X-BYTERANGE` (`-hls_flags single_file`).
+#[allow(clippy::too_many_arguments)]
+fn build_ffmpeg_args(
     input: &Path,
     output_dir: &Path,
     segment_secs: u32,
     start_time: Option<f64>,
     video_codec_override: Option<&str>,
+    plan: TranscodePlan,
+    renditions: &Renditions,
     hw_accel: Option<&HwAccel>,
-) -> Result<Child, TranscodeError> {
+    offset_aware: bool,
+    single_file: bool,
+) -> Vec<String> {
     let mut args: Vec<String> = vec!["-hide_banner".into(), "-y".into()];
+    let start_time = start_time.filter(|t| *t > 0.0);
+    let copy_video = plan.video == StreamAction::Copy && video_codec_override.is_none();
+    // Nothing is decoded when video is copied, so hardware acceleration is moot.
+    let hw_accel = hw_accel.filter(|_| !copy_video);
 
     // HW accel input flags
     if let Some(hw) = hw_accel {
@@ -272,24 +732,11 @@ async fn spawn_ffmpeg(
     // Input
     args.extend(["-i".into(), input.to_string_lossy().into_owned()]);
 
-    // Video codec
-    let vcodec = if let Some(vc) = video_codec_override {
-        vc.to_string()
-    } else if let Some(hw) = hw_accel {
-        match hw {
-            HwAccel::Nvenc => "h264_nvenc".into(),
-            HwAccel::Vaapi => "h264_vaapi".into(),
-            HwAccel::Qsv => "h264_qsv".into(),
-            HwAccel::VideoToolbox => "h264_videotoolbox".into(),
-        }
-    } else {
-        "libx264".into()
-    };
-
-    args.extend(["-c:v".into(), vcodec]);
+    let encoder = video_encoder(plan, video_codec_override, hw_accel);
+    args.extend(["-c:v".into(), encoder.clone()]);
 
     // Video encoding params for software encode
-    if hw_accel.is_none() && video_codec_override.is_none() {
+    if !copy_video && hw_accel.is_none() && video_codec_override.is_none() {
         args.extend([
             "-preset".into(),
             "veryfast".into(),
@@ -298,12 +745,113 @@ async fn spawn_ffmpeg(
         ]);
     }
 
-    // Audio: always AAC for HLS compatibility
-    args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "128k".into()]);
+    // Pin what the master playlist's CODECS says re-encoded video is: 8-bit 4:2:0,
+    // and High profile for H.264 (some hardware encoders default to Main).
+    if !copy_video {
+        if hw_accel.is_none() {
+            args.extend(["-pix_fmt".into(), "yuv420p".into()]);
+        }
+        if VideoFamily::from_name(&encoder) == Some(VideoFamily::H264) {
+            args.extend(["-profile:v".into(), "high".into()]);
+        }
+    }
+
+    // Device limits for re-encoded video
+    if !copy_video {
+        if let Some(kbps) = plan.video_bitrate_kbps {
+            args.extend([
+                "-maxrate".into(),
+                format!("{kbps}k"),
+                "-bufsize".into(),
+                format!("{}k", kbps.saturating_mul(2)),
+            ]);
+        }
+        // Hardware pipelines keep frames on the GPU, where the software scaler can't reach.
+        if hw_accel.is_none() && (plan.max_width.is_some() || plan.max_height.is_some()) {
+            let bound = |max: Option<u32>, dim: &str| {
+                max.map(|m| format!("min({dim},{m})"))
+                    .unwrap_or_else(|| dim.to_string())
+            };
+            args.extend([
+                "-vf".into(),
+                format!(
+                    "scale=w='{}':h='{}':force_original_aspect_ratio=decrease:force_divisible_by=2",
+                    bound(plan.max_width, "iw"),
+                    bound(plan.max_height, "ih"),
+                ),
+            ]);
+        }
+    }
+
+    let multi = renditions.is_multi();
+    if multi {
+        args.extend(["-map".into(), "0:v:0".into()]);
+        for audio in &renditions.audio {
+            args.extend(["-map".into(), format!("0:{}", audio.stream_index)]);
+        }
+        for (n, audio) in renditions.audio.iter().enumerate() {
+            if audio.copy {
+                args.extend([format!("-c:a:{n}"), "copy".into()]);
+            } else {
+                args.extend([
+                    format!("-c:a:{n}"),
+                    "aac".into(),
+                    format!("-b:a:{n}"),
+                    "128k".into(),
+                ]);
+            }
+        }
+    } else {
+        // Audio: AAC for HLS compatibility unless the source track already fits
+        match plan.audio {
+            StreamAction::Copy => args.extend(["-c:a".into(), "copy".into()]),
+            StreamAction::Transcode => {
+                args.extend(["-c:a".into(), "aac".into(), "-b:a".into(), "128k".into()])
+            }
+        }
+    }
+
+    let offset = start_time.filter(|_| offset_aware);
+    if let Some(t) = offset {
+        let first_segment = (t / segment_secs.max(1) as f64).floor() as u64;
+        args.extend([
+            "-output_ts_offset".into(),
+            format!("{t:.3}"),
+            "-start_number".into(),
+            first_segment.to_string(),
+        ]);
+    }
 
     // HLS output
-    let seg_pattern = output_dir.join("seg_%05d.ts");
-    let master = output_dir.join("master.m3u8");
+    let (seg_pattern, playlist) = if multi {
+        let with_audio = if renditions.audio.is_empty() {
+            ""
+        } else {
+            ",agroup:audio"
+        };
+        let mut stream_map = vec![format!("v:0{with_audio},name:video")];
+        stream_map.extend(
+            (0..renditions.audio.len()).map(|n| format!("a:{n},agroup:audio,name:audio_{n}")),
+        );
+        args.extend(["-var_stream_map".into(), stream_map.join(" ")]);
+        let segments = if single_file {
+            "seg_%v.ts"
+        } else {
+            "seg_%v_%05d.ts"
+        };
+        (output_dir.join(segments), output_dir.join("stream_%v.m3u8"))
+    } else {
+        let segments = if single_file { "seg.ts" } else { "seg_%05d.ts" };
+        (
+            output_dir.join(segments),
+            output_dir.join(crate::hls::VIDEO_PLAYLIST),
+        )
+    };
+    let hls_flags = if single_file {
+        "independent_segments+single_file"
+    } else {
+        "independent_segments"
+    };
 
     args.extend([
         "-f".into(),
@@ -315,10 +863,63 @@ async fn spawn_ffmpeg(
         "-hls_segment_filename".into(),
         seg_pattern.to_string_lossy().into_owned(),
         "-hls_flags".into(),
-        "independent_segments".into(),
-        master.to_string_lossy().into_owned(),
+        hls_flags.into(),
+        playlist.to_string_lossy().into_owned(),
     ]);
 
+    // Subtitles: one WebVTT output per track, on the same timeline as the video.
+    for (n, subtitle) in renditions.subtitles.iter().enumerate() {
+        args.extend([
+            "-map".into(),
+            format!("0:{}", subtitle.stream_index),
+            "-c:s".into(),
+            "webvtt".into(),
+        ]);
+        if let Some(t) = offset {
+            args.extend(["-output_ts_offset".into(), format!("{t:.3}")]);
+        }
+        args.extend([
+            "-f".into(),
+            "webvtt".into(),
+            output_dir
+                .join(crate::hls::subtitle_vtt_name(n))
+                .to_string_lossy()
+                .into_owned(),
+        ]);
+    }
+
+    args
+}
+
+/// The `-c:v` a session uses: `copy`, the override, or the H.264 encoder of the
+/// hardware acceleration in use.
+fn video_encoder(
+    plan: TranscodePlan,
+    video_codec_override: Option<&str>,
+    hw_accel: Option<&HwAccel>,
+) -> String {
+    if plan.video == StreamAction::Copy && video_codec_override.is_none() {
+        "copy".to_string()
+    } else if let Some(vc) = video_codec_override {
+        vc.to_string()
+    } else if let Some(hw) = hw_accel {
+        match hw {
+            HwAccel::Nvenc => "h264_nvenc".into(),
+            HwAccel::Vaapi => "h264_vaapi".into(),
+            HwAccel::Qsv => "h264_qsv".into(),
+            HwAccel::VideoToolbox => "h264_videotoolbox".into(),
+        }
+    } else {
+        "libx264".into()
+    }
+}
+
+/// Spawn ffmpeg with the given arguments, logging stderr into the session dir.
+async fn spawn_ffmpeg(
+    ffmpeg_path: &Path,
+    output_dir: &Path,
+    args: Vec<String>,
+) -> Result<Child, TranscodeError> {
     // Log file
     let log_path = output_dir.join("ffmpeg.log");
 
@@ -336,3 +937,521 @@ async fn spawn_ffmpeg(
     info!(?ffmpeg_path, ?args, "spawned ffmpeg for HLS");
     Ok(child)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn low_space(_: &Path) -> std::io::Result<u64> {
+        Ok(100 * 1024 * 1024)
+    }
+
+    fn plenty_of_space(_: &Path) -> std::io::Result<u64> {
+        Ok(10 * 1024 * 1024 * 1024)
+    }
+
+    /// A scratch directory, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(prefix: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("{prefix}_{}", uuid::Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn manager(dir: &TempDir, probe: FreeSpaceProbe) -> SessionManager {
+        SessionManager::new(TranscoderConfig {
+            // Exits right away; the session only needs a child process.
+            ffmpeg_path: PathBuf::from("true"),
+            transcode_dir: dir.0.clone(),
+            min_free_bytes: 1024 * 1024 * 1024,
+            ..TranscoderConfig::default()
+        })
+        .with_free_space_probe(probe)
+    }
+
+    async fn start(mgr: &SessionManager) -> Result<String, TranscodeError> {
+        mgr.create_session(
+            PathBuf::from("/media/movie.mkv"),
+            None,
+            None,
+            TranscodePlan::default(),
+            Renditions::default(),
+            None,
+            "user".into(),
+            "file".into(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn startup_removes_only_stale_session_dirs() {
+        let tmp = TempDir::new("rf_stale");
+        let dir = tmp.0.clone();
+        let stale = dir.join(uuid::Uuid::new_v4().to_string());
+        let foreign = dir.join("not-a-session");
+        std::fs::create_dir_all(&stale).unwrap();
+        std::fs::write(stale.join("seg_00001.ts"), b"x").unwrap();
+        std::fs::create_dir_all(&foreign).unwrap();
+        let mgr = |stale_dir_age_secs| {
+            SessionManager::new(TranscoderConfig {
+                transcode_dir: dir.clone(),
+                stale_dir_age_secs,
+                ..TranscoderConfig::default()
+            })
+        };
+
+        // Too recent: another server may still be writing it.
+        assert_eq!(mgr(60 * 60).remove_stale_session_dirs().await, 0);
+        assert!(stale.exists());
+
+        assert_eq!(mgr(0).remove_stale_session_dirs().await, 1);
+        assert!(!stale.exists());
+        assert!(foreign.exists());
+    }
+
+    #[tokio::test]
+    async fn session_is_refused_below_free_space_threshold() {
+        let dir = TempDir::new("rf_space");
+        let mgr = manager(&dir, low_space);
+        let err = start(&mgr).await.unwrap_err();
+        assert!(matches!(
+            err,
+            TranscodeError::InsufficientDiskSpace {
+                available: 104_857_600,
+                required: 1_073_741_824,
+            }
+        ));
+        assert_eq!(mgr.active_count().await, 0);
+
+        let mgr = manager(&dir, plenty_of_space);
+        let id = start(&mgr).await.unwrap();
+        assert_eq!(mgr.list_sessions().await, vec![id.clone()]);
+        mgr.stop_session(&id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn critical_space_stops_least_recently_used_quiet_session() {
+        let dir = TempDir::new("rf_space");
+        let mut mgr = manager(&dir, plenty_of_space);
+        let older = start(&mgr).await.unwrap();
+        let newer = start(&mgr).await.unwrap();
+        let quiet_for = |secs| Instant::now() - std::time::Duration::from_secs(secs);
+        for (id, secs) in [
+            (&older, LOW_SPACE_IDLE_SECS + 10),
+            (&newer, LOW_SPACE_IDLE_SECS + 5),
+        ] {
+            mgr.sessions.lock().await.get_mut(id).unwrap().last_ping = quiet_for(secs);
+        }
+
+        mgr.cleanup_idle().await;
+        assert_eq!(mgr.active_count().await, 2);
+
+        mgr.free_space = low_space;
+        mgr.cleanup_idle().await;
+        assert_eq!(mgr.list_sessions().await, vec![newer.clone()]);
+
+        // A session that is still being played is left alone.
+        assert!(mgr.ping(&newer).await);
+        mgr.cleanup_idle().await;
+        assert_eq!(mgr.list_sessions().await, vec![newer.clone()]);
+        mgr.stop_session(&newer).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn held_guards_follow_restarts_and_drop_with_the_session() {
+        let dir = TempDir::new("rf_space");
+        let mgr = manager(&dir, plenty_of_space);
+        let id = start(&mgr).await.unwrap();
+        let guard = Arc::new(());
+        assert!(mgr.hold_for_session(&id, Box::new(guard.clone())).await);
+        assert!(
+            !mgr.hold_for_session("missing", Box::new(guard.clone()))
+                .await
+        );
+        assert_eq!(Arc::strong_count(&guard), 2);
+
+        let restarted = mgr.restart_session_at(&id, 30.0).await.unwrap();
+        assert_eq!(Arc::strong_count(&guard), 2);
+        mgr.stop_session(&restarted).await.unwrap();
+        assert_eq!(Arc::strong_count(&guard), 1);
+    }
+
+    #[tokio::test]
+    async fn subtitle_playlist_closes_once_ffmpeg_exits_cleanly() {
+        use crate::hls::SubtitleRendition;
+
+        let dir = TempDir::new("rf_subs");
+        let renditions = Renditions {
+            subtitles: vec![SubtitleRendition {
+                stream_index: 2,
+                name: "English".into(),
+                language: Some("eng".into()),
+                default: false,
+                forced: false,
+            }],
+            ..Renditions::default()
+        };
+        for (ffmpeg, finished) in [("false", false), ("true", true)] {
+            let mut mgr = manager(&dir, plenty_of_space);
+            mgr.config.ffmpeg_path = PathBuf::from(ffmpeg);
+            let id = mgr
+                .create_session(
+                    PathBuf::from("/media/movie.mkv"),
+                    Some(40.0),
+                    None,
+                    TranscodePlan::default(),
+                    renditions.clone(),
+                    Some(100.0),
+                    "user".into(),
+                    "file".into(),
+                )
+                .await
+                .unwrap();
+            if let Some(child) = mgr
+                .sessions
+                .lock()
+                .await
+                .get_mut(&id)
+                .unwrap()
+                .child
+                .as_mut()
+            {
+                child.wait().await.unwrap();
+            }
+
+            // The session starts 40s into a 100s source.
+            let playlist = mgr.subtitle_playlist(&id, 0).await.unwrap();
+            assert!(playlist.contains("#EXT-X-TARGETDURATION:60\n"));
+            assert_eq!(
+                playlist.ends_with("#EXTINF:60.000,\nsubs_0.vtt\n#EXT-X-ENDLIST\n"),
+                finished,
+                "{ffmpeg}: {playlist}"
+            );
+            assert!(mgr.subtitle_playlist(&id, 1).await.is_err());
+            mgr.stop_session(&id).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_restart_keeps_the_old_session() {
+        let dir = TempDir::new("rf_restart");
+        let mut mgr = manager(&dir, plenty_of_space);
+        mgr.config.max_concurrent = 1;
+        mgr.semaphore = Arc::new(Semaphore::new(1));
+        let id = start(&mgr).await.unwrap();
+        let guard = Arc::new(());
+        assert!(mgr.hold_for_session(&id, Box::new(guard.clone())).await);
+
+        mgr.config.ffmpeg_path = dir.0.join("missing-ffmpeg");
+        let err = mgr.restart_session_at(&id, 30.0).await.unwrap_err();
+        assert!(matches!(err, TranscodeError::FfmpegFailed(_)), "{err}");
+        assert_eq!(mgr.list_sessions().await, vec![id.clone()]);
+        assert_eq!(Arc::strong_count(&guard), 2);
+        let dirs = std::fs::read_dir(&dir.0).unwrap().count();
+        assert_eq!(dirs, 1, "the failed restart's output dir is removed");
+
+        // With the only permit taken, a working restart reuses the old session's.
+        mgr.config.ffmpeg_path = PathBuf::from("true");
+        let restarted = mgr.restart_session_at(&id, 30.0).await.unwrap();
+        assert_eq!(mgr.list_sessions().await, vec![restarted.clone()]);
+        assert_eq!(mgr.session_start_time(&restarted).await, Some(30.0));
+        assert_eq!(Arc::strong_count(&guard), 2);
+        assert!(!dir.0.join(&id).exists());
+        mgr.stop_session(&restarted).await.unwrap();
+        assert_eq!(Arc::strong_count(&guard), 1);
+    }
+
+    /// Fails with `EBUSY` the first time it is called, then deletes normally.
+    fn busy_once(path: &Path) -> std::io::Result<()> {
+        static BUSY: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(true);
+        if BUSY.swap(false, std::sync::atomic::Ordering::SeqCst) {
+            return Err(std::io::Error::from_raw_os_error(libc::EBUSY));
+        }
+        std::fs::remove_dir_all(path)
+    }
+
+    #[tokio::test]
+    async fn dirs_that_fail_to_delete_are_retried_by_cleanup() {
+        let tmp = TempDir::new("rf_space");
+        let mgr = manager(&tmp, plenty_of_space).with_dir_remover(busy_once);
+        let id = start(&mgr).await.unwrap();
+        let dir = mgr.get_file_path(&id, "").await.unwrap();
+        assert!(dir.exists());
+
+        mgr.stop_session(&id).await.unwrap();
+        assert!(mgr.list_sessions().await.is_empty());
+        assert!(dir.exists());
+        assert_eq!(mgr.deferred_cleanup_count().await, 1);
+
+        mgr.cleanup_idle().await;
+        assert!(!dir.exists());
+        assert_eq!(mgr.deferred_cleanup_count().await, 0);
+    }
+
+    #[test]
+    fn seeked_session_args_start_at_offset() {
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            Some(125.5),
+            None,
+            TranscodePlan::default(),
+            &Renditions::default(),
+            None,
+            true,
+            false,
+        );
+
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        // Input seeking: -ss must come before -i.
+        assert!(pos("-ss") < pos("-i"));
+        assert_eq!(args[pos("-ss") + 1], "125.500");
+        assert_eq!(args[pos("-output_ts_offset") + 1], "125.500");
+        // 125.5s / 4s segments => playlist starts at segment 31.
+        assert_eq!(args[pos("-start_number") + 1], "31");
+        assert!(args.last().unwrap().ends_with("stream_video.m3u8"));
+    }
+
+    #[test]
+    fn unseeked_session_has_no_offset_args() {
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            TranscodePlan::default(),
+            &Renditions::default(),
+            None,
+            true,
+            false,
+        );
+        assert!(!args.iter().any(|a| a == "-ss"));
+        assert!(!args.iter().any(|a| a == "-output_ts_offset"));
+        assert!(!args.iter().any(|a| a == "-start_number"));
+    }
+
+    #[test]
+    fn plain_seek_keeps_zero_based_playlist() {
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            Some(60.0),
+            None,
+            TranscodePlan::default(),
+            &Renditions::default(),
+            None,
+            false,
+            false,
+        );
+        assert!(args.iter().any(|a| a == "-ss"));
+        assert!(!args.iter().any(|a| a == "-output_ts_offset"));
+    }
+
+    #[test]
+    fn single_file_session_writes_one_byterange_segment_file() {
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            TranscodePlan::default(),
+            &Renditions::default(),
+            None,
+            true,
+            true,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        assert_eq!(
+            args[pos("-hls_flags") + 1],
+            "independent_segments+single_file"
+        );
+        assert_eq!(args[pos("-hls_segment_filename") + 1], "/tmp/sess/seg.ts");
+        assert!(args.last().unwrap().ends_with("stream_video.m3u8"));
+
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            TranscodePlan::default(),
+            &Renditions::default(),
+            None,
+            true,
+            false,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        assert_eq!(args[pos("-hls_flags") + 1], "independent_segments");
+        assert_eq!(
+            args[pos("-hls_segment_filename") + 1],
+            "/tmp/sess/seg_%05d.ts"
+        );
+    }
+
+    #[test]
+    fn audio_only_plan_copies_video() {
+        let plan = TranscodePlan {
+            video: StreamAction::Copy,
+            audio: StreamAction::Transcode,
+            video_bitrate_kbps: Some(3000),
+            ..TranscodePlan::default()
+        };
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            plan,
+            &Renditions::default(),
+            Some(&HwAccel::Nvenc),
+            true,
+            false,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        assert_eq!(args[pos("-c:v") + 1], "copy");
+        assert_eq!(args[pos("-c:a") + 1], "aac");
+        assert!(
+            !args
+                .iter()
+                .any(|a| a == "-hwaccel" || a == "-preset" || a == "-maxrate")
+        );
+    }
+
+    #[test]
+    fn remux_plan_copies_both_streams() {
+        let plan = TranscodePlan {
+            video: StreamAction::Copy,
+            audio: StreamAction::Copy,
+            ..TranscodePlan::default()
+        };
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            plan,
+            &Renditions::default(),
+            None,
+            true,
+            false,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        assert_eq!(args[pos("-c:v") + 1], "copy");
+        assert_eq!(args[pos("-c:a") + 1], "copy");
+        assert!(!args.iter().any(|a| a == "-b:a"));
+    }
+
+    #[test]
+    fn transcode_plan_applies_device_limits() {
+        let plan = TranscodePlan {
+            video_bitrate_kbps: Some(3000),
+            max_width: Some(1280),
+            ..TranscodePlan::default()
+        };
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            plan,
+            &Renditions::default(),
+            None,
+            true,
+            false,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        assert_eq!(args[pos("-c:v") + 1], "libx264");
+        // What `avc1.6400xx` in the master playlist promises.
+        assert_eq!(args[pos("-pix_fmt") + 1], "yuv420p");
+        assert_eq!(args[pos("-profile:v") + 1], "high");
+        assert_eq!(args[pos("-maxrate") + 1], "3000k");
+        assert_eq!(args[pos("-bufsize") + 1], "6000k");
+        let filter = &args[pos("-vf") + 1];
+        assert!(filter.contains("w='min(iw,1280)'"));
+        assert!(filter.contains("h='ih'"));
+    }
+
+    #[test]
+    fn multi_rendition_session_maps_each_audio_track_and_subtitle() {
+        use crate::hls::{AudioRendition, SubtitleRendition};
+
+        let renditions = Renditions {
+            audio: vec![
+                AudioRendition {
+                    stream_index: 1,
+                    name: "English".into(),
+                    language: Some("eng".into()),
+                    default: true,
+                    copy: true,
+                    codec: "aac".into(),
+                },
+                AudioRendition {
+                    stream_index: 2,
+                    name: "French".into(),
+                    language: Some("fre".into()),
+                    default: false,
+                    copy: false,
+                    codec: "dts".into(),
+                },
+            ],
+            subtitles: vec![SubtitleRendition {
+                stream_index: 3,
+                name: "English".into(),
+                language: Some("eng".into()),
+                default: false,
+                forced: false,
+            }],
+            source_bitrate_kbps: None,
+            video: None,
+        };
+        let plan = TranscodePlan {
+            video: StreamAction::Copy,
+            audio: StreamAction::Copy,
+            ..TranscodePlan::default()
+        };
+        let args = build_ffmpeg_args(
+            Path::new("/media/movie.mkv"),
+            Path::new("/tmp/sess"),
+            4,
+            None,
+            None,
+            plan,
+            &renditions,
+            None,
+            true,
+            false,
+        );
+        let pos = |flag: &str| args.iter().position(|a| a == flag).unwrap();
+        let maps: Vec<&str> = args
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| *a == "-map")
+            .map(|(i, _)| args[i + 1].as_str())
+            .collect();
+        assert_eq!(maps, ["0:v:0", "0:1", "0:2", "0:3"]);
+        assert_eq!(args[pos("-c:a:0") + 1], "copy");
+        assert_eq!(args[pos("-c:a:1") + 1], "aac");
+        assert_eq!(
+            args[pos("-var_stream_map") + 1],
+            "v:0,agroup:audio,name:video a:0,agroup:audio,name:audio_0 a:1,agroup:audio,name:audio_1"
+        );
+        // The HLS output comes first; the subtitle track follows as its own WebVTT output.
+        assert!(args[pos("-var_stream_map")..].contains(&"/tmp/sess/stream_%v.m3u8".to_string()));
+        assert!(pos("/tmp/sess/stream_%v.m3u8") < pos("-c:s"));
+        assert_eq!(args.last().unwrap(), "/tmp/sess/subs_0.vtt");
+    }
+}
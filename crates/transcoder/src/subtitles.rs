@@ -0,0 +1,50 @@
+//! One-off extraction of an embedded subtitle track to WebVTT.
+
+use std::path::Path;
+
+use crate::TranscodeError;
+
+/// Convert subtitle stream `stream_index` of `input` to a WebVTT file at `output`.
+///
+/// ffmpeg writes to a temporary file next to `output` which is renamed into place,
+/// so a reader never sees a half-written track.
+pub async fn extract_vtt(
+    ffmpeg_path: &Path,
+    input: &Path,
+    stream_index: u32,
+    output: &Path,
+) -> Result<(), TranscodeError> {
+    let partial = output.with_extension("vtt.partial");
+    let result = tokio::process::Command::new(ffmpeg_path)
+        .args(["-nostdin", "-v", "error", "-y", "-i"])
+        .arg(input)
+        .args([
+            "-map",
+            &format!("0:{stream_index}"),
+            "-c:s",
+            "webvtt",
+            "-f",
+            "webvtt",
+        ])
+        .arg(&partial)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await;
+    let out = match result {
+        Ok(out) => out,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(TranscodeError::BinaryNotFound(ffmpeg_path.to_path_buf()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    if !out.status.success() {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(TranscodeError::FfmpegFailed(format!(
+            "subtitle extraction exited with {}: {}",
+            out.status,
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+    tokio::fs::rename(&partial, output).await?;
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Startup check for the external ffmpeg and ffprobe binaries.
+//!
+//! Runs `<binary> -version` once so requests can fail fast with a clear message
+//! instead of a spawn error deep inside a handler.
+
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Whether one binary could be run. Paths and error text are kept out of the
+/// serialized form, which is shown on the admin system info endpoint.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ToolStatus {
+    /// Not checked (tests, embedded use); treated as present.
+    #[default]
+    Unchecked,
+    Available {
+        #[serde(skip)]
+        path: String,
+        /// Version from the first line of `-version`, e.g. `6.1.1`.
+        version: Option<String>,
+    },
+    Missing {
+        #[serde(skip)]
+        path: String,
+        #[serde(skip)]
+        error: String,
+    },
+}
+
+impl ToolStatus {
+    pub fn is_missing(&self) -> bool {
+        matches!(self, Self::Missing { .. })
+    }
+}
+
+/// ffmpeg and ffprobe as found at startup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MediaTools {
+    pub ffmpeg: ToolStatus,
+    pub ffprobe: ToolStatus,
+}
+
+impl MediaTools {
+    pub async fn detect(ffmpeg_path: &Path, ffprobe_path: &Path) -> Self {
+        let (ffmpeg, ffprobe) = tokio::join!(check(ffmpeg_path), check(ffprobe_path));
+        Self { ffmpeg, ffprobe }
+    }
+}
+
+/// Run `path -version` and report whether it succeeded.
+pub async fn check(path: &Path) -> ToolStatus {
+    let display = path.display().to_string();
+    match tokio::process::Command::new(path)
+        .arg("-version")
+        .output()
+        .await
+    {
+        Ok(out) if out.status.success() => ToolStatus::Available {
+            path: display,
+            version: parse_version(&String::from_utf8_lossy(&out.stdout)),
+        },
+        Ok(out) => ToolStatus::Missing {
+            path: display,
+            error: format!(
+                "exited with {}: {}",
+                out.status,
+                String::from_utf8_lossy(&out.stderr).trim()
+            ),
+        },
+        Err(e) => ToolStatus::Missing {
+            path: display,
+            error: e.to_string(),
+        },
+    }
+}
+
+/// Version from `ffmpeg version 6.1.1-3ubuntu5 Copyright ...`.
+fn parse_version(output: &str) -> Option<String> {
+    let mut words = output.lines().next()?.split_whitespace();
+    words.find(|w| *w == "version")?;
+    words.next().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_comes_from_the_first_line() {
+        assert_eq!(
+            parse_version("ffmpeg version 6.1.1-3ubuntu5 Copyright (c) 2000-2023\nbuilt with gcc")
+                .as_deref(),
+            Some("6.1.1-3ubuntu5")
+        );
+        assert_eq!(
+            parse_version("ffprobe version n7.0 Copyright").as_deref(),
+            Some("n7.0")
+        );
+        assert_eq!(parse_version("garbage"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[tokio::test]
+    async fn missing_binary_is_reported() {
+        let status = check(Path::new("/nonexistent/rustfin-ffmpeg")).await;
+        assert!(status.is_missing());
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({ "status": "missing" })
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Default audio and subtitle track choices.
+//!
+//! Languages are compared with [`same_language`], so a sidecar tagged `en` matches
+//! an audio track tagged `eng`.
+
+use rustfin_core::language::same_language;
+
+use crate::ffprobe::AudioStream;
+
+/// The track the file presents as its main audio: the one flagged default, or the
+/// first. Treated as the original-language track.
+pub fn original_audio(audio: &[AudioStream]) -> Option<&AudioStream> {
+    audio
+        .iter()
+        .find(|a| a.is_default)
+        .or_else(|| audio.first())
+}
+
+/// The audio track a player should start with: the first track in the user's most
+/// preferred language, falling back to [`original_audio`].
+pub fn choose_audio<'a>(audio: &'a [AudioStream], preferred: &[String]) -> Option<&'a AudioStream> {
+    preferred
+        .iter()
+        .find_map(|lang| {
+            audio.iter().find(|a| {
+                a.language
+                    .as_deref()
+                    .is_some_and(|l| same_language(l, lang))
+            })
+        })
+        .or_else(|| original_audio(audio))
+}
+
+/// Whether a subtitle should be switched on without the user asking: it is forced
+/// (it only covers foreign-language scenes), it is in the language of the chosen
+/// audio, and that audio is the original-language track. Dubbed audio usually
+/// translates those scenes already, so forced subtitles stay off for it.
+pub fn auto_select_forced(
+    forced: bool,
+    subtitle_language: Option<&str>,
+    audio: &[AudioStream],
+    preferred_audio: &[String],
+) -> bool {
+    if !forced {
+        return false;
+    }
+    let (Some(chosen), Some(original)) =
+        (choose_audio(audio, preferred_audio), original_audio(audio))
+    else {
+        return false;
+    };
+    chosen.index == original.index
+        && subtitle_language
+            .zip(chosen.language.as_deref())
+            .is_some_and(|(sub, audio)| same_language(sub, audio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn audio(index: u32, language: &str, is_default: bool) -> AudioStream {
+        AudioStream {
+            index,
+            codec: "aac".into(),
+            channels: 2,
+            language: Some(language.into()),
+            title: None,
+            is_default,
+        }
+    }
+
+    #[test]
+    fn preferred_language_picks_the_audio_track() {
+        let tracks = [audio(1, "eng", true), audio(2, "ger", false)];
+        assert_eq!(choose_audio(&tracks, &["de".into()]).unwrap().index, 2);
+        assert_eq!(choose_audio(&tracks, &["ja".into()]).unwrap().index, 1);
+        assert_eq!(choose_audio(&tracks, &[]).unwrap().index, 1);
+    }
+
+    #[test]
+    fn forced_subtitles_auto_select_only_with_original_audio() {
+        let tracks = [audio(1, "eng", true), audio(2, "ger", false)];
+        let english = ["en".to_string()];
+        let german = ["de".to_string()];
+
+        assert!(auto_select_forced(true, Some("en"), &tracks, &english));
+        assert!(!auto_select_forced(false, Some("en"), &tracks, &english));
+        // Wrong language, or dubbed audio chosen.
+        assert!(!auto_select_forced(true, Some("de"), &tracks, &english));
+        assert!(!auto_select_forced(true, Some("de"), &tracks, &german));
+        assert!(!auto_select_forced(true, None, &tracks, &english));
+        assert!(!auto_select_forced(true, Some("en"), &[], &english));
+    }
+}
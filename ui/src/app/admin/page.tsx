@@ -96,9 +96,9 @@ export default function AdminPage() {
 
   const loadData = useCallback(async () => {
     try {
-      const [libs, jobList, userList, tmdb] = await Promise.all([
+      const [libs, jobPage, userList, tmdb] = await Promise.all([
         apiJson<Library[]>('/libraries'),
-        apiJson<Job[]>('/jobs'),
+        apiJson<{ jobs: Job[]; total: number }>('/jobs?limit=50'),
         apiJson<UserAccount[]>('/users'),
         apiJson<TmdbConfig>('/system/tmdb'),
       ]);
@@ -114,7 +114,7 @@ export default function AdminPage() {
         };
       }
       setLibraryEdits(nextLibEdits);
-      setJobs(jobList);
+      setJobs(jobPage.jobs);
       setUsers(userList);
 
       const nextEdits: Record<string, UserEditState> = {};
@@ -207,6 +207,19 @@ export default function AdminPage() {
     }
   }
 
+  async function refreshLibraryMetadata(libId: string) {
+    try {
+      await apiJson(`/libraries/${libId}/refresh-metadata`, {
+        method: 'POST',
+        body: JSON.stringify({ replace: false }),
+      });
+      setOk('Metadata refresh started');
+      await loadData();
+    } catch (err: any) {
+      setErr(err.message || 'Failed to start metadata refresh');
+    }
+  }
+
   async function browseLibraryPath() {
     setPickingPath(true);
     try {
@@ -751,6 +764,12 @@ export default function AdminPage() {
                 >
                   Scan
                 </button>
+                <button
+                  onClick={() => refreshLibraryMetadata(lib.id)}
+                  className="btn-secondary px-3 py-1.5 text-sm"
+                >
+                  Refresh metadata
+                </button>
                 <button
                   onClick={() => saveLibrary(lib.id)}
                   className="btn-primary px-3 py-1.5 text-sm"